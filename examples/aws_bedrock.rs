@@ -21,10 +21,9 @@ use swiftide::{
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
-    let aws_bedrock = integrations::aws_bedrock::AwsBedrock::build_anthropic_family(
-        "anthropic.claude-3-sonnet-20240229-v1:0",
-    )
-    .build()?;
+    let aws_bedrock = integrations::aws_bedrock::AwsBedrock::builder()
+        .model_id("anthropic.claude-3-sonnet-20240229-v1:0")
+        .build()?;
 
     let memory_storage = MemoryStorage::default();
 
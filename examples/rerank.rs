@@ -0,0 +1,66 @@
+//! # [Swiftide] Reranking with fastembed
+//!
+//! This example retrieves a wide set of documents with a similarity search, and reranks them
+//! locally with a fastembed cross-encoder before answering. Only the most relevant documents are
+//! passed to the llm.
+//!
+//! [Swiftide]: https://github.com/bosun-ai/swiftide
+//! [examples]: https://github.com/bosun-ai/swiftide/blob/master/examples
+
+use swiftide::{
+    indexing::{
+        self,
+        loaders::FileLoader,
+        transformers::{ChunkMarkdown, Embed},
+    },
+    integrations::{fastembed, openai, qdrant::Qdrant},
+    query::{self, answers, query_transformers, search_strategies::SimilaritySingleEmbedding},
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let embed = fastembed::FastEmbed::try_default()?;
+
+    let qdrant = Qdrant::builder()
+        .batch_size(50)
+        .vector_size(384)
+        .collection_name("swiftide-rerank-example")
+        .build()?;
+
+    indexing::Pipeline::from_loader(FileLoader::new("README.md"))
+        .then_chunk(ChunkMarkdown::from_chunk_range(10..2048))
+        .then_in_batch(Embed::new(embed.clone()).with_batch_size(50))
+        .then_store_with(qdrant.clone())
+        .run()
+        .await?;
+
+    // Keep the five most relevant documents, and drop documents the model considers irrelevant
+    let rerank = fastembed::Rerank::builder()
+        .top_n(5)
+        .threshold(0.0)
+        .build()?;
+
+    let openai = openai::OpenAI::builder()
+        .default_prompt_model("gpt-4o-mini")
+        .build()?;
+
+    // Retrieve more documents than needed, the reranker picks the best ones
+    let pipeline = query::Pipeline::from_search_strategy(
+        SimilaritySingleEmbedding::default()
+            .with_top_k(30)
+            .to_owned(),
+    )
+    .then_transform_query(query_transformers::Embed::from_client(embed))
+    .then_retrieve(qdrant)
+    .then_rerank(rerank)
+    .then_answer(answers::Simple::from_client(openai));
+
+    let result = pipeline
+        .query("How do I run a query pipeline in swiftide?")
+        .await?;
+
+    println!("{}", result.answer());
+    Ok(())
+}
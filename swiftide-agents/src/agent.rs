@@ -1,27 +1,41 @@
 #![allow(dead_code)]
 use crate::{
+    audit::AuditLog,
     default_context::DefaultContext,
+    guardrails::{self, Checked, Guardrail, Target},
     hooks::{
         AfterCompletionFn, AfterEachFn, AfterToolFn, BeforeAllFn, BeforeCompletionFn, BeforeToolFn,
-        Hook, HookTypes, MessageHookFn, OnStartFn,
+        Hook, HookTypes, MessageHookFn, OnStartFn, OnStopFn,
     },
     state,
     system_prompt::SystemPrompt,
-    tools::{arg_preprocessor::ArgPreprocessor, control::Stop},
+    tools::{
+        arg_preprocessor::ArgPreprocessor, control::Stop, output::ProcessToolOutput,
+        policy::ToolPolicy,
+    },
+    trajectory::TrajectoryRecorder,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{collections::HashSet, sync::Arc};
 
 use anyhow::Result;
 use derive_builder::Builder;
 use swiftide_core::{
     chat_completion::{
-        ChatCompletion, ChatCompletionRequest, ChatMessage, Tool, ToolCall, ToolOutput,
+        errors::ToolError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+        ChatMessage, Tool, ToolCall, ToolOutput,
     },
-    prompt::Prompt,
-    AgentContext,
+    AgentContext, CancellationToken,
 };
+use tokio::task::JoinHandle;
 use tracing::{debug, Instrument};
 
+/// The output of a spawned tool call and how long it took
+type ToolCallResult = (Result<ToolOutput, ToolError>, Duration);
+
 /// Agents are the main interface for building agentic systems.
 ///
 /// Construct agents by calling the builder, setting an llm, configure hooks, tools and other
@@ -70,12 +84,49 @@ pub struct Agent {
     ///         .build().unwrap())
     ///     .build().unwrap();
     /// ```
-    #[builder(setter(into, strip_option), default = Some(SystemPrompt::default().into()))]
-    pub(crate) system_prompt: Option<Prompt>,
+    ///
+    /// Variables of the system prompt are resolved and the system prompt is rendered again at the
+    /// start of every run, see [`SystemPrompt`].
+    #[builder(setter(into, strip_option), default = Some(SystemPrompt::default()))]
+    pub(crate) system_prompt: Option<SystemPrompt>,
+
+    /// The system prompt as rendered at the start of the current run
+    #[builder(setter(skip), default)]
+    pub(crate) rendered_system_prompt: Option<String>,
 
     /// Initial state of the agent
     #[builder(private, default = state::State::default())]
     pub(crate) state: state::State,
+
+    /// Records every tool call to an audit sink, see [`crate::audit`]
+    #[builder(setter(into, strip_option), default)]
+    pub(crate) audit_log: Option<AuditLog>,
+
+    /// Timeouts, retries and error feedback by tool name, see [`ToolPolicy`]
+    #[builder(default, setter(custom))]
+    pub(crate) tool_policies: HashMap<String, ToolPolicy>,
+
+    /// The policy of tools without a policy of their own
+    #[builder(default)]
+    pub(crate) default_tool_policy: ToolPolicy,
+
+    /// Checks the user input, tool arguments and answers, see [`crate::guardrails`]
+    #[builder(default, setter(custom))]
+    pub(crate) guardrails: Vec<Guardrail>,
+
+    /// How often an answer rejected by a guardrail is rewritten before the agent fails, see
+    /// [`Action::Rewrite`](crate::guardrails::Action::Rewrite)
+    #[builder(default = 3)]
+    pub(crate) max_answer_rewrites: usize,
+
+    /// Processes the text output of tool calls before it is added to the history, i.e. to
+    /// truncate large outputs, see [`crate::tools::output`]
+    #[builder(default, setter(custom))]
+    pub(crate) tool_output_processor: Option<Box<dyn ProcessToolOutput>>,
+
+    /// Records the completions and tool calls of the agent, see [`crate::trajectory`]
+    #[builder(setter(into, strip_option), default)]
+    pub(crate) trajectory_recorder: Option<TrajectoryRecorder>,
 }
 
 impl std::fmt::Debug for Agent {
@@ -142,6 +193,13 @@ impl AgentBuilder {
         self.add_hook(Hook::OnStart(Box::new(hook)))
     }
 
+    /// Add a hook that runs when the agent stops, because it is done, stopped, cancelled or
+    /// failed. Like `on_start`, it runs every time the agent stops, also when a hook or guardrail
+    /// fails before the agent started.
+    pub fn on_stop(&mut self, hook: impl OnStopFn + 'static) -> &mut Self {
+        self.add_hook(Hook::OnStop(Box::new(hook)))
+    }
+
     /// Add a hook that runs before each completion.
     pub fn before_completion(&mut self, hook: impl BeforeCompletionFn + 'static) -> &mut Self {
         self.add_hook(Hook::BeforeCompletion(Box::new(hook)))
@@ -178,6 +236,43 @@ impl AgentBuilder {
         self.add_hook(Hook::OnNewMessage(Box::new(hook)))
     }
 
+    /// Sets the timeout, retries and error feedback of a tool by its name
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use swiftide_agents::{Agent, tools::policy::ToolPolicy};
+    /// Agent::builder().tool_policy(
+    ///     "search_code",
+    ///     ToolPolicy::retries(3).with_timeout(Duration::from_secs(30)),
+    /// );
+    /// ```
+    pub fn tool_policy(&mut self, tool_name: impl Into<String>, policy: ToolPolicy) -> &mut Self {
+        self.tool_policies
+            .get_or_insert_with(HashMap::new)
+            .insert(tool_name.into(), policy);
+
+        self
+    }
+
+    /// Adds a guardrail that checks the user input, tool arguments or answers of the agent.
+    /// Guardrails run in the order they are added.
+    pub fn guardrail(&mut self, guardrail: Guardrail) -> &mut Self {
+        self.guardrails.get_or_insert_with(Vec::new).push(guardrail);
+
+        self
+    }
+
+    /// Processes the text output of every tool call before it is added to the history
+    pub fn tool_output_processor(
+        &mut self,
+        processor: impl ProcessToolOutput + 'static,
+    ) -> &mut Self {
+        self.tool_output_processor = Some(Some(Box::new(processor)));
+        self
+    }
+
     /// Set the LLM for the agent. An LLM must implement the `ChatCompletion` trait.
     pub fn llm<LLM: ChatCompletion + Clone + 'static>(&mut self, llm: &LLM) -> &mut Self {
         let boxed: Box<dyn ChatCompletion> = Box::new(llm.clone()) as Box<dyn ChatCompletion>;
@@ -214,34 +309,100 @@ impl Agent {
 
 impl Agent {
     /// Default tools for the agent that it always includes
-    fn default_tools() -> HashSet<Box<dyn Tool>> {
+    pub(crate) fn default_tools() -> HashSet<Box<dyn Tool>> {
         HashSet::from([Box::new(Stop::default()) as Box<dyn Tool>])
     }
 
     /// Run the agent with a user message. The agent will loop completions, make tool calls, until
     /// no new messages are available.
-    #[tracing::instrument(skip_all, name = "agent.query")]
+    #[tracing::instrument(
+        skip_all,
+        name = "agent.query",
+        fields(
+            gen_ai.operation.name = "invoke_agent",
+            openinference.span.kind = "AGENT"
+        )
+    )]
     pub async fn query(&mut self, query: impl Into<String> + std::fmt::Debug) -> Result<()> {
-        self.run_agent(Some(query.into()), false).await
+        self.run_agent(Some(query.into()), false, &CancellationToken::new())
+            .await
+    }
+
+    /// Run the agent with a user message until it is done or the token is cancelled.
+    ///
+    /// On cancellation, a pending completion and running tool calls are aborted, `on_stop` hooks
+    /// run and the agent stops with `Ok`. Aborted tool calls get a failed output, so that every
+    /// tool call in the history has an output and the agent can be resumed with [`Agent::run`].
+    #[tracing::instrument(
+        skip_all,
+        name = "agent.query_with_cancel",
+        fields(
+            gen_ai.operation.name = "invoke_agent",
+            openinference.span.kind = "AGENT"
+        )
+    )]
+    pub async fn query_with_cancel(
+        &mut self,
+        query: impl Into<String> + std::fmt::Debug,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        self.run_agent(Some(query.into()), false, &cancel).await
     }
 
     /// Run the agent with a user message once.
-    #[tracing::instrument(skip_all, name = "agent.query_once")]
+    #[tracing::instrument(
+        skip_all,
+        name = "agent.query_once",
+        fields(
+            gen_ai.operation.name = "invoke_agent",
+            openinference.span.kind = "AGENT"
+        )
+    )]
     pub async fn query_once(&mut self, query: impl Into<String> + std::fmt::Debug) -> Result<()> {
-        self.run_agent(Some(query.into()), true).await
+        self.run_agent(Some(query.into()), true, &CancellationToken::new())
+            .await
     }
 
     /// Run the agent with without user message. The agent will loop completions, make tool calls, until
     /// no new messages are available.
-    #[tracing::instrument(skip_all, name = "agent.run")]
+    #[tracing::instrument(
+        skip_all,
+        name = "agent.run",
+        fields(
+            gen_ai.operation.name = "invoke_agent",
+            openinference.span.kind = "AGENT"
+        )
+    )]
     pub async fn run(&mut self) -> Result<()> {
-        self.run_agent(None, false).await
+        self.run_agent(None, false, &CancellationToken::new()).await
+    }
+
+    /// Run the agent without user message until it is done or the token is cancelled.
+    ///
+    /// See [`Agent::query_with_cancel`] for what happens on cancellation.
+    #[tracing::instrument(
+        skip_all,
+        name = "agent.run_with_cancel",
+        fields(
+            gen_ai.operation.name = "invoke_agent",
+            openinference.span.kind = "AGENT"
+        )
+    )]
+    pub async fn run_with_cancel(&mut self, cancel: CancellationToken) -> Result<()> {
+        self.run_agent(None, false, &cancel).await
     }
 
     /// Run the agent with without user message. The agent will loop completions, make tool calls, until
-    #[tracing::instrument(skip_all, name = "agent.run_once")]
+    #[tracing::instrument(
+        skip_all,
+        name = "agent.run_once",
+        fields(
+            gen_ai.operation.name = "invoke_agent",
+            openinference.span.kind = "AGENT"
+        )
+    )]
     pub async fn run_once(&mut self) -> Result<()> {
-        self.run_agent(None, true).await
+        self.run_agent(None, true, &CancellationToken::new()).await
     }
 
     /// Retrieve the message history of the agent
@@ -249,15 +410,72 @@ impl Agent {
         self.context.history().await
     }
 
-    async fn run_agent(&mut self, maybe_query: Option<String>, just_once: bool) -> Result<()> {
+    async fn run_agent(
+        &mut self,
+        maybe_query: Option<String>,
+        just_once: bool,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
         if self.state.is_running() {
             anyhow::bail!("Agent is already running");
         }
 
+        let mut result = self.run_until_stopped(maybe_query, just_once, cancel).await;
+
+        // If there are no new messages, ensure we update our state
+        if self.state.is_running() {
+            self.stop();
+        }
+
+        // On every exit, including hooks or guardrails failing before the agent started
+        for hook in self.hooks_by_type(HookTypes::OnStop) {
+            if let Hook::OnStop(hook) = hook {
+                let span = tracing::info_span!(
+                    "hook",
+                    "otel.name" = format!("hook.{}", HookTypes::OnStop)
+                );
+                tracing::info!("Calling {} hook", HookTypes::OnStop);
+                if let Err(err) = hook(self).instrument(span.or_current()).await {
+                    // The error the agent stopped with takes precedence
+                    if result.is_ok() {
+                        result = Err(err);
+                    } else {
+                        tracing::error!("Error in {} hook: {err}", HookTypes::OnStop);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    async fn run_until_stopped(
+        &mut self,
+        maybe_query: Option<String>,
+        just_once: bool,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        // Input is checked before anything runs, a blocked query leaves no trace
+        let maybe_query = match maybe_query {
+            Some(query) => match guardrails::check(&self.guardrails, Target::Input, query).await? {
+                Checked::Pass(query) => Some(query),
+                // User input cannot be rewritten
+                Checked::Rewrite(reason) => {
+                    anyhow::bail!("Blocked by guardrail on {:?}: {reason}", Target::Input)
+                }
+            },
+            None => None,
+        };
+
+        self.rendered_system_prompt = match &self.system_prompt {
+            Some(system_prompt) => Some(system_prompt.render(self).await?),
+            None => None,
+        };
+
         if self.state.is_pending() {
-            if let Some(system_prompt) = &self.system_prompt {
+            if let Some(system_prompt) = &self.rendered_system_prompt {
                 self.context
-                    .add_messages(vec![ChatMessage::System(system_prompt.render().await?)])
+                    .add_messages(vec![ChatMessage::System(system_prompt.clone())])
                     .await;
             }
             for hook in self.hooks_by_type(HookTypes::BeforeAll) {
@@ -290,32 +508,57 @@ impl Agent {
         }
 
         while let Some(messages) = self.context.next_completion().await {
-            let result = self.run_completions(&messages).await;
-
-            if let Err(err) = result {
-                self.stop();
+            if let Err(err) = self.run_completions(&messages, cancel).await {
                 tracing::error!(error = ?err, "Agent stopped with error {err}");
                 return Err(err);
             }
 
-            if just_once || self.state.is_stopped() {
+            if just_once || self.state.is_stopped() || cancel.is_cancelled() {
                 break;
             }
         }
 
-        // If there are no new messages, ensure we update our state
-        self.stop();
-
         Ok(())
     }
 
-    #[tracing::instrument(skip_all, err)]
-    async fn run_completions(&mut self, messages: &[ChatMessage]) -> Result<()> {
+    /// Span fields follow the `OpenTelemetry` semantic conventions for generative AI and the
+    /// `OpenInference` conventions, so that completions and their usage can be exported with
+    /// `tracing-opentelemetry` and visualized in i.e. Arize Phoenix
+    #[tracing::instrument(
+        skip_all,
+        err,
+        fields(
+            otel.name = "chat",
+            gen_ai.operation.name = "chat",
+            gen_ai.usage.input_tokens = tracing::field::Empty,
+            gen_ai.usage.output_tokens = tracing::field::Empty,
+            openinference.span.kind = "LLM",
+            llm.token_count.prompt = tracing::field::Empty,
+            llm.token_count.completion = tracing::field::Empty,
+            llm.token_count.total = tracing::field::Empty,
+        )
+    )]
+    async fn run_completions(
+        &mut self,
+        messages: &[ChatMessage],
+        cancel: &CancellationToken,
+    ) -> Result<()> {
         debug!(
             "Running completion for agent with {} messages",
             messages.len()
         );
 
+        // The system prompt in the history is from the first run, use the one of this run
+        let messages = messages
+            .iter()
+            .map(|message| match (message, &self.rendered_system_prompt) {
+                (ChatMessage::System(_), Some(system_prompt)) => {
+                    ChatMessage::System(system_prompt.clone())
+                }
+                _ => message.clone(),
+            })
+            .collect::<Vec<_>>();
+
         let mut chat_completion_request = ChatCompletionRequest::builder()
             .messages(messages)
             .tools_spec(
@@ -350,20 +593,13 @@ impl Agent {
                 .join(",\n")
         );
 
-        let mut response = self.llm.complete(&chat_completion_request).await?;
+        let Some(response) = self
+            .complete_checked(&mut chat_completion_request, cancel)
+            .await?
+        else {
+            return Ok(());
+        };
 
-        for hook in self.hooks_by_type(HookTypes::AfterCompletion) {
-            if let Hook::AfterCompletion(hook) = hook {
-                let span = tracing::info_span!(
-                    "hook",
-                    "otel.name" = format!("hook.{}", HookTypes::AfterCompletion)
-                );
-                tracing::info!("Calling {} hook", HookTypes::AfterCompletion);
-                hook(&*self, &mut response)
-                    .instrument(span.or_current())
-                    .await?;
-            }
-        }
         self.add_message(ChatMessage::Assistant(
             response.message,
             response.tool_calls.clone(),
@@ -371,8 +607,12 @@ impl Agent {
         .await?;
 
         if let Some(tool_calls) = response.tool_calls {
-            self.invoke_tools(tool_calls).await?;
-        };
+            self.invoke_tools(tool_calls, cancel).await?;
+        }
+
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
 
         for hook in self.hooks_by_type(HookTypes::AfterEach) {
             if let Hook::AfterEach(hook) = hook {
@@ -388,7 +628,89 @@ impl Agent {
         Ok(())
     }
 
-    async fn invoke_tools(&mut self, tool_calls: Vec<ToolCall>) -> Result<()> {
+    /// Completes the request until the answer passes the guardrails, rewriting rejected answers
+    /// up to `max_answer_rewrites` times. Returns `None` if the agent is cancelled.
+    async fn complete_checked(
+        &self,
+        chat_completion_request: &mut ChatCompletionRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Option<ChatCompletionResponse>> {
+        let mut rewrites = 0;
+        loop {
+            // Dropping the completion aborts the request, nothing is added to the history
+            let mut response = tokio::select! {
+                biased;
+                () = cancel.cancelled() => {
+                    tracing::warn!("Agent cancelled before completion finished");
+                    return Ok(None);
+                }
+                response = self.llm.complete(chat_completion_request) => response?,
+            };
+
+            if let Some(recorder) = &self.trajectory_recorder {
+                recorder
+                    .record_completion(chat_completion_request, &response)
+                    .await;
+            }
+
+            if let Some(usage) = response.usage() {
+                let span = tracing::Span::current();
+                span.record("gen_ai.usage.input_tokens", usage.prompt_tokens);
+                span.record("gen_ai.usage.output_tokens", usage.completion_tokens);
+                span.record("llm.token_count.prompt", usage.prompt_tokens);
+                span.record("llm.token_count.completion", usage.completion_tokens);
+                span.record("llm.token_count.total", usage.total_tokens);
+            }
+
+            for hook in self.hooks_by_type(HookTypes::AfterCompletion) {
+                if let Hook::AfterCompletion(hook) = hook {
+                    let span = tracing::info_span!(
+                        "hook",
+                        "otel.name" = format!("hook.{}", HookTypes::AfterCompletion)
+                    );
+                    tracing::info!("Calling {} hook", HookTypes::AfterCompletion);
+                    hook(&*self, &mut response)
+                        .instrument(span.or_current())
+                        .await?;
+                }
+            }
+
+            let Some(message) = response.message.take() else {
+                return Ok(Some(response));
+            };
+
+            match guardrails::check(&self.guardrails, Target::Answer, message.clone()).await? {
+                Checked::Pass(checked) => {
+                    response.message = Some(checked);
+                    return Ok(Some(response));
+                }
+                Checked::Rewrite(reason) => {
+                    rewrites += 1;
+                    if rewrites > self.max_answer_rewrites {
+                        anyhow::bail!(
+                            "Answer rejected by guardrail after {} rewrites: {reason}",
+                            self.max_answer_rewrites
+                        );
+                    }
+
+                    // The rejected answer is only sent for the rewrite and never added to the
+                    // history. Its tool calls are dropped, so none are left without an output.
+                    chat_completion_request.messages.extend([
+                        ChatMessage::Assistant(Some(message), None),
+                        ChatMessage::User(format!(
+                            "Your answer was rejected: {reason}. Please rewrite it."
+                        )),
+                    ]);
+                }
+            }
+        }
+    }
+
+    async fn invoke_tools(
+        &mut self,
+        tool_calls: Vec<ToolCall>,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
         debug!("LLM returned tool calls: {:?}", tool_calls);
 
         let mut handles = vec![];
@@ -399,8 +721,33 @@ impl Agent {
             };
             tracing::info!("Calling tool `{}`", tool_call.name());
 
-            let tool_args = tool_call.args().map(String::from);
+            let tool_args = match tool_call.args() {
+                Some(args) => {
+                    match guardrails::check(&self.guardrails, Target::ToolArguments, args.into())
+                        .await?
+                    {
+                        Checked::Pass(args) => Some(args),
+                        Checked::Rewrite(reason) => {
+                            self.add_message(ChatMessage::ToolOutput(
+                                tool_call,
+                                ToolOutput::Fail(format!(
+                                    "The arguments were rejected: {reason}. Call the tool again \
+                                     with different arguments."
+                                )),
+                            ))
+                            .await?;
+                            continue;
+                        }
+                    }
+                }
+                None => None,
+            };
             let context: Arc<dyn AgentContext> = Arc::clone(&self.context);
+            let policy = self
+                .tool_policies
+                .get(tool_call.name())
+                .unwrap_or(&self.default_tool_policy)
+                .clone();
 
             for hook in self.hooks_by_type(HookTypes::BeforeTool) {
                 if let Hook::BeforeTool(hook) = hook {
@@ -415,44 +762,133 @@ impl Agent {
                 }
             }
 
-            let tool_span =
-                tracing::info_span!("tool", "otel.name" = format!("tool.{}", tool.name()));
+            let tool_span = tracing::info_span!(
+                "tool",
+                "otel.name" = format!("tool.{}", tool.name()),
+                "gen_ai.operation.name" = "execute_tool",
+                "gen_ai.tool.name" = tool.name(),
+                "gen_ai.tool.call.id" = tool_call.id(),
+                "openinference.span.kind" = "TOOL",
+                "tool.name" = tool.name()
+            );
+
+            let processor = self.tool_output_processor.clone();
+            let processed_call = tool_call.clone();
 
             let handle = tokio::spawn(async move {
+                    let started = Instant::now();
                     let tool_args = ArgPreprocessor::preprocess(tool_args.as_deref());
-                    let output = tool.invoke(&*context, tool_args.as_deref()).await.map_err(|e| { tracing::error!(error = %e, "Failed tool call"); e })?;
+                    let mut output = policy.invoke(&*tool, &*context, tool_args.as_deref()).await.inspect_err(|e| tracing::error!(error = %e, "Failed tool call"));
 
-                    tracing::debug!(output = output.to_string(), args = ?tool_args, tool_name = tool.name(), "Completed tool call");
+                    // Processed in the task, so that i.e. summaries of parallel calls run concurrently
+                    if let (Ok(ToolOutput::Text(text)), Some(processor)) = (&mut output, &processor) {
+                        match processor.process(&processed_call, std::mem::take(text)).await {
+                            Ok(processed) => *text = processed,
+                            Err(err) => output = Err(err.into()),
+                        }
+                    }
 
-                    Ok(output)
+                    if let Ok(output) = &output {
+                        tracing::debug!(output = output.to_string(), args = ?tool_args, tool_name = tool.name(), "Completed tool call");
+                    }
+
+                    (output, started.elapsed())
                 }.instrument(tool_span.or_current()));
 
             handles.push((handle, tool_call));
         }
 
-        for (handle, tool_call) in handles {
-            let mut output = handle.await?;
+        let mut handles = handles.into_iter();
+        while let Some((mut handle, tool_call)) = handles.next() {
+            let finished = tokio::select! {
+                biased;
+                result = &mut handle => Some(result?),
+                () = cancel.cancelled() => None,
+            };
 
-            // Invoking hooks feels too verbose and repetitive
-            for hook in self.hooks_by_type(HookTypes::AfterTool) {
-                if let Hook::AfterTool(hook) = hook {
-                    let span = tracing::info_span!(
-                        "hook",
-                        "otel.name" = format!("hook.{}", HookTypes::AfterTool)
-                    );
-                    tracing::info!("Calling {} hook", HookTypes::AfterTool);
-                    hook(&*self, &tool_call, &mut output)
-                        .instrument(span.or_current())
-                        .await?;
+            let Some((output, duration)) = finished else {
+                tracing::warn!("Agent cancelled before tool calls finished");
+                return self
+                    .cancel_tool_calls(std::iter::once((handle, tool_call)).chain(handles))
+                    .await;
+            };
+
+            self.handle_tool_output(tool_call, output, duration).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Aborts the tool calls that are still running. Tool calls that finished in the meantime keep
+    /// their output, the others get a failed output, so the history stays valid for a resume.
+    async fn cancel_tool_calls(
+        &mut self,
+        handles: impl Iterator<Item = (JoinHandle<ToolCallResult>, ToolCall)>,
+    ) -> Result<()> {
+        // Abort everything first, so no tool keeps running if recording an output fails
+        let handles = handles
+            .map(|(handle, tool_call)| {
+                let finished = handle.is_finished();
+                if !finished {
+                    handle.abort();
                 }
+                (handle, tool_call, finished)
+            })
+            .collect::<Vec<_>>();
+
+        for (handle, tool_call, finished) in handles {
+            if finished {
+                let (output, duration) = handle.await?;
+                self.handle_tool_output(tool_call, output, duration).await?;
+                continue;
             }
 
-            let output = output?;
-            self.handle_control_tools(&output);
-            self.add_message(ChatMessage::ToolOutput(tool_call, output))
+            self.add_message(ChatMessage::ToolOutput(
+                tool_call,
+                ToolOutput::Fail("Cancelled before the tool call finished".to_string()),
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Records the output of a finished tool call, runs the hooks and adds it to the history
+    async fn handle_tool_output(
+        &mut self,
+        tool_call: ToolCall,
+        mut output: Result<ToolOutput, ToolError>,
+        duration: Duration,
+    ) -> Result<()> {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log
+                .record_tool_call(&tool_call, &output, duration)
                 .await?;
         }
 
+        if let Some(recorder) = &self.trajectory_recorder {
+            recorder.record_tool_call(&tool_call, &output).await;
+        }
+
+        // Invoking hooks feels too verbose and repetitive
+        for hook in self.hooks_by_type(HookTypes::AfterTool) {
+            if let Hook::AfterTool(hook) = hook {
+                let span = tracing::info_span!(
+                    "hook",
+                    "otel.name" = format!("hook.{}", HookTypes::AfterTool)
+                );
+                tracing::info!("Calling {} hook", HookTypes::AfterTool);
+                hook(&*self, &tool_call, &mut output)
+                    .instrument(span.or_current())
+                    .await?;
+            }
+        }
+
+        let output = output?;
+        self.handle_control_tools(&output);
+        self.add_message(ChatMessage::ToolOutput(tool_call, output))
+            .await?;
+
         Ok(())
     }
 
@@ -617,6 +1053,345 @@ mod tests {
         agent.query(prompt).await.unwrap();
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_agent_query_with_cancel() {
+        // The llm is never called
+        let mock_llm = MockChatCompletion::new();
+
+        let mut agent = Agent::builder()
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .build()
+            .unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        agent
+            .query_with_cancel("Write a poem", cancel)
+            .await
+            .unwrap();
+
+        assert!(agent.is_stopped());
+        assert_eq!(agent.history().await, vec![user!("Write a poem")]);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_agent_guardrail_blocks_input() {
+        // The llm is never called
+        let mock_llm = MockChatCompletion::new();
+
+        let mut agent = Agent::builder()
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .guardrail(Guardrail::new(guardrails::Keywords::new(&["password"])))
+            .build()
+            .unwrap();
+
+        let error = agent.query("What is the password?").await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Blocked by guardrail on Input: contains `password`"
+        );
+        assert!(agent.history().await.is_empty());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_agent_guardrail_rewrites_answer() {
+        let mock_llm = MockChatCompletion::new();
+        let rejected = assistant!("The password is hunter2");
+        let rewrite = user!("Your answer was rejected: contains `password`. Please rewrite it.");
+
+        mock_llm.expect_complete(
+            chat_request! { user!("What is the password?"); tools = [] },
+            Ok(chat_response! { "The password is hunter2"; tool_calls = ["stop"] }),
+        );
+        mock_llm.expect_complete(
+            chat_request! {
+                user!("What is the password?"),
+                rejected.clone(),
+                rewrite.clone();
+                tools = []
+            },
+            Ok(chat_response! { "I cannot share that"; tool_calls = ["stop"] }),
+        );
+
+        let mut agent = Agent::builder()
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .guardrail(
+                Guardrail::new(guardrails::Keywords::new(&["password"]))
+                    .with_targets(&[Target::Answer])
+                    .with_action(guardrails::Action::Rewrite),
+            )
+            .build()
+            .unwrap();
+
+        agent.query("What is the password?").await.unwrap();
+
+        let history = agent.history().await;
+        assert_eq!(history[1], assistant!("I cannot share that", ["stop"]));
+        assert!(!history.contains(&rejected));
+        assert!(!history.contains(&rewrite));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_agent_guardrail_limits_rewrites() {
+        let mock_llm = MockChatCompletion::new();
+
+        mock_llm.expect_complete(
+            chat_request! { user!("What is the password?"); tools = [] },
+            Ok(chat_response! { "The password is hunter2"; tool_calls = [] }),
+        );
+        mock_llm.expect_complete(
+            chat_request! {
+                user!("What is the password?"),
+                assistant!("The password is hunter2"),
+                user!("Your answer was rejected: contains `password`. Please rewrite it.");
+                tools = []
+            },
+            Ok(chat_response! { "Fine, the password is hunter2"; tool_calls = [] }),
+        );
+
+        let mut agent = Agent::builder()
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .guardrail(
+                Guardrail::new(guardrails::Keywords::new(&["password"]))
+                    .with_targets(&[Target::Answer])
+                    .with_action(guardrails::Action::Rewrite),
+            )
+            .max_answer_rewrites(1)
+            .build()
+            .unwrap();
+
+        let error = agent.query("What is the password?").await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Answer rejected by guardrail after 1 rewrites: contains `password`"
+        );
+        assert_eq!(agent.history().await, vec![user!("What is the password?")]);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_agent_record_and_replay_trajectory() {
+        let mock_llm = MockChatCompletion::new();
+        mock_llm.expect_complete(
+            chat_request! { user!("Write a poem"); tools = [] },
+            Ok(chat_response! { "Roses are red"; tool_calls = ["stop"] }),
+        );
+
+        let recorder = TrajectoryRecorder::default();
+        Agent::builder()
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .trajectory_recorder(recorder.clone())
+            .build()
+            .unwrap()
+            .query("Write a poem")
+            .await
+            .unwrap();
+
+        let recorded = recorder.trajectory().await;
+        assert_eq!(recorded.steps.len(), 2);
+
+        let replayed = TrajectoryRecorder::default();
+        let mut agent = Agent::builder()
+            .llm(&crate::trajectory::Replay::new(recorded.clone()))
+            .no_system_prompt()
+            .trajectory_recorder(replayed.clone())
+            .build()
+            .unwrap();
+        agent.query("Write a poem").await.unwrap();
+
+        assert_eq!(replayed.trajectory().await, recorded);
+        assert!(agent.is_stopped());
+    }
+
+    #[derive(Clone, Debug)]
+    struct SleepTool;
+
+    #[async_trait::async_trait]
+    impl Tool for SleepTool {
+        async fn invoke(
+            &self,
+            _agent_context: &dyn AgentContext,
+            _raw_args: Option<&str>,
+        ) -> Result<ToolOutput, swiftide_core::chat_completion::errors::ToolError> {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            Ok("Slept".into())
+        }
+
+        fn name(&self) -> &'static str {
+            "sleep"
+        }
+
+        fn tool_spec(&self) -> swiftide_core::chat_completion::ToolSpec {
+            swiftide_core::chat_completion::ToolSpec::builder()
+                .name("sleep")
+                .description("Sleeps for a minute")
+                .build()
+                .unwrap()
+        }
+    }
+
+    impl From<SleepTool> for Box<dyn Tool> {
+        fn from(val: SleepTool) -> Self {
+            Box::new(val)
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_agent_query_with_cancel_aborts_tool_calls() {
+        let mock_llm = MockChatCompletion::new();
+        let mock_on_stop = MockHook::new("on_stop").expect_calls(1).to_owned();
+
+        mock_llm.expect_complete(
+            chat_request! {
+                user!("Take a nap");
+                tools = [SleepTool]
+            },
+            Ok(chat_response! {
+                "Sleeping";
+                tool_calls = ["sleep"]
+            }),
+        );
+
+        let cancel = CancellationToken::new();
+        let cancel_before_tool = cancel.clone();
+
+        let mut agent = Agent::builder()
+            .tools([SleepTool])
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .before_tool(move |_, _| {
+                let cancel = cancel_before_tool.clone();
+                Box::pin(async move {
+                    cancel.cancel();
+                    Ok(())
+                })
+            })
+            .on_stop(mock_on_stop.on_stop_fn())
+            .build()
+            .unwrap();
+
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            agent.query_with_cancel("Take a nap", cancel),
+        )
+        .await
+        .expect("Tool call was not aborted")
+        .unwrap();
+
+        assert!(agent.is_stopped());
+        assert_eq!(
+            agent.history().await,
+            vec![
+                user!("Take a nap"),
+                assistant!("Sleeping", ["sleep"]),
+                ChatMessage::ToolOutput(
+                    ToolCall::builder().name("sleep").id("1").build().unwrap(),
+                    ToolOutput::Fail("Cancelled before the tool call finished".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[derive(Clone, Debug)]
+    struct CancelTool(CancellationToken);
+
+    #[async_trait::async_trait]
+    impl Tool for CancelTool {
+        async fn invoke(
+            &self,
+            _agent_context: &dyn AgentContext,
+            _raw_args: Option<&str>,
+        ) -> Result<ToolOutput, swiftide_core::chat_completion::errors::ToolError> {
+            self.0.cancel();
+            Ok("Cancelled".into())
+        }
+
+        fn name(&self) -> &'static str {
+            "cancel"
+        }
+
+        fn tool_spec(&self) -> swiftide_core::chat_completion::ToolSpec {
+            swiftide_core::chat_completion::ToolSpec::builder()
+                .name("cancel")
+                .description("Cancels the agent")
+                .build()
+                .unwrap()
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_agent_query_with_cancel_keeps_finished_tool_calls() {
+        let mock_llm = MockChatCompletion::new();
+        let cancel = CancellationToken::new();
+        let cancel_tool = CancelTool(cancel.clone());
+
+        mock_llm.expect_complete(
+            chat_request! {
+                user!("Take a nap");
+                tools = [SleepTool, cancel_tool.clone()]
+            },
+            Ok(chat_response! {
+                "Sleeping";
+                tool_calls = ["sleep", "cancel"]
+            }),
+        );
+
+        let tools: [Box<dyn Tool>; 2] = [Box::new(SleepTool), Box::new(cancel_tool)];
+        let mut agent = Agent::builder()
+            .tools(tools)
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .build()
+            .unwrap();
+
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            agent.query_with_cancel("Take a nap", cancel),
+        )
+        .await
+        .expect("Tool call was not aborted")
+        .unwrap();
+
+        // The sleep is still running when the agent is cancelled, the cancel call has finished
+        assert_eq!(
+            agent.history().await,
+            vec![
+                user!("Take a nap"),
+                assistant!("Sleeping", ["sleep", "cancel"]),
+                ChatMessage::ToolOutput(
+                    ToolCall::builder().name("sleep").id("1").build().unwrap(),
+                    ToolOutput::Fail("Cancelled before the tool call finished".to_string())
+                ),
+                tool_output!("cancel", "Cancelled"),
+            ]
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_agent_on_stop_runs_when_start_fails() {
+        let mock_llm = MockChatCompletion::new();
+        let mock_on_stop = MockHook::new("on_stop").expect_calls(1).to_owned();
+
+        let mut agent = Agent::builder()
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .on_start(|_| Box::pin(async { anyhow::bail!("Failed to start") }))
+            .on_stop(mock_on_stop.on_stop_fn())
+            .build()
+            .unwrap();
+
+        let error = agent.query("Write a poem").await.unwrap_err();
+
+        assert_eq!(error.to_string(), "Failed to start");
+        assert!(agent.history().await.is_empty());
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_agent_tool_run_once() {
         let prompt = "Write a poem";
@@ -735,6 +1510,63 @@ mod tests {
         assert!(agent.state.is_stopped());
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_system_prompt_is_rendered_every_run() {
+        let mock_llm = MockChatCompletion::new();
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let system_prompt = SystemPrompt::builder()
+            .template("Run {{ run }}")
+            .variable("run", move |_: &Agent| {
+                let runs = Arc::clone(&runs);
+                Box::pin(async move {
+                    let run = runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                    Ok(serde_json::Value::from(run))
+                })
+            })
+            .build()
+            .unwrap();
+
+        let mock_response = chat_response! {
+            "Roses are red";
+            tool_calls = []
+        };
+
+        mock_llm.expect_complete(
+            chat_request! {
+                system!("Run 1"),
+                user!("Write a poem");
+                tools = []
+            },
+            Ok(mock_response.clone()),
+        );
+
+        let mut agent = Agent::builder()
+            .system_prompt(system_prompt)
+            .llm(&mock_llm)
+            .build()
+            .unwrap();
+
+        agent.query_once("Write a poem").await.unwrap();
+
+        agent
+            .context
+            .add_message(ChatMessage::new_summary("Summary"))
+            .await;
+
+        mock_llm.expect_complete(
+            chat_request! {
+                system!("Run 2"),
+                summary!("Summary"),
+                user!("Write another poem");
+                tools = []
+            },
+            Ok(mock_response),
+        );
+
+        agent.query_once("Write another poem").await.unwrap();
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_summary() {
         let prompt = "Write a poem";
@@ -798,6 +1630,7 @@ mod tests {
     async fn test_agent_hooks() {
         let mock_before_all = MockHook::new("before_all").expect_calls(1).to_owned();
         let mock_on_start_fn = MockHook::new("on_start").expect_calls(1).to_owned();
+        let mock_on_stop_fn = MockHook::new("on_stop").expect_calls(1).to_owned();
         let mock_before_completion = MockHook::new("before_completion")
             .expect_calls(2)
             .to_owned();
@@ -849,6 +1682,7 @@ mod tests {
             .no_system_prompt()
             .before_all(mock_before_all.hook_fn())
             .on_start(mock_on_start_fn.on_start_fn())
+            .on_stop(mock_on_stop_fn.on_stop_fn())
             .before_completion(mock_before_completion.before_completion_fn())
             .before_tool(mock_before_tool.before_tool_fn())
             .after_completion(mock_after_completion.after_completion_fn())
@@ -0,0 +1,230 @@
+//! Records every tool call of an agent to an audit sink
+//!
+//! Unlike tracing, audit records are always written and never sampled, which makes them suitable
+//! for compliance when agents operate on production systems. Every record holds the tool, its
+//! arguments, a hash of its result, the duration, and the caller and executor if configured.
+//!
+//! Records are written after the tool finished and before any `after_tool` hooks run. If a record
+//! cannot be written, the agent stops with an error.
+//!
+//! Implement [`AuditSink`] to write records elsewhere, i.e. to a database or message queue.
+//! [`FileAuditSink`] appends records as json lines to a file.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_agents::{Agent, audit::{AuditLog, FileAuditSink}};
+//! # use swiftide_core::ChatCompletion;
+//! # fn build(llm: &(impl ChatCompletion + Clone + 'static)) {
+//! Agent::builder()
+//!     .llm(llm)
+//!     .audit_log(
+//!         AuditLog::new(FileAuditSink::new("audit.jsonl"))
+//!             .with_caller("deploy-agent")
+//!             .with_executor("docker"),
+//!     )
+//!     .build()
+//!     .unwrap();
+//! # }
+//! ```
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use swiftide_core::chat_completion::{errors::ToolError, ToolCall, ToolOutput};
+use tokio::{io::AsyncWriteExt as _, sync::Mutex};
+
+/// A single tool call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    /// Milliseconds since the unix epoch when the tool call finished
+    pub timestamp_ms: u64,
+    pub caller: Option<String>,
+    pub executor: Option<String>,
+    pub tool_name: String,
+    pub tool_call_id: String,
+    /// The raw arguments as provided by the language model
+    pub arguments: Option<String>,
+    /// Sha256 of the output or error, hex encoded
+    pub result_hash: String,
+    pub success: bool,
+    pub duration_ms: u64,
+}
+
+/// Writes audit records
+#[async_trait]
+pub trait AuditSink: Send + Sync + DynClone {
+    async fn record(&self, record: &ToolCallRecord) -> Result<()>;
+}
+
+dyn_clone::clone_trait_object!(AuditSink);
+
+/// Appends audit records as json lines to a file
+#[derive(Debug, Clone)]
+pub struct FileAuditSink {
+    path: PathBuf,
+    // Serializes writes from agents sharing the sink
+    lock: Arc<Mutex<()>>,
+}
+
+impl FileAuditSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditSink for FileAuditSink {
+    async fn record(&self, record: &ToolCallRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open audit log {}", self.path.display()))?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+/// Configures where and how the tool calls of an agent are audited
+#[derive(Clone)]
+pub struct AuditLog {
+    sink: Arc<dyn AuditSink>,
+    caller: Option<String>,
+    executor: Option<String>,
+}
+
+impl std::fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("caller", &self.caller)
+            .field("executor", &self.executor)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuditLog {
+    pub fn new(sink: impl AuditSink + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            caller: None,
+            executor: None,
+        }
+    }
+
+    /// Identifies the agent, user or service on whose behalf tools are called
+    #[must_use]
+    pub fn with_caller(mut self, caller: impl Into<String>) -> Self {
+        self.caller = Some(caller.into());
+        self
+    }
+
+    /// Identifies where tools are executed, i.e. `local` or `docker`
+    #[must_use]
+    pub fn with_executor(mut self, executor: impl Into<String>) -> Self {
+        self.executor = Some(executor.into());
+        self
+    }
+
+    pub(crate) async fn record_tool_call(
+        &self,
+        tool_call: &ToolCall,
+        output: &Result<ToolOutput, ToolError>,
+        duration: Duration,
+    ) -> Result<()> {
+        let result = match output {
+            Ok(output) => output.to_string(),
+            Err(error) => error.to_string(),
+        };
+
+        let record = ToolCallRecord {
+            timestamp_ms: millis(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default(),
+            ),
+            caller: self.caller.clone(),
+            executor: self.executor.clone(),
+            tool_name: tool_call.name().to_string(),
+            tool_call_id: tool_call.id().to_string(),
+            arguments: tool_call.args().map(str::to_string),
+            result_hash: format!("{:x}", Sha256::digest(result.as_bytes())),
+            success: output.is_ok(),
+            duration_ms: millis(duration),
+        };
+
+        self.sink
+            .record(&record)
+            .await
+            .context("Failed to record tool call in audit log")
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_audit_sink_appends_records() {
+        let temp_dir = temp_dir::TempDir::new().unwrap();
+        let path = temp_dir.child("audit.jsonl");
+        let audit_log = AuditLog::new(FileAuditSink::new(&path)).with_caller("test");
+
+        let tool_call = ToolCall::builder()
+            .id("1")
+            .name("shell")
+            .args(r#"{"cmd":"ls"}"#)
+            .build()
+            .unwrap();
+
+        audit_log
+            .record_tool_call(&tool_call, &Ok("output".into()), Duration::from_millis(5))
+            .await
+            .unwrap();
+        audit_log
+            .record_tool_call(
+                &tool_call,
+                &Err(ToolError::MissingArguments("shell".to_string())),
+                Duration::from_millis(5),
+            )
+            .await
+            .unwrap();
+
+        let records = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<ToolCallRecord>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].caller.as_deref(), Some("test"));
+        assert_eq!(records[0].arguments.as_deref(), Some(r#"{"cmd":"ls"}"#));
+        assert_eq!(
+            records[0].result_hash,
+            format!("{:x}", Sha256::digest(b"output"))
+        );
+        assert!(records[0].success);
+        assert!(!records[1].success);
+    }
+}
@@ -0,0 +1,260 @@
+//! Compacts the conversation of long running agents to stay within the context window
+//!
+//! The [`DefaultContext`](crate::DefaultContext) compacts the messages of a completion with a
+//! [`ContextCompactor`] when they exceed a token limit, see
+//! [`DefaultContext::with_compaction`](crate::DefaultContext::with_compaction). The full history
+//! is kept, the compacted messages replace the older messages for later completions.
+//!
+//! * [`SlidingWindow`] drops the oldest messages until the conversation fits in a token budget
+//! * [`Summarize`] summarizes the older messages with a language model and keeps the most recent
+//!   messages as they are
+//!
+//! Both keep the system prompt, and never separate tool outputs from the tool calls that
+//! requested them.
+use std::{fmt::Debug, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+use indoc::formatdoc;
+use swiftide_core::{
+    chat_completion::ChatMessage,
+    tokenizer::{ApproximateTokens, EstimateTokens},
+    SimplePrompt,
+};
+
+/// Compacts the messages of a completion into fewer tokens
+#[async_trait]
+pub trait ContextCompactor: Send + Sync + Debug + DynClone {
+    /// Returns the compacted messages to complete on instead
+    async fn compact(&self, messages: Vec<ChatMessage>) -> Result<Vec<ChatMessage>>;
+}
+
+dyn_clone::clone_trait_object!(ContextCompactor);
+
+/// Keeps the most recent messages that fit in a token budget, and the system prompt
+///
+/// If the most recent message does not fit on its own, it is kept anyway.
+#[derive(Debug, Clone)]
+pub struct SlidingWindow {
+    max_tokens: usize,
+    estimator: Arc<dyn EstimateTokens>,
+}
+
+impl SlidingWindow {
+    /// Keeps at most `max_tokens`, estimated with [`ApproximateTokens`]
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            estimator: Arc::new(ApproximateTokens::default()),
+        }
+    }
+
+    /// Estimates tokens with a tokenizer, i.e. tiktoken for `OpenAI` models
+    #[must_use]
+    pub fn with_estimator(mut self, estimator: impl EstimateTokens + 'static) -> Self {
+        self.estimator = Arc::new(estimator);
+        self
+    }
+}
+
+#[async_trait]
+impl ContextCompactor for SlidingWindow {
+    async fn compact(&self, messages: Vec<ChatMessage>) -> Result<Vec<ChatMessage>> {
+        let (system, conversation): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .partition(|message| matches!(message, ChatMessage::System(_)));
+
+        let mut budget = self
+            .max_tokens
+            .saturating_sub(self.estimator.estimate(&system).await?);
+
+        let mut start = conversation.len();
+        for message in conversation.iter().rev() {
+            let tokens = self.estimator.estimate(message).await?;
+            if tokens > budget && start < conversation.len() {
+                break;
+            }
+            budget = budget.saturating_sub(tokens);
+            start -= 1;
+        }
+
+        let start = tool_call_boundary(&conversation, start);
+        tracing::debug!(dropped = start, "Compacted context with a sliding window");
+
+        Ok(system
+            .into_iter()
+            .chain(conversation.into_iter().skip(start))
+            .collect())
+    }
+}
+
+/// Summarizes all but the most recent messages with a language model
+///
+/// The summary is added as a [`ChatMessage::Summary`] after the system prompt, followed by the
+/// most recent messages.
+#[derive(Debug, Clone)]
+pub struct Summarize {
+    llm: Arc<dyn SimplePrompt>,
+    keep_recent: usize,
+}
+
+impl Summarize {
+    /// Summarizes with the language model, keeping the last 10 messages
+    pub fn from_client(llm: impl SimplePrompt + 'static) -> Self {
+        Self {
+            llm: Arc::new(llm),
+            keep_recent: 10,
+        }
+    }
+
+    /// The number of most recent messages that are kept as they are
+    #[must_use]
+    pub fn with_keep_recent(mut self, keep_recent: usize) -> Self {
+        self.keep_recent = keep_recent;
+        self
+    }
+
+    fn prompt(messages: &[ChatMessage]) -> String {
+        let conversation = messages
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        formatdoc! {"
+            Summarize the conversation below between an assistant and a user, so that the
+            assistant can continue the task with only the summary.
+
+            Keep the goal of the user, the decisions that were made, the results of tools that
+            are still relevant and the work that remains. Leave out anything else.
+
+            Respond with the summary only.
+
+            ## Conversation
+            {conversation}
+        "}
+    }
+}
+
+#[async_trait]
+impl ContextCompactor for Summarize {
+    async fn compact(&self, messages: Vec<ChatMessage>) -> Result<Vec<ChatMessage>> {
+        let (system, conversation): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .partition(|message| matches!(message, ChatMessage::System(_)));
+
+        let start = tool_call_boundary(
+            &conversation,
+            conversation.len().saturating_sub(self.keep_recent),
+        );
+
+        if start == 0 {
+            return Ok(system.into_iter().chain(conversation).collect());
+        }
+
+        let summary = self
+            .llm
+            .prompt(Self::prompt(&conversation[..start]).into())
+            .await?;
+        tracing::debug!(summarized = start, "Compacted context with a summary");
+
+        Ok(system
+            .into_iter()
+            .chain(std::iter::once(ChatMessage::Summary(summary)))
+            .chain(conversation.into_iter().skip(start))
+            .collect())
+    }
+}
+
+/// Moves the start of the kept messages back, so that tool outputs keep their tool call
+fn tool_call_boundary(messages: &[ChatMessage], mut start: usize) -> usize {
+    while start > 0 && matches!(messages.get(start), Some(ChatMessage::ToolOutput(..))) {
+        start -= 1;
+    }
+
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::{
+        chat_completion::{ToolCall, ToolOutput},
+        MockSimplePrompt,
+    };
+
+    use super::*;
+    use crate::{assistant, system, tool_output, user};
+
+    #[tokio::test]
+    async fn test_sliding_window_keeps_system_and_recent_messages() {
+        let messages = vec![
+            system!("You are a helpful assistant"),
+            user!("a".repeat(400)),
+            assistant!("b".repeat(400)),
+            user!("What is the weather?"),
+            assistant!("", ["weather"]),
+            tool_output!("weather", "Sunny"),
+        ];
+
+        let compacted = SlidingWindow::new(50).compact(messages).await.unwrap();
+
+        assert_eq!(
+            compacted,
+            vec![
+                system!("You are a helpful assistant"),
+                user!("What is the weather?"),
+                assistant!("", ["weather"]),
+                tool_output!("weather", "Sunny"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sliding_window_keeps_tool_calls_with_outputs() {
+        let messages = vec![
+            user!("What is the weather?"),
+            assistant!("a".repeat(400), ["weather"]),
+            tool_output!("weather", "Sunny"),
+        ];
+
+        let compacted = SlidingWindow::new(10)
+            .compact(messages.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(compacted, messages[1..]);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_older_messages() {
+        let mut llm = MockSimplePrompt::new();
+        llm.expect_prompt()
+            .times(1)
+            .returning(|_| Ok("The user greeted".to_string()));
+
+        let messages = vec![
+            system!("You are a helpful assistant"),
+            user!("Hello"),
+            assistant!("Hi!"),
+            user!("What is the weather?"),
+        ];
+
+        let compacted = Summarize::from_client(llm)
+            .with_keep_recent(1)
+            .compact(messages)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            compacted,
+            vec![
+                system!("You are a helpful assistant"),
+                ChatMessage::Summary("The user greeted".to_string()),
+                user!("What is the weather?"),
+            ]
+        );
+
+        assert!(Summarize::prompt(&[user!("Hello")]).contains("User: \"Hello\""));
+    }
+}
@@ -7,17 +7,25 @@
 //! If chat messages include a `ChatMessage::Summary`, all previous messages are ignored except the
 //! system prompt. This is useful for maintaining focus in long conversations or managing token
 //! limits.
+//!
+//! With a [`ContextCompactor`], the messages of a completion are compacted when they exceed a
+//! token limit, see [`DefaultContext::with_compaction`].
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use swiftide_core::chat_completion::ChatMessage;
+use swiftide_core::tokenizer::{ApproximateTokens, EstimateTokens};
 use swiftide_core::{AgentContext, Command, CommandError, CommandOutput, ToolExecutor};
 use tokio::sync::Mutex;
 
+use crate::compaction::ContextCompactor;
 use crate::tools::local_executor::LocalExecutor;
 
+/// Compacted messages that replace the history up to an index
+type Compacted = Option<(usize, Vec<ChatMessage>)>;
+
 // TODO: Remove unit as executor and implement a local executor instead
 #[derive(Clone)]
 pub struct DefaultContext {
@@ -34,6 +42,15 @@ pub struct DefaultContext {
 
     /// Stop if last message is from the assistant
     stop_on_assistant: bool,
+
+    /// Compacts the messages of a completion when they exceed the token limit
+    compactor: Option<(Arc<dyn ContextCompactor>, usize)>,
+
+    /// Estimates the tokens of a completion for compaction
+    token_estimator: Arc<dyn EstimateTokens>,
+
+    /// The messages of the last compaction
+    compacted: Arc<Mutex<Compacted>>,
 }
 
 impl Default for DefaultContext {
@@ -44,6 +61,9 @@ impl Default for DefaultContext {
             current_completions_ptr: Arc::new(AtomicUsize::new(0)),
             tool_executor: Arc::new(LocalExecutor::default()) as Arc<dyn ToolExecutor>,
             stop_on_assistant: true,
+            compactor: None,
+            token_estimator: Arc::new(ApproximateTokens::default()),
+            compacted: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -56,6 +76,8 @@ impl std::fmt::Debug for DefaultContext {
             .field("current_completions_ptr", &self.current_completions_ptr)
             .field("tool_executor", &"Arc<dyn ToolExecutor>")
             .field("stop_on_assistant", &self.stop_on_assistant)
+            .field("compactor", &self.compactor)
+            .field("token_estimator", &self.token_estimator)
             .finish()
     }
 }
@@ -75,27 +97,100 @@ impl DefaultContext {
         self.stop_on_assistant = stop;
         self
     }
+
+    /// Compacts the messages of a completion when they exceed `max_tokens`
+    ///
+    /// The history is kept as is, later completions continue from the compacted messages.
+    pub fn with_compaction(
+        &mut self,
+        compactor: impl ContextCompactor + 'static,
+        max_tokens: usize,
+    ) -> &mut Self {
+        self.compactor = Some((Arc::new(compactor), max_tokens));
+        self
+    }
+
+    /// Estimates the tokens of a completion for compaction, defaults to [`ApproximateTokens`]
+    pub fn with_token_estimator(&mut self, estimator: impl EstimateTokens + 'static) -> &mut Self {
+        self.token_estimator = Arc::new(estimator);
+        self
+    }
+
+    /// Compacts the messages if they exceed the token limit
+    ///
+    /// Returns `None` if the messages are within the limit or compaction fails, the messages are
+    /// then completed on as they are.
+    async fn compact(&self, messages: &[ChatMessage]) -> Option<Vec<ChatMessage>> {
+        let (compactor, max_tokens) = self.compactor.as_ref()?;
+
+        let tokens = match self.token_estimator.estimate(&messages).await {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                tracing::warn!(?error, "Failed to estimate tokens for compaction");
+                return None;
+            }
+        };
+
+        if tokens <= *max_tokens {
+            return None;
+        }
+
+        tracing::debug!(tokens, max_tokens, "Compacting context");
+        match compactor.compact(messages.to_vec()).await {
+            Ok(messages) => Some(messages),
+            Err(error) => {
+                tracing::error!(?error, "Failed to compact context");
+                None
+            }
+        }
+    }
 }
 #[async_trait]
 impl AgentContext for DefaultContext {
     /// Retrieve messages for the next completion
+    ///
+    /// The history is not locked while the messages are compacted.
     async fn next_completion(&self) -> Option<Vec<ChatMessage>> {
-        let history = self.completion_history.lock().await;
+        let (messages, end) = {
+            let history = self.completion_history.lock().await;
 
-        let current = self.completions_ptr.load(Ordering::SeqCst);
+            let current = self.completions_ptr.load(Ordering::SeqCst);
+
+            if history[current..].is_empty()
+                || (self.stop_on_assistant
+                    && matches!(history.last(), Some(ChatMessage::Assistant(_, _))))
+            {
+                return None;
+            }
 
-        if history[current..].is_empty()
-            || (self.stop_on_assistant
-                && matches!(history.last(), Some(ChatMessage::Assistant(_, _))))
-        {
-            None
-        } else {
             let previous = self.completions_ptr.swap(history.len(), Ordering::SeqCst);
             self.current_completions_ptr
                 .store(previous, Ordering::SeqCst);
 
-            Some(filter_messages_since_summary(history.clone()))
+            let messages = match self.compacted.lock().await.as_ref() {
+                Some((end, messages)) => messages.iter().chain(&history[*end..]).cloned().collect(),
+                None => history.clone(),
+            };
+
+            (filter_messages_since_summary(messages), history.len())
+        };
+
+        let Some(compacted_messages) = self.compact(&messages).await else {
+            return Some(messages);
+        };
+
+        // Keeps the compaction unless the history was redriven or compacted further meanwhile
+        let history = self.completion_history.lock().await;
+        let mut compacted = self.compacted.lock().await;
+        if end <= history.len()
+            && compacted
+                .as_ref()
+                .is_none_or(|(compacted_end, _)| *compacted_end <= end)
+        {
+            *compacted = Some((end, compacted_messages.clone()));
         }
+
+        Some(compacted_messages)
     }
 
     /// Returns the messages the agent is currently completing on
@@ -141,6 +236,15 @@ impl AgentContext for DefaultContext {
 
         // delete everything after the last completion
         history.truncate(redrive_ptr);
+
+        // a compaction of the redriven completion no longer matches the history
+        let mut compacted = self.compacted.lock().await;
+        if compacted
+            .as_ref()
+            .is_some_and(|(end, _)| *end > history.len())
+        {
+            *compacted = None;
+        }
     }
 }
 
@@ -170,6 +274,7 @@ mod tests {
     use crate::{assistant, tool_output, user};
 
     use super::*;
+    use crate::compaction::SlidingWindow;
     use swiftide_core::chat_completion::{ChatMessage, ToolCall, ToolOutput};
 
     #[tokio::test]
@@ -332,6 +437,89 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_compacts_messages_over_token_limit() {
+        let mut context = DefaultContext::default();
+        context.with_compaction(SlidingWindow::new(20), 20);
+
+        context
+            .add_messages(vec![
+                ChatMessage::System("System".into()),
+                ChatMessage::User("a".repeat(100)),
+                ChatMessage::User("Hello".into()),
+            ])
+            .await;
+
+        let messages = context.next_completion().await.unwrap();
+        assert_eq!(
+            messages,
+            vec![
+                ChatMessage::System("System".into()),
+                ChatMessage::User("Hello".into())
+            ]
+        );
+
+        // Later completions continue from the compacted messages
+        context.add_message(ChatMessage::User("Bye".into())).await;
+
+        let messages = context.next_completion().await.unwrap();
+        assert_eq!(
+            messages,
+            vec![
+                ChatMessage::System("System".into()),
+                ChatMessage::User("Hello".into()),
+                ChatMessage::User("Bye".into())
+            ]
+        );
+
+        // The history is kept
+        assert_eq!(context.history().await.len(), 4);
+    }
+
+    /// Signals when it starts compacting and waits to be released
+    #[derive(Debug, Clone, Default)]
+    struct BlockingCompactor {
+        started: Arc<tokio::sync::Notify>,
+        release: Arc<tokio::sync::Notify>,
+    }
+
+    #[async_trait]
+    impl ContextCompactor for BlockingCompactor {
+        async fn compact(&self, messages: Vec<ChatMessage>) -> Result<Vec<ChatMessage>> {
+            self.started.notify_one();
+            self.release.notified().await;
+            Ok(messages.into_iter().take(1).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_is_not_locked_while_compacting() {
+        let compactor = BlockingCompactor::default();
+        let mut context = DefaultContext::default();
+        context.with_compaction(compactor.clone(), 0);
+
+        context
+            .add_messages(vec![user!("Hello"), user!("How are you?")])
+            .await;
+
+        let completion = tokio::spawn({
+            let context = context.clone();
+            async move { context.next_completion().await }
+        });
+        compactor.started.notified().await;
+
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            context.add_message(user!("Bye")),
+        )
+        .await
+        .expect("History is locked while compacting");
+        compactor.release.notify_one();
+
+        assert_eq!(completion.await.unwrap().unwrap(), vec![user!("Hello")]);
+        assert_eq!(context.history().await.len(), 3);
+    }
+
     #[tokio::test]
     async fn test_redrive() {
         let context = DefaultContext::default();
@@ -0,0 +1,488 @@
+//! Guardrails check the user input, tool arguments and answers of an agent
+//!
+//! A [`Guardrail`] pairs a [`Validator`] with the [`Target`]s it checks and the [`Action`] taken
+//! on a violation. Guardrails are added to the agent with
+//! [`AgentBuilder::guardrail`](crate::AgentBuilder::guardrail) and run in the order they are
+//! added.
+//!
+//! * [`Pattern`] flags text matching a regex, i.e. secrets or email addresses
+//! * [`Keywords`] flags text containing any of a list of words
+//! * [`Classifier`] asks a language model whether text violates a policy
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_agents::{Agent, guardrails::{Action, Guardrail, Keywords, Pattern, Target}};
+//! # fn main() -> anyhow::Result<()> {
+//! Agent::builder()
+//!     .guardrail(Guardrail::new(Keywords::new(&["password", "credit card"])))
+//!     .guardrail(
+//!         Guardrail::new(Pattern::new(r"sk-[a-zA-Z0-9]{20,}")?)
+//!             .with_targets(&[Target::ToolArguments, Target::Answer])
+//!             .with_action(Action::Redact),
+//!     );
+//! # Ok(())
+//! # }
+//! ```
+use std::{fmt::Debug, ops::Range, sync::Arc};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+use indoc::formatdoc;
+use regex::Regex;
+use swiftide_core::SimplePrompt;
+
+const REDACTED: &str = "[REDACTED]";
+const VIOLATION: &str = "VIOLATION:";
+
+/// The result of validating a text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    /// The text violates the validator, with the spans that are redacted by [`Action::Redact`]
+    ///
+    /// Without spans, the whole text is redacted.
+    Violation {
+        reason: String,
+        spans: Vec<Range<usize>>,
+    },
+}
+
+/// Validates text for a guardrail
+#[async_trait]
+pub trait Validator: Send + Sync + Debug + DynClone {
+    async fn validate(&self, text: &str) -> Result<Verdict>;
+}
+
+dyn_clone::clone_trait_object!(Validator);
+
+/// What a guardrail checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// The user message the agent is queried with
+    Input,
+    /// The arguments of tool calls, before the tool is invoked
+    ToolArguments,
+    /// The content of messages of the assistant
+    Answer,
+}
+
+/// What happens when a guardrail is violated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Action {
+    /// Stops the agent with an error
+    #[default]
+    Block,
+    /// Replaces the violating spans with `[REDACTED]` and continues
+    ///
+    /// Tool arguments in json are redacted per string value, so they stay valid json.
+    Redact,
+    /// Asks the llm to try again with the reason of the violation
+    ///
+    /// A rejected tool call gets a failed output instead of being invoked. A rejected answer is
+    /// sent back with a user message asking for a rewrite, without adding either to the history,
+    /// up to [`AgentBuilder::max_answer_rewrites`](crate::AgentBuilder::max_answer_rewrites)
+    /// times. User input cannot be rewritten and is blocked instead.
+    Rewrite,
+}
+
+/// A validator with the targets it checks and the action on a violation
+#[derive(Debug, Clone)]
+pub struct Guardrail {
+    validator: Box<dyn Validator>,
+    targets: Vec<Target>,
+    action: Action,
+}
+
+impl Guardrail {
+    /// Creates a guardrail that checks all targets and blocks on a violation
+    pub fn new(validator: impl Validator + 'static) -> Self {
+        Self {
+            validator: Box::new(validator),
+            targets: vec![Target::Input, Target::ToolArguments, Target::Answer],
+            action: Action::default(),
+        }
+    }
+
+    /// Only checks these targets
+    #[must_use]
+    pub fn with_targets(mut self, targets: &[Target]) -> Self {
+        self.targets = targets.to_vec();
+        self
+    }
+
+    #[must_use]
+    pub fn with_action(mut self, action: Action) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+/// The text after all guardrails passed, or the reason to ask the llm for a rewrite
+///
+/// It is up to the agent to handle a rewrite for the target, user input is blocked instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Checked {
+    Pass(String),
+    Rewrite(String),
+}
+
+/// Runs the guardrails for a target in order, redacting the text as it goes
+///
+/// # Errors
+///
+/// Errors if a blocking guardrail is violated or a validator fails
+pub(crate) async fn check(
+    guardrails: &[Guardrail],
+    target: Target,
+    text: String,
+) -> Result<Checked> {
+    let mut text = text;
+
+    for guardrail in guardrails
+        .iter()
+        .filter(|guardrail| guardrail.targets.contains(&target))
+    {
+        let Verdict::Violation { reason, spans } = guardrail.validator.validate(&text).await?
+        else {
+            continue;
+        };
+
+        tracing::warn!(?target, action = ?guardrail.action, %reason, "Guardrail violated");
+
+        match guardrail.action {
+            Action::Redact if target == Target::ToolArguments => {
+                text = redact_json(&*guardrail.validator, &text, spans).await?;
+            }
+            Action::Redact => text = redact(&text, spans),
+            Action::Rewrite => return Ok(Checked::Rewrite(reason)),
+            Action::Block => anyhow::bail!("Blocked by guardrail on {target:?}: {reason}"),
+        }
+    }
+
+    Ok(Checked::Pass(text))
+}
+
+fn redact(text: &str, mut spans: Vec<Range<usize>>) -> String {
+    if spans.is_empty() {
+        return REDACTED.to_string();
+    }
+
+    spans.sort_by_key(|span| span.start);
+
+    let mut redacted = String::with_capacity(text.len());
+    let mut position = 0;
+    for span in spans {
+        if span.end <= position {
+            continue;
+        }
+        if span.start > position {
+            redacted.push_str(&text[position..span.start]);
+        }
+        redacted.push_str(REDACTED);
+        position = span.end;
+    }
+    redacted.push_str(&text[position..]);
+
+    redacted
+}
+
+/// Validates and redacts each string value of json on its own, as redacting spans of the
+/// serialized json can break it. Text that is not json is redacted as is.
+async fn redact_json(
+    validator: &dyn Validator,
+    text: &str,
+    spans: Vec<Range<usize>>,
+) -> Result<String> {
+    let Ok(mut json) = serde_json::from_str::<serde_json::Value>(text) else {
+        return Ok(redact(text, spans));
+    };
+
+    let mut values = vec![];
+    string_values(&mut json, &mut values);
+
+    for value in values {
+        if let Verdict::Violation { spans, .. } = validator.validate(value).await? {
+            *value = redact(value, spans);
+        }
+    }
+
+    Ok(json.to_string())
+}
+
+fn string_values<'a>(json: &'a mut serde_json::Value, values: &mut Vec<&'a mut String>) {
+    match json {
+        serde_json::Value::String(value) => values.push(value),
+        serde_json::Value::Array(array) => {
+            for json in array {
+                string_values(json, values);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            for json in object.values_mut() {
+                string_values(json, values);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Flags text that matches a regex
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Creates the validator from a regex
+    ///
+    /// # Errors
+    ///
+    /// Errors if the regex is invalid
+    pub fn new(pattern: &str) -> Result<Self> {
+        Ok(Self {
+            regex: Regex::new(pattern).with_context(|| format!("Invalid pattern {pattern}"))?,
+        })
+    }
+}
+
+#[async_trait]
+impl Validator for Pattern {
+    async fn validate(&self, text: &str) -> Result<Verdict> {
+        let spans = self
+            .regex
+            .find_iter(text)
+            .map(|found| found.range())
+            .collect::<Vec<_>>();
+
+        if spans.is_empty() {
+            return Ok(Verdict::Pass);
+        }
+
+        Ok(Verdict::Violation {
+            reason: format!("matches pattern `{}`", self.regex.as_str()),
+            spans,
+        })
+    }
+}
+
+/// Flags text that contains any of the keywords, as whole words and ignoring case
+#[derive(Debug, Clone)]
+pub struct Keywords {
+    regex: Regex,
+}
+
+impl Keywords {
+    /// Creates the validator from a list of keywords
+    ///
+    /// # Panics
+    ///
+    /// Never panics in practice, the keywords are escaped and always form a valid regex
+    pub fn new(keywords: &[impl AsRef<str>]) -> Self {
+        let alternatives = keywords
+            .iter()
+            .map(|keyword| regex::escape(keyword.as_ref()))
+            .collect::<Vec<_>>()
+            .join("|");
+
+        Self {
+            regex: Regex::new(&format!(r"(?i)\b(?:{alternatives})\b"))
+                .expect("Escaped keywords are a valid regex"),
+        }
+    }
+}
+
+#[async_trait]
+impl Validator for Keywords {
+    async fn validate(&self, text: &str) -> Result<Verdict> {
+        // Without keywords, the regex matches empty strings
+        let found = self
+            .regex
+            .find_iter(text)
+            .filter(|found| !found.is_empty())
+            .collect::<Vec<_>>();
+
+        let Some(first) = found.first() else {
+            return Ok(Verdict::Pass);
+        };
+
+        Ok(Verdict::Violation {
+            reason: format!("contains `{}`", first.as_str()),
+            spans: found.iter().map(regex::Match::range).collect(),
+        })
+    }
+}
+
+/// Asks a language model whether text violates a policy
+///
+/// The whole text is redacted with [`Action::Redact`], as the model does not report spans.
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    llm: Arc<dyn SimplePrompt>,
+    policy: String,
+}
+
+impl Classifier {
+    /// Classifies with the language model against a policy, i.e. "No medical advice"
+    pub fn from_client(llm: impl SimplePrompt + 'static, policy: impl Into<String>) -> Self {
+        Self {
+            llm: Arc::new(llm),
+            policy: policy.into(),
+        }
+    }
+
+    fn prompt(&self, text: &str) -> String {
+        formatdoc! {"
+            Check whether the text below violates the policy.
+
+            If it does not, respond with `PASS`. If it does, respond with `VIOLATION: ` followed
+            by the reason in one sentence. Respond with nothing else.
+
+            ## Policy
+            {policy}
+
+            ## Text
+            {text}
+        ", policy = self.policy}
+    }
+}
+
+#[async_trait]
+impl Validator for Classifier {
+    async fn validate(&self, text: &str) -> Result<Verdict> {
+        let response = self.llm.prompt(self.prompt(text).into()).await?;
+        let response = response.trim();
+
+        if response.to_uppercase().starts_with("PASS") {
+            return Ok(Verdict::Pass);
+        }
+
+        let reason = response
+            .get(..VIOLATION.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(VIOLATION))
+            .map_or(response, |_| &response[VIOLATION.len()..])
+            .trim();
+
+        Ok(Verdict::Violation {
+            reason: reason.to_string(),
+            spans: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::MockSimplePrompt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_redacts_matching_spans() {
+        let guardrails = [
+            Guardrail::new(Pattern::new(r"sk-[a-z0-9]+").unwrap()).with_action(Action::Redact),
+            Guardrail::new(Keywords::new(&["Secret"])).with_action(Action::Redact),
+        ];
+
+        let checked = check(
+            &guardrails,
+            Target::Answer,
+            "My secret key is sk-abc123, or sk-def456".to_string(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            checked,
+            Checked::Pass("My [REDACTED] key is [REDACTED], or [REDACTED]".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redacts_tool_arguments_as_json() {
+        let guardrails = [Guardrail::new(Keywords::new(&["secret"])).with_action(Action::Redact)];
+
+        let checked = check(
+            &guardrails,
+            Target::ToolArguments,
+            r#"{"query": "\"secret\" key", "tags": ["secret", "public"]}"#.to_string(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            checked,
+            Checked::Pass(
+                r#"{"query":"\"[REDACTED]\" key","tags":["[REDACTED]","public"]}"#.to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blocks_and_rewrites_by_target() {
+        let guardrails = [Guardrail::new(Keywords::new(&["password"]))
+            .with_targets(&[Target::Input, Target::ToolArguments])
+            .with_action(Action::Rewrite)];
+
+        let checked = check(
+            &guardrails,
+            Target::ToolArguments,
+            r#"{"query": "admin password"}"#.to_string(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(checked, Checked::Rewrite("contains `password`".to_string()));
+
+        // The agent blocks input instead of rewriting it
+        let checked = check(&guardrails, Target::Input, "My Password".to_string())
+            .await
+            .unwrap();
+        assert_eq!(checked, Checked::Rewrite("contains `Password`".to_string()));
+
+        let blocking = [Guardrail::new(Keywords::new(&["password"]))];
+        let error = check(&blocking, Target::Input, "My Password".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Blocked by guardrail on Input: contains `Password`"
+        );
+
+        // Answers are not checked
+        let checked = check(&guardrails, Target::Answer, "password".to_string())
+            .await
+            .unwrap();
+        assert_eq!(checked, Checked::Pass("password".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_classifier() {
+        let mut llm = MockSimplePrompt::new();
+        llm.expect_prompt()
+            .times(1)
+            .returning(|_| Ok("Violation: Gives medical advice".to_string()));
+        let classifier = Classifier::from_client(llm, "No medical advice");
+
+        assert_eq!(
+            classifier.validate("Take an aspirin").await.unwrap(),
+            Verdict::Violation {
+                reason: "Gives medical advice".to_string(),
+                spans: vec![]
+            }
+        );
+        assert!(classifier
+            .prompt("Take an aspirin")
+            .contains("## Policy\nNo medical advice"));
+
+        let mut llm = MockSimplePrompt::new();
+        llm.expect_prompt()
+            .times(1)
+            .returning(|_| Ok("PASS".to_string()));
+        let classifier = Classifier::from_client(llm, "No medical advice");
+
+        assert_eq!(
+            classifier.validate("Drink water").await.unwrap(),
+            Verdict::Pass
+        );
+    }
+}
@@ -149,6 +149,17 @@ pub trait OnStartFn:
 
 dyn_clone::clone_trait_object!(OnStartFn);
 
+/// Hooks that are called when the agent stops, because it is done, stopped, cancelled or failed
+pub trait OnStopFn:
+    for<'a> Fn(&'a Agent) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>
+    + Send
+    + Sync
+    + DynClone
+{
+}
+
+dyn_clone::clone_trait_object!(OnStopFn);
+
 /// Wrapper around the different types of hooks
 #[derive(Clone, strum_macros::EnumDiscriminants, strum_macros::Display)]
 #[strum_discriminants(name(HookTypes), derive(strum_macros::Display))]
@@ -170,6 +181,8 @@ pub enum Hook {
     OnNewMessage(Box<dyn MessageHookFn>),
     /// Runs when the agent starts, either from pending or stopped
     OnStart(Box<dyn OnStartFn>),
+    /// Runs when the agent stops, because it is done, stopped, cancelled or failed
+    OnStop(Box<dyn OnStopFn>),
 }
 
 impl<F> BeforeAllFn for F where
@@ -248,6 +261,14 @@ impl<F> OnStartFn for F where
 {
 }
 
+impl<F> OnStopFn for F where
+    F: for<'a> Fn(&'a Agent) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>
+        + Send
+        + Sync
+        + DynClone
+{
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Agent;
@@ -257,6 +278,7 @@ mod tests {
         Agent::builder()
             .before_all(|_| Box::pin(async { Ok(()) }))
             .on_start(|_| Box::pin(async { Ok(()) }))
+            .on_stop(|_| Box::pin(async { Ok(()) }))
             .before_completion(|_, _| Box::pin(async { Ok(()) }))
             .before_tool(|_, _| Box::pin(async { Ok(()) }))
             .after_tool(|_, _, _| Box::pin(async { Ok(()) }))
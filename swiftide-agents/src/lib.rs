@@ -8,6 +8,12 @@
 //! * **Tool Execution**: A context takes a tool executor (local by default) to execute its tools on. This enables tools to be run i.e. in containers, remote, etc.
 //! * **System prompt defaults**: `SystemPrompt` provides a default, customizable prompt for the agent. If you want to provider your own prompt, the builder takes anything that converts into a `Prompt`, including strings.
 //! * **Open Telemetry**: Agents are fully instrumented with open telemetry.
+//! * **Audit log**: Every tool call can be recorded to a pluggable audit sink, see [`audit`].
+//! * **Context compaction**: Long conversations can be compacted to stay within the context window, see [`compaction`].
+//! * **Guardrails**: User input, tool arguments and answers can be checked with validators that block, redact or ask for a rewrite, see [`guardrails`].
+//! * **Trajectories**: Agent runs can be recorded and replayed without a language model for deterministic tests, see [`trajectory`].
+//! * **Sub-agents**: An agent can be used as a tool of another agent, see [`tools::agent_tool`].
+//! * **Knowledge base**: With the `rag` feature, agents can search an index with a query pipeline, see `tools::knowledge_base`.
 //!
 //! # Example
 //!
@@ -37,13 +43,17 @@
 //!
 //! Agents run in a loop as long as they have new messages to process.
 mod agent;
+pub mod audit;
+pub mod compaction;
 mod default_context;
+pub mod guardrails;
 pub mod hooks;
 mod state;
 pub mod system_prompt;
 pub mod tools;
+pub mod trajectory;
 
-pub use agent::Agent;
+pub use agent::{Agent, AgentBuilder};
 pub use default_context::DefaultContext;
 
 #[cfg(test)]
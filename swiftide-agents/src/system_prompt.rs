@@ -8,11 +8,62 @@
 //!
 //! For customization, either the builder can be used to profit from defaults, or an override can
 //! be provided on the agent level.
+//!
+//! Variables that are only known at run time, like the current date or memories retrieved for the
+//! user, can be added with [`SystemPromptBuilder::variable`]. They are resolved and the system
+//! prompt is rendered again at the start of every run of the agent.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_agents::{system_prompt::SystemPrompt, Agent};
+//! # use swiftide_core::AgentContext;
+//! let system_prompt = SystemPrompt::builder()
+//!     .role("You are a helpful assistant")
+//!     .template("{{ role }}\n\nThe workspace is at {{ workspace }}")
+//!     .variable("workspace", |agent: &Agent| {
+//!         Box::pin(async move {
+//!             let output = agent
+//!                 .context()
+//!                 .exec_cmd(&swiftide_core::Command::shell("pwd"))
+//!                 .await?;
+//!             Ok(serde_json::Value::from(output.to_string()))
+//!         })
+//!     })
+//!     .build()
+//!     .unwrap();
+//! ```
+use std::{future::Future, pin::Pin};
 
+use anyhow::Result;
 use derive_builder::Builder;
+use dyn_clone::DynClone;
 use swiftide_core::{prompt::Prompt, template::Template};
 
-#[derive(Clone, Debug, Builder)]
+use crate::Agent;
+
+/// Resolves the value of a system prompt variable when the agent starts a run
+pub trait SystemPromptVariableFn:
+    for<'a> Fn(&'a Agent) -> Pin<Box<dyn Future<Output = Result<serde_json::Value>> + Send + 'a>>
+    + Send
+    + Sync
+    + DynClone
+{
+}
+
+dyn_clone::clone_trait_object!(SystemPromptVariableFn);
+
+impl<F> SystemPromptVariableFn for F where
+    F: for<'a> Fn(
+            &'a Agent,
+        ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value>> + Send + 'a>>
+        + Send
+        + Sync
+        + DynClone
+{
+}
+
+#[derive(Clone, Builder)]
 #[builder(setter(into, strip_option))]
 pub struct SystemPrompt {
     /// The role the agent is expected to fulfil.
@@ -29,12 +80,74 @@ pub struct SystemPrompt {
     /// The template to use for the system prompt
     #[builder(default = default_prompt_template())]
     template: Template,
+
+    /// A prompt that is used as is, instead of the template with role, guidelines and
+    /// constraints
+    #[builder(default, setter(skip))]
+    prompt: Option<Prompt>,
+
+    /// Variables resolved at the start of every run
+    #[builder(default, setter(custom))]
+    variables: Vec<(String, Box<dyn SystemPromptVariableFn>)>,
+}
+
+impl std::fmt::Debug for SystemPrompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SystemPrompt")
+            .field("role", &self.role)
+            .field("guidelines", &self.guidelines)
+            .field("constraints", &self.constraints)
+            .field("template", &self.template)
+            .field("prompt", &self.prompt)
+            .field(
+                "variables",
+                &self
+                    .variables
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
 }
 
 impl SystemPrompt {
     pub fn builder() -> SystemPromptBuilder {
         SystemPromptBuilder::default()
     }
+
+    /// Returns true if the system prompt has variables that are resolved at run time
+    pub fn has_variables(&self) -> bool {
+        !self.variables.is_empty()
+    }
+
+    /// Resolves the variables and renders the system prompt
+    ///
+    /// # Errors
+    ///
+    /// Errors if a variable cannot be resolved or the template fails to render
+    pub async fn render(&self, agent: &Agent) -> Result<String> {
+        let mut prompt = self.to_prompt();
+
+        for (name, variable) in &self.variables {
+            let value = variable(agent).await?;
+            prompt = prompt.with_context_value(name, value);
+        }
+
+        prompt.render().await
+    }
+
+    fn to_prompt(&self) -> Prompt {
+        if let Some(prompt) = &self.prompt {
+            return prompt.clone();
+        }
+
+        self.template
+            .to_prompt()
+            .with_context_value("role", self.role.clone())
+            .with_context_value("guidelines", self.guidelines.clone())
+            .with_context_value("constraints", self.constraints.clone())
+    }
 }
 
 impl Default for SystemPrompt {
@@ -44,10 +157,33 @@ impl Default for SystemPrompt {
             guidelines: Vec::new(),
             constraints: Vec::new(),
             template: default_prompt_template(),
+            prompt: None,
+            variables: Vec::new(),
+        }
+    }
+}
+
+impl From<Prompt> for SystemPrompt {
+    fn from(prompt: Prompt) -> Self {
+        SystemPrompt {
+            prompt: Some(prompt),
+            ..Default::default()
         }
     }
 }
 
+impl From<&'static str> for SystemPrompt {
+    fn from(prompt: &'static str) -> Self {
+        Prompt::from(prompt).into()
+    }
+}
+
+impl From<String> for SystemPrompt {
+    fn from(prompt: String) -> Self {
+        Prompt::from(prompt).into()
+    }
+}
+
 impl SystemPromptBuilder {
     pub fn guidelines<T: IntoIterator<Item = S>, S: AsRef<str>>(
         &mut self,
@@ -76,25 +212,30 @@ impl SystemPromptBuilder {
     }
 }
 
+impl SystemPromptBuilder {
+    /// Adds a variable to the template that is resolved at the start of every run, e.g. the
+    /// current date or memories retrieved for the user
+    pub fn variable(
+        &mut self,
+        name: impl Into<String>,
+        variable: impl SystemPromptVariableFn + 'static,
+    ) -> &mut Self {
+        self.variables
+            .get_or_insert_with(Vec::new)
+            .push((name.into(), Box::new(variable)));
+        self
+    }
+}
+
 fn default_prompt_template() -> Template {
     include_str!("system_prompt_template.md").into()
 }
 
 #[allow(clippy::from_over_into)]
 impl Into<Prompt> for SystemPrompt {
+    /// Variables that are resolved at run time are not available in the prompt
     fn into(self) -> Prompt {
-        let SystemPrompt {
-            role,
-            guidelines,
-            constraints,
-            template,
-        } = self;
-
-        template
-            .to_prompt()
-            .with_context_value("role", role)
-            .with_context_value("guidelines", guidelines)
-            .with_context_value("constraints", constraints)
+        self.to_prompt()
     }
 }
 
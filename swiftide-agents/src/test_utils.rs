@@ -7,7 +7,7 @@ use swiftide_core::AgentContext;
 
 use crate::hooks::{
     AfterCompletionFn, AfterToolFn, BeforeAllFn, BeforeCompletionFn, BeforeToolFn, MessageHookFn,
-    OnStartFn,
+    OnStartFn, OnStopFn,
 };
 use crate::Agent;
 
@@ -229,6 +229,19 @@ impl MockHook {
             })
         }
     }
+
+    pub fn on_stop_fn(&self) -> impl OnStopFn {
+        let called = Arc::clone(&self.called);
+        move |_: &Agent| {
+            let called = Arc::clone(&called);
+            Box::pin(async move {
+                let mut called = called.lock().unwrap();
+                *called += 1;
+                Ok(())
+            })
+        }
+    }
+
     pub fn before_completion_fn(&self) -> impl BeforeCompletionFn {
         let called = Arc::clone(&self.called);
         move |_: &Agent, _| {
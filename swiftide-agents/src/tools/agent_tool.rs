@@ -0,0 +1,215 @@
+//! Use an agent as a tool of another agent
+//!
+//! A parent agent can delegate a task to a specialized sub-agent, with its own tools, system
+//! prompt and language model, in a single tool call. The final answer of the sub-agent is
+//! returned as the output of the tool call.
+//!
+//! # Example
+//!
+//! ```ignore
+//! # use swiftide_agents::{Agent, tools::agent_tool::AgentTool};
+//! # use swiftide_integrations as integrations;
+//! # fn main() -> anyhow::Result<()> {
+//! # let openai = integrations::openai::OpenAI::builder().build()?;
+//! let researcher = Agent::builder()
+//!     .llm(&openai)
+//!     .system_prompt("You research topics on the web and report your findings")
+//!     .build()?;
+//!
+//! let agent = Agent::builder()
+//!     .llm(&openai)
+//!     .tools([AgentTool::new(
+//!         researcher,
+//!         "research",
+//!         "Researches a topic on the web and reports the findings",
+//!     )])
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use swiftide_core::{
+    chat_completion::{errors::ToolError, ChatMessage, ParamSpec, Tool, ToolOutput, ToolSpec},
+    AgentContext,
+};
+use tokio::sync::Mutex;
+
+use crate::Agent;
+
+/// Runs a sub-agent with the task of the tool call, and returns its final answer
+///
+/// The final answer is the last message of the assistant with content. If the sub-agent stops
+/// without one, the tool call fails so the parent agent can handle it.
+///
+/// The sub-agent keeps its history between calls, so it can build on earlier tasks. Calls to the
+/// same sub-agent are run one at a time.
+#[derive(Clone)]
+pub struct AgentTool {
+    agent: Arc<Mutex<Agent>>,
+    name: &'static str,
+    description: &'static str,
+}
+
+#[derive(Deserialize)]
+struct AgentToolArgs {
+    task: String,
+}
+
+impl AgentTool {
+    /// Creates a tool with a name and a description of what the sub-agent does
+    pub fn new(agent: Agent, name: &'static str, description: &'static str) -> Self {
+        Self {
+            agent: Arc::new(Mutex::new(agent)),
+            name,
+            description,
+        }
+    }
+}
+
+impl std::fmt::Debug for AgentTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentTool")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Tool for AgentTool {
+    #[tracing::instrument(skip_all, fields(tool_name = self.name))]
+    async fn invoke(
+        &self,
+        _agent_context: &dyn AgentContext,
+        raw_args: Option<&str>,
+    ) -> Result<ToolOutput, ToolError> {
+        let args = self
+            .tool_spec()
+            .prepare_args(raw_args)?
+            .ok_or_else(|| ToolError::MissingArguments(self.name().to_string()))?;
+        let args: AgentToolArgs = serde_json::from_str(&args)?;
+
+        let mut agent = self.agent.lock().await;
+        let seen = agent.history().await.len();
+
+        agent.query(args.task).await?;
+
+        let answer = agent
+            .history()
+            .await
+            .into_iter()
+            .skip(seen)
+            .rev()
+            .find_map(|message| match message {
+                ChatMessage::Assistant(Some(content), _) if !content.trim().is_empty() => {
+                    Some(content)
+                }
+                _ => None,
+            });
+
+        match answer {
+            Some(answer) => Ok(ToolOutput::Text(answer)),
+            None => Ok(ToolOutput::Fail(format!(
+                "{} stopped without an answer",
+                self.name
+            ))),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn tool_spec(&self) -> ToolSpec {
+        ToolSpec::builder()
+            .name(self.name)
+            .description(self.description)
+            .parameters(vec![ParamSpec::builder()
+                .name("task")
+                .description("The task to complete, with all the context that is needed")
+                .build()
+                .expect("infallible")])
+            .build()
+            .expect("infallible")
+    }
+}
+
+impl From<AgentTool> for Box<dyn Tool> {
+    fn from(val: AgentTool) -> Self {
+        Box::new(val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use swiftide_core::{
+        chat_completion::{ChatCompletionRequest, ChatCompletionResponse, ToolCall},
+        test_utils::MockChatCompletion,
+    };
+
+    use super::*;
+    use crate::{chat_request, chat_response, user, DefaultContext};
+
+    #[test_log::test(tokio::test)]
+    async fn test_returns_final_answer_of_sub_agent() {
+        let mock_llm = MockChatCompletion::new();
+        mock_llm.expect_complete(
+            chat_request! { user!("Find the capital of France"); tools = [] },
+            Ok(chat_response! { "The capital of France is Paris"; tool_calls = ["stop"] }),
+        );
+
+        let agent = Agent::builder()
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .build()
+            .unwrap();
+        let tool = AgentTool::new(agent, "research", "Researches a topic");
+
+        let output = tool
+            .invoke(
+                &DefaultContext::default(),
+                Some(r#"{"task": "Find the capital of France"}"#),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output,
+            ToolOutput::Text("The capital of France is Paris".to_string())
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_fails_without_answer() {
+        let mock_llm = MockChatCompletion::new();
+        mock_llm.expect_complete(
+            chat_request! { user!("Find the capital of France"); tools = [] },
+            Ok(chat_response! { ""; tool_calls = ["stop"] }),
+        );
+
+        let agent = Agent::builder()
+            .llm(&mock_llm)
+            .no_system_prompt()
+            .build()
+            .unwrap();
+        let tool = AgentTool::new(agent, "research", "Researches a topic");
+
+        let output = tool
+            .invoke(
+                &DefaultContext::default(),
+                Some(r#"{"task": "Find the capital of France"}"#),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output,
+            ToolOutput::Fail("research stopped without an answer".to_string())
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! A tool that lets agents run code snippets in a sandboxed container
+//!
+//! Snippets run with `docker` (or a compatible runtime like `podman`) in a throwaway container
+//! without network access by default, with a read-only filesystem and limited memory, cpu and
+//! processes. The code is passed on stdin, the merged stdout and stderr is returned to the agent.
+//!
+//! The output is streamed while the snippet runs, i.e. to show progress to a user, see
+//! [`CodeInterpreter::subscribe`].
+//!
+//! Requires the runtime and the images to be available on the host.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_agents::{Agent, tools::code_interpreter::{CodeInterpreter, Language}};
+//! # fn main() -> anyhow::Result<()> {
+//! let interpreter = CodeInterpreter::builder()
+//!     .languages(vec![Language::Python])
+//!     .timeout(std::time::Duration::from_secs(10))
+//!     .build()?;
+//!
+//! let agent = Agent::builder().tools([interpreter]);
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    process::Stdio,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use derive_builder::Builder;
+use serde::Deserialize;
+use swiftide_core::{
+    chat_completion::{errors::ToolError, ParamConstraint, ParamSpec, Tool, ToolOutput, ToolSpec},
+    util::safe_truncate_utf8,
+    AgentContext,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt as _, AsyncWriteExt as _},
+    sync::broadcast,
+};
+
+static CONTAINER_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// The number of output chunks buffered per subscriber
+const OUTPUT_CAPACITY: usize = 1024;
+
+const DESCRIPTION: &str =
+    "Runs a code snippet in a sandbox without network access and returns its output. Print the \
+     results you need, state is not kept between runs.";
+const DESCRIPTION_WITH_NETWORK: &str =
+    "Runs a code snippet in a sandbox with network access and returns its output. Print the \
+     results you need, state is not kept between runs.";
+
+/// Languages the interpreter can run
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Deserialize, strum_macros::Display, strum_macros::EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Language {
+    Python,
+    Rust,
+}
+
+impl Language {
+    /// Shell script run in the container, with the code on stdin
+    fn script(self) -> &'static str {
+        match self {
+            Language::Python => "python3 -",
+            Language::Rust => {
+                "cat > /tmp/main.rs && rustc --edition 2021 -o /tmp/main /tmp/main.rs && /tmp/main"
+            }
+        }
+    }
+}
+
+/// Which output of a snippet a chunk is from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Output of a running snippet, as it is written
+///
+/// Chunks are split as they are read, a character can be split over two chunks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputChunk {
+    /// The name of the container running the snippet, to tell concurrent runs apart
+    pub container: String,
+    pub stream: OutputStream,
+    pub text: String,
+}
+
+/// Runs python or rust snippets in a sandboxed container
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into), build_fn(error = "anyhow::Error"))]
+pub struct CodeInterpreter {
+    /// The container runtime, i.e. `docker` or `podman`
+    #[builder(default = "\"docker\".to_string()")]
+    runtime: String,
+
+    /// The languages the agent can use
+    #[builder(default = "vec![Language::Python, Language::Rust]")]
+    languages: Vec<Language>,
+
+    #[builder(default = "\"python:3.12-slim\".to_string()")]
+    python_image: String,
+
+    #[builder(default = "\"rust:1-slim\".to_string()")]
+    rust_image: String,
+
+    /// Kills the container if the snippet runs longer, including compilation
+    #[builder(default = "Duration::from_mins(1)")]
+    timeout: Duration,
+
+    /// Memory limit of the container, in the format of the runtime
+    #[builder(default = "\"512m\".to_string()")]
+    memory: String,
+
+    /// Number of cpus available to the container
+    #[builder(default = "\"1\".to_string()")]
+    cpus: String,
+
+    /// Allows the snippet to access the network
+    #[builder(default)]
+    network: bool,
+
+    /// Output is truncated to this many characters
+    #[builder(default = "10_000")]
+    max_output_length: usize,
+
+    #[builder(setter(skip), default = "broadcast::channel(OUTPUT_CAPACITY).0")]
+    output: broadcast::Sender<OutputChunk>,
+}
+
+#[derive(Deserialize)]
+struct RunCodeArgs {
+    language: Language,
+    code: String,
+}
+
+impl CodeInterpreter {
+    pub fn builder() -> CodeInterpreterBuilder {
+        CodeInterpreterBuilder::default()
+    }
+
+    /// Streams the output of snippets while they run
+    ///
+    /// Every subscriber receives the output written after it subscribed. A slow subscriber skips
+    /// the oldest chunks and receives a `RecvError::Lagged`. The agent still gets the whole
+    /// output when the snippet finishes.
+    pub fn subscribe(&self) -> broadcast::Receiver<OutputChunk> {
+        self.output.subscribe()
+    }
+
+    /// Sends chunks of a stream of a container to the subscribers
+    fn stream_output(&self, container: &str, stream: OutputStream) -> impl FnMut(&[u8]) + '_ {
+        let container = container.to_string();
+
+        move |chunk| {
+            if self.output.receiver_count() == 0 {
+                return;
+            }
+
+            // Only fails without subscribers
+            let _ = self.output.send(OutputChunk {
+                container: container.clone(),
+                stream,
+                text: String::from_utf8_lossy(chunk).into_owned(),
+            });
+        }
+    }
+
+    fn image(&self, language: Language) -> &str {
+        match language {
+            Language::Python => &self.python_image,
+            Language::Rust => &self.rust_image,
+        }
+    }
+
+    /// Arguments for the runtime to run a snippet in a sandboxed container
+    fn container_args(&self, name: &str, language: Language) -> Vec<String> {
+        let mut args = vec![
+            "run".to_string(),
+            "--rm".to_string(),
+            "--interactive".to_string(),
+            format!("--name={name}"),
+            format!("--memory={}", self.memory),
+            format!("--cpus={}", self.cpus),
+            "--pids-limit=128".to_string(),
+            "--read-only".to_string(),
+            "--tmpfs=/tmp:rw,exec,size=256m".to_string(),
+            "--security-opt=no-new-privileges".to_string(),
+            "--cap-drop=ALL".to_string(),
+            "--workdir=/tmp".to_string(),
+            "--env=HOME=/tmp".to_string(),
+        ];
+
+        if !self.network {
+            args.push("--network=none".to_string());
+        }
+
+        args.extend([
+            self.image(language).to_string(),
+            "sh".to_string(),
+            "-c".to_string(),
+            language.script().to_string(),
+        ]);
+
+        args
+    }
+
+    async fn run(&self, language: Language, code: &str) -> Result<ToolOutput> {
+        let name = format!(
+            "swiftide-code-{}-{}",
+            std::process::id(),
+            CONTAINER_COUNT.fetch_add(1, Ordering::Relaxed)
+        );
+
+        tracing::debug!(%language, container = %name, "Running code in sandbox");
+
+        let mut child = tokio::process::Command::new(&self.runtime)
+            .args(self.container_args(&name, language))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Could not start container with {}", self.runtime))?;
+
+        let mut stdin = child.stdin.take().context("Missing stdin")?;
+        let stdout = child.stdout.take().context("Missing stdout")?;
+        let stderr = child.stderr.take().context("Missing stderr")?;
+
+        let execution = async {
+            // Written while reading, so a snippet blocking on stdin or output still times out
+            let write = async move {
+                stdin.write_all(code.as_bytes()).await?;
+                // Closes stdin, so the snippet starts
+                drop(stdin);
+                anyhow::Ok(())
+            };
+
+            let (written, stdout, stderr, status) = tokio::join!(
+                write,
+                read_capped(
+                    stdout,
+                    self.max_output_length,
+                    self.stream_output(&name, OutputStream::Stdout)
+                ),
+                read_capped(
+                    stderr,
+                    self.max_output_length,
+                    self.stream_output(&name, OutputStream::Stderr)
+                ),
+                child.wait()
+            );
+            written?;
+            anyhow::Ok((format!("{}{}", stdout?, stderr?), status?))
+        };
+
+        let Ok(result) = tokio::time::timeout(self.timeout, Box::pin(execution)).await else {
+            // Killing the runtime cli does not always stop the container
+            let _ = tokio::process::Command::new(&self.runtime)
+                .args(["kill", &name])
+                .output()
+                .await;
+
+            return Ok(ToolOutput::Fail(format!(
+                "Execution timed out after {:?}",
+                self.timeout
+            )));
+        };
+
+        let (output, status) = result?;
+        let output = safe_truncate_utf8(output, self.max_output_length);
+
+        if status.success() {
+            Ok(ToolOutput::Text(output))
+        } else {
+            Ok(ToolOutput::Fail(format!(
+                "Exited with {}\n{output}",
+                status
+                    .code()
+                    .map_or_else(|| "a signal".to_string(), |code| format!("code {code}"))
+            )))
+        }
+    }
+}
+
+/// Reads the output as it is written, keeping at most `max` bytes so a chatty snippet does not
+/// block on a full pipe or exhaust memory. Every chunk read is passed to `on_chunk`.
+async fn read_capped(
+    mut reader: impl AsyncRead + Unpin,
+    max: usize,
+    mut on_chunk: impl FnMut(&[u8]),
+) -> Result<String> {
+    let mut output = Vec::new();
+    let mut buffer = [0; 8192];
+
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        on_chunk(&buffer[..read]);
+
+        let remaining = max.saturating_sub(output.len());
+        output.extend_from_slice(&buffer[..read.min(remaining)]);
+    }
+
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+#[async_trait]
+impl Tool for CodeInterpreter {
+    async fn invoke(
+        &self,
+        _agent_context: &dyn AgentContext,
+        raw_args: Option<&str>,
+    ) -> Result<ToolOutput, ToolError> {
+        let args = self
+            .tool_spec()
+            .prepare_args(raw_args)?
+            .ok_or_else(|| ToolError::MissingArguments(self.name().to_string()))?;
+        let args: RunCodeArgs = serde_json::from_str(&args)?;
+
+        Ok(Box::pin(self.run(args.language, &args.code)).await?)
+    }
+
+    fn name(&self) -> &'static str {
+        "run_code"
+    }
+
+    fn tool_spec(&self) -> ToolSpec {
+        ToolSpec::builder()
+            .name("run_code")
+            .description(if self.network {
+                DESCRIPTION_WITH_NETWORK
+            } else {
+                DESCRIPTION
+            })
+            .parameters(vec![
+                ParamSpec::builder()
+                    .name("language")
+                    .description("The language of the snippet")
+                    .constraint(ParamConstraint::OneOf(
+                        self.languages.iter().map(ToString::to_string).collect(),
+                    ))
+                    .build()
+                    .expect("infallible"),
+                ParamSpec::builder()
+                    .name("code")
+                    .description("The code to run, rust snippets need a main function")
+                    .build()
+                    .expect("infallible"),
+            ])
+            .build()
+            .expect("infallible")
+    }
+}
+
+impl From<CodeInterpreter> for Box<dyn Tool> {
+    fn from(val: CodeInterpreter) -> Self {
+        Box::new(val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_is_sandboxed() {
+        let interpreter = CodeInterpreter::builder().build().unwrap();
+        let args = interpreter.container_args("test", Language::Python);
+
+        assert!(args.contains(&"--network=none".to_string()));
+        assert!(args.contains(&"--read-only".to_string()));
+        assert!(args.contains(&"--memory=512m".to_string()));
+        assert_eq!(
+            args[args.len() - 4..],
+            ["python:3.12-slim", "sh", "-c", "python3 -"]
+        );
+
+        let interpreter = CodeInterpreter::builder().network(true).build().unwrap();
+        let args = interpreter.container_args("test", Language::Rust);
+        assert!(!args.contains(&"--network=none".to_string()));
+    }
+
+    #[test]
+    fn test_only_configured_languages_are_allowed() {
+        let interpreter = CodeInterpreter::builder()
+            .languages(vec![Language::Python])
+            .build()
+            .unwrap();
+
+        let spec = interpreter.tool_spec();
+        assert!(spec
+            .prepare_args(Some(r#"{"language": "rust", "code": "fn main() {}"}"#))
+            .is_err());
+        assert!(spec
+            .prepare_args(Some(r#"{"language": "python", "code": "print(1)"}"#))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_description_mentions_network_access() {
+        let interpreter = CodeInterpreter::builder().build().unwrap();
+        assert!(interpreter
+            .tool_spec()
+            .description
+            .contains("without network access"));
+
+        let interpreter = CodeInterpreter::builder().network(true).build().unwrap();
+        assert!(interpreter
+            .tool_spec()
+            .description
+            .contains("with network access"));
+    }
+
+    #[tokio::test]
+    async fn test_read_capped_streams_output() {
+        let interpreter = CodeInterpreter::builder().build().unwrap();
+        let mut output = interpreter.subscribe();
+
+        let capped = read_capped(
+            &b"hello world"[..],
+            5,
+            interpreter.stream_output("test", OutputStream::Stdout),
+        )
+        .await
+        .unwrap();
+        assert_eq!(capped, "hello");
+
+        // The whole output is streamed
+        assert_eq!(
+            output.recv().await.unwrap(),
+            OutputChunk {
+                container: "test".to_string(),
+                stream: OutputStream::Stdout,
+                text: "hello world".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! A tool that lets agents search an index with a query pipeline
+//!
+//! The agent calls `search_knowledge_base` with a query, and gets the retrieved documents back,
+//! numbered and with their citations, so that it can refer to its sources.
+//!
+//! The knowledge base is a [`RetrievalTool`] that answers with the retrieved documents. Use the
+//! retrieval tool directly to answer with a language model or to let the agent filter on metadata.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_agents::tools::knowledge_base::KnowledgeBase;
+//! # use swiftide_core::{
+//! #     querying::search_strategies::SimilaritySingleEmbedding, EmbeddingModel, Retrieve,
+//! # };
+//! # use swiftide_query::{query_transformers, Pipeline};
+//! # fn knowledge_base(
+//! #     embed: impl EmbeddingModel + Clone + 'static,
+//! #     qdrant: impl Retrieve<SimilaritySingleEmbedding> + Clone + 'static,
+//! # ) -> KnowledgeBase {
+//! KnowledgeBase::from_pipeline(move |strategy| {
+//!     Pipeline::from_search_strategy(strategy)
+//!         .then_transform_query(query_transformers::Embed::from_client(embed.clone()))
+//!         .then_retrieve(qdrant.clone())
+//! })
+//! .with_top_k(5)
+//! .with_citations(&["path", "url"])
+//! # }
+//! ```
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use swiftide_core::{
+    chat_completion::{errors::ToolError, Tool, ToolOutput, ToolSpec},
+    querying::{search_strategies::SimilaritySingleEmbedding, states, Document, Query},
+    AgentContext,
+};
+use swiftide_query::{Pipeline, RetrievalTool};
+
+const DEFAULT_NAME: &str = "search_knowledge_base";
+const DEFAULT_DESCRIPTION: &str =
+    "Searches the knowledge base and returns the most relevant documents with their sources";
+const DEFAULT_TOP_K: u64 = 5;
+const DEFAULT_MAX_TOP_K: u64 = 20;
+
+/// Formats the content of a retrieved document for the agent
+pub type DocumentFormatter = Arc<dyn Fn(&Document) -> String + Send + Sync>;
+
+/// Builds the pipeline that retrieves the documents of a search, with the strategy of the call
+pub type RetrievePipelineFn = Arc<
+    dyn Fn(
+            SimilaritySingleEmbedding,
+        ) -> Pipeline<'static, SimilaritySingleEmbedding, states::Retrieved>
+        + Send
+        + Sync,
+>;
+
+/// Searches an index with a query pipeline that retrieves documents
+///
+/// A pipeline is built for every search, so searches run concurrently. The documents of the last
+/// step are returned to the agent instead of an answer. Response transformers, like a reranker,
+/// are applied as usual.
+///
+/// Every document is numbered and preceded by its citations, the metadata values under the
+/// citation keys, `path` by default.
+#[derive(Clone)]
+pub struct KnowledgeBase {
+    pipeline: RetrievePipelineFn,
+    name: &'static str,
+    description: &'static str,
+    top_k: u64,
+    max_top_k: u64,
+    citations: Arc<Vec<String>>,
+    formatter: DocumentFormatter,
+}
+
+impl KnowledgeBase {
+    /// Creates the tool from a function that builds a query pipeline that retrieves documents
+    pub fn from_pipeline(
+        pipeline: impl Fn(
+                SimilaritySingleEmbedding,
+            ) -> Pipeline<'static, SimilaritySingleEmbedding, states::Retrieved>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            pipeline: Arc::new(pipeline),
+            name: DEFAULT_NAME,
+            description: DEFAULT_DESCRIPTION,
+            top_k: DEFAULT_TOP_K,
+            max_top_k: DEFAULT_MAX_TOP_K,
+            citations: Arc::new(vec!["path".to_string()]),
+            formatter: Arc::new(|document| document.content().to_string()),
+        }
+    }
+
+    /// Sets the name of the tool, i.e. to give an agent several knowledge bases
+    #[must_use]
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Describes the knowledge base to the agent, so it knows when to search it
+    #[must_use]
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// The number of documents retrieved if the agent does not ask for a number, defaults to 5
+    #[must_use]
+    pub fn with_top_k(mut self, top_k: u64) -> Self {
+        self.top_k = top_k;
+        self.max_top_k = self.max_top_k.max(top_k);
+        self
+    }
+
+    /// The maximum number of documents the agent can ask for, defaults to 20
+    #[must_use]
+    pub fn with_max_top_k(mut self, max_top_k: u64) -> Self {
+        self.max_top_k = max_top_k;
+        self.top_k = self.top_k.min(max_top_k);
+        self
+    }
+
+    /// The metadata keys cited for every document, defaults to `path`
+    ///
+    /// Keys missing from a document are left out.
+    #[must_use]
+    pub fn with_citations(mut self, keys: &[impl AsRef<str>]) -> Self {
+        self.citations = Arc::new(keys.iter().map(|key| key.as_ref().to_string()).collect());
+        self
+    }
+
+    /// Formats the content of every document, defaults to the content as is
+    #[must_use]
+    pub fn with_formatter(
+        mut self,
+        formatter: impl Fn(&Document) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.formatter = Arc::new(formatter);
+        self
+    }
+
+    /// The retrieval tool that answers with the formatted documents
+    fn retrieval_tool(&self) -> RetrievalTool {
+        let pipeline = Arc::clone(&self.pipeline);
+        let citations = Arc::clone(&self.citations);
+        let formatter = Arc::clone(&self.formatter);
+
+        RetrievalTool::builder()
+            .name(self.name)
+            .description(self.description)
+            .default_top_k(self.top_k)
+            .max_top_k(self.max_top_k)
+            .pipeline(move |strategy| {
+                let citations = Arc::clone(&citations);
+                let formatter = Arc::clone(&formatter);
+
+                pipeline(strategy).then_answer(move |query: Query<states::Retrieved>| {
+                    let answer = format_documents(query.documents(), &citations, &formatter);
+                    Ok(query.answered(answer))
+                })
+            })
+            .build()
+            .expect("The top k is at most the maximum")
+    }
+}
+
+fn format_documents(
+    documents: &[Document],
+    citations: &[String],
+    formatter: &DocumentFormatter,
+) -> String {
+    if documents.is_empty() {
+        return "No documents found".to_string();
+    }
+
+    documents
+        .iter()
+        .enumerate()
+        .map(|(i, document)| {
+            let citations = citations
+                .iter()
+                .filter_map(|key| {
+                    let value = document.metadata().get(key)?;
+                    let value = value
+                        .as_str()
+                        .map_or_else(|| value.to_string(), str::to_string);
+
+                    Some(format!("{key}: {value}"))
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let content = formatter(document);
+            if citations.is_empty() {
+                format!("[{}]\n{content}", i + 1)
+            } else {
+                format!("[{}] {citations}\n{content}", i + 1)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+impl std::fmt::Debug for KnowledgeBase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KnowledgeBase")
+            .field("name", &self.name)
+            .field("top_k", &self.top_k)
+            .field("max_top_k", &self.max_top_k)
+            .field("citations", &self.citations)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Tool for KnowledgeBase {
+    async fn invoke(
+        &self,
+        agent_context: &dyn AgentContext,
+        raw_args: Option<&str>,
+    ) -> Result<ToolOutput, ToolError> {
+        self.retrieval_tool().invoke(agent_context, raw_args).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn tool_spec(&self) -> ToolSpec {
+        self.retrieval_tool().tool_spec()
+    }
+}
+
+impl From<KnowledgeBase> for Box<dyn Tool> {
+    fn from(val: KnowledgeBase) -> Self {
+        Box::new(val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::indexing::Metadata;
+
+    use super::*;
+    use crate::DefaultContext;
+
+    fn knowledge_base() -> KnowledgeBase {
+        KnowledgeBase::from_pipeline(|strategy| {
+            Pipeline::from_search_strategy(strategy).then_retrieve(
+                |strategy: &SimilaritySingleEmbedding, query: Query<states::Pending>| {
+                    let documents = vec![
+                        Document::new(
+                            format!("About {}", query.original()),
+                            Some(Metadata::from([("path", "docs/a.md"), ("title", "A")])),
+                        ),
+                        Document::new("Unrelated", None),
+                    ];
+                    let top_k = usize::try_from(strategy.top_k()).unwrap();
+
+                    Ok(query.retrieved_documents(documents.into_iter().take(top_k).collect()))
+                },
+            )
+        })
+    }
+
+    #[tokio::test]
+    async fn test_search_returns_documents_with_citations() {
+        let context = DefaultContext::default();
+        let tool = knowledge_base().with_citations(&["title", "path"]);
+
+        let output = tool
+            .invoke(&context, Some(r#"{"query": "agents"}"#))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output,
+            ToolOutput::Text(
+                "[1] title: A, path: docs/a.md\nAbout agents\n\n[2]\nUnrelated".to_string()
+            )
+        );
+
+        // The agent can ask for fewer documents
+        let output = tool
+            .invoke(&context, Some(r#"{"query": "tools", "top_k": 1}"#))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output,
+            ToolOutput::Text("[1] title: A, path: docs/a.md\nAbout tools".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_requires_a_query() {
+        let context = DefaultContext::default();
+
+        assert!(knowledge_base().invoke(&context, None).await.is_err());
+    }
+
+    #[test]
+    fn test_top_k_is_within_the_maximum() {
+        let spec = knowledge_base().with_top_k(50).tool_spec();
+        assert!(spec
+            .prepare_args(Some(r#"{"query": "agents", "top_k": 50}"#))
+            .is_ok());
+
+        let spec = knowledge_base().with_max_top_k(2).tool_spec();
+        assert!(spec
+            .prepare_args(Some(r#"{"query": "agents", "top_k": 3}"#))
+            .is_err());
+    }
+}
@@ -1,4 +1,12 @@
 //! Default tools and executor for agents
+pub mod agent_tool;
 pub mod arg_preprocessor;
+pub mod code_interpreter;
 pub mod control;
+#[cfg(feature = "rag")]
+pub mod knowledge_base;
 pub mod local_executor;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+pub mod output;
+pub mod policy;
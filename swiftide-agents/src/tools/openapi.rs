@@ -0,0 +1,656 @@
+//! Generates tools from an `OpenAPI` 3 specification, so agents can call existing REST apis
+//!
+//! Every selected operation becomes a tool. Path, query and header parameters, and a json request
+//! body as `body`, are mapped to the parameters of the tool with their schema. Local references to
+//! `#/components` are inlined, as not every provider supports references.
+//!
+//! Responses are returned to the agent as text, truncated to `max_response_length` characters.
+//! Responses with an error status are returned as a failed tool call, so the agent can recover.
+//!
+//! Requires the `openapi` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_agents::{tools::openapi::OpenApiTools, Agent};
+//! # fn main() -> anyhow::Result<()> {
+//! let tools = OpenApiTools::builder()
+//!     .spec_yaml(&std::fs::read_to_string("petstore.yaml")?)?
+//!     .bearer_auth("my-token")
+//!     .operations(["listPets", "showPetById"])
+//!     .build()?
+//!     .tools()?;
+//!
+//! let agent = Agent::builder().tools(tools);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Names and descriptions of tools are static, every distinct string of the specification is
+//! leaked once, so generating the tools again does not use more memory.
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use derive_builder::Builder;
+use reqwest::Method;
+use serde_json::{Map, Value};
+use swiftide_core::{
+    chat_completion::{errors::ToolError, ParamSpec, Tool, ToolOutput, ToolSpec},
+    util::safe_truncate_utf8,
+    AgentContext,
+};
+
+/// Responses are truncated to this many characters by default
+const DEFAULT_MAX_RESPONSE_LENGTH: usize = 10_000;
+
+/// References are inlined up to this depth, deeper (often recursive) schemas become objects
+const MAX_REF_DEPTH: usize = 8;
+
+const METHODS: [&str; 7] = ["get", "put", "post", "delete", "patch", "head", "options"];
+
+/// Generates a tool for the operations of an `OpenAPI` specification
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct OpenApiTools {
+    /// The parsed specification
+    #[builder(setter(custom))]
+    spec: Value,
+
+    /// Url the paths are relative to, defaults to the first server in the specification
+    #[builder(default)]
+    base_url: Option<String>,
+
+    /// Headers added to every request, e.g. for authentication
+    #[builder(default, setter(custom))]
+    headers: Vec<(String, String)>,
+
+    /// Only generate tools for these operation ids, defaults to all operations
+    #[builder(default, setter(custom))]
+    operations: Option<HashSet<String>>,
+
+    /// Responses are truncated to this many characters
+    #[builder(default = "DEFAULT_MAX_RESPONSE_LENGTH")]
+    max_response_length: usize,
+
+    #[builder(default)]
+    http_client: reqwest::Client,
+}
+
+impl OpenApiToolsBuilder {
+    /// Uses a specification in json
+    ///
+    /// # Errors
+    ///
+    /// Errors if the specification is not valid json
+    pub fn spec_json(&mut self, spec: &str) -> Result<&mut Self> {
+        self.spec = Some(serde_json::from_str(spec).context("Invalid OpenAPI specification")?);
+        Ok(self)
+    }
+
+    /// Uses a specification in yaml
+    ///
+    /// # Errors
+    ///
+    /// Errors if the specification is not valid yaml
+    pub fn spec_yaml(&mut self, spec: &str) -> Result<&mut Self> {
+        self.spec = Some(serde_yaml::from_str(spec).context("Invalid OpenAPI specification")?);
+        Ok(self)
+    }
+
+    /// Adds a header to every request
+    pub fn header(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.headers
+            .get_or_insert_with(Vec::new)
+            .push((name.into(), value.into()));
+        self
+    }
+
+    /// Authenticates every request with a bearer token
+    pub fn bearer_auth(&mut self, token: impl AsRef<str>) -> &mut Self {
+        self.header("Authorization", format!("Bearer {}", token.as_ref()))
+    }
+
+    /// Only generates tools for these operation ids
+    pub fn operations<S: Into<String>>(
+        &mut self,
+        operations: impl IntoIterator<Item = S>,
+    ) -> &mut Self {
+        self.operations = Some(Some(operations.into_iter().map(Into::into).collect()));
+        self
+    }
+}
+
+impl OpenApiTools {
+    pub fn builder() -> OpenApiToolsBuilder {
+        OpenApiToolsBuilder::default()
+    }
+
+    /// Generates a tool for every selected operation
+    ///
+    /// # Errors
+    ///
+    /// Errors if there is no base url, or a selected operation does not exist
+    pub fn tools(&self) -> Result<Vec<OpenApiTool>> {
+        let base_url = self
+            .base_url
+            .clone()
+            .or_else(|| {
+                self.spec["servers"][0]["url"]
+                    .as_str()
+                    .map(ToString::to_string)
+            })
+            .context("No base url set and no servers in the specification")?;
+        let base_url = base_url.trim_end_matches('/').to_string();
+        let headers = Arc::new(self.headers.clone());
+
+        let mut tools = Vec::new();
+        let paths = self.spec["paths"].as_object().cloned().unwrap_or_default();
+        for (path, item) in &paths {
+            let item = self.resolve(item, 0);
+            for method in METHODS {
+                let Some(operation) = item.get(method) else {
+                    continue;
+                };
+                let operation = self.resolve(operation, 0);
+
+                let name = operation["operationId"]
+                    .as_str()
+                    .map_or_else(|| operation_name(method, path), tool_name);
+                if self
+                    .operations
+                    .as_ref()
+                    .is_some_and(|operations| !operations.contains(&name))
+                {
+                    continue;
+                }
+
+                // Parameters of the path item apply to all its operations, unless the operation
+                // overrides them with the same name and location
+                let mut parameters: Vec<Value> = Vec::new();
+                for parameter in item["parameters"]
+                    .as_array()
+                    .into_iter()
+                    .chain(operation["parameters"].as_array())
+                    .flatten()
+                {
+                    let parameter = self.resolve(parameter, 0);
+                    match parameters.iter_mut().find(|existing| {
+                        existing["name"] == parameter["name"] && existing["in"] == parameter["in"]
+                    }) {
+                        Some(existing) => *existing = parameter,
+                        None => parameters.push(parameter),
+                    }
+                }
+
+                tools.push(OpenApiTool {
+                    spec: self.tool_spec(&name, method, path, &operation, &parameters),
+                    method: method.to_uppercase().parse()?,
+                    url: format!("{base_url}{path}"),
+                    parameters: parameters
+                        .iter()
+                        .filter_map(|parameter| {
+                            Some((
+                                parameter["name"].as_str()?.to_string(),
+                                parameter["in"].as_str()?.parse().ok()?,
+                            ))
+                        })
+                        .collect(),
+                    headers: Arc::clone(&headers),
+                    max_response_length: self.max_response_length,
+                    http_client: self.http_client.clone(),
+                });
+            }
+        }
+
+        if let Some(operations) = &self.operations {
+            for operation in operations {
+                if !tools
+                    .iter()
+                    .any(|tool| tool.spec.name == operation.as_str())
+                {
+                    anyhow::bail!("Operation {operation} not found in the specification");
+                }
+            }
+        }
+
+        Ok(tools)
+    }
+
+    fn tool_spec(
+        &self,
+        name: &str,
+        method: &str,
+        path: &str,
+        operation: &Value,
+        parameters: &[Value],
+    ) -> ToolSpec {
+        let description = [&operation["summary"], &operation["description"]]
+            .iter()
+            .filter_map(|value| value.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let description = if description.is_empty() {
+            format!("{} {path}", method.to_uppercase())
+        } else {
+            description
+        };
+
+        let mut params = parameters
+            .iter()
+            .filter_map(|parameter| {
+                let mut param = ParamSpec::builder();
+                param
+                    .name(leak(parameter["name"].as_str()?))
+                    .description(leak(parameter["description"].as_str().unwrap_or("")))
+                    .required(parameter["required"].as_bool().unwrap_or(false));
+                if let Some(schema) = parameter.get("schema") {
+                    param.schema(self.resolve(schema, 0));
+                }
+                param.build().ok()
+            })
+            .collect::<Vec<_>>();
+
+        let body = &operation["requestBody"];
+        let body = self.resolve(body, 0);
+        if let Some(schema) = body["content"]["application/json"].get("schema") {
+            params.extend(
+                ParamSpec::builder()
+                    .name("body")
+                    .description(leak(
+                        body["description"]
+                            .as_str()
+                            .unwrap_or("The json request body"),
+                    ))
+                    .required(body["required"].as_bool().unwrap_or(false))
+                    .schema(self.resolve(schema, 0))
+                    .build()
+                    .ok(),
+            );
+        }
+
+        ToolSpec::builder()
+            .name(leak(name))
+            .description(leak(&description))
+            .parameters(params)
+            .build()
+            .expect("infallible")
+    }
+
+    /// Inlines local references in a value
+    fn resolve(&self, value: &Value, depth: usize) -> Value {
+        match value {
+            Value::Object(map) => {
+                if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                    if depth >= MAX_REF_DEPTH {
+                        return serde_json::json!({ "type": "object" });
+                    }
+                    return match reference
+                        .strip_prefix('#')
+                        .and_then(|pointer| self.spec.pointer(pointer))
+                    {
+                        Some(resolved) => self.resolve(resolved, depth + 1),
+                        None => serde_json::json!({ "type": "object" }),
+                    };
+                }
+
+                Value::Object(
+                    map.iter()
+                        .map(|(key, value)| (key.clone(), self.resolve(value, depth)))
+                        .collect(),
+                )
+            }
+            Value::Array(values) => Value::Array(
+                values
+                    .iter()
+                    .map(|value| self.resolve(value, depth))
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+}
+
+/// Where the argument of a parameter goes in the request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::EnumString)]
+#[strum(serialize_all = "lowercase")]
+enum ParameterLocation {
+    Path,
+    Query,
+    Header,
+}
+
+/// Calls a single operation of an `OpenAPI` specification
+#[derive(Debug, Clone)]
+pub struct OpenApiTool {
+    spec: ToolSpec,
+    method: Method,
+    /// The url with the path parameters as `{name}`
+    url: String,
+    parameters: Vec<(String, ParameterLocation)>,
+    headers: Arc<Vec<(String, String)>>,
+    max_response_length: usize,
+    http_client: reqwest::Client,
+}
+
+#[async_trait]
+impl Tool for OpenApiTool {
+    async fn invoke(
+        &self,
+        _agent_context: &dyn AgentContext,
+        raw_args: Option<&str>,
+    ) -> Result<ToolOutput, ToolError> {
+        let mut args = match self.spec.prepare_args(raw_args)? {
+            Some(args) => serde_json::from_str::<Map<String, Value>>(&args)?,
+            None => Map::new(),
+        };
+
+        let mut url = self.url.clone();
+        let mut query = Vec::new();
+        let mut request_headers = Vec::new();
+        for (name, location) in &self.parameters {
+            let Some(value) = args.remove(name).filter(|value| !value.is_null()) else {
+                continue;
+            };
+            let value = match value {
+                Value::String(value) => value,
+                value => value.to_string(),
+            };
+
+            match location {
+                ParameterLocation::Path => {
+                    url = url.replace(&format!("{{{name}}}"), &encode_path_segment(&value));
+                }
+                ParameterLocation::Query => query.push((name.clone(), value)),
+                ParameterLocation::Header => request_headers.push((name.clone(), value)),
+            }
+        }
+
+        let mut request = self
+            .http_client
+            .request(self.method.clone(), &url)
+            .query(&query);
+        for (name, value) in self.headers.iter().chain(&request_headers) {
+            request = request.header(name, value);
+        }
+        if let Some(body) = args.remove("body") {
+            request = request.json(&body);
+        }
+
+        tracing::debug!(method = %self.method, url = %url, "[OpenApiTool] Request");
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Request to {url} failed"))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .context("Failed to read response body")?;
+        let body = safe_truncate_utf8(&body, self.max_response_length);
+
+        if status.is_success() {
+            Ok(ToolOutput::Text(body))
+        } else {
+            Ok(ToolOutput::Fail(format!("{status}: {body}")))
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.spec.name
+    }
+
+    fn tool_spec(&self) -> ToolSpec {
+        self.spec.clone()
+    }
+}
+
+impl From<OpenApiTool> for Box<dyn Tool> {
+    fn from(val: OpenApiTool) -> Self {
+        Box::new(val)
+    }
+}
+
+/// Tool names may only contain alphanumerics, underscores and dashes, up to 64 characters
+fn tool_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    safe_truncate_utf8(name.trim_matches('_'), 64)
+}
+
+/// The name of an operation without an `operationId`, i.e. `post_pets` for `post /pets`
+fn operation_name(method: &str, path: &str) -> String {
+    let name = tool_name(&format!("{method}_{}", path.trim_start_matches('/')));
+
+    let mut collapsed = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c != '_' || !collapsed.ends_with('_') {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+/// Percent encodes everything but unreserved characters
+fn encode_path_segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Leaks every distinct string once, and returns the leaked string after that
+fn leak(value: &str) -> &'static str {
+    static LEAKED: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+    let mut leaked = LEAKED
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(value) = leaked.get(value) {
+        return value;
+    }
+
+    let value: &'static str = Box::leak(value.to_string().into_boxed_str());
+    leaked.insert(value);
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::default_context::DefaultContext;
+
+    fn spec(base_url: &str) -> String {
+        json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Pets", "version": "1.0" },
+            "servers": [{ "url": base_url }],
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [
+                        { "$ref": "#/components/parameters/PetId" },
+                        { "name": "fields", "in": "query", "description": "Overridden" }
+                    ],
+                    "get": {
+                        "operationId": "showPetById",
+                        "summary": "Info for a specific pet",
+                        "parameters": [
+                            { "name": "fields", "in": "query", "schema": { "type": "string" } }
+                        ]
+                    },
+                    "delete": {}
+                },
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "required": true,
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/Pet" }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "parameters": {
+                    "PetId": {
+                        "name": "petId",
+                        "in": "path",
+                        "required": true,
+                        "description": "The id of the pet",
+                        "schema": { "type": "string" }
+                    }
+                },
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": { "name": { "type": "string" } }
+                    }
+                }
+            }
+        })
+        .to_string()
+    }
+
+    fn tools(base_url: &str) -> Vec<OpenApiTool> {
+        OpenApiTools::builder()
+            .spec_json(&spec(base_url))
+            .unwrap()
+            .bearer_auth("token")
+            .build()
+            .unwrap()
+            .tools()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_generates_tool_specs() {
+        let tools = tools("http://localhost");
+        let show_pet = tools.iter().find(|tool| tool.name() == "showPetById");
+        let show_pet = show_pet.unwrap().tool_spec();
+
+        assert_eq!(show_pet.description, "Info for a specific pet");
+        assert_eq!(show_pet.parameters[0].name, "petId");
+        assert!(show_pet.parameters[0].required);
+        assert_eq!(show_pet.parameters[1].name, "fields");
+        // The parameter of the operation overrides the one of the path item
+        assert_eq!(show_pet.parameters.len(), 2);
+        assert_eq!(show_pet.parameters[1].description, "");
+
+        let create_pet = tools.iter().find(|tool| tool.name() == "post_pets");
+        let create_pet = create_pet.unwrap().tool_spec();
+
+        assert_eq!(create_pet.description, "POST /pets");
+        assert_eq!(create_pet.parameters[0].name, "body");
+        assert_eq!(
+            create_pet.parameters[0].schema,
+            Some(json!({ "type": "object", "properties": { "name": { "type": "string" } } }))
+        );
+    }
+
+    #[test]
+    fn test_names_operations_without_id() {
+        assert_eq!(operation_name("post", "/pets"), "post_pets");
+        assert_eq!(
+            operation_name("delete", "/pets/{petId}"),
+            "delete_pets_petId"
+        );
+
+        assert!(tools("http://localhost")
+            .iter()
+            .any(|tool| tool.name() == "delete_pets_petId"));
+    }
+
+    #[test]
+    fn test_leaks_strings_once() {
+        let first = tools("http://localhost");
+        let second = tools("http://localhost");
+
+        assert!(std::ptr::eq(first[0].spec.name, second[0].spec.name));
+        assert!(std::ptr::eq(
+            first[0].spec.description,
+            second[0].spec.description
+        ));
+    }
+
+    #[test]
+    fn test_unknown_operation_errors() {
+        let result = OpenApiTools::builder()
+            .spec_json(&spec("http://localhost"))
+            .unwrap()
+            .operations(["deletePet"])
+            .build()
+            .unwrap()
+            .tools();
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invokes_operation() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/pets/a%20b"))
+            .and(query_param("fields", "name"))
+            .and(header("Authorization", "Bearer token"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("Rex"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/pets"))
+            .and(body_json(json!({ "name": "Rex" })))
+            .respond_with(ResponseTemplate::new(422).set_body_string("Name taken"))
+            .mount(&mock_server)
+            .await;
+
+        let tools = tools(&mock_server.uri());
+        let context = DefaultContext::default();
+
+        let show_pet = tools.iter().find(|tool| tool.name() == "showPetById");
+        let output = show_pet
+            .unwrap()
+            .invoke(&context, Some(r#"{"petId": "a b", "fields": "name"}"#))
+            .await
+            .unwrap();
+        assert_eq!(output, ToolOutput::Text("Rex".to_string()));
+
+        let create_pet = tools.iter().find(|tool| tool.name() == "post_pets");
+        let output = create_pet
+            .unwrap()
+            .invoke(&context, Some(r#"{"body": {"name": "Rex"}}"#))
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            ToolOutput::Fail("422 Unprocessable Entity: Name taken".to_string())
+        );
+    }
+}
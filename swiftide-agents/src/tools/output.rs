@@ -0,0 +1,273 @@
+//! Keeps large tool outputs from blowing up the context
+//!
+//! An output processor on the agent runs on the text output of every tool call, before it is
+//! added to the history, see
+//! [`AgentBuilder::tool_output_processor`](crate::AgentBuilder::tool_output_processor).
+//!
+//! * [`Truncate`] cuts outputs off at a number of tokens
+//! * [`SummarizeOutput`] summarizes outputs over a number of tokens with a language model
+//!
+//! Tokens are estimated with [`ApproximateTokens`] by default. For precise counts with `OpenAI`
+//! models, use the `TikToken` estimator from the tiktoken integration.
+//!
+//! # Example
+//!
+//! ```
+//! # use swiftide_agents::{Agent, tools::output::Truncate};
+//! # use swiftide_core::tokenizer::ApproximateTokens;
+//! Agent::builder()
+//!     .tool_output_processor(Truncate::new(4_000).with_estimator(ApproximateTokens::default()));
+//! ```
+use std::{fmt::Debug, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+use indoc::formatdoc;
+use swiftide_core::{
+    chat_completion::ToolCall,
+    tokenizer::{ApproximateTokens, EstimateTokens},
+    SimplePrompt,
+};
+
+/// Processes the text output of a tool call before it is added to the history
+#[async_trait]
+pub trait ProcessToolOutput: Send + Sync + Debug + DynClone {
+    async fn process(&self, tool_call: &ToolCall, output: String) -> Result<String>;
+}
+
+dyn_clone::clone_trait_object!(ProcessToolOutput);
+
+/// Truncates outputs to a maximum number of tokens, including a notice that it was truncated
+#[derive(Debug, Clone)]
+pub struct Truncate {
+    max_tokens: usize,
+    estimator: Arc<dyn EstimateTokens>,
+}
+
+impl Truncate {
+    /// Keeps at most `max_tokens`, estimated with [`ApproximateTokens`]
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            estimator: Arc::new(ApproximateTokens::default()),
+        }
+    }
+
+    /// Counts the tokens of the kept output with a tokenizer, i.e. tiktoken for `OpenAI` models
+    #[must_use]
+    pub fn with_estimator(mut self, estimator: impl EstimateTokens + 'static) -> Self {
+        self.estimator = Arc::new(estimator);
+        self
+    }
+}
+
+/// The largest char boundary at or before `index`, like the unstable `str::floor_char_boundary`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    (0..=index.min(text.len()))
+        .rev()
+        .find(|&i| text.is_char_boundary(i))
+        .unwrap_or(0)
+}
+
+/// The smallest char boundary at or after `index`, like the unstable `str::ceil_char_boundary`
+fn ceil_char_boundary(text: &str, index: usize) -> usize {
+    (index..=text.len())
+        .find(|&i| text.is_char_boundary(i))
+        .unwrap_or(text.len())
+}
+
+fn truncation_notice(kept: usize, total: usize) -> String {
+    format!("\n\n[Output truncated, showing {kept} of {total} characters]")
+}
+
+#[async_trait]
+impl ProcessToolOutput for Truncate {
+    async fn process(&self, tool_call: &ToolCall, output: String) -> Result<String> {
+        if self.estimator.estimate(&output).await? <= self.max_tokens {
+            return Ok(output);
+        }
+
+        let total = output.chars().count();
+        // The notice with the total in place of the kept characters is at least as long
+        let budget = self.max_tokens.saturating_sub(
+            self.estimator
+                .estimate(&truncation_notice(total, total))
+                .await?,
+        );
+
+        // Finds the longest prefix within the budget, the tokenizer does not truncate itself.
+        // The prefix at `low` fits and the one at `high` does not, the whole output is too long.
+        let (mut low, mut high) = (0, output.len());
+        loop {
+            let mut mid = floor_char_boundary(&output, low + (high - low) / 2);
+            if mid <= low {
+                mid = ceil_char_boundary(&output, low + 1);
+            }
+            if mid <= low || mid >= high {
+                break;
+            }
+
+            if self.estimator.estimate(&&output[..mid]).await? <= budget {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        let kept = output[..low].chars().count();
+        tracing::debug!(
+            tool_name = tool_call.name(),
+            kept,
+            total,
+            "Truncated tool output"
+        );
+
+        Ok(format!(
+            "{}{}",
+            &output[..low],
+            truncation_notice(kept, total)
+        ))
+    }
+}
+
+/// Summarizes outputs over a number of tokens with a language model
+///
+/// The summary focuses on what is relevant to the tool call. Outputs within the limit are kept
+/// as they are.
+#[derive(Debug, Clone)]
+pub struct SummarizeOutput {
+    llm: Arc<dyn SimplePrompt>,
+    max_tokens: usize,
+    estimator: Arc<dyn EstimateTokens>,
+}
+
+impl SummarizeOutput {
+    /// Summarizes outputs over 2000 tokens with the language model
+    pub fn from_client(llm: impl SimplePrompt + 'static) -> Self {
+        Self {
+            llm: Arc::new(llm),
+            max_tokens: 2000,
+            estimator: Arc::new(ApproximateTokens::default()),
+        }
+    }
+
+    /// Only summarizes outputs over `max_tokens`
+    #[must_use]
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Decides which outputs are over the limit with a tokenizer, defaults to
+    /// [`ApproximateTokens`]
+    #[must_use]
+    pub fn with_estimator(mut self, estimator: impl EstimateTokens + 'static) -> Self {
+        self.estimator = Arc::new(estimator);
+        self
+    }
+
+    fn prompt(tool_call: &ToolCall, output: &str) -> String {
+        formatdoc! {"
+            Summarize the output of the tool call below for an assistant that is working on a
+            task. Keep everything the assistant might need from it, like names, numbers, paths,
+            errors and code that is referred to. Leave out anything else.
+
+            Respond with the summary only.
+
+            ## Tool call
+            {name} {args}
+
+            ## Output
+            {output}
+        ", name = tool_call.name(), args = tool_call.args().unwrap_or_default()}
+    }
+}
+
+#[async_trait]
+impl ProcessToolOutput for SummarizeOutput {
+    async fn process(&self, tool_call: &ToolCall, output: String) -> Result<String> {
+        if self.estimator.estimate(&output).await? <= self.max_tokens {
+            return Ok(output);
+        }
+
+        let summary = self
+            .llm
+            .prompt(Self::prompt(tool_call, &output).into())
+            .await?;
+        tracing::debug!(tool_name = tool_call.name(), "Summarized tool output");
+
+        Ok(format!("[Output summarized]\n{summary}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::MockSimplePrompt;
+
+    use super::*;
+
+    fn tool_call() -> ToolCall {
+        ToolCall::builder()
+            .id("1")
+            .name("read_file")
+            .args(r#"{"path": "README.md"}"#)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_truncate() {
+        let truncate = Truncate::new(25);
+
+        let output = truncate
+            .process(&tool_call(), "short".to_string())
+            .await
+            .unwrap();
+        assert_eq!(output, "short");
+
+        let output = truncate
+            .process(&tool_call(), "é".repeat(400))
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            format!(
+                "{}\n\n[Output truncated, showing 48 of 400 characters]",
+                "é".repeat(48)
+            )
+        );
+        assert!(
+            ApproximateTokens::default()
+                .estimate(&output)
+                .await
+                .unwrap()
+                <= 25
+        );
+    }
+
+    #[tokio::test]
+    async fn test_summarize_large_outputs() {
+        let mut llm = MockSimplePrompt::new();
+        llm.expect_prompt()
+            .times(1)
+            .returning(|_| Ok("A readme about swiftide".to_string()));
+
+        let summarize = SummarizeOutput::from_client(llm).with_max_tokens(10);
+
+        let output = summarize
+            .process(&tool_call(), "short".to_string())
+            .await
+            .unwrap();
+        assert_eq!(output, "short");
+
+        let output = summarize
+            .process(&tool_call(), "swiftide ".repeat(100))
+            .await
+            .unwrap();
+        assert_eq!(output, "[Output summarized]\nA readme about swiftide");
+
+        assert!(SummarizeOutput::prompt(&tool_call(), "output")
+            .contains("## Tool call\nread_file {\"path\": \"README.md\"}"));
+    }
+}
@@ -0,0 +1,259 @@
+//! Timeouts, retries and error feedback for tool calls
+//!
+//! Policies are configured per tool on the agent, see
+//! [`AgentBuilder::tool_policy`](crate::AgentBuilder::tool_policy).
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use swiftide_agents::{Agent, tools::policy::ToolPolicy};
+//! Agent::builder().tool_policy(
+//!     "search_code",
+//!     ToolPolicy::retries(3)
+//!         .with_timeout(Duration::from_secs(30))
+//!         .with_errors_as_feedback(),
+//! );
+//! ```
+use std::{sync::Arc, time::Duration};
+
+use swiftide_core::{
+    chat_completion::{errors::ToolError, Tool, ToolOutput},
+    AgentContext,
+};
+
+/// Formats a failed tool call for the llm
+pub type ErrorFormatter = Arc<dyn Fn(&ToolError) -> String + Send + Sync>;
+
+/// How a tool is invoked by the agent
+///
+/// By default tools are invoked once without a timeout, and errors stop the agent.
+///
+/// Retries wait with an exponential backoff, starting at one second. Only failed executions and
+/// timeouts are retried, invalid arguments would fail again.
+///
+/// With error feedback, the error of the last attempt is returned to the llm as a failed tool
+/// output, so that it can correct itself instead of stopping the agent.
+#[derive(Clone)]
+pub struct ToolPolicy {
+    timeout: Option<Duration>,
+    max_retries: u32,
+    backoff: Duration,
+    error_formatter: Option<ErrorFormatter>,
+}
+
+impl Default for ToolPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            max_retries: 0,
+            backoff: Duration::from_secs(1),
+            error_formatter: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for ToolPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolPolicy")
+            .field("timeout", &self.timeout)
+            .field("max_retries", &self.max_retries)
+            .field("backoff", &self.backoff)
+            .field("error_feedback", &self.error_formatter.is_some())
+            .finish()
+    }
+}
+
+impl ToolPolicy {
+    /// Retries failed calls up to `max_retries` times
+    pub fn retries(max_retries: u32) -> Self {
+        Self::default().with_retries(max_retries)
+    }
+
+    /// Fails calls that take longer than the timeout
+    pub fn timeout(timeout: Duration) -> Self {
+        Self::default().with_timeout(timeout)
+    }
+
+    #[must_use]
+    pub fn with_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Fails a call that takes longer than the timeout, every retry has its own timeout
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The delay before the first retry, doubled for every next retry
+    #[must_use]
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Returns errors to the llm as `Tool failed: <error>`
+    #[must_use]
+    pub fn with_errors_as_feedback(self) -> Self {
+        self.with_error_formatter(|error| format!("Tool failed: {error:#}"))
+    }
+
+    /// Returns errors to the llm, formatted with the closure
+    #[must_use]
+    pub fn with_error_formatter(
+        mut self,
+        formatter: impl Fn(&ToolError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.error_formatter = Some(Arc::new(formatter));
+        self
+    }
+
+    /// Invokes the tool with the timeout, retries and error feedback of the policy
+    pub(crate) async fn invoke(
+        &self,
+        tool: &dyn Tool,
+        context: &dyn AgentContext,
+        args: Option<&str>,
+    ) -> Result<ToolOutput, ToolError> {
+        let mut retries = 0;
+        let mut delay = self.backoff;
+
+        let result = loop {
+            let result = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, tool.invoke(context, args))
+                    .await
+                    .unwrap_or(Err(ToolError::Timeout(timeout))),
+                None => tool.invoke(context, args).await,
+            };
+
+            match result {
+                Err(error) if retries < self.max_retries && is_retryable(&error) => {
+                    retries += 1;
+                    tracing::warn!(%error, retries, tool = tool.name(), "Retrying tool call");
+
+                    tokio::time::sleep(delay).await;
+                    delay = delay.saturating_mul(2);
+                }
+                result => break result,
+            }
+        };
+
+        match (result, &self.error_formatter) {
+            (Err(error), Some(formatter)) => {
+                tracing::warn!(%error, tool = tool.name(), "Returning tool error to the llm");
+                Ok(ToolOutput::Fail(formatter(&error)))
+            }
+            (result, _) => result,
+        }
+    }
+}
+
+fn is_retryable(error: &ToolError) -> bool {
+    matches!(
+        error,
+        ToolError::ExecutionFailed(_) | ToolError::Timeout(_) | ToolError::Unknown(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use async_trait::async_trait;
+    use swiftide_core::chat_completion::ToolSpec;
+
+    use super::*;
+    use crate::DefaultContext;
+
+    /// Fails until it has been called `succeed_after` times, sleeping for `delay` on every call
+    #[derive(Clone, Debug)]
+    struct FlakyTool {
+        calls: Arc<AtomicU32>,
+        succeed_after: u32,
+        delay: Duration,
+    }
+
+    impl FlakyTool {
+        fn new(succeed_after: u32, delay: Duration) -> Self {
+            Self {
+                calls: Arc::new(AtomicU32::new(0)),
+                succeed_after,
+                delay,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Tool for FlakyTool {
+        async fn invoke(
+            &self,
+            _agent_context: &dyn AgentContext,
+            _raw_args: Option<&str>,
+        ) -> Result<ToolOutput, ToolError> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            tokio::time::sleep(self.delay).await;
+
+            if calls < self.succeed_after {
+                Err(anyhow::anyhow!("Connection refused").into())
+            } else {
+                Ok("Done".into())
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn tool_spec(&self) -> ToolSpec {
+            ToolSpec::builder()
+                .name("flaky")
+                .description("Fails a few times")
+                .build()
+                .unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_calls() {
+        let context = DefaultContext::default();
+        let tool = FlakyTool::new(3, Duration::ZERO);
+
+        let output = ToolPolicy::retries(2)
+            .with_backoff(Duration::from_millis(1))
+            .invoke(&tool, &context, None)
+            .await
+            .unwrap();
+
+        assert_eq!(output, ToolOutput::Text("Done".to_string()));
+        assert_eq!(tool.calls.load(Ordering::SeqCst), 3);
+
+        let tool = FlakyTool::new(3, Duration::ZERO);
+        let result = ToolPolicy::retries(1)
+            .with_backoff(Duration::from_millis(1))
+            .invoke(&tool, &context, None)
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(tool.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_with_error_feedback() {
+        let context = DefaultContext::default();
+        let tool = FlakyTool::new(0, Duration::from_secs(10));
+
+        let output = ToolPolicy::timeout(Duration::from_millis(10))
+            .with_errors_as_feedback()
+            .invoke(&tool, &context, None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output,
+            ToolOutput::Fail("Tool failed: tool timed out after 10ms".to_string())
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Records agent runs as trajectories and replays them for deterministic tests
+//!
+//! A [`TrajectoryRecorder`] on the agent records every completion, with the messages and names
+//! of the tools sent and the response received, and every tool call with its output. The
+//! [`Trajectory`] can be saved as json and checked in with the tests.
+//!
+//! [`Replay`] stubs the language model with the recorded responses, so that an agent can be run
+//! again without calling a model. By default the replay fails when the agent sends different
+//! messages or tools than recorded, which catches changes in prompts, tools and control flow.
+//! Tools are compared by name, changes to their descriptions or parameters are not caught.
+//!
+//! Tools are invoked as usual on a replay. Record the replay as well to compare the tool calls
+//! with the recording.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_agents::{Agent, trajectory::{Replay, Trajectory, TrajectoryRecorder}};
+//! # use swiftide_core::ChatCompletion;
+//! # async fn run(llm: &(impl ChatCompletion + Clone + 'static)) -> anyhow::Result<()> {
+//! // Record a run with a real model
+//! let recorder = TrajectoryRecorder::default();
+//! Agent::builder()
+//!     .llm(llm)
+//!     .trajectory_recorder(recorder.clone())
+//!     .build()?
+//!     .query("What is the weather in Amsterdam?")
+//!     .await?;
+//! recorder.trajectory().await.save("tests/fixtures/weather.json").await?;
+//!
+//! // Replay it in a test
+//! let recorded = Trajectory::load("tests/fixtures/weather.json").await?;
+//! let replayed = TrajectoryRecorder::default();
+//! Agent::builder()
+//!     .llm(&Replay::new(recorded.clone()))
+//!     .trajectory_recorder(replayed.clone())
+//!     .build()?
+//!     .query("What is the weather in Amsterdam?")
+//!     .await?;
+//!
+//! assert_eq!(replayed.trajectory().await, recorded);
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use swiftide_core::{
+    chat_completion::{
+        errors::{LanguageModelError, ToolError},
+        ChatCompletionRequest, ChatCompletionResponse, ChatMessage, ToolCall, ToolOutput,
+    },
+    ChatCompletion,
+};
+use tokio::sync::Mutex;
+
+/// The completions and tool calls of an agent run, in order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trajectory {
+    pub steps: Vec<Step>,
+}
+
+/// A single step of a trajectory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Step {
+    /// A completion, with the messages and sorted tool names sent and the response before any
+    /// hooks ran
+    Completion {
+        messages: Vec<ChatMessage>,
+        tools: Vec<String>,
+        response: ChatCompletionResponse,
+    },
+    /// A tool call, with its output or the error it failed with
+    ToolCall {
+        tool_call: ToolCall,
+        output: Option<ToolOutput>,
+        error: Option<String>,
+    },
+}
+
+impl Trajectory {
+    /// Loads a trajectory from a json file
+    ///
+    /// # Errors
+    ///
+    /// Errors if the file cannot be read or is not a trajectory
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read trajectory {}", path.display()))?;
+
+        serde_json::from_slice(&json)
+            .with_context(|| format!("Invalid trajectory {}", path.display()))
+    }
+
+    /// Saves the trajectory as pretty printed json
+    ///
+    /// # Errors
+    ///
+    /// Errors if the file cannot be written
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_vec_pretty(self)?;
+
+        tokio::fs::write(path, json)
+            .await
+            .with_context(|| format!("Failed to write trajectory {}", path.display()))
+    }
+
+    /// The recorded completions, in order
+    pub fn completions(&self) -> impl Iterator<Item = (&[ChatMessage], &ChatCompletionResponse)> {
+        self.steps.iter().filter_map(|step| match step {
+            Step::Completion {
+                messages, response, ..
+            } => Some((messages.as_slice(), response)),
+            Step::ToolCall { .. } => None,
+        })
+    }
+}
+
+/// Records the trajectory of an agent, see
+/// [`AgentBuilder::trajectory_recorder`](crate::AgentBuilder::trajectory_recorder)
+///
+/// Clones record to the same trajectory, keep a clone to read it after the run.
+#[derive(Debug, Clone, Default)]
+pub struct TrajectoryRecorder {
+    trajectory: Arc<Mutex<Trajectory>>,
+}
+
+impl TrajectoryRecorder {
+    /// The trajectory recorded so far
+    pub async fn trajectory(&self) -> Trajectory {
+        self.trajectory.lock().await.clone()
+    }
+
+    pub(crate) async fn record_completion(
+        &self,
+        request: &ChatCompletionRequest,
+        response: &ChatCompletionResponse,
+    ) {
+        self.trajectory.lock().await.steps.push(Step::Completion {
+            messages: request.messages().to_vec(),
+            tools: tool_names(request),
+            response: response.clone(),
+        });
+    }
+
+    pub(crate) async fn record_tool_call(
+        &self,
+        tool_call: &ToolCall,
+        output: &Result<ToolOutput, ToolError>,
+    ) {
+        let (output, error) = match output {
+            Ok(output) => (Some(output.clone()), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        self.trajectory.lock().await.steps.push(Step::ToolCall {
+            tool_call: tool_call.clone(),
+            output,
+            error,
+        });
+    }
+}
+
+/// The names of the tools of a request, sorted as the tools are unordered
+fn tool_names(request: &ChatCompletionRequest) -> Vec<String> {
+    let mut names = request
+        .tools_spec()
+        .iter()
+        .map(|spec| spec.name.to_string())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+}
+
+/// Stubs a language model with the responses of a recorded trajectory
+///
+/// Responses are returned in the order they were recorded. Clones share their position in the
+/// trajectory.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    trajectory: Arc<Trajectory>,
+    position: Arc<AtomicUsize>,
+    strict: bool,
+}
+
+impl Replay {
+    /// Replays the trajectory, failing when the agent sends different messages or tools than
+    /// recorded
+    pub fn new(trajectory: Trajectory) -> Self {
+        Self {
+            trajectory: Arc::new(trajectory),
+            position: Arc::default(),
+            strict: true,
+        }
+    }
+
+    /// Returns the recorded responses regardless of the messages and tools the agent sends
+    #[must_use]
+    pub fn lenient(mut self) -> Self {
+        self.strict = false;
+        self
+    }
+}
+
+#[async_trait]
+impl ChatCompletion for Replay {
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let position = self.position.fetch_add(1, Ordering::SeqCst);
+
+        let completion = self
+            .trajectory
+            .steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Completion {
+                    messages,
+                    tools,
+                    response,
+                } => Some((messages, tools, response)),
+                Step::ToolCall { .. } => None,
+            })
+            .nth(position);
+
+        let Some((messages, tools, response)) = completion else {
+            return Err(LanguageModelError::permanent(format!(
+                "Trajectory has no completion {}, the agent made more completions than recorded",
+                position + 1
+            )));
+        };
+
+        if !self.strict {
+            return Ok(response.clone());
+        }
+
+        if messages != request.messages() {
+            return Err(LanguageModelError::permanent(format!(
+                "Completion {} diverged from the trajectory\nrecorded: {messages:#?}\nreceived: {:#?}",
+                position + 1,
+                request.messages()
+            )));
+        }
+
+        let received = tool_names(request);
+        if *tools != received {
+            return Err(LanguageModelError::permanent(format!(
+                "Completion {} diverged from the trajectory in tools\nrecorded: {tools:?}\nreceived: \
+                 {received:?}",
+                position + 1
+            )));
+        }
+
+        Ok(response.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use swiftide_core::chat_completion::ToolSpec;
+    use temp_dir::TempDir;
+
+    use super::*;
+    use crate::{assistant, tool_output, user};
+
+    fn response(message: &str, tool_calls: &[&str]) -> ChatCompletionResponse {
+        let tool_calls = tool_calls
+            .iter()
+            .map(|name| ToolCall::builder().name(*name).id("1").build().unwrap())
+            .collect::<Vec<_>>();
+
+        ChatCompletionResponse::builder()
+            .message(message)
+            .tool_calls(tool_calls)
+            .build()
+            .unwrap()
+    }
+
+    fn trajectory() -> Trajectory {
+        Trajectory {
+            steps: vec![
+                Step::Completion {
+                    messages: vec![user!("What is the weather?")],
+                    tools: vec![],
+                    response: response("", &["weather"]),
+                },
+                Step::ToolCall {
+                    tool_call: ToolCall::builder().name("weather").id("1").build().unwrap(),
+                    output: Some(ToolOutput::Text("Sunny".to_string())),
+                    error: None,
+                },
+                Step::Completion {
+                    messages: vec![
+                        user!("What is the weather?"),
+                        assistant!("", ["weather"]),
+                        tool_output!("weather", "Sunny"),
+                    ],
+                    tools: vec![],
+                    response: response("It is sunny", &["stop"]),
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.child("trajectory.json");
+
+        trajectory().save(&path).await.unwrap();
+
+        assert_eq!(Trajectory::load(&path).await.unwrap(), trajectory());
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let replay = Replay::new(trajectory());
+
+        let response = replay
+            .complete(&vec![user!("What is the weather?")].into())
+            .await
+            .unwrap();
+        assert_eq!(response.tool_calls.unwrap()[0].name(), "weather");
+
+        // Diverged
+        let error = replay
+            .complete(&vec![user!("What is the time?")].into())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Completion 2 diverged"));
+
+        // Same messages, but a tool was added since the recording
+        let replay = Replay::new(trajectory());
+        let request = ChatCompletionRequest::builder()
+            .messages(vec![user!("What is the weather?")])
+            .tools_spec(HashSet::from([ToolSpec::builder()
+                .name("weather")
+                .description("Gets the weather")
+                .build()
+                .unwrap()]))
+            .build()
+            .unwrap();
+        let error = replay.complete(&request).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Completion 1 diverged from the trajectory in tools"));
+
+        // Out of completions, lenient replays do not compare the messages
+        let replay = Replay::new(trajectory()).lenient();
+        for _ in 0..2 {
+            replay
+                .complete(&vec![user!("What is the time?")].into())
+                .await
+                .unwrap();
+        }
+        assert!(replay
+            .complete(&vec![user!("What is the time?")].into())
+            .await
+            .is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
 use derive_builder::Builder;
+use schemars::{schema::RootSchema, JsonSchema};
 
 use super::{chat_message::ChatMessage, tools::ToolSpec};
 
@@ -12,6 +13,9 @@ pub struct ChatCompletionRequest {
     pub messages: Vec<ChatMessage>,
     #[builder(default)]
     pub tools_spec: HashSet<ToolSpec>,
+    /// Constrains the response to json matching the schema, if the provider supports it
+    #[builder(default)]
+    pub response_schema: Option<RootSchema>,
 }
 
 impl ChatCompletionRequest {
@@ -26,6 +30,10 @@ impl ChatCompletionRequest {
     pub fn tools_spec(&self) -> &HashSet<ToolSpec> {
         &self.tools_spec
     }
+
+    pub fn response_schema(&self) -> Option<&RootSchema> {
+        self.response_schema.as_ref()
+    }
 }
 
 impl From<Vec<ChatMessage>> for ChatCompletionRequest {
@@ -33,6 +41,15 @@ impl From<Vec<ChatMessage>> for ChatCompletionRequest {
         ChatCompletionRequest {
             messages,
             tools_spec: HashSet::new(),
+            response_schema: None,
         }
     }
 }
+
+impl ChatCompletionRequestBuilder {
+    /// Constrains the response to json matching the schema of `T`
+    pub fn response_schema_for<T: JsonSchema>(&mut self) -> &mut Self {
+        self.response_schema = Some(Some(schemars::schema_for!(T)));
+        self
+    }
+}
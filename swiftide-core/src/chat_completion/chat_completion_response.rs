@@ -1,14 +1,58 @@
 use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
 
 use super::tools::ToolCall;
 
-#[derive(Clone, Builder, Debug)]
+#[derive(Clone, Builder, Debug, PartialEq, Serialize, Deserialize)]
 #[builder(setter(strip_option, into), build_fn(error = anyhow::Error))]
 pub struct ChatCompletionResponse {
     pub message: Option<String>,
 
     #[builder(default)]
     pub tool_calls: Option<Vec<ToolCall>>,
+
+    /// Reasoning the model did before answering, if the provider returns it separately
+    #[builder(default)]
+    pub reasoning_content: Option<String>,
+
+    /// Token usage as reported by the provider
+    #[builder(default)]
+    pub usage: Option<Usage>,
+
+    /// When streaming, the message content received since the previous response in the stream
+    #[builder(default)]
+    pub delta: Option<String>,
+
+    /// Log probabilities of the tokens in the message, if requested and supported by the provider
+    #[builder(default)]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+}
+
+/// Log probability of a generated token
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f32,
+    /// The most likely tokens at this position, if requested
+    #[serde(default)]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// Log probability of one of the most likely tokens at a position
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f32,
+}
+
+/// Token usage of a single completion
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+    /// Prompt tokens that were served from the provider's context cache, if reported
+    pub cached_prompt_tokens: Option<u32>,
 }
 
 impl ChatCompletionResponse {
@@ -23,6 +67,22 @@ impl ChatCompletionResponse {
     pub fn tool_calls(&self) -> Option<&[ToolCall]> {
         self.tool_calls.as_deref()
     }
+
+    pub fn reasoning_content(&self) -> Option<&str> {
+        self.reasoning_content.as_deref()
+    }
+
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+
+    pub fn delta(&self) -> Option<&str> {
+        self.delta.as_deref()
+    }
+
+    pub fn logprobs(&self) -> Option<&[TokenLogprob]> {
+        self.logprobs.as_deref()
+    }
 }
 
 impl ChatCompletionResponseBuilder {
@@ -35,4 +95,27 @@ impl ChatCompletionResponseBuilder {
         self.tool_calls = Some(tool_calls.into());
         self
     }
+
+    pub fn maybe_reasoning_content<T: Into<Option<String>>>(
+        &mut self,
+        reasoning_content: T,
+    ) -> &mut Self {
+        self.reasoning_content = Some(reasoning_content.into());
+        self
+    }
+
+    pub fn maybe_usage<T: Into<Option<Usage>>>(&mut self, usage: T) -> &mut Self {
+        self.usage = Some(usage.into());
+        self
+    }
+
+    pub fn maybe_delta<T: Into<Option<String>>>(&mut self, delta: T) -> &mut Self {
+        self.delta = Some(delta.into());
+        self
+    }
+
+    pub fn maybe_logprobs<T: Into<Option<Vec<TokenLogprob>>>>(&mut self, logprobs: T) -> &mut Self {
+        self.logprobs = Some(logprobs.into());
+        self
+    }
 }
@@ -1,9 +1,14 @@
+use serde::{Deserialize, Serialize};
+
 use super::tools::{ToolCall, ToolOutput};
 
-#[derive(Clone, strum_macros::EnumIs, PartialEq, Debug)]
+#[derive(Clone, strum_macros::EnumIs, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ChatMessage {
     System(String),
     User(String),
+    /// A user message with multiple parts, i.e. text and images
+    UserWithParts(Vec<ContentPart>),
     Assistant(Option<String>, Option<Vec<ToolCall>>),
     ToolOutput(ToolCall, ToolOutput),
 
@@ -17,6 +22,15 @@ impl std::fmt::Display for ChatMessage {
         match self {
             ChatMessage::System(s) => write!(f, "System: \"{s}\""),
             ChatMessage::User(s) => write!(f, "User: \"{s}\""),
+            ChatMessage::UserWithParts(parts) => write!(
+                f,
+                "User: {}",
+                parts
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
             ChatMessage::Assistant(message, tool_calls) => write!(
                 f,
                 "Assistant: \"{}\", tools: {}",
@@ -43,6 +57,10 @@ impl ChatMessage {
         ChatMessage::User(message.into())
     }
 
+    pub fn new_user_with_parts(parts: impl IntoIterator<Item = impl Into<ContentPart>>) -> Self {
+        ChatMessage::UserWithParts(parts.into_iter().map(Into::into).collect())
+    }
+
     pub fn new_assistant(
         message: Option<impl Into<String>>,
         tool_calls: Option<Vec<ToolCall>>,
@@ -58,3 +76,78 @@ impl ChatMessage {
         ChatMessage::Summary(message.into())
     }
 }
+
+/// A part of a multimodal message
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentPart {
+    Text(String),
+    Image(ImageContent),
+}
+
+/// An image in a message, either by url or inline
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageContent {
+    /// An image hosted at an url
+    Url(String),
+    /// A base64 encoded image with its media type, i.e. `image/png`
+    Base64 { media_type: String, data: String },
+}
+
+impl ImageContent {
+    pub fn from_url(url: impl Into<String>) -> Self {
+        ImageContent::Url(url.into())
+    }
+
+    pub fn from_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        ImageContent::Base64 {
+            media_type: media_type.into(),
+            data: data.into(),
+        }
+    }
+
+    /// Returns the image as an url, inline images are returned as a data url
+    pub fn to_url(&self) -> String {
+        match self {
+            ImageContent::Url(url) => url.clone(),
+            ImageContent::Base64 { media_type, data } => format!("data:{media_type};base64,{data}"),
+        }
+    }
+}
+
+impl From<&str> for ContentPart {
+    fn from(text: &str) -> Self {
+        ContentPart::Text(text.to_string())
+    }
+}
+
+impl From<String> for ContentPart {
+    fn from(text: String) -> Self {
+        ContentPart::Text(text)
+    }
+}
+
+impl From<ImageContent> for ContentPart {
+    fn from(image: ImageContent) -> Self {
+        ContentPart::Image(image)
+    }
+}
+
+impl std::fmt::Display for ContentPart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentPart::Text(text) => write!(f, "\"{text}\""),
+            ContentPart::Image(image) => write!(f, "{image}"),
+        }
+    }
+}
+
+impl std::fmt::Display for ImageContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageContent::Url(url) => write!(f, "Image: {url}"),
+            ImageContent::Base64 { media_type, .. } => write!(f, "Image: <{media_type}>"),
+        }
+    }
+}
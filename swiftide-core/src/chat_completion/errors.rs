@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-use crate::CommandError;
+use crate::{errors::has_transient_cause, CommandError};
 
 #[derive(Error, Debug)]
 pub enum ToolError {
@@ -8,6 +8,11 @@ pub enum ToolError {
     #[error("arguments for tool failed to parse")]
     WrongArguments(#[from] serde_json::Error),
 
+    /// The arguments are missing a required value or do not satisfy the constraints of a
+    /// parameter, the message is meant for the llm
+    #[error("invalid arguments for tool: {0}")]
+    InvalidArguments(String),
+
     /// Tool requires arguments but none were provided
     #[error("no arguments provided for tool {0:#}")]
     MissingArguments(String),
@@ -16,16 +21,64 @@ pub enum ToolError {
     #[error("tool execution failed: {0:#}")]
     ExecutionFailed(#[from] CommandError),
 
+    /// Tool did not complete within its timeout
+    #[error("tool timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
     #[error(transparent)]
     Unknown(#[from] anyhow::Error),
 }
 
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors returned by language models, for prompting, chat completion and embedding
+///
+/// Providers classify their errors, so that callers can decide to retry or give up.
 #[derive(Error, Debug)]
-pub enum ChatCompletionError {
-    /// Underlying errors by the llm
-    #[error("llm returned an error: {0}")]
-    LLM(Box<dyn std::error::Error + Send + Sync>),
+pub enum LanguageModelError {
+    /// The input does not fit in the context window of the model
+    #[error("context length exceeded: {0:#}")]
+    ContextLengthExceeded(BoxedError),
 
-    #[error(transparent)]
-    Unknown(#[from] anyhow::Error),
+    /// Errors that will not succeed on a retry, i.e. an invalid request or api key
+    #[error("permanent error: {0:#}")]
+    PermanentError(BoxedError),
+
+    /// Errors that might succeed on a retry, i.e. rate limits, timeouts and server errors
+    #[error("transient error: {0:#}")]
+    TransientError(BoxedError),
+}
+
+impl LanguageModelError {
+    pub fn permanent(err: impl Into<BoxedError>) -> Self {
+        LanguageModelError::PermanentError(err.into())
+    }
+
+    pub fn transient(err: impl Into<BoxedError>) -> Self {
+        LanguageModelError::TransientError(err.into())
+    }
+
+    pub fn context_length_exceeded(err: impl Into<BoxedError>) -> Self {
+        LanguageModelError::ContextLengthExceeded(err.into())
+    }
+
+    /// Returns true if the request might succeed when retried
+    pub fn is_transient(&self) -> bool {
+        matches!(self, LanguageModelError::TransientError(_))
+    }
+}
+
+/// Renamed to [`LanguageModelError`], which also covers prompting and embedding
+#[deprecated(since = "0.19.0", note = "Use `LanguageModelError` instead")]
+pub type ChatCompletionError = LanguageModelError;
+
+/// Unclassified errors are considered permanent, unless they have a transient cause
+impl From<anyhow::Error> for LanguageModelError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<LanguageModelError>() {
+            Ok(err) => err,
+            Err(err) if has_transient_cause(&err) => LanguageModelError::TransientError(err.into()),
+            Err(err) => LanguageModelError::PermanentError(err.into()),
+        }
+    }
 }
@@ -1,12 +1,22 @@
+use std::hash::{Hash, Hasher};
+
 use derive_builder::Builder;
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use super::{errors::ToolError, ImageContent};
 
 /// Output of a `ToolCall` which will be added as a message for the agent to use.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 #[non_exhaustive]
 pub enum ToolOutput {
     /// Adds the result of the toolcall to messages
     Text(String),
 
+    /// Adds an image returned by the toolcall to messages, i.e. a screenshot
+    Image(ImageContent),
+
     /// Indicates that the toolcall failed, but can be handled by the llm
     Fail(String),
     /// Stops an agent
@@ -20,6 +30,13 @@ impl ToolOutput {
             _ => None,
         }
     }
+
+    pub fn image(&self) -> Option<&ImageContent> {
+        match self {
+            ToolOutput::Image(image) => Some(image),
+            _ => None,
+        }
+    }
 }
 
 impl<T: AsRef<str>> From<T> for ToolOutput {
@@ -33,13 +50,14 @@ impl std::fmt::Display for ToolOutput {
         match self {
             ToolOutput::Text(value) => write!(f, "{value}"),
             ToolOutput::Fail(value) => write!(f, "Tool call failed: {value}"),
+            ToolOutput::Image(image) => write!(f, "{image}"),
             ToolOutput::Stop => write!(f, "Stop"),
         }
     }
 }
 
 /// A tool call that can be executed by the executor
-#[derive(Clone, Debug, Builder, PartialEq)]
+#[derive(Clone, Debug, Builder, PartialEq, Serialize, Deserialize)]
 #[builder(setter(into, strip_option))]
 pub struct ToolCall {
     id: String,
@@ -94,18 +112,356 @@ impl ToolSpec {
     pub fn builder() -> ToolSpecBuilder {
         ToolSpecBuilder::default()
     }
+
+    /// Applies the defaults of the parameters to the arguments of a tool call, and validates
+    /// them against the constraints of the parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToolError::InvalidArguments` with a message for the llm if a required argument is
+    /// missing or an argument is invalid, and `ToolError::WrongArguments` if the arguments are
+    /// not json.
+    pub fn prepare_args(&self, args: Option<&str>) -> Result<Option<String>, ToolError> {
+        if self.parameters.is_empty() {
+            return Ok(args.map(str::to_string));
+        }
+
+        let mut args = match args.map(str::trim) {
+            Some(args) if !args.is_empty() => {
+                serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(args)?
+            }
+            _ => serde_json::Map::new(),
+        };
+
+        for param in &self.parameters {
+            match args.get(param.name).filter(|value| !value.is_null()) {
+                Some(value) => param.validate(value)?,
+                None => {
+                    if let Some(default) = &param.default {
+                        args.insert(param.name.to_string(), default.clone());
+                    } else if param.required {
+                        return Err(ToolError::InvalidArguments(format!(
+                            "Missing required argument `{}`",
+                            param.name
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(Some(serde_json::to_string(&args)?))
+    }
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq, Builder)]
+#[derive(Clone, Debug, Eq, PartialEq, Builder)]
 pub struct ParamSpec {
     pub name: &'static str,
     pub description: &'static str,
     #[builder(default = true)]
     pub required: bool,
+    /// The json schema of the parameter, defaults to a string
+    #[builder(default, setter(strip_option))]
+    pub schema: Option<serde_json::Value>,
+    /// The value used when the argument is missing
+    #[builder(default, setter(strip_option, name = "default_value"))]
+    pub default: Option<serde_json::Value>,
+    /// Constraints the argument is validated against before the tool is invoked
+    #[builder(default, setter(each(name = "constraint")))]
+    pub constraints: Vec<ParamConstraint>,
+}
+
+/// A constraint on the argument of a parameter
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ParamConstraint {
+    /// The argument must be a number of at least this value
+    Minimum(serde_json::Number),
+    /// The argument must be a number of at most this value
+    Maximum(serde_json::Number),
+    /// The argument must be a string matching this regex
+    Pattern(String),
+    /// The argument must be one of these strings
+    OneOf(Vec<String>),
+}
+
+impl ParamConstraint {
+    fn validate(&self, name: &str, value: &serde_json::Value) -> Result<(), ToolError> {
+        let valid = match self {
+            ParamConstraint::Minimum(minimum) => value
+                .as_f64()
+                .zip(minimum.as_f64())
+                .is_some_and(|(value, minimum)| value >= minimum),
+            ParamConstraint::Maximum(maximum) => value
+                .as_f64()
+                .zip(maximum.as_f64())
+                .is_some_and(|(value, maximum)| value <= maximum),
+            ParamConstraint::Pattern(pattern) => {
+                let regex = regex::Regex::new(pattern).map_err(anyhow::Error::from)?;
+                value.as_str().is_some_and(|value| regex.is_match(value))
+            }
+            ParamConstraint::OneOf(values) => value
+                .as_str()
+                .is_some_and(|value| values.iter().any(|allowed| allowed == value)),
+        };
+
+        if valid {
+            return Ok(());
+        }
+
+        let expected = match self {
+            ParamConstraint::Minimum(minimum) => format!("a number of at least {minimum}"),
+            ParamConstraint::Maximum(maximum) => format!("a number of at most {maximum}"),
+            ParamConstraint::Pattern(pattern) => format!("a string matching `{pattern}`"),
+            ParamConstraint::OneOf(values) => format!("one of {}", values.join(", ")),
+        };
+
+        Err(ToolError::InvalidArguments(format!(
+            "Argument `{name}` must be {expected}, got {value}"
+        )))
+    }
 }
 
 impl ParamSpec {
     pub fn builder() -> ParamSpecBuilder {
         ParamSpecBuilder::default()
     }
+
+    /// The json schema of the parameter with its description
+    pub fn json_schema(&self) -> serde_json::Value {
+        let mut schema = self
+            .schema
+            .clone()
+            .unwrap_or_else(|| serde_json::json!({ "type": "string" }));
+
+        if let Some(schema) = schema.as_object_mut() {
+            schema.insert("description".to_string(), self.description.into());
+
+            if let Some(default) = &self.default {
+                schema.insert("default".to_string(), default.clone());
+            }
+
+            for constraint in &self.constraints {
+                let (key, value) = match constraint {
+                    ParamConstraint::Minimum(minimum) => ("minimum", minimum.clone().into()),
+                    ParamConstraint::Maximum(maximum) => ("maximum", maximum.clone().into()),
+                    ParamConstraint::Pattern(pattern) => ("pattern", pattern.clone().into()),
+                    ParamConstraint::OneOf(values) => ("enum", values.clone().into()),
+                };
+                schema.insert(key.to_string(), value);
+            }
+        }
+
+        schema
+    }
+
+    /// Validates an argument against the constraints of the parameter
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToolError::InvalidArguments` if the argument does not satisfy a constraint
+    pub fn validate(&self, value: &serde_json::Value) -> Result<(), ToolError> {
+        self.constraints
+            .iter()
+            .try_for_each(|constraint| constraint.validate(self.name, value))
+    }
+}
+
+impl ParamSpecBuilder {
+    /// Sets the schema to the json schema of `T`, for parameters that are not a string
+    ///
+    /// Nested types are inlined, as not every provider supports references.
+    ///
+    /// # Panics
+    ///
+    /// Never panics in practice, a generated schema always serializes to json
+    pub fn schema_for<T: JsonSchema>(&mut self) -> &mut Self {
+        let root = SchemaSettings::draft07()
+            .with(|settings| {
+                settings.inline_subschemas = true;
+                settings.meta_schema = None;
+            })
+            .into_generator()
+            .into_root_schema_for::<T>();
+
+        let mut schema = serde_json::to_value(root).expect("infallible");
+        if let Some(schema) = schema.as_object_mut() {
+            schema.remove("title");
+        }
+
+        self.schema(schema)
+    }
+
+    /// The argument must be a number of at least `minimum`
+    pub fn minimum(&mut self, minimum: f64) -> &mut Self {
+        match serde_json::Number::from_f64(minimum) {
+            Some(minimum) => self.constraint(ParamConstraint::Minimum(minimum)),
+            None => self,
+        }
+    }
+
+    /// The argument must be a number of at most `maximum`
+    pub fn maximum(&mut self, maximum: f64) -> &mut Self {
+        match serde_json::Number::from_f64(maximum) {
+            Some(maximum) => self.constraint(ParamConstraint::Maximum(maximum)),
+            None => self,
+        }
+    }
+
+    /// The argument must be a string matching the regex `pattern`
+    pub fn pattern(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.constraint(ParamConstraint::Pattern(pattern.into()))
+    }
+
+    /// The argument must be one of the given strings
+    pub fn one_of<V: Into<String>>(&mut self, values: impl IntoIterator<Item = V>) -> &mut Self {
+        self.constraint(ParamConstraint::OneOf(
+            values.into_iter().map(Into::into).collect(),
+        ))
+    }
+}
+
+// Json values are not hashable, the schema and default are hashed as a string instead
+impl Hash for ParamSpec {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.description.hash(state);
+        self.required.hash(state);
+        self.schema
+            .as_ref()
+            .map(serde_json::Value::to_string)
+            .hash(state);
+        self.default
+            .as_ref()
+            .map(serde_json::Value::to_string)
+            .hash(state);
+        self.constraints.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Filter {
+        language: String,
+        paths: Vec<String>,
+    }
+
+    #[test]
+    fn test_string_param_schema() {
+        let param = ParamSpec::builder()
+            .name("query")
+            .description("The query")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            param.json_schema(),
+            serde_json::json!({ "type": "string", "description": "The query" })
+        );
+    }
+
+    #[test]
+    fn test_param_schema_for_nested_type() {
+        let param = ParamSpec::builder()
+            .name("filters")
+            .description("The filters")
+            .schema_for::<Vec<Filter>>()
+            .build()
+            .unwrap();
+
+        let schema = param.json_schema();
+
+        assert_eq!(schema["type"], "array");
+        assert_eq!(schema["description"], "The filters");
+        assert_eq!(schema["items"]["properties"]["paths"]["type"], "array");
+        assert!(schema.get("definitions").is_none());
+        assert!(schema.get("$schema").is_none());
+    }
+
+    fn search_spec() -> ToolSpec {
+        ToolSpec::builder()
+            .name("search")
+            .description("Searches")
+            .parameters(vec![
+                ParamSpec::builder()
+                    .name("query")
+                    .description("The query")
+                    .pattern("^[a-z]+$")
+                    .build()
+                    .unwrap(),
+                ParamSpec::builder()
+                    .name("limit")
+                    .description("The number of results")
+                    .schema_for::<usize>()
+                    .default_value(10.into())
+                    .minimum(1.0)
+                    .maximum(50.0)
+                    .build()
+                    .unwrap(),
+                ParamSpec::builder()
+                    .name("language")
+                    .description("The language")
+                    .required(false)
+                    .one_of(["rust", "python"])
+                    .build()
+                    .unwrap(),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_constraints_in_schema() {
+        let schema = search_spec().parameters[1].json_schema();
+
+        assert_eq!(schema["default"], 10);
+        assert_eq!(schema["minimum"], 1.0);
+        assert_eq!(schema["maximum"], 50.0);
+        assert_eq!(
+            search_spec().parameters[2].json_schema()["enum"],
+            serde_json::json!(["rust", "python"])
+        );
+    }
+
+    #[test]
+    fn test_prepare_args_applies_defaults() {
+        let args = search_spec()
+            .prepare_args(Some(r#"{"query": "hello"}"#))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&args).unwrap(),
+            serde_json::json!({ "query": "hello", "limit": 10 })
+        );
+    }
+
+    #[test]
+    fn test_prepare_args_validates() {
+        let spec = search_spec();
+
+        for (args, expected) in [
+            ("{}", "Missing required argument `query`"),
+            (
+                r#"{"query": "Hello"}"#,
+                "Argument `query` must be a string matching `^[a-z]+$`, got \"Hello\"",
+            ),
+            (
+                r#"{"query": "hello", "limit": 100}"#,
+                "Argument `limit` must be a number of at most 50.0, got 100",
+            ),
+            (
+                r#"{"query": "hello", "language": "go"}"#,
+                "Argument `language` must be one of rust, python, got \"go\"",
+            ),
+        ] {
+            let Err(ToolError::InvalidArguments(message)) = spec.prepare_args(Some(args)) else {
+                panic!("Expected invalid arguments for {args}");
+            };
+            assert_eq!(message, expected);
+        }
+    }
 }
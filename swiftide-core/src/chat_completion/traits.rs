@@ -1,21 +1,40 @@
+use std::pin::Pin;
+
 use async_trait::async_trait;
 use dyn_clone::DynClone;
+use futures_util::Stream;
 
 use crate::{AgentContext, CommandOutput};
 
 use super::{
     chat_completion_request::ChatCompletionRequest,
     chat_completion_response::ChatCompletionResponse,
-    errors::{ChatCompletionError, ToolError},
+    errors::{LanguageModelError, ToolError},
     ToolOutput, ToolSpec,
 };
 
+/// A stream of chat completion responses
+///
+/// Every item is the response accumulated so far, with the newly received content in
+/// [`ChatCompletionResponse::delta`]. The last item is the complete response.
+pub type ChatCompletionStream =
+    Pin<Box<dyn Stream<Item = Result<ChatCompletionResponse, LanguageModelError>> + Send>>;
+
 #[async_trait]
 pub trait ChatCompletion: Send + Sync + DynClone {
     async fn complete(
         &self,
         request: &ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse, ChatCompletionError>;
+    ) -> Result<ChatCompletionResponse, LanguageModelError>;
+
+    /// Streams the completion as it is generated
+    ///
+    /// By default, providers without streaming support return a stream with the complete
+    /// response as the only item.
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        let response = self.complete(request).await;
+        Box::pin(futures_util::stream::once(async move { response }))
+    }
 }
 
 #[async_trait]
@@ -23,9 +42,13 @@ impl ChatCompletion for Box<dyn ChatCompletion> {
     async fn complete(
         &self,
         request: &ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse, ChatCompletionError> {
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
         (**self).complete(request).await
     }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        (**self).complete_stream(request).await
+    }
 }
 
 #[async_trait]
@@ -33,9 +56,13 @@ impl ChatCompletion for &dyn ChatCompletion {
     async fn complete(
         &self,
         request: &ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse, ChatCompletionError> {
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
         (**self).complete(request).await
     }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        (**self).complete_stream(request).await
+    }
 }
 
 #[async_trait]
@@ -46,9 +73,13 @@ where
     async fn complete(
         &self,
         request: &ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse, ChatCompletionError> {
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
         (**self).complete(request).await
     }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        (**self).complete_stream(request).await
+    }
 }
 
 impl<LLM> From<&LLM> for Box<dyn ChatCompletion>
@@ -0,0 +1,343 @@
+//! Estimates the cost of completions from their token usage
+//!
+//! A [`PricingTable`] maps model names to their [`ModelPricing`]. The default table bundles
+//! prices for common models, and can be extended or overridden with your own.
+//!
+//! A [`CostAccumulator`] aggregates usage and cost per model. It is cheap to clone and all clones
+//! share the same totals, so a single accumulator can be passed to multiple pipelines and agents.
+//!
+//! # Example
+//!
+//! ```
+//! # use swiftide_core::cost::{CostAccumulator, ModelPricing, PricingTable};
+//! # use swiftide_core::chat_completion::Usage;
+//! let pricing = PricingTable::default().with_model("my-model", ModelPricing::new(1.0, 2.0));
+//! let accumulator = CostAccumulator::new(pricing);
+//!
+//! accumulator.record(
+//!     "my-model",
+//!     &Usage {
+//!         prompt_tokens: 1_000_000,
+//!         completion_tokens: 500_000,
+//!         total_tokens: 1_500_000,
+//!         cached_prompt_tokens: None,
+//!     },
+//! );
+//!
+//! assert!((accumulator.total_cost() - 2.0).abs() < f64::EPSILON);
+//! ```
+//!
+//! Prices are estimates in USD and may be outdated, providers change their pricing regularly.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::chat_completion::Usage;
+
+/// Pricing of a model in USD per million tokens
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+    /// Price of prompt tokens served from the cache, defaults to the input price
+    pub cached_input_per_million: Option<f64>,
+}
+
+impl ModelPricing {
+    pub fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+            cached_input_per_million: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_cached_input_per_million(mut self, cached_input_per_million: f64) -> Self {
+        self.cached_input_per_million = Some(cached_input_per_million);
+        self
+    }
+
+    /// Estimated cost in USD of the given usage
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let cached = usage
+            .cached_prompt_tokens
+            .unwrap_or_default()
+            .min(usage.prompt_tokens);
+        let uncached = usage.prompt_tokens - cached;
+
+        let cached_price = self
+            .cached_input_per_million
+            .unwrap_or(self.input_per_million);
+
+        (f64::from(uncached) * self.input_per_million
+            + f64::from(cached) * cached_price
+            + f64::from(usage.completion_tokens) * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Maps model names to their pricing
+///
+/// Lookups match the exact model name first, and otherwise the longest known prefix. This way
+/// dated model versions, like `gpt-4o-2024-08-06`, use the pricing of `gpt-4o`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PricingTable {
+    models: HashMap<String, ModelPricing>,
+}
+
+impl PricingTable {
+    /// An empty pricing table without any bundled prices
+    pub fn empty() -> Self {
+        Self {
+            models: HashMap::new(),
+        }
+    }
+
+    /// Adds or overrides the pricing of a model
+    #[must_use]
+    pub fn with_model(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.insert(model, pricing);
+        self
+    }
+
+    /// Adds or overrides the pricing of a model
+    pub fn insert(&mut self, model: impl Into<String>, pricing: ModelPricing) {
+        self.models.insert(model.into(), pricing);
+    }
+
+    /// Returns the pricing of a model, if known
+    pub fn get(&self, model: &str) -> Option<&ModelPricing> {
+        self.models.get(model).or_else(|| {
+            self.models
+                .iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, pricing)| pricing)
+        })
+    }
+
+    /// Estimated cost in USD of the given usage, if the model is known
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.get(model).map(|pricing| pricing.cost(usage))
+    }
+}
+
+impl Default for PricingTable {
+    /// Bundled pricing for common models
+    fn default() -> Self {
+        let bundled = [
+            // OpenAI
+            (
+                "gpt-4o",
+                ModelPricing::new(2.5, 10.0).with_cached_input_per_million(1.25),
+            ),
+            (
+                "gpt-4o-mini",
+                ModelPricing::new(0.15, 0.6).with_cached_input_per_million(0.075),
+            ),
+            (
+                "o1",
+                ModelPricing::new(15.0, 60.0).with_cached_input_per_million(7.5),
+            ),
+            (
+                "o1-mini",
+                ModelPricing::new(3.0, 12.0).with_cached_input_per_million(1.5),
+            ),
+            (
+                "o3-mini",
+                ModelPricing::new(1.1, 4.4).with_cached_input_per_million(0.55),
+            ),
+            ("gpt-4-turbo", ModelPricing::new(10.0, 30.0)),
+            ("gpt-3.5-turbo", ModelPricing::new(0.5, 1.5)),
+            ("text-embedding-3-small", ModelPricing::new(0.02, 0.0)),
+            ("text-embedding-3-large", ModelPricing::new(0.13, 0.0)),
+            ("text-embedding-ada-002", ModelPricing::new(0.1, 0.0)),
+            // Anthropic
+            (
+                "claude-3-5-sonnet",
+                ModelPricing::new(3.0, 15.0).with_cached_input_per_million(0.3),
+            ),
+            (
+                "claude-3-5-haiku",
+                ModelPricing::new(0.8, 4.0).with_cached_input_per_million(0.08),
+            ),
+            (
+                "claude-3-opus",
+                ModelPricing::new(15.0, 75.0).with_cached_input_per_million(1.5),
+            ),
+            (
+                "claude-3-haiku",
+                ModelPricing::new(0.25, 1.25).with_cached_input_per_million(0.03),
+            ),
+            // DeepSeek
+            (
+                "deepseek-chat",
+                ModelPricing::new(0.27, 1.1).with_cached_input_per_million(0.07),
+            ),
+            (
+                "deepseek-reasoner",
+                ModelPricing::new(0.55, 2.19).with_cached_input_per_million(0.14),
+            ),
+            // Groq
+            ("llama-3.3-70b-versatile", ModelPricing::new(0.59, 0.79)),
+            ("llama-3.1-8b-instant", ModelPricing::new(0.05, 0.08)),
+        ];
+
+        Self {
+            models: bundled
+                .into_iter()
+                .map(|(model, pricing)| (model.to_string(), pricing))
+                .collect(),
+        }
+    }
+}
+
+/// Usage and estimated cost of a single model
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ModelCost {
+    /// Number of completions or embedding calls recorded
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cached_prompt_tokens: u64,
+    /// Estimated cost in USD, zero if the model is not in the pricing table
+    pub cost: f64,
+}
+
+/// Aggregates usage and cost per model
+///
+/// Clones share the same totals.
+#[derive(Clone, Debug, Default)]
+pub struct CostAccumulator {
+    pricing: Arc<PricingTable>,
+    models: Arc<Mutex<HashMap<String, ModelCost>>>,
+}
+
+impl CostAccumulator {
+    pub fn new(pricing: PricingTable) -> Self {
+        Self {
+            pricing: Arc::new(pricing),
+            models: Arc::default(),
+        }
+    }
+
+    pub fn pricing(&self) -> &PricingTable {
+        &self.pricing
+    }
+
+    /// Records the usage of a model and returns its estimated cost, if the model is known
+    ///
+    /// Usage of unknown models is still recorded, without cost.
+    pub fn record(&self, model: &str, usage: &Usage) -> Option<f64> {
+        let cost = self.pricing.cost(model, usage);
+
+        if cost.is_none() {
+            tracing::warn!(model, "No pricing known for model, cost is not tracked");
+        }
+
+        let mut models = self.models();
+        let entry = models.entry(model.to_string()).or_default();
+        entry.requests += 1;
+        entry.prompt_tokens += u64::from(usage.prompt_tokens);
+        entry.completion_tokens += u64::from(usage.completion_tokens);
+        entry.cached_prompt_tokens += u64::from(usage.cached_prompt_tokens.unwrap_or_default());
+        entry.cost += cost.unwrap_or_default();
+
+        cost
+    }
+
+    /// Total estimated cost in USD over all models
+    pub fn total_cost(&self) -> f64 {
+        self.models().values().map(|model| model.cost).sum()
+    }
+
+    /// Usage and cost per model
+    pub fn by_model(&self) -> HashMap<String, ModelCost> {
+        self.models().clone()
+    }
+
+    /// Clears all recorded usage
+    pub fn reset(&self) {
+        self.models().clear();
+    }
+
+    /// The recorded usage, a panic while recording leaves at most one usage partially recorded
+    fn models(&self) -> MutexGuard<'_, HashMap<String, ModelCost>> {
+        self.models.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: u32, completion_tokens: u32, cached: Option<u32>) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            cached_prompt_tokens: cached,
+        }
+    }
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{left} != {right}");
+    }
+
+    #[test]
+    fn test_cost_with_cached_tokens() {
+        let pricing = ModelPricing::new(2.0, 8.0).with_cached_input_per_million(1.0);
+
+        assert_close(
+            pricing.cost(&usage(1_000_000, 1_000_000, Some(500_000))),
+            0.5 * 2.0 + 0.5 * 1.0 + 8.0,
+        );
+    }
+
+    #[test]
+    fn test_lookup_by_longest_prefix() {
+        let table = PricingTable::default();
+
+        assert_eq!(table.get("gpt-4o-2024-08-06"), table.get("gpt-4o"));
+        assert_eq!(
+            table.get("gpt-4o-mini-2024-07-18"),
+            table.get("gpt-4o-mini")
+        );
+        assert_ne!(table.get("gpt-4o-mini"), table.get("gpt-4o"));
+        assert!(table.get("unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_override_bundled_pricing() {
+        let table = PricingTable::default().with_model("gpt-4o", ModelPricing::new(1.0, 1.0));
+
+        assert_close(
+            table.cost("gpt-4o", &usage(1_000_000, 0, None)).unwrap(),
+            1.0,
+        );
+    }
+
+    #[test]
+    fn test_accumulator_is_shared_between_clones() {
+        let accumulator = CostAccumulator::new(
+            PricingTable::empty().with_model("my-model", ModelPricing::new(1.0, 2.0)),
+        );
+        let other = accumulator.clone();
+
+        accumulator.record("my-model", &usage(1_000_000, 0, None));
+        other.record("my-model", &usage(0, 1_000_000, None));
+        other.record("unknown", &usage(10, 10, None));
+
+        assert_close(accumulator.total_cost(), 3.0);
+
+        let by_model = accumulator.by_model();
+        assert_eq!(by_model["my-model"].requests, 2);
+        assert_eq!(by_model["unknown"].prompt_tokens, 10);
+        assert_close(by_model["unknown"].cost, 0.0);
+
+        other.reset();
+        assert!(accumulator.by_model().is_empty());
+    }
+}
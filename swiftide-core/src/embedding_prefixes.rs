@@ -0,0 +1,81 @@
+//! Prefixes for asymmetric embedding models
+//!
+//! Asymmetric models embed queries and documents differently, and expect an instruction or a
+//! prefix to tell them apart, e.g. e5 models expect `query: ` and `passage: `.
+//!
+//! Embedding clients can be configured with prefixes, which the `Embed` transformers apply to the
+//! documents when indexing and to the query when querying.
+use serde::{Deserialize, Serialize};
+
+/// The prefixes an embedding model expects for queries and documents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingPrefixes {
+    /// Prepended to queries
+    pub query: Option<String>,
+    /// Prepended to documents
+    pub document: Option<String>,
+}
+
+impl EmbeddingPrefixes {
+    pub fn new(query: impl Into<String>, document: impl Into<String>) -> Self {
+        Self {
+            query: Some(query.into()),
+            document: Some(document.into()),
+        }
+    }
+
+    /// Only prefixes queries, e.g. with a task instruction
+    pub fn query_only(query: impl Into<String>) -> Self {
+        Self {
+            query: Some(query.into()),
+            document: None,
+        }
+    }
+
+    /// Prefixes for the e5 family, `query: ` and `passage: `
+    pub fn e5() -> Self {
+        Self::new("query: ", "passage: ")
+    }
+
+    /// Prefixes for the english bge v1.5 models, an instruction for queries only
+    pub fn bge_en() -> Self {
+        Self::query_only("Represent this sentence for searching relevant passages: ")
+    }
+
+    /// Prefixes for nomic embed, `search_query: ` and `search_document: `
+    pub fn nomic() -> Self {
+        Self::new("search_query: ", "search_document: ")
+    }
+
+    /// Prepends the query prefix, if any
+    pub fn apply_query(&self, query: impl Into<String>) -> String {
+        apply(self.query.as_deref(), query.into())
+    }
+
+    /// Prepends the document prefix, if any
+    pub fn apply_document(&self, document: impl Into<String>) -> String {
+        apply(self.document.as_deref(), document.into())
+    }
+}
+
+fn apply(prefix: Option<&str>, input: String) -> String {
+    match prefix {
+        Some(prefix) => format!("{prefix}{input}"),
+        None => input,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applies_prefixes() {
+        let prefixes = EmbeddingPrefixes::e5();
+        assert_eq!(prefixes.apply_query("rust"), "query: rust");
+        assert_eq!(prefixes.apply_document("rust"), "passage: rust");
+
+        let prefixes = EmbeddingPrefixes::bge_en();
+        assert_eq!(prefixes.apply_document("rust"), "rust");
+    }
+}
@@ -0,0 +1,186 @@
+//! Errors returned by storages, retrievers, loaders and node caches
+//!
+//! Like [`LanguageModelError`], errors are classified as transient or permanent, so that callers
+//! can decide to retry, fall back to another storage or give up. The error of the provider is
+//! kept as is and can be downcasted to inspect it.
+//!
+//! Errors that are not classified, i.e. `anyhow::Error`, are considered permanent, unless they
+//! are caused by a transient error, i.e. a classified transient error or a timed out connection.
+//!
+//! [`LanguageModelError`]: crate::chat_completion::errors::LanguageModelError
+use thiserror::Error;
+
+use crate::chat_completion::errors::{BoxedError, LanguageModelError};
+
+/// Errors returned by [`Persist`][crate::Persist]
+#[derive(Error, Debug)]
+pub enum PersistError {
+    /// The storage does not support the operation, i.e. deleting nodes
+    #[error("not supported: {0}")]
+    Unsupported(String),
+
+    /// Errors that will not succeed on a retry, i.e. an invalid schema or node
+    #[error("permanent error: {0:#}")]
+    PermanentError(BoxedError),
+
+    /// Errors that might succeed on a retry, i.e. connection errors and timeouts
+    #[error("transient error: {0:#}")]
+    TransientError(BoxedError),
+}
+
+/// Errors returned by [`Retrieve`][crate::Retrieve]
+#[derive(Error, Debug)]
+pub enum RetrieveError {
+    /// The search strategy requires an embedding, but the query was not embedded
+    #[error("no embedding for query")]
+    MissingEmbedding,
+
+    /// Errors that will not succeed on a retry, i.e. an invalid filter or query
+    #[error("permanent error: {0:#}")]
+    PermanentError(BoxedError),
+
+    /// Errors that might succeed on a retry, i.e. connection errors and timeouts
+    #[error("transient error: {0:#}")]
+    TransientError(BoxedError),
+}
+
+/// Errors emitted by a [`Loader`][crate::Loader]
+///
+/// Loaders stream their errors as `anyhow::Error`, the `LoaderError` can be retrieved with
+/// `downcast_ref`.
+#[derive(Error, Debug)]
+pub enum LoaderError {
+    /// Errors that will not succeed on a retry, i.e. an unreadable file
+    #[error("permanent error: {0:#}")]
+    PermanentError(BoxedError),
+
+    /// Errors that might succeed on a retry, i.e. connection errors and timeouts
+    #[error("transient error: {0:#}")]
+    TransientError(BoxedError),
+}
+
+/// Errors returned by [`NodeCache`][crate::NodeCache]
+#[derive(Error, Debug)]
+pub enum NodeCacheError {
+    /// The cache does not support the operation, i.e. clearing it
+    #[error("not supported: {0}")]
+    Unsupported(String),
+
+    /// Errors that will not succeed on a retry
+    #[error("permanent error: {0:#}")]
+    PermanentError(BoxedError),
+
+    /// Errors that might succeed on a retry, i.e. connection errors and timeouts
+    #[error("transient error: {0:#}")]
+    TransientError(BoxedError),
+}
+
+/// Implements the constructors and conversions shared by all errors
+macro_rules! classified_error {
+    ($error:ident) => {
+        impl $error {
+            pub fn permanent(err: impl Into<BoxedError>) -> Self {
+                $error::PermanentError(err.into())
+            }
+
+            pub fn transient(err: impl Into<BoxedError>) -> Self {
+                $error::TransientError(err.into())
+            }
+
+            /// Returns true if the operation might succeed when retried
+            pub fn is_transient(&self) -> bool {
+                matches!(self, $error::TransientError(_))
+            }
+        }
+
+        /// Unclassified errors are considered permanent, unless they have a transient cause
+        impl From<anyhow::Error> for $error {
+            fn from(err: anyhow::Error) -> Self {
+                match err.downcast::<$error>() {
+                    Ok(err) => err,
+                    Err(err) if has_transient_cause(&err) => $error::TransientError(err.into()),
+                    Err(err) => $error::PermanentError(err.into()),
+                }
+            }
+        }
+    };
+}
+
+/// Returns true if any error in the chain is known to be transient
+pub(crate) fn has_transient_cause(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(
+                err.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+            );
+        }
+
+        cause
+            .downcast_ref::<LanguageModelError>()
+            .is_some_and(LanguageModelError::is_transient)
+            || cause
+                .downcast_ref::<PersistError>()
+                .is_some_and(PersistError::is_transient)
+            || cause
+                .downcast_ref::<RetrieveError>()
+                .is_some_and(RetrieveError::is_transient)
+            || cause
+                .downcast_ref::<LoaderError>()
+                .is_some_and(LoaderError::is_transient)
+            || cause
+                .downcast_ref::<NodeCacheError>()
+                .is_some_and(NodeCacheError::is_transient)
+    })
+}
+
+classified_error!(PersistError);
+classified_error!(RetrieveError);
+classified_error!(LoaderError);
+classified_error!(NodeCacheError);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unclassified_errors_are_permanent() {
+        let err = PersistError::from(anyhow::anyhow!("invalid node"));
+
+        assert!(!err.is_transient());
+        assert_eq!(err.to_string(), "permanent error: invalid node");
+    }
+
+    #[test]
+    fn test_unclassified_errors_with_a_transient_cause_are_transient() {
+        let err = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::TimedOut))
+            .context("Failed to store node");
+        assert!(PersistError::from(err).is_transient());
+
+        let err = anyhow::Error::new(RetrieveError::transient(anyhow::anyhow!("unavailable")))
+            .context("Failed to retrieve documents");
+        assert!(RetrieveError::from(err).is_transient());
+
+        // Classified errors are kept as they are
+        let err = anyhow::Error::new(PersistError::Unsupported("delete".to_string()));
+        assert!(matches!(
+            PersistError::from(err),
+            PersistError::Unsupported(_)
+        ));
+    }
+
+    #[test]
+    fn test_provider_error_can_be_downcasted() {
+        let err = RetrieveError::transient(std::io::Error::from(std::io::ErrorKind::TimedOut));
+
+        assert!(err.is_transient());
+        let RetrieveError::TransientError(source) = err else {
+            panic!("Expected a transient error");
+        };
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
+    }
+}
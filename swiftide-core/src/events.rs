@@ -0,0 +1,266 @@
+//! Typed events emitted by the indexing and query pipelines
+//!
+//! Unlike tracing, events are meant to be consumed by applications, i.e. to drive a progress UI
+//! or to persist the history of runs. Subscribe with `subscribe` on either pipeline. Every
+//! subscriber receives every event emitted after it subscribed.
+//!
+//! Events are sent on a bounded broadcast channel, so a slow subscriber never blocks the
+//! pipeline. Instead it skips the oldest events and receives a `RecvError::Lagged`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let pipeline = Pipeline::from_loader(loader).then_store_with(storage);
+//! let mut events = pipeline.subscribe();
+//!
+//! tokio::spawn(async move {
+//!     while let Ok(event) = events.recv().await {
+//!         println!("{event:?}");
+//!     }
+//! });
+//!
+//! pipeline.run().await?;
+//! ```
+use std::{future::Future, time::Duration};
+
+use anyhow::Result;
+use futures_util::{StreamExt as _, TryStreamExt as _};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+use web_time::Instant;
+
+use crate::indexing::IndexingStream;
+
+/// The number of events buffered per subscriber
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// An event emitted by a pipeline
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PipelineEvent {
+    /// The pipeline started running
+    RunStarted,
+    /// The pipeline finished, with the number of nodes or answered queries it produced
+    RunFinished { total: usize, elapsed: Duration },
+    /// The pipeline stopped on an error
+    RunFailed { error: String },
+    /// The run was cancelled, with the number of nodes or answered queries produced until then
+    RunCancelled { total: usize, elapsed: Duration },
+    /// A step started processing a node, query or batch of nodes
+    StepStarted { step: &'static str, nodes: usize },
+    /// A step finished processing a node, query or batch of nodes
+    StepFinished {
+        step: &'static str,
+        nodes: usize,
+        elapsed: Duration,
+    },
+    /// A step failed to process a node or query
+    ///
+    /// The id of the node is only known if the step processes nodes one by one.
+    NodeFailed {
+        step: &'static str,
+        node_id: Option<Uuid>,
+        error: String,
+    },
+    /// A batch of nodes was handed to storage
+    ///
+    /// Nodes that failed to store are reported with `NodeFailed`.
+    BatchFlushed { storage: &'static str, nodes: usize },
+}
+
+/// Sends events to the subscribers of a pipeline
+///
+/// Cheap to clone, all clones send to the same subscribers.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    sender: broadcast::Sender<PipelineEvent>,
+}
+
+impl Default for EventSender {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl EventSender {
+    /// Creates a sender that buffers `capacity` events per subscriber
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is 0
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PipelineEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Sends an event to all subscribers, if there are any
+    pub fn send(&self, event: PipelineEvent) {
+        if self.sender.receiver_count() > 0 {
+            // Only fails if all subscribers dropped in the meantime
+            let _ = self.sender.send(event);
+        }
+    }
+
+    /// Emits the start of a run, and its finish or failure when the future resolves
+    ///
+    /// `total` counts the nodes or queries the run produced.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the future
+    pub async fn observe_run<T>(
+        &self,
+        future: impl Future<Output = Result<T>>,
+        total: impl FnOnce(&T) -> usize,
+    ) -> Result<T> {
+        self.observe_cancellable_run(&CancellationToken::new(), future, total)
+            .await
+    }
+
+    /// Like [`EventSender::observe_run`], but emits `RunCancelled` instead of `RunFinished` if
+    /// the token is cancelled by the time the future resolves
+    ///
+    /// The future itself is responsible for stopping early when the token is cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the future
+    pub async fn observe_cancellable_run<T>(
+        &self,
+        cancel: &CancellationToken,
+        future: impl Future<Output = Result<T>>,
+        total: impl FnOnce(&T) -> usize,
+    ) -> Result<T> {
+        let start = Instant::now();
+        self.send(PipelineEvent::RunStarted);
+
+        let result = future.await;
+
+        match &result {
+            Ok(output) if cancel.is_cancelled() => self.send(PipelineEvent::RunCancelled {
+                total: total(output),
+                elapsed: start.elapsed(),
+            }),
+            Ok(output) => self.send(PipelineEvent::RunFinished {
+                total: total(output),
+                elapsed: start.elapsed(),
+            }),
+            Err(error) => self.send(PipelineEvent::RunFailed {
+                error: format!("{error:#}"),
+            }),
+        }
+
+        result
+    }
+
+    /// Emits the start of a step, and its finish or failure when the future resolves
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the future
+    pub async fn observe<T>(
+        &self,
+        step: &'static str,
+        node_id: Option<Uuid>,
+        future: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let start = Instant::now();
+        self.send(PipelineEvent::StepStarted { step, nodes: 1 });
+
+        let result = future.await;
+
+        match &result {
+            Ok(_) => self.send(PipelineEvent::StepFinished {
+                step,
+                nodes: 1,
+                elapsed: start.elapsed(),
+            }),
+            Err(error) => self.send(PipelineEvent::NodeFailed {
+                step,
+                node_id,
+                error: format!("{error:#}"),
+            }),
+        }
+
+        result
+    }
+
+    /// Emits the start of a step on a batch of nodes, and its finish when the future resolves
+    ///
+    /// Errors in the resulting stream are emitted as they are consumed.
+    pub async fn observe_stream(
+        &self,
+        step: &'static str,
+        nodes: usize,
+        future: impl Future<Output = IndexingStream>,
+    ) -> IndexingStream {
+        let start = Instant::now();
+        self.send(PipelineEvent::StepStarted { step, nodes });
+
+        let stream = future.await;
+
+        self.send(PipelineEvent::StepFinished {
+            step,
+            nodes,
+            elapsed: start.elapsed(),
+        });
+
+        let events = self.clone();
+        stream
+            .inspect_err(move |error| {
+                events.send(PipelineEvent::NodeFailed {
+                    step,
+                    node_id: None,
+                    error: format!("{error:#}"),
+                });
+            })
+            .boxed()
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_observe_emits_start_and_failure() {
+        let events = EventSender::default();
+        let mut receiver = events.subscribe();
+
+        let node_id = Uuid::new_v4();
+        let result = events
+            .observe("step", Some(node_id), async {
+                Err::<(), _>(anyhow::anyhow!("failed"))
+            })
+            .await;
+        assert!(result.is_err());
+
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            PipelineEvent::StepStarted {
+                step: "step",
+                nodes: 1
+            }
+        );
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            PipelineEvent::NodeFailed {
+                step: "step",
+                node_id: Some(node_id),
+                error: "failed".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_send_without_subscribers() {
+        EventSender::default().send(PipelineEvent::RunStarted);
+    }
+}
@@ -3,17 +3,23 @@
 //! All steps defined in the indexing pipeline and the generic transformers can also take a
 //! trait. To bring your own transformers, models and loaders, all you need to do is implement the
 //! trait and it should work out of the box.
+use crate::metadata::Metadata;
 use crate::node::Node;
-use crate::Embeddings;
 use crate::{
     indexing_defaults::IndexingDefaults, indexing_stream::IndexingStream, SparseEmbeddings,
 };
+use crate::{EmbeddingPrefixes, Embeddings, MultiEmbeddings};
 use std::fmt::Debug;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::chat_completion::errors::LanguageModelError;
+use crate::errors::{NodeCacheError, PersistError};
 use crate::prompt::Prompt;
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use async_trait::async_trait;
+use schemars::{schema::RootSchema, JsonSchema};
+use serde::de::DeserializeOwned;
 
 pub use dyn_clone::DynClone;
 /// All traits are easily mockable under tests
@@ -192,6 +198,9 @@ impl BatchableTransformer for &dyn BatchableTransformer {
 }
 
 /// Starting point of a stream
+///
+/// The stream has `anyhow::Error`s, loaders that classify them wrap a
+/// [`LoaderError`][crate::errors::LoaderError], which can be retrieved with `downcast_ref`.
 pub trait Loader: DynClone {
     fn into_stream(self) -> IndexingStream;
 
@@ -339,8 +348,11 @@ pub trait NodeCache: Send + Sync + Debug + DynClone {
     async fn set(&self, node: &Node);
 
     /// Optionally provide a method to clear the cache
-    async fn clear(&self) -> Result<()> {
-        unimplemented!("Clear not implemented")
+    async fn clear(&self) -> Result<(), NodeCacheError> {
+        Err(NodeCacheError::Unsupported(format!(
+            "{} cannot be cleared",
+            self.name()
+        )))
     }
 
     fn name(&self) -> &'static str {
@@ -360,7 +372,7 @@ mock! {
     impl NodeCache for NodeCache {
         async fn get(&self, node: &Node) -> bool;
         async fn set(&self, node: &Node);
-        async fn clear(&self) -> Result<()>;
+        async fn clear(&self) -> Result<(), NodeCacheError>;
         fn name(&self) -> &'static str;
 
     }
@@ -378,7 +390,7 @@ impl NodeCache for Box<dyn NodeCache> {
     async fn set(&self, node: &Node) {
         self.as_ref().set(node).await;
     }
-    async fn clear(&self) -> Result<()> {
+    async fn clear(&self) -> Result<(), NodeCacheError> {
         self.as_ref().clear().await
     }
     fn name(&self) -> &'static str {
@@ -394,7 +406,7 @@ impl NodeCache for Arc<dyn NodeCache> {
     async fn set(&self, node: &Node) {
         self.as_ref().set(node).await;
     }
-    async fn clear(&self) -> Result<()> {
+    async fn clear(&self) -> Result<(), NodeCacheError> {
         self.as_ref().clear().await
     }
     fn name(&self) -> &'static str {
@@ -410,7 +422,7 @@ impl NodeCache for &dyn NodeCache {
     async fn set(&self, node: &Node) {
         (*self).set(node).await;
     }
-    async fn clear(&self) -> Result<()> {
+    async fn clear(&self) -> Result<(), NodeCacheError> {
         (*self).clear().await
     }
 }
@@ -419,12 +431,19 @@ impl NodeCache for &dyn NodeCache {
 /// Embeds a list of strings and returns its embeddings.
 /// Assumes the strings will be moved.
 pub trait EmbeddingModel: Send + Sync + Debug + DynClone {
-    async fn embed(&self, input: Vec<String>) -> Result<Embeddings>;
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError>;
 
     fn name(&self) -> &'static str {
         let name = std::any::type_name::<Self>();
         name.split("::").last().unwrap_or(name)
     }
+
+    /// Prefixes the model expects for queries and documents, if it is asymmetric
+    ///
+    /// Applied by the `Embed` transformers when indexing and querying.
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        None
+    }
 }
 
 dyn_clone::clone_trait_object!(EmbeddingModel);
@@ -436,7 +455,7 @@ mock! {
 
     #[async_trait]
     impl EmbeddingModel for EmbeddingModel {
-        async fn embed(&self, input: Vec<String>) -> Result<Embeddings>;
+        async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError>;
         fn name(&self) -> &'static str;
     }
 
@@ -447,38 +466,53 @@ mock! {
 
 #[async_trait]
 impl EmbeddingModel for Box<dyn EmbeddingModel> {
-    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
         self.as_ref().embed(input).await
     }
 
     fn name(&self) -> &'static str {
         self.as_ref().name()
     }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.as_ref().prefixes()
+    }
 }
 
 #[async_trait]
 impl EmbeddingModel for Arc<dyn EmbeddingModel> {
-    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
         self.as_ref().embed(input).await
     }
 
     fn name(&self) -> &'static str {
         self.as_ref().name()
     }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.as_ref().prefixes()
+    }
 }
 
 #[async_trait]
 impl EmbeddingModel for &dyn EmbeddingModel {
-    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
         (*self).embed(input).await
     }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        (*self).prefixes()
+    }
 }
 
 #[async_trait]
 /// Embeds a list of strings and returns its embeddings.
 /// Assumes the strings will be moved.
 pub trait SparseEmbeddingModel: Send + Sync + Debug + DynClone {
-    async fn sparse_embed(&self, input: Vec<String>) -> Result<SparseEmbeddings>;
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError>;
 
     fn name(&self) -> &'static str {
         let name = std::any::type_name::<Self>();
@@ -495,7 +529,7 @@ mock! {
 
     #[async_trait]
     impl SparseEmbeddingModel for SparseEmbeddingModel {
-    async fn sparse_embed(&self, input: Vec<String>) -> Result<SparseEmbeddings>;
+    async fn sparse_embed(&self, input: Vec<String>) -> Result<SparseEmbeddings, LanguageModelError>;
         fn name(&self) -> &'static str;
     }
 
@@ -506,7 +540,10 @@ mock! {
 
 #[async_trait]
 impl SparseEmbeddingModel for Box<dyn SparseEmbeddingModel> {
-    async fn sparse_embed(&self, input: Vec<String>) -> Result<SparseEmbeddings> {
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError> {
         self.as_ref().sparse_embed(input).await
     }
 
@@ -517,7 +554,10 @@ impl SparseEmbeddingModel for Box<dyn SparseEmbeddingModel> {
 
 #[async_trait]
 impl SparseEmbeddingModel for Arc<dyn SparseEmbeddingModel> {
-    async fn sparse_embed(&self, input: Vec<String>) -> Result<SparseEmbeddings> {
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError> {
         self.as_ref().sparse_embed(input).await
     }
 
@@ -528,16 +568,139 @@ impl SparseEmbeddingModel for Arc<dyn SparseEmbeddingModel> {
 
 #[async_trait]
 impl SparseEmbeddingModel for &dyn SparseEmbeddingModel {
-    async fn sparse_embed(&self, input: Vec<String>) -> Result<SparseEmbeddings> {
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError> {
         (*self).sparse_embed(input).await
     }
 }
 
+#[async_trait]
+/// Embeds a list of strings into an embedding per token, for late interaction retrieval.
+/// Assumes the strings will be moved.
+pub trait MultiEmbeddingModel: Send + Sync + Debug + DynClone {
+    async fn multi_embed(&self, input: Vec<String>) -> Result<MultiEmbeddings, LanguageModelError>;
+
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.split("::").last().unwrap_or(name)
+    }
+}
+
+dyn_clone::clone_trait_object!(MultiEmbeddingModel);
+
+#[cfg(feature = "test-utils")]
+mock! {
+    #[derive(Debug)]
+    pub MultiEmbeddingModel {}
+
+    #[async_trait]
+    impl MultiEmbeddingModel for MultiEmbeddingModel {
+        async fn multi_embed(&self, input: Vec<String>) -> Result<MultiEmbeddings, LanguageModelError>;
+        fn name(&self) -> &'static str;
+    }
+
+    impl Clone for MultiEmbeddingModel {
+        fn clone(&self) -> Self;
+    }
+}
+
+#[async_trait]
+impl MultiEmbeddingModel for Box<dyn MultiEmbeddingModel> {
+    async fn multi_embed(&self, input: Vec<String>) -> Result<MultiEmbeddings, LanguageModelError> {
+        self.as_ref().multi_embed(input).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
+#[async_trait]
+impl MultiEmbeddingModel for Arc<dyn MultiEmbeddingModel> {
+    async fn multi_embed(&self, input: Vec<String>) -> Result<MultiEmbeddings, LanguageModelError> {
+        self.as_ref().multi_embed(input).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
+#[async_trait]
+impl MultiEmbeddingModel for &dyn MultiEmbeddingModel {
+    async fn multi_embed(&self, input: Vec<String>) -> Result<MultiEmbeddings, LanguageModelError> {
+        (*self).multi_embed(input).await
+    }
+}
+
+#[async_trait]
+/// Embeds a list of images and returns its embeddings.
+///
+/// Images are passed by their path. Models that embed images and text in the same space, like
+/// CLIP, allow images and text to be stored and searched in the same collection.
+pub trait ImageEmbeddingModel: Send + Sync + Debug + DynClone {
+    async fn embed_images(&self, images: Vec<PathBuf>) -> Result<Embeddings, LanguageModelError>;
+
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.split("::").last().unwrap_or(name)
+    }
+}
+
+dyn_clone::clone_trait_object!(ImageEmbeddingModel);
+
+#[cfg(feature = "test-utils")]
+mock! {
+    #[derive(Debug)]
+    pub ImageEmbeddingModel {}
+
+    #[async_trait]
+    impl ImageEmbeddingModel for ImageEmbeddingModel {
+        async fn embed_images(&self, images: Vec<PathBuf>) -> Result<Embeddings, LanguageModelError>;
+        fn name(&self) -> &'static str;
+    }
+
+    impl Clone for ImageEmbeddingModel {
+        fn clone(&self) -> Self;
+    }
+}
+
+#[async_trait]
+impl ImageEmbeddingModel for Box<dyn ImageEmbeddingModel> {
+    async fn embed_images(&self, images: Vec<PathBuf>) -> Result<Embeddings, LanguageModelError> {
+        self.as_ref().embed_images(images).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
+#[async_trait]
+impl ImageEmbeddingModel for Arc<dyn ImageEmbeddingModel> {
+    async fn embed_images(&self, images: Vec<PathBuf>) -> Result<Embeddings, LanguageModelError> {
+        self.as_ref().embed_images(images).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
+#[async_trait]
+impl ImageEmbeddingModel for &dyn ImageEmbeddingModel {
+    async fn embed_images(&self, images: Vec<PathBuf>) -> Result<Embeddings, LanguageModelError> {
+        (*self).embed_images(images).await
+    }
+}
+
 #[async_trait]
 /// Given a string prompt, queries an LLM
 pub trait SimplePrompt: Debug + Send + Sync + DynClone {
     // Takes a simple prompt, prompts the llm and returns the response
-    async fn prompt(&self, prompt: Prompt) -> Result<String>;
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError>;
 
     fn name(&self) -> &'static str {
         let name = std::any::type_name::<Self>();
@@ -554,7 +717,7 @@ mock! {
 
     #[async_trait]
     impl SimplePrompt for SimplePrompt {
-        async fn prompt(&self, prompt: Prompt) -> Result<String>;
+        async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError>;
         fn name(&self) -> &'static str;
     }
 
@@ -565,7 +728,7 @@ mock! {
 
 #[async_trait]
 impl SimplePrompt for Box<dyn SimplePrompt> {
-    async fn prompt(&self, prompt: Prompt) -> Result<String> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
         self.as_ref().prompt(prompt).await
     }
 
@@ -576,7 +739,7 @@ impl SimplePrompt for Box<dyn SimplePrompt> {
 
 #[async_trait]
 impl SimplePrompt for Arc<dyn SimplePrompt> {
-    async fn prompt(&self, prompt: Prompt) -> Result<String> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
         self.as_ref().prompt(prompt).await
     }
 
@@ -587,21 +750,123 @@ impl SimplePrompt for Arc<dyn SimplePrompt> {
 
 #[async_trait]
 impl SimplePrompt for &dyn SimplePrompt {
-    async fn prompt(&self, prompt: Prompt) -> Result<String> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
         (*self).prompt(prompt).await
     }
 }
 
+/// Prompts an llm for json matching a json schema
+///
+/// Providers with native support (i.e. `OpenAI` structured outputs) validate the response
+/// against the schema.
+#[async_trait]
+pub trait StructuredPrompt: Debug + Send + Sync + DynClone {
+    /// Prompts the llm and returns json matching the given schema
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<serde_json::Value, LanguageModelError>;
+
+    /// Prompts the llm and deserializes the response into `T`, using the json schema of `T`
+    async fn structured_prompt<T>(&self, prompt: Prompt) -> Result<T, LanguageModelError>
+    where
+        Self: Sized,
+        T: DeserializeOwned + JsonSchema + Send,
+    {
+        let value = self
+            .structured_prompt_dyn(prompt, schemars::schema_for!(T))
+            .await?;
+
+        serde_json::from_value(value)
+            .context("Response does not match the schema")
+            .map_err(LanguageModelError::from)
+    }
+
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.split("::").last().unwrap_or(name)
+    }
+}
+
+dyn_clone::clone_trait_object!(StructuredPrompt);
+
+#[async_trait]
+impl StructuredPrompt for Box<dyn StructuredPrompt> {
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<serde_json::Value, LanguageModelError> {
+        self.as_ref().structured_prompt_dyn(prompt, schema).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
+#[async_trait]
+impl StructuredPrompt for Arc<dyn StructuredPrompt> {
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<serde_json::Value, LanguageModelError> {
+        self.as_ref().structured_prompt_dyn(prompt, schema).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
 #[async_trait]
 /// Persists nodes
+///
+/// Errors are returned as [`PersistError`]. The stream of `batch_store` has `anyhow::Error`s,
+/// storages that classify them wrap a [`PersistError`], which can be retrieved with
+/// `downcast_ref`.
 pub trait Persist: Debug + Send + Sync + DynClone {
-    async fn setup(&self) -> Result<()>;
-    async fn store(&self, node: Node) -> Result<Node>;
+    async fn setup(&self) -> Result<(), PersistError>;
+    async fn store(&self, node: Node) -> Result<Node, PersistError>;
     async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream;
     fn batch_size(&self) -> Option<usize> {
         None
     }
 
+    /// Limits batches to an estimated payload size in bytes, see [`Node::payload_size`]
+    ///
+    /// Batches are cut when either the batch size or the payload size is reached, so that large
+    /// nodes do not exceed the maximum request size of the storage. A single node larger than
+    /// the limit is stored in a batch of its own. Only applies if a batch size is set.
+    fn max_batch_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    /// Deletes nodes by their id, i.e. to remove stale nodes when indexing incrementally
+    ///
+    /// Storages that do not support deletion return an error.
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        Err(PersistError::Unsupported(format!(
+            "Deleting {} nodes is not supported by {}",
+            ids.len(),
+            self.name()
+        )))
+    }
+
+    /// Deletes all nodes with metadata matching every entry of the filter, i.e. all nodes of a
+    /// source document
+    ///
+    /// Storages that do not support deletion return an error.
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        Err(PersistError::Unsupported(format!(
+            "Deleting nodes by metadata ({:?}) is not supported by {}",
+            filter.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            self.name()
+        )))
+    }
+
     fn name(&self) -> &'static str {
         let name = std::any::type_name::<Self>();
         name.split("::").last().unwrap_or(name)
@@ -617,10 +882,13 @@ mock! {
 
     #[async_trait]
     impl Persist for Persist {
-        async fn setup(&self) -> Result<()>;
-        async fn store(&self, node: Node) -> Result<Node>;
+        async fn setup(&self) -> Result<(), PersistError>;
+        async fn store(&self, node: Node) -> Result<Node, PersistError>;
         async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream;
         fn batch_size(&self) -> Option<usize>;
+        fn max_batch_bytes(&self) -> Option<usize>;
+        async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError>;
+        async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError>;
 
         fn name(&self) -> &'static str;
     }
@@ -632,10 +900,10 @@ mock! {
 
 #[async_trait]
 impl Persist for Box<dyn Persist> {
-    async fn setup(&self) -> Result<()> {
+    async fn setup(&self) -> Result<(), PersistError> {
         self.as_ref().setup().await
     }
-    async fn store(&self, node: Node) -> Result<Node> {
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
         self.as_ref().store(node).await
     }
     async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
@@ -644,6 +912,15 @@ impl Persist for Box<dyn Persist> {
     fn batch_size(&self) -> Option<usize> {
         self.as_ref().batch_size()
     }
+    fn max_batch_bytes(&self) -> Option<usize> {
+        self.as_ref().max_batch_bytes()
+    }
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        self.as_ref().delete(ids).await
+    }
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        self.as_ref().delete_by_metadata(filter).await
+    }
     fn name(&self) -> &'static str {
         self.as_ref().name()
     }
@@ -651,10 +928,10 @@ impl Persist for Box<dyn Persist> {
 
 #[async_trait]
 impl Persist for Arc<dyn Persist> {
-    async fn setup(&self) -> Result<()> {
+    async fn setup(&self) -> Result<(), PersistError> {
         self.as_ref().setup().await
     }
-    async fn store(&self, node: Node) -> Result<Node> {
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
         self.as_ref().store(node).await
     }
     async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
@@ -663,6 +940,15 @@ impl Persist for Arc<dyn Persist> {
     fn batch_size(&self) -> Option<usize> {
         self.as_ref().batch_size()
     }
+    fn max_batch_bytes(&self) -> Option<usize> {
+        self.as_ref().max_batch_bytes()
+    }
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        self.as_ref().delete(ids).await
+    }
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        self.as_ref().delete_by_metadata(filter).await
+    }
     fn name(&self) -> &'static str {
         self.as_ref().name()
     }
@@ -670,10 +956,10 @@ impl Persist for Arc<dyn Persist> {
 
 #[async_trait]
 impl Persist for &dyn Persist {
-    async fn setup(&self) -> Result<()> {
+    async fn setup(&self) -> Result<(), PersistError> {
         (*self).setup().await
     }
-    async fn store(&self, node: Node) -> Result<Node> {
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
         (*self).store(node).await
     }
     async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
@@ -682,6 +968,57 @@ impl Persist for &dyn Persist {
     fn batch_size(&self) -> Option<usize> {
         (*self).batch_size()
     }
+    fn max_batch_bytes(&self) -> Option<usize> {
+        (*self).max_batch_bytes()
+    }
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        (*self).delete(ids).await
+    }
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        (*self).delete_by_metadata(filter).await
+    }
+}
+
+/// Streams all stored nodes out of a storage, i.e. for backups or migrations between storages
+///
+/// Nodes are streamed with their chunk, metadata and vectors. Implementations page through the
+/// storage lazily, so the stream can be consumed without loading everything into memory.
+pub trait Scroll: Debug + Send + Sync + DynClone {
+    fn scroll(&self) -> IndexingStream;
+
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.split("::").last().unwrap_or(name)
+    }
+}
+
+dyn_clone::clone_trait_object!(Scroll);
+
+impl Scroll for Box<dyn Scroll> {
+    fn scroll(&self) -> IndexingStream {
+        self.as_ref().scroll()
+    }
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
+impl Scroll for Arc<dyn Scroll> {
+    fn scroll(&self) -> IndexingStream {
+        self.as_ref().scroll()
+    }
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
+impl Scroll for &dyn Scroll {
+    fn scroll(&self) -> IndexingStream {
+        (*self).scroll()
+    }
+    fn name(&self) -> &'static str {
+        (*self).name()
+    }
 }
 
 /// Allows for passing defaults from the pipeline to the transformer
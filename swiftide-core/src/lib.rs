@@ -2,21 +2,43 @@
 
 pub mod agent_traits;
 pub mod chat_completion;
+pub mod cost;
+pub mod errors;
+pub mod events;
 mod indexing_defaults;
 mod indexing_stream;
 pub mod indexing_traits;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
+pub mod model_provider;
 mod node;
+pub mod normalize;
 mod query;
 mod query_stream;
 pub mod query_traits;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod rate_limit;
+pub mod reranking;
+pub mod response_cache;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod retry;
 mod search_strategies;
 pub mod type_aliases;
 
 pub mod document;
+mod embedding_prefixes;
 pub mod prompt;
 pub mod template;
+pub mod template_engine;
+pub mod tokenizer;
 pub use type_aliases::*;
 
+pub use embedding_prefixes::EmbeddingPrefixes;
+
+/// Cooperatively cancels a running pipeline or agent
+pub use tokio_util::sync::CancellationToken;
+
 mod metadata;
 mod query_evaluation;
 
@@ -27,6 +49,7 @@ pub use crate::indexing_traits::*;
 pub use crate::query_traits::*;
 
 pub mod indexing {
+    pub use crate::errors::{LoaderError, NodeCacheError, PersistError};
     pub use crate::indexing_defaults::*;
     pub use crate::indexing_stream::IndexingStream;
     pub use crate::indexing_traits::*;
@@ -36,6 +59,7 @@ pub mod indexing {
 
 pub mod querying {
     pub use crate::document::*;
+    pub use crate::errors::RetrieveError;
     pub use crate::query::*;
     pub use crate::query_evaluation::*;
     pub use crate::query_stream::*;
@@ -3,12 +3,25 @@
 //! Typically metadata is used to extract or generate additional information about the node
 //!
 //! Internally it uses a `BTreeMap` to store the key-value pairs, to ensure the data is sorted.
+//!
+//! Values are json, typed values can be read and written with [`Metadata::get_as`] and
+//! [`Metadata::try_insert`]. A [`MetadataSchema`] declares the types of fields, so that storages
+//! with typed columns can validate metadata before persisting it.
 use std::collections::{btree_map::IntoValues, BTreeMap};
 
-use serde::Deserializer;
+use anyhow::{Context as _, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 
 use crate::util::debug_long_utf8;
 
+lazy_static! {
+    static ref RFC3339: Regex =
+        Regex::new(r"^\d{4}-\d{2}-\d{2}[Tt ]\d{2}:\d{2}:\d{2}(\.\d+)?([Zz]|[+-]\d{2}:\d{2})$")
+            .unwrap();
+}
+
 #[derive(Clone, Default, PartialEq, Eq)]
 pub struct Metadata {
     inner: BTreeMap<String, serde_json::Value>,
@@ -50,6 +63,39 @@ impl Metadata {
         self.inner.get(key.as_ref())
     }
 
+    /// Deserializes the value of a field into `T`
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be deserialized into `T`
+    pub fn get_as<T: DeserializeOwned>(&self, key: impl AsRef<str>) -> Result<Option<T>> {
+        let key = key.as_ref();
+
+        self.inner
+            .get(key)
+            .map(|value| serde_json::from_value(value.clone()))
+            .transpose()
+            .with_context(|| format!("Invalid value for metadata field `{key}`"))
+    }
+
+    /// Serializes the value and inserts it
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be serialized
+    pub fn try_insert<K, V>(&mut self, key: K, value: &V) -> Result<()>
+    where
+        K: Into<String>,
+        V: Serialize + ?Sized,
+    {
+        let key = key.into();
+        let value = serde_json::to_value(value)
+            .with_context(|| format!("Failed to serialize metadata field `{key}`"))?;
+
+        self.inner.insert(key, value);
+        Ok(())
+    }
+
     pub fn into_values(self) -> IntoValues<String, serde_json::Value> {
         self.inner.into_values()
     }
@@ -160,6 +206,107 @@ impl serde::Serialize for Metadata {
     }
 }
 
+/// The type of a field in a [`MetadataSchema`]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, strum_macros::Display,
+)]
+#[non_exhaustive]
+pub enum MetadataFieldType {
+    String,
+    /// A signed or unsigned integer
+    Integer,
+    /// Any number
+    Float,
+    Boolean,
+    /// A string with an RFC 3339 date and time, i.e. `2025-01-01T12:00:00Z`
+    Timestamp,
+    /// Any json value
+    Json,
+}
+
+impl MetadataFieldType {
+    /// Returns true if the json value is of this type
+    pub fn matches(self, value: &serde_json::Value) -> bool {
+        match self {
+            MetadataFieldType::String => value.is_string(),
+            MetadataFieldType::Integer => value.is_i64() || value.is_u64(),
+            MetadataFieldType::Float => value.is_number(),
+            MetadataFieldType::Boolean => value.is_boolean(),
+            MetadataFieldType::Timestamp => value.as_str().is_some_and(|v| RFC3339.is_match(v)),
+            MetadataFieldType::Json => true,
+        }
+    }
+}
+
+/// Declares the types of metadata fields
+///
+/// Storages with typed columns use the schema to create the columns, and validate metadata
+/// before persisting it instead of storing everything as text. Fields that are not in the schema
+/// are not validated, and fields in the schema are optional.
+///
+/// # Example
+///
+/// ```
+/// # use swiftide_core::indexing::{Metadata, MetadataFieldType, MetadataSchema};
+/// let schema = MetadataSchema::new()
+///     .with_field("title", MetadataFieldType::String)
+///     .with_field("priority", MetadataFieldType::Integer);
+///
+/// assert!(schema.validate(&Metadata::from([("priority", 1)])).is_ok());
+/// assert!(schema.validate(&Metadata::from([("priority", "high")])).is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataSchema {
+    fields: BTreeMap<String, MetadataFieldType>,
+}
+
+impl MetadataSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a field to the schema, replacing its type if it already exists
+    #[must_use]
+    pub fn with_field(mut self, name: impl Into<String>, field_type: MetadataFieldType) -> Self {
+        self.fields.insert(name.into(), field_type);
+        self
+    }
+
+    /// Returns the type of the field, if it is in the schema
+    pub fn field_type(&self, name: impl AsRef<str>) -> Option<MetadataFieldType> {
+        self.fields.get(name.as_ref()).copied()
+    }
+
+    /// Iterates over the fields and their types, sorted by name
+    pub fn fields(&self) -> impl Iterator<Item = (&str, MetadataFieldType)> {
+        self.fields
+            .iter()
+            .map(|(name, field_type)| (name.as_str(), *field_type))
+    }
+
+    /// Validates the types of the fields in the metadata that are in the schema
+    ///
+    /// # Errors
+    ///
+    /// Errors with every field that does not match its type
+    pub fn validate(&self, metadata: &Metadata) -> Result<()> {
+        let invalid = metadata
+            .iter()
+            .filter_map(|(name, value)| {
+                let field_type = self.field_type(name)?;
+                (!field_type.matches(value))
+                    .then(|| format!("`{name}` should be {field_type}, got {value}"))
+            })
+            .collect::<Vec<_>>();
+
+        if !invalid.is_empty() {
+            anyhow::bail!("Invalid metadata: {}", invalid.join(", "));
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -213,4 +360,43 @@ mod tests {
         let values: Vec<_> = metadata.into_values().collect();
         assert_eq!(values, vec![json!("value1"), json!("value2")]);
     }
+
+    #[test]
+    fn test_typed_get_and_insert() {
+        let mut metadata = Metadata::default();
+        metadata.try_insert("tags", &["a", "b"]).unwrap();
+        metadata.insert("priority", "high");
+
+        assert_eq!(
+            metadata.get_as::<Vec<String>>("tags").unwrap(),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(metadata.get_as::<u64>("missing").unwrap(), None);
+        assert!(metadata.get_as::<u64>("priority").is_err());
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let schema = MetadataSchema::new()
+            .with_field("priority", MetadataFieldType::Integer)
+            .with_field("score", MetadataFieldType::Float)
+            .with_field("published", MetadataFieldType::Boolean)
+            .with_field("created_at", MetadataFieldType::Timestamp);
+
+        let valid = Metadata::from([
+            ("priority", json!(1)),
+            ("score", json!(0.5)),
+            ("published", json!(true)),
+            ("created_at", json!("2025-01-01T12:00:00Z")),
+            ("not in schema", json!("anything")),
+        ]);
+        assert!(schema.validate(&valid).is_ok());
+
+        let invalid =
+            Metadata::from([("priority", json!("1")), ("created_at", json!("yesterday"))]);
+        assert_eq!(
+            schema.validate(&invalid).unwrap_err().to_string(),
+            "Invalid metadata: `created_at` should be Timestamp, got \"yesterday\", `priority` should be Integer, got \"1\""
+        );
+    }
 }
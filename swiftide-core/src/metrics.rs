@@ -0,0 +1,497 @@
+//! Records metrics of language models, indexing steps and storage with the `metrics` crate
+//!
+//! Wrap any client, transformer or storage in [`Metered`] to record its latency, throughput and
+//! errors. Metrics are recorded with the `metrics` facade, so they go to whichever recorder is
+//! installed. With the `prometheus` feature, [`install_prometheus_exporter`] installs a recorder
+//! with a scrape endpoint.
+//!
+//! | Metric | Type | Labels |
+//! |--------|------|--------|
+//! | `swiftide_llm_requests_total` | counter | `client`, `operation`, `status` |
+//! | `swiftide_llm_request_duration_seconds` | histogram | `client`, `operation` |
+//! | `swiftide_llm_tokens_total` | counter | `client`, `kind` (`input` or `output`) |
+//! | `swiftide_pipeline_nodes_total` | counter | `step`, `status` |
+//! | `swiftide_pipeline_step_duration_seconds` | histogram | `step` |
+//! | `swiftide_store_operations_total` | counter | `store`, `collection`, `operation`, `status` |
+//! | `swiftide_store_duration_seconds` | histogram | `store`, `collection`, `operation` |
+//! | `swiftide_store_nodes_total` | counter | `store`, `collection`, `status` |
+//! | `swiftide_store_batch_size` | histogram | `store`, `collection`, `operation` |
+//!
+//! The `client`, `step` and `store` labels default to the name of the wrapped type, and can be
+//! set with [`Metered::with_label`]. The `status` label is either `ok` or `error`.
+//!
+//! The storage integrations record their inserts and queries themselves when their `metrics`
+//! feature is enabled, with the collection or table as `collection`. Custom `Persist` and
+//! `Retrieve` implementations can do the same with [`measure_store_operation`].
+//!
+//! # Example
+//!
+//! ```
+//! # use swiftide_core::{metrics::Metered, SimplePrompt};
+//! # fn wrap(client: impl SimplePrompt + Clone) -> impl SimplePrompt {
+//! Metered::new(client).with_label("openai")
+//! # }
+//! ```
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use schemars::schema::RootSchema;
+
+use crate::{
+    chat_completion::{
+        errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+        ChatCompletionStream,
+    },
+    errors::PersistError,
+    indexing::{IndexingStream, Metadata, Node},
+    prompt::Prompt,
+    BatchableTransformer, EmbeddingModel, EmbeddingPrefixes, Embeddings, Persist, SimplePrompt,
+    SparseEmbeddingModel, SparseEmbeddings, StructuredPrompt, Transformer,
+};
+
+pub const LLM_REQUESTS_TOTAL: &str = "swiftide_llm_requests_total";
+pub const LLM_REQUEST_DURATION_SECONDS: &str = "swiftide_llm_request_duration_seconds";
+pub const LLM_TOKENS_TOTAL: &str = "swiftide_llm_tokens_total";
+pub const PIPELINE_NODES_TOTAL: &str = "swiftide_pipeline_nodes_total";
+pub const PIPELINE_STEP_DURATION_SECONDS: &str = "swiftide_pipeline_step_duration_seconds";
+pub const STORE_OPERATIONS_TOTAL: &str = "swiftide_store_operations_total";
+pub const STORE_DURATION_SECONDS: &str = "swiftide_store_duration_seconds";
+pub const STORE_NODES_TOTAL: &str = "swiftide_store_nodes_total";
+pub const STORE_BATCH_SIZE: &str = "swiftide_store_batch_size";
+
+/// Registers the descriptions and units of all metrics with the installed recorder
+pub fn describe() {
+    describe_counter!(LLM_REQUESTS_TOTAL, "Requests to language models");
+    describe_histogram!(
+        LLM_REQUEST_DURATION_SECONDS,
+        Unit::Seconds,
+        "Latency of requests to language models"
+    );
+    describe_counter!(
+        LLM_TOKENS_TOTAL,
+        "Tokens used by chat completions, as reported by the provider"
+    );
+    describe_counter!(PIPELINE_NODES_TOTAL, "Nodes processed by indexing steps");
+    describe_histogram!(
+        PIPELINE_STEP_DURATION_SECONDS,
+        Unit::Seconds,
+        "Latency of indexing steps"
+    );
+    describe_counter!(
+        STORE_OPERATIONS_TOTAL,
+        "Inserts, queries and other operations on storage"
+    );
+    describe_histogram!(
+        STORE_DURATION_SECONDS,
+        Unit::Seconds,
+        "Latency of operations on storage"
+    );
+    describe_counter!(STORE_NODES_TOTAL, "Nodes stored");
+    describe_histogram!(
+        STORE_BATCH_SIZE,
+        Unit::Count,
+        "Number of nodes per storage operation"
+    );
+}
+
+/// Installs a Prometheus recorder with a scrape endpoint on the given address, and describes all
+/// metrics
+///
+/// Must be called from within a tokio runtime.
+///
+/// # Errors
+///
+/// Errors if the endpoint cannot be bound or a recorder is already installed.
+#[cfg(feature = "prometheus")]
+pub fn install_prometheus_exporter(address: impl Into<std::net::SocketAddr>) -> anyhow::Result<()> {
+    metrics_exporter_prometheus::PrometheusBuilder::new()
+        .with_http_listener(address)
+        .install()?;
+    describe();
+
+    Ok(())
+}
+
+/// Wraps a language model client, transformer or storage and records its metrics
+///
+/// Implements the same traits as the value it wraps, so it can be used as a drop in replacement.
+/// Streaming chat completions are passed through without metrics.
+#[derive(Debug, Clone)]
+pub struct Metered<T> {
+    inner: T,
+    label: String,
+}
+
+impl<T> Metered<T> {
+    pub fn new(inner: T) -> Self {
+        let name = std::any::type_name::<T>();
+        let name = name.split('<').next().unwrap_or(name);
+
+        Self {
+            inner,
+            label: name.rsplit("::").next().unwrap_or(name).to_string(),
+        }
+    }
+
+    /// Sets the label to record metrics with, defaults to the name of the wrapped type
+    #[must_use]
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    async fn measure_request<R>(
+        &self,
+        operation: &'static str,
+        request: impl Future<Output = Result<R, LanguageModelError>>,
+    ) -> Result<R, LanguageModelError> {
+        let start = Instant::now();
+        let result = request.await;
+
+        histogram!(LLM_REQUEST_DURATION_SECONDS, "client" => self.label.clone(), "operation" => operation)
+            .record(start.elapsed().as_secs_f64());
+        counter!(
+            LLM_REQUESTS_TOTAL,
+            "client" => self.label.clone(),
+            "operation" => operation,
+            "status" => status(&result)
+        )
+        .increment(1);
+
+        result
+    }
+
+    fn record_step(&self, start: Instant, nodes: usize, status: &'static str) {
+        histogram!(PIPELINE_STEP_DURATION_SECONDS, "step" => self.label.clone())
+            .record(start.elapsed().as_secs_f64());
+        counter!(PIPELINE_NODES_TOTAL, "step" => self.label.clone(), "status" => status)
+            .increment(nodes as u64);
+    }
+
+    fn record_store(&self, start: Instant, operation: &'static str, nodes: usize, success: bool) {
+        record_store_operation(&self.label, "", operation, nodes, start.elapsed(), success);
+    }
+}
+
+/// Records an operation on storage, i.e. an insert or query
+///
+/// The collection is the collection, table or index operated on, or empty if unknown. For
+/// operations on nodes, the number of nodes is recorded as the batch size.
+pub fn record_store_operation(
+    store: &str,
+    collection: &str,
+    operation: &'static str,
+    nodes: usize,
+    elapsed: Duration,
+    success: bool,
+) {
+    let status = if success { "ok" } else { "error" };
+    let store = store.to_string();
+    let collection = collection.to_string();
+
+    histogram!(
+        STORE_DURATION_SECONDS,
+        "store" => store.clone(),
+        "collection" => collection.clone(),
+        "operation" => operation
+    )
+    .record(elapsed.as_secs_f64());
+    counter!(
+        STORE_OPERATIONS_TOTAL,
+        "store" => store.clone(),
+        "collection" => collection.clone(),
+        "operation" => operation,
+        "status" => status
+    )
+    .increment(1);
+
+    if nodes > 0 {
+        #[allow(clippy::cast_precision_loss)]
+        let batch_size = nodes as f64;
+
+        histogram!(
+            STORE_BATCH_SIZE,
+            "store" => store.clone(),
+            "collection" => collection.clone(),
+            "operation" => operation
+        )
+        .record(batch_size);
+        counter!(
+            STORE_NODES_TOTAL,
+            "store" => store,
+            "collection" => collection,
+            "status" => status
+        )
+        .increment(nodes as u64);
+    }
+}
+
+/// Measures an operation on storage, see [`record_store_operation`]
+///
+/// # Errors
+///
+/// Returns the error of the operation
+pub async fn measure_store_operation<T, E>(
+    store: &str,
+    collection: &str,
+    operation: &'static str,
+    nodes: usize,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let start = Instant::now();
+    let result = future.await;
+
+    record_store_operation(
+        store,
+        collection,
+        operation,
+        nodes,
+        start.elapsed(),
+        result.is_ok(),
+    );
+
+    result
+}
+
+fn status<R, E>(result: &Result<R, E>) -> &'static str {
+    if result.is_ok() {
+        "ok"
+    } else {
+        "error"
+    }
+}
+
+#[async_trait]
+impl<T: SimplePrompt + Clone> SimplePrompt for Metered<T> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+        self.measure_request("prompt", self.inner.prompt(prompt))
+            .await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: StructuredPrompt + Clone> StructuredPrompt for Metered<T> {
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<serde_json::Value, LanguageModelError> {
+        self.measure_request(
+            "structured_prompt",
+            self.inner.structured_prompt_dyn(prompt, schema),
+        )
+        .await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: EmbeddingModel + Clone> EmbeddingModel for Metered<T> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
+        self.measure_request("embed", self.inner.embed(input)).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.inner.prefixes()
+    }
+}
+
+#[async_trait]
+impl<T: SparseEmbeddingModel + Clone> SparseEmbeddingModel for Metered<T> {
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError> {
+        self.measure_request("sparse_embed", self.inner.sparse_embed(input))
+            .await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: ChatCompletion + Clone> ChatCompletion for Metered<T> {
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let response = self
+            .measure_request("complete", self.inner.complete(request))
+            .await?;
+
+        if let Some(usage) = response.usage() {
+            counter!(LLM_TOKENS_TOTAL, "client" => self.label.clone(), "kind" => "input")
+                .increment(u64::from(usage.prompt_tokens));
+            counter!(LLM_TOKENS_TOTAL, "client" => self.label.clone(), "kind" => "output")
+                .increment(u64::from(usage.completion_tokens));
+        }
+
+        Ok(response)
+    }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        self.inner.complete_stream(request).await
+    }
+}
+
+#[async_trait]
+impl<T: Transformer + Clone> Transformer for Metered<T> {
+    async fn transform_node(&self, node: Node) -> anyhow::Result<Node> {
+        let start = Instant::now();
+        let result = self.inner.transform_node(node).await;
+        self.record_step(start, 1, status(&result));
+
+        result
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.inner.concurrency()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: BatchableTransformer + Clone> BatchableTransformer for Metered<T> {
+    /// Records the number of nodes in the batch, and the latency until the resulting stream is
+    /// returned
+    async fn batch_transform(&self, nodes: Vec<Node>) -> IndexingStream {
+        let start = Instant::now();
+        let count = nodes.len();
+        let stream = self.inner.batch_transform(nodes).await;
+        self.record_step(start, count, "ok");
+
+        stream
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.inner.concurrency()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.inner.batch_size()
+    }
+}
+
+#[async_trait]
+impl<T: Persist + Clone> Persist for Metered<T> {
+    async fn setup(&self) -> Result<(), PersistError> {
+        self.inner.setup().await
+    }
+
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        let start = Instant::now();
+        let result = self.inner.store(node).await;
+        self.record_store(start, "store", 1, result.is_ok());
+
+        result
+    }
+
+    /// Records the number of nodes in the batch, and the latency until the resulting stream is
+    /// returned
+    async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
+        let start = Instant::now();
+        let count = nodes.len();
+        let stream = self.inner.batch_store(nodes).await;
+        self.record_store(start, "batch_store", count, true);
+
+        stream
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.inner.batch_size()
+    }
+
+    fn max_batch_bytes(&self) -> Option<usize> {
+        self.inner.max_batch_bytes()
+    }
+
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        self.inner.delete(ids).await
+    }
+
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        self.inner.delete_by_metadata(filter).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct Echo;
+
+    #[async_trait]
+    impl SimplePrompt for Echo {
+        async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+            Ok(prompt.render().await.unwrap())
+        }
+    }
+
+    #[test]
+    fn test_default_label() {
+        assert_eq!(Metered::new(Echo).label(), "Echo");
+        assert_eq!(Metered::new(Echo).with_label("echo").label(), "echo");
+    }
+
+    #[tokio::test]
+    async fn test_records_requests() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+
+        let _guard = metrics::set_default_local_recorder(&recorder);
+
+        Metered::new(Echo).prompt("hello".into()).await.unwrap();
+
+        let requests = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .find(|(key, ..)| key.key().name() == LLM_REQUESTS_TOTAL)
+            .map(|(.., value)| value);
+
+        assert_eq!(requests, Some(DebugValue::Counter(1)));
+    }
+}
@@ -0,0 +1,349 @@
+//! Intercepts requests to and responses from language models
+//!
+//! A [`Middleware`] can modify requests before they are sent and inspect or modify responses
+//! before they are returned, i.e. to redact sensitive data or to keep an audit log. All hooks
+//! default to passing the value through, so a middleware only implements what it needs.
+//!
+//! Wrap a client once in [`WithMiddleware`] to run any number of middleware on every request.
+//! Requests pass through the middleware in the order they were added, responses in reverse order.
+//!
+//! # Example
+//!
+//! ```
+//! # use async_trait::async_trait;
+//! # use swiftide_core::{middleware::{Middleware, WithMiddleware}, prompt::Prompt, chat_completion::errors::LanguageModelError, SimplePrompt};
+//! #[derive(Debug, Clone)]
+//! struct AuditLog;
+//!
+//! #[async_trait]
+//! impl Middleware for AuditLog {
+//!     async fn after_prompt(&self, response: String) -> Result<String, LanguageModelError> {
+//!         tracing::info!(response, "Prompted");
+//!         Ok(response)
+//!     }
+//! }
+//!
+//! # fn wrap(client: impl SimplePrompt + Clone) -> impl SimplePrompt {
+//! WithMiddleware::new(client).with_middleware(AuditLog)
+//! # }
+//! ```
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+use futures_util::StreamExt as _;
+use schemars::schema::RootSchema;
+
+use crate::{
+    chat_completion::{
+        errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+        ChatCompletionStream,
+    },
+    prompt::Prompt,
+    EmbeddingModel, EmbeddingPrefixes, Embeddings, SimplePrompt, SparseEmbeddingModel,
+    SparseEmbeddings, StructuredPrompt,
+};
+
+/// Hooks that run before requests are sent to and after responses are received from a language
+/// model
+///
+/// Returning an error from a hook fails the request.
+#[async_trait]
+pub trait Middleware: Send + Sync + Debug + DynClone {
+    /// Runs before a prompt is sent, for both simple and structured prompts
+    async fn before_prompt(&self, prompt: Prompt) -> Result<Prompt, LanguageModelError> {
+        Ok(prompt)
+    }
+
+    async fn after_prompt(&self, response: String) -> Result<String, LanguageModelError> {
+        Ok(response)
+    }
+
+    async fn after_structured_prompt(
+        &self,
+        response: serde_json::Value,
+    ) -> Result<serde_json::Value, LanguageModelError> {
+        Ok(response)
+    }
+
+    /// Runs before a chat completion is sent, streaming or not
+    async fn before_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionRequest, LanguageModelError> {
+        Ok(request)
+    }
+
+    /// Runs after a chat completion is received
+    ///
+    /// When streaming, runs for every response in the stream.
+    async fn after_completion(
+        &self,
+        response: ChatCompletionResponse,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        Ok(response)
+    }
+
+    /// Runs before texts are embedded, for both dense and sparse embeddings
+    async fn before_embed(&self, input: Vec<String>) -> Result<Vec<String>, LanguageModelError> {
+        Ok(input)
+    }
+
+    /// Runs when the language model returns an error
+    async fn on_error(&self, _error: &LanguageModelError) {}
+}
+
+dyn_clone::clone_trait_object!(Middleware);
+
+/// Wraps a language model client and runs middleware on its requests and responses
+#[derive(Debug, Clone)]
+pub struct WithMiddleware<T> {
+    inner: T,
+    middleware: Vec<Box<dyn Middleware>>,
+}
+
+impl<T> WithMiddleware<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Adds a middleware, which runs after the middleware added before it
+    #[must_use]
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Box::new(middleware));
+        self
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    async fn on_error<R>(
+        &self,
+        result: Result<R, LanguageModelError>,
+    ) -> Result<R, LanguageModelError> {
+        if let Err(err) = &result {
+            for middleware in &self.middleware {
+                middleware.on_error(err).await;
+            }
+        }
+        result
+    }
+
+    async fn before_prompt(&self, mut prompt: Prompt) -> Result<Prompt, LanguageModelError> {
+        for middleware in &self.middleware {
+            prompt = middleware.before_prompt(prompt).await?;
+        }
+        Ok(prompt)
+    }
+
+    async fn before_embed(
+        &self,
+        mut input: Vec<String>,
+    ) -> Result<Vec<String>, LanguageModelError> {
+        for middleware in &self.middleware {
+            input = middleware.before_embed(input).await?;
+        }
+        Ok(input)
+    }
+
+    async fn before_completion(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionRequest, LanguageModelError> {
+        let mut request = request.clone();
+        for middleware in &self.middleware {
+            request = middleware.before_completion(request).await?;
+        }
+        Ok(request)
+    }
+}
+
+/// Runs the `after_completion` hooks of all middleware, in reverse order
+async fn after_completion(
+    middleware: &[Box<dyn Middleware>],
+    mut response: ChatCompletionResponse,
+) -> Result<ChatCompletionResponse, LanguageModelError> {
+    for middleware in middleware.iter().rev() {
+        response = middleware.after_completion(response).await?;
+    }
+    Ok(response)
+}
+
+#[async_trait]
+impl<T: SimplePrompt + Clone> SimplePrompt for WithMiddleware<T> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+        let prompt = self.before_prompt(prompt).await?;
+        let mut response = self.on_error(self.inner.prompt(prompt).await).await?;
+
+        for middleware in self.middleware.iter().rev() {
+            response = middleware.after_prompt(response).await?;
+        }
+        Ok(response)
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: StructuredPrompt + Clone> StructuredPrompt for WithMiddleware<T> {
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<serde_json::Value, LanguageModelError> {
+        let prompt = self.before_prompt(prompt).await?;
+        let mut response = self
+            .on_error(self.inner.structured_prompt_dyn(prompt, schema).await)
+            .await?;
+
+        for middleware in self.middleware.iter().rev() {
+            response = middleware.after_structured_prompt(response).await?;
+        }
+        Ok(response)
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: EmbeddingModel + Clone> EmbeddingModel for WithMiddleware<T> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
+        let input = self.before_embed(input).await?;
+        self.on_error(self.inner.embed(input).await).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.inner.prefixes()
+    }
+}
+
+#[async_trait]
+impl<T: SparseEmbeddingModel + Clone> SparseEmbeddingModel for WithMiddleware<T> {
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError> {
+        let input = self.before_embed(input).await?;
+        self.on_error(self.inner.sparse_embed(input).await).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: ChatCompletion + Clone> ChatCompletion for WithMiddleware<T> {
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let request = self.before_completion(request).await?;
+        let response = self.on_error(self.inner.complete(&request).await).await?;
+
+        after_completion(&self.middleware, response).await
+    }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        let request = match self.before_completion(request).await {
+            Ok(request) => request,
+            Err(err) => return Box::pin(futures_util::stream::once(async move { Err(err) })),
+        };
+
+        let middleware = self.middleware.clone();
+        let stream = self.inner.complete_stream(&request).await;
+
+        Box::pin(stream.then(move |response| {
+            let middleware = middleware.clone();
+            async move {
+                match response {
+                    Ok(response) => after_completion(&middleware, response).await,
+                    Err(err) => {
+                        for middleware in &middleware {
+                            middleware.on_error(&err).await;
+                        }
+                        Err(err)
+                    }
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct Echo;
+
+    #[async_trait]
+    impl SimplePrompt for Echo {
+        async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+            Ok(prompt.render().await?)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct Redact;
+
+    #[async_trait]
+    impl Middleware for Redact {
+        async fn before_prompt(&self, prompt: Prompt) -> Result<Prompt, LanguageModelError> {
+            Ok(prompt
+                .render()
+                .await?
+                .replace("secret", "[redacted]")
+                .into())
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct Record(&'static str, Arc<Mutex<Vec<String>>>);
+
+    #[async_trait]
+    impl Middleware for Record {
+        async fn after_prompt(&self, response: String) -> Result<String, LanguageModelError> {
+            self.1
+                .lock()
+                .unwrap()
+                .push(format!("{}: {response}", self.0));
+            Ok(response)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runs_middleware_in_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let client = WithMiddleware::new(Echo)
+            .with_middleware(Record("first", log.clone()))
+            .with_middleware(Redact)
+            .with_middleware(Record("last", log.clone()));
+
+        let response = client.prompt("my secret".into()).await.unwrap();
+
+        assert_eq!(response, "my [redacted]");
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["last: my [redacted]", "first: my [redacted]"]
+        );
+    }
+}
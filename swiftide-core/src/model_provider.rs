@@ -0,0 +1,85 @@
+//! Checks the health of and lists the models of language model providers
+//!
+//! Implemented by the integrations with a models api. Use [`ModelProvider::health_check`] at
+//! startup to verify the configuration, like the api key, before running a pipeline or agent, and
+//! [`ModelProvider::list_models`] to let users pick a model.
+//!
+//! # Example
+//!
+//! ```
+//! # use swiftide_core::model_provider::ModelProvider;
+//! # async fn check(provider: &dyn ModelProvider) -> anyhow::Result<()> {
+//! provider.health_check().await?;
+//!
+//! for model in provider.list_models().await? {
+//!     println!("{}", model.id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
+
+use crate::chat_completion::errors::LanguageModelError;
+
+/// A model available from a provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// The id to use as model in requests
+    pub id: String,
+    /// The organization owning the model, if reported by the provider
+    pub owned_by: Option<String>,
+    /// Unix timestamp of when the model was created, if reported by the provider
+    pub created: Option<u64>,
+}
+
+impl ModelInfo {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            owned_by: None,
+            created: None,
+        }
+    }
+}
+
+/// A language model provider that can list its models
+#[async_trait]
+pub trait ModelProvider: Send + Sync + Debug + DynClone {
+    /// Lists the models available with the current configuration
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LanguageModelError>;
+
+    /// Checks that the provider is reachable and the configuration is valid
+    ///
+    /// By default, this lists the models and discards them.
+    async fn health_check(&self) -> Result<(), LanguageModelError> {
+        self.list_models().await.map(|_| ())
+    }
+}
+
+dyn_clone::clone_trait_object!(ModelProvider);
+
+#[async_trait]
+impl ModelProvider for Box<dyn ModelProvider> {
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LanguageModelError> {
+        (**self).list_models().await
+    }
+
+    async fn health_check(&self) -> Result<(), LanguageModelError> {
+        (**self).health_check().await
+    }
+}
+
+#[async_trait]
+impl ModelProvider for &dyn ModelProvider {
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LanguageModelError> {
+        (**self).list_models().await
+    }
+
+    async fn health_check(&self) -> Result<(), LanguageModelError> {
+        (**self).health_check().await
+    }
+}
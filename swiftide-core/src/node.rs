@@ -18,18 +18,20 @@
 //! individual units of data. It is particularly useful in scenarios where metadata and data chunks
 //! need to be processed together.
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::Debug,
     hash::{Hash, Hasher},
-    os::unix::ffi::OsStrExt,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+use anyhow::Result;
 use derive_builder::Builder;
 use itertools::Itertools;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::{metadata::Metadata, util::debug_long_utf8, Embedding, SparseEmbedding};
+use crate::{
+    metadata::Metadata, util::debug_long_utf8, Embedding, MultiEmbedding, SparseEmbedding,
+};
 
 /// Represents a unit of data in the indexing process.
 ///
@@ -50,6 +52,9 @@ pub struct Node {
     /// Optional sparse vector representation of embedded data.
     #[builder(default)]
     pub sparse_vectors: Option<HashMap<EmbeddedField, SparseEmbedding>>,
+    /// Optional multi-vector (embedding per token) representation of embedded data.
+    #[builder(default)]
+    pub multi_vectors: Option<HashMap<EmbeddedField, MultiEmbedding>>,
     /// Metadata associated with the node.
     #[builder(default)]
     pub metadata: Metadata,
@@ -64,6 +69,84 @@ pub struct Node {
     pub offset: usize,
 }
 
+/// A flat, serializable representation of a stored node, i.e. for exports and backups
+///
+/// Vectors are keyed by the name of their field, see [`EmbeddedField::field_name`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeRecord {
+    pub id: uuid::Uuid,
+    pub path: PathBuf,
+    pub chunk: String,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub vectors: BTreeMap<String, Embedding>,
+    #[serde(default)]
+    pub sparse_vectors: BTreeMap<String, SparseEmbedding>,
+    #[serde(default)]
+    pub multi_vectors: BTreeMap<String, MultiEmbedding>,
+}
+
+impl From<Node> for NodeRecord {
+    fn from(node: Node) -> Self {
+        Self {
+            id: node.id(),
+            vectors: node
+                .vectors
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(field, vector)| (field.field_name(), vector))
+                .collect(),
+            sparse_vectors: node
+                .sparse_vectors
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(field, vector)| (field.field_name(), vector))
+                .collect(),
+            multi_vectors: node
+                .multi_vectors
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(field, vector)| (field.field_name(), vector))
+                .collect(),
+            path: node.path,
+            chunk: node.chunk,
+            metadata: node.metadata,
+        }
+    }
+}
+
+impl TryFrom<NodeRecord> for Node {
+    type Error = anyhow::Error;
+
+    fn try_from(record: NodeRecord) -> Result<Self, Self::Error> {
+        let vectors = record
+            .vectors
+            .into_iter()
+            .map(|(field, vector)| Ok((field.parse()?, vector)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        let sparse_vectors = record
+            .sparse_vectors
+            .into_iter()
+            .map(|(field, vector)| Ok((field.parse()?, vector)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        let multi_vectors = record
+            .multi_vectors
+            .into_iter()
+            .map(|(field, vector)| Ok((field.parse()?, vector)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+        NodeBuilder::default()
+            .path(record.path)
+            .chunk(record.chunk)
+            .metadata(record.metadata)
+            .maybe_vectors((!vectors.is_empty()).then_some(vectors))
+            .maybe_sparse_vectors((!sparse_vectors.is_empty()).then_some(sparse_vectors))
+            .maybe_multi_vectors((!multi_vectors.is_empty()).then_some(multi_vectors))
+            .build()
+    }
+}
+
 impl NodeBuilder {
     pub fn maybe_sparse_vectors(
         &mut self,
@@ -80,6 +163,14 @@ impl NodeBuilder {
         self.vectors = Some(vectors);
         self
     }
+
+    pub fn maybe_multi_vectors(
+        &mut self,
+        multi_vectors: Option<HashMap<EmbeddedField, MultiEmbedding>>,
+    ) -> &mut Self {
+        self.multi_vectors = Some(multi_vectors);
+        self
+    }
 }
 
 impl Debug for Node {
@@ -117,11 +208,35 @@ impl Debug for Node {
                     })
                     .join(","),
             )
+            .field(
+                "multi_vectors",
+                &self
+                    .multi_vectors
+                    .iter()
+                    .flat_map(HashMap::iter)
+                    .map(|(embed_type, vec)| format!("'{embed_type}': {}", vec.len()))
+                    .join(","),
+            )
             .field("embed_mode", &self.embed_mode)
             .finish()
     }
 }
 
+/// Extensions of images that can be embedded
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "tiff"];
+
+/// Returns true if the path has one of the [`IMAGE_EXTENSIONS`], ignoring case
+pub fn is_image_path(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|image| image.eq_ignore_ascii_case(ext))
+        })
+}
+
 impl Node {
     /// Builds a new instance of `Node`, returning a `NodeBuilder`. Copies
     /// over the fields from the provided `Node`.
@@ -132,6 +247,7 @@ impl Node {
             .metadata(node.metadata.clone())
             .maybe_vectors(node.vectors.clone())
             .maybe_sparse_vectors(node.sparse_vectors.clone())
+            .maybe_multi_vectors(node.multi_vectors.clone())
             .embed_mode(node.embed_mode)
             .original_size(node.original_size)
             .offset(node.offset)
@@ -156,11 +272,42 @@ impl Node {
         }
     }
 
+    /// Returns true if the path of the node is an image, by its extension
+    ///
+    /// Image nodes are embedded from the image at their path, see
+    /// [`crate::ImageEmbeddingModel`]. Their chunk can hold a caption or be empty.
+    pub fn is_image(&self) -> bool {
+        is_image_path(&self.path)
+    }
+
     pub fn with_metadata(&mut self, metadata: impl Into<Metadata>) -> &mut Self {
         self.metadata = metadata.into();
         self
     }
 
+    /// Deserializes the value of a metadata field into `T`
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be deserialized into `T`
+    pub fn get_metadata<T: DeserializeOwned>(&self, key: impl AsRef<str>) -> Result<Option<T>> {
+        self.metadata.get_as(key)
+    }
+
+    /// Serializes the value and sets it as a metadata field
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be serialized
+    pub fn set_metadata<V: Serialize + ?Sized>(
+        &mut self,
+        key: impl Into<String>,
+        value: &V,
+    ) -> Result<&mut Self> {
+        self.metadata.try_insert(key, value)?;
+        Ok(self)
+    }
+
     pub fn with_vectors(
         &mut self,
         vectors: impl Into<HashMap<EmbeddedField, Embedding>>,
@@ -177,6 +324,14 @@ impl Node {
         self
     }
 
+    pub fn with_multi_vectors(
+        &mut self,
+        multi_vectors: impl Into<HashMap<EmbeddedField, MultiEmbedding>>,
+    ) -> &mut Self {
+        self.multi_vectors = Some(multi_vectors.into());
+        self
+    }
+
     /// Creates embeddable data depending on chosen `EmbedMode`.
     ///
     /// # Returns
@@ -236,10 +391,53 @@ impl Node {
     ///
     /// WARN: Does not memoize the id. Use sparingly.
     pub fn id(&self) -> uuid::Uuid {
-        let bytes = [self.path.as_os_str().as_bytes(), self.chunk.as_bytes()].concat();
+        let bytes = [
+            self.path.as_os_str().as_encoded_bytes(),
+            self.chunk.as_bytes(),
+        ]
+        .concat();
 
         uuid::Uuid::new_v3(&uuid::Uuid::NAMESPACE_OID, &bytes)
     }
+
+    /// Estimates the size of the node in bytes when sent to a storage
+    ///
+    /// Counts the chunk, path, metadata as json and vectors as 4 byte floats. Storages encode
+    /// nodes differently, the estimate is meant for sizing batches, not as an exact size.
+    pub fn payload_size(&self) -> usize {
+        const FLOAT_SIZE: usize = std::mem::size_of::<f32>();
+
+        let metadata = serde_json::to_vec(&self.metadata).map_or(0, |json| json.len());
+
+        let vectors = self
+            .vectors
+            .iter()
+            .flat_map(HashMap::values)
+            .map(|vector| vector.len() * FLOAT_SIZE)
+            .sum::<usize>();
+
+        let sparse_vectors = self
+            .sparse_vectors
+            .iter()
+            .flat_map(HashMap::values)
+            .map(|sparse| (sparse.indices.len() + sparse.values.len()) * FLOAT_SIZE)
+            .sum::<usize>();
+
+        let multi_vectors = self
+            .multi_vectors
+            .iter()
+            .flat_map(HashMap::values)
+            .flatten()
+            .map(|vector| vector.len() * FLOAT_SIZE)
+            .sum::<usize>();
+
+        self.chunk.len()
+            + self.path.as_os_str().len()
+            + metadata
+            + vectors
+            + sparse_vectors
+            + multi_vectors
+    }
 }
 
 impl Hash for Node {
@@ -296,6 +494,27 @@ impl EmbeddedField {
     pub fn field_name(&self) -> String {
         format!("{self}")
     }
+
+    /// Returns the name of the field when it would be a multi-vector
+    pub fn multi_field_name(&self) -> String {
+        format!("{self}_multi")
+    }
+}
+
+/// Parses the field from its name, the inverse of [`EmbeddedField::field_name`]
+impl std::str::FromStr for EmbeddedField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Combined" => Ok(EmbeddedField::Combined),
+            "Chunk" => Ok(EmbeddedField::Chunk),
+            _ => s
+                .strip_prefix("Metadata: ")
+                .map(|name| EmbeddedField::Metadata(name.to_string()))
+                .ok_or_else(|| anyhow::anyhow!("Unknown embedded field: {s}")),
+        }
+    }
 }
 
 #[allow(clippy::from_over_into)]
@@ -318,6 +537,14 @@ mod tests {
         assert_eq!(embedded_field.sparse_field_name(), expected[1]);
     }
 
+    #[test_case("cat.png", true)]
+    #[test_case("photos/cat.JPG", true)]
+    #[test_case("README.md", false)]
+    #[test_case("png", false)]
+    fn test_is_image_path(path: &str, expected: bool) {
+        assert_eq!(is_image_path(path), expected);
+    }
+
     #[test]
     fn test_debugging_node_with_utf8_char_boundary() {
         let node = Node::new("🦀".repeat(101));
@@ -329,6 +556,28 @@ mod tests {
         let _ = format!("{node:?}");
     }
 
+    #[test]
+    fn test_typed_metadata() {
+        let mut node = Node::new("chunk");
+        node.set_metadata("priority", &2).unwrap();
+
+        assert_eq!(node.get_metadata::<u32>("priority").unwrap(), Some(2));
+        assert!(node.get_metadata::<bool>("priority").is_err());
+    }
+
+    #[test]
+    fn test_payload_size() {
+        let node = Node::builder()
+            .path("a.rs")
+            .chunk("chunk")
+            .vectors(HashMap::from([(EmbeddedField::Combined, vec![1.0; 10])]))
+            .build()
+            .unwrap();
+
+        // chunk, path, empty metadata as json and 10 floats
+        assert_eq!(node.payload_size(), 5 + 4 + 2 + 40);
+    }
+
     #[test]
     fn test_build_from_other_without_vectors() {
         let original_node = Node::new("test_chunk")
@@ -368,4 +617,39 @@ mod tests {
 
         assert_eq!(original_node, new_node);
     }
+
+    #[test_case(EmbeddedField::Combined)]
+    #[test_case(EmbeddedField::Chunk)]
+    #[test_case(EmbeddedField::Metadata("test: nested".into()))]
+    fn test_embedded_field_from_str(embedded_field: EmbeddedField) {
+        assert_eq!(
+            embedded_field
+                .field_name()
+                .parse::<EmbeddedField>()
+                .unwrap(),
+            embedded_field
+        );
+    }
+
+    #[test]
+    fn test_node_record_roundtrip() {
+        let node = Node::builder()
+            .path("src/main.rs")
+            .chunk("fn main() {}")
+            .metadata(Metadata::from(("lang", "rust")))
+            .vectors(HashMap::from([
+                (EmbeddedField::Combined, vec![1.0, 2.0]),
+                (EmbeddedField::Metadata("lang".into()), vec![3.0]),
+            ]))
+            .build()
+            .unwrap();
+
+        let record = NodeRecord::from(node.clone());
+        assert_eq!(record.id, node.id());
+        assert_eq!(record.vectors["Metadata: lang"], vec![3.0]);
+
+        let json = serde_json::to_string(&record).unwrap();
+        let record: NodeRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(Node::try_from(record).unwrap(), node);
+    }
 }
@@ -0,0 +1,170 @@
+//! Normalizes and truncates embeddings
+//!
+//! Wrap any embedding model in [`Normalized`] to L2-normalize its vectors, so that cosine
+//! similarity and dot product are equivalent. Optionally truncate the vectors to fewer dimensions
+//! first, for models trained with matryoshka representation learning, like `OpenAI`'s
+//! `text-embedding-3` models. This allows storing embeddings of larger models in a store with
+//! fixed, smaller dimensions.
+//!
+//! # Example
+//!
+//! ```
+//! # use swiftide_core::{normalize::Normalized, EmbeddingModel};
+//! # fn wrap(model: impl EmbeddingModel + Clone) -> impl EmbeddingModel {
+//! Normalized::new(model).with_dimensions(256)
+//! # }
+//! ```
+use async_trait::async_trait;
+
+use crate::{
+    chat_completion::errors::LanguageModelError, EmbeddingModel, EmbeddingPrefixes, Embeddings,
+};
+
+/// Wraps an embedding model, truncates its vectors and L2-normalizes them
+///
+/// Truncation happens before normalization, as a truncated matryoshka embedding is no longer
+/// normalized.
+#[derive(Debug, Clone)]
+pub struct Normalized<T> {
+    inner: T,
+    dimensions: Option<usize>,
+    normalize: bool,
+}
+
+impl<T> Normalized<T> {
+    /// Wraps an embedding model, normalizing its vectors without truncating them
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            dimensions: None,
+            normalize: true,
+        }
+    }
+
+    /// Truncates vectors to the given number of dimensions
+    ///
+    /// Embedding fails if the model returns vectors with fewer dimensions.
+    #[must_use]
+    pub fn with_dimensions(mut self, dimensions: impl Into<Option<usize>>) -> Self {
+        self.dimensions = dimensions.into();
+        self
+    }
+
+    /// Enables or disables normalization, enabled by default
+    #[must_use]
+    pub fn with_normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    pub fn dimensions(&self) -> Option<usize> {
+        self.dimensions
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn process(&self, mut vector: Vec<f32>) -> Result<Vec<f32>, LanguageModelError> {
+        if let Some(dimensions) = self.dimensions {
+            if vector.len() < dimensions {
+                return Err(LanguageModelError::permanent(format!(
+                    "Cannot truncate embedding with {} dimensions to {dimensions}",
+                    vector.len()
+                )));
+            }
+            vector.truncate(dimensions);
+        }
+
+        if self.normalize {
+            l2_normalize(&mut vector);
+        }
+
+        Ok(vector)
+    }
+}
+
+/// Scales a vector to unit length, zero vectors are left as is
+pub fn l2_normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+
+    if norm > f32::EPSILON {
+        for value in vector.iter_mut() {
+            *value /= norm;
+        }
+    }
+}
+
+#[async_trait]
+impl<T: EmbeddingModel + Clone> EmbeddingModel for Normalized<T> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
+        self.inner
+            .embed(input)
+            .await?
+            .into_iter()
+            .map(|vector| self.process(vector))
+            .collect()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.inner.prefixes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct FixedEmbeddings(Vec<f32>);
+
+    #[async_trait]
+    impl EmbeddingModel for FixedEmbeddings {
+        async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
+            Ok(input.iter().map(|_| self.0.clone()).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_normalizes_embeddings() {
+        let model = Normalized::new(FixedEmbeddings(vec![3.0, 4.0]));
+
+        let embeddings = model.embed(vec!["a".into(), "b".into()]).await.unwrap();
+
+        assert_eq!(embeddings, vec![vec![0.6, 0.8], vec![0.6, 0.8]]);
+    }
+
+    #[tokio::test]
+    async fn test_truncates_before_normalizing() {
+        let model = Normalized::new(FixedEmbeddings(vec![3.0, 4.0, 12.0])).with_dimensions(2);
+
+        let embeddings = model.embed(vec!["a".into()]).await.unwrap();
+
+        assert_eq!(embeddings, vec![vec![0.6, 0.8]]);
+    }
+
+    #[tokio::test]
+    async fn test_fails_on_too_few_dimensions() {
+        let model = Normalized::new(FixedEmbeddings(vec![1.0])).with_dimensions(2);
+
+        let err = model.embed(vec!["a".into()]).await.unwrap_err();
+
+        assert!(matches!(err, LanguageModelError::PermanentError(_)));
+    }
+
+    #[test]
+    fn test_leaves_zero_vectors() {
+        let mut vector = vec![0.0, 0.0];
+        l2_normalize(&mut vector);
+
+        assert_eq!(vector, vec![0.0, 0.0]);
+    }
+}
@@ -19,6 +19,13 @@
 //!
 //! It's recommended to precompile your templates.
 //!
+//! Templates can also be loaded from a directory of `.prompt.md` files at startup with
+//! [`Template::load_dir`]. Loaded templates can use tera partials, includes and inheritance, and
+//! override the default prompts of transformers by file name, i.e. `metadata_qa_text.prompt.md`.
+//!
+//! Prompts that conflict with tera's syntax can be rendered with another
+//! [`TemplateEngine`][crate::template_engine::TemplateEngine] using [`Prompt::with_engine`].
+//!
 //! # Example
 //!
 //! ```
@@ -31,15 +38,18 @@
 //! assert_eq!(prompt.render().await.unwrap(), "hello swiftide");
 //! # }
 //! ```
+use std::sync::Arc;
+
 use anyhow::Result;
 
-use crate::{node::Node, template::Template};
+use crate::{node::Node, template::Template, template_engine::TemplateEngine};
 
 /// A Prompt can be used with large language models to prompt.
 #[derive(Clone, Debug)]
 pub struct Prompt {
     template: Template,
     context: Option<tera::Context>,
+    engine: Option<Arc<dyn TemplateEngine>>,
 }
 
 #[deprecated(
@@ -74,6 +84,14 @@ impl Prompt {
         self
     }
 
+    /// Renders the prompt with another engine than tera, i.e.
+    /// [`Literal`][crate::template_engine::Literal] for prompts that contain `{{ }}` literally
+    #[must_use]
+    pub fn with_engine(mut self, engine: impl TemplateEngine + 'static) -> Self {
+        self.engine = Some(Arc::new(engine));
+        self
+    }
+
     /// Renders a prompt
     ///
     /// If no context is provided, the prompt will be rendered as is.
@@ -83,10 +101,17 @@ impl Prompt {
     /// See `Template::render`
     pub async fn render(&self) -> Result<String> {
         if let Some(context) = &self.context {
-            self.template.render(context).await
+            match &self.engine {
+                Some(engine) => {
+                    self.template
+                        .render_with_engine(engine.as_ref(), context)
+                        .await
+                }
+                None => self.template.render(context).await,
+            }
         } else {
             match &self.template {
-                Template::CompiledTemplate(_) => {
+                Template::CompiledTemplate(_) | Template::Named { .. } => {
                     self.template.render(&tera::Context::default()).await
                 }
                 Template::String(string) => Ok(string.clone()),
@@ -101,6 +126,7 @@ impl From<&'static str> for Prompt {
         Prompt {
             template: Template::Static(prompt),
             context: None,
+            engine: None,
         }
     }
 }
@@ -110,6 +136,7 @@ impl From<String> for Prompt {
         Prompt {
             template: Template::String(prompt),
             context: None,
+            engine: None,
         }
     }
 }
@@ -119,6 +146,7 @@ impl From<&Template> for Prompt {
         Prompt {
             template: template.clone(),
             context: None,
+            engine: None,
         }
     }
 }
@@ -224,6 +252,65 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_load_templates_from_dir() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("partials")).unwrap();
+        std::fs::write(
+            dir.child("partials/greeting.prompt.md"),
+            "hello {{ world }}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.child("base.prompt.md"),
+            "{% block content %}{% endblock %}!",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.child("test_load_dir.prompt.md"),
+            r#"{% extends "base.prompt.md" %}{% block content %}{% include "partials/greeting.prompt.md" %}{% endblock %}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.child("ignored.txt"), "ignored").unwrap();
+
+        let mut names = Template::load_dir(dir.path()).await.unwrap();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "base.prompt.md",
+                "partials/greeting.prompt.md",
+                "test_load_dir.prompt.md"
+            ]
+        );
+
+        let prompt = Template::named("test_load_dir.prompt.md", "default {{ world }}")
+            .to_prompt()
+            .with_context_value("world", "swiftide");
+        assert_eq!(prompt.render().await.unwrap(), "hello swiftide!");
+
+        let prompt = Template::named("test_not_overridden.prompt.md", "default {{ world }}")
+            .to_prompt()
+            .with_context_value("world", "swiftide");
+        assert_eq!(prompt.render().await.unwrap(), "default swiftide");
+    }
+
+    #[tokio::test]
+    async fn test_render_with_engine() {
+        let prompt = Prompt::from("hello {{world}}")
+            .with_context_value("world", "swiftide")
+            .with_engine(crate::template_engine::Literal);
+
+        assert_eq!(prompt.render().await.unwrap(), "hello {{world}}");
+
+        let prompt = Template::named("test_engine_not_overridden.prompt.md", "hello {{world}}")
+            .to_prompt()
+            .with_context_value("world", "swiftide")
+            .with_engine(crate::template_engine::Literal);
+
+        assert_eq!(prompt.render().await.unwrap(), "hello {{world}}");
+    }
+
     #[tokio::test]
     async fn test_assume_rendered_unless_context_methods_called() {
         let prompt = Prompt::from("hello {{world}}");
@@ -7,7 +7,9 @@
 //! `states::Answered`: The query has been answered
 use derive_builder::Builder;
 
-use crate::{document::Document, util::debug_long_utf8, Embedding, SparseEmbedding};
+use crate::{
+    document::Document, util::debug_long_utf8, Embedding, MultiEmbedding, SparseEmbedding,
+};
 
 /// A query is the main object going through a query pipeline
 ///
@@ -34,6 +36,10 @@ pub struct Query<STATE: QueryState> {
     #[builder(default)]
     pub sparse_embedding: Option<SparseEmbedding>,
 
+    /// An embedding per token, for late interaction retrieval
+    #[builder(default)]
+    pub multi_embedding: Option<MultiEmbedding>,
+
     /// Documents the query will operate on
     ///
     /// A query can retrieve multiple times, accumulating documents
@@ -76,6 +82,7 @@ impl<STATE: Clone + QueryState> Query<STATE> {
             transformation_history: self.transformation_history,
             embedding: self.embedding,
             sparse_embedding: self.sparse_embedding,
+            multi_embedding: self.multi_embedding,
             documents: self.documents,
         }
     }
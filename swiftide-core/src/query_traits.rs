@@ -5,6 +5,8 @@ use async_trait::async_trait;
 use dyn_clone::DynClone;
 
 use crate::{
+    document::Document,
+    errors::RetrieveError,
     query::{
         states::{self, Retrieved},
         Query,
@@ -95,13 +97,16 @@ impl TransformQuery for Arc<dyn TransformQuery> {
 pub trait SearchStrategy: Clone + Send + Sync + Default {}
 
 /// Can retrieve documents given a SearchStrategy
+///
+/// Closures returning an `anyhow::Result` can be used as a retriever, their errors are
+/// considered permanent.
 #[async_trait]
 pub trait Retrieve<S: SearchStrategy>: Send + Sync + DynClone {
     async fn retrieve(
         &self,
         search_strategy: &S,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>>;
+    ) -> Result<Query<states::Retrieved>, RetrieveError>;
 
     fn name(&self) -> &'static str {
         let name = std::any::type_name::<Self>();
@@ -117,7 +122,7 @@ impl<S: SearchStrategy> Retrieve<S> for Box<dyn Retrieve<S>> {
         &self,
         search_strategy: &S,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         self.as_ref().retrieve(search_strategy, query).await
     }
 
@@ -132,7 +137,7 @@ impl<S: SearchStrategy> Retrieve<S> for Arc<dyn Retrieve<S>> {
         &self,
         search_strategy: &S,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         self.as_ref().retrieve(search_strategy, query).await
     }
 
@@ -151,8 +156,8 @@ where
         &self,
         search_strategy: &S,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
-        (self)(search_strategy, query)
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        (self)(search_strategy, query).map_err(Into::into)
     }
 }
 
@@ -218,6 +223,63 @@ impl TransformResponse for Arc<dyn TransformResponse> {
     }
 }
 
+/// Reranks retrieved documents by their relevance to a query
+///
+/// Typically backed by a cross-encoder model, either hosted or local. Use
+/// `query::Pipeline::then_rerank` to use a reranker in a query pipeline.
+#[async_trait]
+pub trait Rerank: Send + Sync + DynClone {
+    /// Returns the documents ordered by relevance to the query, most relevant first
+    ///
+    /// Implementations may drop documents, i.e. when limited to a top n or below a threshold.
+    async fn rerank(&self, query: &str, documents: Vec<Document>) -> Result<Vec<Document>>;
+
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.split("::").last().unwrap_or(name)
+    }
+}
+
+dyn_clone::clone_trait_object!(Rerank);
+
+#[cfg(feature = "test-utils")]
+mock! {
+    #[derive(Debug)]
+    pub Rerank {}
+
+    #[async_trait]
+    impl Rerank for Rerank {
+        async fn rerank(&self, query: &str, documents: Vec<Document>) -> Result<Vec<Document>>;
+        fn name(&self) -> &'static str;
+    }
+
+    impl Clone for Rerank {
+        fn clone(&self) -> Self;
+    }
+}
+
+#[async_trait]
+impl Rerank for Box<dyn Rerank> {
+    async fn rerank(&self, query: &str, documents: Vec<Document>) -> Result<Vec<Document>> {
+        self.as_ref().rerank(query, documents).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
+#[async_trait]
+impl Rerank for Arc<dyn Rerank> {
+    async fn rerank(&self, query: &str, documents: Vec<Document>) -> Result<Vec<Document>> {
+        self.as_ref().rerank(query, documents).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+}
+
 /// Can answer the original query
 #[async_trait]
 pub trait Answer: Send + Sync + DynClone {
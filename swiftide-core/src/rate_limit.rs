@@ -0,0 +1,323 @@
+//! Limits the requests and tokens per minute sent to a language model
+//!
+//! Wrap any client in [`RateLimited`] to stay within the quota of a provider. Tokens are
+//! estimated before sending the request with an [`EstimateTokens`] implementation, by default
+//! [`ApproximateTokens`].
+//!
+//! Clones share the same limits, so a single rate limited client can be used by multiple
+//! pipelines and agents at once.
+//!
+//! # Example
+//!
+//! ```
+//! # use swiftide_core::{rate_limit::{RateLimit, RateLimited}, SimplePrompt};
+//! # fn wrap(client: impl SimplePrompt + Clone) -> impl SimplePrompt {
+//! RateLimited::new(
+//!     client,
+//!     RateLimit::default()
+//!         .with_requests_per_minute(500)
+//!         .with_tokens_per_minute(200_000),
+//! )
+//! # }
+//! ```
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use schemars::schema::RootSchema;
+use tokio::time::Instant;
+
+use crate::{
+    chat_completion::{
+        errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+        ChatCompletionStream,
+    },
+    prompt::Prompt,
+    tokenizer::{ApproximateTokens, Estimatable, EstimateTokens},
+    EmbeddingModel, EmbeddingPrefixes, Embeddings, SimplePrompt, SparseEmbeddingModel,
+    SparseEmbeddings, StructuredPrompt,
+};
+
+/// Requests and tokens allowed per minute, unlimited if not set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_minute: Option<usize>,
+    pub tokens_per_minute: Option<usize>,
+    window: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            window: Duration::from_mins(1),
+        }
+    }
+}
+
+impl RateLimit {
+    /// Requests with a limit of zero fail, as they would never be sent
+    #[must_use]
+    pub fn with_requests_per_minute(mut self, requests_per_minute: usize) -> Self {
+        self.requests_per_minute = Some(requests_per_minute);
+        self
+    }
+
+    #[must_use]
+    pub fn with_tokens_per_minute(mut self, tokens_per_minute: usize) -> Self {
+        self.tokens_per_minute = Some(tokens_per_minute);
+        self
+    }
+}
+
+/// Keeps track of the requests in the current window
+#[derive(Debug)]
+struct Limiter {
+    limit: RateLimit,
+    requests: Mutex<VecDeque<(Instant, usize)>>,
+}
+
+impl Limiter {
+    /// Waits until a request with the given tokens fits within the limits
+    ///
+    /// A request with more tokens than the limit is let through once the window is empty, so
+    /// that it does not wait forever.
+    async fn acquire(&self, tokens: usize) {
+        loop {
+            let wait_until = {
+                let mut requests = self.requests.lock().expect("poisoned lock");
+                let now = Instant::now();
+
+                while requests
+                    .front()
+                    .is_some_and(|(at, _)| now.duration_since(*at) >= self.limit.window)
+                {
+                    requests.pop_front();
+                }
+
+                let used_tokens = requests.iter().map(|(_, tokens)| tokens).sum::<usize>();
+                let requests_available = self
+                    .limit
+                    .requests_per_minute
+                    .is_none_or(|limit| requests.len() < limit);
+                let tokens_available = self
+                    .limit
+                    .tokens_per_minute
+                    .is_none_or(|limit| requests.is_empty() || used_tokens + tokens <= limit);
+
+                if requests_available && tokens_available {
+                    requests.push_back((now, tokens));
+                    return;
+                }
+
+                requests
+                    .front()
+                    .map(|(at, _)| *at + self.limit.window)
+                    .expect("requests cannot be empty when limited")
+            };
+
+            tracing::debug!(
+                wait = ?wait_until.duration_since(Instant::now()),
+                "Rate limit reached, waiting"
+            );
+            tokio::time::sleep_until(wait_until).await;
+        }
+    }
+}
+
+/// Wraps a language model client and limits the requests and tokens per minute
+///
+/// Only the input is counted towards the token limit, as the output is not known in advance.
+#[derive(Debug, Clone)]
+pub struct RateLimited<T> {
+    inner: T,
+    limiter: Arc<Limiter>,
+    estimator: Box<dyn EstimateTokens>,
+}
+
+impl<T> RateLimited<T> {
+    pub fn new(inner: T, limit: RateLimit) -> Self {
+        Self {
+            inner,
+            limiter: Arc::new(Limiter {
+                limit,
+                requests: Mutex::new(VecDeque::new()),
+            }),
+            estimator: Box::new(ApproximateTokens::default()),
+        }
+    }
+
+    /// Sets the estimator used for the token limit
+    #[must_use]
+    pub fn with_estimator(mut self, estimator: impl EstimateTokens + 'static) -> Self {
+        self.estimator = Box::new(estimator);
+        self
+    }
+
+    pub fn limit(&self) -> &RateLimit {
+        &self.limiter.limit
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    async fn acquire(&self, value: &dyn Estimatable) -> Result<(), LanguageModelError> {
+        if self.limiter.limit.requests_per_minute == Some(0) {
+            return Err(LanguageModelError::permanent(
+                "Rate limit of 0 requests per minute does not allow any requests",
+            ));
+        }
+
+        let tokens = if self.limiter.limit.tokens_per_minute.is_some() {
+            self.estimator.estimate(value).await?
+        } else {
+            0
+        };
+
+        self.limiter.acquire(tokens).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: SimplePrompt + Clone> SimplePrompt for RateLimited<T> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+        self.acquire(&prompt).await?;
+        self.inner.prompt(prompt).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: StructuredPrompt + Clone> StructuredPrompt for RateLimited<T> {
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<serde_json::Value, LanguageModelError> {
+        self.acquire(&prompt).await?;
+        self.inner.structured_prompt_dyn(prompt, schema).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: EmbeddingModel + Clone> EmbeddingModel for RateLimited<T> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
+        self.acquire(&input).await?;
+        self.inner.embed(input).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.inner.prefixes()
+    }
+}
+
+#[async_trait]
+impl<T: SparseEmbeddingModel + Clone> SparseEmbeddingModel for RateLimited<T> {
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError> {
+        self.acquire(&input).await?;
+        self.inner.sparse_embed(input).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: ChatCompletion + Clone> ChatCompletion for RateLimited<T> {
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        self.acquire(request).await?;
+        self.inner.complete(request).await
+    }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        if let Err(err) = self.acquire(request).await {
+            return Box::pin(futures_util::stream::once(async move { Err(err) }));
+        }
+        self.inner.complete_stream(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(limit: RateLimit) -> Limiter {
+        Limiter {
+            limit: RateLimit {
+                window: Duration::from_millis(100),
+                ..limit
+            },
+            requests: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits_requests() {
+        let limiter = limiter(RateLimit::default().with_requests_per_minute(2));
+        let start = Instant::now();
+
+        limiter.acquire(0).await;
+        limiter.acquire(0).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        limiter.acquire(0).await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_limits_tokens() {
+        let limiter = limiter(RateLimit::default().with_tokens_per_minute(10));
+        let start = Instant::now();
+
+        limiter.acquire(6).await;
+        limiter.acquire(4).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        limiter.acquire(1).await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_lets_oversized_requests_through_on_empty_window() {
+        let limiter = limiter(RateLimit::default().with_tokens_per_minute(10));
+        let start = Instant::now();
+
+        limiter.acquire(100).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_zero_requests_per_minute_fails() {
+        let rate_limited = RateLimited::new((), RateLimit::default().with_requests_per_minute(0));
+
+        let err = rate_limited
+            .acquire(&vec!["hello".to_string()])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LanguageModelError::PermanentError(_)));
+    }
+}
@@ -0,0 +1,63 @@
+//! Helpers shared by implementations of [`Rerank`](crate::Rerank)
+use crate::document::Document;
+
+/// Orders documents by the scores of a reranker, most relevant first
+///
+/// Scores are pairs of the index of a document and its score, as returned by most rerankers.
+/// Documents scoring below the `threshold` are dropped, and at most `top_n` documents are kept.
+/// Documents without a score are dropped as well.
+pub fn select_by_score(
+    documents: Vec<Document>,
+    scores: impl IntoIterator<Item = (usize, f32)>,
+    top_n: Option<usize>,
+    threshold: Option<f32>,
+) -> Vec<Document> {
+    let mut scores = scores.into_iter().collect::<Vec<_>>();
+    // Rerankers often return the scores in order, but we do not want to depend on it
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut documents = documents.into_iter().map(Some).collect::<Vec<_>>();
+    scores
+        .into_iter()
+        .filter(|(_, score)| threshold.is_none_or(|threshold| *score >= threshold))
+        .filter_map(|(index, _)| documents.get_mut(index).and_then(Option::take))
+        .take(top_n.unwrap_or(usize::MAX))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn documents() -> Vec<Document> {
+        vec!["a".into(), "b".into(), "c".into(), "d".into()]
+    }
+
+    #[test]
+    fn test_select_by_score() {
+        let scores = vec![(0, -2.0), (1, 3.0), (2, 1.0), (3, 0.5)];
+
+        assert_eq!(
+            select_by_score(documents(), scores.clone(), None, None),
+            vec![Document::from("b"), "c".into(), "d".into(), "a".into()]
+        );
+        assert_eq!(
+            select_by_score(documents(), scores.clone(), None, Some(0.0)),
+            vec![Document::from("b"), "c".into(), "d".into()]
+        );
+        assert_eq!(
+            select_by_score(documents(), scores, Some(2), Some(0.0)),
+            vec![Document::from("b"), "c".into()]
+        );
+    }
+
+    #[test]
+    fn test_select_by_score_ignores_unknown_and_duplicate_indices() {
+        let scores = vec![(7, 5.0), (1, 3.0), (1, 2.0)];
+
+        assert_eq!(
+            select_by_score(documents(), scores, Some(2), None),
+            vec![Document::from("b")]
+        );
+    }
+}
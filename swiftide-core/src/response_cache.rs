@@ -0,0 +1,363 @@
+//! Caches responses of language models
+//!
+//! Wrap a client in [`Cached`] to serve repeated, identical requests from a [`ResponseCache`]
+//! instead of calling the model again. This is useful in tests and when re-running indexing
+//! pipelines with metadata transformers.
+//!
+//! Keys are a hash of the rendered prompt or chat messages, tools and schema, the type of the
+//! wrapped client and a namespace. The model and options are not known to the wrapper; include
+//! them in the namespace so that changing them does not return stale responses.
+//!
+//! [`MemoryResponseCache`] keeps responses in memory. Redis and Redb implementations are available
+//! in `swiftide-integrations`.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::time::Duration;
+//! # use swiftide_core::{response_cache::{Cached, MemoryResponseCache}, SimplePrompt};
+//! # fn wrap(client: impl SimplePrompt + Clone) -> impl SimplePrompt {
+//! Cached::new(client, MemoryResponseCache::default())
+//!     .with_namespace("gpt-4o-mini")
+//!     .with_ttl(Duration::from_secs(60 * 60))
+//! # }
+//! ```
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+use itertools::Itertools as _;
+use schemars::schema::RootSchema;
+use serde::{de::DeserializeOwned, Serialize};
+use web_time::Instant;
+
+use crate::{
+    chat_completion::{
+        errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+        ChatCompletionStream, ToolSpec,
+    },
+    prompt::Prompt,
+    SimplePrompt, StructuredPrompt,
+};
+
+/// Stores serialized responses by key
+#[async_trait]
+pub trait ResponseCache: Send + Sync + Debug + DynClone {
+    async fn get(&self, key: &str) -> Result<Option<String>>;
+
+    /// Stores a value, expiring after `ttl` if set
+    async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<()>;
+}
+
+dyn_clone::clone_trait_object!(ResponseCache);
+
+/// Responses by key, with the time they expire
+type Responses = HashMap<String, (String, Option<Instant>)>;
+
+/// Keeps responses in memory
+///
+/// Clones share the same responses. Expired responses are removed when they are read.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResponseCache {
+    responses: Arc<Mutex<Responses>>,
+}
+
+impl MemoryResponseCache {
+    /// # Panics
+    ///
+    /// Panics if the lock is poisoned
+    pub fn len(&self) -> usize {
+        self.responses.lock().expect("poisoned lock").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the lock is poisoned
+    pub fn clear(&self) {
+        self.responses.lock().expect("poisoned lock").clear();
+    }
+}
+
+#[async_trait]
+impl ResponseCache for MemoryResponseCache {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        let mut responses = self.responses.lock().expect("poisoned lock");
+
+        match responses.get(key) {
+            Some((_, Some(expires_at))) if *expires_at <= Instant::now() => {
+                responses.remove(key);
+                Ok(None)
+            }
+            Some((value, _)) => Ok(Some(value.clone())),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<()> {
+        self.responses.lock().expect("poisoned lock").insert(
+            key.to_string(),
+            (value.to_string(), ttl.map(|ttl| Instant::now() + ttl)),
+        );
+        Ok(())
+    }
+}
+
+/// Wraps a language model client and caches its responses
+///
+/// Only successful responses are cached. Failing to read from or write to the cache is logged and
+/// otherwise ignored, the request is then sent to the client as usual.
+///
+/// Streaming chat completions are not cached.
+#[derive(Debug, Clone)]
+pub struct Cached<T> {
+    inner: T,
+    cache: Box<dyn ResponseCache>,
+    namespace: String,
+    ttl: Option<Duration>,
+    bypass: bool,
+}
+
+impl<T> Cached<T> {
+    pub fn new(inner: T, cache: impl ResponseCache + 'static) -> Self {
+        Self {
+            inner,
+            cache: Box::new(cache),
+            namespace: String::new(),
+            ttl: None,
+            bypass: false,
+        }
+    }
+
+    /// Adds a namespace to all keys, i.e. the model and its options
+    #[must_use]
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Expires responses after the given duration, responses are kept forever by default
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: impl Into<Option<Duration>>) -> Self {
+        self.ttl = ttl.into();
+        self
+    }
+
+    /// Skips reading from the cache, fresh responses are still stored
+    ///
+    /// Useful to refresh the cache without clearing it.
+    #[must_use]
+    pub fn with_bypass(mut self, bypass: bool) -> Self {
+        self.bypass = bypass;
+        self
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Hashes the request into a key unique for the wrapped client and namespace
+    fn key(&self, kind: &str, request: &str) -> String {
+        let bytes = format!(
+            "{}\n{}\n{kind}\n{request}",
+            std::any::type_name::<T>(),
+            self.namespace
+        );
+
+        uuid::Uuid::new_v3(&uuid::Uuid::NAMESPACE_OID, bytes.as_bytes()).to_string()
+    }
+
+    async fn cached<R, Fut>(
+        &self,
+        key: String,
+        operation: impl FnOnce() -> Fut,
+    ) -> Result<R, LanguageModelError>
+    where
+        R: Serialize + DeserializeOwned,
+        Fut: Future<Output = Result<R, LanguageModelError>>,
+    {
+        if !self.bypass {
+            match self.cache.get(&key).await {
+                Ok(Some(value)) => match serde_json::from_str(&value) {
+                    Ok(response) => {
+                        tracing::debug!(%key, "Response served from cache");
+                        return Ok(response);
+                    }
+                    Err(err) => tracing::warn!(%key, error = %err, "Invalid cached response"),
+                },
+                Ok(None) => (),
+                Err(err) => tracing::warn!(%key, error = %err, "Failed to read response cache"),
+            }
+        }
+
+        let response = operation().await?;
+
+        match serde_json::to_string(&response) {
+            Ok(value) => {
+                if let Err(err) = self.cache.set(&key, &value, self.ttl).await {
+                    tracing::warn!(%key, error = %err, "Failed to write response cache");
+                }
+            }
+            Err(err) => tracing::warn!(%key, error = %err, "Failed to serialize response"),
+        }
+
+        Ok(response)
+    }
+}
+
+/// Represents a chat completion request as a stable string
+///
+/// Tools are sorted by name, as their order in the request is not stable.
+fn chat_request_for_key(request: &ChatCompletionRequest) -> Result<String> {
+    let tools = request
+        .tools_spec()
+        .iter()
+        .sorted_by_key(|spec| spec.name)
+        .collect::<Vec<&ToolSpec>>();
+    let schema = request
+        .response_schema()
+        .map(serde_json::to_string)
+        .transpose()?;
+
+    Ok(format!("{:?}\n{tools:?}\n{schema:?}", request.messages()))
+}
+
+#[async_trait]
+impl<T: SimplePrompt + Clone> SimplePrompt for Cached<T> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+        let key = self.key("prompt", &prompt.render().await?);
+        self.cached(key, || self.inner.prompt(prompt)).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: StructuredPrompt + Clone> StructuredPrompt for Cached<T> {
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<serde_json::Value, LanguageModelError> {
+        let request = format!(
+            "{}\n{}",
+            prompt.render().await?,
+            serde_json::to_string(&schema).map_err(LanguageModelError::permanent)?
+        );
+        let key = self.key("structured_prompt", &request);
+
+        self.cached(key, || self.inner.structured_prompt_dyn(prompt, schema))
+            .await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: ChatCompletion + Clone> ChatCompletion for Cached<T> {
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let key = self.key("complete", &chat_request_for_key(request)?);
+        self.cached(key, || self.inner.complete(request)).await
+    }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        self.inner.complete_stream(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Debug, Clone, Default)]
+    struct CountingPrompt {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl SimplePrompt for CountingPrompt {
+        async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("{} {calls}", prompt.render().await?))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_serves_repeated_prompts_from_cache() {
+        let client = CountingPrompt::default();
+        let cached = Cached::new(client.clone(), MemoryResponseCache::default());
+
+        let first = cached.prompt("Hi".into()).await.unwrap();
+        let second = cached.prompt("Hi".into()).await.unwrap();
+        let other = cached.prompt("Hello".into()).await.unwrap();
+
+        assert_eq!(first, "Hi 0");
+        assert_eq!(second, "Hi 0");
+        assert_eq!(other, "Hello 1");
+        assert_eq!(client.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_and_bypass() {
+        let client = CountingPrompt::default();
+        let cache = MemoryResponseCache::default();
+        let cached = Cached::new(client.clone(), cache.clone()).with_namespace("model-a");
+
+        cached.prompt("Hi".into()).await.unwrap();
+        cached
+            .clone()
+            .with_namespace("model-b")
+            .prompt("Hi".into())
+            .await
+            .unwrap();
+        assert_eq!(client.calls.load(Ordering::SeqCst), 2);
+
+        let refreshed = cached
+            .clone()
+            .with_bypass(true)
+            .prompt("Hi".into())
+            .await
+            .unwrap();
+        assert_eq!(refreshed, "Hi 2");
+        assert_eq!(cached.prompt("Hi".into()).await.unwrap(), "Hi 2");
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_expires_responses() {
+        let cache = MemoryResponseCache::default();
+
+        cache
+            .set("key", "value", Some(Duration::from_millis(10)))
+            .await
+            .unwrap();
+        assert_eq!(cache.get("key").await.unwrap().as_deref(), Some("value"));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,310 @@
+//! Retries language model requests that fail with a transient error
+//!
+//! Wrap any client in [`Retryable`] to retry [`LanguageModelError::TransientError`] with jittered
+//! exponential backoff. Permanent errors and exceeded context lengths are returned immediately.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::time::Duration;
+//! # use swiftide_core::{retry::{Retryable, RetryPolicy}, SimplePrompt};
+//! # fn wrap(client: impl SimplePrompt + Clone) -> impl SimplePrompt {
+//! Retryable::new(client)
+//!     .with_policy(RetryPolicy::default().with_max_elapsed_time(Duration::from_secs(60)))
+//! # }
+//! ```
+use std::{future::Future, time::Duration};
+
+use async_trait::async_trait;
+use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
+use schemars::schema::RootSchema;
+
+use crate::{
+    chat_completion::{
+        errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+        ChatCompletionStream,
+    },
+    prompt::Prompt,
+    EmbeddingModel, EmbeddingPrefixes, Embeddings, SimplePrompt, SparseEmbeddingModel,
+    SparseEmbeddings, StructuredPrompt,
+};
+
+/// Configures the exponential backoff between retries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Delay before the first retry
+    pub initial_interval: Duration,
+    /// Factor the delay grows with after every retry
+    pub multiplier: f64,
+    /// Randomizes every delay by this factor, i.e. 0.5 is a delay between 50% and 150%
+    pub randomization_factor: f64,
+    /// Upper bound of the delay between retries
+    pub max_interval: Duration,
+    /// Gives up after this duration, retries forever if `None`
+    pub max_elapsed_time: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_secs(1),
+            multiplier: 2.0,
+            randomization_factor: 0.5,
+            max_interval: Duration::from_mins(1),
+            max_elapsed_time: Some(Duration::from_mins(5)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    #[must_use]
+    pub fn with_initial_interval(mut self, initial_interval: Duration) -> Self {
+        self.initial_interval = initial_interval;
+        self
+    }
+
+    #[must_use]
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    #[must_use]
+    pub fn with_randomization_factor(mut self, randomization_factor: f64) -> Self {
+        self.randomization_factor = randomization_factor;
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_elapsed_time(mut self, max_elapsed_time: impl Into<Option<Duration>>) -> Self {
+        self.max_elapsed_time = max_elapsed_time.into();
+        self
+    }
+
+    fn to_backoff(self) -> ExponentialBackoff {
+        ExponentialBackoffBuilder::new()
+            .with_initial_interval(self.initial_interval)
+            .with_multiplier(self.multiplier)
+            .with_randomization_factor(self.randomization_factor)
+            .with_max_interval(self.max_interval)
+            .with_max_elapsed_time(self.max_elapsed_time)
+            .build()
+    }
+}
+
+/// Wraps a language model client and retries transient errors
+///
+/// Implements the same language model traits as the client it wraps, so it can be used as a drop
+/// in replacement.
+///
+/// Streaming chat completions are not retried, as the stream might already have been partially
+/// consumed.
+#[derive(Debug, Clone)]
+pub struct Retryable<T> {
+    inner: T,
+    policy: RetryPolicy,
+}
+
+impl<T> Retryable<T> {
+    /// Wraps a client with the default retry policy
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            policy: RetryPolicy::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    async fn retry<R, F, Fut>(&self, mut operation: F) -> Result<R, LanguageModelError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, LanguageModelError>>,
+    {
+        backoff::future::retry_notify(
+            self.policy.to_backoff(),
+            || {
+                let fut = operation();
+                async move {
+                    fut.await.map_err(|err| {
+                        if err.is_transient() {
+                            backoff::Error::transient(err)
+                        } else {
+                            backoff::Error::permanent(err)
+                        }
+                    })
+                }
+            },
+            |err, delay: Duration| {
+                tracing::warn!(error = %err, ?delay, "Transient error, retrying");
+            },
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<T: SimplePrompt + Clone> SimplePrompt for Retryable<T> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+        self.retry(|| self.inner.prompt(prompt.clone())).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: StructuredPrompt + Clone> StructuredPrompt for Retryable<T> {
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<serde_json::Value, LanguageModelError> {
+        self.retry(|| {
+            self.inner
+                .structured_prompt_dyn(prompt.clone(), schema.clone())
+        })
+        .await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: EmbeddingModel + Clone> EmbeddingModel for Retryable<T> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
+        self.retry(|| self.inner.embed(input.clone())).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.inner.prefixes()
+    }
+}
+
+#[async_trait]
+impl<T: SparseEmbeddingModel + Clone> SparseEmbeddingModel for Retryable<T> {
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError> {
+        self.retry(|| self.inner.sparse_embed(input.clone())).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+#[async_trait]
+impl<T: ChatCompletion + Clone> ChatCompletion for Retryable<T> {
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        self.retry(|| self.inner.complete(request)).await
+    }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        self.inner.complete_stream(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct FailingPrompt {
+        calls: Arc<AtomicUsize>,
+        transient_failures: usize,
+        permanent: bool,
+    }
+
+    #[async_trait]
+    impl SimplePrompt for FailingPrompt {
+        async fn prompt(&self, _prompt: Prompt) -> Result<String, LanguageModelError> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst);
+
+            if self.permanent {
+                Err(LanguageModelError::permanent("invalid request"))
+            } else if calls < self.transient_failures {
+                Err(LanguageModelError::transient("rate limited"))
+            } else {
+                Ok("Hello".to_string())
+            }
+        }
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_initial_interval(Duration::from_millis(1))
+            .with_max_interval(Duration::from_millis(5))
+            .with_max_elapsed_time(Duration::from_secs(5))
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = Retryable::new(FailingPrompt {
+            calls: calls.clone(),
+            transient_failures: 2,
+            permanent: false,
+        })
+        .with_policy(fast_policy());
+
+        let response = client.prompt("Hi".into()).await.unwrap();
+
+        assert_eq!(response, "Hello");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_permanent_errors() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = Retryable::new(FailingPrompt {
+            calls: calls.clone(),
+            transient_failures: 0,
+            permanent: true,
+        })
+        .with_policy(fast_policy());
+
+        let err = client.prompt("Hi".into()).await.unwrap_err();
+
+        assert!(matches!(err, LanguageModelError::PermanentError(_)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -5,6 +5,7 @@
 
 mod custom_strategy;
 mod hybrid_search;
+mod multi_vector_search;
 mod similarity_single_embedding;
 
 pub(crate) const DEFAULT_TOP_K: u64 = 10;
@@ -12,6 +13,7 @@ pub(crate) const DEFAULT_TOP_N: u64 = 10;
 
 pub use custom_strategy::*;
 pub use hybrid_search::*;
+pub use multi_vector_search::*;
 pub use similarity_single_embedding::*;
 
 pub trait SearchFilter: Clone + Sync + Send {}
@@ -23,3 +25,7 @@ impl SearchFilter for qdrant_client::qdrant::Filter {}
 impl SearchFilter for () {}
 // Lancedb uses a string filter
 impl SearchFilter for String {}
+// Elasticsearch uses the json query dsl
+impl SearchFilter for serde_json::Value {}
+// In-memory stores filter on exact metadata matches
+impl SearchFilter for crate::metadata::Metadata {}
@@ -0,0 +1,94 @@
+use crate::{indexing::EmbeddedField, querying, Embedding};
+
+use super::DEFAULT_TOP_K;
+
+/// A late interaction search, like `ColBERT`, on the embedding per token of the current query
+///
+/// Documents are scored by [`max_sim`] of the query and their multi-vector for the field, and the
+/// `top_k` are returned.
+///
+/// Defaults to a maximum of 10 documents and `EmbeddedField::Combined` for the field.
+#[derive(Debug, Clone)]
+pub struct MultiVectorSearch {
+    /// Maximum number of documents to return
+    top_k: u64,
+
+    /// The field to use for the multi-vector
+    vector_field: EmbeddedField,
+}
+
+impl querying::SearchStrategy for MultiVectorSearch {}
+
+impl Default for MultiVectorSearch {
+    fn default() -> Self {
+        Self {
+            top_k: DEFAULT_TOP_K,
+            vector_field: EmbeddedField::Combined,
+        }
+    }
+}
+
+impl MultiVectorSearch {
+    /// Set the maximum amount of documents to be returned
+    pub fn with_top_k(&mut self, top_k: u64) -> &mut Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// Returns the maximum of documents to be returned
+    pub fn top_k(&self) -> u64 {
+        self.top_k
+    }
+
+    /// Sets the field for the multi-vector
+    ///
+    /// Defaults to `EmbeddedField::Combined`
+    pub fn with_vector_field(&mut self, vector_field: impl Into<EmbeddedField>) -> &mut Self {
+        self.vector_field = vector_field.into();
+        self
+    }
+
+    /// Returns the field for the multi-vector
+    pub fn vector_field(&self) -> &EmbeddedField {
+        &self.vector_field
+    }
+}
+
+/// Scores a document for a query by late interaction
+///
+/// Sums, for every token of the query, the highest dot product with any token of the document.
+/// Expects normalized embeddings, as returned by `ColBERT` models. Query tokens without a
+/// positive match contribute nothing.
+pub fn max_sim(query: &[Embedding], document: &[Embedding]) -> f32 {
+    query
+        .iter()
+        .map(|query_token| {
+            document
+                .iter()
+                .map(|document_token| {
+                    query_token
+                        .iter()
+                        .zip(document_token)
+                        .map(|(a, b)| a * b)
+                        .sum::<f32>()
+                })
+                .fold(0.0, f32::max)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_sim() {
+        let query = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+
+        // Every query token matches a document token
+        assert!((max_sim(&query, &[vec![1.0, 0.0], vec![0.0, 1.0]]) - 2.0).abs() < f32::EPSILON);
+        // Only the best matching document token counts
+        assert!((max_sim(&query, &[vec![1.0, 0.0], vec![0.5, 0.0]]) - 1.0).abs() < f32::EPSILON);
+        assert!(max_sim(&query, &[]).abs() < f32::EPSILON);
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use anyhow::{Context as _, Result};
 use tokio::sync::RwLock;
 
@@ -5,7 +7,7 @@ use lazy_static::lazy_static;
 use tera::Tera;
 use uuid::Uuid;
 
-use crate::prompt::Prompt;
+use crate::{prompt::Prompt, template_engine::TemplateEngine};
 
 lazy_static! {
     /// Tera repository for templates
@@ -23,12 +25,22 @@ lazy_static! {
         }
     };
 }
+
+/// Extension of template files loaded with [`Template::load_dir`]
+const TEMPLATE_EXTENSION: &str = ".prompt.md";
+
 /// A `Template` defines a template for a prompt
 #[derive(Clone, Debug)]
 pub enum Template {
     CompiledTemplate(String),
     String(String),
     Static(&'static str),
+    /// A template that can be overridden by registering a template with the same name, i.e. the
+    /// default prompts of transformers. Renders `default` if no such template is registered.
+    Named {
+        name: &'static str,
+        default: &'static str,
+    },
 }
 
 impl Template {
@@ -41,6 +53,77 @@ impl Template {
         Template::String(template.into())
     }
 
+    /// Creates a template that renders `default`, unless a template with `name` is registered in
+    /// the repository
+    pub fn named(name: &'static str, default: &'static str) -> Template {
+        Template::Named { name, default }
+    }
+
+    /// Loads all `.prompt.md` templates from a directory and its subdirectories into the
+    /// repository, and returns their names.
+    ///
+    /// Templates are named by their path relative to the directory, i.e.
+    /// `partials/context.prompt.md`, and can include, import and extend each other by that name.
+    /// Existing templates with the same name are replaced, which allows overriding the default
+    /// prompts of transformers, i.e. with a `metadata_qa_text.prompt.md` file.
+    ///
+    /// Intended to be called once at startup.
+    ///
+    /// WARN: Do not use this inside a pipeline or any form of load, as it will lock the repository
+    ///
+    /// # Errors
+    ///
+    /// Errors if the directory cannot be read or a template fails to compile
+    pub async fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<String>> {
+        let dir = dir.as_ref();
+        let mut templates = Vec::new();
+        collect_templates(dir, dir, &mut templates)
+            .with_context(|| format!("Failed to read templates from {}", dir.display()))?;
+
+        let names = templates
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        TEMPLATE_REPOSITORY
+            .write()
+            .await
+            .add_raw_templates(templates)
+            .with_context(|| format!("Failed to add templates from {}", dir.display()))?;
+
+        tracing::debug!(?names, "Loaded templates from {}", dir.display());
+
+        Ok(names)
+    }
+
+    /// Adds a template with the given name to the repository, replacing any existing template
+    /// with that name, and returns a reference to it.
+    ///
+    /// WARN: Do not use this inside a pipeline or any form of load, as it will lock the repository
+    ///
+    /// # Errors
+    ///
+    /// Errors if the template fails to compile
+    pub async fn register(name: impl Into<String>, template: impl AsRef<str>) -> Result<Template> {
+        let name = name.into();
+        TEMPLATE_REPOSITORY
+            .write()
+            .await
+            .add_raw_template(&name, template.as_ref())
+            .with_context(|| format!("Failed to add template '{name}'"))?;
+
+        Ok(Template::CompiledTemplate(name))
+    }
+
+    /// Returns true if a template with the given name is registered in the repository
+    pub async fn is_registered(name: &str) -> bool {
+        TEMPLATE_REPOSITORY
+            .read()
+            .await
+            .get_template_names()
+            .any(|registered| registered == name)
+    }
+
     /// Extends the prompt repository with a custom [`tera::Tera`] instance.
     ///
     /// If you have your own prompt templates or want to add other functionality, you can extend
@@ -86,7 +169,7 @@ impl Template {
     /// - One-off template has errors
     /// - Context is missing that is required by the template
     pub async fn render(&self, context: &tera::Context) -> Result<String> {
-        use Template::{CompiledTemplate, Static, String};
+        use Template::{CompiledTemplate, Named, Static, String};
 
         let template = match self {
             CompiledTemplate(id) => {
@@ -111,16 +194,80 @@ impl Template {
                 .context("Failed to render one-off template")?,
             Static(template) => Tera::one_off(template, context, false)
                 .context("Failed to render one-off template")?,
+            Named { name, default } => {
+                let lock = TEMPLATE_REPOSITORY.read().await;
+                if lock
+                    .get_template_names()
+                    .any(|registered| registered == *name)
+                {
+                    lock.render(name, context)
+                        .with_context(|| format!("Failed to render template '{name}'"))?
+                } else {
+                    Tera::one_off(default, context, false)
+                        .with_context(|| format!("Failed to render default template '{name}'"))?
+                }
+            }
         };
         Ok(template)
     }
 
+    /// Renders a template with another [`TemplateEngine`] than tera
+    ///
+    /// Templates compiled into the repository are rendered with tera regardless, as are named
+    /// templates that are overridden in the repository.
+    ///
+    /// # Errors
+    ///
+    /// See [`Template::render`] and [`TemplateEngine::render`]
+    pub async fn render_with_engine(
+        &self,
+        engine: &dyn TemplateEngine,
+        context: &tera::Context,
+    ) -> Result<String> {
+        match self {
+            Template::String(template) => engine.render(template, context),
+            Template::Static(template) => engine.render(template, context),
+            Template::Named { name, default } if !Template::is_registered(name).await => engine
+                .render(default, context)
+                .with_context(|| format!("Failed to render default template '{name}'")),
+            _ => self.render(context).await,
+        }
+    }
+
     /// Builds a Prompt from a template with an empty context
     pub fn to_prompt(&self) -> Prompt {
         self.into()
     }
 }
 
+/// Recursively collects the name and contents of all templates in `dir`
+fn collect_templates(root: &Path, dir: &Path, templates: &mut Vec<(String, String)>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_templates(root, &path, templates)?;
+        } else if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(TEMPLATE_EXTENSION))
+        {
+            let name = path
+                .strip_prefix(root)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let template = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read template {}", path.display()))?;
+
+            templates.push((name, template));
+        }
+    }
+
+    Ok(())
+}
+
 impl From<&'static str> for Template {
     fn from(template: &'static str) -> Self {
         Template::Static(template)
@@ -0,0 +1,182 @@
+//! Engines that render the source of prompts
+//!
+//! Prompts are rendered with [tera] by default. Tera's syntax conflicts with prompts that
+//! contain `{{ }}` literally, i.e. code or other templates, and some prompts are written for
+//! other engines. A [`TemplateEngine`] can be set per prompt with
+//! [`Prompt::with_engine`][crate::prompt::Prompt::with_engine].
+//!
+//! Available engines:
+//! - [`TeraEngine`]: the default
+//! - [`Literal`]: does not render, the prompt is used as is
+//! - [`MiniJinja`]: Jinja2 compatible, with the `minijinja` feature
+//! - [`Handlebars`]: with the `handlebars` feature
+//!
+//! Templates compiled into the repository, i.e. with [`Template::register`], are always rendered
+//! with tera.
+//!
+//! # Example
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() {
+//! # use swiftide_core::{prompt::Prompt, template_engine::Literal};
+//! let prompt = Prompt::from("fn main() { println!(\"{{}}\", 1); }")
+//!     .with_context_value("unused", "value")
+//!     .with_engine(Literal);
+//!
+//! assert_eq!(
+//!     prompt.render().await.unwrap(),
+//!     "fn main() { println!(\"{{}}\", 1); }"
+//! );
+//! # }
+//! ```
+//!
+//! [`Template::register`]: crate::template::Template::register
+use std::fmt::Debug;
+
+use anyhow::{Context as _, Result};
+use dyn_clone::DynClone;
+
+/// Renders the source of a template with a context
+pub trait TemplateEngine: Send + Sync + Debug + DynClone {
+    /// Renders the template with the values in the context
+    ///
+    /// # Errors
+    ///
+    /// Errors if the template is invalid or cannot be rendered with the context
+    fn render(&self, template: &str, context: &tera::Context) -> Result<String>;
+
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.split("::").last().unwrap_or(name)
+    }
+}
+
+dyn_clone::clone_trait_object!(TemplateEngine);
+
+/// Renders with [tera], the default engine
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TeraEngine;
+
+impl TemplateEngine for TeraEngine {
+    fn render(&self, template: &str, context: &tera::Context) -> Result<String> {
+        tera::Tera::one_off(template, context, false).context("Failed to render one-off template")
+    }
+}
+
+/// Does not render, the template is used as is
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Literal;
+
+impl TemplateEngine for Literal {
+    fn render(&self, template: &str, _context: &tera::Context) -> Result<String> {
+        Ok(template.to_string())
+    }
+}
+
+/// Renders with [minijinja], which is compatible with Jinja2
+///
+/// Filters, functions and other settings can be added to the environment it is created from. The
+/// default environment has the builtin filters, tests and functions.
+#[cfg(feature = "minijinja")]
+#[derive(Debug, Clone)]
+pub struct MiniJinja {
+    environment: minijinja::Environment<'static>,
+}
+
+#[cfg(feature = "minijinja")]
+impl Default for MiniJinja {
+    fn default() -> Self {
+        Self::new(minijinja::Environment::new())
+    }
+}
+
+#[cfg(feature = "minijinja")]
+impl MiniJinja {
+    pub fn new(environment: minijinja::Environment<'static>) -> Self {
+        Self { environment }
+    }
+}
+
+#[cfg(feature = "minijinja")]
+impl TemplateEngine for MiniJinja {
+    fn render(&self, template: &str, context: &tera::Context) -> Result<String> {
+        self.environment
+            .render_str(template, context.clone().into_json())
+            .context("Failed to render minijinja template")
+    }
+}
+
+/// Renders with [handlebars]
+///
+/// Helpers and other settings can be added to the registry it is created from.
+#[cfg(feature = "handlebars")]
+#[derive(Debug, Clone, Default)]
+pub struct Handlebars {
+    registry: handlebars::Handlebars<'static>,
+}
+
+#[cfg(feature = "handlebars")]
+impl Handlebars {
+    pub fn new(registry: handlebars::Handlebars<'static>) -> Self {
+        Self { registry }
+    }
+}
+
+#[cfg(feature = "handlebars")]
+impl TemplateEngine for Handlebars {
+    fn render(&self, template: &str, context: &tera::Context) -> Result<String> {
+        self.registry
+            .render_template(template, &context.clone().into_json())
+            .context("Failed to render handlebars template")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> tera::Context {
+        let mut context = tera::Context::new();
+        context.insert("world", "swiftide");
+        context
+    }
+
+    #[test]
+    fn test_tera() {
+        assert_eq!(
+            TeraEngine.render("hello {{ world }}", &context()).unwrap(),
+            "hello swiftide"
+        );
+    }
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(
+            Literal.render("hello {{ world }}", &context()).unwrap(),
+            "hello {{ world }}"
+        );
+    }
+
+    #[cfg(feature = "minijinja")]
+    #[test]
+    fn test_minijinja() {
+        assert_eq!(
+            MiniJinja::default()
+                .render("hello {{ world | upper }}", &context())
+                .unwrap(),
+            "hello SWIFTIDE"
+        );
+    }
+
+    #[cfg(feature = "handlebars")]
+    #[test]
+    fn test_handlebars() {
+        assert_eq!(
+            Handlebars::default()
+                .render("hello {{ world }}", &context())
+                .unwrap(),
+            "hello swiftide"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Estimates the number of tokens of prompts, messages and text
+//!
+//! Token estimates are used to stay within rate limits and context windows. Implement
+//! [`EstimateTokens`] for a tokenizer, and [`Estimatable`] for anything that can be estimated.
+//!
+//! [`ApproximateTokens`] estimates from the number of characters and works for any model. For
+//! precise numbers with `OpenAI` models, use the tiktoken integration.
+use std::{borrow::Cow, fmt::Debug};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+
+use crate::{
+    chat_completion::{ChatCompletionRequest, ChatMessage, ContentPart},
+    prompt::Prompt,
+};
+
+/// Estimates the number of tokens in a value
+#[async_trait]
+pub trait EstimateTokens: Send + Sync + Debug + DynClone {
+    async fn estimate(&self, value: &dyn Estimatable) -> Result<usize>;
+}
+
+dyn_clone::clone_trait_object!(EstimateTokens);
+
+/// A value that can be estimated in tokens
+#[async_trait]
+pub trait Estimatable: Send + Sync {
+    /// The text to count the tokens of
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>>;
+
+    /// Tokens to add on top of the counted text, i.e. the formatting overhead of chat messages
+    fn additional_tokens(&self) -> usize {
+        0
+    }
+}
+
+/// Estimates tokens from the number of characters
+///
+/// Defaults to 4 characters per token, which is a reasonable estimate for English text with most
+/// tokenizers.
+#[derive(Debug, Clone, Copy)]
+pub struct ApproximateTokens {
+    chars_per_token: f32,
+}
+
+impl ApproximateTokens {
+    pub fn new(chars_per_token: f32) -> Self {
+        Self { chars_per_token }
+    }
+}
+
+impl Default for ApproximateTokens {
+    fn default() -> Self {
+        Self::new(4.0)
+    }
+}
+
+#[async_trait]
+impl EstimateTokens for ApproximateTokens {
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    async fn estimate(&self, value: &dyn Estimatable) -> Result<usize> {
+        let chars = value
+            .for_estimate()
+            .await?
+            .iter()
+            .map(|text| text.chars().count())
+            .sum::<usize>();
+
+        Ok((chars as f32 / self.chars_per_token).ceil() as usize + value.additional_tokens())
+    }
+}
+
+#[async_trait]
+impl Estimatable for &str {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        Ok(vec![Cow::Borrowed(*self)])
+    }
+}
+
+#[async_trait]
+impl Estimatable for String {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        Ok(vec![Cow::Borrowed(self.as_str())])
+    }
+}
+
+#[async_trait]
+impl Estimatable for &[String] {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        Ok(strings_for_estimate(self))
+    }
+}
+
+#[async_trait]
+impl Estimatable for Vec<String> {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        Ok(strings_for_estimate(self))
+    }
+}
+
+fn strings_for_estimate(strings: &[String]) -> Vec<Cow<'_, str>> {
+    strings
+        .iter()
+        .map(|text| Cow::Borrowed(text.as_str()))
+        .collect()
+}
+
+#[async_trait]
+impl Estimatable for Prompt {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        Ok(vec![Cow::Owned(self.render().await?)])
+    }
+}
+
+/// Roughly the tokens chat apis add to every message for the role and formatting
+const TOKENS_PER_MESSAGE: usize = 4;
+
+#[async_trait]
+impl Estimatable for ChatMessage {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        let texts = match self {
+            ChatMessage::System(text) | ChatMessage::User(text) | ChatMessage::Summary(text) => {
+                vec![Cow::Borrowed(text.as_str())]
+            }
+            ChatMessage::UserWithParts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text(text) => Some(Cow::Borrowed(text.as_str())),
+                    ContentPart::Image(_) => None,
+                })
+                .collect(),
+            ChatMessage::Assistant(message, tool_calls) => message
+                .iter()
+                .map(|message| Cow::Borrowed(message.as_str()))
+                .chain(tool_calls.iter().flatten().flat_map(|tool_call| {
+                    std::iter::once(Cow::Borrowed(tool_call.name()))
+                        .chain(tool_call.args().map(Cow::Borrowed))
+                }))
+                .collect(),
+            ChatMessage::ToolOutput(_, output) => output
+                .content()
+                .map(|content| vec![Cow::Borrowed(content)])
+                .unwrap_or_default(),
+        };
+
+        Ok(texts)
+    }
+
+    fn additional_tokens(&self) -> usize {
+        TOKENS_PER_MESSAGE
+    }
+}
+
+#[async_trait]
+impl Estimatable for &[ChatMessage] {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        messages_for_estimate(self).await
+    }
+
+    fn additional_tokens(&self) -> usize {
+        self.len() * TOKENS_PER_MESSAGE
+    }
+}
+
+#[async_trait]
+impl Estimatable for Vec<ChatMessage> {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        messages_for_estimate(self).await
+    }
+
+    fn additional_tokens(&self) -> usize {
+        self.len() * TOKENS_PER_MESSAGE
+    }
+}
+
+#[async_trait]
+impl Estimatable for ChatCompletionRequest {
+    async fn for_estimate(&self) -> Result<Vec<Cow<'_, str>>> {
+        messages_for_estimate(self.messages()).await
+    }
+
+    fn additional_tokens(&self) -> usize {
+        self.messages().len() * TOKENS_PER_MESSAGE
+    }
+}
+
+async fn messages_for_estimate(messages: &[ChatMessage]) -> Result<Vec<Cow<'_, str>>> {
+    let mut texts = Vec::new();
+    for message in messages {
+        texts.extend(message.for_estimate().await?);
+    }
+    Ok(texts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_approximate_tokens() {
+        let estimator = ApproximateTokens::default();
+
+        assert_eq!(estimator.estimate(&"hello world!").await.unwrap(), 3);
+        assert_eq!(estimator.estimate(&"").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_approximate_tokens_of_messages() {
+        let estimator = ApproximateTokens::default();
+        let messages = vec![
+            ChatMessage::new_system("12345678"),
+            ChatMessage::new_user("1234"),
+        ];
+
+        assert_eq!(
+            estimator.estimate(&messages).await.unwrap(),
+            3 + 2 * TOKENS_PER_MESSAGE
+        );
+    }
+}
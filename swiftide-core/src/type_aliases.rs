@@ -5,6 +5,11 @@ use serde::{Deserialize, Serialize};
 pub type Embedding = Vec<f32>;
 pub type Embeddings = Vec<Embedding>;
 
+/// A multi-vector embedding with an embedding per token, as in late interaction models like
+/// `ColBERT`
+pub type MultiEmbedding = Vec<Embedding>;
+pub type MultiEmbeddings = Vec<MultiEmbedding>;
+
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct SparseEmbedding {
     pub indices: Vec<u32>,
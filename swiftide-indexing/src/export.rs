@@ -0,0 +1,76 @@
+//! Exports all nodes of a storage, i.e. for backups or migrations between storages
+//!
+//! Nodes are exported as [`NodeRecord`]s, with their chunk, metadata and vectors.
+use anyhow::{Context as _, Result};
+use futures_util::TryStreamExt as _;
+use swiftide_core::{indexing::NodeRecord, Scroll};
+use tokio::io::{AsyncWrite, AsyncWriteExt as _};
+
+/// Writes every node of the storage as a json line, returning the number of exported nodes
+///
+/// # Errors
+///
+/// Errors if scrolling the storage or writing fails.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_indexing::{export, persist::MemoryStorage};
+/// # async fn run() -> anyhow::Result<()> {
+/// let storage = MemoryStorage::default();
+/// let file = tokio::fs::File::create("backup.jsonl").await?;
+/// export::export_jsonl(&storage, file).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn export_jsonl(
+    storage: &dyn Scroll,
+    mut writer: impl AsyncWrite + Unpin,
+) -> Result<usize> {
+    let mut nodes = storage.scroll();
+    let mut count = 0;
+
+    while let Some(node) = nodes
+        .try_next()
+        .await
+        .with_context(|| format!("Failed to scroll {}", storage.name()))?
+    {
+        let mut line = serde_json::to_vec(&NodeRecord::from(node))?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+        count += 1;
+    }
+    writer.flush().await?;
+
+    tracing::info!(count, storage = storage.name(), "Exported nodes to jsonl");
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::{
+        indexing::{EmbeddedField, Node},
+        Persist as _,
+    };
+
+    use super::*;
+    use crate::persist::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_export_jsonl() {
+        let storage = MemoryStorage::default();
+        let mut node = Node::new("chunk")
+            .with_metadata(("lang", "rust"))
+            .to_owned();
+        node.with_vectors([(EmbeddedField::Combined, vec![1.0, 2.0])]);
+        storage.store(node.clone()).await.unwrap();
+
+        let mut buffer = Vec::new();
+        let count = export_jsonl(&storage, &mut buffer).await.unwrap();
+        assert_eq!(count, 1);
+
+        let lines = String::from_utf8(buffer).unwrap();
+        let record: NodeRecord = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(Node::try_from(record).unwrap(), node);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod export;
 pub mod loaders;
 pub mod persist;
 pub mod transformers;
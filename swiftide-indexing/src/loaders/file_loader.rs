@@ -1,7 +1,10 @@
 //! Load files from a directory
 use anyhow::Context as _;
 use std::path::{Path, PathBuf};
-use swiftide_core::{indexing::IndexingStream, indexing::Node, Loader};
+use swiftide_core::{
+    indexing::{is_image_path, IndexingStream, LoaderError, Node},
+    Loader,
+};
 
 /// The `FileLoader` struct is responsible for loading files from a specified directory,
 /// filtering them based on their extensions, and creating a stream of these files for further processing.
@@ -70,14 +73,7 @@ impl FileLoader {
             .map(ignore::DirEntry::into_path)
             .map(|entry| {
                 tracing::debug!("Reading file: {:?}", entry);
-                let content = std::fs::read_to_string(&entry).unwrap();
-                let original_size = content.len();
-                Node::builder()
-                    .path(entry)
-                    .chunk(content)
-                    .original_size(original_size)
-                    .build()
-                    .expect("Failed to build node")
+                read_node(&entry).expect("Failed to read file")
             })
             .collect()
     }
@@ -85,7 +81,7 @@ impl FileLoader {
     // Helper function to check if a file has the specified extension.
     // If no extensions are specified, this function will return true.
     // If the file has no extension, this function will return false.
-    fn file_has_extension(&self, path: &Path) -> bool {
+    pub(crate) fn file_has_extension(&self, path: &Path) -> bool {
         self.extensions.as_ref().map_or(true, |exts| {
             let Some(ext) = path.extension() else {
                 return false;
@@ -95,6 +91,33 @@ impl FileLoader {
     }
 }
 
+/// Reads a file into a node
+///
+/// Images are not read, their node has the path of the image and an empty chunk, see
+/// [`Node::is_image`].
+pub(crate) fn read_node(path: &Path) -> anyhow::Result<Node> {
+    if is_image_path(path) {
+        let original_size = std::fs::metadata(path)
+            .context("Failed to read image metadata")?
+            .len();
+
+        return Node::builder()
+            .path(path)
+            .chunk(String::new())
+            .original_size(usize::try_from(original_size).unwrap_or(usize::MAX))
+            .build();
+    }
+
+    let content = std::fs::read_to_string(path).context("Failed to read file")?;
+    let original_size = content.len();
+
+    Node::builder()
+        .path(path)
+        .chunk(content)
+        .original_size(original_size)
+        .build()
+}
+
 impl Loader for FileLoader {
     /// Converts the `FileLoader` into a stream of `Node`.
     ///
@@ -110,15 +133,8 @@ impl Loader for FileLoader {
             .filter(move |entry| self.file_has_extension(entry.path()))
             .map(|entry| {
                 tracing::debug!("Reading file: {:?}", entry);
-                let content =
-                    std::fs::read_to_string(entry.path()).context("Failed to read file")?;
-                let original_size = content.len();
-
-                Node::builder()
-                    .path(entry.path())
-                    .chunk(content)
-                    .original_size(original_size)
-                    .build()
+                read_node(entry.path())
+                    .map_err(|err| anyhow::Error::from(LoaderError::permanent(err)))
             });
 
         IndexingStream::iter(files)
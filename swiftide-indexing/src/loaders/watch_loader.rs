@@ -0,0 +1,453 @@
+//! Continuously load files from a directory as they change
+use std::{
+    collections::{BTreeSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use futures_util::StreamExt as _;
+use ignore::gitignore::Gitignore;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use swiftide_core::{
+    indexing::{IndexingStream, LoaderError, Metadata, Node},
+    Loader, Persist,
+};
+use tokio::sync::mpsc;
+
+use super::{file_loader::read_node, FileLoader};
+
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+const DEFAULT_PATH_KEY: &str = "path";
+
+/// Loads the files in a directory, and then keeps loading files as they are created or changed
+///
+/// Turns [`Pipeline::run`](crate::Pipeline::run) into a long running, incremental indexer. The
+/// stream does not end by itself, stop the pipeline with
+/// [`Pipeline::run_with_cancel`](crate::Pipeline::run_with_cancel).
+///
+/// Every node gets the path of its file as metadata, under `path` by default. Storages added with
+/// [`WatchLoader::purge_from`] delete the nodes of a file by that metadata when the file is
+/// removed, and before a changed file is loaded again so that no stale chunks are left behind.
+/// When a directory is removed, the nodes of every file loaded from it are deleted. Files under it
+/// that were stored before the loader started, i.e. without an initial scan, are not known and
+/// are left in the storage.
+/// Because all nodes of a changed file are replaced, do not filter the nodes with a node cache
+/// when purging.
+///
+/// Events are debounced, a file that is written several times in a row is loaded once. Hidden
+/// files and files ignored by the `.gitignore` in the root of the directory are skipped, as are
+/// files that cannot be read, so that one file does not stop the indexer.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_indexing as indexing;
+/// # use swiftide_indexing::{loaders::WatchLoader, persist::MemoryStorage};
+/// # async fn run() -> anyhow::Result<()> {
+/// let storage = MemoryStorage::default();
+/// let cancel = swiftide_core::CancellationToken::new();
+///
+/// indexing::Pipeline::from_loader(
+///     WatchLoader::new("./docs")
+///         .with_extensions(&["md"])
+///         .purge_from(storage.clone()),
+/// )
+/// .then_store_with(storage)
+/// .run_with_cancel(cancel)
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct WatchLoader {
+    files: FileLoader,
+    debounce: Duration,
+    initial_scan: bool,
+    path_key: String,
+    purge: Vec<Arc<dyn Persist>>,
+}
+
+impl WatchLoader {
+    /// Creates a new `WatchLoader` for a directory
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            files: FileLoader::new(path),
+            debounce: DEFAULT_DEBOUNCE,
+            initial_scan: true,
+            path_key: DEFAULT_PATH_KEY.to_string(),
+            purge: Vec::new(),
+        }
+    }
+
+    /// Only loads files with these extensions, without the leading dot
+    #[must_use]
+    pub fn with_extensions(mut self, extensions: &[impl AsRef<str>]) -> Self {
+        self.files = self.files.with_extensions(extensions);
+        self
+    }
+
+    /// Waits for the files to be quiet for this long before loading them, defaults to 500ms
+    #[must_use]
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Only loads files that change after the loader started, instead of loading all files first
+    #[must_use]
+    pub fn without_initial_scan(mut self) -> Self {
+        self.initial_scan = false;
+        self
+    }
+
+    /// Sets the metadata key the path of the file is stored under, defaults to `path`
+    ///
+    /// Storages that only filter on configured metadata fields, like pgvector and `LanceDB`,
+    /// need this field configured for purging.
+    #[must_use]
+    pub fn with_path_key(mut self, path_key: impl Into<String>) -> Self {
+        self.path_key = path_key.into();
+        self
+    }
+
+    /// Deletes the nodes of removed and changed files from the storage
+    ///
+    /// The storage must support [`Persist::delete_by_metadata`].
+    #[must_use]
+    pub fn purge_from(mut self, storage: impl Persist + 'static) -> Self {
+        self.purge.push(Arc::new(storage));
+        self
+    }
+}
+
+impl Loader for WatchLoader {
+    fn into_stream(self) -> IndexingStream {
+        let (sender, events) = mpsc::unbounded_channel();
+
+        let mut watcher = match notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        }) {
+            Ok(watcher) => watcher,
+            Err(err) => return anyhow::Error::from(LoaderError::permanent(err)).into(),
+        };
+
+        // Scanned and watched paths are the same, so that purging matches the stored paths
+        let root = match self.files.path.canonicalize() {
+            Ok(root) => root,
+            Err(err) => return anyhow::Error::from(LoaderError::permanent(err)).into(),
+        };
+
+        if let Err(err) = watcher.watch(&root, RecursiveMode::Recursive) {
+            return anyhow::Error::from(LoaderError::permanent(err)).into();
+        }
+
+        let (gitignore, _) = Gitignore::new(root.join(".gitignore"));
+
+        let queue = if self.initial_scan {
+            ignore::Walk::new(&root)
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+                .map(ignore::DirEntry::into_path)
+                .collect()
+        } else {
+            VecDeque::new()
+        };
+
+        let watch = Watch {
+            files: WatchedFiles {
+                loader: self,
+                root,
+                gitignore,
+                loaded: BTreeSet::new(),
+            },
+            _watcher: watcher,
+            events,
+            queue,
+        };
+
+        futures_util::stream::unfold(watch, |mut watch| async move {
+            loop {
+                if let Some(path) = watch.queue.pop_front() {
+                    if let Some(result) = watch.files.load(&path).await {
+                        return Some((result, watch));
+                    }
+                    continue;
+                }
+
+                match watch.next_changes().await? {
+                    Ok(paths) => watch.queue.extend(paths),
+                    Err(err) => return Some((Err(err), watch)),
+                }
+            }
+        })
+        .boxed()
+        .into()
+    }
+
+    fn into_stream_boxed(self: Box<Self>) -> IndexingStream {
+        self.into_stream()
+    }
+}
+
+/// The state of a running watch, the watcher stops when it is dropped with the stream
+struct Watch {
+    files: WatchedFiles,
+    _watcher: RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+    queue: VecDeque<PathBuf>,
+}
+
+/// Decides which changed files are loaded and purged
+struct WatchedFiles {
+    loader: WatchLoader,
+    root: PathBuf,
+    gitignore: Gitignore,
+    /// The files that were loaded, to purge the files under a removed directory
+    loaded: BTreeSet<PathBuf>,
+}
+
+impl Watch {
+    /// Waits for changes and collects the changed paths until the files are quiet
+    ///
+    /// Returns `None` if the watcher stopped.
+    async fn next_changes(&mut self) -> Option<anyhow::Result<BTreeSet<PathBuf>>> {
+        let mut paths = BTreeSet::new();
+        let mut event = self.events.recv().await?;
+
+        loop {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Access(_)) => (),
+                Ok(event) => paths.extend(event.paths),
+                Err(err) => return Some(Err(LoaderError::transient(err).into())),
+            }
+
+            match tokio::time::timeout(self.files.loader.debounce, self.events.recv()).await {
+                Ok(Some(next)) => event = next,
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        tracing::debug!(num_paths = paths.len(), "Files changed");
+        Some(Ok(paths))
+    }
+}
+
+impl WatchedFiles {
+    /// Purges and loads a changed file, returning `None` if nothing needs to be indexed
+    async fn load(&mut self, path: &Path) -> Option<anyhow::Result<Node>> {
+        if self.is_ignored(path) {
+            return None;
+        }
+
+        if !path.exists() {
+            return self.purge_removed(path).await.err().map(Err);
+        }
+
+        if !path.is_file() || !self.loader.files.file_has_extension(path) {
+            return None;
+        }
+
+        if let Err(err) = self.purge(path).await {
+            return Some(Err(err));
+        }
+
+        tracing::debug!(?path, "Loading changed file");
+        match read_node(path) {
+            Ok(mut node) => {
+                node.metadata.insert(
+                    self.loader.path_key.clone(),
+                    path.to_string_lossy().to_string(),
+                );
+                self.loaded.insert(path.to_path_buf());
+                Some(Ok(node))
+            }
+            Err(error) => {
+                tracing::warn!(?path, ?error, "Skipping file that cannot be loaded");
+                None
+            }
+        }
+    }
+
+    /// Purges a removed file, or every loaded file under a removed directory
+    ///
+    /// A directory that is moved away is reported as a single path, without its files.
+    async fn purge_removed(&mut self, path: &Path) -> anyhow::Result<()> {
+        let mut removed = self
+            .loaded
+            .iter()
+            .filter(|loaded| loaded.starts_with(path))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        // Files stored before the loader started are purged as well
+        if self.loader.files.file_has_extension(path) {
+            removed.insert(path.to_path_buf());
+        }
+
+        for removed_path in removed {
+            self.purge(&removed_path).await?;
+            self.loaded.remove(&removed_path);
+        }
+
+        Ok(())
+    }
+
+    async fn purge(&self, path: &Path) -> anyhow::Result<()> {
+        for storage in &self.loader.purge {
+            let filter = Metadata::from((
+                self.loader.path_key.clone(),
+                path.to_string_lossy().to_string(),
+            ));
+
+            storage.delete_by_metadata(filter).await.with_context(|| {
+                format!("Failed to purge {} from {}", path.display(), storage.name())
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Skips hidden files and files ignored by the root `.gitignore`, like the initial scan
+    fn is_ignored(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        relative
+            .components()
+            .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+            || self
+                .gitignore
+                .matched_path_or_any_parents(relative, path.is_dir())
+                .is_ignore()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::TryStreamExt as _;
+    use swiftide_core::indexing::MockPersist;
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_loads_and_purges_changed_files() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.md"), "existing").unwrap();
+        std::fs::write(root.join("ignored.txt"), "ignored").unwrap();
+
+        let purged = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut storage = MockPersist::new();
+        let purged_clone = Arc::clone(&purged);
+        storage
+            .expect_delete_by_metadata()
+            .returning(move |filter| {
+                let path = filter.get("path").unwrap().as_str().unwrap().to_string();
+                purged_clone.lock().unwrap().push(path);
+                Ok(())
+            });
+
+        let mut stream = WatchLoader::new(&root)
+            .with_extensions(&["md"])
+            .with_debounce(Duration::from_millis(50))
+            .purge_from(storage)
+            .into_stream();
+
+        let timeout = Duration::from_secs(5);
+
+        let node = tokio::time::timeout(timeout, stream.try_next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(node.chunk, "existing");
+
+        std::fs::remove_file(root.join("a.md")).unwrap();
+        std::fs::write(root.join("b.md"), "created").unwrap();
+
+        let node = tokio::time::timeout(timeout, stream.try_next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(node.chunk, "created");
+        assert_eq!(
+            node.metadata.get("path").unwrap(),
+            &serde_json::Value::from(root.join("b.md").to_string_lossy().to_string())
+        );
+
+        let a = root.join("a.md").to_string_lossy().to_string();
+        // Purged before the initial load and after removal
+        assert_eq!(
+            purged
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|path| **path == a)
+                .count(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_purges_files_under_removed_directories() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/a.md"), "a").unwrap();
+        std::fs::write(root.join("docs/b.md"), "b").unwrap();
+        std::fs::write(root.join("c.md"), "c").unwrap();
+
+        let purged = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut storage = MockPersist::new();
+        let purged_clone = Arc::clone(&purged);
+        storage
+            .expect_delete_by_metadata()
+            .returning(move |filter| {
+                let path = filter.get("path").unwrap().as_str().unwrap().to_string();
+                purged_clone.lock().unwrap().push(path);
+                Ok(())
+            });
+
+        let mut stream = WatchLoader::new(&root)
+            .with_extensions(&["md"])
+            .with_debounce(Duration::from_millis(50))
+            .purge_from(storage)
+            .into_stream();
+
+        for _ in 0..3 {
+            tokio::time::timeout(Duration::from_secs(5), stream.try_next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+        }
+        purged.lock().unwrap().clear();
+
+        // Moving the directory away only reports the directory
+        let moved = TempDir::new().unwrap();
+        std::fs::rename(root.join("docs"), moved.path().join("docs")).unwrap();
+
+        // Nothing is loaded, the files are purged while waiting for changes
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), stream.try_next())
+                .await
+                .is_err()
+        );
+
+        let mut purged = purged.lock().unwrap().clone();
+        purged.sort();
+        purged.dedup();
+        assert_eq!(
+            purged,
+            vec![
+                root.join("docs/a.md").to_string_lossy().to_string(),
+                root.join("docs/b.md").to_string_lossy().to_string(),
+            ]
+        );
+    }
+}
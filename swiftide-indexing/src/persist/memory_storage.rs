@@ -11,9 +11,10 @@ use async_trait::async_trait;
 use derive_builder::Builder;
 use tokio::sync::RwLock;
 
+use futures_util::{stream, StreamExt as _};
 use swiftide_core::{
-    indexing::{IndexingStream, Node},
-    Persist,
+    indexing::{IndexingStream, Node, PersistError},
+    Persist, Scroll,
 };
 
 #[derive(Debug, Default, Builder, Clone)]
@@ -60,14 +61,14 @@ impl MemoryStorage {
 
 #[async_trait]
 impl Persist for MemoryStorage {
-    async fn setup(&self) -> Result<()> {
+    async fn setup(&self) -> Result<(), PersistError> {
         Ok(())
     }
 
     /// Store a node by its id
     ///
     /// If the node does not have an id, a simple counter is used as the key.
-    async fn store(&self, node: Node) -> Result<Node> {
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
         self.data.write().await.insert(self.key(), node.clone());
 
         Ok(node)
@@ -91,6 +92,18 @@ impl Persist for MemoryStorage {
     }
 }
 
+impl Scroll for MemoryStorage {
+    /// Streams all nodes in the storage
+    fn scroll(&self) -> IndexingStream {
+        let storage = self.clone();
+
+        stream::once(async move { storage.get_all_values().await })
+            .flat_map(|nodes| stream::iter(nodes.into_iter().map(Ok)))
+            .boxed()
+            .into()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -135,4 +148,20 @@ mod test {
         assert_eq!(result[0], node1);
         assert_eq!(result[1], node2);
     }
+
+    #[tokio::test]
+    async fn test_scroll() {
+        let storage = MemoryStorage::default();
+        let nodes = vec![Node::new("first"), Node::new("second")];
+        storage
+            .batch_store(nodes.clone())
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut scrolled = storage.scroll().try_collect::<Vec<_>>().await.unwrap();
+        scrolled.sort_by(|a, b| a.chunk.cmp(&b.chunk));
+        assert_eq!(scrolled, nodes);
+    }
 }
@@ -0,0 +1,444 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use derive_builder::Builder;
+use futures_util::{stream, StreamExt as _};
+use itertools::Itertools as _;
+use tokio::{
+    io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader},
+    sync::RwLock,
+};
+
+use swiftide_core::{
+    document::Document,
+    indexing::{
+        EmbeddedField, IndexingStream, Metadata, Node, NodeCacheError, NodeRecord, PersistError,
+    },
+    querying::{
+        search_strategies::{max_sim, MultiVectorSearch, SimilaritySingleEmbedding},
+        states, Query, RetrieveError,
+    },
+    NodeCache, Persist, Retrieve, Scroll,
+};
+
+/// An in-memory vector store with brute-force similarity search
+///
+/// Implements `Persist`, `Retrieve`, `NodeCache` and `Scroll`, and can be saved to and loaded
+/// from a file. Great for tests, examples and small datasets, where running a vector database is
+/// overkill.
+///
+/// Nodes are stored by their id, so storing a node again replaces it. Retrieval ranks the nodes
+/// with a vector for the configured field by cosine similarity, and can be filtered on exact
+/// metadata matches. Nodes with multi-vectors can be retrieved by `MaxSim` with
+/// `MultiVectorSearch`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_indexing::persist::MemoryStore;
+/// # use swiftide_core::indexing::EmbeddedField;
+/// let store = MemoryStore::builder()
+///     .vector_field(EmbeddedField::Combined)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct MemoryStore {
+    /// The embedding to search on, defaults to `EmbeddedField::Combined`
+    #[builder(default)]
+    vector_field: EmbeddedField,
+    #[builder(default)]
+    batch_size: Option<usize>,
+    #[builder(setter(skip), default)]
+    nodes: Arc<RwLock<HashMap<uuid::Uuid, Node>>>,
+    #[builder(setter(skip), default)]
+    cached: Arc<RwLock<HashSet<uuid::Uuid>>>,
+}
+
+impl MemoryStore {
+    pub fn builder() -> MemoryStoreBuilder {
+        MemoryStoreBuilder::default()
+    }
+
+    /// Retrieve a node by its id
+    pub async fn get(&self, id: uuid::Uuid) -> Option<Node> {
+        self.nodes.read().await.get(&id).cloned()
+    }
+
+    /// The number of stored nodes
+    pub async fn len(&self) -> usize {
+        self.nodes.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.nodes.read().await.is_empty()
+    }
+
+    /// Writes all nodes to a file as json lines
+    ///
+    /// # Errors
+    ///
+    /// Errors if the file cannot be written
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = tokio::fs::File::create(path.as_ref())
+            .await
+            .with_context(|| format!("Failed to create {}", path.as_ref().display()))?;
+
+        for node in self.nodes.read().await.values() {
+            let mut line = serde_json::to_vec(&NodeRecord::from(node.clone()))?;
+            line.push(b'\n');
+            file.write_all(&line).await?;
+        }
+        file.flush().await?;
+
+        Ok(())
+    }
+
+    /// Loads nodes from a file written with [`MemoryStore::save`], replacing stored nodes with
+    /// the same id
+    ///
+    /// # Errors
+    ///
+    /// Errors if the file cannot be read or contains invalid nodes
+    pub async fn load(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = tokio::fs::File::open(path.as_ref())
+            .await
+            .with_context(|| format!("Failed to open {}", path.as_ref().display()))?;
+
+        let mut lines = BufReader::new(file).lines();
+        let mut nodes = self.nodes.write().await;
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: NodeRecord = serde_json::from_str(&line)?;
+            let node = Node::try_from(record)?;
+            nodes.insert(node.id(), node);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Persist for MemoryStore {
+    async fn setup(&self) -> Result<(), PersistError> {
+        Ok(())
+    }
+
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        self.nodes.write().await.insert(node.id(), node.clone());
+
+        Ok(node)
+    }
+
+    async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
+        let mut lock = self.nodes.write().await;
+
+        for node in &nodes {
+            lock.insert(node.id(), node.clone());
+        }
+
+        nodes.into()
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+    }
+
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        let mut lock = self.nodes.write().await;
+
+        for id in ids {
+            lock.remove(&id);
+        }
+
+        Ok(())
+    }
+
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        self.nodes
+            .write()
+            .await
+            .retain(|_, node| !matches_filter(node, &filter));
+
+        Ok(())
+    }
+}
+
+impl Scroll for MemoryStore {
+    fn scroll(&self) -> IndexingStream {
+        let nodes = Arc::clone(&self.nodes);
+
+        stream::once(async move { nodes.read().await.values().cloned().collect_vec() })
+            .flat_map(|nodes| stream::iter(nodes.into_iter().map(Ok)))
+            .boxed()
+            .into()
+    }
+}
+
+#[async_trait]
+impl NodeCache for MemoryStore {
+    async fn get(&self, node: &Node) -> bool {
+        self.cached.read().await.contains(&node.id())
+    }
+
+    async fn set(&self, node: &Node) {
+        self.cached.write().await.insert(node.id());
+    }
+
+    async fn clear(&self) -> Result<(), NodeCacheError> {
+        self.cached.write().await.clear();
+
+        Ok(())
+    }
+}
+
+/// Implement the `Retrieve` trait for `SimilaritySingleEmbedding` search strategy.
+///
+/// Ranks all nodes by the cosine similarity of their vector for the configured field. Filters
+/// are metadata that every returned node must match exactly.
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding<Metadata>> for MemoryStore {
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding<Metadata>,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.embedding else {
+            return Err(RetrieveError::MissingEmbedding);
+        };
+        let top_k = usize::try_from(search_strategy.top_k()).map_err(RetrieveError::permanent)?;
+
+        let documents = self
+            .nodes
+            .read()
+            .await
+            .values()
+            .filter(|node| {
+                search_strategy
+                    .filter()
+                    .iter()
+                    .all(|filter| matches_filter(node, filter))
+            })
+            .filter_map(|node| {
+                let vector = node.vectors.as_ref()?.get(&self.vector_field)?;
+                Some((cosine_similarity(embedding, vector), node))
+            })
+            .sorted_by(|(a, _), (b, _)| b.total_cmp(a))
+            .take(top_k)
+            .map(|(_, node)| Document::new(&node.chunk, Some(node.metadata.clone())))
+            .collect();
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding> for MemoryStore {
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        Retrieve::<SimilaritySingleEmbedding<Metadata>>::retrieve(
+            self,
+            &search_strategy.into_concrete_filter::<Metadata>(),
+            query,
+        )
+        .await
+    }
+}
+
+/// Implement the `Retrieve` trait for the `MultiVectorSearch` search strategy.
+///
+/// Ranks all nodes with a multi-vector for the field of the strategy by `MaxSim`.
+#[async_trait]
+impl Retrieve<MultiVectorSearch> for MemoryStore {
+    async fn retrieve(
+        &self,
+        search_strategy: &MultiVectorSearch,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.multi_embedding else {
+            return Err(RetrieveError::permanent(
+                "No multi-vector embedding for query",
+            ));
+        };
+        let top_k = usize::try_from(search_strategy.top_k()).map_err(RetrieveError::permanent)?;
+
+        let documents = self
+            .nodes
+            .read()
+            .await
+            .values()
+            .filter_map(|node| {
+                let vector = node
+                    .multi_vectors
+                    .as_ref()?
+                    .get(search_strategy.vector_field())?;
+                Some((max_sim(embedding, vector), node))
+            })
+            .sorted_by(|(a, _), (b, _)| b.total_cmp(a))
+            .take(top_k)
+            .map(|(_, node)| Document::new(&node.chunk, Some(node.metadata.clone())))
+            .collect();
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+fn matches_filter(node: &Node, filter: &Metadata) -> bool {
+    filter
+        .iter()
+        .all(|(key, value)| node.metadata.get(key) == Some(value))
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+    let norm = |vector: &[f32]| vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    let denominator = norm(a) * norm(b);
+    if denominator <= f32::EPSILON {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt as _;
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    fn node(chunk: &str, vector: Vec<f32>, category: &str) -> Node {
+        Node::new(chunk)
+            .with_metadata(("category", category))
+            .with_vectors([(EmbeddedField::Combined, vector)])
+            .to_owned()
+    }
+
+    async fn store() -> MemoryStore {
+        let store = MemoryStore::default();
+        store
+            .batch_store(vec![
+                node("closest", vec![1.0, 0.1], "a"),
+                node("close", vec![1.0, 0.5], "b"),
+                node("opposite", vec![-1.0, 0.0], "a"),
+            ])
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        store
+    }
+
+    fn query() -> Query<states::Pending> {
+        let mut query = Query::<states::Pending>::new("query");
+        query.embedding = Some(vec![1.0, 0.0]);
+        query
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_by_similarity() {
+        let store = store().await;
+
+        let mut search_strategy = SimilaritySingleEmbedding::<()>::default();
+        search_strategy.with_top_k(2);
+        let result = store.retrieve(&search_strategy, query()).await.unwrap();
+
+        let contents = result
+            .documents()
+            .iter()
+            .map(Document::content)
+            .collect_vec();
+        assert_eq!(contents, ["closest", "close"]);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_with_filter() {
+        let store = store().await;
+
+        let search_strategy =
+            SimilaritySingleEmbedding::from_filter(Metadata::from(("category", "a")));
+        let result = store.retrieve(&search_strategy, query()).await.unwrap();
+
+        let contents = result
+            .documents()
+            .iter()
+            .map(Document::content)
+            .collect_vec();
+        assert_eq!(contents, ["closest", "opposite"]);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_by_max_sim() {
+        let store = MemoryStore::default();
+        store
+            .batch_store(vec![
+                Node::new("one match")
+                    .with_multi_vectors([(EmbeddedField::Combined, vec![vec![1.0, 0.0]])])
+                    .to_owned(),
+                Node::new("two matches")
+                    .with_multi_vectors([(
+                        EmbeddedField::Combined,
+                        vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+                    )])
+                    .to_owned(),
+                Node::new("no multi-vectors"),
+            ])
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut query = Query::<states::Pending>::new("query");
+        query.multi_embedding = Some(vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        let result = store
+            .retrieve(&MultiVectorSearch::default(), query)
+            .await
+            .unwrap();
+
+        let contents = result
+            .documents()
+            .iter()
+            .map(Document::content)
+            .collect_vec();
+        assert_eq!(contents, ["two matches", "one match"]);
+    }
+
+    #[tokio::test]
+    async fn test_node_cache() {
+        let store = MemoryStore::default();
+        let node = Node::new("cached");
+
+        assert!(!NodeCache::get(&store, &node).await);
+        store.set(&node).await;
+        assert!(NodeCache::get(&store, &node).await);
+        store.clear().await.unwrap();
+        assert!(!NodeCache::get(&store, &node).await);
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load() {
+        let tempdir = TempDir::new().unwrap();
+        let path = tempdir.child("snapshot.jsonl");
+        let store = store().await;
+        store.save(&path).await.unwrap();
+
+        let loaded = MemoryStore::default();
+        loaded.load(&path).await.unwrap();
+        assert_eq!(loaded.len().await, 3);
+
+        let node = node("closest", vec![1.0, 0.1], "a");
+        assert_eq!(loaded.get(node.id()).await, Some(node));
+    }
+}
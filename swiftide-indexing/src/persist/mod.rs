@@ -2,4 +2,9 @@
 //!
 //! More storage implementations are available as integrations.
 mod memory_storage;
+mod memory_store;
+mod multi_persist;
+
 pub use memory_storage::MemoryStorage;
+pub use memory_store::{MemoryStore, MemoryStoreBuilder};
+pub use multi_persist::MultiPersist;
@@ -0,0 +1,264 @@
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use futures_util::{future::try_join_all, TryStreamExt as _};
+use itertools::Itertools as _;
+use swiftide_core::{
+    indexing::{IndexingStream, Metadata, Node, PersistError},
+    Persist,
+};
+
+/// Persists every node in multiple storages concurrently, i.e. `Qdrant` for vectors and
+/// `PostgreSQL` for full text.
+///
+/// Every storage batches independently with its own batch size. If a storage fails, an error with
+/// the name of the storage is yielded instead of the nodes, while the other storages still store
+/// them.
+///
+/// # Example
+///
+/// ```ignore
+/// Pipeline::from_loader(loader)
+///     .then_store_with(
+///         MultiPersist::new()
+///             .with_store(qdrant)
+///             .with_store(pgvector),
+///     )
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MultiPersist {
+    stores: Vec<Arc<dyn Persist>>,
+    batch_size: Option<usize>,
+}
+
+impl MultiPersist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a storage to persist nodes in
+    #[must_use]
+    pub fn with_store(mut self, store: impl Persist + 'static) -> Self {
+        self.stores.push(Arc::new(store));
+        self
+    }
+
+    /// Sets the number of nodes the pipeline hands over at once
+    ///
+    /// Defaults to the largest batch size of the storages. The nodes are then split into the
+    /// batch size of every storage.
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    async fn store_in(store: &dyn Persist, nodes: &[Node]) -> Result<()> {
+        match store.batch_size() {
+            Some(batch_size) => {
+                for batch in split_batches(nodes, batch_size.max(1), store.max_batch_bytes()) {
+                    store
+                        .batch_store(batch.to_vec())
+                        .await
+                        .try_collect::<Vec<_>>()
+                        .await?;
+                }
+            }
+            None => {
+                try_join_all(nodes.iter().map(|node| store.store(node.clone()))).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits nodes into batches of at most `batch_size` nodes and `max_bytes` estimated bytes
+fn split_batches(nodes: &[Node], batch_size: usize, max_bytes: Option<usize>) -> Vec<&[Node]> {
+    let Some(max_bytes) = max_bytes else {
+        return nodes.chunks(batch_size).collect();
+    };
+
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+
+    for (index, node) in nodes.iter().enumerate() {
+        let size = node.payload_size();
+        if index > start && (index - start >= batch_size || bytes + size > max_bytes) {
+            batches.push(&nodes[start..index]);
+            start = index;
+            bytes = 0;
+        }
+        bytes += size;
+    }
+
+    if start < nodes.len() {
+        batches.push(&nodes[start..]);
+    }
+
+    batches
+}
+
+#[async_trait]
+impl Persist for MultiPersist {
+    async fn setup(&self) -> Result<(), PersistError> {
+        try_join_all(self.stores.iter().map(|store| async move {
+            store
+                .setup()
+                .await
+                .with_context(|| format!("Failed to set up {}", store.name()))
+        }))
+        .await?;
+
+        Ok(())
+    }
+
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        let mut nodes = self.batch_store(vec![node]).await;
+
+        Ok(nodes
+            .try_next()
+            .await?
+            .context("Expected the stored node")?)
+    }
+
+    /// Stores the nodes in every storage concurrently
+    ///
+    /// Yields the nodes if all storages succeed, otherwise an error for every failed storage.
+    async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
+        let results = futures_util::future::join_all(self.stores.iter().map(|store| async {
+            Self::store_in(store.as_ref(), &nodes)
+                .await
+                .with_context(|| {
+                    format!("Failed to store {} nodes in {}", nodes.len(), store.name())
+                })
+        }))
+        .await;
+
+        let errors = results
+            .into_iter()
+            .filter_map(Result::err)
+            .inspect(|error| tracing::error!(?error, "Storage failed"))
+            .collect_vec();
+
+        if errors.is_empty() {
+            nodes.into()
+        } else {
+            IndexingStream::iter(errors.into_iter().map(Err))
+        }
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.batch_size.or_else(|| {
+            self.stores
+                .iter()
+                .filter_map(|store| store.batch_size())
+                .max()
+        })
+    }
+
+    /// The smallest payload limit of the storages
+    fn max_batch_bytes(&self) -> Option<usize> {
+        self.stores
+            .iter()
+            .filter_map(|store| store.max_batch_bytes())
+            .min()
+    }
+
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        try_join_all(self.stores.iter().map(|store| store.delete(ids.clone()))).await?;
+        Ok(())
+    }
+
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        try_join_all(
+            self.stores
+                .iter()
+                .map(|store| store.delete_by_metadata(filter.clone())),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::indexing::MockPersist;
+
+    use super::*;
+    use crate::persist::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_stores_in_every_storage() {
+        let storage = MemoryStorage::default();
+
+        let mut batched = MockPersist::new();
+        batched.expect_batch_size().returning(|| Some(1));
+        batched.expect_max_batch_bytes().returning(|| None);
+        batched
+            .expect_batch_store()
+            .times(2)
+            .returning(|nodes| nodes.into());
+
+        let multi = MultiPersist::new()
+            .with_store(storage.clone())
+            .with_store(batched);
+        assert_eq!(multi.batch_size(), Some(1));
+
+        let nodes = vec![Node::new("first"), Node::new("second")];
+        let stored = multi
+            .batch_store(nodes.clone())
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(stored, nodes);
+        assert_eq!(storage.get_all_values().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reports_failing_storage() {
+        let storage = MemoryStorage::default();
+
+        let mut failing = MockPersist::new();
+        failing.expect_batch_size().returning(|| None);
+        failing.expect_store().returning(|_| {
+            Err(PersistError::transient(anyhow::anyhow!(
+                "Connection refused"
+            )))
+        });
+        failing.expect_name().returning(|| "FailingStorage");
+
+        let multi = MultiPersist::new()
+            .with_store(storage.clone())
+            .with_store(failing);
+
+        let error = multi.store(Node::new("chunk")).await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "permanent error: Failed to store 1 nodes in FailingStorage: transient error: Connection refused"
+        );
+        assert_eq!(storage.get_all_values().await.len(), 1);
+    }
+
+    #[test]
+    fn test_split_batches_by_bytes() {
+        let nodes = vec![
+            Node::new("a".repeat(10)),
+            Node::new("b".repeat(10)),
+            Node::new("c".repeat(100)),
+            Node::new("d".repeat(10)),
+        ];
+
+        let sizes = |batches: Vec<&[Node]>| batches.iter().map(|b| b.len()).collect::<Vec<_>>();
+
+        assert_eq!(sizes(split_batches(&nodes, 3, None)), vec![3, 1]);
+        // Every node is 2 bytes of metadata json larger than its chunk
+        assert_eq!(sizes(split_batches(&nodes, 3, Some(30))), vec![2, 1, 1]);
+        assert_eq!(sizes(split_batches(&[], 3, Some(30))), Vec::<usize>::new());
+    }
+}
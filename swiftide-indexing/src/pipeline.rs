@@ -1,10 +1,15 @@
 use anyhow::Result;
-use futures_util::{StreamExt, TryFutureExt, TryStreamExt};
+use futures_util::{stream::BoxStream, StreamExt, TryFutureExt, TryStreamExt};
 use swiftide_core::{
-    indexing::IndexingDefaults, BatchableTransformer, ChunkerTransformer, Loader, NodeCache,
-    Persist, SimplePrompt, Transformer, WithBatchIndexingDefaults, WithIndexingDefaults,
+    events::{EventSender, PipelineEvent},
+    indexing::IndexingDefaults,
+    BatchableTransformer, CancellationToken, ChunkerTransformer, Loader, NodeCache, Persist,
+    SimplePrompt, Transformer, WithBatchIndexingDefaults, WithIndexingDefaults,
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task,
 };
-use tokio::{sync::mpsc, task};
 use tracing::Instrument;
 
 use std::{sync::Arc, time::Duration};
@@ -13,6 +18,8 @@ use swiftide_core::indexing::{EmbedMode, IndexingStream, Node};
 
 /// The default batch size for batch processing.
 const DEFAULT_BATCH_SIZE: usize = 256;
+/// The default number of nodes buffered by [`Pipeline::buffered`] and [`Pipeline::split_by`]
+const DEFAULT_BUFFER_SIZE: usize = 1000;
 
 /// A pipeline for indexing files, adding metadata, chunking, transforming, embedding, and then storing them.
 ///
@@ -24,6 +31,8 @@ const DEFAULT_BATCH_SIZE: usize = 256;
 /// * `stream` - The stream of `Node` items to be processed.
 /// * `storage` - Optional storage backend where the processed nodes will be stored.
 /// * `concurrency` - The level of concurrency for processing nodes.
+/// * `buffer_size` - The number of nodes buffered by [`Pipeline::buffered`] and [`Pipeline::split_by`].
+/// * `events` - Sends the events of the pipeline to its subscribers, see [`Pipeline::subscribe`].
 ///
 pub struct Pipeline {
     stream: IndexingStream,
@@ -31,6 +40,26 @@ pub struct Pipeline {
     concurrency: usize,
     indexing_defaults: IndexingDefaults,
     batch_size: usize,
+    buffer_size: usize,
+    events: EventSender,
+}
+
+/// An error from before a fork, shared by both forks of [`Pipeline::fork`]
+///
+/// The original error is the source, so that it is found when walking the chain.
+#[derive(Debug, Clone)]
+struct ForkedError(Arc<anyhow::Error>);
+
+impl std::fmt::Display for ForkedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed before the pipeline was forked")
+    }
+}
+
+impl std::error::Error for ForkedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&**self.0)
+    }
 }
 
 impl Default for Pipeline {
@@ -42,6 +71,8 @@ impl Default for Pipeline {
             concurrency: num_cpus::get(),
             indexing_defaults: IndexingDefaults::default(),
             batch_size: DEFAULT_BATCH_SIZE,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            events: EventSender::default(),
         }
     }
 }
@@ -88,6 +119,14 @@ impl Pipeline {
         }
     }
 
+    /// Subscribes to the events of the pipeline, see [`swiftide_core::events`].
+    ///
+    /// Pipelines split with [`Pipeline::split_by`] share their subscribers. When merging, the
+    /// subscribers of the pipeline merged into are kept.
+    pub fn subscribe(&self) -> broadcast::Receiver<PipelineEvent> {
+        self.events.subscribe()
+    }
+
     /// Sets the concurrency level for the pipeline. By default the concurrency is set to the
     /// number of cpus.
     ///
@@ -104,6 +143,22 @@ impl Pipeline {
         self
     }
 
+    /// Sets the number of nodes buffered by [`Pipeline::buffered`] and [`Pipeline::split_by`].
+    /// Defaults to 1000.
+    ///
+    /// Steps otherwise pull nodes on demand, with at most `concurrency` nodes in flight per step.
+    /// Lower the buffer size to bound memory use with big chunks and slow steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer size is 0
+    #[must_use]
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        assert!(buffer_size > 0, "Buffer size must be greater than 0");
+        self.buffer_size = buffer_size;
+        self
+    }
+
     /// Sets the embed mode for the pipeline. The embed mode controls what (combination) fields of a [`Node`]
     /// be embedded with a vector when transforming with [`crate::transformers::Embed`]
     ///
@@ -185,15 +240,18 @@ impl Pipeline {
         transformer.with_indexing_defaults(self.indexing_defaults.clone());
 
         let transformer = Arc::new(transformer);
+        let events = self.events.clone();
         self.stream = self
             .stream
             .map_ok(move |node| {
                 let transformer = transformer.clone();
+                let events = events.clone();
                 let span = tracing::trace_span!("then", node = ?node);
 
                 task::spawn(async move {
                     tracing::debug!(node = ?node, transformer = transformer.name(), "Transforming node");
-                    transformer.transform_node(node).await
+                    let node_id = node.id();
+                    events.observe(transformer.name(), Some(node_id), transformer.transform_node(node)).await
                 }.instrument(span.or_current())
                 )
                 .err_into::<anyhow::Error>()
@@ -227,11 +285,13 @@ impl Pipeline {
         transformer.with_indexing_defaults(self.indexing_defaults.clone());
 
         let transformer = Arc::new(transformer);
+        let events = self.events.clone();
         self.stream = self
             .stream
             .try_chunks(transformer.batch_size().unwrap_or(self.batch_size))
             .map_ok(move |nodes| {
                 let transformer = Arc::clone(&transformer);
+                let events = events.clone();
                 let span = tracing::trace_span!("then_in_batch",  nodes = ?nodes );
 
                 tokio::spawn(
@@ -241,7 +301,13 @@ impl Pipeline {
                             num_nodes = nodes.len(),
                             "Batch transforming nodes"
                         );
-                        transformer.batch_transform(nodes).await
+                        events
+                            .observe_stream(
+                                transformer.name(),
+                                nodes.len(),
+                                transformer.batch_transform(nodes),
+                            )
+                            .await
                     }
                     .instrument(span.or_current()),
                 )
@@ -249,7 +315,7 @@ impl Pipeline {
             })
             .err_into::<anyhow::Error>()
             .try_buffer_unordered(concurrency) // First get the streams from each future
-            .try_flatten_unordered(None) // Then flatten all the streams back into one
+            .try_flatten_unordered(concurrency) // Then flatten all the streams back into one
             .boxed()
             .into();
         self
@@ -268,16 +334,20 @@ impl Pipeline {
     pub fn then_chunk(mut self, chunker: impl ChunkerTransformer + 'static) -> Self {
         let chunker = Arc::new(chunker);
         let concurrency = chunker.concurrency().unwrap_or(self.concurrency);
+        let events = self.events.clone();
         self.stream = self
             .stream
             .map_ok(move |node| {
                 let chunker = Arc::clone(&chunker);
+                let events = events.clone();
                 let span = tracing::trace_span!("then_chunk", chunker = ?chunker, node = ?node );
 
                 tokio::spawn(
                     async move {
                         tracing::debug!(chunker = chunker.name(), "Chunking node");
-                        chunker.transform_node(node).await
+                        events
+                            .observe_stream(chunker.name(), 1, chunker.transform_node(node))
+                            .await
                     }
                     .instrument(span.or_current()),
                 )
@@ -285,7 +355,7 @@ impl Pipeline {
             })
             .err_into::<anyhow::Error>()
             .try_buffer_unordered(concurrency)
-            .try_flatten_unordered(None)
+            .try_flatten_unordered(concurrency)
             .boxed()
             .into();
 
@@ -309,19 +379,34 @@ impl Pipeline {
     #[must_use]
     pub fn then_store_with(mut self, storage: impl Persist + 'static) -> Self {
         let storage = Arc::new(storage);
+        let events = self.events.clone();
         self.storage.push(storage.clone());
         // add storage to the stream instead of doing it at the end
         if storage.batch_size().is_some() {
-            self.stream = self
-                .stream
-                .try_chunks(storage.batch_size().unwrap())
+            let batches = match storage.max_batch_bytes() {
+                Some(max_bytes) => {
+                    chunks_by_size(self.stream, storage.batch_size().unwrap(), max_bytes)
+                }
+                None => self
+                    .stream
+                    .try_chunks(storage.batch_size().unwrap())
+                    .err_into::<anyhow::Error>()
+                    .boxed(),
+            };
+
+            self.stream = batches
                 .map_ok(move |nodes| {
                     let storage = Arc::clone(&storage);
+                    let events = events.clone();
                     let span = tracing::trace_span!("then_store_with_batched", storage = ?storage, nodes = ?nodes );
 
                 tokio::spawn(async move {
                         tracing::debug!(storage = storage.name(), num_nodes = nodes.len(), "Batch Storing nodes");
-                        storage.batch_store(nodes).await
+                        let num_nodes = nodes.len();
+                        let stream = events.observe_stream(storage.name(), num_nodes, storage.batch_store(nodes)).await;
+                        events.send(PipelineEvent::BatchFlushed { storage: storage.name(), nodes: num_nodes });
+
+                        stream
                     }
                     .instrument(span.or_current())
                     )
@@ -330,13 +415,14 @@ impl Pipeline {
                 })
                 .err_into::<anyhow::Error>()
                 .try_buffer_unordered(self.concurrency)
-                .try_flatten_unordered(None)
+                .try_flatten_unordered(self.concurrency)
                 .boxed().into();
         } else {
             self.stream = self
                 .stream
                 .map_ok(move |node| {
                     let storage = Arc::clone(&storage);
+                    let events = events.clone();
                     let span =
                         tracing::trace_span!("then_store_with", storage = ?storage, node = ?node );
 
@@ -344,7 +430,14 @@ impl Pipeline {
                         async move {
                             tracing::debug!(storage = storage.name(), "Storing node");
 
-                            storage.store(node).await
+                            let node_id = node.id();
+                            events
+                                .observe(
+                                    storage.name(),
+                                    Some(node_id),
+                                    storage.store(node).err_into(),
+                                )
+                                .await
                         }
                         .instrument(span.or_current()),
                     )
@@ -359,6 +452,35 @@ impl Pipeline {
         self
     }
 
+    /// Decouples the steps before from the steps after with a bounded buffer.
+    ///
+    /// The steps before keep processing nodes while the steps after are busy, until
+    /// `buffer_size` nodes are buffered. Then they wait until there is room again. Useful before
+    /// a slow step, i.e. storage, so that the steps before it are not idle.
+    ///
+    /// Note that this is not lazy, the steps before start processing immediately.
+    #[must_use]
+    pub fn buffered(mut self) -> Self {
+        let (tx, rx) = mpsc::channel(self.buffer_size);
+
+        let mut stream = self.stream;
+        let span = tracing::trace_span!("buffered");
+        tokio::spawn(
+            async move {
+                while let Some(item) = stream.next().await {
+                    if tx.send(item).await.is_err() {
+                        tracing::debug!("Buffered stream dropped, stopping");
+                        break;
+                    }
+                }
+            }
+            .instrument(span.or_current()),
+        );
+
+        self.stream = rx.into();
+        self
+    }
+
     /// Splits the stream into two streams based on a predicate.
     ///
     /// Note that this is not lazy. It will start consuming the stream immediately
@@ -380,8 +502,8 @@ impl Pipeline {
     {
         let predicate = Arc::new(predicate);
 
-        let (left_tx, left_rx) = mpsc::channel(1000);
-        let (right_tx, right_rx) = mpsc::channel(1000);
+        let (left_tx, left_rx) = mpsc::channel(self.buffer_size);
+        let (right_tx, right_rx) = mpsc::channel(self.buffer_size);
 
         let stream = self.stream;
         let span = tracing::trace_span!("split_by");
@@ -419,6 +541,8 @@ impl Pipeline {
             concurrency: self.concurrency,
             indexing_defaults: self.indexing_defaults.clone(),
             batch_size: self.batch_size,
+            buffer_size: self.buffer_size,
+            events: self.events.clone(),
         };
 
         let right_pipeline = Self {
@@ -427,6 +551,108 @@ impl Pipeline {
             concurrency: self.concurrency,
             indexing_defaults: self.indexing_defaults.clone(),
             batch_size: self.batch_size,
+            buffer_size: self.buffer_size,
+            events: self.events.clone(),
+        };
+
+        (left_pipeline, right_pipeline)
+    }
+
+    /// Forks the stream into two streams that both receive every node.
+    ///
+    /// Use it to process the same loaded and chunked nodes in different ways without running the
+    /// loader and chunkers twice, i.e. one fork embeds the nodes and stores them in a vector
+    /// store, while the other stores the raw chunks.
+    ///
+    /// Like `split_by` this is not lazy, it starts consuming the stream immediately. Both forks
+    /// have a buffer, the slowest fork determines the pace of the other. If one fork is dropped,
+    /// the other still receives every node.
+    ///
+    /// Errors are sent to both forks. Errors cannot be cloned, so both forks get an error that
+    /// shares the original one as its source. Find the original with [`anyhow::Error::chain`],
+    /// i.e. `err.chain().find_map(|err| err.downcast_ref::<LoaderError>())`.
+    ///
+    /// The forks can be run concurrently, or merged back together with `merge` and run as one.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let (embedded, raw) = Pipeline::from_loader(loader)
+    ///     .then_chunk(ChunkMarkdown::from_chunk_range(10..512))
+    ///     .fork();
+    ///
+    /// embedded
+    ///     .then_in_batch(Embed::new(openai))
+    ///     .then_store_with(qdrant)
+    ///     .merge(raw.then_store_with(memory_storage))
+    ///     .run()
+    ///     .await?;
+    /// ```
+    #[must_use]
+    pub fn fork(self) -> (Self, Self) {
+        let (left_tx, left_rx) = mpsc::channel(self.buffer_size);
+        let (right_tx, right_rx) = mpsc::channel(self.buffer_size);
+
+        let mut stream = self.stream;
+        let span = tracing::trace_span!("fork");
+        tokio::spawn(
+            async move {
+                let mut left_tx = Some(left_tx);
+                let mut right_tx = Some(right_tx);
+
+                while let Some(item) = stream.next().await {
+                    let (left_item, right_item) = match item {
+                        Ok(node) => (Ok(node.clone()), Ok(node)),
+                        Err(err) => {
+                            let err = Arc::new(err);
+                            (
+                                Err(ForkedError(Arc::clone(&err)).into()),
+                                Err(ForkedError(err).into()),
+                            )
+                        }
+                    };
+
+                    if let Some(tx) = &left_tx {
+                        if tx.send(left_item).await.is_err() {
+                            tracing::debug!("Left fork dropped");
+                            left_tx = None;
+                        }
+                    }
+
+                    if let Some(tx) = &right_tx {
+                        if tx.send(right_item).await.is_err() {
+                            tracing::debug!("Right fork dropped");
+                            right_tx = None;
+                        }
+                    }
+
+                    if left_tx.is_none() && right_tx.is_none() {
+                        tracing::debug!("Both forks dropped, stopping");
+                        break;
+                    }
+                }
+            }
+            .instrument(span.or_current()),
+        );
+
+        let left_pipeline = Self {
+            stream: left_rx.into(),
+            storage: self.storage.clone(),
+            concurrency: self.concurrency,
+            indexing_defaults: self.indexing_defaults.clone(),
+            batch_size: self.batch_size,
+            buffer_size: self.buffer_size,
+            events: self.events.clone(),
+        };
+
+        let right_pipeline = Self {
+            stream: right_rx.into(),
+            storage: self.storage,
+            concurrency: self.concurrency,
+            indexing_defaults: self.indexing_defaults,
+            batch_size: self.batch_size,
+            buffer_size: self.buffer_size,
+            events: self.events,
         };
 
         (left_pipeline, right_pipeline)
@@ -434,13 +660,26 @@ impl Pipeline {
 
     /// Merges two streams into one
     ///
-    /// This is useful for merging two streams that have been split using the `split_by` method.
+    /// This is useful for merging two streams that have been split using the `split_by` or
+    /// `fork` methods.
+    ///
+    /// The storages of both pipelines are set up when the merged pipeline runs.
     ///
     /// The full stream can then be processed using the `run` method.
     #[must_use]
-    pub fn merge(self, other: Self) -> Self {
+    pub fn merge(mut self, other: Self) -> Self {
         let stream = tokio_stream::StreamExt::merge(self.stream, other.stream);
 
+        for storage in other.storage {
+            if !self
+                .storage
+                .iter()
+                .any(|existing| Arc::ptr_eq(existing, &storage))
+            {
+                self.storage.push(storage);
+            }
+        }
+
         Self {
             stream: stream.boxed().into(),
             ..self
@@ -544,8 +783,21 @@ impl Pipeline {
     /// # Errors
     ///
     /// Returns an error if no storage backend is configured or if any stage of the pipeline fails.
+    pub async fn run(self) -> Result<()> {
+        self.run_with_cancel(CancellationToken::new()).await
+    }
+
+    /// Runs the indexing pipeline until it completes or the token is cancelled.
+    ///
+    /// On cancellation the pipeline stops pulling nodes and returns `Ok`. Nodes that are still
+    /// being transformed are dropped, nodes that were already stored stay stored. With a node
+    /// cache, i.e. `filter_cached`, running the pipeline again continues where it left off.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no storage backend is configured or if any stage of the pipeline fails.
     #[tracing::instrument(skip_all, fields(total_nodes), name = "indexing_pipeline.run")]
-    pub async fn run(mut self) -> Result<()> {
+    pub async fn run_with_cancel(self, cancel: CancellationToken) -> Result<()> {
         tracing::info!(
             "Starting indexing pipeline with {} concurrency",
             self.concurrency
@@ -561,12 +813,37 @@ impl Pipeline {
             .into_iter()
             .map(|storage| async move { storage.setup().await })
             .collect::<Vec<_>>();
-        futures_util::future::try_join_all(setup_futures).await?;
 
-        let mut total_nodes = 0;
-        while self.stream.try_next().await?.is_some() {
-            total_nodes += 1;
-        }
+        let mut stream = self.stream;
+        let total_nodes = self
+            .events
+            .observe_cancellable_run(
+                &cancel,
+                async {
+                    futures_util::future::try_join_all(setup_futures).await?;
+
+                    let mut total_nodes = 0;
+                    loop {
+                        tokio::select! {
+                            biased;
+                            () = cancel.cancelled() => {
+                                tracing::warn!("Indexing pipeline cancelled");
+                                break;
+                            }
+                            node = stream.try_next() => {
+                                if node?.is_none() {
+                                    break;
+                                }
+                                total_nodes += 1;
+                            }
+                        }
+                    }
+
+                    Ok(total_nodes)
+                },
+                |total_nodes| *total_nodes,
+            )
+            .await?;
 
         let elapsed_in_seconds = now.elapsed().as_secs();
         tracing::warn!(
@@ -581,6 +858,54 @@ impl Pipeline {
     }
 }
 
+/// Chunks the stream into batches of at most `batch_size` nodes and `max_bytes` estimated bytes
+///
+/// A node that does not fit in the current batch starts the next one, a node larger than
+/// `max_bytes` is batched on its own. Errors are yielded after the nodes batched before them.
+fn chunks_by_size(
+    stream: IndexingStream,
+    batch_size: usize,
+    max_bytes: usize,
+) -> BoxStream<'static, Result<Vec<Node>>> {
+    futures_util::stream::unfold(
+        (stream, None::<Result<Node>>),
+        move |(mut stream, mut pending)| async move {
+            let mut batch = Vec::new();
+            let mut bytes = 0;
+
+            loop {
+                let next = match pending.take() {
+                    Some(item) => Some(item),
+                    None => stream.next().await,
+                };
+
+                match next {
+                    Some(Ok(node)) => {
+                        let size = node.payload_size();
+                        if !batch.is_empty() && bytes + size > max_bytes {
+                            return Some((Ok(batch), (stream, Some(Ok(node)))));
+                        }
+
+                        bytes += size;
+                        batch.push(node);
+
+                        if batch.len() >= batch_size {
+                            return Some((Ok(batch), (stream, None)));
+                        }
+                    }
+                    Some(Err(error)) if batch.is_empty() => {
+                        return Some((Err(error), (stream, None)));
+                    }
+                    Some(Err(error)) => return Some((Ok(batch), (stream, Some(Err(error))))),
+                    None if batch.is_empty() => return None,
+                    None => return Some((Ok(batch), (stream, None))),
+                }
+            }
+        },
+    )
+    .boxed()
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -657,6 +982,28 @@ mod tests {
         pipeline.run().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_chunks_by_size() {
+        let stream: IndexingStream = vec![
+            Ok(Node::new("a".repeat(10))),
+            Ok(Node::new("b".repeat(10))),
+            Ok(Node::new("c".repeat(100))),
+            Err(anyhow::anyhow!("Failed")),
+            Ok(Node::new("d".repeat(10))),
+            Ok(Node::new("e".repeat(10))),
+            Ok(Node::new("f".repeat(10))),
+        ]
+        .into();
+
+        // Every node is 2 bytes of metadata json larger than its chunk
+        let batches = chunks_by_size(stream, 2, 30)
+            .map(|batch| batch.map(|nodes| nodes.len()).map_err(|_| ()))
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(batches, vec![Ok(2), Ok(1), Err(()), Ok(2), Ok(1)]);
+    }
+
     #[tokio::test]
     async fn test_skipping_errors() {
         let mut loader = MockLoader::new();
@@ -747,6 +1094,69 @@ mod tests {
         assert_eq!(processed_node.chunk, "transformed");
     }
 
+    #[tokio::test]
+    async fn test_subscribe_to_events() {
+        let pipeline = Pipeline::from_stream(vec![Ok(Node::default())])
+            .then(|node: Node| Ok(node))
+            .then_store_with(MemoryStorage::default());
+        let mut receiver = pipeline.subscribe();
+
+        pipeline.run().await.unwrap();
+
+        let mut events = vec![];
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+
+        assert_eq!(events.first(), Some(&PipelineEvent::RunStarted));
+        assert!(matches!(
+            events.last(),
+            Some(PipelineEvent::RunFinished { total: 1, .. })
+        ));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, PipelineEvent::StepFinished { nodes: 1, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_buffered() {
+        let storage = MemoryStorage::default();
+        Pipeline::from_stream(vec![Ok(Node::new("first")), Ok(Node::new("second"))])
+            .with_buffer_size(1)
+            .buffered()
+            .then(|node: Node| Ok(node))
+            .then_store_with(storage.clone())
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(storage.get_all_values().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_with_cancel() {
+        let storage = MemoryStorage::default();
+        let pipeline = Pipeline::from_stream(vec![Ok(Node::default())])
+            .then(|node: Node| Ok(node))
+            .then_store_with(storage.clone());
+        let mut receiver = pipeline.subscribe();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        pipeline.run_with_cancel(cancel).await.unwrap();
+
+        assert!(storage.get("0").await.is_none());
+
+        let mut events = vec![];
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        assert!(matches!(
+            events.last(),
+            Some(PipelineEvent::RunCancelled { total: 0, .. })
+        ));
+    }
+
     #[tokio::test]
     async fn test_arbitrary_closures_as_batch_transformer() {
         let mut loader = MockLoader::new();
@@ -864,6 +1274,64 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_fork_and_merge() {
+        let embedded = MemoryStorage::default();
+        let raw = MemoryStorage::default();
+
+        let pipeline = Pipeline::from_stream(vec![Node::new("first"), Node::new("second")]);
+        let (left, right) = pipeline.fork();
+
+        left.then(move |mut node: Node| {
+            node.chunk = format!("embedded {}", node.chunk);
+            Ok(node)
+        })
+        .then_store_with(embedded.clone())
+        .merge(right.then_store_with(raw.clone()))
+        .run()
+        .await
+        .unwrap();
+
+        let mut embedded = embedded
+            .get_all_values()
+            .await
+            .into_iter()
+            .map(|node| node.chunk)
+            .collect::<Vec<_>>();
+        embedded.sort();
+        assert_eq!(embedded, vec!["embedded first", "embedded second"]);
+
+        let mut raw = raw
+            .get_all_values()
+            .await
+            .into_iter()
+            .map(|node| node.chunk)
+            .collect::<Vec<_>>();
+        raw.sort();
+        assert_eq!(raw, vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_fork_sends_errors_to_both_forks() {
+        let err = std::io::Error::new(std::io::ErrorKind::NotFound, "missing.md");
+        let pipeline = Pipeline::from_stream(vec![Err::<Node, _>(err.into())]);
+        let (left, right) = pipeline.fork();
+
+        for mut fork in [left, right] {
+            let err = fork.stream.next().await.unwrap().unwrap_err();
+
+            assert_eq!(
+                format!("{err:#}"),
+                "Failed before the pipeline was forked: missing.md"
+            );
+            let original = err
+                .chain()
+                .find_map(|err| err.downcast_ref::<std::io::Error>())
+                .unwrap();
+            assert_eq!(original.kind(), std::io::ErrorKind::NotFound);
+        }
+    }
+
     #[tokio::test]
     async fn test_all_steps_should_work_as_dyn_box() {
         let mut loader = MockLoader::new();
@@ -5,15 +5,23 @@ use anyhow::bail;
 use async_trait::async_trait;
 use swiftide_core::{
     indexing::{IndexingStream, Node},
-    BatchableTransformer, EmbeddingModel, WithBatchIndexingDefaults, WithIndexingDefaults,
+    BatchableTransformer, EmbeddingModel, EmbeddingPrefixes, WithBatchIndexingDefaults,
+    WithIndexingDefaults,
 };
 
 /// A transformer that can generate embeddings for an `Node`
 ///
 /// This file defines the `Embed` struct and its implementation of the `BatchableTransformer` trait.
+///
+/// Image nodes are passed through as is, they are embedded with
+/// [`super::EmbedImages`].
+///
+/// If the model has [`EmbeddingPrefixes`], the document prefix is prepended to everything that
+/// is embedded.
 #[derive(Clone)]
 pub struct Embed {
     embed_model: Arc<dyn EmbeddingModel>,
+    prefixes: Option<EmbeddingPrefixes>,
     concurrency: Option<usize>,
     batch_size: Option<usize>,
 }
@@ -21,6 +29,7 @@ pub struct Embed {
 impl std::fmt::Debug for Embed {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Embed")
+            .field("prefixes", &self.prefixes)
             .field("concurrency", &self.concurrency)
             .field("batch_size", &self.batch_size)
             .finish()
@@ -40,11 +49,19 @@ impl Embed {
     pub fn new(model: impl EmbeddingModel + 'static) -> Self {
         Self {
             embed_model: Arc::new(model),
+            prefixes: None,
             concurrency: None,
             batch_size: None,
         }
     }
 
+    /// Uses these prefixes instead of the prefixes configured on the model
+    #[must_use]
+    pub fn with_prefixes(mut self, prefixes: EmbeddingPrefixes) -> Self {
+        self.prefixes = Some(prefixes);
+        self
+    }
+
     #[must_use]
     pub fn with_concurrency(mut self, concurrency: usize) -> Self {
         self.concurrency = Some(concurrency);
@@ -95,7 +112,12 @@ impl BatchableTransformer for Embed {
         let embeddables_data = nodes
             .iter_mut()
             .fold(Vec::new(), |mut embeddables_data, node| {
-                let embeddables = node.as_embeddables();
+                // Images are embedded by `EmbedImages`
+                let embeddables = if node.is_image() {
+                    Vec::new()
+                } else {
+                    node.as_embeddables()
+                };
                 let mut embeddables_keys = Vec::with_capacity(embeddables.len());
                 for (embeddable_key, embeddable_data) in embeddables {
                     embeddables_keys.push(embeddable_key);
@@ -105,10 +127,22 @@ impl BatchableTransformer for Embed {
                 embeddables_data
             });
 
+        let embeddables_data = match self
+            .prefixes
+            .as_ref()
+            .or_else(|| self.embed_model.prefixes())
+        {
+            Some(prefixes) => embeddables_data
+                .into_iter()
+                .map(|data| prefixes.apply_document(data))
+                .collect(),
+            None => embeddables_data,
+        };
+
         // Embeddings vectors of every node stored in order of processed nodes.
         let mut embeddings = match self.embed_model.embed(embeddables_data).await {
             Ok(embeddngs) => VecDeque::from(embeddngs),
-            Err(err) => return err.into(),
+            Err(err) => return anyhow::Error::from(err).into(),
         };
 
         // Iterator of nodes with embeddings vectors map.
@@ -116,6 +150,9 @@ impl BatchableTransformer for Embed {
             let Some(embedding_keys) = embeddings_keys_groups.pop_front() else {
                 bail!("Missing embedding data");
             };
+            if node.is_image() {
+                return Ok(node);
+            }
             node.vectors = embedding_keys
                 .into_iter()
                 .map(|embedded_field| {
@@ -141,8 +178,9 @@ impl BatchableTransformer for Embed {
 
 #[cfg(test)]
 mod tests {
+    use swiftide_core::chat_completion::errors::LanguageModelError;
     use swiftide_core::indexing::{EmbedMode, EmbeddedField, Metadata, Node};
-    use swiftide_core::{BatchableTransformer, MockEmbeddingModel};
+    use swiftide_core::{BatchableTransformer, EmbeddingPrefixes, MockEmbeddingModel};
 
     use super::Embed;
 
@@ -314,7 +352,7 @@ mod tests {
         model_mock
             .expect_embed()
             .times(1)
-            .returning(|_| Err(anyhow::anyhow!("error")));
+            .returning(|_| Err(LanguageModelError::permanent("error")));
         let embed = Embed::new(model_mock);
         let mut stream = embed.batch_transform(test_nodes).await;
         let error = stream
@@ -325,4 +363,30 @@ mod tests {
 
         assert_eq!(error.to_string(), "error");
     }
+
+    #[tokio::test]
+    async fn test_applies_document_prefix() {
+        let node = Node::builder()
+            .chunk("chunk")
+            .embed_mode(EmbedMode::PerField)
+            .build()
+            .unwrap();
+        let mut model_mock = MockEmbeddingModel::new();
+        model_mock
+            .expect_embed()
+            .withf(|embeddables| *embeddables == ["passage: chunk"])
+            .times(1)
+            .returning(|_| Ok(vec![vec![1.0]]));
+
+        let embed = Embed::new(model_mock).with_prefixes(EmbeddingPrefixes::e5());
+        let node = embed
+            .batch_transform(vec![node])
+            .await
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(node.vectors.unwrap().contains_key(&EmbeddedField::Chunk));
+    }
 }
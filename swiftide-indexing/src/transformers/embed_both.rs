@@ -0,0 +1,233 @@
+//! Dense and sparse embeddings in a single step, for hybrid search
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use swiftide_core::{
+    indexing::{IndexingStream, Node},
+    BatchableTransformer, EmbeddingModel, SparseEmbeddingModel, WithBatchIndexingDefaults,
+    WithIndexingDefaults,
+};
+
+/// Generates dense and sparse embeddings for a batch of nodes
+///
+/// Both models embed the same batch concurrently. Equivalent to [`super::Embed`] followed by
+/// [`super::SparseEmbed`], in a single step.
+///
+/// # Example
+///
+/// ```ignore
+/// indexing::Pipeline::from_loader(FileLoader::new("./src").with_extensions(&["rs"]))
+///     .then_chunk(ChunkCode::try_for_language("rust")?)
+///     .then_in_batch(EmbedBoth::new(
+///         FastEmbed::try_default()?,
+///         FastEmbed::try_default_sparse()?,
+///     ))
+///     .then_store_with(qdrant)
+///     .run()
+///     .await?;
+/// ```
+#[derive(Clone)]
+pub struct EmbedBoth {
+    embed_model: Arc<dyn EmbeddingModel>,
+    sparse_embed_model: Arc<dyn SparseEmbeddingModel>,
+    concurrency: Option<usize>,
+    batch_size: Option<usize>,
+}
+
+impl std::fmt::Debug for EmbedBoth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbedBoth")
+            .field("concurrency", &self.concurrency)
+            .field("batch_size", &self.batch_size)
+            .finish()
+    }
+}
+
+impl EmbedBoth {
+    pub fn new(
+        model: impl EmbeddingModel + 'static,
+        sparse_model: impl SparseEmbeddingModel + 'static,
+    ) -> Self {
+        Self {
+            embed_model: Arc::new(model),
+            sparse_embed_model: Arc::new(sparse_model),
+            concurrency: None,
+            batch_size: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Sets the batch size for the transformer.
+    /// If the batch size is not set, the transformer will use the default batch size set by the pipeline
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+}
+
+impl WithBatchIndexingDefaults for EmbedBoth {}
+impl WithIndexingDefaults for EmbedBoth {}
+
+#[async_trait]
+impl BatchableTransformer for EmbedBoth {
+    #[tracing::instrument(skip_all, name = "transformers.embed_both")]
+    async fn batch_transform(&self, mut nodes: Vec<Node>) -> IndexingStream {
+        // EmbeddedFields grouped by node, and the embeddable data of all nodes, in order
+        let mut embeddings_keys_groups = Vec::with_capacity(nodes.len());
+        let mut embeddables_data = Vec::new();
+        for node in &nodes {
+            // Images are embedded by `EmbedImages`
+            let embeddables = if node.is_image() {
+                Vec::new()
+            } else {
+                node.as_embeddables()
+            };
+
+            let (keys, data): (Vec<_>, Vec<_>) = embeddables.into_iter().unzip();
+            embeddings_keys_groups.push(keys);
+            embeddables_data.extend(data);
+        }
+
+        // Only the dense model is instructed, sparse models match on terms
+        let dense_data = match self.embed_model.prefixes() {
+            Some(prefixes) => embeddables_data
+                .iter()
+                .map(|data| prefixes.apply_document(data.as_str()))
+                .collect(),
+            None => embeddables_data.clone(),
+        };
+
+        let expected = embeddables_data.len();
+        let (embeddings, sparse_embeddings) = match tokio::join!(
+            self.embed_model.embed(dense_data),
+            self.sparse_embed_model.sparse_embed(embeddables_data)
+        ) {
+            (Ok(embeddings), Ok(sparse_embeddings)) => (embeddings, sparse_embeddings),
+            (Err(err), _) | (_, Err(err)) => return anyhow::Error::from(err).into(),
+        };
+
+        if embeddings.len() != expected || sparse_embeddings.len() != expected {
+            return anyhow::anyhow!(
+                "Expected {expected} embeddings, got {} dense and {} sparse",
+                embeddings.len(),
+                sparse_embeddings.len()
+            )
+            .into();
+        }
+
+        let mut embeddings = embeddings.into_iter();
+        let mut sparse_embeddings = sparse_embeddings.into_iter();
+        for (node, keys) in nodes.iter_mut().zip(embeddings_keys_groups) {
+            if node.is_image() {
+                continue;
+            }
+
+            node.vectors = Some(keys.iter().cloned().zip(embeddings.by_ref()).collect());
+            node.sparse_vectors = Some(keys.into_iter().zip(sparse_embeddings.by_ref()).collect());
+        }
+
+        nodes.into()
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.concurrency
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures_util::StreamExt as _;
+    use swiftide_core::{
+        chat_completion::errors::LanguageModelError,
+        indexing::{EmbedMode, EmbeddedField},
+        MockEmbeddingModel, MockSparseEmbeddingModel, SparseEmbedding,
+    };
+
+    use super::*;
+
+    fn sparse(value: f32) -> SparseEmbedding {
+        SparseEmbedding {
+            indices: vec![0],
+            values: vec![value],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embeds_dense_and_sparse() {
+        let mut model = MockEmbeddingModel::new();
+        model
+            .expect_embed()
+            .withf(|input| *input == ["first", "second"])
+            .times(1)
+            .returning(|_| Ok(vec![vec![1.0], vec![2.0]]));
+
+        let mut sparse_model = MockSparseEmbeddingModel::new();
+        sparse_model
+            .expect_sparse_embed()
+            .withf(|input| *input == ["first", "second"])
+            .times(1)
+            .returning(|_| Ok(vec![sparse(1.0), sparse(2.0)]));
+
+        let nodes = ["first", "second"]
+            .into_iter()
+            .map(|chunk| {
+                Node::builder()
+                    .chunk(chunk)
+                    .embed_mode(EmbedMode::PerField)
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+
+        let nodes = EmbedBoth::new(model, sparse_model)
+            .batch_transform(nodes)
+            .await
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            nodes[1].vectors,
+            Some(HashMap::from([(EmbeddedField::Chunk, vec![2.0])]))
+        );
+        assert_eq!(
+            nodes[1].sparse_vectors,
+            Some(HashMap::from([(EmbeddedField::Chunk, sparse(2.0))]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors_if_either_model_fails() {
+        let mut model = MockEmbeddingModel::new();
+        model.expect_embed().returning(|_| Ok(vec![vec![1.0]]));
+
+        let mut sparse_model = MockSparseEmbeddingModel::new();
+        sparse_model
+            .expect_sparse_embed()
+            .returning(|_| Err(LanguageModelError::permanent("sparse failed")));
+
+        let error = EmbedBoth::new(model, sparse_model)
+            .batch_transform(vec![Node::new("chunk")])
+            .await
+            .next()
+            .await
+            .unwrap()
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "sparse failed");
+    }
+}
@@ -0,0 +1,148 @@
+//! Embeds image nodes with an image embedding model
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use swiftide_core::{
+    indexing::{EmbeddedField, IndexingStream, Node},
+    BatchableTransformer, ImageEmbeddingModel, WithBatchIndexingDefaults, WithIndexingDefaults,
+};
+
+/// Embeds the image at the path of image nodes into [`EmbeddedField::Combined`]
+///
+/// Other nodes are passed through as is. Combined with [`super::Embed`] and a model that embeds
+/// images and text in the same space, e.g. CLIP with fastembed, text and images can be stored in
+/// the same collection.
+///
+/// # Example
+///
+/// ```ignore
+/// indexing::Pipeline::from_loader(FileLoader::new("./docs").with_extensions(&["md", "png"]))
+///     .then_in_batch(Embed::new(FastEmbed::builder().embedding_model(clip_text).build()?))
+///     .then_in_batch(EmbedImages::new(FastEmbed::try_default_image()?))
+///     .then_store_with(qdrant)
+///     .run()
+///     .await?;
+/// ```
+#[derive(Clone)]
+pub struct EmbedImages {
+    embed_model: Arc<dyn ImageEmbeddingModel>,
+    concurrency: Option<usize>,
+    batch_size: Option<usize>,
+}
+
+impl std::fmt::Debug for EmbedImages {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbedImages")
+            .field("concurrency", &self.concurrency)
+            .field("batch_size", &self.batch_size)
+            .finish()
+    }
+}
+
+impl EmbedImages {
+    pub fn new(model: impl ImageEmbeddingModel + 'static) -> Self {
+        Self {
+            embed_model: Arc::new(model),
+            concurrency: None,
+            batch_size: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Sets the batch size for the transformer.
+    /// If the batch size is not set, the transformer will use the default batch size set by the pipeline
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+}
+
+impl WithBatchIndexingDefaults for EmbedImages {}
+impl WithIndexingDefaults for EmbedImages {}
+
+#[async_trait]
+impl BatchableTransformer for EmbedImages {
+    #[tracing::instrument(skip_all, name = "transformers.embed_images")]
+    async fn batch_transform(&self, mut nodes: Vec<Node>) -> IndexingStream {
+        let images = nodes
+            .iter()
+            .filter(|node| node.is_image())
+            .map(|node| node.path.clone())
+            .collect::<Vec<_>>();
+
+        if images.is_empty() {
+            return nodes.into();
+        }
+
+        let embeddings = match self.embed_model.embed_images(images).await {
+            Ok(embeddings) => embeddings,
+            Err(err) => return anyhow::Error::from(err).into(),
+        };
+
+        let mut embeddings = embeddings.into_iter();
+        for node in nodes.iter_mut().filter(|node| node.is_image()) {
+            let Some(embedding) = embeddings.next() else {
+                return anyhow::anyhow!("Missing embedding for image {}", node.path.display())
+                    .into();
+            };
+
+            node.vectors
+                .get_or_insert_with(Default::default)
+                .insert(EmbeddedField::Combined, embedding);
+        }
+
+        nodes.into()
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.concurrency
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use futures_util::StreamExt as _;
+    use swiftide_core::MockImageEmbeddingModel;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_only_embeds_images() {
+        let mut model = MockImageEmbeddingModel::new();
+        model
+            .expect_embed_images()
+            .withf(|images| *images == [PathBuf::from("cat.png")])
+            .times(1)
+            .returning(|_| Ok(vec![vec![1.0, 2.0]]));
+
+        let image = Node::builder().path("cat.png").chunk("").build().unwrap();
+        let text = Node::new("A cat");
+
+        let nodes = EmbedImages::new(model)
+            .batch_transform(vec![text.clone(), image])
+            .await
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(nodes[0], text);
+        assert_eq!(
+            nodes[1].vectors,
+            Some(HashMap::from([(EmbeddedField::Combined, vec![1.0, 2.0])]))
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Renders a template over a node into its metadata or a prefix of its chunk, without an LLM
+//!
+//! Covers enrichment that only needs what is already known about a node, e.g. the file name as a
+//! title or a breadcrumb of headings prepended to the chunk.
+//!
+//! The template is rendered with tera and has in its context:
+//! - `path`: the path of the node
+//! - `metadata`: the metadata of the node
+//! - `excerpt`: the first characters of the chunk, see `excerpt_length`
+//! - `node`: the full node, like in prompts
+//!
+//! # Example
+//!
+//! ```
+//! # use swiftide_core::{indexing::{Metadata, Node}, Transformer};
+//! # use swiftide_indexing::transformers::MetadataTemplate;
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let transformer = MetadataTemplate::builder()
+//!     .template("{{ path | split(pat='/') | last }} ({{ metadata.language }})")
+//!     .metadata_field("Title")
+//!     .build()?;
+//!
+//! let node = Node::builder()
+//!     .chunk("fn main() {}")
+//!     .path("src/main.rs")
+//!     .metadata(Metadata::from(("language", "rust")))
+//!     .build()?;
+//!
+//! let node = transformer.transform_node(node).await?;
+//! assert_eq!(node.metadata.get("Title").unwrap(), "main.rs (rust)");
+//! # Ok(())
+//! # }
+//! ```
+use anyhow::Result;
+use async_trait::async_trait;
+use derive_builder::Builder;
+use swiftide_core::{indexing::Node, template::Template, util::safe_truncate_utf8, Transformer};
+
+const DEFAULT_EXCERPT_LENGTH: usize = 500;
+
+/// Where the rendered template goes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateTarget {
+    /// Inserted as metadata with this name
+    Metadata(String),
+    /// Prepended to the chunk, on a line of its own
+    ChunkPrefix,
+}
+
+/// Renders a template over a node into a metadata field or a chunk prefix
+///
+/// Nothing is added if the template renders to an empty string.
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct MetadataTemplate {
+    /// The template to render, with tera
+    template: Template,
+
+    /// Where the rendered template goes
+    #[builder(setter(custom))]
+    target: TemplateTarget,
+
+    /// Number of characters of the chunk that are available as `excerpt`
+    ///
+    /// Defaults to [`DEFAULT_EXCERPT_LENGTH`]
+    #[builder(default = "DEFAULT_EXCERPT_LENGTH")]
+    excerpt_length: usize,
+
+    #[builder(default)]
+    concurrency: Option<usize>,
+}
+
+impl MetadataTemplateBuilder {
+    /// Inserts the rendered template as metadata with this name
+    pub fn metadata_field(&mut self, name: impl Into<String>) -> &mut Self {
+        self.target = Some(TemplateTarget::Metadata(name.into()));
+        self
+    }
+
+    /// Prepends the rendered template to the chunk, on a line of its own
+    pub fn chunk_prefix(&mut self) -> &mut Self {
+        self.target = Some(TemplateTarget::ChunkPrefix);
+        self
+    }
+}
+
+impl MetadataTemplate {
+    pub fn builder() -> MetadataTemplateBuilder {
+        MetadataTemplateBuilder::default()
+    }
+
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+}
+
+#[async_trait]
+impl Transformer for MetadataTemplate {
+    #[tracing::instrument(skip_all, name = "transformers.metadata_template")]
+    async fn transform_node(&self, mut node: Node) -> Result<Node> {
+        let rendered = self
+            .template
+            .to_prompt()
+            .with_node(&node)
+            .with_context_value("path", node.path.to_string_lossy().as_ref())
+            .with_context_value("metadata", serde_json::to_value(&node.metadata)?)
+            .with_context_value(
+                "excerpt",
+                safe_truncate_utf8(&node.chunk, self.excerpt_length),
+            )
+            .render()
+            .await?;
+
+        let rendered = rendered.trim();
+        if rendered.is_empty() {
+            return Ok(node);
+        }
+
+        match &self.target {
+            TemplateTarget::Metadata(name) => node.metadata.insert(name, rendered),
+            TemplateTarget::ChunkPrefix => node.chunk = format!("{rendered}\n{}", node.chunk),
+        }
+
+        Ok(node)
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.concurrency
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_chunk_prefix_from_excerpt() {
+        let transformer = MetadataTemplate::builder()
+            .template("# {{ excerpt | upper }}")
+            .excerpt_length(5usize)
+            .chunk_prefix()
+            .build()
+            .unwrap();
+
+        let node = transformer
+            .transform_node(Node::new("hello world"))
+            .await
+            .unwrap();
+
+        assert_eq!(node.chunk, "# HELLO\nhello world");
+    }
+
+    #[tokio::test]
+    async fn test_empty_render_is_skipped() {
+        let transformer = MetadataTemplate::builder()
+            .template("{% if metadata.language %}{{ metadata.language }}{% endif %}")
+            .metadata_field("Language")
+            .build()
+            .unwrap();
+
+        let node = transformer.transform_node(Node::new("text")).await.unwrap();
+
+        assert!(node.metadata.get("Language").is_none());
+    }
+}
@@ -11,17 +11,25 @@
 pub mod chunk_markdown;
 pub mod chunk_text;
 pub mod embed;
+pub mod embed_both;
+pub mod embed_images;
 pub mod metadata_keywords;
 pub mod metadata_qa_text;
 pub mod metadata_summary;
+pub mod metadata_template;
 pub mod metadata_title;
+pub mod multi_embed;
 pub mod sparse_embed;
 
 pub use chunk_markdown::ChunkMarkdown;
 pub use chunk_text::ChunkText;
 pub use embed::Embed;
+pub use embed_both::EmbedBoth;
+pub use embed_images::EmbedImages;
 pub use metadata_keywords::MetadataKeywords;
 pub use metadata_qa_text::MetadataQAText;
 pub use metadata_summary::MetadataSummary;
+pub use metadata_template::{MetadataTemplate, TemplateTarget};
 pub use metadata_title::MetadataTitle;
+pub use multi_embed::MultiEmbed;
 pub use sparse_embed::SparseEmbed;
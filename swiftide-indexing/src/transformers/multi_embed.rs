@@ -0,0 +1,143 @@
+//! Multi-vector embeddings, an embedding per token, for late interaction retrieval
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use swiftide_core::{
+    indexing::{IndexingStream, Node},
+    BatchableTransformer, MultiEmbeddingModel, WithBatchIndexingDefaults, WithIndexingDefaults,
+};
+
+/// Generates multi-vector embeddings, like `ColBERT`, for a batch of nodes
+///
+/// Stored in [`Node::multi_vectors`], for every embeddable of the node.
+#[derive(Clone)]
+pub struct MultiEmbed {
+    embed_model: Arc<dyn MultiEmbeddingModel>,
+    concurrency: Option<usize>,
+    batch_size: Option<usize>,
+}
+
+impl std::fmt::Debug for MultiEmbed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiEmbed")
+            .field("concurrency", &self.concurrency)
+            .field("batch_size", &self.batch_size)
+            .finish()
+    }
+}
+
+impl MultiEmbed {
+    pub fn new(model: impl MultiEmbeddingModel + 'static) -> Self {
+        Self {
+            embed_model: Arc::new(model),
+            concurrency: None,
+            batch_size: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Sets the batch size for the transformer.
+    /// If the batch size is not set, the transformer will use the default batch size set by the pipeline
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+}
+
+impl WithBatchIndexingDefaults for MultiEmbed {}
+impl WithIndexingDefaults for MultiEmbed {}
+
+#[async_trait]
+impl BatchableTransformer for MultiEmbed {
+    #[tracing::instrument(skip_all, name = "transformers.multi_embed")]
+    async fn batch_transform(&self, mut nodes: Vec<Node>) -> IndexingStream {
+        // EmbeddedFields grouped by node, and the embeddable data of all nodes, in order
+        let mut embeddings_keys_groups = Vec::with_capacity(nodes.len());
+        let mut embeddables_data = Vec::new();
+        for node in &nodes {
+            // Images are embedded by `EmbedImages`
+            let embeddables = if node.is_image() {
+                Vec::new()
+            } else {
+                node.as_embeddables()
+            };
+
+            let (keys, data): (Vec<_>, Vec<_>) = embeddables.into_iter().unzip();
+            embeddings_keys_groups.push(keys);
+            embeddables_data.extend(data);
+        }
+
+        let expected = embeddables_data.len();
+        let embeddings = match self.embed_model.multi_embed(embeddables_data).await {
+            Ok(embeddings) => embeddings,
+            Err(err) => return anyhow::Error::from(err).into(),
+        };
+
+        if embeddings.len() != expected {
+            return anyhow::anyhow!("Expected {expected} embeddings, got {}", embeddings.len())
+                .into();
+        }
+
+        let mut embeddings = embeddings.into_iter();
+        for (node, keys) in nodes.iter_mut().zip(embeddings_keys_groups) {
+            if node.is_image() {
+                continue;
+            }
+
+            node.multi_vectors = Some(keys.into_iter().zip(embeddings.by_ref()).collect());
+        }
+
+        nodes.into()
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.concurrency
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures_util::StreamExt as _;
+    use swiftide_core::{indexing::EmbeddedField, MockMultiEmbeddingModel};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_multi_embeds_nodes() {
+        let mut model = MockMultiEmbeddingModel::new();
+        model
+            .expect_multi_embed()
+            .withf(|input| input.len() == 1)
+            .times(1)
+            .returning(|_| Ok(vec![vec![vec![1.0], vec![2.0]]]));
+
+        let nodes = MultiEmbed::new(model)
+            .batch_transform(vec![Node::new("first")])
+            .await
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            nodes[0].multi_vectors,
+            Some(HashMap::from([(
+                EmbeddedField::Combined,
+                vec![vec![1.0], vec![2.0]]
+            )]))
+        );
+    }
+}
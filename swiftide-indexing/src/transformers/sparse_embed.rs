@@ -107,7 +107,7 @@ impl BatchableTransformer for SparseEmbed {
         // SparseEmbeddings vectors of every node stored in order of processed nodes.
         let mut embeddings = match self.embed_model.sparse_embed(embeddables_data).await {
             Ok(embeddngs) => VecDeque::from(embeddngs),
-            Err(err) => return err.into(),
+            Err(err) => return anyhow::Error::from(err).into(),
         };
 
         // Iterator of nodes with embeddings vectors map.
@@ -140,6 +140,7 @@ impl BatchableTransformer for SparseEmbed {
 
 #[cfg(test)]
 mod tests {
+    use swiftide_core::chat_completion::errors::LanguageModelError;
     use swiftide_core::indexing::{EmbedMode, EmbeddedField, Metadata, Node};
     use swiftide_core::{
         BatchableTransformer, MockSparseEmbeddingModel, SparseEmbedding, SparseEmbeddings,
@@ -341,7 +342,7 @@ mod tests {
         model_mock
             .expect_sparse_embed()
             .times(1)
-            .returning(|_| Err(anyhow::anyhow!("error")));
+            .returning(|_| Err(LanguageModelError::permanent("error")));
         let embed = SparseEmbed::new(model_mock);
         let mut stream = embed.batch_transform(test_nodes).await;
         let error = stream
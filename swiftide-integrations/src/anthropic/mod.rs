@@ -0,0 +1,13 @@
+//! This module provides integration with `Anthropic`'s API.
+//!
+//! Currently it provides [`AnthropicTokens`], which counts the tokens of Claude models with the
+//! token counting endpoint, for precise numbers in rate limits and context windows.
+//!
+//! The module is conditionally compiled based on the "anthropic" feature flag.
+
+mod tokenizer;
+
+pub use tokenizer::{AnthropicTokens, AnthropicTokensBuilder};
+
+const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
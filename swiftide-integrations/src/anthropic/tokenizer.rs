@@ -0,0 +1,130 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use derive_builder::Builder;
+use secrecy::{ExposeSecret as _, SecretString};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use swiftide_core::tokenizer::{Estimatable, EstimateTokens};
+
+use super::{ANTHROPIC_API_BASE, ANTHROPIC_VERSION};
+
+/// Counts tokens of Claude models with the `Anthropic` token counting endpoint
+///
+/// By default it will look for an `ANTHROPIC_API_KEY` environment variable. Every estimate is a
+/// request to the api; the text is counted as a single user message.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_core::tokenizer::EstimateTokens;
+/// # use swiftide_integrations::anthropic::AnthropicTokens;
+/// # async fn run() -> anyhow::Result<()> {
+/// let anthropic = AnthropicTokens::builder()
+///     .model("claude-3-5-sonnet-latest")
+///     .build()?;
+/// let tokens = anthropic.estimate(&"Hello, world!").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct AnthropicTokens {
+    /// The model to count tokens for, i.e. `claude-3-5-sonnet-latest`
+    model: String,
+    #[builder(default = "default_api_key()")]
+    api_key: SecretString,
+    #[builder(default = "ANTHROPIC_API_BASE.to_string()")]
+    api_base: String,
+    #[builder(default)]
+    http_client: reqwest::Client,
+}
+
+impl AnthropicTokens {
+    pub fn builder() -> AnthropicTokensBuilder {
+        AnthropicTokensBuilder::default()
+    }
+}
+
+fn default_api_key() -> SecretString {
+    std::env::var("ANTHROPIC_API_KEY")
+        .unwrap_or_default()
+        .into()
+}
+
+#[derive(Serialize, Debug)]
+struct CountTokensRequest<'a> {
+    model: &'a str,
+    messages: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct CountTokensResponse {
+    input_tokens: usize,
+}
+
+#[async_trait]
+impl EstimateTokens for AnthropicTokens {
+    #[tracing::instrument(skip_all, err)]
+    async fn estimate(&self, value: &dyn Estimatable) -> Result<usize> {
+        let text = value.for_estimate().await?.join("\n");
+
+        // The api rejects empty messages
+        if text.is_empty() {
+            return Ok(value.additional_tokens());
+        }
+
+        let request = CountTokensRequest {
+            model: &self.model,
+            messages: json!([{ "role": "user", "content": text }]),
+        };
+
+        let response = self
+            .http_client
+            .post(format!("{}/messages/count_tokens", self.api_base))
+            .header("x-api-key", self.api_key.expose_secret())
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<CountTokensResponse>()
+            .await?;
+
+        Ok(response.input_tokens + value.additional_tokens())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_estimate() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/messages/count_tokens"))
+            .and(header("x-api-key", "test"))
+            .and(body_partial_json(json!({
+                "model": "claude-3-5-sonnet-latest",
+                "messages": [{ "role": "user", "content": "hello world" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "input_tokens": 9 })))
+            .mount(&mock_server)
+            .await;
+
+        let anthropic = AnthropicTokens::builder()
+            .model("claude-3-5-sonnet-latest")
+            .api_key("test")
+            .api_base(mock_server.uri())
+            .build()
+            .unwrap();
+
+        assert_eq!(anthropic.estimate(&"hello world").await.unwrap(), 9);
+        assert_eq!(anthropic.estimate(&"").await.unwrap(), 0);
+    }
+}
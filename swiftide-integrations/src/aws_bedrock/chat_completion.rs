@@ -0,0 +1,625 @@
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::{
+    operation::converse::ConverseOutput,
+    primitives::Blob,
+    types::{
+        AutoToolChoiceSchema, ContentBlock, ContentBlockDelta, ContentBlockStart, ConversationRole,
+        ConverseOutput as ConverseOutputType, ConverseStreamOutput, ImageBlock, ImageFormat,
+        ImageSource, Message, SystemContentBlock, TokenUsage, Tool, ToolChoice, ToolConfiguration,
+        ToolInputSchema, ToolResultBlock, ToolResultContentBlock, ToolSpecification, ToolUseBlock,
+    },
+};
+use aws_smithy_types::{Document, Number};
+use futures_util::StreamExt as _;
+use itertools::Itertools;
+use serde_json::json;
+use swiftide_core::chat_completion::{
+    errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+    ChatCompletionStream, ChatMessage, ContentPart, ImageContent, ToolCall, ToolSpec, Usage,
+};
+
+use super::{AwsBedrock, ConverseRequest};
+
+#[async_trait]
+impl ChatCompletion for AwsBedrock {
+    #[tracing::instrument(skip_all)]
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let converse_request = self.build_converse_request(request)?;
+
+        tracing::debug!(model = &self.model_id, request = ?converse_request, "Sending request to Bedrock");
+
+        let response = self
+            .client
+            .send_converse(&self.model_id, converse_request)
+            .await?;
+
+        tracing::debug!(?response, "Received response from Bedrock");
+
+        response_from_converse_output(response).map_err(LanguageModelError::from)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        let converse_request = match self.build_converse_request(request) {
+            Ok(converse_request) => converse_request,
+            Err(err) => {
+                return Box::pin(futures_util::stream::once(async move { Err(err.into()) }))
+            }
+        };
+
+        let events = match self
+            .client
+            .send_converse_stream(&self.model_id, converse_request)
+            .await
+        {
+            Ok(events) => events,
+            Err(err) => return Box::pin(futures_util::stream::once(async move { Err(err) })),
+        };
+
+        let stream = events
+            .scan(StreamAccumulator::default(), |accumulator, event| {
+                let item = match event {
+                    Ok(event) => accumulator
+                        .apply(event)
+                        .then(|| accumulator.response().map_err(LanguageModelError::from)),
+                    Err(err) => Some(Err(err)),
+                };
+
+                futures_util::future::ready(Some(item))
+            })
+            .filter_map(futures_util::future::ready);
+
+        Box::pin(stream)
+    }
+}
+
+impl AwsBedrock {
+    fn build_converse_request(&self, request: &ChatCompletionRequest) -> Result<ConverseRequest> {
+        let mut system = Vec::new();
+        let mut messages: Vec<(ConversationRole, Vec<ContentBlock>)> = Vec::new();
+
+        for message in request.messages() {
+            let (role, content) = match message {
+                ChatMessage::System(msg) => {
+                    system.push(SystemContentBlock::Text(msg.to_string()));
+                    continue;
+                }
+                ChatMessage::User(msg) => (
+                    ConversationRole::User,
+                    vec![ContentBlock::Text(msg.to_string())],
+                ),
+                ChatMessage::UserWithParts(parts) => (
+                    ConversationRole::User,
+                    parts
+                        .iter()
+                        .map(|part| match part {
+                            ContentPart::Text(text) => Ok(ContentBlock::Text(text.to_string())),
+                            ContentPart::Image(image) => {
+                                image_to_bedrock(image).map(ContentBlock::Image)
+                            }
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                ),
+                ChatMessage::Summary(msg) => (
+                    ConversationRole::Assistant,
+                    vec![ContentBlock::Text(msg.to_string())],
+                ),
+                ChatMessage::Assistant(msg, tool_calls) => {
+                    let mut content = Vec::new();
+                    if let Some(msg) = msg {
+                        content.push(ContentBlock::Text(msg.to_string()));
+                    }
+                    for tool_call in tool_calls.iter().flatten() {
+                        content.push(ContentBlock::ToolUse(tool_use_from_tool_call(tool_call)?));
+                    }
+                    (ConversationRole::Assistant, content)
+                }
+                ChatMessage::ToolOutput(tool_call, tool_output) => {
+                    let content = match tool_output.image() {
+                        Some(image) => ToolResultContentBlock::Image(image_to_bedrock(image)?),
+                        None => ToolResultContentBlock::Text(
+                            tool_output.content().unwrap_or_default().to_string(),
+                        ),
+                    };
+                    let tool_result = ToolResultBlock::builder()
+                        .tool_use_id(tool_call.id())
+                        .content(content)
+                        .build()?;
+                    (
+                        ConversationRole::User,
+                        vec![ContentBlock::ToolResult(tool_result)],
+                    )
+                }
+            };
+
+            // Bedrock requires roles to alternate, i.e. multiple tool results go into a single
+            // user message
+            match messages.last_mut() {
+                Some((last_role, last_content)) if *last_role == role => {
+                    last_content.extend(content);
+                }
+                _ => messages.push((role, content)),
+            }
+        }
+
+        let messages = messages
+            .into_iter()
+            .map(|(role, content)| {
+                Message::builder()
+                    .role(role)
+                    .set_content(Some(content))
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let tool_config = if request.tools_spec().is_empty() {
+            None
+        } else {
+            Some(
+                ToolConfiguration::builder()
+                    .set_tools(Some(
+                        request
+                            .tools_spec()
+                            .iter()
+                            .map(tool_from_spec)
+                            .collect::<Result<Vec<_>>>()?,
+                    ))
+                    .tool_choice(ToolChoice::Auto(AutoToolChoiceSchema::builder().build()))
+                    .build()?,
+            )
+        };
+
+        Ok(ConverseRequest {
+            messages,
+            system,
+            tool_config,
+            inference_config: self.inference_config(),
+        })
+    }
+}
+
+fn tool_from_spec(spec: &ToolSpec) -> Result<Tool> {
+    let mut properties = serde_json::Map::new();
+
+    for param in &spec.parameters {
+        properties.insert(param.name.to_string(), param.json_schema());
+    }
+
+    let schema = json!({
+        "type": "object",
+        "properties": properties,
+        "required": spec.parameters.iter().filter(|param| param.required).map(|param| param.name).collect_vec(),
+    });
+
+    Ok(Tool::ToolSpec(
+        ToolSpecification::builder()
+            .name(spec.name)
+            .description(spec.description)
+            .input_schema(ToolInputSchema::Json(json_to_document(schema)))
+            .build()?,
+    ))
+}
+
+/// Bedrock only accepts inline images
+fn image_to_bedrock(image: &ImageContent) -> Result<ImageBlock> {
+    let ImageContent::Base64 { media_type, data } = image else {
+        anyhow::bail!("Bedrock only supports base64 encoded images");
+    };
+
+    let format = match media_type.as_str() {
+        "image/png" => ImageFormat::Png,
+        "image/jpeg" | "image/jpg" => ImageFormat::Jpeg,
+        "image/gif" => ImageFormat::Gif,
+        "image/webp" => ImageFormat::Webp,
+        other => anyhow::bail!("Unsupported image type for Bedrock: {other}"),
+    };
+
+    let bytes = aws_smithy_types::base64::decode(data).context("Invalid base64 image")?;
+
+    ImageBlock::builder()
+        .format(format)
+        .source(ImageSource::Bytes(Blob::new(bytes)))
+        .build()
+        .map_err(anyhow::Error::from)
+}
+
+fn tool_use_from_tool_call(tool_call: &ToolCall) -> Result<ToolUseBlock> {
+    let input = match tool_call.args() {
+        Some(args) => serde_json::from_str(args).context("Invalid tool call arguments")?,
+        None => json!({}),
+    };
+
+    ToolUseBlock::builder()
+        .tool_use_id(tool_call.id())
+        .name(tool_call.name())
+        .input(json_to_document(input))
+        .build()
+        .map_err(anyhow::Error::from)
+}
+
+fn tool_call_from_tool_use(tool_use: &ToolUseBlock) -> ToolCall {
+    ToolCall::builder()
+        .id(tool_use.tool_use_id())
+        .name(tool_use.name())
+        .args(document_to_json(tool_use.input()).to_string())
+        .build()
+        .expect("infallible")
+}
+
+fn usage_from_token_usage(usage: &TokenUsage) -> Usage {
+    Usage {
+        prompt_tokens: u32::try_from(usage.input_tokens()).unwrap_or_default(),
+        completion_tokens: u32::try_from(usage.output_tokens()).unwrap_or_default(),
+        total_tokens: u32::try_from(usage.total_tokens()).unwrap_or_default(),
+        cached_prompt_tokens: None,
+    }
+}
+
+fn response_from_converse_output(output: ConverseOutput) -> Result<ChatCompletionResponse> {
+    let Some(ConverseOutputType::Message(message)) = output.output() else {
+        anyhow::bail!("Expected a message in the Bedrock response");
+    };
+
+    let text = message
+        .content()
+        .iter()
+        .filter_map(|block| block.as_text().ok())
+        .join("");
+
+    let tool_calls = message
+        .content()
+        .iter()
+        .filter_map(|block| block.as_tool_use().ok())
+        .map(tool_call_from_tool_use)
+        .collect_vec();
+
+    ChatCompletionResponse::builder()
+        .maybe_message((!text.is_empty()).then_some(text))
+        .maybe_tool_calls((!tool_calls.is_empty()).then_some(tool_calls))
+        .maybe_usage(output.usage().map(usage_from_token_usage))
+        .build()
+}
+
+/// Accumulates Converse stream events into a chat completion response
+#[derive(Debug, Default)]
+struct StreamAccumulator {
+    message: String,
+    delta: Option<String>,
+    /// Tool uses by content block index, with the id, name and partial json input
+    tool_uses: HashMap<i32, (String, String, String)>,
+    usage: Option<Usage>,
+}
+
+impl StreamAccumulator {
+    /// Applies an event, returns `false` if the event does not change the response
+    fn apply(&mut self, event: ConverseStreamOutput) -> bool {
+        self.delta = None;
+
+        match event {
+            ConverseStreamOutput::ContentBlockStart(event) => {
+                if let Some(ContentBlockStart::ToolUse(tool_use)) = event.start() {
+                    self.tool_uses.insert(
+                        event.content_block_index(),
+                        (
+                            tool_use.tool_use_id().to_string(),
+                            tool_use.name().to_string(),
+                            String::new(),
+                        ),
+                    );
+                }
+                false
+            }
+            ConverseStreamOutput::ContentBlockDelta(event) => match event.delta() {
+                Some(ContentBlockDelta::Text(text)) => {
+                    self.message.push_str(text);
+                    self.delta = Some(text.to_string());
+                    true
+                }
+                Some(ContentBlockDelta::ToolUse(delta)) => {
+                    if let Some((_, _, input)) =
+                        self.tool_uses.get_mut(&event.content_block_index())
+                    {
+                        input.push_str(delta.input());
+                    }
+                    false
+                }
+                _ => false,
+            },
+            ConverseStreamOutput::ContentBlockStop(event) => {
+                self.tool_uses.contains_key(&event.content_block_index())
+            }
+            ConverseStreamOutput::Metadata(event) => {
+                self.usage = event.usage().map(usage_from_token_usage);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn response(&self) -> Result<ChatCompletionResponse> {
+        let tool_calls = self
+            .tool_uses
+            .iter()
+            .sorted_by_key(|(index, _)| **index)
+            .map(|(_, (id, name, input))| {
+                ToolCall::builder()
+                    .id(id)
+                    .name(name)
+                    .args(if input.is_empty() {
+                        "{}"
+                    } else {
+                        input.as_str()
+                    })
+                    .build()
+                    .expect("infallible")
+            })
+            .collect_vec();
+
+        ChatCompletionResponse::builder()
+            .maybe_message((!self.message.is_empty()).then(|| self.message.clone()))
+            .maybe_tool_calls((!tool_calls.is_empty()).then_some(tool_calls))
+            .maybe_usage(self.usage)
+            .maybe_delta(self.delta.clone())
+            .build()
+    }
+}
+
+fn json_to_document(value: serde_json::Value) -> Document {
+    match value {
+        serde_json::Value::Null => Document::Null,
+        serde_json::Value::Bool(b) => Document::Bool(b),
+        serde_json::Value::Number(n) => {
+            if let Some(n) = n.as_u64() {
+                Document::Number(Number::PosInt(n))
+            } else if let Some(n) = n.as_i64() {
+                Document::Number(Number::NegInt(n))
+            } else {
+                Document::Number(Number::Float(n.as_f64().unwrap_or_default()))
+            }
+        }
+        serde_json::Value::String(s) => Document::String(s),
+        serde_json::Value::Array(values) => {
+            Document::Array(values.into_iter().map(json_to_document).collect())
+        }
+        serde_json::Value::Object(map) => Document::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, json_to_document(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn document_to_json(document: &Document) -> serde_json::Value {
+    match document {
+        Document::Null => serde_json::Value::Null,
+        Document::Bool(b) => json!(b),
+        Document::Number(Number::PosInt(n)) => json!(n),
+        Document::Number(Number::NegInt(n)) => json!(n),
+        Document::Number(Number::Float(n)) => json!(n),
+        Document::String(s) => json!(s),
+        Document::Array(values) => values.iter().map(document_to_json).collect(),
+        Document::Object(map) => map
+            .iter()
+            .map(|(k, v)| (k.clone(), document_to_json(v)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aws_bedrock::MockBedrockConverse;
+    use aws_sdk_bedrockruntime::types::{
+        ContentBlockDeltaEvent, ContentBlockStartEvent, ContentBlockStopEvent, ConverseMetrics,
+        ConverseStreamMetadataEvent, ConverseStreamMetrics, StopReason, ToolUseBlockDelta,
+        ToolUseBlockStart,
+    };
+    use futures_util::TryStreamExt as _;
+    use swiftide_core::chat_completion::{ParamSpec, ToolOutput};
+
+    fn token_usage() -> TokenUsage {
+        TokenUsage::builder()
+            .input_tokens(10)
+            .output_tokens(5)
+            .total_tokens(15)
+            .build()
+            .unwrap()
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_complete_with_tool_calls() {
+        let mut bedrock_mock = MockBedrockConverse::new();
+
+        bedrock_mock
+            .expect_send_converse()
+            .once()
+            .withf(|model_id, request| {
+                model_id == "my_model"
+                    && request.system.len() == 1
+                    // Tool output and the next user message are merged into a single message
+                    && request.messages.len() == 3
+                    && request.tool_config.is_some()
+            })
+            .returning(|_, _| {
+                Ok(ConverseOutput::builder()
+                    .output(ConverseOutputType::Message(
+                        Message::builder()
+                            .role(ConversationRole::Assistant)
+                            .content(ContentBlock::Text("Let me search".to_string()))
+                            .content(ContentBlock::ToolUse(
+                                ToolUseBlock::builder()
+                                    .tool_use_id("tool_2")
+                                    .name("search")
+                                    .input(json_to_document(json!({"query": "swiftide"})))
+                                    .build()
+                                    .unwrap(),
+                            ))
+                            .build()
+                            .unwrap(),
+                    ))
+                    .stop_reason(StopReason::ToolUse)
+                    .usage(token_usage())
+                    .metrics(ConverseMetrics::builder().latency_ms(1).build().unwrap())
+                    .build()
+                    .unwrap())
+            });
+
+        let bedrock = AwsBedrock::builder()
+            .model_id("my_model")
+            .test_client(bedrock_mock)
+            .build()
+            .unwrap();
+
+        let tool_call = ToolCall::builder()
+            .id("tool_1")
+            .name("search")
+            .args(r#"{"query": "rust"}"#)
+            .build()
+            .unwrap();
+
+        let request = ChatCompletionRequest::builder()
+            .messages(vec![
+                ChatMessage::System("You are a helpful assistant".into()),
+                ChatMessage::User("Search for swiftide".into()),
+                ChatMessage::Assistant(None, Some(vec![tool_call.clone()])),
+                ChatMessage::ToolOutput(tool_call, ToolOutput::Text("No results".into())),
+                ChatMessage::User("Try again".into()),
+            ])
+            .tools_spec([ToolSpec::builder()
+                .name("search")
+                .description("Searches the web")
+                .parameters(vec![ParamSpec::builder()
+                    .name("query")
+                    .description("The search query")
+                    .build()
+                    .unwrap()])
+                .build()
+                .unwrap()])
+            .build()
+            .unwrap();
+
+        let response = bedrock.complete(&request).await.unwrap();
+
+        assert_eq!(response.message(), Some("Let me search"));
+        let tool_calls = response.tool_calls().unwrap();
+        assert_eq!(tool_calls[0].id(), "tool_2");
+        assert_eq!(tool_calls[0].name(), "search");
+        assert_eq!(tool_calls[0].args(), Some(r#"{"query":"swiftide"}"#));
+        assert_eq!(response.usage().unwrap().total_tokens, 15);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_complete_stream() {
+        let mut bedrock_mock = MockBedrockConverse::new();
+
+        bedrock_mock
+            .expect_send_converse_stream()
+            .once()
+            .returning(|_, _| {
+                let events = vec![
+                    ConverseStreamOutput::ContentBlockDelta(
+                        ContentBlockDeltaEvent::builder()
+                            .content_block_index(0)
+                            .delta(ContentBlockDelta::Text("Hello, ".to_string()))
+                            .build()
+                            .unwrap(),
+                    ),
+                    ConverseStreamOutput::ContentBlockDelta(
+                        ContentBlockDeltaEvent::builder()
+                            .content_block_index(0)
+                            .delta(ContentBlockDelta::Text("world!".to_string()))
+                            .build()
+                            .unwrap(),
+                    ),
+                    ConverseStreamOutput::ContentBlockStart(
+                        ContentBlockStartEvent::builder()
+                            .content_block_index(1)
+                            .start(ContentBlockStart::ToolUse(
+                                ToolUseBlockStart::builder()
+                                    .tool_use_id("tool_1")
+                                    .name("search")
+                                    .build()
+                                    .unwrap(),
+                            ))
+                            .build()
+                            .unwrap(),
+                    ),
+                    ConverseStreamOutput::ContentBlockDelta(
+                        ContentBlockDeltaEvent::builder()
+                            .content_block_index(1)
+                            .delta(ContentBlockDelta::ToolUse(
+                                ToolUseBlockDelta::builder()
+                                    .input(r#"{"query": "#)
+                                    .build()
+                                    .unwrap(),
+                            ))
+                            .build()
+                            .unwrap(),
+                    ),
+                    ConverseStreamOutput::ContentBlockDelta(
+                        ContentBlockDeltaEvent::builder()
+                            .content_block_index(1)
+                            .delta(ContentBlockDelta::ToolUse(
+                                ToolUseBlockDelta::builder()
+                                    .input(r#""swiftide"}"#)
+                                    .build()
+                                    .unwrap(),
+                            ))
+                            .build()
+                            .unwrap(),
+                    ),
+                    ConverseStreamOutput::ContentBlockStop(
+                        ContentBlockStopEvent::builder()
+                            .content_block_index(1)
+                            .build()
+                            .unwrap(),
+                    ),
+                    ConverseStreamOutput::Metadata(
+                        ConverseStreamMetadataEvent::builder()
+                            .usage(token_usage())
+                            .metrics(
+                                ConverseStreamMetrics::builder()
+                                    .latency_ms(1)
+                                    .build()
+                                    .unwrap(),
+                            )
+                            .build(),
+                    ),
+                ];
+
+                Ok(futures_util::stream::iter(events.into_iter().map(Ok)).boxed())
+            });
+
+        let bedrock = AwsBedrock::builder()
+            .model_id("my_model")
+            .test_client(bedrock_mock)
+            .build()
+            .unwrap();
+
+        let responses: Vec<ChatCompletionResponse> = bedrock
+            .complete_stream(&vec![ChatMessage::User("Hello".into())].into())
+            .await
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(responses[0].delta(), Some("Hello, "));
+        assert_eq!(responses[1].delta(), Some("world!"));
+
+        let last = responses.last().unwrap();
+        assert_eq!(last.message(), Some("Hello, world!"));
+        assert_eq!(last.delta(), None);
+        assert_eq!(
+            last.tool_calls().unwrap()[0].args(),
+            Some(r#"{"query": "swiftide"}"#)
+        );
+        assert_eq!(last.usage().unwrap().prompt_tokens, 10);
+    }
+}
@@ -1,26 +1,39 @@
 //! An integration with the AWS Bedrock service.
 //!
-//! Supports various model families for prompting.
+//! Uses the Bedrock Converse api, which provides a uniform interface for prompting, chat
+//! completion and tool use over all models that support it.
 use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use aws_sdk_bedrockruntime::{error::SdkError, primitives::Blob, Client};
+use aws_sdk_bedrockruntime::{
+    error::{ProvideErrorMetadata, SdkError},
+    operation::converse::ConverseOutput,
+    types::{
+        ConverseStreamOutput, InferenceConfiguration, Message, SystemContentBlock,
+        ToolConfiguration,
+    },
+    Client,
+};
 use derive_builder::Builder;
-use serde::Serialize;
+use futures_util::{stream::BoxStream, StreamExt as _};
+use swiftide_core::chat_completion::errors::LanguageModelError;
 use tokio::runtime::Handle;
 
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
-mod models;
+mod chat_completion;
 mod simple_prompt;
 
 /// An integration with the AWS Bedrock service.
 ///
-/// Can be used as `SimplePrompt`.
+/// Can be used as `SimplePrompt` and `ChatCompletion`, including tool calling and streaming.
+///
+/// To use Bedrock, you need to have a model id and access to the service. The model must support
+/// the Converse api, which includes Anthropic Claude, Meta Llama, Mistral, Cohere and Amazon
+/// models.
 ///
-/// To use Bedrock, you need to have a model id and access to the service.
 /// By default, the aws sdk will be configured from the environment.
 /// If you have the aws cli properly configured with a region set, it should work out of the box.
 ///
@@ -36,32 +49,123 @@ pub struct AwsBedrock {
 
     #[builder(default = self.default_client(), setter(custom))]
     /// The bedrock runtime client
-    client: Arc<dyn BedrockPrompt>,
+    client: Arc<dyn BedrockConverse>,
     #[builder(default)]
     /// The model configuration to use
     model_config: ModelConfig,
-    /// The model family to use. In bedrock, families share their api.
-    model_family: ModelFamily,
+}
+
+/// The parts of a Converse request that swiftide sets
+#[derive(Debug, Clone)]
+struct ConverseRequest {
+    messages: Vec<Message>,
+    system: Vec<SystemContentBlock>,
+    tool_config: Option<ToolConfiguration>,
+    inference_config: InferenceConfiguration,
 }
 
 #[cfg_attr(test, automock)]
 #[async_trait]
-trait BedrockPrompt: std::fmt::Debug + Send + Sync {
-    async fn prompt_u8(&self, model_id: &str, blob: Blob) -> Result<Vec<u8>>;
+trait BedrockConverse: std::fmt::Debug + Send + Sync {
+    async fn send_converse(
+        &self,
+        model_id: &str,
+        request: ConverseRequest,
+    ) -> Result<ConverseOutput, LanguageModelError>;
+
+    async fn send_converse_stream(
+        &self,
+        model_id: &str,
+        request: ConverseRequest,
+    ) -> Result<
+        BoxStream<'static, Result<ConverseStreamOutput, LanguageModelError>>,
+        LanguageModelError,
+    >;
 }
 
 #[async_trait]
-impl BedrockPrompt for Client {
-    async fn prompt_u8(&self, model_id: &str, blob: Blob) -> Result<Vec<u8>> {
+impl BedrockConverse for Client {
+    async fn send_converse(
+        &self,
+        model_id: &str,
+        request: ConverseRequest,
+    ) -> Result<ConverseOutput, LanguageModelError> {
+        let response = self
+            .converse()
+            .model_id(model_id)
+            .set_messages(Some(request.messages))
+            .set_system(Some(request.system))
+            .set_tool_config(request.tool_config)
+            .inference_config(request.inference_config)
+            .send()
+            .await
+            .map_err(sdk_error_to_language_model_error)?;
+
+        Ok(response)
+    }
+
+    async fn send_converse_stream(
+        &self,
+        model_id: &str,
+        request: ConverseRequest,
+    ) -> Result<
+        BoxStream<'static, Result<ConverseStreamOutput, LanguageModelError>>,
+        LanguageModelError,
+    > {
         let response = self
-            .invoke_model()
-            .body(blob)
+            .converse_stream()
             .model_id(model_id)
+            .set_messages(Some(request.messages))
+            .set_system(Some(request.system))
+            .set_tool_config(request.tool_config)
+            .inference_config(request.inference_config)
             .send()
             .await
-            .map_err(SdkError::into_service_error)?;
+            .map_err(sdk_error_to_language_model_error)?;
+
+        // Stops after the first error
+        let stream = futures_util::stream::unfold(Some(response.stream), |receiver| async move {
+            let mut receiver = receiver?;
+            match receiver.recv().await {
+                Ok(Some(event)) => Some((Ok(event), Some(receiver))),
+                Ok(None) => None,
+                Err(err) => Some((Err(sdk_error_to_language_model_error(err)), None)),
+            }
+        });
 
-        Ok(response.body.into_inner())
+        Ok(stream.boxed())
+    }
+}
+
+/// Classifies errors from the Bedrock sdk
+///
+/// Throttling, timeouts and unavailable models are transient, and might succeed on a retry.
+fn sdk_error_to_language_model_error<E, R>(err: SdkError<E, R>) -> LanguageModelError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+    R: std::fmt::Debug + Send + Sync + 'static,
+{
+    if matches!(
+        err,
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_)
+    ) {
+        return LanguageModelError::transient(err);
+    }
+
+    let err = err.into_service_error();
+    match err.code() {
+        Some(
+            "ThrottlingException"
+            | "InternalServerException"
+            | "ServiceUnavailableException"
+            | "ModelTimeoutException"
+            | "ModelNotReadyException"
+            | "ModelStreamErrorException",
+        ) => LanguageModelError::transient(err),
+        Some("ValidationException") if err.message().is_some_and(|m| m.contains("too long")) => {
+            LanguageModelError::context_length_exceeded(err)
+        }
+        _ => LanguageModelError::permanent(err),
     }
 }
 
@@ -71,7 +175,6 @@ impl Clone for AwsBedrock {
             model_id: self.model_id.clone(),
             client: self.client.clone(),
             model_config: self.model_config.clone(),
-            model_family: self.model_family.clone(),
         }
     }
 }
@@ -82,28 +185,31 @@ impl AwsBedrock {
     }
 
     /// Build a new `AwsBedrock` instance with the Titan model family
+    #[deprecated(note = "Model families are no longer needed with the Converse api, use `builder`")]
     pub fn build_titan_family(model_id: impl Into<String>) -> AwsBedrockBuilder {
-        Self::builder().titan().model_id(model_id).to_owned()
+        Self::builder().model_id(model_id).to_owned()
     }
 
     /// Build a new `AwsBedrock` instance with the Anthropic model family
+    #[deprecated(note = "Model families are no longer needed with the Converse api, use `builder`")]
     pub fn build_anthropic_family(model_id: impl Into<String>) -> AwsBedrockBuilder {
-        Self::builder().anthropic().model_id(model_id).to_owned()
-    }
-}
-impl AwsBedrockBuilder {
-    /// Set the model family to Anthropic
-    pub fn anthropic(&mut self) -> &mut Self {
-        self.model_family = Some(ModelFamily::Anthropic);
-        self
+        Self::builder().model_id(model_id).to_owned()
     }
 
-    /// Set the model family to Titan
-    pub fn titan(&mut self) -> &mut Self {
-        self.model_family = Some(ModelFamily::Titan);
-        self
+    fn inference_config(&self) -> InferenceConfiguration {
+        InferenceConfiguration::builder()
+            .temperature(self.model_config.temperature)
+            .top_p(self.model_config.top_p)
+            .max_tokens(self.model_config.max_tokens)
+            .set_stop_sequences(
+                (!self.model_config.stop_sequences.is_empty())
+                    .then(|| self.model_config.stop_sequences.clone()),
+            )
+            .build()
     }
+}
 
+impl AwsBedrockBuilder {
     #[allow(clippy::unused_self)]
     fn default_config(&self) -> aws_config::SdkConfig {
         tokio::task::block_in_place(|| {
@@ -122,29 +228,38 @@ impl AwsBedrockBuilder {
 
     #[cfg(test)]
     #[allow(private_bounds)]
-    pub fn test_client(&mut self, client: impl BedrockPrompt + 'static) -> &mut Self {
+    pub fn test_client(&mut self, client: impl BedrockConverse + 'static) -> &mut Self {
         self.client = Some(Arc::new(client));
         self
     }
 }
 
-use self::models::ModelFamily;
-
-#[derive(Serialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
+/// Inference parameters for the Converse api
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into))]
 pub struct ModelConfig {
+    #[builder(default = 0.5)]
     temperature: f32,
+    #[builder(default = 0.9)]
     top_p: f32,
-    max_token_count: i32,
+    #[builder(default = 8192)]
+    max_tokens: i32,
+    #[builder(default)]
     stop_sequences: Vec<String>,
 }
 
+impl ModelConfig {
+    pub fn builder() -> ModelConfigBuilder {
+        ModelConfigBuilder::default()
+    }
+}
+
 impl Default for ModelConfig {
     fn default() -> Self {
         Self {
             temperature: 0.5,
             top_p: 0.9,
-            max_token_count: 8192,
+            max_tokens: 8192,
             stop_sequences: vec![],
         }
     }
@@ -1,56 +1,97 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use async_trait::async_trait;
-use aws_sdk_bedrockruntime::primitives::Blob;
-use swiftide_core::{indexing::SimplePrompt, prompt::Prompt};
+use aws_sdk_bedrockruntime::types::{ContentBlock, ConversationRole, Message};
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError, indexing::SimplePrompt, prompt::Prompt,
+    util::debug_long_utf8,
+};
 
-use super::AwsBedrock;
+use super::{AwsBedrock, ConverseRequest};
 
 #[async_trait]
 impl SimplePrompt for AwsBedrock {
     #[tracing::instrument(skip_all, err)]
-    async fn prompt(&self, prompt: Prompt) -> Result<String> {
-        let blob = self
-            .model_family
-            .build_request_to_bytes(prompt.render().await?, &self.model_config)
-            .map(Blob::new)?;
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+        let message = Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::Text(prompt.render().await?))
+            .build()
+            .map_err(LanguageModelError::permanent)?;
+
+        let request = ConverseRequest {
+            messages: vec![message],
+            system: vec![],
+            tool_config: None,
+            inference_config: self.inference_config(),
+        };
+
+        let response = self.client.send_converse(&self.model_id, request).await?;
 
-        let response_bytes = self.client.prompt_u8(&self.model_id, blob).await?;
+        let message = response
+            .output()
+            .and_then(|output| output.as_message().ok())
+            .context("No message in response")?;
+
+        let text = message
+            .content()
+            .iter()
+            .filter_map(|block| block.as_text().ok())
+            .map(String::as_str)
+            .collect::<String>();
 
         tracing::debug!(
-            "Received response: {:?}",
-            std::str::from_utf8(&response_bytes)?
+            response = debug_long_utf8(&text, 100),
+            "[SimplePrompt] Response from bedrock"
         );
 
-        self.model_family.output_message_from_bytes(&response_bytes)
+        Ok(text)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::aws_bedrock::models::*;
-    use crate::aws_bedrock::MockBedrockPrompt;
+    use crate::aws_bedrock::MockBedrockConverse;
 
     use super::*;
-    use anyhow::Context as _;
+    use aws_sdk_bedrockruntime::{
+        operation::converse::ConverseOutput,
+        types::{ConverseMetrics, ConverseOutput as ConverseOutputType, StopReason, TokenUsage},
+    };
     use test_log;
 
     #[test_log::test(tokio::test)]
-    async fn test_prompt_with_titan() {
-        let mut bedrock_mock = MockBedrockPrompt::new();
+    async fn test_prompt() {
+        let mut bedrock_mock = MockBedrockConverse::new();
 
-        bedrock_mock.expect_prompt_u8().once().returning(|_, _| {
-            serde_json::to_vec(&TitanResponse {
-                input_text_token_count: 1,
-                results: vec![TitanTextResult {
-                    output_text: "Hello, world!".to_string(),
-                    token_count: 1,
-                    completion_reason: "STOP".to_string(),
-                }],
-            })
-            .context("Failed to serialize response")
-        });
+        bedrock_mock
+            .expect_send_converse()
+            .once()
+            .withf(|model_id, request| model_id == "my_model" && request.messages.len() == 1)
+            .returning(|_, _| {
+                Ok(ConverseOutput::builder()
+                    .output(ConverseOutputType::Message(
+                        Message::builder()
+                            .role(ConversationRole::Assistant)
+                            .content(ContentBlock::Text("Hello, world!".to_string()))
+                            .build()
+                            .unwrap(),
+                    ))
+                    .stop_reason(StopReason::EndTurn)
+                    .usage(
+                        TokenUsage::builder()
+                            .input_tokens(1)
+                            .output_tokens(1)
+                            .total_tokens(2)
+                            .build()
+                            .unwrap(),
+                    )
+                    .metrics(ConverseMetrics::builder().latency_ms(1).build().unwrap())
+                    .build()
+                    .unwrap())
+            });
 
-        let bedrock = AwsBedrock::build_titan_family("my_model")
+        let bedrock = AwsBedrock::builder()
+            .model_id("my_model")
             .test_client(bedrock_mock)
             .build()
             .unwrap();
@@ -59,34 +100,4 @@ mod test {
 
         assert_eq!(response, "Hello, world!");
     }
-
-    #[test_log::test(tokio::test)]
-    async fn test_prompt_with_anthropic() {
-        let mut bedrock_mock = MockBedrockPrompt::new();
-        bedrock_mock.expect_prompt_u8().once().returning(|_, _| {
-            serde_json::to_vec(&AnthropicResponse {
-                content: vec![AnthropicMessageContent {
-                    _type: "text".to_string(),
-                    text: "Hello, world!".to_string(),
-                }],
-                id: "id".to_string(),
-                model: "model".to_string(),
-                _type: "text".to_string(),
-                role: "user".to_string(),
-                stop_reason: Some("max_tokens".to_string()),
-                stop_sequence: None,
-                usage: AnthropicUsage {
-                    input_tokens: 10,
-                    output_tokens: 10,
-                },
-            })
-            .context("Failed to serialize response")
-        });
-        let bedrock = AwsBedrock::build_anthropic_family("my_model")
-            .test_client(bedrock_mock)
-            .build()
-            .unwrap();
-        let response = bedrock.prompt("Hello".into()).await.unwrap();
-        assert_eq!(response, "Hello, world!");
-    }
 }
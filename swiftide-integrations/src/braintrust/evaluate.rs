@@ -0,0 +1,113 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+use swiftide_core::{
+    querying::{Document, QueryEvaluation},
+    EvaluateQuery,
+};
+
+use super::{Braintrust, Event};
+
+/// Logs the retrievals and answers of a query pipeline
+///
+/// The input is the original query, and the output the retrieved documents or the answer. The
+/// current, possibly transformed, query and the retrieved documents are added to the metadata.
+#[async_trait]
+impl EvaluateQuery for Braintrust {
+    #[tracing::instrument(skip_all)]
+    async fn evaluate(&self, evaluation: QueryEvaluation) -> Result<()> {
+        let event = match evaluation {
+            QueryEvaluation::RetrieveDocuments(query) => Event {
+                input: json!(query.original()),
+                output: documents_to_json(query.documents()),
+                metadata: json!({ "query": query.current(), "step": "retrieve" })
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default(),
+                ..Default::default()
+            },
+            QueryEvaluation::AnswerQuery(query) => Event {
+                input: json!(query.original()),
+                output: json!(query.answer()),
+                metadata: json!({
+                    "query": query.current(),
+                    "step": "answer",
+                    "documents": documents_to_json(query.documents()),
+                })
+                .as_object()
+                .cloned()
+                .unwrap_or_default(),
+                ..Default::default()
+            },
+        };
+
+        self.log(vec![event]).await
+    }
+}
+
+fn documents_to_json(documents: &[Document]) -> serde_json::Value {
+    documents
+        .iter()
+        .map(|document| json!({ "content": document.content(), "metadata": document.metadata() }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::querying::{states, Query};
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_logs_answered_query() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/project"))
+            .and(body_partial_json(json!({ "name": "swiftide" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "project" })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/experiment"))
+            .and(body_partial_json(json!({ "project_id": "project" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "experiment" })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/experiment/experiment/insert"))
+            .and(body_partial_json(json!({
+                "events": [{
+                    "input": "What is Swiftide?",
+                    "output": "A Rust library",
+                    "scores": { "relevance": 1.0 }
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "row_ids": ["1"] })))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let braintrust = Braintrust::builder()
+            .url(mock_server.uri())
+            .api_key("test")
+            .project_name("swiftide")
+            .scores([("relevance".to_string(), 1.0)])
+            .build()
+            .unwrap();
+
+        let query = Query::<states::Pending>::new("What is Swiftide?")
+            .retrieved_documents(vec![Document::new("Swiftide is a Rust library", None)])
+            .answered("A Rust library");
+
+        braintrust.evaluate(query.clone().into()).await.unwrap();
+        // The experiment is only created once
+        braintrust.evaluate(query.into()).await.unwrap();
+    }
+}
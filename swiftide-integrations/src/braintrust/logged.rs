@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde_json::json;
+use swiftide_core::{
+    chat_completion::{
+        errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+        ChatCompletionStream,
+    },
+    prompt::Prompt,
+    SimplePrompt,
+};
+
+use super::{Braintrust, Event};
+
+/// Wraps a language model client and logs its completions and prompts to `Braintrust`
+///
+/// Created with [`Braintrust::log_completions`]. Failing to log is not an error, instead a
+/// warning is traced. Streaming completions are passed through without logging.
+#[derive(Debug, Clone)]
+pub struct Logged<T> {
+    inner: T,
+    braintrust: Braintrust,
+}
+
+impl<T> Logged<T> {
+    pub fn new(inner: T, braintrust: Braintrust) -> Self {
+        Self { inner, braintrust }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    async fn log(&self, event: Event) {
+        if let Err(error) = self.braintrust.log(vec![event]).await {
+            tracing::warn!(error = ?error, "Failed to log to braintrust");
+        }
+    }
+}
+
+#[async_trait]
+impl<T: ChatCompletion + Clone> ChatCompletion for Logged<T> {
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let response = self.inner.complete(request).await?;
+
+        let metrics = response
+            .usage()
+            .map(|usage| {
+                BTreeMap::from([
+                    ("prompt_tokens".to_string(), f64::from(usage.prompt_tokens)),
+                    (
+                        "completion_tokens".to_string(),
+                        f64::from(usage.completion_tokens),
+                    ),
+                    ("tokens".to_string(), f64::from(usage.total_tokens)),
+                ])
+            })
+            .unwrap_or_default();
+
+        self.log(Event {
+            input: request.messages().iter().map(ToString::to_string).collect(),
+            output: json!({
+                "message": response.message(),
+                "tool_calls": response.tool_calls(),
+            }),
+            metrics,
+            ..Default::default()
+        })
+        .await;
+
+        Ok(response)
+    }
+
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        self.inner.complete_stream(request).await
+    }
+}
+
+#[async_trait]
+impl<T: SimplePrompt + Clone> SimplePrompt for Logged<T> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
+        let input = prompt.render().await.ok();
+        let output = self.inner.prompt(prompt).await?;
+
+        self.log(Event {
+            input: json!(input),
+            output: json!(output),
+            ..Default::default()
+        })
+        .await;
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
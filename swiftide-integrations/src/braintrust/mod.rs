@@ -0,0 +1,193 @@
+//! This module provides integration with `Braintrust` for logging and evaluating outputs.
+//!
+//! [`Braintrust`] implements [`swiftide_core::EvaluateQuery`], so that the retrievals and answers
+//! of a query pipeline are logged to an experiment with `evaluate_with`. Language model clients
+//! can be wrapped with [`Braintrust::log_completions`] to log every completion and prompt.
+//!
+//! Events are logged with their input, output, scores and metadata, and can be scored and
+//! compared in the Braintrust ui.
+//!
+//! The module is conditionally compiled based on the "braintrust" feature flag.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{Context as _, Result};
+use derive_builder::Builder;
+use secrecy::{ExposeSecret as _, SecretString};
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+mod evaluate;
+mod logged;
+
+pub use logged::Logged;
+
+const DEFAULT_URL: &str = "https://api.braintrust.dev";
+
+/// Logs events to an experiment in `Braintrust`
+///
+/// The project and experiment are created on first use if they do not exist yet.
+///
+/// By default it will look for a `BRAINTRUST_API_KEY` environment variable.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::braintrust::Braintrust;
+/// Braintrust::builder()
+///     .project_name("swiftide")
+///     .experiment_name("hybrid-search")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct Braintrust {
+    /// The url of the api, defaults to `https://api.braintrust.dev`
+    #[builder(default = "DEFAULT_URL.to_string()")]
+    url: String,
+    #[builder(default = "default_api_key()")]
+    api_key: SecretString,
+    #[builder(default)]
+    http_client: reqwest::Client,
+    /// The project to log to
+    project_name: String,
+    /// The experiment to log to, defaults to `swiftide`
+    #[builder(default = "\"swiftide\".to_string()")]
+    experiment_name: String,
+    /// Scores added to every event, i.e. from an offline evaluation
+    #[builder(default)]
+    scores: BTreeMap<String, f64>,
+    #[builder(default, setter(skip))]
+    experiment_id: Arc<OnceCell<String>>,
+}
+
+/// A single event in an experiment
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Event {
+    pub input: serde_json::Value,
+    pub output: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub scores: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    /// Numeric metrics, i.e. `prompt_tokens` and `completion_tokens`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl std::fmt::Debug for Braintrust {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Braintrust")
+            .field("url", &self.url)
+            .field("project_name", &self.project_name)
+            .field("experiment_name", &self.experiment_name)
+            .finish_non_exhaustive()
+    }
+}
+
+fn default_api_key() -> SecretString {
+    std::env::var("BRAINTRUST_API_KEY")
+        .unwrap_or_default()
+        .into()
+}
+
+#[derive(Debug, Deserialize)]
+struct Created {
+    id: String,
+}
+
+impl Braintrust {
+    pub fn builder() -> BraintrustBuilder {
+        BraintrustBuilder::default()
+    }
+
+    /// Wraps a language model client to log its completions and prompts
+    pub fn log_completions<T>(&self, inner: T) -> Logged<T> {
+        Logged::new(inner, self.clone())
+    }
+
+    /// Logs events to the experiment
+    ///
+    /// Scores configured on the client are added to every event, unless already set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the experiment cannot be created or the request fails.
+    pub async fn log(&self, events: Vec<Event>) -> Result<()> {
+        let events = events
+            .into_iter()
+            .map(|mut event| {
+                for (name, score) in &self.scores {
+                    event.scores.entry(name.clone()).or_insert(*score);
+                }
+                event
+            })
+            .collect::<Vec<_>>();
+
+        let experiment_id = self.experiment_id().await?;
+
+        self.post::<serde_json::Value>(
+            &format!("v1/experiment/{experiment_id}/insert"),
+            &serde_json::json!({ "events": events }),
+        )
+        .await
+        .context("Failed to log events to braintrust")?;
+
+        Ok(())
+    }
+
+    /// Returns the id of the experiment, creating the project and experiment on first use
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project or experiment cannot be created.
+    pub async fn experiment_id(&self) -> Result<&str> {
+        self.experiment_id
+            .get_or_try_init(|| async {
+                // Both return the existing project or experiment if it already exists
+                let project = self
+                    .post::<Created>(
+                        "v1/project",
+                        &serde_json::json!({ "name": self.project_name }),
+                    )
+                    .await
+                    .context("Failed to create braintrust project")?;
+
+                let experiment = self
+                    .post::<Created>(
+                        "v1/experiment",
+                        &serde_json::json!({
+                            "project_id": project.id,
+                            "name": self.experiment_name,
+                            "ensure_new": false,
+                        }),
+                    )
+                    .await
+                    .context("Failed to create braintrust experiment")?;
+
+                Ok(experiment.id)
+            })
+            .await
+            .map(String::as_str)
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<T> {
+        self.http_client
+            .post(format!("{}/{path}", self.url.trim_end_matches('/')))
+            .bearer_auth(self.api_key.expose_secret())
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<T>()
+            .await
+            .map_err(Into::into)
+    }
+}
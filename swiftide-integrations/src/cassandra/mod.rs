@@ -0,0 +1,172 @@
+//! This module provides integration with `Cassandra` 5 and compatible databases for vector search.
+//!
+//! Nodes are stored in a table with their content, path, metadata and embedding, with a storage
+//! attached index on the embedding for approximate nearest neighbour search, and on the metadata
+//! entries for filtering.
+//!
+//! Connections use the `ScyllaDB` driver with token aware load balancing, so that every insert is
+//! sent to a replica owning the node. Astra DB can be used through its CQL proxy.
+//!
+//! The module is conditionally compiled based on the "cassandra" feature flag.
+
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use derive_builder::Builder;
+use scylla::{
+    prepared_statement::PreparedStatement,
+    transport::{load_balancing::DefaultPolicy, ExecutionProfile},
+    Session, SessionBuilder,
+};
+use secrecy::{ExposeSecret as _, SecretString};
+use swiftide_core::indexing::EmbeddedField;
+use tokio::sync::OnceCell;
+
+mod persist;
+mod retrieve;
+
+/// Stores nodes in and retrieves documents from `Cassandra` with vector search
+///
+/// Implements `Persist` and `Retrieve`. `setup` creates the keyspace, table and indices if they
+/// do not exist yet.
+///
+/// The session is created on first use, unless provided with [`CassandraBuilder::session`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::cassandra::Cassandra;
+/// Cassandra::builder()
+///     .known_node("127.0.0.1:9042")
+///     .keyspace("swiftide")
+///     .table_name("nodes")
+///     .vector_size(1536)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct Cassandra {
+    /// Contact points of the cluster, defaults to `127.0.0.1:9042`
+    #[builder(default = "vec![\"127.0.0.1:9042\".to_string()]", setter(custom))]
+    known_nodes: Vec<String>,
+    #[builder(default)]
+    username: Option<String>,
+    #[builder(default)]
+    password: Option<SecretString>,
+    /// Prefers replicas in this datacenter, recommended for clusters with multiple datacenters
+    #[builder(default)]
+    local_datacenter: Option<String>,
+    /// The keyspace of the table, defaults to `swiftide`
+    #[builder(default = "\"swiftide\".to_string()")]
+    keyspace: String,
+    /// The replication factor of the keyspace when it is created, defaults to 1
+    #[builder(default = "1")]
+    replication_factor: usize,
+    /// The name of the table, defaults to `nodes`
+    #[builder(default = "\"nodes\".to_string()")]
+    table_name: String,
+    /// The dimensions of the embeddings
+    vector_size: usize,
+    /// The embedding to store, defaults to `EmbeddedField::Combined`
+    #[builder(default)]
+    vector_field: EmbeddedField,
+    /// The number of nodes stored concurrently, defaults to 50
+    #[builder(default = "50")]
+    batch_size: usize,
+    #[builder(default, setter(custom))]
+    session: Arc<OnceCell<Session>>,
+    #[builder(default, setter(skip))]
+    insert_statement: Arc<OnceCell<PreparedStatement>>,
+}
+
+impl std::fmt::Debug for Cassandra {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cassandra")
+            .field("known_nodes", &self.known_nodes)
+            .field("keyspace", &self.keyspace)
+            .field("table_name", &self.table_name)
+            .field("vector_size", &self.vector_size)
+            .field("vector_field", &self.vector_field)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cassandra {
+    pub fn builder() -> CassandraBuilder {
+        CassandraBuilder::default()
+    }
+
+    /// Returns the session, connecting on first use
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session cannot be created.
+    pub async fn session(&self) -> Result<&Session> {
+        self.session
+            .get_or_try_init(|| async {
+                let mut policy = DefaultPolicy::builder().token_aware(true);
+                if let Some(datacenter) = &self.local_datacenter {
+                    policy = policy.prefer_datacenter(datacenter.clone());
+                }
+                let profile = ExecutionProfile::builder()
+                    .load_balancing_policy(policy.build())
+                    .build();
+
+                let mut builder = SessionBuilder::new()
+                    .known_nodes(&self.known_nodes)
+                    .default_execution_profile_handle(profile.into_handle());
+                if let Some(username) = &self.username {
+                    let password = self
+                        .password
+                        .as_ref()
+                        .map(|password| password.expose_secret().to_string())
+                        .unwrap_or_default();
+                    builder = builder.user(username, password);
+                }
+
+                builder
+                    .build()
+                    .await
+                    .context("Failed to connect to cassandra")
+            })
+            .await
+    }
+
+    /// Returns the prepared insert statement, preparing it on first use
+    async fn insert_statement(&self) -> Result<&PreparedStatement> {
+        self.insert_statement
+            .get_or_try_init(|| async {
+                self.session()
+                    .await?
+                    .prepare(format!(
+                        "INSERT INTO {} (id, path, content, metadata, embedding) VALUES (?, ?, ?, ?, ?)",
+                        self.table()
+                    ))
+                    .await
+                    .context("Failed to prepare insert statement")
+            })
+            .await
+    }
+
+    /// The fully qualified name of the table
+    fn table(&self) -> String {
+        format!("{}.{}", self.keyspace, self.table_name)
+    }
+}
+
+impl CassandraBuilder {
+    /// Adds a contact point, replacing the default `127.0.0.1:9042`
+    pub fn known_node(&mut self, node: impl Into<String>) -> &mut Self {
+        self.known_nodes
+            .get_or_insert_with(Vec::new)
+            .push(node.into());
+        self
+    }
+
+    /// Uses an existing session instead of connecting on first use
+    pub fn session(&mut self, session: Session) -> &mut Self {
+        self.session = Some(Arc::new(OnceCell::new_with(Some(session))));
+        self
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use futures_util::future::try_join_all;
+use swiftide_core::{
+    indexing::{IndexingStream, Node, PersistError},
+    Persist,
+};
+
+use super::Cassandra;
+
+#[async_trait]
+impl Persist for Cassandra {
+    /// Creates the keyspace, the table and indices on the embedding and metadata
+    #[tracing::instrument(skip_all)]
+    async fn setup(&self) -> Result<(), PersistError> {
+        let session = self.session().await?;
+
+        for statement in self.schema_statements() {
+            session
+                .query_unpaged(statement, &[])
+                .await
+                .context("Failed to set up cassandra")?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        self.store_node(&node).await?;
+        Ok(node)
+    }
+
+    /// Stores the nodes concurrently with a prepared statement
+    ///
+    /// Every node is its own partition, so instead of a multi-partition batch, which is
+    /// coordinated by a single node, every insert is routed to a replica owning it.
+    #[tracing::instrument(skip_all)]
+    async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
+        try_join_all(nodes.iter().map(|node| self.store_node(node)))
+            .await
+            .map(|_| nodes)
+            .into()
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        Some(self.batch_size)
+    }
+}
+
+impl Cassandra {
+    fn schema_statements(&self) -> Vec<String> {
+        let table = self.table();
+
+        vec![
+            format!(
+                "CREATE KEYSPACE IF NOT EXISTS {} WITH replication = {{'class': 'SimpleStrategy', 'replication_factor': {}}}",
+                self.keyspace, self.replication_factor
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {table} (id uuid PRIMARY KEY, path text, content text, metadata map<text, text>, embedding vector<float, {}>)",
+                self.vector_size
+            ),
+            format!(
+                "CREATE CUSTOM INDEX IF NOT EXISTS {}_embedding ON {table} (embedding) USING 'StorageAttachedIndex' WITH OPTIONS = {{'similarity_function': 'cosine'}}",
+                self.table_name
+            ),
+            format!(
+                "CREATE CUSTOM INDEX IF NOT EXISTS {}_metadata ON {table} (entries(metadata)) USING 'StorageAttachedIndex'",
+                self.table_name
+            ),
+        ]
+    }
+
+    async fn store_node(&self, node: &Node) -> Result<()> {
+        let embedding = node
+            .vectors
+            .as_ref()
+            .and_then(|vectors| vectors.get(&self.vector_field))
+            .with_context(|| format!("Node without vector for {}", self.vector_field))?;
+
+        // Metadata is stored as text, so that it can be filtered on with the index
+        let metadata = node
+            .metadata
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                (key.clone(), value)
+            })
+            .collect::<HashMap<_, _>>();
+
+        self.session()
+            .await?
+            .execute_unpaged(
+                self.insert_statement().await?,
+                (
+                    node.id(),
+                    node.path.to_string_lossy().to_string(),
+                    &node.chunk,
+                    metadata,
+                    embedding,
+                ),
+            )
+            .await
+            .context("Failed to store node in cassandra")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use swiftide_core::{
+    document::Document,
+    querying::{search_strategies::SimilaritySingleEmbedding, states, Query, RetrieveError},
+    Retrieve,
+};
+
+use super::Cassandra;
+
+/// Implement the `Retrieve` trait for `SimilaritySingleEmbedding` search strategy.
+///
+/// Can be used in the query pipeline to retrieve documents from Cassandra with approximate
+/// nearest neighbour search.
+///
+/// Supports filters as CQL conditions on the indexed metadata, i.e. `metadata['filter'] = 'true'`.
+/// Metadata values are stored as text.
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding<String>> for Cassandra {
+    #[tracing::instrument]
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding<String>,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.embedding else {
+            return Err(RetrieveError::MissingEmbedding);
+        };
+
+        let filter = search_strategy
+            .filter()
+            .as_ref()
+            .map(|filter| format!("WHERE {filter} "))
+            .unwrap_or_default();
+        let statement = format!(
+            "SELECT content, metadata FROM {} {filter}ORDER BY embedding ANN OF ? LIMIT ?",
+            self.table()
+        );
+        let limit = i32::try_from(search_strategy.top_k()).map_err(RetrieveError::permanent)?;
+
+        let result = self
+            .session()
+            .await?
+            .query_unpaged(statement, (embedding, limit))
+            .await
+            .context("Failed to retrieve from cassandra")?
+            .into_rows_result()
+            .context("Expected rows from cassandra")?;
+
+        let documents = result
+            .rows::<(String, Option<HashMap<String, String>>)>()
+            .context("Unexpected rows from cassandra")?
+            .map(|row| {
+                let (content, metadata) = row?;
+                Ok(Document::new(
+                    content,
+                    metadata.map(|metadata| metadata.into_iter().collect::<Vec<_>>().into()),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+/// Ensures that the `SimilaritySingleEmbedding` search strategy can be used when no filter is set.
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding> for Cassandra {
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        Retrieve::<SimilaritySingleEmbedding<String>>::retrieve(
+            self,
+            &search_strategy.into_concrete_filter::<String>(),
+            query,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt as _;
+    use swiftide_core::{
+        indexing::{self, EmbeddedField},
+        Persist as _,
+    };
+    use testcontainers::{runners::AsyncRunner as _, ContainerAsync, GenericImage};
+
+    use super::*;
+
+    async fn setup() -> (ContainerAsync<GenericImage>, Cassandra) {
+        let container = GenericImage::new("cassandra", "5.0")
+            .with_exposed_port(9042.into())
+            .with_wait_for(testcontainers::core::WaitFor::message_on_stdout(
+                "Starting listening for CQL clients",
+            ))
+            .start()
+            .await
+            .expect("Cassandra started");
+
+        let cassandra = Cassandra::builder()
+            .known_node(format!(
+                "{}:{}",
+                container.get_host().await.unwrap(),
+                container.get_host_port_ipv4(9042).await.unwrap()
+            ))
+            .vector_size(3usize)
+            .build()
+            .unwrap();
+        cassandra.setup().await.unwrap();
+
+        (container, cassandra)
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_retrieve_multiple_docs_and_filter() {
+        let (_guard, cassandra) = setup().await;
+        cassandra
+            .setup()
+            .await
+            .expect("Should not error if table exists");
+
+        let nodes = vec![
+            indexing::Node::new("test_query1").with_metadata(("filter", "true")),
+            indexing::Node::new("test_query2").with_metadata(("filter", "true")),
+            indexing::Node::new("test_query3").with_metadata(("filter", "false")),
+        ]
+        .into_iter()
+        .map(|node| {
+            node.with_vectors([(EmbeddedField::Combined, vec![1.0; 3])]);
+            node.to_owned()
+        })
+        .collect();
+
+        cassandra
+            .batch_store(nodes)
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut query = Query::<states::Pending>::new("test_query");
+        query.embedding = Some(vec![1.0; 3]);
+
+        let search_strategy =
+            SimilaritySingleEmbedding::from_filter("metadata['filter'] = 'true'".to_string());
+        let result = cassandra
+            .retrieve(&search_strategy, query.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.documents().len(), 2);
+
+        let search_strategy = SimilaritySingleEmbedding::<()>::default();
+        let result = cassandra.retrieve(&search_strategy, query).await.unwrap();
+        assert_eq!(result.documents().len(), 3);
+    }
+}
@@ -0,0 +1,124 @@
+//! Optional zstd compression of chunks for stores that keep the full text
+//!
+//! Compressed chunks are stored as `zstd:` followed by the base64 encoded compressed bytes, so
+//! they fit in the existing text columns. Stores decompress chunks on retrieval regardless of
+//! their configuration, so compression can be enabled on an existing store.
+//!
+//! Compressed chunks cannot be searched on their text, i.e. with full-text search.
+use std::borrow::Cow;
+
+use anyhow::{Context as _, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+const PREFIX: &str = "zstd:";
+
+/// Default compression level of zstd
+const DEFAULT_LEVEL: i32 = 3;
+
+/// Compresses chunks with zstd before they are stored
+///
+/// Compression pays off for larger chunks, for small chunks the overhead of the encoding can
+/// outweigh the savings. Chunks that do not get smaller are stored as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkCompression {
+    level: i32,
+}
+
+impl Default for ChunkCompression {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LEVEL,
+        }
+    }
+}
+
+impl ChunkCompression {
+    /// Compresses with the default zstd level
+    pub fn zstd() -> Self {
+        Self::default()
+    }
+
+    /// Compresses with a zstd level, from 1 (fastest) to 22 (smallest)
+    pub fn zstd_with_level(level: i32) -> Self {
+        Self { level }
+    }
+
+    /// Compresses a chunk for storage
+    ///
+    /// # Errors
+    ///
+    /// Errors if zstd fails to compress the chunk
+    pub fn compress<'a>(&self, chunk: &'a str) -> Result<Cow<'a, str>> {
+        let compressed =
+            zstd::encode_all(chunk.as_bytes(), self.level).context("Failed to compress chunk")?;
+        let encoded = format!("{PREFIX}{}", STANDARD.encode(compressed));
+
+        if encoded.len() < chunk.len() {
+            Ok(Cow::Owned(encoded))
+        } else {
+            Ok(Cow::Borrowed(chunk))
+        }
+    }
+
+    /// Decompresses a stored chunk, chunks that are not compressed are returned as is
+    ///
+    /// # Errors
+    ///
+    /// Errors if the chunk looks compressed but cannot be decompressed
+    pub fn decompress(chunk: &str) -> Result<Cow<'_, str>> {
+        let Some(encoded) = chunk.strip_prefix(PREFIX) else {
+            return Ok(Cow::Borrowed(chunk));
+        };
+
+        // A chunk that happens to start with the prefix is not valid base64
+        let Ok(compressed) = STANDARD.decode(encoded) else {
+            return Ok(Cow::Borrowed(chunk));
+        };
+
+        let bytes =
+            zstd::decode_all(compressed.as_slice()).context("Failed to decompress chunk")?;
+
+        Ok(Cow::Owned(
+            String::from_utf8(bytes).context("Decompressed chunk is not valid utf-8")?,
+        ))
+    }
+
+    /// Decompresses an owned chunk without copying chunks that are not compressed
+    ///
+    /// # Errors
+    ///
+    /// Errors if the chunk looks compressed but cannot be decompressed
+    pub fn decompress_owned(chunk: String) -> Result<String> {
+        match Self::decompress(&chunk)? {
+            Cow::Borrowed(_) => Ok(chunk),
+            Cow::Owned(decompressed) => Ok(decompressed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let chunk = "Swiftide is a rust library. ".repeat(100);
+        let compressed = ChunkCompression::zstd().compress(&chunk).unwrap();
+
+        assert!(compressed.starts_with(PREFIX));
+        assert!(compressed.len() < chunk.len());
+        assert_eq!(ChunkCompression::decompress(&compressed).unwrap(), chunk);
+    }
+
+    #[test]
+    fn test_small_and_uncompressed_chunks_are_kept() {
+        let compression = ChunkCompression::zstd();
+
+        assert_eq!(compression.compress("short").unwrap(), "short");
+        assert_eq!(ChunkCompression::decompress("plain").unwrap(), "plain");
+        assert_eq!(
+            ChunkCompression::decompress("zstd: not base64!").unwrap(),
+            "zstd: not base64!"
+        );
+    }
+}
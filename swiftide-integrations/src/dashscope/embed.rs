@@ -1,12 +1,13 @@
 use super::Dashscope;
+use crate::openai::openai_error_to_language_model_error;
 use anyhow::{Context as _, Result};
 use async_openai::types::CreateEmbeddingRequestArgs;
 use async_trait::async_trait;
-use swiftide_core::{EmbeddingModel, Embeddings};
+use swiftide_core::{chat_completion::errors::LanguageModelError, EmbeddingModel, Embeddings};
 
 #[async_trait]
 impl EmbeddingModel for Dashscope {
-    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
         let model = self
             .default_options
             .embed_model
@@ -17,13 +18,19 @@ impl EmbeddingModel for Dashscope {
             .model(model)
             .dimensions(dimensions)
             .input(&input)
-            .build()?;
+            .build()
+            .map_err(LanguageModelError::permanent)?;
         tracing::debug!(
             num_chunks = input.len(),
             model = &model,
             "[Embed] Request to qwen"
         );
-        let response = self.client.embeddings().create(request).await?;
+        let response = self
+            .client
+            .embeddings()
+            .create(request)
+            .await
+            .map_err(openai_error_to_language_model_error)?;
 
         let num_embeddings = response.data.len();
         tracing::debug!(num_embeddings = num_embeddings, "[Embed] Response openai");
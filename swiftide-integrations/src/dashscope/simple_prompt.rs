@@ -1,13 +1,14 @@
 use async_openai::types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs};
 use async_trait::async_trait;
-use swiftide_core::{prompt::Prompt, SimplePrompt};
+use swiftide_core::{chat_completion::errors::LanguageModelError, prompt::Prompt, SimplePrompt};
 
 use super::Dashscope;
+use crate::openai::openai_error_to_language_model_error;
 use anyhow::{Context as _, Result};
 
 #[async_trait]
 impl SimplePrompt for Dashscope {
-    async fn prompt(&self, prompt: Prompt) -> Result<String> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
         let model = self
             .default_options
             .prompt_model
@@ -19,19 +20,28 @@ impl SimplePrompt for Dashscope {
             .model(model)
             .messages(vec![ChatCompletionRequestUserMessageArgs::default()
                 .content(prompt.render().await?)
-                .build()?
+                .build()
+                .map_err(LanguageModelError::permanent)?
                 .into()])
-            .build()?;
+            .build()
+            .map_err(LanguageModelError::permanent)?;
 
         tracing::debug!(
-            messages = serde_json::to_string_pretty(&request)?,
+            messages =
+                serde_json::to_string_pretty(&request).map_err(LanguageModelError::permanent)?,
             "[SimplePrompt] Request to qwen"
         );
 
-        let mut response = self.client.chat().create(request).await?;
+        let mut response = self
+            .client
+            .chat()
+            .create(request)
+            .await
+            .map_err(openai_error_to_language_model_error)?;
 
         tracing::debug!(
-            response = serde_json::to_string_pretty(&response)?,
+            response =
+                serde_json::to_string_pretty(&response).map_err(LanguageModelError::permanent)?,
             "[SimplePrompt] Response from qwen"
         );
 
@@ -42,5 +52,6 @@ impl SimplePrompt for Dashscope {
             .content
             .take()
             .context("Expected content in response")
+            .map_err(LanguageModelError::from)
     }
 }
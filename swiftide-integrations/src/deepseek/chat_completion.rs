@@ -0,0 +1,437 @@
+//! Chat completion for `DeepSeek`
+//!
+//! `async_openai` does not know about the `DeepSeek` specific response fields, so the request is
+//! built with `async_openai` types and the response is parsed here. Streamed responses carry the
+//! reasoning in `reasoning_content` deltas, before the answer.
+use anyhow::{Context as _, Result};
+use async_openai::{
+    config::Config as _,
+    types::{
+        ChatCompletionMessageToolCall, ChatCompletionMessageToolCallChunk,
+        ChatCompletionStreamOptions, CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+    },
+};
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt as _};
+use itertools::Itertools;
+use serde::Deserialize;
+use swiftide_core::chat_completion::{
+    errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+    ChatCompletionStream, ToolCall, Usage,
+};
+
+use crate::{
+    openai::{message_to_openai, tools_to_openai, StreamAccumulator},
+    reqwest_errors::reqwest_error_to_language_model_error,
+};
+
+use super::DeepSeek;
+
+#[derive(Deserialize, Debug)]
+struct DeepSeekChatCompletionResponse {
+    choices: Vec<DeepSeekChoice>,
+    usage: Option<DeepSeekUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeepSeekChoice {
+    message: DeepSeekMessage,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeepSeekMessage {
+    content: Option<String>,
+    reasoning_content: Option<String>,
+    tool_calls: Option<Vec<ChatCompletionMessageToolCall>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeepSeekChunk {
+    choices: Vec<DeepSeekChunkChoice>,
+    usage: Option<DeepSeekUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeepSeekChunkChoice {
+    delta: DeepSeekDelta,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeepSeekDelta {
+    content: Option<String>,
+    reasoning_content: Option<String>,
+    tool_calls: Option<Vec<ChatCompletionMessageToolCallChunk>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeepSeekUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    total_tokens: u32,
+    prompt_cache_hit_tokens: Option<u32>,
+}
+
+impl From<DeepSeekUsage> for Usage {
+    fn from(usage: DeepSeekUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            cached_prompt_tokens: usage.prompt_cache_hit_tokens,
+        }
+    }
+}
+
+impl DeepSeek {
+    fn chat_completion_request(
+        &self,
+        request: &ChatCompletionRequest,
+        stream: bool,
+    ) -> Result<CreateChatCompletionRequest, LanguageModelError> {
+        let model = self
+            .default_options
+            .prompt_model
+            .as_ref()
+            .context("Model not set")?;
+
+        let messages = request
+            .messages()
+            .iter()
+            .map(message_to_openai)
+            .flatten_ok()
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut deepseek_request = CreateChatCompletionRequestArgs::default()
+            .model(model)
+            .messages(messages)
+            .to_owned();
+
+        if !request.tools_spec.is_empty() {
+            deepseek_request
+                .tools(
+                    request
+                        .tools_spec()
+                        .iter()
+                        .map(tools_to_openai)
+                        .collect::<Result<Vec<_>>>()?,
+                )
+                .tool_choice("auto");
+        }
+
+        if stream {
+            deepseek_request
+                .stream(true)
+                .stream_options(ChatCompletionStreamOptions {
+                    include_usage: true,
+                });
+        }
+
+        let request = deepseek_request
+            .build()
+            .map_err(LanguageModelError::permanent)?;
+
+        tracing::debug!(
+            model = &model,
+            request = serde_json::to_string_pretty(&request).expect("infallible"),
+            "Sending request to DeepSeek"
+        );
+
+        Ok(request)
+    }
+
+    async fn send(
+        &self,
+        request: &CreateChatCompletionRequest,
+    ) -> Result<reqwest::Response, LanguageModelError> {
+        let config = self.client.config();
+
+        self.http_client
+            .post(config.url("/chat/completions"))
+            .headers(config.headers())
+            .json(request)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(reqwest_error_to_language_model_error)
+    }
+}
+
+#[async_trait]
+impl ChatCompletion for DeepSeek {
+    #[tracing::instrument(skip_all)]
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let request = self.chat_completion_request(request, false)?;
+
+        let response = self
+            .send(&request)
+            .await?
+            .json::<DeepSeekChatCompletionResponse>()
+            .await
+            .map_err(LanguageModelError::permanent)?;
+
+        tracing::debug!(?response, "Received response from DeepSeek");
+
+        let usage = response.usage.map(Usage::from);
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message);
+
+        ChatCompletionResponse::builder()
+            .maybe_message(message.as_ref().and_then(|m| m.content.clone()))
+            .maybe_reasoning_content(message.as_ref().and_then(|m| m.reasoning_content.clone()))
+            .maybe_tool_calls(message.as_ref().and_then(|m| m.tool_calls.as_ref()).map(
+                |tool_calls| {
+                    tool_calls
+                        .iter()
+                        .map(|tool_call| {
+                            ToolCall::builder()
+                                .id(tool_call.id.clone())
+                                .args(tool_call.function.arguments.clone())
+                                .name(tool_call.function.name.clone())
+                                .build()
+                                .expect("infallible")
+                        })
+                        .collect_vec()
+                },
+            ))
+            .maybe_usage(usage)
+            .build()
+            .map_err(LanguageModelError::from)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        let response = match self.chat_completion_request(request, true) {
+            Ok(request) => self.send(&request).await,
+            Err(err) => Err(err),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => return Box::pin(futures_util::stream::once(async move { Err(err) })),
+        };
+
+        let mut accumulated = StreamAccumulator::default();
+
+        Box::pin(sse_data(response).map(move |data| {
+            let chunk = serde_json::from_str::<DeepSeekChunk>(&data?)
+                .map_err(LanguageModelError::permanent)?;
+
+            let mut delta = None;
+            if let Some(choice) = chunk.choices.into_iter().next() {
+                if let Some(reasoning) = &choice.delta.reasoning_content {
+                    accumulated.reasoning_content.push_str(reasoning);
+                }
+                accumulated.add_tool_calls(choice.delta.tool_calls.unwrap_or_default());
+                delta = choice.delta.content;
+            }
+            if let Some(content) = &delta {
+                accumulated.message.push_str(content);
+            }
+            if let Some(usage) = chunk.usage {
+                accumulated.usage = Some(usage.into());
+            }
+
+            accumulated.response(delta)
+        }))
+    }
+}
+
+/// The data of the server-sent events of a streamed response, until `[DONE]`
+///
+/// Comments, like the keep-alives `DeepSeek` sends while it is busy, are skipped.
+fn sse_data(response: reqwest::Response) -> impl Stream<Item = Result<String, LanguageModelError>> {
+    let mut buffer = Vec::new();
+
+    response
+        .bytes_stream()
+        .map(move |bytes| {
+            buffer.extend_from_slice(&bytes.map_err(reqwest_error_to_language_model_error)?);
+
+            // Events can be split over chunks, only complete events are parsed
+            let mut events = Vec::new();
+            while let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+                let event = buffer.drain(..end + 2).collect::<Vec<_>>();
+                events.extend(
+                    String::from_utf8_lossy(&event)
+                        .lines()
+                        .filter_map(|line| line.strip_prefix("data:"))
+                        .map(|data| data.trim().to_string()),
+                );
+            }
+
+            Ok(events)
+        })
+        .flat_map(|events: Result<Vec<String>, LanguageModelError>| {
+            futures_util::stream::iter(match events {
+                Ok(events) => events.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)],
+            })
+        })
+        .take_while(|data| std::future::ready(!matches!(data, Ok(data) if data == "[DONE]")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::deepseek::config::DeepSeekConfig;
+    use futures_util::StreamExt as _;
+    use serde_json::json;
+    use swiftide_core::chat_completion::ChatMessage;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_complete_stream_with_reasoning_content() {
+        let mock_server = MockServer::start().await;
+
+        let chunk = |delta: serde_json::Value| {
+            format!(
+                "data: {}\n\n",
+                json!({ "choices": [{ "index": 0, "delta": delta }] })
+            )
+        };
+        let body = [
+            ": keep-alive\n\n".to_string(),
+            chunk(json!({ "role": "assistant", "reasoning_content": "The capital " })),
+            chunk(json!({ "reasoning_content": "of France is Paris" })),
+            chunk(json!({ "content": "Par" })),
+            chunk(json!({ "content": "is" })),
+            format!(
+                "data: {}\n\n",
+                json!({
+                    "choices": [],
+                    "usage": {
+                        "prompt_tokens": 10,
+                        "completion_tokens": 5,
+                        "total_tokens": 15,
+                        "prompt_cache_hit_tokens": 8
+                    }
+                })
+            ),
+            "data: [DONE]\n\n".to_string(),
+        ]
+        .concat();
+
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&mock_server)
+            .await;
+
+        let config = DeepSeekConfig::builder()
+            .api_base(mock_server.uri())
+            .api_key("test")
+            .build()
+            .unwrap();
+        let deepseek = DeepSeek::builder()
+            .client(async_openai::Client::with_config(config))
+            .default_prompt_model("deepseek-reasoner")
+            .build()
+            .unwrap();
+
+        let request = ChatCompletionRequest::builder()
+            .messages(vec![ChatMessage::User(
+                "What is the capital of France?".into(),
+            )])
+            .build()
+            .unwrap();
+
+        let responses = deepseek
+            .complete_stream(&request)
+            .await
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let deltas = responses
+            .iter()
+            .filter_map(ChatCompletionResponse::delta)
+            .collect::<Vec<_>>();
+        assert_eq!(deltas, vec!["Par", "is"]);
+
+        let response = responses.last().unwrap();
+        assert_eq!(response.message(), Some("Paris"));
+        assert_eq!(
+            response.reasoning_content(),
+            Some("The capital of France is Paris")
+        );
+        assert_eq!(response.usage().unwrap().cached_prompt_tokens, Some(8));
+    }
+
+    #[tokio::test]
+    async fn test_complete_with_reasoning_content() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "deepseek-reasoner",
+                "choices": [{
+                    "index": 0,
+                    "finish_reason": "stop",
+                    "message": {
+                        "role": "assistant",
+                        "content": "Paris",
+                        "reasoning_content": "The capital of France is Paris"
+                    }
+                }],
+                "usage": {
+                    "prompt_tokens": 10,
+                    "completion_tokens": 5,
+                    "total_tokens": 15,
+                    "prompt_cache_hit_tokens": 8,
+                    "prompt_cache_miss_tokens": 2
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = DeepSeekConfig::builder()
+            .api_base(mock_server.uri())
+            .api_key("test")
+            .build()
+            .unwrap();
+        let deepseek = DeepSeek::builder()
+            .client(async_openai::Client::with_config(config))
+            .default_prompt_model("deepseek-reasoner")
+            .build()
+            .unwrap();
+
+        let request = ChatCompletionRequest::builder()
+            .messages(vec![ChatMessage::User(
+                "What is the capital of France?".into(),
+            )])
+            .build()
+            .unwrap();
+
+        let response = deepseek.complete(&request).await.unwrap();
+
+        assert_eq!(response.message(), Some("Paris"));
+        assert_eq!(
+            response.reasoning_content(),
+            Some("The capital of France is Paris")
+        );
+        assert_eq!(
+            response.usage(),
+            Some(&Usage {
+                prompt_tokens: 10,
+                completion_tokens: 5,
+                total_tokens: 15,
+                cached_prompt_tokens: Some(8),
+            })
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! This module provides integration with `DeepSeek`'s API, enabling the use of language models within the Swiftide project.
+//! It includes the `DeepSeek` struct for managing API clients and default options for prompt models.
+//! The module is conditionally compiled based on the "deepseek" feature flag.
+//!
+//! Reasoning models (i.e. `deepseek-reasoner`) return their chain of thought separately from
+//! the answer. It is exposed as [`swiftide_core::chat_completion::ChatCompletionResponse::reasoning_content`],
+//! also on streamed responses, where the reasoning is streamed before the answer.
+//! Context caching is automatic on `DeepSeek`; cache hits are reported as cached prompt tokens on the usage.
+
+use async_trait::async_trait;
+use config::DeepSeekConfig;
+use derive_builder::Builder;
+use std::sync::Arc;
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError,
+    model_provider::{ModelInfo, ModelProvider},
+};
+
+pub mod chat_completion;
+pub mod config;
+pub mod simple_prompt;
+
+/// The `DeepSeek` struct encapsulates a `DeepSeek` client and default options for prompt models.
+/// It uses the `Builder` pattern for flexible and customizable instantiation.
+///
+/// By default it will look for a `DEEPSEEK_API_KEY` environment variable. Note that a prompt model
+/// always needs to be set, either with [`DeepSeek::with_default_prompt_model`] or via the builder.
+/// You can find available models in the `DeepSeek` documentation.
+///
+/// Under the hood it uses [`async_openai`], with the `DeepSeek` openai compatible api. Chat
+/// completions are sent directly, so that the `DeepSeek` specific response fields are available.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::deepseek::DeepSeek;
+/// let deepseek = DeepSeek::builder()
+///     .default_prompt_model("deepseek-reasoner")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Builder, Clone)]
+#[builder(setter(into, strip_option))]
+pub struct DeepSeek {
+    /// The `DeepSeek` client, wrapped in an `Arc` for thread-safe reference counting.
+    #[builder(default = "default_client()", setter(custom))]
+    client: Arc<async_openai::Client<DeepSeekConfig>>,
+    /// Http client used for requests that need `DeepSeek` specific response fields
+    #[builder(default)]
+    http_client: reqwest::Client,
+    /// Default options for the prompt models.
+    #[builder(default)]
+    default_options: Options,
+}
+
+impl Default for DeepSeek {
+    fn default() -> Self {
+        Self {
+            client: default_client(),
+            http_client: reqwest::Client::default(),
+            default_options: Options::default(),
+        }
+    }
+}
+
+/// The `Options` struct holds configuration options for the `DeepSeek` client.
+/// It includes optional fields for specifying the prompt model.
+#[derive(Debug, Default, Clone, Builder)]
+#[builder(setter(into, strip_option))]
+pub struct Options {
+    /// The default prompt model to use, if specified.
+    #[builder(default)]
+    pub prompt_model: Option<String>,
+}
+
+impl Options {
+    /// Creates a new `OptionsBuilder` for constructing `Options` instances.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+}
+
+impl DeepSeek {
+    /// Creates a new `DeepSeekBuilder` for constructing `DeepSeek` instances.
+    pub fn builder() -> DeepSeekBuilder {
+        DeepSeekBuilder::default()
+    }
+
+    /// Sets a default prompt model to use when prompting
+    pub fn with_default_prompt_model(&mut self, model: impl Into<String>) -> &mut Self {
+        self.default_options = Options {
+            prompt_model: Some(model.into()),
+        };
+        self
+    }
+}
+
+impl DeepSeekBuilder {
+    /// Sets the `DeepSeek` client for the `DeepSeek` instance.
+    ///
+    /// # Parameters
+    /// - `client`: The `DeepSeek` client to set.
+    ///
+    /// # Returns
+    /// A mutable reference to the `DeepSeekBuilder`.
+    pub fn client(&mut self, client: async_openai::Client<DeepSeekConfig>) -> &mut Self {
+        self.client = Some(Arc::new(client));
+        self
+    }
+
+    /// Sets the default prompt model for the `DeepSeek` instance.
+    ///
+    /// # Parameters
+    /// - `model`: The prompt model to set.
+    ///
+    /// # Returns
+    /// A mutable reference to the `DeepSeekBuilder`.
+    pub fn default_prompt_model(&mut self, model: impl Into<String>) -> &mut Self {
+        if let Some(options) = self.default_options.as_mut() {
+            options.prompt_model = Some(model.into());
+        } else {
+            self.default_options = Some(Options {
+                prompt_model: Some(model.into()),
+            });
+        }
+        self
+    }
+}
+
+#[async_trait]
+impl ModelProvider for DeepSeek {
+    #[tracing::instrument(skip_all, err)]
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LanguageModelError> {
+        crate::openai::list_models(
+            &self.client,
+            crate::openai::openai_error_to_language_model_error,
+        )
+        .await
+    }
+}
+
+fn default_client() -> Arc<async_openai::Client<DeepSeekConfig>> {
+    Arc::new(async_openai::Client::with_config(DeepSeekConfig::default()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_prompt_model() {
+        let deepseek = DeepSeek::builder()
+            .default_prompt_model("deepseek-chat")
+            .build()
+            .unwrap();
+        assert_eq!(
+            deepseek.default_options.prompt_model,
+            Some("deepseek-chat".to_string())
+        );
+    }
+
+    #[test]
+    fn test_building_via_default_prompt_model() {
+        let mut client = DeepSeek::default();
+
+        assert!(client.default_options.prompt_model.is_none());
+
+        client.with_default_prompt_model("deepseek-reasoner");
+        assert_eq!(
+            client.default_options.prompt_model,
+            Some("deepseek-reasoner".to_string())
+        );
+    }
+}
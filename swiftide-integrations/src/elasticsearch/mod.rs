@@ -0,0 +1,155 @@
+//! This module provides integration with `Elasticsearch` for storing and retrieving nodes.
+//!
+//! Nodes are stored as documents with their chunk as `content`, their metadata under `metadata`
+//! and every configured vector as a `dense_vector` field. Retrieval supports kNN search with the
+//! `SimilaritySingleEmbedding` strategy, with filters in the `Elasticsearch` query dsl, and
+//! hybrid search combining BM25 on the content with kNN with the `HybridSearch` strategy.
+//!
+//! The api is called directly over http, so any `Elasticsearch` 8 cluster works, including
+//! `Elastic Cloud` with an api key.
+//!
+//! The module is conditionally compiled based on the "elasticsearch" feature flag.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use derive_builder::Builder;
+use secrecy::{ExposeSecret as _, SecretString};
+use swiftide_core::indexing::EmbeddedField;
+
+mod persist;
+mod retrieve;
+
+const DEFAULT_URL: &str = "http://localhost:9200";
+const DEFAULT_INDEX_NAME: &str = "swiftide";
+const DEFAULT_BATCH_SIZE: usize = 50;
+
+/// Stores nodes in and retrieves documents from an `Elasticsearch` index
+///
+/// Implements `Persist` and `Retrieve`. `setup` creates the index with mappings for the content,
+/// metadata and vectors if it does not exist yet.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_core::indexing::EmbeddedField;
+/// # use swiftide_integrations::elasticsearch::Elasticsearch;
+/// Elasticsearch::builder()
+///     .url("http://localhost:9200")
+///     .index_name("swiftide")
+///     .vector_size(1536)
+///     .with_vector(EmbeddedField::Combined)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct Elasticsearch {
+    /// The url of the cluster, defaults to `http://localhost:9200`
+    #[builder(default = "DEFAULT_URL.to_string()")]
+    url: String,
+    /// Api key to authenticate with, i.e. for `Elastic Cloud`
+    #[builder(default)]
+    api_key: Option<SecretString>,
+    #[builder(default)]
+    http_client: reqwest::Client,
+    /// The name of the index, defaults to `swiftide`
+    #[builder(default = "DEFAULT_INDEX_NAME.to_string()")]
+    index_name: String,
+    /// The dimensions of the vectors
+    vector_size: u64,
+    /// The vectors to store, defaults to `EmbeddedField::Combined`
+    #[builder(default = "HashSet::from([EmbeddedField::Combined])", setter(custom))]
+    vectors: HashSet<EmbeddedField>,
+    /// The number of nodes in a single bulk request, defaults to 50
+    #[builder(default = "DEFAULT_BATCH_SIZE")]
+    batch_size: usize,
+}
+
+impl std::fmt::Debug for Elasticsearch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Elasticsearch")
+            .field("url", &self.url)
+            .field("index_name", &self.index_name)
+            .field("vector_size", &self.vector_size)
+            .field("vectors", &self.vectors)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Elasticsearch {
+    pub fn builder() -> ElasticsearchBuilder {
+        ElasticsearchBuilder::default()
+    }
+
+    pub fn index_name(&self) -> &str {
+        &self.index_name
+    }
+
+    /// Builds a request to the cluster, authenticated with the api key if set
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .http_client
+            .request(method, format!("{}/{path}", self.url.trim_end_matches('/')));
+
+        match &self.api_key {
+            Some(api_key) => request.header(
+                reqwest::header::AUTHORIZATION,
+                format!("ApiKey {}", api_key.expose_secret()),
+            ),
+            None => request,
+        }
+    }
+
+    /// Sends a request and returns the json response, failing on error statuses with the body
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<serde_json::Value> {
+        let response = request.send().await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Elasticsearch returned {status}: {body}");
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+impl ElasticsearchBuilder {
+    /// Adds a vector to store, replacing the default `EmbeddedField::Combined`
+    pub fn with_vector(&mut self, vector: impl Into<EmbeddedField>) -> &mut Self {
+        self.vectors
+            .get_or_insert_with(HashSet::new)
+            .insert(vector.into());
+        self
+    }
+}
+
+/// Returns the name of the `dense_vector` field for an embedded field
+pub(crate) fn vector_field_name(field: &EmbeddedField) -> String {
+    format!(
+        "vector_{}",
+        field
+            .field_name()
+            .to_lowercase()
+            .replace(|c: char| !c.is_alphanumeric(), "_")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vector_field_name() {
+        assert_eq!(
+            vector_field_name(&EmbeddedField::Combined),
+            "vector_combined"
+        );
+        assert_eq!(
+            vector_field_name(&EmbeddedField::Metadata("Title".into())),
+            "vector_metadata__title"
+        );
+    }
+}
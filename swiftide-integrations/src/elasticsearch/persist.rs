@@ -0,0 +1,204 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+use swiftide_core::{
+    indexing::{IndexingStream, Node, PersistError},
+    Persist,
+};
+
+use super::{vector_field_name, Elasticsearch};
+
+#[async_trait]
+impl Persist for Elasticsearch {
+    /// Creates the index with mappings for the content, metadata and vectors if it does not exist
+    #[tracing::instrument(skip_all)]
+    async fn setup(&self) -> Result<(), PersistError> {
+        let response = self
+            .request(Method::HEAD, &self.index_name)
+            .send()
+            .await
+            .context("Failed to check if index exists")?;
+
+        if response.status() != StatusCode::NOT_FOUND {
+            response
+                .error_for_status()
+                .context("Failed to check if index exists")?;
+            tracing::debug!(index = self.index_name, "Index already exists");
+            return Ok(());
+        }
+
+        tracing::info!(index = self.index_name, "Creating index");
+        self.send(
+            self.request(Method::PUT, &self.index_name)
+                .json(&self.index_mappings()),
+        )
+        .await
+        .context("Failed to create index")?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        self.bulk_index(std::slice::from_ref(&node)).await?;
+        Ok(node)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
+        self.bulk_index(&nodes).await.map(|()| nodes).into()
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        Some(self.batch_size)
+    }
+}
+
+impl Elasticsearch {
+    fn index_mappings(&self) -> serde_json::Value {
+        let mut properties = json!({
+            "content": { "type": "text" },
+            "path": { "type": "keyword" },
+            "metadata": { "type": "object", "dynamic": true },
+        });
+
+        for field in &self.vectors {
+            properties[vector_field_name(field)] = json!({
+                "type": "dense_vector",
+                "dims": self.vector_size,
+                "index": true,
+                "similarity": "cosine",
+            });
+        }
+
+        json!({ "mappings": { "properties": properties } })
+    }
+
+    /// Indexes nodes with the bulk api, replacing existing documents with the same id
+    async fn bulk_index(&self, nodes: &[Node]) -> Result<()> {
+        let mut body = String::new();
+
+        for node in nodes {
+            let action =
+                json!({ "index": { "_index": self.index_name, "_id": node.id().to_string() } });
+            body.push_str(&serde_json::to_string(&action)?);
+            body.push('\n');
+            body.push_str(&serde_json::to_string(&self.node_to_document(node)?)?);
+            body.push('\n');
+        }
+
+        let response = self
+            .send(
+                self.request(Method::POST, "_bulk")
+                    .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+                    .body(body),
+            )
+            .await
+            .context("Failed to store nodes")?;
+
+        // The bulk api succeeds as a whole, with the errors per document in the items
+        if response["errors"].as_bool().unwrap_or_default() {
+            let error = response["items"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find_map(|item| item["index"].get("error"))
+                .cloned()
+                .unwrap_or_default();
+
+            anyhow::bail!("Failed to store nodes: {error}");
+        }
+
+        Ok(())
+    }
+
+    fn node_to_document(&self, node: &Node) -> Result<serde_json::Value> {
+        let mut document = json!({
+            "content": node.chunk,
+            "path": node.path.to_string_lossy(),
+            "metadata": node.metadata,
+        });
+
+        for field in &self.vectors {
+            let vector = node
+                .vectors
+                .as_ref()
+                .and_then(|vectors| vectors.get(field))
+                .with_context(|| format!("Node without vector for {field}"))?;
+
+            document[vector_field_name(field)] = json!(vector);
+        }
+
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::TryStreamExt as _;
+    use swiftide_core::indexing::EmbeddedField;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn elasticsearch(mock_server: &MockServer) -> Elasticsearch {
+        Elasticsearch::builder()
+            .url(mock_server.uri())
+            .index_name("swiftide_test")
+            .vector_size(3u64)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_setup_creates_missing_index() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("HEAD"))
+            .and(path("/swiftide_test"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/swiftide_test"))
+            .and(body_string_contains("dense_vector"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "acknowledged": true })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        elasticsearch(&mock_server).setup().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_store_fails_on_item_errors() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "errors": true,
+                "items": [{ "index": { "status": 400, "error": { "type": "mapper_parsing_exception" } } }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let mut node = Node::new("chunk");
+        node.with_vectors([(EmbeddedField::Combined, vec![1.0; 3])]);
+
+        let result = elasticsearch(&mock_server)
+            .batch_store(vec![node])
+            .await
+            .try_collect::<Vec<_>>()
+            .await;
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("mapper_parsing_exception"));
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use reqwest::Method;
+use serde_json::json;
+use swiftide_core::{
+    document::Document,
+    indexing::{EmbeddedField, Metadata},
+    querying::{
+        search_strategies::{HybridSearch, SimilaritySingleEmbedding},
+        states, Query, RetrieveError,
+    },
+    Retrieve,
+};
+
+use super::{vector_field_name, Elasticsearch};
+
+/// The number of candidates per shard for kNN search, relative to the number of results
+const NUM_CANDIDATES_FACTOR: u64 = 10;
+
+/// Implement the `Retrieve` trait for `SimilaritySingleEmbedding` search strategy.
+///
+/// Can be used in the query pipeline to retrieve documents from Elasticsearch with kNN search.
+///
+/// Supports filters in the `Elasticsearch` query dsl, i.e. `{"term": {"metadata.filter": "true"}}`.
+/// Filters are applied during the kNN search, so that `top_k` documents are returned if enough
+/// documents match.
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding<serde_json::Value>> for Elasticsearch {
+    #[tracing::instrument]
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding<serde_json::Value>,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.embedding else {
+            return Err(RetrieveError::MissingEmbedding);
+        };
+
+        // With multiple vectors, the combined vector is searched
+        let field = match self.vectors.iter().next() {
+            Some(field) if self.vectors.len() == 1 => field,
+            _ => &EmbeddedField::Combined,
+        };
+
+        let mut knn = self.knn(field, embedding, search_strategy.top_k())?;
+        if let Some(filter) = search_strategy.filter() {
+            knn["filter"] = filter.clone();
+        }
+
+        let documents = self
+            .search(json!({
+                "knn": knn,
+                "size": search_strategy.top_k(),
+            }))
+            .await?;
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+/// Ensures that the `SimilaritySingleEmbedding` search strategy can be used when no filter is set.
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding> for Elasticsearch {
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        Retrieve::<SimilaritySingleEmbedding<serde_json::Value>>::retrieve(
+            self,
+            &search_strategy.into_concrete_filter::<serde_json::Value>(),
+            query,
+        )
+        .await
+    }
+}
+
+/// Implement the `Retrieve` trait for `HybridSearch` search strategy.
+///
+/// Combines a BM25 match on the content with the current query and a kNN search on the dense
+/// vector field. `Elasticsearch` sums the scores of both, so no sparse embedding is needed.
+///
+/// Expects a dense embedding to be set on the query.
+#[async_trait]
+impl Retrieve<HybridSearch> for Elasticsearch {
+    #[tracing::instrument]
+    async fn retrieve(
+        &self,
+        search_strategy: &HybridSearch,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.embedding else {
+            return Err(RetrieveError::MissingEmbedding);
+        };
+
+        let knn = self.knn(
+            search_strategy.dense_vector_field(),
+            embedding,
+            search_strategy.top_n(),
+        )?;
+
+        let documents = self
+            .search(json!({
+                "query": { "match": { "content": query.current() } },
+                "knn": knn,
+                "size": search_strategy.top_k(),
+            }))
+            .await?;
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+impl Elasticsearch {
+    fn knn(&self, field: &EmbeddedField, embedding: &[f32], k: u64) -> Result<serde_json::Value> {
+        if !self.vectors.contains(field) {
+            anyhow::bail!("Vector field {field} is not configured");
+        }
+
+        Ok(json!({
+            "field": vector_field_name(field),
+            "query_vector": embedding,
+            "k": k,
+            "num_candidates": k * NUM_CANDIDATES_FACTOR,
+        }))
+    }
+
+    async fn search(&self, mut body: serde_json::Value) -> Result<Vec<Document>> {
+        // Vectors are not needed in the documents
+        body["_source"] = json!(["content", "metadata"]);
+
+        let response = self
+            .send(
+                self.request(Method::POST, &format!("{}/_search", self.index_name))
+                    .json(&body),
+            )
+            .await
+            .context("Failed to retrieve from elasticsearch")?;
+
+        response["hits"]["hits"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(hit_into_document)
+            .collect()
+    }
+}
+
+fn hit_into_document(hit: &serde_json::Value) -> Result<Document> {
+    let source = &hit["_source"];
+
+    let content = source["content"]
+        .as_str()
+        .context("Expected content in elasticsearch document")?;
+
+    let metadata = source["metadata"]
+        .as_object()
+        .map(|metadata| {
+            metadata
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<Vec<_>>()
+        })
+        .map(Metadata::from);
+
+    Ok(Document::new(content, metadata))
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn elasticsearch(mock_server: &MockServer) -> Elasticsearch {
+        Elasticsearch::builder()
+            .url(mock_server.uri())
+            .index_name("swiftide_test")
+            .vector_size(3u64)
+            .build()
+            .unwrap()
+    }
+
+    fn query() -> Query<states::Pending> {
+        let mut query = Query::<states::Pending>::new("test_query");
+        query.embedding = Some(vec![1.0; 3]);
+        query
+    }
+
+    fn hits() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "hits": {
+                "hits": [{
+                    "_id": "1",
+                    "_source": { "content": "test_query1", "metadata": { "filter": "true" } }
+                }]
+            }
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_with_filter() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/swiftide_test/_search"))
+            .and(body_partial_json(json!({
+                "knn": {
+                    "field": "vector_combined",
+                    "k": 10,
+                    "filter": { "term": { "metadata.filter": "true" } }
+                }
+            })))
+            .respond_with(hits())
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let search_strategy = SimilaritySingleEmbedding::from_filter(
+            json!({ "term": { "metadata.filter": "true" } }),
+        );
+        let result = elasticsearch(&mock_server)
+            .retrieve(&search_strategy, query())
+            .await
+            .unwrap();
+
+        let document = &result.documents()[0];
+        assert_eq!(document.content(), "test_query1");
+        assert_eq!(document.metadata().get("filter"), Some(&json!("true")));
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_hybrid() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/swiftide_test/_search"))
+            .and(body_partial_json(json!({
+                "query": { "match": { "content": "test_query" } },
+                "knn": { "field": "vector_combined" }
+            })))
+            .respond_with(hits())
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = elasticsearch(&mock_server)
+            .retrieve(&HybridSearch::default(), query())
+            .await
+            .unwrap();
+
+        assert_eq!(result.documents().len(), 1);
+    }
+}
@@ -1,16 +1,26 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use swiftide_core::{EmbeddingModel, Embeddings};
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError, EmbeddingModel, EmbeddingPrefixes, Embeddings,
+};
 
 use super::{EmbeddingModelType, FastEmbed};
 #[async_trait]
 impl EmbeddingModel for FastEmbed {
     #[tracing::instrument(skip_all)]
-    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
         if let EmbeddingModelType::Dense(embedding_model) = &*self.embedding_model {
-            embedding_model.embed(input, self.batch_size)
+            embedding_model
+                .embed(input, self.batch_size)
+                .map_err(LanguageModelError::from)
         } else {
-            Err(anyhow::anyhow!("Expected dense model, got sparse"))
+            Err(LanguageModelError::permanent(
+                "Expected dense model, got sparse",
+            ))
         }
     }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.prefixes.as_ref()
+    }
 }
@@ -0,0 +1,155 @@
+//! Configures how and where the ONNX runtime runs the models
+use std::sync::Arc;
+
+use anyhow::Result;
+use fastembed::{
+    EmbeddingModel, ExecutionProviderDispatch, InitOptions, RerankInitOptions, RerankerModel,
+    TextEmbedding, TextRerank,
+};
+use ort::execution_providers::{
+    CPUExecutionProvider, CUDAExecutionProvider, CoreMLExecutionProvider,
+    DirectMLExecutionProvider, ExecutionProvider as _,
+};
+
+use super::EmbeddingModelType;
+
+/// Hardware the ONNX runtime runs a model on
+///
+/// Providers are tried in order. If a provider is not available, e.g. because the runtime was
+/// not built with it, the next one is tried, falling back to the CPU.
+///
+/// CUDA, `CoreML` and `DirectML` need the `fastembed-cuda`, `fastembed-coreml` and
+/// `fastembed-directml` features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExecutionProvider {
+    Cpu,
+    Cuda,
+    CoreML,
+    DirectML,
+}
+
+impl ExecutionProvider {
+    /// Returns true if the ONNX runtime can run models on this provider
+    pub fn is_available(self) -> bool {
+        match self {
+            ExecutionProvider::Cpu => true,
+            ExecutionProvider::Cuda => CUDAExecutionProvider::default()
+                .is_available()
+                .unwrap_or(false),
+            ExecutionProvider::CoreML => CoreMLExecutionProvider::default()
+                .is_available()
+                .unwrap_or(false),
+            ExecutionProvider::DirectML => DirectMLExecutionProvider::default()
+                .is_available()
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl From<ExecutionProvider> for ExecutionProviderDispatch {
+    fn from(provider: ExecutionProvider) -> Self {
+        match provider {
+            ExecutionProvider::Cpu => CPUExecutionProvider::default().build(),
+            ExecutionProvider::Cuda => CUDAExecutionProvider::default().build(),
+            ExecutionProvider::CoreML => CoreMLExecutionProvider::default().build(),
+            ExecutionProvider::DirectML => DirectMLExecutionProvider::default().build(),
+        }
+    }
+}
+
+/// Returns the quantized variant of a text embedding model
+///
+/// # Errors
+///
+/// Errors if fastembed has no quantized variant of the model
+pub(super) fn quantized(model: EmbeddingModel) -> Result<EmbeddingModel> {
+    Ok(match model {
+        EmbeddingModel::AllMiniLML6V2 | EmbeddingModel::AllMiniLML6V2Q => {
+            EmbeddingModel::AllMiniLML6V2Q
+        }
+        EmbeddingModel::AllMiniLML12V2 | EmbeddingModel::AllMiniLML12V2Q => {
+            EmbeddingModel::AllMiniLML12V2Q
+        }
+        EmbeddingModel::BGEBaseENV15 | EmbeddingModel::BGEBaseENV15Q => {
+            EmbeddingModel::BGEBaseENV15Q
+        }
+        EmbeddingModel::BGELargeENV15 | EmbeddingModel::BGELargeENV15Q => {
+            EmbeddingModel::BGELargeENV15Q
+        }
+        EmbeddingModel::BGESmallENV15 | EmbeddingModel::BGESmallENV15Q => {
+            EmbeddingModel::BGESmallENV15Q
+        }
+        EmbeddingModel::NomicEmbedTextV15 | EmbeddingModel::NomicEmbedTextV15Q => {
+            EmbeddingModel::NomicEmbedTextV15Q
+        }
+        EmbeddingModel::ParaphraseMLMiniLML12V2 | EmbeddingModel::ParaphraseMLMiniLML12V2Q => {
+            EmbeddingModel::ParaphraseMLMiniLML12V2Q
+        }
+        EmbeddingModel::MxbaiEmbedLargeV1 | EmbeddingModel::MxbaiEmbedLargeV1Q => {
+            EmbeddingModel::MxbaiEmbedLargeV1Q
+        }
+        EmbeddingModel::GTEBaseENV15 | EmbeddingModel::GTEBaseENV15Q => {
+            EmbeddingModel::GTEBaseENV15Q
+        }
+        EmbeddingModel::GTELargeENV15 | EmbeddingModel::GTELargeENV15Q => {
+            EmbeddingModel::GTELargeENV15Q
+        }
+        model => anyhow::bail!("No quantized variant of {model:?}"),
+    })
+}
+
+/// Loads the text embedding model configured on the builder
+pub(super) fn text_embedding(
+    model: Option<EmbeddingModel>,
+    quantize: bool,
+    execution_providers: Vec<ExecutionProvider>,
+) -> Result<Arc<EmbeddingModelType>> {
+    let mut model = model.unwrap_or(EmbeddingModel::BGESmallENV15);
+    if quantize {
+        model = quantized(model)?;
+    }
+
+    let options = InitOptions::new(model).with_execution_providers(
+        execution_providers
+            .into_iter()
+            .map(ExecutionProviderDispatch::from)
+            .collect(),
+    );
+
+    Ok(Arc::new(TextEmbedding::try_new(options)?.into()))
+}
+
+/// Loads the reranker model configured on the builder
+pub(super) fn text_rerank(
+    model: Option<RerankerModel>,
+    execution_providers: Vec<ExecutionProvider>,
+) -> Result<Arc<TextRerank>> {
+    let options = RerankInitOptions::new(model.unwrap_or(RerankerModel::BGERerankerBase))
+        .with_execution_providers(
+            execution_providers
+                .into_iter()
+                .map(ExecutionProviderDispatch::from)
+                .collect(),
+        );
+
+    Ok(Arc::new(TextRerank::try_new(options)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantized() {
+        assert_eq!(
+            quantized(EmbeddingModel::BGESmallENV15).unwrap(),
+            EmbeddingModel::BGESmallENV15Q
+        );
+        assert_eq!(
+            quantized(EmbeddingModel::BGESmallENV15Q).unwrap(),
+            EmbeddingModel::BGESmallENV15Q
+        );
+        assert!(quantized(EmbeddingModel::ClipVitB32).is_err());
+    }
+}
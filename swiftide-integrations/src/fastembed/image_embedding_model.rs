@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use swiftide_core::{chat_completion::errors::LanguageModelError, Embeddings, ImageEmbeddingModel};
+
+use super::{EmbeddingModelType, FastEmbed};
+#[async_trait]
+impl ImageEmbeddingModel for FastEmbed {
+    #[tracing::instrument(skip_all)]
+    async fn embed_images(&self, images: Vec<PathBuf>) -> Result<Embeddings, LanguageModelError> {
+        if let EmbeddingModelType::Image(embedding_model) = &*self.embedding_model {
+            embedding_model
+                .embed(images, self.batch_size)
+                .map_err(LanguageModelError::from)
+        } else {
+            Err(LanguageModelError::permanent(
+                "Expected image model, got text",
+            ))
+        }
+    }
+}
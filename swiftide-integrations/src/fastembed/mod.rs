@@ -1,20 +1,32 @@
-//! `FastEmbed` integration for text embedding.
+//! `FastEmbed` integration for text and image embedding, and reranking.
 
 use std::sync::Arc;
 
 use anyhow::Result;
 use derive_builder::Builder;
-use fastembed::{SparseTextEmbedding, TextEmbedding};
-
+use fastembed::{
+    EmbeddingModel as TextEmbeddingModel, ImageEmbedding, SparseTextEmbedding, TextEmbedding,
+};
+use swiftide_core::EmbeddingPrefixes;
+
+pub use execution_provider::ExecutionProvider;
+pub use model_files::ModelFiles;
+pub use rerank::Rerank;
 pub use swiftide_core::EmbeddingModel as _;
+pub use swiftide_core::ImageEmbeddingModel as _;
 pub use swiftide_core::SparseEmbeddingModel as _;
 
 mod embedding_model;
+mod execution_provider;
+mod image_embedding_model;
+mod model_files;
+mod rerank;
 mod sparse_embedding_model;
 
 pub enum EmbeddingModelType {
     Dense(TextEmbedding),
     Sparse(SparseTextEmbedding),
+    Image(ImageEmbedding),
 }
 
 impl From<TextEmbedding> for EmbeddingModelType {
@@ -29,6 +41,12 @@ impl From<SparseTextEmbedding> for EmbeddingModelType {
     }
 }
 
+impl From<ImageEmbedding> for EmbeddingModelType {
+    fn from(val: ImageEmbedding) -> Self {
+        EmbeddingModelType::Image(val)
+    }
+}
+
 /// Default batch size for embedding
 ///
 /// Matches the default batch size in [`fastembed`](https://docs.rs/fastembed)
@@ -42,7 +60,11 @@ const DEFAULT_BATCH_SIZE: usize = 256;
 /// A default can also be used for sparse embeddings, which by default uses Splade. Sparse
 /// embeddings are useful for more exact search in combination with dense vectors.
 ///
-/// `Into` is implemented for all available models from fastembed-rs.
+/// Images can be embedded with an image model, by default CLIP. Embed text with the matching
+/// CLIP text model to store text and images in the same collection.
+///
+/// `Into` is implemented for all available models from fastembed-rs. Fine-tuned and other ONNX
+/// models can be loaded with [`FastEmbed::try_from_model_files`].
 ///
 /// See the [FastEmbed documentation](https://docs.rs/fastembed) for more information on usage.
 ///
@@ -50,6 +72,10 @@ const DEFAULT_BATCH_SIZE: usize = 256;
 /// also be set and is recommended. Batch size should match the batch size in the indexing
 /// pipeline.
 ///
+/// For throughput, the builder can load a quantized variant of the model and run it on a GPU with
+/// an [`ExecutionProvider`]. The ONNX runtime uses a thread per core, fastembed does not allow
+/// configuring this.
+///
 /// Note that the embedding vector dimensions need to match the dimensions of the vector database collection
 ///
 /// Requires the `fastembed` feature to be enabled.
@@ -62,17 +88,40 @@ const DEFAULT_BATCH_SIZE: usize = 256;
 pub struct FastEmbed {
     #[builder(
         setter(custom),
-        default = "Arc::new(TextEmbedding::try_new(Default::default())?.into())"
+        default = "execution_provider::text_embedding(self.model.clone().flatten(), self.quantized.unwrap_or_default(), self.execution_providers.clone().unwrap_or_default())?"
     )]
     embedding_model: Arc<EmbeddingModelType>,
     #[builder(default = "Some(DEFAULT_BATCH_SIZE)")]
     batch_size: Option<usize>,
+    /// The text embedding model to load, defaults to `Flag Embedding`
+    ///
+    /// Ignored if an embedding model is set directly.
+    #[builder(default)]
+    model: Option<TextEmbeddingModel>,
+    /// Loads the quantized variant of the model, which is smaller and faster at a small cost in
+    /// quality
+    ///
+    /// Ignored if an embedding model is set directly.
+    #[builder(default)]
+    quantized: bool,
+    /// Hardware to run the model on, in order of preference, defaults to the CPU
+    ///
+    /// Ignored if an embedding model is set directly.
+    #[builder(default, setter(custom))]
+    execution_providers: Vec<ExecutionProvider>,
+    /// Prefixes for queries and documents, e.g. [`EmbeddingPrefixes::e5`] for e5 models
+    #[builder(default)]
+    prefixes: Option<EmbeddingPrefixes>,
 }
 
 impl std::fmt::Debug for FastEmbed {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FastEmbedBuilder")
             .field("batch_size", &self.batch_size)
+            .field("model", &self.model)
+            .field("quantized", &self.quantized)
+            .field("execution_providers", &self.execution_providers)
+            .field("prefixes", &self.prefixes)
             .finish()
     }
 }
@@ -100,6 +149,21 @@ impl FastEmbed {
             .build()
     }
 
+    /// Tries to build a default `FastEmbed` for image embeddings using CLIP
+    ///
+    /// Text embedded with `fastembed::EmbeddingModel::ClipVitB32` is in the same space.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the build fails
+    pub fn try_default_image() -> Result<Self> {
+        Self::builder()
+            .embedding_model(ImageEmbedding::try_new(
+                fastembed::ImageInitOptions::default(),
+            )?)
+            .build()
+    }
+
     pub fn builder() -> FastEmbedBuilder {
         FastEmbedBuilder::default()
     }
@@ -112,6 +176,16 @@ impl FastEmbedBuilder {
 
         self
     }
+
+    /// Adds a provider to run the model on, providers are tried in the order they are added
+    #[must_use]
+    pub fn execution_provider(mut self, provider: ExecutionProvider) -> Self {
+        self.execution_providers
+            .get_or_insert_with(Vec::new)
+            .push(provider);
+
+        self
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,182 @@
+//! Load user provided ONNX models, e.g. fine-tuned embedders, from disk or the Hugging Face hub
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use fastembed::{
+    InitOptionsUserDefined, Pooling, TextEmbedding, TokenizerFiles, UserDefinedEmbeddingModel,
+};
+
+use super::FastEmbed;
+
+/// Candidates for the ONNX file, relative to the model directory or repository
+const ONNX_FILES: [&str; 2] = ["model.onnx", "onnx/model.onnx"];
+const TOKENIZER_FILE: &str = "tokenizer.json";
+const CONFIG_FILE: &str = "config.json";
+const SPECIAL_TOKENS_MAP_FILE: &str = "special_tokens_map.json";
+const TOKENIZER_CONFIG_FILE: &str = "tokenizer_config.json";
+
+/// The files of a dense text embedding model in the ONNX format, with its tokenizer
+///
+/// Follows the layout of Hugging Face repositories with ONNX exports: a `model.onnx` (or
+/// `onnx/model.onnx`) next to a `tokenizer.json`, `config.json`, `special_tokens_map.json` and
+/// `tokenizer_config.json`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::fastembed::{FastEmbed, ModelFiles};
+/// # fn main() -> anyhow::Result<()> {
+/// let fastembed = FastEmbed::try_from_model_files(ModelFiles::from_dir("./my-embedder"))?;
+///
+/// let fastembed =
+///     FastEmbed::try_from_model_files(ModelFiles::from_hf_repo("my-org/my-embedder")?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModelFiles {
+    onnx_file: PathBuf,
+    tokenizer_file: PathBuf,
+    config_file: PathBuf,
+    special_tokens_map_file: PathBuf,
+    tokenizer_config_file: PathBuf,
+    pooling: Option<Pooling>,
+}
+
+impl ModelFiles {
+    /// Uses the model files in a local directory
+    ///
+    /// The files are only read when the model is loaded.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+        let onnx_file = ONNX_FILES
+            .iter()
+            .map(|file| dir.join(file))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| dir.join(ONNX_FILES[0]));
+
+        Self {
+            onnx_file,
+            tokenizer_file: dir.join(TOKENIZER_FILE),
+            config_file: dir.join(CONFIG_FILE),
+            special_tokens_map_file: dir.join(SPECIAL_TOKENS_MAP_FILE),
+            tokenizer_config_file: dir.join(TOKENIZER_CONFIG_FILE),
+            pooling: None,
+        }
+    }
+
+    /// Downloads the model files from a Hugging Face repository, e.g. `my-org/my-embedder`
+    ///
+    /// Files are cached in the Hugging Face cache, `HF_HOME` and `HF_TOKEN` are respected.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the repository does not exist or a file could not be downloaded
+    pub fn from_hf_repo(repo_id: impl Into<String>) -> Result<Self> {
+        let repo_id = repo_id.into();
+        let repo = hf_hub::api::sync::ApiBuilder::from_env()
+            .build()
+            .context("Failed to build Hugging Face api")?
+            .model(repo_id.clone());
+
+        let get = |file: &str| {
+            repo.get(file)
+                .with_context(|| format!("Failed to download {file} from {repo_id}"))
+        };
+
+        let onnx_file = ONNX_FILES
+            .iter()
+            .find_map(|file| repo.get(file).ok())
+            .with_context(|| format!("No ONNX model found in {repo_id}"))?;
+
+        Ok(Self {
+            onnx_file,
+            tokenizer_file: get(TOKENIZER_FILE)?,
+            config_file: get(CONFIG_FILE)?,
+            special_tokens_map_file: get(SPECIAL_TOKENS_MAP_FILE)?,
+            tokenizer_config_file: get(TOKENIZER_CONFIG_FILE)?,
+            pooling: None,
+        })
+    }
+
+    /// Uses an ONNX file with another name or location
+    #[must_use]
+    pub fn with_onnx_file(mut self, onnx_file: impl Into<PathBuf>) -> Self {
+        self.onnx_file = onnx_file.into();
+        self
+    }
+
+    /// Sets how token embeddings are pooled, defaults to the default of fastembed
+    #[must_use]
+    pub fn with_pooling(mut self, pooling: Pooling) -> Self {
+        self.pooling = Some(pooling);
+        self
+    }
+
+    fn load(&self) -> Result<UserDefinedEmbeddingModel> {
+        let read = |path: &Path| {
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+        };
+
+        let tokenizer_files = TokenizerFiles {
+            tokenizer_file: read(&self.tokenizer_file)?,
+            config_file: read(&self.config_file)?,
+            special_tokens_map_file: read(&self.special_tokens_map_file)?,
+            tokenizer_config_file: read(&self.tokenizer_config_file)?,
+        };
+
+        let model = UserDefinedEmbeddingModel::new(read(&self.onnx_file)?, tokenizer_files);
+
+        Ok(match self.pooling.clone() {
+            Some(pooling) => model.with_pooling(pooling),
+            None => model,
+        })
+    }
+}
+
+impl FastEmbed {
+    /// Tries to build a `FastEmbed` with a user provided ONNX model
+    ///
+    /// # Errors
+    ///
+    /// Errors if the model files cannot be read or the model cannot be loaded
+    pub fn try_from_model_files(files: ModelFiles) -> Result<Self> {
+        let model = TextEmbedding::try_new_from_user_defined(
+            files.load()?,
+            InitOptionsUserDefined::default(),
+        )?;
+
+        Self::builder().embedding_model(model).build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_from_dir_finds_onnx_file() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(
+            ModelFiles::from_dir(dir.path()).onnx_file,
+            dir.path().join("model.onnx")
+        );
+
+        std::fs::create_dir(dir.path().join("onnx")).unwrap();
+        std::fs::write(dir.path().join("onnx/model.onnx"), b"").unwrap();
+        assert_eq!(
+            ModelFiles::from_dir(dir.path()).onnx_file,
+            dir.path().join("onnx/model.onnx")
+        );
+    }
+
+    #[test]
+    fn test_missing_files_error() {
+        let dir = TempDir::new().unwrap();
+        let err = FastEmbed::try_from_model_files(ModelFiles::from_dir(dir.path())).unwrap_err();
+
+        assert!(err.to_string().contains("tokenizer.json"));
+    }
+}
@@ -0,0 +1,175 @@
+//! Local reranking with a `FastEmbed` cross-encoder
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use derive_builder::Builder;
+use fastembed::{RerankerModel, TextRerank};
+use swiftide_core::{document::Document, reranking::select_by_score};
+
+use super::{execution_provider, ExecutionProvider};
+
+/// Reranks documents locally with a `FastEmbed` cross-encoder, implementing
+/// [`swiftide_core::Rerank`].
+///
+/// By default it uses the `BAAI/bge-reranker-base` model. Documents can be limited to the `top_n`
+/// most relevant, and documents scoring below a threshold can be dropped. Scores are the raw
+/// scores of the model, and their range differs per model.
+///
+/// Requires the `fastembed` feature to be enabled.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::fastembed;
+/// # fn main() -> anyhow::Result<()> {
+/// let rerank = fastembed::Rerank::builder()
+///     .top_n(5)
+///     .threshold(0.0)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Use it in a query pipeline with `query::Pipeline::then_rerank`.
+#[derive(Builder, Clone)]
+#[builder(
+    pattern = "owned",
+    setter(strip_option),
+    build_fn(error = "anyhow::Error")
+)]
+pub struct Rerank {
+    #[builder(
+        setter(custom),
+        default = "execution_provider::text_rerank(self.model.clone().flatten(), self.execution_providers.clone().unwrap_or_default())?"
+    )]
+    reranker: Arc<TextRerank>,
+    /// The reranker model to load, defaults to `BAAI/bge-reranker-base`
+    ///
+    /// Ignored if a reranker is set directly.
+    #[builder(default)]
+    model: Option<RerankerModel>,
+    /// Hardware to run the model on, in order of preference, defaults to the CPU
+    ///
+    /// Ignored if a reranker is set directly.
+    #[builder(default, setter(custom))]
+    execution_providers: Vec<ExecutionProvider>,
+    /// Only return the `top_n` most relevant documents
+    #[builder(default)]
+    top_n: Option<usize>,
+    /// Drop documents scoring below the threshold
+    #[builder(default)]
+    threshold: Option<f32>,
+    #[builder(default)]
+    batch_size: Option<usize>,
+}
+
+impl std::fmt::Debug for Rerank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Rerank")
+            .field("model", &self.model)
+            .field("execution_providers", &self.execution_providers)
+            .field("top_n", &self.top_n)
+            .field("threshold", &self.threshold)
+            .field("batch_size", &self.batch_size)
+            .finish()
+    }
+}
+
+impl Rerank {
+    /// Tries to build a default `Rerank` with `BAAI/bge-reranker-base`
+    ///
+    /// # Errors
+    ///
+    /// Errors if the build fails
+    pub fn try_default() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> RerankBuilder {
+        RerankBuilder::default()
+    }
+}
+
+impl RerankBuilder {
+    #[must_use]
+    pub fn reranker(mut self, reranker: TextRerank) -> Self {
+        self.reranker = Some(Arc::new(reranker));
+
+        self
+    }
+
+    /// Adds a hardware provider to run the model on, tried in the order they are added
+    #[must_use]
+    pub fn execution_provider(mut self, provider: ExecutionProvider) -> Self {
+        self.execution_providers
+            .get_or_insert_with(Vec::new)
+            .push(provider);
+
+        self
+    }
+}
+
+#[async_trait]
+impl swiftide_core::Rerank for Rerank {
+    #[tracing::instrument(skip_all, err)]
+    async fn rerank(&self, query: &str, documents: Vec<Document>) -> Result<Vec<Document>> {
+        if documents.is_empty() {
+            return Ok(documents);
+        }
+
+        let results = self.reranker.rerank(
+            query,
+            documents.iter().map(Document::content).collect(),
+            false,
+            self.batch_size,
+        )?;
+
+        let before = documents.len();
+        let reranked = select_by_score(
+            documents,
+            results
+                .into_iter()
+                .map(|result| (result.index, result.score)),
+            self.top_n,
+            self.threshold,
+        );
+
+        tracing::debug!(
+            before,
+            after = reranked.len(),
+            "[Rerank] Reranked with fastembed"
+        );
+
+        Ok(reranked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swiftide_core::Rerank as _;
+
+    #[tokio::test]
+    async fn test_rerank() {
+        let rerank = Rerank::builder().top_n(1).build().unwrap();
+
+        let documents = rerank
+            .rerank(
+                "What is swiftide?",
+                vec![
+                    "Rust is a programming language".into(),
+                    "Swiftide is a library for building llm applications".into(),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            documents,
+            vec![Document::from(
+                "Swiftide is a library for building llm applications"
+            )]
+        );
+    }
+}
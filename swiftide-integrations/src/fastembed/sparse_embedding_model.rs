@@ -1,12 +1,18 @@
 use anyhow::{Context as _, Result};
 use async_trait::async_trait;
-use swiftide_core::{SparseEmbedding, SparseEmbeddingModel, SparseEmbeddings};
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError, SparseEmbedding, SparseEmbeddingModel,
+    SparseEmbeddings,
+};
 
 use super::{EmbeddingModelType, FastEmbed};
 #[async_trait]
 impl SparseEmbeddingModel for FastEmbed {
     #[tracing::instrument(skip_all)]
-    async fn sparse_embed(&self, input: Vec<String>) -> Result<SparseEmbeddings> {
+    async fn sparse_embed(
+        &self,
+        input: Vec<String>,
+    ) -> Result<SparseEmbeddings, LanguageModelError> {
         if let EmbeddingModelType::Sparse(embedding_model) = &*self.embedding_model {
             embedding_model
                 .embed(input, self.batch_size)
@@ -29,8 +35,11 @@ impl SparseEmbeddingModel for FastEmbed {
                         })
                         .collect()
                 })
+                .map_err(LanguageModelError::from)
         } else {
-            Err(anyhow::anyhow!("Expected dense model, got sparse"))
+            Err(LanguageModelError::permanent(
+                "Expected dense model, got sparse",
+            ))
         }
     }
 }
@@ -2,7 +2,10 @@ use std::string::ToString;
 
 use anyhow::Context as _;
 use futures_util::{StreamExt as _, TryStreamExt as _};
-use swiftide_core::{indexing::IndexingStream, indexing::Node, Loader};
+use swiftide_core::{
+    indexing::{IndexingStream, LoaderError, Node},
+    Loader,
+};
 use tokio::runtime::Handle;
 
 use super::Fluvio;
@@ -34,7 +37,8 @@ impl Loader for Fluvio {
 
                 node
             })
-            .map_err(anyhow::Error::from);
+            // Errors while consuming are from the connection to the cluster
+            .map_err(|err| anyhow::Error::from(LoaderError::transient(err)));
 
         swiftide_stream.boxed().into()
     }
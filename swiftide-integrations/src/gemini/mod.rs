@@ -0,0 +1,12 @@
+//! This module provides integration with Google's `Gemini` API.
+//!
+//! Currently it provides [`GeminiTokens`], which counts the tokens of Gemini models with the
+//! `countTokens` endpoint, for precise numbers in rate limits and context windows.
+//!
+//! The module is conditionally compiled based on the "gemini" feature flag.
+
+mod tokenizer;
+
+pub use tokenizer::{GeminiTokens, GeminiTokensBuilder};
+
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
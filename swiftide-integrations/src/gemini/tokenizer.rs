@@ -0,0 +1,127 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use derive_builder::Builder;
+use secrecy::{ExposeSecret as _, SecretString};
+use serde::Deserialize;
+use serde_json::json;
+use swiftide_core::tokenizer::{Estimatable, EstimateTokens};
+
+use super::GEMINI_API_BASE;
+
+/// Counts tokens of Gemini models with the `countTokens` endpoint
+///
+/// By default it will look for a `GEMINI_API_KEY` environment variable. Every estimate is a
+/// request to the api.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_core::tokenizer::EstimateTokens;
+/// # use swiftide_integrations::gemini::GeminiTokens;
+/// # async fn run() -> anyhow::Result<()> {
+/// let gemini = GeminiTokens::builder().model("gemini-2.0-flash").build()?;
+/// let tokens = gemini.estimate(&"Hello, world!").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct GeminiTokens {
+    /// The model to count tokens for, i.e. `gemini-2.0-flash`
+    model: String,
+    #[builder(default = "default_api_key()")]
+    api_key: SecretString,
+    #[builder(default = "GEMINI_API_BASE.to_string()")]
+    api_base: String,
+    #[builder(default)]
+    http_client: reqwest::Client,
+}
+
+impl GeminiTokens {
+    pub fn builder() -> GeminiTokensBuilder {
+        GeminiTokensBuilder::default()
+    }
+}
+
+fn default_api_key() -> SecretString {
+    std::env::var("GEMINI_API_KEY").unwrap_or_default().into()
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CountTokensResponse {
+    total_tokens: usize,
+}
+
+#[async_trait]
+impl EstimateTokens for GeminiTokens {
+    #[tracing::instrument(skip_all, err)]
+    async fn estimate(&self, value: &dyn Estimatable) -> Result<usize> {
+        let parts = value
+            .for_estimate()
+            .await?
+            .iter()
+            .filter(|text| !text.is_empty())
+            .map(|text| json!({ "text": text }))
+            .collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            return Ok(value.additional_tokens());
+        }
+
+        let response = self
+            .http_client
+            .post(format!(
+                "{}/models/{}:countTokens",
+                self.api_base, self.model
+            ))
+            .header("x-goog-api-key", self.api_key.expose_secret())
+            .json(&json!({ "contents": [{ "role": "user", "parts": parts }] }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<CountTokensResponse>()
+            .await?;
+
+        Ok(response.total_tokens + value.additional_tokens())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_estimate() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.0-flash:countTokens"))
+            .and(header("x-goog-api-key", "test"))
+            .and(body_partial_json(json!({
+                "contents": [{ "parts": [{ "text": "hello" }, { "text": "world" }] }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "totalTokens": 2 })))
+            .mount(&mock_server)
+            .await;
+
+        let gemini = GeminiTokens::builder()
+            .model("gemini-2.0-flash")
+            .api_key("test")
+            .api_base(mock_server.uri())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            gemini
+                .estimate(&vec!["hello".to_string(), "world".to_string()])
+                .await
+                .unwrap(),
+            2
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! Chat completion with tool calling and streaming for `Groq`
+//!
+//! Groq exposes an `OpenAI` compatible api, so requests are built with the `OpenAI` mapping. Groq
+//! does not support parallel tool calls for all models, so it is left to the provider default.
+use anyhow::{Context as _, Result};
+use async_openai::types::{CreateChatCompletionRequest, CreateChatCompletionRequestArgs};
+use async_trait::async_trait;
+use futures_util::StreamExt as _;
+use itertools::Itertools;
+use swiftide_core::chat_completion::{
+    errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+    ChatCompletionStream, ToolCall,
+};
+
+use crate::openai::{message_to_openai, tools_to_openai, usage_from_openai, StreamAccumulator};
+
+use super::{groq_error_to_language_model_error, Groq};
+
+impl Groq {
+    fn chat_completion_request(
+        &self,
+        request: &ChatCompletionRequest,
+        stream: bool,
+    ) -> Result<CreateChatCompletionRequest, LanguageModelError> {
+        let model = self
+            .default_options
+            .prompt_model
+            .as_ref()
+            .context("Model not set")?;
+
+        let messages = request
+            .messages()
+            .iter()
+            .map(message_to_openai)
+            .flatten_ok()
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut groq_request = CreateChatCompletionRequestArgs::default()
+            .model(model)
+            .messages(messages)
+            .to_owned();
+
+        if !request.tools_spec.is_empty() {
+            groq_request
+                .tools(
+                    request
+                        .tools_spec()
+                        .iter()
+                        .map(tools_to_openai)
+                        .collect::<Result<Vec<_>>>()?,
+                )
+                .tool_choice("auto");
+        }
+
+        if stream {
+            groq_request.stream(true);
+        }
+
+        let request = groq_request
+            .build()
+            .map_err(LanguageModelError::permanent)?;
+
+        tracing::debug!(
+            model = &model,
+            request = serde_json::to_string_pretty(&request).expect("infallible"),
+            "Sending request to Groq"
+        );
+
+        Ok(request)
+    }
+}
+
+#[async_trait]
+impl ChatCompletion for Groq {
+    #[tracing::instrument(skip_all)]
+    async fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let request = self.chat_completion_request(request, false)?;
+
+        let response = self
+            .client
+            .chat()
+            .create(request)
+            .await
+            .map_err(groq_error_to_language_model_error)?;
+
+        tracing::debug!(
+            response = serde_json::to_string_pretty(&response).expect("infallible"),
+            "Received response from Groq"
+        );
+
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message);
+
+        ChatCompletionResponse::builder()
+            .maybe_message(message.as_ref().and_then(|m| m.content.clone()))
+            .maybe_tool_calls(message.and_then(|m| m.tool_calls).map(|tool_calls| {
+                tool_calls
+                    .into_iter()
+                    .map(|tool_call| {
+                        ToolCall::builder()
+                            .id(tool_call.id)
+                            .args(tool_call.function.arguments)
+                            .name(tool_call.function.name)
+                            .build()
+                            .expect("infallible")
+                    })
+                    .collect_vec()
+            }))
+            .maybe_usage(response.usage.as_ref().map(usage_from_openai))
+            .build()
+            .map_err(LanguageModelError::from)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn complete_stream(&self, request: &ChatCompletionRequest) -> ChatCompletionStream {
+        let request = match self.chat_completion_request(request, true) {
+            Ok(request) => request,
+            Err(err) => return Box::pin(futures_util::stream::once(async move { Err(err) })),
+        };
+
+        let stream = match self.client.chat().create_stream(request).await {
+            Ok(stream) => stream,
+            Err(err) => {
+                let err = groq_error_to_language_model_error(err);
+                return Box::pin(futures_util::stream::once(async move { Err(err) }));
+            }
+        };
+
+        let mut accumulated = StreamAccumulator::default();
+
+        Box::pin(stream.map(move |chunk| {
+            let chunk = chunk.map_err(groq_error_to_language_model_error)?;
+
+            let mut delta = None;
+            if let Some(choice) = chunk.choices.into_iter().next() {
+                delta = choice.delta.content;
+                accumulated.add_tool_calls(choice.delta.tool_calls.unwrap_or_default());
+            }
+            if let Some(content) = &delta {
+                accumulated.message.push_str(content);
+            }
+            if let Some(usage) = &chunk.usage {
+                accumulated.usage = Some(usage_from_openai(usage));
+            }
+
+            accumulated.response(delta)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::groq::GroqConfig;
+    use serde_json::json;
+    use swiftide_core::chat_completion::{ChatMessage, ToolSpec};
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn groq(mock_server: &MockServer) -> Groq {
+        let mut config = GroqConfig::default();
+        config
+            .with_api_base(&mock_server.uri())
+            .with_api_key("test");
+
+        Groq::builder()
+            .client(async_openai::Client::with_config(config))
+            .default_prompt_model("llama-3.3-70b-versatile")
+            .build()
+            .unwrap()
+    }
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest::builder()
+            .messages(vec![ChatMessage::new_user("What is the weather?")])
+            .tools_spec(
+                [ToolSpec::builder()
+                    .name("get_weather")
+                    .description("Gets the weather")
+                    .build()
+                    .unwrap()]
+                .into_iter()
+                .collect::<std::collections::HashSet<_>>(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_complete_with_tool_calls() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "tool_choice": "auto" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "llama-3.3-70b-versatile",
+                "choices": [{
+                    "index": 0,
+                    "finish_reason": "tool_calls",
+                    "message": {
+                        "role": "assistant",
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "get_weather", "arguments": "{}" }
+                        }]
+                    }
+                }],
+                "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let response = groq(&mock_server).complete(&request()).await.unwrap();
+
+        let tool_call = &response.tool_calls().unwrap()[0];
+        assert_eq!(tool_call.id(), "call_1");
+        assert_eq!(tool_call.name(), "get_weather");
+        assert_eq!(tool_call.args(), Some("{}"));
+        assert_eq!(response.usage().unwrap().total_tokens, 15);
+    }
+
+    #[tokio::test]
+    async fn test_tool_use_failed_is_transient() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": {
+                    "message": "Failed to call a function. Please adjust your prompt.",
+                    "type": "invalid_request_error",
+                    "code": "tool_use_failed",
+                    "failed_generation": "<function=get_weather>"
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let err = groq(&mock_server).complete(&request()).await.unwrap_err();
+
+        assert!(matches!(err, LanguageModelError::TransientError(_)));
+    }
+}
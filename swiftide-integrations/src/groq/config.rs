@@ -11,6 +11,20 @@ pub struct GroqConfig {
     api_key: SecretString,
 }
 
+impl GroqConfig {
+    pub fn with_api_base(&mut self, api_base: &str) -> &mut Self {
+        self.api_base = api_base.to_string();
+
+        self
+    }
+
+    pub fn with_api_key(&mut self, api_key: impl Into<SecretString>) -> &mut Self {
+        self.api_key = api_key.into();
+
+        self
+    }
+}
+
 impl Default for GroqConfig {
     fn default() -> Self {
         Self {
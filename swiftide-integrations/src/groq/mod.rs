@@ -2,15 +2,25 @@
 //! It includes the `Groq` struct for managing API clients and default options for prompt models.
 //! The module is conditionally compiled based on the "groq" feature flag.
 
+use async_openai::error::OpenAIError;
+use async_trait::async_trait;
 use derive_builder::Builder;
 use std::sync::Arc;
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError,
+    model_provider::{ModelInfo, ModelProvider},
+};
 
-use self::config::GroqConfig;
+use crate::openai::openai_error_to_language_model_error;
 
+pub use self::config::GroqConfig;
+
+mod chat_completion;
 mod config;
 mod simple_prompt;
 
 /// The `Groq` struct encapsulates a `Groq` client that implements [`swiftide_core::SimplePrompt`]
+/// and [`swiftide_core::ChatCompletion`], including tool calling and streaming.
 ///
 /// There is also a builder available.
 ///
@@ -105,10 +115,34 @@ impl GroqBuilder {
     }
 }
 
+#[async_trait]
+impl ModelProvider for Groq {
+    #[tracing::instrument(skip_all, err)]
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LanguageModelError> {
+        crate::openai::list_models(&self.client, groq_error_to_language_model_error).await
+    }
+}
+
 fn default_client() -> Arc<async_openai::Client<GroqConfig>> {
     async_openai::Client::with_config(GroqConfig::default()).into()
 }
 
+/// Classifies errors from the Groq api
+///
+/// Groq validates tool calls generated by the model, and fails the request with `tool_use_failed`
+/// if they are invalid. The model might generate a valid tool call on a retry, so it is
+/// transient. Other errors are classified like `OpenAI` errors.
+pub(crate) fn groq_error_to_language_model_error(err: OpenAIError) -> LanguageModelError {
+    match &err {
+        OpenAIError::ApiError(api_error)
+            if api_error.code.as_deref() == Some("tool_use_failed") =>
+        {
+            LanguageModelError::transient(err)
+        }
+        _ => openai_error_to_language_model_error(err),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -134,6 +168,21 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_classifies_tool_use_failed_as_transient() {
+        let err = OpenAIError::ApiError(async_openai::error::ApiError {
+            message: "Failed to call a function".to_string(),
+            r#type: Some("invalid_request_error".to_string()),
+            param: None,
+            code: Some("tool_use_failed".to_string()),
+        });
+
+        assert!(matches!(
+            groq_error_to_language_model_error(err),
+            LanguageModelError::TransientError(_)
+        ));
+    }
+
     #[test]
     fn test_building_via_default() {
         let mut client = Groq::default();
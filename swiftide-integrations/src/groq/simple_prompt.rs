@@ -3,9 +3,9 @@
 //! and generating responses as part of the Swiftide system.
 use async_openai::types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs};
 use async_trait::async_trait;
-use swiftide_core::{prompt::Prompt, SimplePrompt};
+use swiftide_core::{chat_completion::errors::LanguageModelError, prompt::Prompt, SimplePrompt};
 
-use super::Groq;
+use super::{groq_error_to_language_model_error, Groq};
 use anyhow::{Context as _, Result};
 
 /// The `SimplePrompt` trait defines a method for sending a prompt to an AI model and receiving a response.
@@ -17,7 +17,7 @@ impl SimplePrompt for Groq {
     /// - `prompt`: A string slice that holds the prompt to be sent to the Groq API.
     ///
     /// # Returns
-    /// - `Result<String>`: On success, returns the content of the response as a `String`.
+    /// - `Result<String, LanguageModelError>`: On success, returns the content of the response as a `String`.
     ///   On failure, returns an error wrapped in a `Result`.
     ///
     /// # Errors
@@ -25,7 +25,7 @@ impl SimplePrompt for Groq {
     /// - Returns an error if the request to the Groq API fails.
     /// - Returns an error if the response does not contain the expected content.
     #[tracing::instrument(skip_all, err)]
-    async fn prompt(&self, prompt: Prompt) -> Result<String> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
         // Retrieve the model from the default options, returning an error if not set.
         let model = self
             .default_options
@@ -38,22 +38,31 @@ impl SimplePrompt for Groq {
             .model(model)
             .messages(vec![ChatCompletionRequestUserMessageArgs::default()
                 .content(prompt.render().await?)
-                .build()?
+                .build()
+                .map_err(LanguageModelError::permanent)?
                 .into()])
-            .build()?;
+            .build()
+            .map_err(LanguageModelError::permanent)?;
 
         // Log the request for debugging purposes.
         tracing::debug!(
-            messages = serde_json::to_string_pretty(&request)?,
+            messages =
+                serde_json::to_string_pretty(&request).map_err(LanguageModelError::permanent)?,
             "[SimplePrompt] Request to groq"
         );
 
         // Send the request to the Groq API and await the response.
-        let mut response = self.client.chat().create(request).await?;
+        let mut response = self
+            .client
+            .chat()
+            .create(request)
+            .await
+            .map_err(groq_error_to_language_model_error)?;
 
         // Log the response for debugging purposes.
         tracing::debug!(
-            response = serde_json::to_string_pretty(&response)?,
+            response =
+                serde_json::to_string_pretty(&response).map_err(LanguageModelError::permanent)?,
             "[SimplePrompt] Response from groq"
         );
 
@@ -65,5 +74,6 @@ impl SimplePrompt for Groq {
             .content
             .take()
             .context("Expected content in response")
+            .map_err(LanguageModelError::from)
     }
 }
@@ -0,0 +1,66 @@
+use derive_builder::Builder;
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use secrecy::{ExposeSecret as _, SecretString};
+use serde::Deserialize;
+
+const JINA_API_BASE: &str = "https://api.jina.ai/v1";
+
+#[derive(Clone, Debug, Deserialize, Builder)]
+#[serde(default)]
+#[builder(setter(into, strip_option))]
+pub struct JinaConfig {
+    #[builder(default = JINA_API_BASE.to_string())]
+    api_base: String,
+    api_key: SecretString,
+}
+
+impl JinaConfig {
+    pub fn builder() -> JinaConfigBuilder {
+        JinaConfigBuilder::default()
+    }
+
+    pub fn with_api_base(&mut self, api_base: &str) -> &mut Self {
+        self.api_base = api_base.to_string();
+
+        self
+    }
+
+    pub fn with_api_key(&mut self, api_key: impl Into<SecretString>) -> &mut Self {
+        self.api_key = api_key.into();
+
+        self
+    }
+
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        let api_key = self.api_key.expose_secret();
+        assert!(!api_key.is_empty(), "API key for Jina is required");
+
+        headers.insert(
+            AUTHORIZATION,
+            format!("Bearer {api_key}").as_str().parse().unwrap(),
+        );
+
+        headers
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_base, path)
+    }
+
+    pub fn api_base(&self) -> &str {
+        &self.api_base
+    }
+}
+
+impl Default for JinaConfig {
+    fn default() -> Self {
+        Self {
+            api_base: JINA_API_BASE.to_string(),
+            api_key: std::env::var("JINA_API_KEY")
+                .unwrap_or_else(|_| String::new())
+                .into(),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use swiftide_core::{chat_completion::errors::LanguageModelError, EmbeddingModel, Embeddings};
+
+use crate::reqwest_errors::reqwest_error_to_language_model_error;
+
+use super::Jina;
+
+#[derive(Serialize, Debug)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    late_chunking: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait]
+impl EmbeddingModel for Jina {
+    #[tracing::instrument(skip_all, err)]
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
+        let model = self
+            .default_options
+            .embed_model
+            .as_ref()
+            .context("Model not set")?;
+
+        let request = EmbeddingRequest {
+            model,
+            input: &input,
+            late_chunking: self.default_options.late_chunking,
+            task: self.default_options.task.as_deref(),
+            dimensions: self.default_options.dimensions,
+        };
+
+        tracing::debug!(
+            num_chunks = input.len(),
+            model = &model,
+            late_chunking = request.late_chunking,
+            "[Embed] Request to jina"
+        );
+
+        let response = self
+            .http_client
+            .post(self.config.url("/embeddings"))
+            .headers(self.config.headers())
+            .json(&request)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(reqwest_error_to_language_model_error)?
+            .json::<EmbeddingResponse>()
+            .await
+            .map_err(LanguageModelError::permanent)?;
+
+        let mut data = response.data;
+        tracing::debug!(num_embeddings = data.len(), "[Embed] Response jina");
+
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jina::JinaConfig;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_embed_with_late_chunking() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .and(body_partial_json(json!({
+                "model": "jina-embeddings-v3",
+                "input": ["first", "second"],
+                "late_chunking": true
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "jina-embeddings-v3",
+                "object": "list",
+                "data": [
+                    { "object": "embedding", "index": 1, "embedding": [0.3, 0.4] },
+                    { "object": "embedding", "index": 0, "embedding": [0.1, 0.2] }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = JinaConfig::builder()
+            .api_base(mock_server.uri())
+            .api_key("test")
+            .build()
+            .unwrap();
+
+        let jina = Jina::builder()
+            .config(config)
+            .default_embed_model("jina-embeddings-v3")
+            .late_chunking(true)
+            .build()
+            .unwrap();
+
+        let embeddings = jina
+            .embed(vec!["first".to_string(), "second".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    }
+}
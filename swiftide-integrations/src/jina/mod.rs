@@ -0,0 +1,154 @@
+//! This module provides integration with `Jina AI`'s API, enabling the use of Jina embedding and
+//! reranker models within the Swiftide project.
+//!
+//! [`Jina`] implements [`swiftide_core::EmbeddingModel`], with optional late chunking. [`Rerank`]
+//! implements [`swiftide_core::Rerank`] and can be used in a query pipeline with `then_rerank`.
+//!
+//! The module is conditionally compiled based on the "jina" feature flag.
+
+use derive_builder::Builder;
+
+pub mod config;
+mod embed;
+mod rerank;
+
+pub use config::JinaConfig;
+pub use rerank::{Rerank, RerankBuilder};
+
+/// The `Jina` struct encapsulates the configuration for the Jina api and default options for
+/// embedding.
+///
+/// By default it will look for a `JINA_API_KEY` environment variable. Note that an embedding
+/// model always needs to be set, either with [`Jina::with_default_embed_model`] or via the builder.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::jina::Jina;
+/// let jina = Jina::builder()
+///     .default_embed_model("jina-embeddings-v3")
+///     .late_chunking(true)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Builder, Clone, Default)]
+#[builder(setter(into, strip_option))]
+pub struct Jina {
+    /// Api key and base url, defaults to the `JINA_API_KEY` environment variable
+    #[builder(default)]
+    config: JinaConfig,
+    #[builder(default)]
+    http_client: reqwest::Client,
+    /// Default options for embedding
+    #[builder(default)]
+    default_options: Options,
+}
+
+/// The `Options` struct holds configuration options for the `Jina` client.
+#[derive(Debug, Default, Clone, Builder)]
+#[builder(setter(into, strip_option))]
+pub struct Options {
+    /// The default embedding model to use, if specified.
+    #[builder(default)]
+    pub embed_model: Option<String>,
+
+    /// Embeds the chunks of a batch in the context of the whole batch
+    ///
+    /// Jina concatenates the input, embeds it as a whole and then splits the embeddings per input.
+    /// Only makes sense if a batch contains (consecutive) chunks of the same document.
+    #[builder(default)]
+    pub late_chunking: bool,
+
+    /// The downstream task the embeddings are optimized for, i.e. `retrieval.passage`
+    #[builder(default)]
+    pub task: Option<String>,
+
+    /// Truncates the embeddings to the given number of dimensions
+    #[builder(default)]
+    pub dimensions: Option<u32>,
+}
+
+impl Options {
+    /// Creates a new `OptionsBuilder` for constructing `Options` instances.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+}
+
+impl Jina {
+    /// Creates a new `JinaBuilder` for constructing `Jina` instances.
+    pub fn builder() -> JinaBuilder {
+        JinaBuilder::default()
+    }
+
+    /// Sets a default embedding model to use when embedding
+    pub fn with_default_embed_model(&mut self, model: impl Into<String>) -> &mut Self {
+        self.default_options.embed_model = Some(model.into());
+        self
+    }
+}
+
+impl JinaBuilder {
+    /// Sets the default embedding model for the `Jina` instance.
+    pub fn default_embed_model(&mut self, model: impl Into<String>) -> &mut Self {
+        self.default_options_mut().embed_model = Some(model.into());
+        self
+    }
+
+    /// Enables or disables late chunking
+    pub fn late_chunking(&mut self, late_chunking: bool) -> &mut Self {
+        self.default_options_mut().late_chunking = late_chunking;
+        self
+    }
+
+    /// Sets the downstream task for the embeddings, i.e. `retrieval.passage`
+    pub fn task(&mut self, task: impl Into<String>) -> &mut Self {
+        self.default_options_mut().task = Some(task.into());
+        self
+    }
+
+    /// Truncates the embeddings to the given number of dimensions
+    pub fn dimensions(&mut self, dimensions: u32) -> &mut Self {
+        self.default_options_mut().dimensions = Some(dimensions);
+        self
+    }
+
+    fn default_options_mut(&mut self) -> &mut Options {
+        self.default_options.get_or_insert_with(Options::default)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_options() {
+        let jina = Jina::builder()
+            .default_embed_model("jina-embeddings-v3")
+            .late_chunking(true)
+            .dimensions(256)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            jina.default_options.embed_model.as_deref(),
+            Some("jina-embeddings-v3")
+        );
+        assert!(jina.default_options.late_chunking);
+        assert_eq!(jina.default_options.dimensions, Some(256));
+    }
+
+    #[test]
+    fn test_building_via_default_embed_model() {
+        let mut jina = Jina::default();
+
+        assert!(jina.default_options.embed_model.is_none());
+
+        jina.with_default_embed_model("jina-embeddings-v3");
+        assert_eq!(
+            jina.default_options.embed_model.as_deref(),
+            Some("jina-embeddings-v3")
+        );
+    }
+}
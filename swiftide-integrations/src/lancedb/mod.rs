@@ -1,13 +1,15 @@
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use anyhow::Context as _;
 use anyhow::Result;
+use arrow_array::{Array, BooleanArray, Float64Array, Int64Array, StringArray};
 use connection_pool::LanceDBConnectionPool;
 use connection_pool::LanceDBPoolManager;
 use deadpool::managed::Object;
 use derive_builder::Builder;
 use lancedb::arrow::arrow_schema::{DataType, Field, Schema};
-use swiftide_core::indexing::EmbeddedField;
+use swiftide_core::indexing::{EmbeddedField, MetadataFieldType, MetadataSchema};
 pub mod connection_pool;
 pub mod persist;
 pub mod retrieve;
@@ -22,9 +24,13 @@ See examples for more information.
 Implements `Persist` and `Retrieve`.
 
 If you want to store / retrieve metadata in Lance, the columns can be defined with `with_metadata`.
+Without a type only string values are stored, typed columns can be defined with
+`MetadataConfig::with_type` or from a `MetadataSchema` with `with_metadata_schema`.
 
-Note: For querying large tables you manually need to create an index. You can get an
-active connection via `get_connection`.
+Vector indices are created once the table holds enough rows, see `vector_index`. Configure
+`full_text_search` for a full-text index on the chunks, required for hybrid search. Rows added
+after an index was created are searched without it until the table is optimized with
+`optimize`. For anything else you can get an active connection via `get_connection`.
 
 # Example
 
@@ -83,17 +89,50 @@ pub struct LanceDB {
     #[builder(default = "256")]
     batch_size: usize,
 
+    /// Limits batches to an estimated size in bytes, in addition to the batch size.
+    ///
+    /// Keeps the record batches of large nodes within memory bounds.
+    #[builder(default)]
+    max_batch_bytes: Option<usize>,
+
     /// Field configuration for `LanceDB`, will result in the eventual schema.
     ///
     /// Supports multiple field types, see [`FieldConfig`] for more details.
     #[builder(default = "self.default_fields()")]
     fields: Vec<FieldConfig>,
+
+    /// The index created on every vector column, defaults to IVF_PQ. Set to `None` to disable.
+    #[builder(default = "Some(VectorIndex::default())")]
+    vector_index: Option<VectorIndex>,
+
+    /// The number of rows after which the vector indices are created, defaults to 100.000.
+    ///
+    /// Below that, brute force search is fast enough, and the index lacks data to train on.
+    #[builder(default = "100_000")]
+    vector_index_after_rows: usize,
+
+    /// Creates a full-text index on the chunks, enabling hybrid search. Defaults to false.
+    #[builder(default)]
+    full_text_search: bool,
+
+    /// The number of rows after which the full-text index is created, defaults to 0, i.e. on the
+    /// first stored batch.
+    #[builder(default)]
+    full_text_search_after_rows: usize,
+
+    #[builder(private, default)]
+    vector_index_created: Arc<AtomicBool>,
+
+    #[builder(private, default)]
+    fts_index_created: Arc<AtomicBool>,
 }
 
 impl std::fmt::Debug for LanceDB {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.debug_struct("LanceDB")
             .field("schema", &self.schema)
+            .field("vector_index", &self.vector_index)
+            .field("full_text_search", &self.full_text_search)
             .finish()
     }
 }
@@ -157,6 +196,16 @@ impl LanceDBBuilder {
         self
     }
 
+    /// Adds a typed metadata column for every field in the schema
+    ///
+    /// Metadata is validated against the column types before it is stored.
+    pub fn with_metadata_schema(&mut self, schema: &MetadataSchema) -> &mut Self {
+        for (name, field_type) in schema.fields() {
+            self.with_metadata(MetadataConfig::from(name).with_type(field_type));
+        }
+        self
+    }
+
     #[allow(clippy::unused_self)]
     fn default_fields(&self) -> Vec<FieldConfig> {
         vec![FieldConfig::ID, FieldConfig::Chunk]
@@ -185,8 +234,8 @@ impl LanceDBBuilder {
                 FieldConfig::Chunk => {
                     fields.push(Field::new(field.field_name(), DataType::Utf8, false));
                 }
-                FieldConfig::Metadata(_) => {
-                    fields.push(Field::new(field.field_name(), DataType::Utf8, true));
+                FieldConfig::Metadata(config) => {
+                    fields.push(Field::new(field.field_name(), config.data_type(), true));
                 }
                 FieldConfig::ID => {
                     fields.push(Field::new(
@@ -219,6 +268,64 @@ impl LanceDBBuilder {
     }
 }
 
+/// Vector index created on the vector columns
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VectorIndex {
+    /// `IVF_PQ`, compact and fast to build. Parameters are derived from the data if not set.
+    IvfPq {
+        num_partitions: Option<u32>,
+        num_sub_vectors: Option<u32>,
+    },
+    /// `IVF_HNSW_SQ`, better recall at the cost of memory and build time. Parameters use the
+    /// `LanceDB` defaults if not set.
+    IvfHnswSq {
+        /// The number of edges per node in the graph
+        m: Option<u32>,
+        ef_construction: Option<u32>,
+    },
+}
+
+impl Default for VectorIndex {
+    fn default() -> Self {
+        VectorIndex::IvfPq {
+            num_partitions: None,
+            num_sub_vectors: None,
+        }
+    }
+}
+
+impl VectorIndex {
+    fn to_index(&self) -> lancedb::index::Index {
+        use lancedb::index::vector::{IvfHnswSqIndexBuilder, IvfPqIndexBuilder};
+
+        match self {
+            VectorIndex::IvfPq {
+                num_partitions,
+                num_sub_vectors,
+            } => {
+                let mut builder = IvfPqIndexBuilder::default();
+                if let Some(num_partitions) = num_partitions {
+                    builder = builder.num_partitions(*num_partitions);
+                }
+                if let Some(num_sub_vectors) = num_sub_vectors {
+                    builder = builder.num_sub_vectors(*num_sub_vectors);
+                }
+                lancedb::index::Index::IvfPq(builder)
+            }
+            VectorIndex::IvfHnswSq { m, ef_construction } => {
+                let mut builder = IvfHnswSqIndexBuilder::default();
+                if let Some(m) = m {
+                    builder = builder.num_edges(*m);
+                }
+                if let Some(ef_construction) = ef_construction {
+                    builder = builder.ef_construction(*ef_construction);
+                }
+                lancedb::index::Index::IvfHnswSq(builder)
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum FieldConfig {
     Vector(VectorConfig),
@@ -266,6 +373,56 @@ impl From<EmbeddedField> for VectorConfig {
 pub struct MetadataConfig {
     field: String,
     original_field: String,
+    field_type: Option<MetadataFieldType>,
+}
+
+impl MetadataConfig {
+    /// Stores the field in a typed column, validating values before they are stored
+    ///
+    /// Without a type, only string values are stored.
+    #[must_use]
+    pub fn with_type(mut self, field_type: MetadataFieldType) -> Self {
+        self.field_type = Some(field_type);
+        self
+    }
+
+    /// The arrow type of the column, json is stored serialized as text
+    pub(crate) fn data_type(&self) -> DataType {
+        match self.field_type {
+            Some(MetadataFieldType::Integer) => DataType::Int64,
+            Some(MetadataFieldType::Float) => DataType::Float64,
+            Some(MetadataFieldType::Boolean) => DataType::Boolean,
+            _ => DataType::Utf8,
+        }
+    }
+
+    /// Reads the value in a row of the column of the field
+    pub(crate) fn value_from_column(
+        &self,
+        column: &dyn Array,
+        row: usize,
+    ) -> Option<serde_json::Value> {
+        if column.is_null(row) {
+            return None;
+        }
+
+        let column = column.as_any();
+        if let Some(values) = column.downcast_ref::<Int64Array>() {
+            return Some(values.value(row).into());
+        }
+        if let Some(values) = column.downcast_ref::<Float64Array>() {
+            return Some(values.value(row).into());
+        }
+        if let Some(values) = column.downcast_ref::<BooleanArray>() {
+            return Some(values.value(row).into());
+        }
+
+        let value = column.downcast_ref::<StringArray>()?.value(row);
+        if self.field_type == Some(MetadataFieldType::Json) {
+            return serde_json::from_str(value).ok();
+        }
+        Some(value.into())
+    }
 }
 
 impl<T: AsRef<str>> From<T> for MetadataConfig {
@@ -273,6 +430,7 @@ impl<T: AsRef<str>> From<T> for MetadataConfig {
         MetadataConfig {
             field: normalize_field_name(val.as_ref()),
             original_field: val.as_ref().to_string(),
+            field_type: None,
         }
     }
 }
@@ -6,22 +6,33 @@ use arrow_array::types::Float32Type;
 use arrow_array::types::UInt8Type;
 use arrow_array::types::Utf8Type;
 use arrow_array::Array;
+use arrow_array::BooleanArray;
 use arrow_array::FixedSizeListArray;
+use arrow_array::Float64Array;
 use arrow_array::GenericByteArray;
+use arrow_array::Int64Array;
 use arrow_array::RecordBatch;
 use arrow_array::RecordBatchIterator;
 use async_trait::async_trait;
+use lancedb::index::{scalar::FtsIndexBuilder, Index};
+use lancedb::table::OptimizeAction;
+use std::sync::atomic::Ordering;
 use swiftide_core::indexing::IndexingStream;
+use swiftide_core::indexing::Metadata;
+use swiftide_core::indexing::MetadataFieldType;
 use swiftide_core::indexing::Node;
+use swiftide_core::indexing::PersistError;
 use swiftide_core::Persist;
 
 use super::FieldConfig;
 use super::LanceDB;
+use super::MetadataConfig;
+use crate::metrics;
 
 #[async_trait]
 impl Persist for LanceDB {
     #[tracing::instrument(skip_all)]
-    async fn setup(&self) -> Result<()> {
+    async fn setup(&self) -> Result<(), PersistError> {
         let conn = self.get_connection().await?;
         let schema = self.schema.clone();
 
@@ -33,17 +44,46 @@ impl Persist for LanceDB {
                     .map(|_| ())
                     .map_err(anyhow::Error::from)?;
             } else {
-                return Err(err.into());
+                return Err(PersistError::permanent(err));
             }
         }
 
+        // Indices might exist from an earlier run
+        let indexed_columns = self
+            .open_table()
+            .await?
+            .list_indices()
+            .await
+            .context("Failed to list indices")?
+            .into_iter()
+            .flat_map(|index| index.columns)
+            .collect::<Vec<_>>();
+
+        if self
+            .vector_columns()
+            .iter()
+            .all(|column| indexed_columns.contains(column))
+        {
+            self.vector_index_created.store(true, Ordering::Release);
+        }
+        if indexed_columns.contains(&FieldConfig::Chunk.field_name()) {
+            self.fts_index_created.store(true, Ordering::Release);
+        }
+
         Ok(())
     }
 
     #[tracing::instrument(skip_all)]
-    async fn store(&self, node: Node) -> Result<Node> {
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
         let mut nodes = vec![node; 1];
-        self.store_nodes(&nodes).await?;
+        metrics::measure(
+            "lancedb",
+            &self.table_name,
+            "store",
+            1,
+            self.store_nodes(&nodes),
+        )
+        .await?;
 
         let node = nodes.swap_remove(0);
 
@@ -52,12 +92,43 @@ impl Persist for LanceDB {
 
     #[tracing::instrument(skip_all)]
     async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
-        self.store_nodes(&nodes).await.map(|()| nodes).into()
+        metrics::measure(
+            "lancedb",
+            &self.table_name,
+            "batch_store",
+            nodes.len(),
+            self.store_nodes(&nodes),
+        )
+        .await
+        .map(|()| nodes)
+        .into()
     }
 
     fn batch_size(&self) -> Option<usize> {
         Some(self.batch_size)
     }
+
+    fn max_batch_bytes(&self) -> Option<usize> {
+        self.max_batch_bytes
+    }
+
+    /// Deletes all rows matching every entry of the filter
+    ///
+    /// Every key must be a configured metadata field. Metadata is stored as text, so values are
+    /// compared as text.
+    #[tracing::instrument(skip_all)]
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        let predicate = self.metadata_predicate(filter)?;
+        tracing::debug!(predicate, "Deleting by metadata");
+
+        self.open_table()
+            .await?
+            .delete(&predicate)
+            .await
+            .context("Failed to delete from table")?;
+
+        Ok(())
+    }
 }
 
 impl LanceDB {
@@ -84,9 +155,175 @@ impl LanceDB {
 
         merge_insert.execute(Box::new(data)).await?;
 
+        self.create_indices_if_ready(&table).await
+    }
+
+    /// Creates the vector and full-text indices once the table holds enough rows, if they have
+    /// not been created yet.
+    async fn create_indices_if_ready(&self, table: &lancedb::Table) -> Result<()> {
+        let vector_index = self
+            .vector_index
+            .as_ref()
+            .filter(|_| !self.vector_index_created.load(Ordering::Acquire));
+        let full_text_search =
+            self.full_text_search && !self.fts_index_created.load(Ordering::Acquire);
+
+        if vector_index.is_none() && !full_text_search {
+            return Ok(());
+        }
+
+        let rows = table.count_rows(None).await?;
+
+        if let Some(vector_index) = vector_index {
+            if rows >= self.vector_index_after_rows
+                && !self.vector_index_created.swap(true, Ordering::AcqRel)
+            {
+                for column in self.vector_columns() {
+                    tracing::info!(rows, column, "Creating vector index");
+                    if let Err(err) = table
+                        .create_index(&[&column], vector_index.to_index())
+                        .execute()
+                        .await
+                    {
+                        self.vector_index_created.store(false, Ordering::Release);
+                        return Err(err).context("Failed to create vector index");
+                    }
+                }
+            }
+        }
+
+        if full_text_search
+            && rows >= self.full_text_search_after_rows
+            && !self.fts_index_created.swap(true, Ordering::AcqRel)
+        {
+            tracing::info!(rows, "Creating full-text index");
+            if let Err(err) = table
+                .create_index(
+                    &[&FieldConfig::Chunk.field_name()],
+                    Index::FTS(FtsIndexBuilder::default()),
+                )
+                .execute()
+                .await
+            {
+                self.fts_index_created.store(false, Ordering::Release);
+                return Err(err).context("Failed to create full-text index");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Optimizes the table, compacting files and adding rows stored since an index was created to
+    /// the indices.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table cannot be opened or optimized.
+    pub async fn optimize(&self) -> Result<()> {
+        self.open_table()
+            .await?
+            .optimize(OptimizeAction::All)
+            .await
+            .context("Failed to optimize table")?;
         Ok(())
     }
 
+    /// Builds the column of a metadata field
+    ///
+    /// Typed fields are validated first, untyped fields only store string values.
+    fn metadata_array(config: &MetadataConfig, nodes: &[Node]) -> Result<Arc<dyn Array>> {
+        let values = nodes
+            .iter()
+            .map(|node| node.metadata.get(&config.original_field))
+            .collect::<Vec<_>>();
+
+        let Some(field_type) = config.field_type else {
+            return Ok(Arc::new(GenericByteArray::<Utf8Type>::from_iter(
+                values
+                    .iter()
+                    .map(|value| value.and_then(serde_json::Value::as_str)),
+            )));
+        };
+
+        if let Some(value) = values
+            .iter()
+            .flatten()
+            .find(|value| !field_type.matches(value))
+        {
+            anyhow::bail!(
+                "Metadata field `{}` should be {field_type}, got {value}",
+                config.original_field
+            );
+        }
+
+        Ok(match field_type {
+            MetadataFieldType::Integer => Arc::new(Int64Array::from_iter(
+                values
+                    .iter()
+                    .map(|value| value.and_then(serde_json::Value::as_i64)),
+            )),
+            MetadataFieldType::Float => Arc::new(Float64Array::from_iter(
+                values
+                    .iter()
+                    .map(|value| value.and_then(serde_json::Value::as_f64)),
+            )),
+            MetadataFieldType::Boolean => Arc::new(BooleanArray::from_iter(
+                values
+                    .iter()
+                    .map(|value| value.and_then(serde_json::Value::as_bool)),
+            )),
+            MetadataFieldType::String | MetadataFieldType::Timestamp => {
+                Arc::new(GenericByteArray::<Utf8Type>::from_iter(
+                    values
+                        .iter()
+                        .map(|value| value.and_then(serde_json::Value::as_str)),
+                ))
+            }
+            _ => Arc::new(GenericByteArray::<Utf8Type>::from_iter(
+                values.iter().map(|value| value.map(ToString::to_string)),
+            )),
+        })
+    }
+
+    fn metadata_predicate(&self, filter: Metadata) -> Result<String> {
+        if filter.is_empty() {
+            anyhow::bail!("Refusing to delete by an empty metadata filter");
+        }
+
+        filter
+            .into_iter()
+            .map(|(key, value)| {
+                let field = self
+                    .fields
+                    .iter()
+                    .find(|field| {
+                        matches!(field, FieldConfig::Metadata(config) if config.original_field == key)
+                    })
+                    .with_context(|| format!("Filter on unknown metadata field {key}"))?;
+
+                let value = match value {
+                    serde_json::Value::String(value) => value,
+                    value => value.to_string(),
+                };
+
+                Ok(format!(
+                    "{} = '{}'",
+                    field.field_name(),
+                    value.replace('\'', "''")
+                ))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|conditions| conditions.join(" AND "))
+    }
+
+    pub(crate) fn vector_columns(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|field| matches!(field, FieldConfig::Vector(_)))
+            .map(FieldConfig::field_name)
+            .collect()
+    }
+
     fn extract_arrow_batches_from_nodes(
         &self,
         nodes: &[Node],
@@ -120,18 +357,7 @@ impl LanceDB {
                     >(row, vector_size)));
                 }
                 FieldConfig::Metadata(config) => {
-                    let mut row = Vec::with_capacity(nodes.len());
-
-                    for node in nodes {
-                        let data = node
-                            .metadata
-                            .get(&config.original_field)
-                            // TODO: Verify this gives the correct data
-                            .and_then(|v| v.as_str());
-
-                        row.push(data);
-                    }
-                    batches.push(Arc::new(GenericByteArray::<Utf8Type>::from_iter(row)));
+                    batches.push(Self::metadata_array(config, nodes)?);
                 }
                 FieldConfig::Chunk => {
                     let mut row = Vec::with_capacity(nodes.len());
@@ -166,6 +392,7 @@ mod test {
     use temp_dir::TempDir;
 
     use super::*;
+    use crate::lancedb::VectorIndex;
 
     async fn setup() -> (TempDir, LanceDB) {
         let tempdir = TempDir::new().unwrap();
@@ -182,6 +409,117 @@ mod test {
         (tempdir, lancedb)
     }
 
+    #[test]
+    fn test_typed_metadata_arrays() {
+        let config = MetadataConfig::from("priority").with_type(MetadataFieldType::Integer);
+        let nodes = vec![
+            Node::new("first").with_metadata(("priority", 1)).to_owned(),
+            Node::new("second"),
+        ];
+
+        let array = LanceDB::metadata_array(&config, &nodes).unwrap();
+        assert_eq!(config.value_from_column(array.as_ref(), 0), Some(1.into()));
+        assert_eq!(config.value_from_column(array.as_ref(), 1), None);
+
+        let invalid = vec![Node::new("first")
+            .with_metadata(("priority", "high"))
+            .to_owned()];
+        assert_eq!(
+            LanceDB::metadata_array(&config, &invalid)
+                .unwrap_err()
+                .to_string(),
+            "Metadata field `priority` should be Integer, got \"high\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_creates_indices_after_rows() {
+        let tempdir = TempDir::new().unwrap();
+        let lancedb = LanceDB::builder()
+            .uri(tempdir.child("lancedb").to_str().unwrap())
+            .vector_size(8)
+            .with_vector(EmbeddedField::Combined)
+            .table_name("swiftide_test")
+            .vector_index(VectorIndex::IvfPq {
+                num_partitions: Some(2),
+                num_sub_vectors: Some(2),
+            })
+            .vector_index_after_rows(300usize)
+            .full_text_search(true)
+            .build()
+            .unwrap();
+        lancedb.setup().await.unwrap();
+
+        let nodes = (0..300)
+            .map(|i| {
+                #[allow(clippy::cast_precision_loss)]
+                Node::new(format!("chunk {i}"))
+                    .with_vectors([(EmbeddedField::Combined, vec![i as f32; 8])])
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+
+        lancedb.store_nodes(&nodes[..100]).await.unwrap();
+        assert_eq!(indexed_columns(&lancedb).await, vec!["chunk"]);
+
+        lancedb.store_nodes(&nodes[100..]).await.unwrap();
+        assert_eq!(
+            indexed_columns(&lancedb).await,
+            vec!["chunk", "vector_combined"]
+        );
+    }
+
+    async fn indexed_columns(lancedb: &LanceDB) -> Vec<String> {
+        let mut columns = lancedb
+            .open_table()
+            .await
+            .unwrap()
+            .list_indices()
+            .await
+            .unwrap()
+            .into_iter()
+            .flat_map(|index| index.columns)
+            .collect::<Vec<_>>();
+        columns.sort();
+        columns
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_metadata() {
+        let (_guard, lancedb) = setup().await;
+
+        let nodes = ["true", "true", "false"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, filter)| {
+                Node::new(format!("chunk {i}"))
+                    .with_metadata(("filter", filter))
+                    .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+        lancedb.store_nodes(&nodes).await.unwrap();
+
+        lancedb
+            .delete_by_metadata(("filter", "true").into())
+            .await
+            .unwrap();
+
+        let rows = lancedb
+            .open_table()
+            .await
+            .unwrap()
+            .count_rows(None)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+
+        assert!(lancedb
+            .delete_by_metadata(("unknown", "true").into())
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_no_error_when_table_exists() {
         let (_guard, lancedb) = setup().await;
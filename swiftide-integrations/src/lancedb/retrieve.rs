@@ -1,20 +1,27 @@
-use anyhow::Result;
-use arrow_array::{RecordBatch, StringArray};
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use arrow_array::{Array as _, FixedSizeListArray, Float32Array, RecordBatch, StringArray};
 use async_trait::async_trait;
-use futures_util::TryStreamExt;
+use futures_util::{future, stream, StreamExt as _, TryStreamExt};
 use itertools::Itertools;
+use lancedb::index::scalar::FullTextSearchQuery;
 use lancedb::query::{ExecutableQuery, QueryBase};
 use swiftide_core::{
     document::Document,
-    indexing::Metadata,
+    indexing::{IndexingStream, Metadata, Node},
     querying::{
-        search_strategies::{CustomStrategy, SimilaritySingleEmbedding},
-        states, Query,
+        search_strategies::{CustomStrategy, HybridSearch, SimilaritySingleEmbedding},
+        states, Query, RetrieveError,
     },
-    Retrieve,
+    Retrieve, Scroll,
 };
 
-use super::{FieldConfig, LanceDB};
+use super::{FieldConfig, LanceDB, VectorConfig};
+use crate::metrics;
+
+/// Constant of reciprocal rank fusion, dampening the weight of the top ranks
+const RRF_K: f64 = 60.0;
 
 /// Implement the `Retrieve` trait for `SimilaritySingleEmbedding` search strategy.
 ///
@@ -28,9 +35,9 @@ impl Retrieve<SimilaritySingleEmbedding<String>> for LanceDB {
         &self,
         search_strategy: &SimilaritySingleEmbedding<String>,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         let Some(embedding) = &query.embedding else {
-            anyhow::bail!("No embedding for query")
+            return Err(RetrieveError::MissingEmbedding);
         };
 
         let table = self
@@ -38,7 +45,8 @@ impl Retrieve<SimilaritySingleEmbedding<String>> for LanceDB {
             .await?
             .open_table(&self.table_name)
             .execute()
-            .await?;
+            .await
+            .context("Failed to open table")?;
 
         let vector_fields = self
             .fields
@@ -47,26 +55,29 @@ impl Retrieve<SimilaritySingleEmbedding<String>> for LanceDB {
             .collect_vec();
 
         if vector_fields.is_empty() || vector_fields.len() > 1 {
-            anyhow::bail!("Zero or multiple vector fields configured in schema")
+            return Err(RetrieveError::permanent(
+                "Zero or multiple vector fields configured in schema",
+            ));
         }
 
         let column_name = vector_fields.first().map(|v| v.field_name()).unwrap();
 
         let mut query_builder = table
             .query()
-            .nearest_to(embedding.as_slice())?
+            .nearest_to(embedding.as_slice())
+            .map_err(RetrieveError::permanent)?
             .column(&column_name)
-            .limit(usize::try_from(search_strategy.top_k())?);
+            .limit(usize::try_from(search_strategy.top_k()).map_err(RetrieveError::permanent)?);
 
         if let Some(filter) = &search_strategy.filter() {
             query_builder = query_builder.only_if(filter);
         }
 
-        let batches = query_builder
-            .execute()
-            .await?
-            .try_collect::<Vec<_>>()
-            .await?;
+        let batches = metrics::measure("lancedb", &self.table_name, "retrieve", 0, async {
+            query_builder.execute().await?.try_collect::<Vec<_>>().await
+        })
+        .await
+        .context("Failed to retrieve from lancedb")?;
 
         let documents = Self::retrieve_from_record_batches(&batches);
 
@@ -80,7 +91,7 @@ impl Retrieve<SimilaritySingleEmbedding> for LanceDB {
         &self,
         search_strategy: &SimilaritySingleEmbedding,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         Retrieve::<SimilaritySingleEmbedding<String>>::retrieve(
             self,
             &search_strategy.into_concrete_filter::<String>(),
@@ -90,6 +101,106 @@ impl Retrieve<SimilaritySingleEmbedding> for LanceDB {
     }
 }
 
+/// Implement the `Retrieve` trait for `HybridSearch` search strategy.
+///
+/// Runs a vector search and a full-text search on the chunks, each limited to `top_n` results,
+/// and fuses both rankings with reciprocal rank fusion into `top_k` documents.
+///
+/// Requires `full_text_search` to be enabled, and the full-text index to be created.
+#[async_trait]
+impl Retrieve<HybridSearch> for LanceDB {
+    #[tracing::instrument]
+    async fn retrieve(
+        &self,
+        search_strategy: &HybridSearch,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.embedding else {
+            return Err(RetrieveError::MissingEmbedding);
+        };
+
+        if !self.full_text_search {
+            return Err(RetrieveError::permanent(
+                "Hybrid search requires full text search to be enabled",
+            ));
+        }
+
+        let column_name =
+            VectorConfig::from(search_strategy.dense_vector_field().clone()).field_name();
+        if !self.vector_columns().contains(&column_name) {
+            return Err(RetrieveError::permanent(format!(
+                "Vector field {} is not configured",
+                search_strategy.dense_vector_field()
+            )));
+        }
+
+        let table = self.open_table().await?;
+        let top_n = usize::try_from(search_strategy.top_n()).map_err(RetrieveError::permanent)?;
+
+        let vector_query = table
+            .query()
+            .nearest_to(embedding.as_slice())
+            .map_err(RetrieveError::permanent)?
+            .column(&column_name)
+            .limit(top_n);
+        let text_query = table
+            .query()
+            .full_text_search(FullTextSearchQuery::new(query.current().to_string()))
+            .limit(top_n);
+
+        let (vector_batches, text_batches) =
+            metrics::measure("lancedb", &self.table_name, "retrieve_hybrid", 0, async {
+                let vector_batches = vector_query
+                    .execute()
+                    .await?
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                let text_batches = text_query.execute().await?.try_collect::<Vec<_>>().await?;
+
+                Ok::<_, lancedb::Error>((vector_batches, text_batches))
+            })
+            .await
+            .context("Failed to retrieve from lancedb")?;
+
+        let documents = reciprocal_rank_fusion(
+            [
+                Self::retrieve_from_record_batches(&vector_batches),
+                Self::retrieve_from_record_batches(&text_batches),
+            ],
+            usize::try_from(search_strategy.top_k()).map_err(RetrieveError::permanent)?,
+        );
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+/// Fuses rankings of documents by the sum of their reciprocal ranks
+#[allow(clippy::cast_precision_loss)]
+fn reciprocal_rank_fusion(
+    rankings: impl IntoIterator<Item = Vec<Document>>,
+    top_k: usize,
+) -> Vec<Document> {
+    let mut scored: Vec<(Document, f64)> = Vec::new();
+
+    for ranking in rankings {
+        for (rank, document) in ranking.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f64 + 1.0);
+            if let Some((_, total)) = scored.iter_mut().find(|(other, _)| *other == document) {
+                *total += score;
+            } else {
+                scored.push((document, score));
+            }
+        }
+    }
+
+    scored.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    scored
+        .into_iter()
+        .take(top_k)
+        .map(|(document, _)| document)
+        .collect()
+}
+
 #[async_trait]
 impl<Q: ExecutableQuery + Send + Sync + 'static> Retrieve<CustomStrategy<Q>> for LanceDB {
     /// Implements vector similarity search for LanceDB using a custom query strategy.
@@ -100,16 +211,16 @@ impl<Q: ExecutableQuery + Send + Sync + 'static> Retrieve<CustomStrategy<Q>> for
         &self,
         search_strategy: &CustomStrategy<Q>,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         // Build the custom query using both strategy and query state
         let query_builder = search_strategy.build_query(&query).await?;
 
         // Execute the query using the builder's built-in methods
-        let batches = query_builder
-            .execute()
-            .await?
-            .try_collect::<Vec<_>>()
-            .await?;
+        let batches = metrics::measure("lancedb", &self.table_name, "retrieve_custom", 0, async {
+            query_builder.execute().await?.try_collect::<Vec<_>>().await
+        })
+        .await
+        .context("Failed to retrieve from lancedb")?;
 
         let documents = Self::retrieve_from_record_batches(&batches);
 
@@ -117,6 +228,31 @@ impl<Q: ExecutableQuery + Send + Sync + 'static> Retrieve<CustomStrategy<Q>> for
     }
 }
 
+impl Scroll for LanceDB {
+    /// Streams all rows of the table as nodes, one record batch at a time
+    ///
+    /// The table does not store the path of a node, so nodes are restored without a path, with
+    /// only the configured metadata and vectors. Metadata is restored as strings.
+    fn scroll(&self) -> IndexingStream {
+        let lancedb = self.clone();
+
+        stream::once(async move {
+            let batches = lancedb.open_table().await?.query().execute().await?;
+
+            Ok::<_, anyhow::Error>(
+                batches
+                    .map_err(anyhow::Error::from)
+                    .and_then(move |batch| future::ready(lancedb.nodes_from_record_batch(&batch))),
+            )
+        })
+        .try_flatten()
+        .map_ok(|nodes| stream::iter(nodes.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+        .into()
+    }
+}
+
 impl LanceDB {
     /// Retrieves documents from Arrow `RecordBatches` by processing each row and extracting content
     /// and metadata fields.
@@ -172,6 +308,57 @@ impl LanceDB {
 
         documents
     }
+
+    /// Restores nodes from a record batch with the chunk, configured metadata and vectors
+    fn nodes_from_record_batch(&self, batch: &RecordBatch) -> Result<Vec<Node>> {
+        let chunks = batch
+            .column_by_name("chunk")
+            .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+            .context("Expected chunk column in record batch")?;
+        let mut nodes = chunks
+            .iter()
+            .map(|chunk| Node::new(chunk.unwrap_or_default()))
+            .collect_vec();
+
+        for field in &self.fields {
+            match field {
+                FieldConfig::Metadata(config) => {
+                    let Some(column) = batch.column_by_name(&config.field) else {
+                        continue;
+                    };
+                    for (row, node) in nodes.iter_mut().enumerate() {
+                        if let Some(value) = config.value_from_column(column.as_ref(), row) {
+                            node.metadata.insert(config.original_field.clone(), value);
+                        }
+                    }
+                }
+                FieldConfig::Vector(config) => {
+                    let Some(vectors) = batch
+                        .column_by_name(&config.field_name())
+                        .and_then(|column| column.as_any().downcast_ref::<FixedSizeListArray>())
+                    else {
+                        continue;
+                    };
+                    for (row, node) in nodes.iter_mut().enumerate() {
+                        if vectors.is_null(row) {
+                            continue;
+                        }
+                        let vector = vectors.value(row);
+                        let vector = vector
+                            .as_any()
+                            .downcast_ref::<Float32Array>()
+                            .context("Expected float vectors in record batch")?;
+                        node.vectors
+                            .get_or_insert_with(HashMap::new)
+                            .insert(config.embedded_field.clone(), vector.values().to_vec());
+                    }
+                }
+                FieldConfig::Chunk | FieldConfig::ID => (),
+            }
+        }
+
+        Ok(nodes)
+    }
 }
 
 #[cfg(test)]
@@ -248,4 +435,93 @@ mod test {
             .unwrap();
         assert_eq!(result.documents().len(), 3);
     }
+
+    #[tokio::test]
+    async fn test_retrieve_hybrid_search() {
+        let tempdir = TempDir::new().unwrap();
+        let lancedb = LanceDB::builder()
+            .uri(tempdir.child("lancedb").to_str().unwrap())
+            .vector_size(384)
+            .with_vector(EmbeddedField::Combined)
+            .table_name("swiftide_test")
+            .full_text_search(true)
+            .build()
+            .unwrap();
+        lancedb.setup().await.unwrap();
+
+        let nodes = vec![
+            indexing::Node::new("swiftide is a rust library")
+                .with_vectors([(EmbeddedField::Combined, vec![-1.0; 384])])
+                .to_owned(),
+            indexing::Node::new("something else entirely")
+                .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+                .to_owned(),
+            indexing::Node::new("unrelated")
+                .with_vectors([(EmbeddedField::Combined, vec![-1.0; 384])])
+                .to_owned(),
+        ];
+
+        lancedb
+            .batch_store(nodes)
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut query = Query::<states::Pending>::new("rust library");
+        query.embedding = Some(vec![1.0; 384]);
+
+        let mut search_strategy = HybridSearch::default();
+        search_strategy.with_top_n(1).with_top_k(5);
+
+        let result = lancedb.retrieve(&search_strategy, query).await.unwrap();
+
+        // The best vector match and the best full-text match are both retrieved
+        let contents = result
+            .documents()
+            .iter()
+            .map(Document::content)
+            .collect_vec();
+        assert_eq!(contents.len(), 2);
+        assert!(contents.contains(&"swiftide is a rust library"));
+        assert!(contents.contains(&"something else entirely"));
+    }
+
+    #[tokio::test]
+    async fn test_scroll_all_nodes() {
+        let (_guard, lancedb) = setup().await;
+
+        let nodes = ["first", "second", "third"]
+            .into_iter()
+            .map(|chunk| {
+                indexing::Node::new(chunk)
+                    .with_metadata(("filter", chunk))
+                    .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+                    .to_owned()
+            })
+            .collect_vec();
+
+        lancedb
+            .batch_store(nodes.clone())
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut scrolled = lancedb.scroll().try_collect::<Vec<_>>().await.unwrap();
+        scrolled.sort_by(|a, b| a.chunk.cmp(&b.chunk));
+
+        assert_eq!(scrolled, nodes);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let a = Document::new("a", None);
+        let b = Document::new("b", None);
+        let c = Document::new("c", None);
+
+        let fused =
+            reciprocal_rank_fusion([vec![a.clone(), b.clone()], vec![b.clone(), c.clone()]], 2);
+        assert_eq!(fused, vec![b, a]);
+    }
 }
@@ -1,17 +1,37 @@
 //! Integrations with various platforms and external services.
 
+#[cfg(feature = "anthropic")]
+pub mod anthropic;
 #[cfg(feature = "aws-bedrock")]
 pub mod aws_bedrock;
+#[cfg(feature = "braintrust")]
+pub mod braintrust;
+#[cfg(feature = "cassandra")]
+pub mod cassandra;
+#[cfg(any(feature = "pgvector", feature = "redis"))]
+pub mod compression;
 #[cfg(feature = "dashscope")]
 pub mod dashscope;
+#[cfg(feature = "deepseek")]
+pub mod deepseek;
+#[cfg(feature = "elasticsearch")]
+pub mod elasticsearch;
 #[cfg(feature = "fastembed")]
 pub mod fastembed;
 #[cfg(feature = "fluvio")]
 pub mod fluvio;
+#[cfg(feature = "gemini")]
+pub mod gemini;
 #[cfg(feature = "groq")]
 pub mod groq;
+#[cfg(feature = "jina")]
+pub mod jina;
 #[cfg(feature = "lancedb")]
 pub mod lancedb;
+#[cfg(any(feature = "qdrant", feature = "pgvector", feature = "lancedb"))]
+mod metrics;
+#[cfg(feature = "neo4j")]
+pub mod neo4j;
 #[cfg(feature = "ollama")]
 pub mod ollama;
 #[cfg(feature = "open-router")]
@@ -28,7 +48,17 @@ pub mod qdrant;
 pub mod redb;
 #[cfg(feature = "redis")]
 pub mod redis;
+#[cfg(any(feature = "deepseek", feature = "jina", feature = "ollama"))]
+mod reqwest_errors;
+#[cfg(feature = "rhai")]
+pub mod rhai;
 #[cfg(feature = "scraping")]
 pub mod scraping;
+#[cfg(feature = "tiktoken")]
+pub mod tiktoken;
+#[cfg(feature = "together")]
+pub mod together;
+#[cfg(feature = "tokenizers")]
+pub mod tokenizers;
 #[cfg(feature = "tree-sitter")]
 pub mod treesitter;
@@ -0,0 +1,24 @@
+//! Measures operations on storage when the "metrics" feature is enabled, otherwise a no-op
+//!
+//! See `swiftide_core::metrics` for the recorded metrics.
+use std::future::Future;
+
+pub(crate) async fn measure<T, E>(
+    store: &str,
+    collection: &str,
+    operation: &'static str,
+    nodes: usize,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    #[cfg(feature = "metrics")]
+    {
+        swiftide_core::metrics::measure_store_operation(store, collection, operation, nodes, future)
+            .await
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    {
+        let _ = (store, collection, operation, nodes);
+        future.await
+    }
+}
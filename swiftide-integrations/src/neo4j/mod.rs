@@ -0,0 +1,197 @@
+//! This module provides integration with `Neo4j` as a graph store, to back `GraphRAG` pipelines.
+//!
+//! Nodes are stored as `Chunk` nodes with their content, path, metadata and optionally their
+//! embedding. Entity and relation triples in the metadata of a node, i.e. extracted by a metadata
+//! transformer, are stored as `Entity` nodes connected by `RELATES_TO` relationships, and linked
+//! to the chunk they were mentioned in with `MENTIONS`.
+//!
+//! Retrieval supports vector search on the chunks, extended with the relations of the entities
+//! they mention, and custom Cypher queries for any graph traversal.
+//!
+//! The http api is used, so any `Neo4j` 5 server or `AuraDB` instance works.
+//!
+//! The module is conditionally compiled based on the "neo4j" feature flag.
+
+use anyhow::{Context as _, Result};
+use derive_builder::Builder;
+use secrecy::{ExposeSecret as _, SecretString};
+use serde::{Deserialize, Serialize};
+use swiftide_core::indexing::EmbeddedField;
+
+mod persist;
+mod retrieve;
+
+pub use retrieve::CypherQuery;
+
+const DEFAULT_URL: &str = "http://localhost:7474";
+
+/// Stores nodes and their entities in and retrieves documents from `Neo4j`
+///
+/// Implements `Persist` and `Retrieve`. `setup` creates uniqueness constraints for chunks and
+/// entities, and a vector index if a vector size is set.
+///
+/// By default it will look for a `NEO4J_PASSWORD` environment variable.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::neo4j::Neo4j;
+/// Neo4j::builder()
+///     .url("http://localhost:7474")
+///     .username("neo4j")
+///     .vector_size(1536)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct Neo4j {
+    /// The url of the http api, defaults to `http://localhost:7474`
+    #[builder(default = "DEFAULT_URL.to_string()")]
+    url: String,
+    /// The database to use, defaults to `neo4j`
+    #[builder(default = "\"neo4j\".to_string()")]
+    database: String,
+    #[builder(default = "\"neo4j\".to_string()")]
+    username: String,
+    #[builder(default = "default_password()")]
+    password: SecretString,
+    #[builder(default)]
+    http_client: reqwest::Client,
+    /// The dimensions of the embeddings, stores embeddings and creates a vector index if set
+    #[builder(default)]
+    vector_size: Option<usize>,
+    /// The embedding to store, defaults to `EmbeddedField::Combined`
+    #[builder(default)]
+    vector_field: EmbeddedField,
+    /// The name of the vector index, defaults to `swiftide_chunks`
+    #[builder(default = "\"swiftide_chunks\".to_string()")]
+    vector_index_name: String,
+    /// The metadata key holding the triples of a node, defaults to `triples`
+    ///
+    /// The value is expected to be a list of [`Triple`].
+    #[builder(default = "\"triples\".to_string()")]
+    triples_key: String,
+    /// The number of nodes stored in a single transaction, defaults to 50
+    #[builder(default = "50")]
+    batch_size: usize,
+}
+
+/// An entity and relation triple, i.e. `("Swiftide", "is written in", "Rust")`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Triple {
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+}
+
+impl Triple {
+    pub fn new(
+        subject: impl Into<String>,
+        predicate: impl Into<String>,
+        object: impl Into<String>,
+    ) -> Self {
+        Self {
+            subject: subject.into(),
+            predicate: predicate.into(),
+            object: object.into(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Neo4j {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Neo4j")
+            .field("url", &self.url)
+            .field("database", &self.database)
+            .field("username", &self.username)
+            .field("vector_size", &self.vector_size)
+            .field("vector_field", &self.vector_field)
+            .finish_non_exhaustive()
+    }
+}
+
+fn default_password() -> SecretString {
+    std::env::var("NEO4J_PASSWORD").unwrap_or_default().into()
+}
+
+/// The result of a single statement, as returned by the http api
+#[derive(Debug, Deserialize)]
+pub(crate) struct StatementResult {
+    columns: Vec<String>,
+    data: Vec<Row>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    row: Vec<serde_json::Value>,
+}
+
+impl StatementResult {
+    /// Returns every row as a map of column to value
+    pub(crate) fn rows(
+        &self,
+    ) -> impl Iterator<Item = serde_json::Map<String, serde_json::Value>> + '_ {
+        self.data.iter().map(|row| {
+            self.columns
+                .iter()
+                .cloned()
+                .zip(row.row.iter().cloned())
+                .collect()
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TransactionResponse {
+    results: Vec<StatementResult>,
+    errors: Vec<serde_json::Value>,
+}
+
+impl Neo4j {
+    pub fn builder() -> Neo4jBuilder {
+        Neo4jBuilder::default()
+    }
+
+    /// Runs statements with their parameters in a single transaction
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or any statement fails, in which case the
+    /// transaction is rolled back.
+    pub(crate) async fn run(
+        &self,
+        statements: Vec<(String, serde_json::Value)>,
+    ) -> Result<Vec<StatementResult>> {
+        let statements = statements
+            .into_iter()
+            .map(|(statement, parameters)| {
+                serde_json::json!({ "statement": statement, "parameters": parameters })
+            })
+            .collect::<Vec<_>>();
+
+        let response = self
+            .http_client
+            .post(format!(
+                "{}/db/{}/tx/commit",
+                self.url.trim_end_matches('/'),
+                self.database
+            ))
+            .basic_auth(&self.username, Some(self.password.expose_secret()))
+            .json(&serde_json::json!({ "statements": statements }))
+            .send()
+            .await
+            .context("Failed to send request to neo4j")?
+            .error_for_status()?
+            .json::<TransactionResponse>()
+            .await
+            .context("Failed to parse response from neo4j")?;
+
+        // Statement errors are returned in the body with a successful status
+        if let Some(error) = response.errors.first() {
+            anyhow::bail!("Neo4j returned an error: {error}");
+        }
+
+        Ok(response.results)
+    }
+}
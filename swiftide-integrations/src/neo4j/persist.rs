@@ -0,0 +1,206 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use serde_json::json;
+use swiftide_core::{
+    indexing::{IndexingStream, Node, PersistError},
+    Persist,
+};
+
+use super::{Neo4j, Triple};
+
+/// Merges chunks and their triples, so that storing a node again updates it
+///
+/// Entities are merged by name, so entities mentioned in multiple chunks are the same node in the
+/// graph.
+const STORE_NODES: &str = "
+UNWIND $nodes AS node
+MERGE (chunk:Chunk {id: node.id})
+SET chunk.content = node.content,
+    chunk.path = node.path,
+    chunk.metadata = node.metadata,
+    chunk.embedding = node.embedding
+WITH chunk, node
+UNWIND node.triples AS triple
+MERGE (subject:Entity {name: triple.subject})
+MERGE (object:Entity {name: triple.object})
+MERGE (subject)-[:RELATES_TO {type: triple.predicate}]->(object)
+MERGE (chunk)-[:MENTIONS]->(subject)
+MERGE (chunk)-[:MENTIONS]->(object)
+";
+
+#[async_trait]
+impl Persist for Neo4j {
+    /// Creates uniqueness constraints for chunks and entities, and the vector index if a vector
+    /// size is set
+    #[tracing::instrument(skip_all)]
+    async fn setup(&self) -> Result<(), PersistError> {
+        let mut statements = vec![
+            "CREATE CONSTRAINT swiftide_chunk_id IF NOT EXISTS FOR (c:Chunk) REQUIRE c.id IS UNIQUE"
+                .to_string(),
+            "CREATE CONSTRAINT swiftide_entity_name IF NOT EXISTS FOR (e:Entity) REQUIRE e.name IS UNIQUE"
+                .to_string(),
+        ];
+
+        if let Some(vector_size) = self.vector_size {
+            statements.push(format!(
+                "CREATE VECTOR INDEX {} IF NOT EXISTS FOR (c:Chunk) ON c.embedding \
+                 OPTIONS {{indexConfig: {{`vector.dimensions`: {vector_size}, `vector.similarity_function`: 'cosine'}}}}",
+                self.vector_index_name
+            ));
+        }
+
+        // Schema changes cannot be mixed with other statements in a transaction
+        for statement in statements {
+            self.run(vec![(statement, json!({}))])
+                .await
+                .context("Failed to set up neo4j")?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        self.store_nodes(std::slice::from_ref(&node)).await?;
+        Ok(node)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
+        self.store_nodes(&nodes).await.map(|()| nodes).into()
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        Some(self.batch_size)
+    }
+}
+
+impl Neo4j {
+    async fn store_nodes(&self, nodes: &[Node]) -> Result<()> {
+        let nodes = nodes
+            .iter()
+            .map(|node| self.node_to_parameters(node))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.run(vec![(STORE_NODES.to_string(), json!({ "nodes": nodes }))])
+            .await
+            .context("Failed to store nodes in neo4j")?;
+
+        Ok(())
+    }
+
+    fn node_to_parameters(&self, node: &Node) -> Result<serde_json::Value> {
+        let triples = node
+            .metadata
+            .get(&self.triples_key)
+            .map(|triples| serde_json::from_value::<Vec<Triple>>(triples.clone()))
+            .transpose()
+            .with_context(|| format!("Invalid triples in metadata key {}", self.triples_key))?
+            .unwrap_or_default();
+
+        // Properties cannot be maps, so the metadata is stored as json
+        let metadata = node
+            .metadata
+            .iter()
+            .filter(|(key, _)| **key != self.triples_key)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<serde_json::Map<_, _>>();
+
+        let embedding = if self.vector_size.is_some() {
+            let embedding = node
+                .vectors
+                .as_ref()
+                .and_then(|vectors| vectors.get(&self.vector_field))
+                .with_context(|| format!("Node without vector for {}", self.vector_field))?;
+            Some(embedding)
+        } else {
+            None
+        };
+
+        Ok(json!({
+            "id": node.id().to_string(),
+            "content": node.chunk,
+            "path": node.path.to_string_lossy(),
+            "metadata": serde_json::to_string(&metadata)?,
+            "embedding": embedding,
+            "triples": triples,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn neo4j(mock_server: &MockServer) -> Neo4j {
+        Neo4j::builder()
+            .url(mock_server.uri())
+            .password("test")
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_store_with_triples() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/db/neo4j/tx/commit"))
+            .and(body_partial_json(json!({
+                "statements": [{
+                    "parameters": {
+                        "nodes": [{
+                            "content": "Swiftide is written in Rust",
+                            "metadata": "{\"lang\":\"en\"}",
+                            "triples": [{
+                                "subject": "Swiftide",
+                                "predicate": "is written in",
+                                "object": "Rust"
+                            }]
+                        }]
+                    }
+                }]
+            })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "results": [], "errors": [] })),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut node = Node::new("Swiftide is written in Rust");
+        node.metadata.insert("lang", "en");
+        node.metadata.insert(
+            "triples",
+            serde_json::to_value(vec![Triple::new("Swiftide", "is written in", "Rust")]).unwrap(),
+        );
+
+        neo4j(&mock_server).store(node).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_statement_errors_fail() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/db/neo4j/tx/commit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [],
+                "errors": [{ "code": "Neo.ClientError.Statement.SyntaxError", "message": "Invalid input" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let err = neo4j(&mock_server)
+            .store(Node::new("chunk"))
+            .await
+            .unwrap_err();
+
+        assert!(format!("{err:#}").contains("SyntaxError"));
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use serde_json::json;
+use swiftide_core::{
+    document::Document,
+    indexing::Metadata,
+    querying::{
+        search_strategies::{CustomStrategy, SimilaritySingleEmbedding},
+        states, Query, RetrieveError,
+    },
+    Retrieve,
+};
+
+use super::{Neo4j, StatementResult};
+
+/// Finds the most similar chunks and the relations of the entities they mention
+const VECTOR_SEARCH: &str = "
+CALL db.index.vector.queryNodes($index, $top_k, $embedding) YIELD node AS chunk, score
+OPTIONAL MATCH (chunk)-[:MENTIONS]->(subject:Entity)-[relation:RELATES_TO]->(object:Entity)
+RETURN chunk.content AS content,
+       chunk.metadata AS metadata,
+       score,
+       collect(DISTINCT subject.name + ' ' + relation.type + ' ' + object.name) AS relations
+ORDER BY score DESC
+";
+
+/// A Cypher statement with parameters, for retrieval with a `CustomStrategy`
+///
+/// The statement must return a `content` column, and can return a `metadata` column with a map
+/// or json string. Any other columns are added to the metadata of the documents.
+///
+/// The current query and its embedding are available as the `$query` and `$embedding`
+/// parameters, unless set explicitly. This allows reusing a statement as a template for every
+/// query.
+///
+/// # Example
+///
+/// ```
+/// # use swiftide_integrations::neo4j::CypherQuery;
+/// # use swiftide_core::querying::search_strategies::CustomStrategy;
+/// let strategy = CustomStrategy::from_query(|_query| {
+///     Ok(CypherQuery::new(
+///         "MATCH (chunk:Chunk)-[:MENTIONS]->(entity:Entity)
+///          WHERE toLower($query) CONTAINS toLower(entity.name)
+///          RETURN DISTINCT chunk.content AS content, chunk.metadata AS metadata
+///          LIMIT $limit",
+///     )
+///     .with_parameter("limit", 10))
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct CypherQuery {
+    statement: String,
+    parameters: serde_json::Map<String, serde_json::Value>,
+}
+
+impl CypherQuery {
+    pub fn new(statement: impl Into<String>) -> Self {
+        Self {
+            statement: statement.into(),
+            parameters: serde_json::Map::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_parameter(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.parameters.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// Implement the `Retrieve` trait for `SimilaritySingleEmbedding` search strategy.
+///
+/// Searches the vector index of the chunks, and adds the relations of the entities mentioned in
+/// every chunk to its metadata under `relations`, i.e. `Swiftide is written in Rust`.
+///
+/// Requires a vector size to be configured. Filters are not supported, use a `CustomStrategy`
+/// with a `CypherQuery` instead.
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding> for Neo4j {
+    #[tracing::instrument]
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.embedding else {
+            return Err(RetrieveError::MissingEmbedding);
+        };
+
+        if self.vector_size.is_none() {
+            return Err(RetrieveError::permanent(
+                "Vector search requires a vector size to be configured",
+            ));
+        }
+
+        let results = self
+            .run(vec![(
+                VECTOR_SEARCH.to_string(),
+                json!({
+                    "index": self.vector_index_name,
+                    "top_k": search_strategy.top_k(),
+                    "embedding": embedding,
+                }),
+            )])
+            .await
+            .context("Failed to retrieve from neo4j")?;
+
+        let documents = documents_from_results(&results)?;
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+#[async_trait]
+impl Retrieve<CustomStrategy<CypherQuery>> for Neo4j {
+    /// Runs a custom Cypher statement, see [`CypherQuery`]
+    async fn retrieve(
+        &self,
+        search_strategy: &CustomStrategy<CypherQuery>,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let CypherQuery {
+            statement,
+            mut parameters,
+        } = search_strategy.build_query(&query).await?;
+
+        parameters
+            .entry("query")
+            .or_insert_with(|| query.current().into());
+        if let Some(embedding) = &query.embedding {
+            parameters
+                .entry("embedding")
+                .or_insert_with(|| json!(embedding));
+        }
+
+        let results = self
+            .run(vec![(statement, parameters.into())])
+            .await
+            .context("Failed to retrieve from neo4j")?;
+
+        let documents = documents_from_results(&results)?;
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+fn documents_from_results(results: &[StatementResult]) -> Result<Vec<Document>> {
+    results
+        .iter()
+        .flat_map(StatementResult::rows)
+        .map(|mut row| {
+            let content = row
+                .remove("content")
+                .and_then(|content| content.as_str().map(str::to_string))
+                .context("Expected content column in neo4j result")?;
+
+            let mut metadata = match row.remove("metadata") {
+                Some(serde_json::Value::String(metadata)) => {
+                    serde_json::from_str::<Metadata>(&metadata)?
+                }
+                Some(serde_json::Value::Null) | None => Metadata::default(),
+                Some(metadata) => serde_json::from_value::<Metadata>(metadata)?,
+            };
+            metadata.extend(row);
+
+            Ok(Document::new(content, Some(metadata)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn neo4j(mock_server: &MockServer) -> Neo4j {
+        Neo4j::builder()
+            .url(mock_server.uri())
+            .password("test")
+            .vector_size(3usize)
+            .build()
+            .unwrap()
+    }
+
+    fn response() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "results": [{
+                "columns": ["content", "metadata", "score", "relations"],
+                "data": [{
+                    "row": [
+                        "Swiftide is written in Rust",
+                        "{\"lang\":\"en\"}",
+                        0.9,
+                        ["Swiftide is written in Rust"]
+                    ]
+                }]
+            }],
+            "errors": []
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_with_relations() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/db/neo4j/tx/commit"))
+            .and(body_partial_json(json!({
+                "statements": [{ "parameters": { "index": "swiftide_chunks", "top_k": 10 } }]
+            })))
+            .respond_with(response())
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut query = Query::<states::Pending>::new("What is Swiftide written in?");
+        query.embedding = Some(vec![1.0; 3]);
+
+        let result = neo4j(&mock_server)
+            .retrieve(&SimilaritySingleEmbedding::default(), query)
+            .await
+            .unwrap();
+
+        let document = &result.documents()[0];
+        assert_eq!(document.content(), "Swiftide is written in Rust");
+        assert_eq!(document.metadata().get("lang"), Some(&json!("en")));
+        assert_eq!(
+            document.metadata().get("relations"),
+            Some(&json!(["Swiftide is written in Rust"]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_with_cypher_query() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/db/neo4j/tx/commit"))
+            .and(body_partial_json(json!({
+                "statements": [{
+                    "statement": "MATCH (chunk:Chunk) RETURN chunk.content AS content",
+                    "parameters": { "query": "Swiftide", "limit": 5 }
+                }]
+            })))
+            .respond_with(response())
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let strategy = CustomStrategy::from_query(|_| {
+            Ok(
+                CypherQuery::new("MATCH (chunk:Chunk) RETURN chunk.content AS content")
+                    .with_parameter("limit", 5),
+            )
+        });
+
+        let result = neo4j(&mock_server)
+            .retrieve(&strategy, Query::<states::Pending>::new("Swiftide"))
+            .await
+            .unwrap();
+
+        assert_eq!(result.documents().len(), 1);
+    }
+}
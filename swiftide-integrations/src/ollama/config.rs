@@ -22,6 +22,14 @@ impl OllamaConfig {
 
         self
     }
+
+    /// Url of the native Ollama api, which is not under the `/v1` openai mapping
+    pub(crate) fn native_url(&self, path: &str) -> String {
+        let api_base = self.api_base.trim_end_matches('/');
+        let api_base = api_base.strip_suffix("/v1").unwrap_or(api_base);
+
+        format!("{api_base}{path}")
+    }
 }
 
 impl Default for OllamaConfig {
@@ -54,3 +62,23 @@ impl async_openai::config::Config for OllamaConfig {
         vec![]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_url() {
+        let mut config = OllamaConfig::default();
+        assert_eq!(
+            config.native_url("/api/chat"),
+            "http://localhost:11434/api/chat"
+        );
+
+        config.with_api_base("http://ollama:11434/");
+        assert_eq!(
+            config.native_url("/api/chat"),
+            "http://ollama:11434/api/chat"
+        );
+    }
+}
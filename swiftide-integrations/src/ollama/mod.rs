@@ -2,14 +2,21 @@
 //! It includes the `Ollama` struct for managing API clients and default options for embedding and prompt models.
 //! The module is conditionally compiled based on the "ollama" feature flag.
 
+use async_trait::async_trait;
 use config::OllamaConfig;
 use derive_builder::Builder;
 use std::sync::Arc;
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError,
+    model_provider::{ModelInfo, ModelProvider},
+    EmbeddingPrefixes,
+};
 
 pub mod chat_completion;
 pub mod config;
 pub mod embed;
 pub mod simple_prompt;
+pub mod structured_prompt;
 
 /// The `Ollama` struct encapsulates an `Ollama` client and default options for embedding and prompt models.
 /// It uses the `Builder` pattern for flexible and customizable instantiation.
@@ -20,6 +27,9 @@ pub mod simple_prompt;
 ///
 /// Under the hood it uses [`async_openai`], with the Ollama openai mapping. This means
 /// some features might not work as expected. See the Ollama documentation for details.
+///
+/// Structured prompts use the native Ollama api instead, which constrains the output to the
+/// schema, see [`OutputFormat`].
 #[derive(Debug, Builder, Clone)]
 #[builder(setter(into, strip_option))]
 pub struct Ollama {
@@ -29,6 +39,9 @@ pub struct Ollama {
     /// Default options for the embedding and prompt models.
     #[builder(default)]
     default_options: Options,
+    /// Client for the native Ollama api, used for structured output
+    #[builder(default)]
+    http_client: reqwest::Client,
 }
 
 impl Default for Ollama {
@@ -36,6 +49,7 @@ impl Default for Ollama {
         Self {
             client: default_client(),
             default_options: Options::default(),
+            http_client: reqwest::Client::default(),
         }
     }
 }
@@ -49,6 +63,10 @@ pub struct Options {
     #[builder(default)]
     pub embed_model: Option<String>,
 
+    /// Prefixes for queries and documents, for asymmetric embedding models, e.g. e5
+    #[builder(default)]
+    pub embed_prefixes: Option<EmbeddingPrefixes>,
+
     /// The default prompt model to use, if specified.
     #[builder(default)]
     pub prompt_model: Option<String>,
@@ -71,7 +89,7 @@ impl Ollama {
     pub fn with_default_prompt_model(&mut self, model: impl Into<String>) -> &mut Self {
         self.default_options = Options {
             prompt_model: Some(model.into()),
-            embed_model: self.default_options.embed_model.clone(),
+            ..self.default_options.clone()
         };
         self
     }
@@ -79,8 +97,8 @@ impl Ollama {
     /// Sets a default embedding model to use when embedding
     pub fn with_default_embed_model(&mut self, model: impl Into<String>) -> &mut Self {
         self.default_options = Options {
-            prompt_model: self.default_options.prompt_model.clone(),
             embed_model: Some(model.into()),
+            ..self.default_options.clone()
         };
         self
     }
@@ -138,6 +156,18 @@ impl OllamaBuilder {
     }
 }
 
+#[async_trait]
+impl ModelProvider for Ollama {
+    #[tracing::instrument(skip_all, err)]
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LanguageModelError> {
+        crate::openai::list_models(
+            &self.client,
+            crate::openai::openai_error_to_language_model_error,
+        )
+        .await
+    }
+}
+
 fn default_client() -> Arc<async_openai::Client<OllamaConfig>> {
     Arc::new(async_openai::Client::with_config(OllamaConfig::default()))
 }
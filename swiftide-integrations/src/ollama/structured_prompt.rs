@@ -0,0 +1,185 @@
+//! This module provides an implementation of the `StructuredPrompt` trait for the `Ollama` struct.
+//!
+//! Uses the native Ollama chat api, which constrains decoding to the requested format. The
+//! openai mapping of Ollama does not support this.
+use async_trait::async_trait;
+use schemars::schema::RootSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError, prompt::Prompt, util::debug_long_utf8,
+    StructuredPrompt,
+};
+
+use super::{Ollama, OutputFormat};
+use crate::reqwest_errors::reqwest_error_to_language_model_error;
+use anyhow::{Context as _, Result};
+
+#[derive(Serialize, Debug)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage>,
+    format: Value,
+    stream: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatResponse {
+    message: ChatMessage,
+}
+
+#[async_trait]
+impl StructuredPrompt for Ollama {
+    #[tracing::instrument(skip_all, err)]
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<Value, LanguageModelError> {
+        let model = self
+            .default_options
+            .prompt_model
+            .as_ref()
+            .context("Model not set")?;
+
+        let format = match self.default_options.output_format.unwrap_or_default() {
+            OutputFormat::Json => Value::String("json".to_string()),
+            OutputFormat::JsonSchema => {
+                serde_json::to_value(&schema).map_err(LanguageModelError::permanent)?
+            }
+        };
+
+        let request = ChatRequest {
+            model,
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: prompt.render().await?,
+            }],
+            format,
+            stream: false,
+        };
+
+        tracing::debug!(
+            model = &model,
+            messages = debug_long_utf8(&request.messages[0].content, 100),
+            "[StructuredPrompt] Request to ollama"
+        );
+
+        let response = self
+            .http_client
+            .post(self.client.config().native_url("/api/chat"))
+            .json(&request)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(reqwest_error_to_language_model_error)?
+            .json::<ChatResponse>()
+            .await
+            .map_err(LanguageModelError::permanent)?
+            .message
+            .content;
+
+        tracing::debug!(
+            response = debug_long_utf8(&response, 100),
+            "[StructuredPrompt] Response from ollama"
+        );
+
+        serde_json::from_str(&response)
+            .context("Expected json in response")
+            .map_err(LanguageModelError::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ollama::{config::OllamaConfig, Options};
+    use schemars::JsonSchema;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    struct Answer {
+        answer: String,
+    }
+
+    async fn ollama_with_format(
+        mock_server: &MockServer,
+        output_format: Option<OutputFormat>,
+    ) -> Ollama {
+        let mut config = OllamaConfig::default();
+        config.with_api_base(&format!("{}/v1", mock_server.uri()));
+
+        let mut options = Options::builder();
+        options.prompt_model("llama3.1");
+        if let Some(output_format) = output_format {
+            options.output_format(output_format);
+        }
+
+        Ollama::builder()
+            .client(async_openai::Client::with_config(config))
+            .default_options(options.build().unwrap())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_structured_prompt_with_schema() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(json!({
+                "model": "llama3.1",
+                "stream": false,
+                "format": { "required": ["answer"] }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "llama3.1",
+                "message": { "role": "assistant", "content": "{\"answer\": \"42\"}" },
+                "done": true
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let ollama = ollama_with_format(&mock_server, None).await;
+        let answer: Answer = ollama.structured_prompt("Question?".into()).await.unwrap();
+
+        assert_eq!(
+            answer,
+            Answer {
+                answer: "42".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_structured_prompt_with_json_format() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(json!({ "format": "json" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "llama3.1",
+                "message": { "role": "assistant", "content": "{\"answer\": \"42\"}" },
+                "done": true
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let ollama = ollama_with_format(&mock_server, Some(OutputFormat::Json)).await;
+        let answer: Answer = ollama.structured_prompt("Question?".into()).await.unwrap();
+
+        assert_eq!(answer.answer, "42");
+    }
+}
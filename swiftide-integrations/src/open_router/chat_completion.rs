@@ -1,18 +1,18 @@
 use anyhow::{Context as _, Result};
 use async_openai::types::{
-    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
-    ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestToolMessageArgs,
-    ChatCompletionRequestUserMessageArgs, ChatCompletionTool, ChatCompletionToolArgs,
-    ChatCompletionToolType, CreateChatCompletionRequestArgs, FunctionCall, FunctionObjectArgs,
+    ChatCompletionTool, ChatCompletionToolArgs, ChatCompletionToolType,
+    CreateChatCompletionRequestArgs, FunctionObjectArgs,
 };
 use async_trait::async_trait;
 use itertools::Itertools;
 use serde_json::json;
 use swiftide_core::chat_completion::{
-    errors::ChatCompletionError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
-    ChatMessage, ToolCall, ToolSpec,
+    errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+    ToolCall, ToolSpec,
 };
 
+use crate::openai::{message_to_openai, openai_error_to_language_model_error, usage_from_openai};
+
 use super::OpenRouter;
 
 #[async_trait]
@@ -21,7 +21,7 @@ impl ChatCompletion for OpenRouter {
     async fn complete(
         &self,
         request: &ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse, ChatCompletionError> {
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
         let model = self
             .default_options
             .prompt_model
@@ -32,6 +32,7 @@ impl ChatCompletion for OpenRouter {
             .messages()
             .iter()
             .map(message_to_openai)
+            .flatten_ok()
             .collect::<Result<Vec<_>>>()?;
 
         // Build the request to be sent to the OpenAI API.
@@ -55,7 +56,7 @@ impl ChatCompletion for OpenRouter {
 
         let request = openai_request
             .build()
-            .map_err(|e| ChatCompletionError::LLM(Box::new(e)))?;
+            .map_err(LanguageModelError::permanent)?;
 
         tracing::debug!(
             model = &model,
@@ -68,7 +69,7 @@ impl ChatCompletion for OpenRouter {
             .chat()
             .create(request)
             .await
-            .map_err(|e| ChatCompletionError::LLM(Box::new(e)))?;
+            .map_err(openai_error_to_language_model_error)?;
 
         tracing::debug!(
             response = serde_json::to_string_pretty(&response).expect("infallible"),
@@ -101,8 +102,9 @@ impl ChatCompletion for OpenRouter {
                             .collect_vec()
                     }),
             )
+            .maybe_usage(response.usage.as_ref().map(usage_from_openai))
             .build()
-            .map_err(ChatCompletionError::from)
+            .map_err(LanguageModelError::from)
     }
 }
 
@@ -112,13 +114,7 @@ fn tools_to_openai(spec: &ToolSpec) -> Result<ChatCompletionTool> {
     let mut properties = serde_json::Map::new();
 
     for param in &spec.parameters {
-        properties.insert(
-            param.name.to_string(),
-            json!({
-                "type": "string",
-                "description": param.description,
-            }),
-        );
+        properties.insert(param.name.to_string(), param.json_schema());
     }
 
     ChatCompletionToolArgs::default()
@@ -134,63 +130,3 @@ fn tools_to_openai(spec: &ToolSpec) -> Result<ChatCompletionTool> {
             })).build()?).build()
         .map_err(anyhow::Error::from)
 }
-
-fn message_to_openai(
-    message: &ChatMessage,
-) -> Result<async_openai::types::ChatCompletionRequestMessage> {
-    let openai_message = match message {
-        ChatMessage::User(msg) => ChatCompletionRequestUserMessageArgs::default()
-            .content(msg.as_str())
-            .build()?
-            .into(),
-        ChatMessage::System(msg) => ChatCompletionRequestSystemMessageArgs::default()
-            .content(msg.as_str())
-            .build()?
-            .into(),
-        ChatMessage::Summary(msg) => ChatCompletionRequestAssistantMessageArgs::default()
-            .content(msg.as_str())
-            .build()?
-            .into(),
-        ChatMessage::ToolOutput(tool_call, tool_output) => {
-            let Some(content) = tool_output.content() else {
-                return Ok(ChatCompletionRequestToolMessageArgs::default()
-                    .tool_call_id(tool_call.id())
-                    .build()?
-                    .into());
-            };
-
-            ChatCompletionRequestToolMessageArgs::default()
-                .content(content)
-                .tool_call_id(tool_call.id())
-                .build()?
-                .into()
-        }
-        ChatMessage::Assistant(msg, tool_calls) => {
-            let mut builder = ChatCompletionRequestAssistantMessageArgs::default();
-
-            if let Some(msg) = msg {
-                builder.content(msg.as_str());
-            }
-
-            if let Some(tool_calls) = tool_calls {
-                builder.tool_calls(
-                    tool_calls
-                        .iter()
-                        .map(|tool_call| ChatCompletionMessageToolCall {
-                            id: tool_call.id().to_string(),
-                            r#type: ChatCompletionToolType::Function,
-                            function: FunctionCall {
-                                name: tool_call.name().to_string(),
-                                arguments: tool_call.args().unwrap_or_default().to_string(),
-                            },
-                        })
-                        .collect::<Vec<_>>(),
-                );
-            }
-
-            builder.build()?.into()
-        }
-    };
-
-    Ok(openai_message)
-}
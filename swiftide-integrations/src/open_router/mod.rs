@@ -2,9 +2,14 @@
 //! It includes the `OpenRouter` struct for managing API clients and default options for embedding and prompt models.
 //! The module is conditionally compiled based on the "openrouter" feature flag.
 
+use async_trait::async_trait;
 use config::OpenRouterConfig;
 use derive_builder::Builder;
 use std::sync::Arc;
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError,
+    model_provider::{ModelInfo, ModelProvider},
+};
 
 pub mod chat_completion;
 pub mod config;
@@ -103,6 +108,18 @@ impl OpenRouterBuilder {
     }
 }
 
+#[async_trait]
+impl ModelProvider for OpenRouter {
+    #[tracing::instrument(skip_all, err)]
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LanguageModelError> {
+        crate::openai::list_models(
+            &self.client,
+            crate::openai::openai_error_to_language_model_error,
+        )
+        .await
+    }
+}
+
 fn default_client() -> Arc<async_openai::Client<OpenRouterConfig>> {
     Arc::new(async_openai::Client::with_config(
         OpenRouterConfig::default(),
@@ -3,9 +3,13 @@
 //! and generating responses as part of the Swiftide system.
 use async_openai::types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs};
 use async_trait::async_trait;
-use swiftide_core::{prompt::Prompt, util::debug_long_utf8, SimplePrompt};
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError, prompt::Prompt, util::debug_long_utf8,
+    SimplePrompt,
+};
 
 use super::OpenRouter;
+use crate::openai::openai_error_to_language_model_error;
 use anyhow::{Context as _, Result};
 
 /// The `SimplePrompt` trait defines a method for sending a prompt to an AI model and receiving a response.
@@ -17,7 +21,7 @@ impl SimplePrompt for OpenRouter {
     /// - `prompt`: A string slice that holds the prompt to be sent to the OpenRouter API.
     ///
     /// # Returns
-    /// - `Result<String>`: On success, returns the content of the response as a `String`.
+    /// - `Result<String, LanguageModelError>`: On success, returns the content of the response as a `String`.
     ///   On failure, returns an error wrapped in a `Result`.
     ///
     /// # Errors
@@ -25,7 +29,7 @@ impl SimplePrompt for OpenRouter {
     /// - Returns an error if the request to the OpenRouter API fails.
     /// - Returns an error if the response does not contain the expected content.
     #[tracing::instrument(skip_all, err)]
-    async fn prompt(&self, prompt: Prompt) -> Result<String> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
         // Retrieve the model from the default options, returning an error if not set.
         let model = self
             .default_options
@@ -38,15 +42,18 @@ impl SimplePrompt for OpenRouter {
             .model(model)
             .messages(vec![ChatCompletionRequestUserMessageArgs::default()
                 .content(prompt.render().await?)
-                .build()?
+                .build()
+                .map_err(LanguageModelError::permanent)?
                 .into()])
-            .build()?;
+            .build()
+            .map_err(LanguageModelError::permanent)?;
 
         // Log the request for debugging purposes.
         tracing::debug!(
             model = &model,
             messages = debug_long_utf8(
-                serde_json::to_string_pretty(&request.messages.first())?,
+                serde_json::to_string_pretty(&request.messages.first())
+                    .map_err(LanguageModelError::permanent)?,
                 100
             ),
             "[SimplePrompt] Request to openrouter"
@@ -57,7 +64,8 @@ impl SimplePrompt for OpenRouter {
             .client
             .chat()
             .create(request)
-            .await?
+            .await
+            .map_err(openai_error_to_language_model_error)?
             .choices
             .remove(0)
             .message
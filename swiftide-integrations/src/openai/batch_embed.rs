@@ -0,0 +1,361 @@
+//! Embeddings via the `OpenAI` Batch api
+//!
+//! Batches are processed asynchronously by `OpenAI` within 24 hours, at half the cost of the
+//! regular embedding api.
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        BatchCompletionWindow, BatchEndpoint, BatchRequestArgs, BatchStatus,
+        CreateEmbeddingResponse, CreateFileRequestArgs, FileInput, FilePurpose,
+    },
+};
+use async_trait::async_trait;
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+use swiftide_core::{chat_completion::errors::LanguageModelError, EmbeddingModel, Embeddings};
+
+use super::GenericOpenAI;
+
+/// Embeds with the `OpenAI` Batch api, trading latency for a 50% cost reduction
+///
+/// Every call to `embed` uploads the input as a batch file, creates a batch, polls it until it
+/// completes and downloads the embeddings. A call can take up to the 24 hour completion window,
+/// so this is only suited for large indexing runs that are not latency sensitive.
+///
+/// Nodes are held in memory by the pipeline until their batch completes. Use a large batch size
+/// on the `Embed` transformer, and its concurrency to control how many batches run in parallel.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::openai::{BatchEmbed, OpenAI};
+/// let openai = OpenAI::builder()
+///     .default_embed_model("text-embedding-3-small")
+///     .build()
+///     .unwrap();
+///
+/// let batch_embed = BatchEmbed::from_client(openai);
+/// ```
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into))]
+pub struct BatchEmbed<
+    C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static = OpenAIConfig,
+> {
+    /// The `OpenAI` client, with a default embedding model set
+    client: GenericOpenAI<C>,
+    /// How often the status of a batch is checked
+    #[builder(default = "Duration::from_secs(30)")]
+    poll_interval: Duration,
+}
+
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    BatchEmbed<C>
+{
+    pub fn builder() -> BatchEmbedBuilder<C> {
+        BatchEmbedBuilder::default()
+    }
+
+    /// Creates a new `BatchEmbed` from a client with a default embedding model
+    pub fn from_client(client: GenericOpenAI<C>) -> Self {
+        Self {
+            client,
+            poll_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct BatchRequestLine<'a> {
+    custom_id: String,
+    method: &'static str,
+    url: &'static str,
+    body: BatchRequestBody<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchRequestBody<'a> {
+    model: &'a str,
+    input: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchResponseLine {
+    custom_id: String,
+    response: Option<BatchResponse>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchResponse {
+    body: CreateEmbeddingResponse,
+}
+
+#[async_trait]
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    EmbeddingModel for BatchEmbed<C>
+{
+    /// Errors are permanent, retrying would start a new batch
+    #[tracing::instrument(skip_all, err)]
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
+        Ok(self.embed_batch(input).await?)
+    }
+}
+
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    BatchEmbed<C>
+{
+    async fn embed_batch(&self, input: Vec<String>) -> Result<Embeddings> {
+        let model = self
+            .client
+            .options()
+            .embed_model
+            .as_ref()
+            .context("Model not set")?;
+
+        let mut jsonl = Vec::new();
+        for (index, text) in input.iter().enumerate() {
+            serde_json::to_writer(
+                &mut jsonl,
+                &BatchRequestLine {
+                    custom_id: index.to_string(),
+                    method: "POST",
+                    url: "/v1/embeddings",
+                    body: BatchRequestBody { model, input: text },
+                },
+            )?;
+            jsonl.push(b'\n');
+        }
+
+        let client = self.client.client();
+
+        let file = client
+            .files()
+            .create(
+                CreateFileRequestArgs::default()
+                    .file(FileInput::from_vec_u8(
+                        "embeddings.jsonl".to_string(),
+                        jsonl,
+                    ))
+                    .purpose(FilePurpose::Batch)
+                    .build()?,
+            )
+            .await
+            .context("Failed to upload batch file")?;
+
+        let mut batch = client
+            .batches()
+            .create(
+                BatchRequestArgs::default()
+                    .input_file_id(file.id)
+                    .endpoint(BatchEndpoint::V1Embeddings)
+                    .completion_window(BatchCompletionWindow::W24H)
+                    .build()?,
+            )
+            .await
+            .context("Failed to create batch")?;
+
+        tracing::debug!(
+            batch_id = &batch.id,
+            num_chunks = input.len(),
+            model = &model,
+            "[BatchEmbed] Created batch"
+        );
+
+        loop {
+            match batch.status {
+                BatchStatus::Completed => break,
+                BatchStatus::Failed
+                | BatchStatus::Expired
+                | BatchStatus::Cancelling
+                | BatchStatus::Cancelled => {
+                    anyhow::bail!(
+                        "Batch {} did not complete with status {:?}: {:?}",
+                        batch.id,
+                        batch.status,
+                        batch.errors
+                    );
+                }
+                _ => {
+                    tokio::time::sleep(self.poll_interval).await;
+                    batch = client
+                        .batches()
+                        .retrieve(&batch.id)
+                        .await
+                        .context("Failed to retrieve batch")?;
+                }
+            }
+        }
+
+        let output_file_id = batch
+            .output_file_id
+            .context("Completed batch has no output file")?;
+        let output = client
+            .files()
+            .content(&output_file_id)
+            .await
+            .context("Failed to download batch output")?;
+
+        let mut embeddings = vec![None; input.len()];
+        for line in output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+        {
+            let line: BatchResponseLine = serde_json::from_slice(line)?;
+            let index: usize = line.custom_id.parse()?;
+
+            let Some(response) = line.response else {
+                anyhow::bail!("Failed to embed input {index}: {:?}", line.error);
+            };
+
+            let embedding = response
+                .body
+                .data
+                .into_iter()
+                .next()
+                .context("Expected an embedding in the response")?
+                .embedding;
+
+            *embeddings
+                .get_mut(index)
+                .context("Unexpected id in batch output")? = Some(embedding);
+        }
+
+        tracing::debug!(
+            batch_id = &batch.id,
+            num_embeddings = embeddings.len(),
+            "[BatchEmbed] Batch completed"
+        );
+
+        embeddings
+            .into_iter()
+            .enumerate()
+            .map(|(index, embedding)| {
+                embedding.with_context(|| format!("Missing embedding {index}"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::openai::OpenAI;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn batch(status: &str, output_file_id: Option<&str>) -> serde_json::Value {
+        json!({
+            "id": "batch_1",
+            "object": "batch",
+            "endpoint": "/v1/embeddings",
+            "errors": null,
+            "input_file_id": "file-input",
+            "completion_window": "24h",
+            "status": status,
+            "output_file_id": output_file_id,
+            "error_file_id": null,
+            "created_at": 0,
+            "in_progress_at": null,
+            "expires_at": null,
+            "finalizing_at": null,
+            "completed_at": null,
+            "failed_at": null,
+            "expired_at": null,
+            "cancelling_at": null,
+            "cancelled_at": null,
+            "request_counts": { "total": 2, "completed": 0, "failed": 0 },
+            "metadata": null
+        })
+    }
+
+    fn embedding_line(custom_id: &str, embedding: &[f32]) -> String {
+        json!({
+            "id": format!("req_{custom_id}"),
+            "custom_id": custom_id,
+            "response": {
+                "status_code": 200,
+                "request_id": "req",
+                "body": {
+                    "object": "list",
+                    "model": "text-embedding-3-small",
+                    "data": [{ "object": "embedding", "index": 0, "embedding": embedding }],
+                    "usage": { "prompt_tokens": 1, "total_tokens": 1 }
+                }
+            },
+            "error": null
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_embed_via_batch() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "file-input",
+                "object": "file",
+                "bytes": 100,
+                "created_at": 0,
+                "filename": "embeddings.jsonl",
+                "purpose": "batch"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/batches"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch("validating", None)))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/batches/batch_1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(batch("completed", Some("file-output"))),
+            )
+            .mount(&mock_server)
+            .await;
+
+        // Out of order on purpose
+        Mock::given(method("GET"))
+            .and(path("/files/file-output/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "{}\n{}\n",
+                embedding_line("1", &[0.3, 0.4]),
+                embedding_line("0", &[0.1, 0.2])
+            )))
+            .mount(&mock_server)
+            .await;
+
+        let openai = OpenAI::builder()
+            .client(async_openai::Client::with_config(
+                OpenAIConfig::new()
+                    .with_api_base(mock_server.uri())
+                    .with_api_key("test"),
+            ))
+            .default_embed_model("text-embedding-3-small")
+            .build()
+            .unwrap();
+
+        let batch_embed = BatchEmbed::builder()
+            .client(openai)
+            .poll_interval(Duration::from_millis(1))
+            .build()
+            .unwrap();
+
+        let embeddings = batch_embed
+            .embed(vec!["first".to_string(), "second".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    }
+}
@@ -1,27 +1,35 @@
 use anyhow::{Context as _, Result};
 use async_openai::types::{
     ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
+    ChatCompletionRequestMessageContentPartImage, ChatCompletionRequestMessageContentPartText,
     ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestToolMessageArgs,
-    ChatCompletionRequestUserMessageArgs, ChatCompletionTool, ChatCompletionToolArgs,
-    ChatCompletionToolType, CreateChatCompletionRequestArgs, FunctionCall, FunctionObjectArgs,
+    ChatCompletionRequestUserMessageArgs, ChatCompletionRequestUserMessageContent,
+    ChatCompletionRequestUserMessageContentPart, ChatCompletionTokenLogprob, ChatCompletionTool,
+    ChatCompletionToolArgs, ChatCompletionToolType, CompletionUsage,
+    CreateChatCompletionRequestArgs, FunctionCall, FunctionObjectArgs, ImageUrl,
 };
 use async_trait::async_trait;
 use itertools::Itertools;
 use serde_json::json;
 use swiftide_core::chat_completion::{
-    errors::ChatCompletionError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
-    ChatMessage, ToolCall, ToolSpec,
+    errors::LanguageModelError, ChatCompletion, ChatCompletionRequest, ChatCompletionResponse,
+    ChatMessage, ContentPart, TokenLogprob, ToolCall, ToolSpec, TopLogprob, Usage,
 };
 
-use super::OpenAI;
+use super::{
+    openai_error_to_language_model_error, structured_prompt::response_format_from_schema,
+    GenericOpenAI,
+};
 
 #[async_trait]
-impl ChatCompletion for OpenAI {
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    ChatCompletion for GenericOpenAI<C>
+{
     #[tracing::instrument(skip_all)]
     async fn complete(
         &self,
         request: &ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse, ChatCompletionError> {
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
         let model = self
             .default_options
             .prompt_model
@@ -32,6 +40,7 @@ impl ChatCompletion for OpenAI {
             .messages()
             .iter()
             .map(message_to_openai)
+            .flatten_ok()
             .collect::<Result<Vec<_>>>()?;
 
         // Build the request to be sent to the OpenAI API.
@@ -53,9 +62,15 @@ impl ChatCompletion for OpenAI {
                 .parallel_tool_calls(true);
         }
 
+        if let Some(schema) = request.response_schema() {
+            openai_request.response_format(response_format_from_schema(schema)?);
+        }
+
+        self.default_options.apply_to_request(&mut openai_request);
+
         let request = openai_request
             .build()
-            .map_err(|e| ChatCompletionError::LLM(Box::new(e)))?;
+            .map_err(LanguageModelError::permanent)?;
 
         tracing::debug!(
             model = &model,
@@ -68,7 +83,7 @@ impl ChatCompletion for OpenAI {
             .chat()
             .create(request)
             .await
-            .map_err(|e| ChatCompletionError::LLM(Box::new(e)))?;
+            .map_err(openai_error_to_language_model_error)?;
 
         tracing::debug!(
             response = serde_json::to_string_pretty(&response).expect("infallible"),
@@ -101,24 +116,62 @@ impl ChatCompletion for OpenAI {
                             .collect_vec()
                     }),
             )
+            .maybe_logprobs(
+                response
+                    .choices
+                    .first()
+                    .and_then(|choice| choice.logprobs.as_ref())
+                    .and_then(|logprobs| logprobs.content.as_ref())
+                    .map(|content| content.iter().map(logprob_from_openai).collect_vec()),
+            )
+            .maybe_usage(response.usage.as_ref().map(usage_from_openai))
             .build()
-            .map_err(ChatCompletionError::from)
+            .map_err(LanguageModelError::from)
+    }
+}
+
+fn logprob_from_openai(logprob: &ChatCompletionTokenLogprob) -> TokenLogprob {
+    TokenLogprob {
+        token: logprob.token.clone(),
+        logprob: logprob.logprob,
+        top_logprobs: logprob
+            .top_logprobs
+            .iter()
+            .map(|top| TopLogprob {
+                token: top.token.clone(),
+                logprob: top.logprob,
+            })
+            .collect(),
+    }
+}
+
+/// Maps the usage of `OpenAI` compatible apis, including prompt tokens served from the
+/// provider's prompt cache if reported
+pub(crate) fn usage_from_openai(usage: &CompletionUsage) -> Usage {
+    Usage {
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        total_tokens: usage.total_tokens,
+        cached_prompt_tokens: usage
+            .prompt_tokens_details
+            .as_ref()
+            .and_then(|details| details.cached_tokens),
     }
 }
 
 // TODO: Maybe just into the whole thing? Types are not in this crate
 
-fn tools_to_openai(spec: &ToolSpec) -> Result<ChatCompletionTool> {
+pub(crate) fn tools_to_openai(spec: &ToolSpec) -> Result<ChatCompletionTool> {
+    // Strict mode requires every nested object to disallow additional properties, which
+    // generated schemas do not, and does not support every constraint
+    let strict = spec.parameters.iter().all(|param| {
+        param.schema.is_none() && param.default.is_none() && param.constraints.is_empty()
+    });
+
     let mut properties = serde_json::Map::new();
 
     for param in &spec.parameters {
-        properties.insert(
-            param.name.to_string(),
-            json!({
-                "type": "string",
-                "description": param.description,
-            }),
-        );
+        properties.insert(param.name.to_string(), param.json_schema());
     }
 
     ChatCompletionToolArgs::default()
@@ -126,7 +179,7 @@ fn tools_to_openai(spec: &ToolSpec) -> Result<ChatCompletionTool> {
         .function(FunctionObjectArgs::default()
             .name(spec.name)
             .description(spec.description)
-            .strict(true)
+            .strict(strict)
             .parameters(json!({
                 "type": "object",
                 "properties": properties,
@@ -136,14 +189,24 @@ fn tools_to_openai(spec: &ToolSpec) -> Result<ChatCompletionTool> {
         .map_err(anyhow::Error::from)
 }
 
-fn message_to_openai(
+/// Converts a chat message to `OpenAI` messages
+///
+/// `OpenAI` only accepts text in tool messages, images returned by a tool are added in a
+/// separate user message.
+pub(crate) fn message_to_openai(
     message: &ChatMessage,
-) -> Result<async_openai::types::ChatCompletionRequestMessage> {
+) -> Result<Vec<async_openai::types::ChatCompletionRequestMessage>> {
     let openai_message = match message {
         ChatMessage::User(msg) => ChatCompletionRequestUserMessageArgs::default()
             .content(msg.as_str())
             .build()?
             .into(),
+        ChatMessage::UserWithParts(parts) => ChatCompletionRequestUserMessageArgs::default()
+            .content(ChatCompletionRequestUserMessageContent::Array(
+                parts.iter().map(content_part_to_openai).collect(),
+            ))
+            .build()?
+            .into(),
         ChatMessage::System(msg) => ChatCompletionRequestSystemMessageArgs::default()
             .content(msg.as_str())
             .build()?
@@ -153,11 +216,27 @@ fn message_to_openai(
             .build()?
             .into(),
         ChatMessage::ToolOutput(tool_call, tool_output) => {
+            if let Some(image) = tool_output.image() {
+                return Ok(vec![
+                    ChatCompletionRequestToolMessageArgs::default()
+                        .content("The tool returned an image, it is attached in the next message")
+                        .tool_call_id(tool_call.id())
+                        .build()?
+                        .into(),
+                    ChatCompletionRequestUserMessageArgs::default()
+                        .content(ChatCompletionRequestUserMessageContent::Array(vec![
+                            content_part_to_openai(&ContentPart::Image(image.clone())),
+                        ]))
+                        .build()?
+                        .into(),
+                ]);
+            }
+
             let Some(content) = tool_output.content() else {
-                return Ok(ChatCompletionRequestToolMessageArgs::default()
+                return Ok(vec![ChatCompletionRequestToolMessageArgs::default()
                     .tool_call_id(tool_call.id())
                     .build()?
-                    .into());
+                    .into()]);
             };
 
             ChatCompletionRequestToolMessageArgs::default()
@@ -193,5 +272,92 @@ fn message_to_openai(
         }
     };
 
-    Ok(openai_message)
+    Ok(vec![openai_message])
+}
+
+fn content_part_to_openai(part: &ContentPart) -> ChatCompletionRequestUserMessageContentPart {
+    match part {
+        ContentPart::Text(text) => ChatCompletionRequestUserMessageContentPart::Text(
+            ChatCompletionRequestMessageContentPartText { text: text.clone() },
+        ),
+        ContentPart::Image(image) => ChatCompletionRequestUserMessageContentPart::ImageUrl(
+            ChatCompletionRequestMessageContentPartImage {
+                image_url: ImageUrl {
+                    url: image.to_url(),
+                    detail: None,
+                },
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use swiftide_core::chat_completion::ImageContent;
+
+    #[test]
+    fn test_user_message_with_image_parts() {
+        let message = ChatMessage::new_user_with_parts(vec![
+            ContentPart::from("What is in this image?"),
+            ImageContent::from_base64("image/png", "aGVsbG8=").into(),
+        ]);
+
+        let openai_messages = message_to_openai(&message).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&openai_messages).unwrap(),
+            serde_json::json!([{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "What is in this image?" },
+                    { "type": "image_url", "image_url": { "url": "data:image/png;base64,aGVsbG8=" } }
+                ]
+            }])
+        );
+    }
+
+    #[test]
+    fn test_usage_with_cached_tokens() {
+        let usage: CompletionUsage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 100,
+            "completion_tokens": 10,
+            "total_tokens": 110,
+            "prompt_tokens_details": { "cached_tokens": 80 }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            usage_from_openai(&usage),
+            Usage {
+                prompt_tokens: 100,
+                completion_tokens: 10,
+                total_tokens: 110,
+                cached_prompt_tokens: Some(80),
+            }
+        );
+    }
+
+    #[test]
+    fn test_logprobs() {
+        let logprob: ChatCompletionTokenLogprob = serde_json::from_value(serde_json::json!({
+            "token": "Paris",
+            "logprob": -0.5,
+            "bytes": null,
+            "top_logprobs": [{ "token": "Paris", "logprob": -0.5, "bytes": null }]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            logprob_from_openai(&logprob),
+            TokenLogprob {
+                token: "Paris".to_string(),
+                logprob: -0.5,
+                top_logprobs: vec![TopLogprob {
+                    token: "Paris".to_string(),
+                    logprob: -0.5,
+                }],
+            }
+        );
+    }
 }
@@ -2,13 +2,17 @@ use anyhow::{Context as _, Result};
 use async_openai::types::CreateEmbeddingRequestArgs;
 use async_trait::async_trait;
 
-use swiftide_core::{EmbeddingModel, Embeddings};
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError, EmbeddingModel, EmbeddingPrefixes, Embeddings,
+};
 
-use super::OpenAI;
+use super::{openai_error_to_language_model_error, GenericOpenAI};
 
 #[async_trait]
-impl EmbeddingModel for OpenAI {
-    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    EmbeddingModel for GenericOpenAI<C>
+{
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings, LanguageModelError> {
         let model = self
             .default_options
             .embed_model
@@ -18,18 +22,19 @@ impl EmbeddingModel for OpenAI {
         let request = CreateEmbeddingRequestArgs::default()
             .model(model)
             .input(&input)
-            .build()?;
+            .build()
+            .map_err(LanguageModelError::permanent)?;
         tracing::debug!(
             num_chunks = input.len(),
             model = &model,
             "[Embed] Request to openai"
         );
         let response = self
-            .client
+            .embed_client()
             .embeddings()
             .create(request)
             .await
-            .context("Request to OpenAI Failed")?;
+            .map_err(openai_error_to_language_model_error)?;
 
         let num_embeddings = response.data.len();
         tracing::debug!(num_embeddings = num_embeddings, "[Embed] Response openai");
@@ -37,4 +42,8 @@ impl EmbeddingModel for OpenAI {
         // WARN: Naively assumes that the order is preserved. Might not always be the case.
         Ok(response.data.into_iter().map(|d| d.embedding).collect())
     }
+
+    fn prefixes(&self) -> Option<&EmbeddingPrefixes> {
+        self.default_options.embed_prefixes.as_ref()
+    }
 }
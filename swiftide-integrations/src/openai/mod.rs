@@ -1,13 +1,31 @@
 //! This module provides integration with `OpenAI`'s API, enabling the use of language models and embeddings within the Swiftide project.
 //! It includes the `OpenAI` struct for managing API clients and default options for embedding and prompt models.
 //! The module is conditionally compiled based on the "openai" feature flag.
+//!
+//! Providers with an `OpenAI` compatible api can reuse this integration via [`GenericOpenAI`],
+//! with a custom [`async_openai::config::Config`].
 
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    types::{CreateChatCompletionRequestArgs, Stop},
+};
 use derive_builder::Builder;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use swiftide_core::{chat_completion::errors::LanguageModelError, EmbeddingPrefixes};
 
+mod batch_embed;
 mod chat_completion;
 mod embed;
+mod model_provider;
 mod simple_prompt;
+mod stream_accumulator;
+mod structured_prompt;
+
+pub use batch_embed::{BatchEmbed, BatchEmbedBuilder};
+pub(crate) use chat_completion::{message_to_openai, tools_to_openai, usage_from_openai};
+pub(crate) use model_provider::list_models;
+pub(crate) use stream_accumulator::StreamAccumulator;
 
 /// The `OpenAI` struct encapsulates an `OpenAI` client and default options for embedding and prompt models.
 /// It uses the `Builder` pattern for flexible and customizable instantiation.
@@ -29,16 +47,42 @@ mod simple_prompt;
 ///     .default_prompt_model("gpt-4")
 ///     .client(async_openai::Client::with_config(async_openai::config::OpenAIConfig::default().with_api_key("my-api-key")))
 ///     .build().unwrap();
+///
+/// // Create an OpenAI client for a project, behind a proxy and a gateway.
+/// let openai = OpenAI::builder()
+///     .default_prompt_model("gpt-4")
+///     .organization("org-123")
+///     .project("proj-123")
+///     .api_base("https://gateway.example.com/v1")
+///     .proxy("http://proxy.example.com:8080")
+///     .timeout(std::time::Duration::from_secs(60))
+///     .build().unwrap();
 ///```
+pub type OpenAI = GenericOpenAI<OpenAIConfig>;
+pub type OpenAIBuilder = GenericOpenAIBuilder<OpenAIConfig>;
+
+/// A generic `OpenAI` client that works with any provider exposing an `OpenAI` compatible api.
+///
+/// The provider is configured through the [`async_openai::config::Config`] it is generic over,
+/// i.e. [`OpenAI`] is `GenericOpenAI<OpenAIConfig>`.
 #[derive(Debug, Builder, Clone)]
 #[builder(setter(into, strip_option))]
-pub struct OpenAI {
+pub struct GenericOpenAI<
+    C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static = OpenAIConfig,
+> {
     /// The `OpenAI` client, wrapped in an `Arc` for thread-safe reference counting.
-    /// Defaults to a new instance of `async_openai::Client`.
-    #[builder(default = "Arc::new(async_openai::Client::new())", setter(custom))]
-    client: Arc<async_openai::Client<async_openai::config::OpenAIConfig>>,
+    /// Defaults to a new instance of `async_openai::Client`, with the networking settings of the
+    /// builder.
+    #[builder(
+        field(ty = "ClientSettings<C>", build = "self.client.build_client()?"),
+        setter(custom)
+    )]
+    client: Arc<async_openai::Client<C>>,
+    /// Client for embeddings, if they are served from another base url
+    #[builder(setter(skip), default = "self.client.build_embed_client()?")]
+    embed_client: Option<Arc<async_openai::Client<C>>>,
     /// Default options for embedding and prompt models.
-    #[builder(default)]
+    #[builder(default, setter(custom))]
     default_options: Options,
 }
 
@@ -50,9 +94,35 @@ pub struct Options {
     /// The default embedding model to use, if specified.
     #[builder(default)]
     pub embed_model: Option<String>,
+    /// Prefixes for queries and documents, for asymmetric embedding models, e.g. e5
+    #[builder(default)]
+    pub embed_prefixes: Option<EmbeddingPrefixes>,
     /// The default prompt model to use, if specified.
     #[builder(default)]
     pub prompt_model: Option<String>,
+    /// Nucleus sampling, only tokens within the top `top_p` probability mass are considered.
+    #[builder(default)]
+    pub top_p: Option<f32>,
+    /// Penalizes tokens based on how often they already occur in the completion, between -2.0 and
+    /// 2.0.
+    #[builder(default)]
+    pub frequency_penalty: Option<f32>,
+    /// Modifies the likelihood of tokens, mapping token ids to a bias between -100 and 100.
+    #[builder(default)]
+    pub logit_bias: Option<HashMap<String, serde_json::Value>>,
+    /// Sequences where the model stops generating further tokens.
+    #[builder(default)]
+    pub stop: Option<Vec<String>>,
+    /// How many completions to generate. Only the first is used, but some providers require it.
+    #[builder(default)]
+    pub n: Option<u8>,
+    /// Returns the log probabilities of the generated tokens on chat completions.
+    #[builder(default)]
+    pub logprobs: Option<bool>,
+    /// Number of most likely tokens to return at each position, between 0 and 20. Requires
+    /// `logprobs`.
+    #[builder(default)]
+    pub top_logprobs: Option<u8>,
 }
 
 impl Options {
@@ -60,16 +130,120 @@ impl Options {
     pub fn builder() -> OptionsBuilder {
         OptionsBuilder::default()
     }
+
+    /// Merges the options with another set of options, values set in `other` take precedence.
+    pub fn merge(&mut self, other: &Options) {
+        if let Some(embed_model) = &other.embed_model {
+            self.embed_model = Some(embed_model.clone());
+        }
+        if let Some(embed_prefixes) = &other.embed_prefixes {
+            self.embed_prefixes = Some(embed_prefixes.clone());
+        }
+        if let Some(prompt_model) = &other.prompt_model {
+            self.prompt_model = Some(prompt_model.clone());
+        }
+        if let Some(top_p) = other.top_p {
+            self.top_p = Some(top_p);
+        }
+        if let Some(frequency_penalty) = other.frequency_penalty {
+            self.frequency_penalty = Some(frequency_penalty);
+        }
+        if let Some(logit_bias) = &other.logit_bias {
+            self.logit_bias = Some(logit_bias.clone());
+        }
+        if let Some(stop) = &other.stop {
+            self.stop = Some(stop.clone());
+        }
+        if let Some(n) = other.n {
+            self.n = Some(n);
+        }
+        if let Some(logprobs) = other.logprobs {
+            self.logprobs = Some(logprobs);
+        }
+        if let Some(top_logprobs) = other.top_logprobs {
+            self.top_logprobs = Some(top_logprobs);
+        }
+    }
+
+    /// Sets the request options on a chat completion request, leaving the provider defaults for
+    /// options that are not set.
+    pub(crate) fn apply_to_request(&self, request: &mut CreateChatCompletionRequestArgs) {
+        if let Some(top_p) = self.top_p {
+            request.top_p(top_p);
+        }
+        if let Some(frequency_penalty) = self.frequency_penalty {
+            request.frequency_penalty(frequency_penalty);
+        }
+        if let Some(logit_bias) = &self.logit_bias {
+            request.logit_bias(logit_bias.clone());
+        }
+        if let Some(stop) = &self.stop {
+            request.stop(Stop::StringArray(stop.clone()));
+        }
+        if let Some(n) = self.n {
+            request.n(n);
+        }
+        if let Some(logprobs) = self.logprobs {
+            request.logprobs(logprobs);
+        }
+        if let Some(top_logprobs) = self.top_logprobs {
+            request.top_logprobs(top_logprobs);
+        }
+    }
 }
 
-impl OpenAI {
-    /// Creates a new `OpenAIBuilder` for constructing `OpenAI` instances.
-    pub fn builder() -> OpenAIBuilder {
-        OpenAIBuilder::default()
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static> Default
+    for GenericOpenAI<C>
+{
+    fn default() -> Self {
+        Self {
+            client: Arc::new(async_openai::Client::with_config(C::default())),
+            embed_client: None,
+            default_options: Options::default(),
+        }
+    }
+}
+
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    GenericOpenAI<C>
+{
+    /// Creates a new `GenericOpenAIBuilder` for constructing `GenericOpenAI` instances.
+    pub fn builder() -> GenericOpenAIBuilder<C> {
+        GenericOpenAIBuilder::default()
+    }
+
+    /// Returns the underlying `async_openai` client
+    pub fn client(&self) -> &Arc<async_openai::Client<C>> {
+        &self.client
+    }
+
+    /// Returns the client for embeddings, which is the underlying client unless embeddings are
+    /// served from another base url
+    pub(crate) fn embed_client(&self) -> &Arc<async_openai::Client<C>> {
+        self.embed_client.as_ref().unwrap_or(&self.client)
+    }
+
+    /// Returns the default options
+    pub fn options(&self) -> &Options {
+        &self.default_options
+    }
+
+    /// Sets a default prompt model to use when prompting
+    pub fn with_default_prompt_model(&mut self, model: impl Into<String>) -> &mut Self {
+        self.default_options.prompt_model = Some(model.into());
+        self
+    }
+
+    /// Sets a default embedding model to use when embedding
+    pub fn with_default_embed_model(&mut self, model: impl Into<String>) -> &mut Self {
+        self.default_options.embed_model = Some(model.into());
+        self
     }
 }
 
-impl OpenAIBuilder {
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    GenericOpenAIBuilder<C>
+{
     /// Sets the `OpenAI` client for the `OpenAI` instance.
     ///
     /// # Parameters
@@ -77,11 +251,28 @@ impl OpenAIBuilder {
     ///
     /// # Returns
     /// A mutable reference to the `OpenAIBuilder`.
-    pub fn client(
-        &mut self,
-        client: async_openai::Client<async_openai::config::OpenAIConfig>,
-    ) -> &mut Self {
-        self.client = Some(Arc::new(client));
+    pub fn client(&mut self, client: async_openai::Client<C>) -> &mut Self {
+        self.client.client = Some(Arc::new(client));
+        self
+    }
+
+    /// Sets the default options for the `OpenAI` instance.
+    ///
+    /// Options that are already set, i.e. with `default_prompt_model`, are kept unless they are
+    /// also set in `options`.
+    ///
+    /// # Parameters
+    /// - `options`: The options to merge into the default options.
+    ///
+    /// # Returns
+    /// A mutable reference to the `OpenAIBuilder`.
+    pub fn default_options(&mut self, options: impl Into<Options>) -> &mut Self {
+        let options = options.into();
+        if let Some(default_options) = self.default_options.as_mut() {
+            default_options.merge(&options);
+        } else {
+            self.default_options = Some(options);
+        }
         self
     }
 
@@ -124,6 +315,167 @@ impl OpenAIBuilder {
     }
 }
 
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    GenericOpenAIBuilder<C>
+{
+    /// Sets a timeout on requests
+    ///
+    /// Ignored if a client is set directly.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.client.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends all requests through a proxy, e.g. `http://proxy.internal:8080`
+    ///
+    /// Ignored if a client is set directly.
+    pub fn proxy(&mut self, proxy: impl Into<String>) -> &mut Self {
+        self.client.proxy = Some(proxy.into());
+        self
+    }
+}
+
+impl GenericOpenAIBuilder<OpenAIConfig> {
+    /// Sets the organization that requests are attributed to, sent as `OpenAI-Organization`
+    ///
+    /// Ignored if a client is set directly.
+    pub fn organization(&mut self, organization: impl Into<String>) -> &mut Self {
+        let organization = organization.into();
+        self.client
+            .update_configs(|config| config.with_org_id(organization.clone()));
+        self
+    }
+
+    /// Sets the project that requests are attributed to, sent as `OpenAI-Project`
+    ///
+    /// Ignored if a client is set directly.
+    pub fn project(&mut self, project: impl Into<String>) -> &mut Self {
+        let project = project.into();
+        self.client
+            .update_configs(|config| config.with_project_id(project.clone()));
+        self
+    }
+
+    /// Sets the api key, defaults to the `OPENAI_API_KEY` environment variable
+    ///
+    /// Ignored if a client is set directly.
+    pub fn api_key(&mut self, api_key: impl Into<String>) -> &mut Self {
+        let api_key = api_key.into();
+        self.client
+            .update_configs(|config| config.with_api_key(api_key.clone()));
+        self
+    }
+
+    /// Sets the base url for all requests, e.g. for a gateway
+    ///
+    /// Ignored if a client is set directly.
+    pub fn api_base(&mut self, api_base: impl Into<String>) -> &mut Self {
+        let config = self.client.config.take().unwrap_or_default();
+        self.client.config = Some(config.with_api_base(api_base));
+        self
+    }
+
+    /// Sets the base url for embedding requests only, other requests use the default or
+    /// [`GenericOpenAIBuilder::api_base`]
+    ///
+    /// Ignored if a client is set directly.
+    pub fn embed_api_base(&mut self, api_base: impl Into<String>) -> &mut Self {
+        let config = self
+            .client
+            .embed_config
+            .take()
+            .or_else(|| self.client.config.clone())
+            .unwrap_or_default();
+        self.client.embed_config = Some(config.with_api_base(api_base));
+        self
+    }
+}
+
+/// Networking settings the builder creates the `async_openai` clients from
+#[derive(Clone, Default)]
+struct ClientSettings<C> {
+    /// A client that was set directly, other settings are ignored
+    client: Option<Arc<async_openai::Client<C>>>,
+    config: Option<C>,
+    embed_config: Option<C>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+}
+
+impl<C: async_openai::config::Config + Default + Clone> ClientSettings<C> {
+    /// Applies a change to the config of all clients
+    fn update_configs(&mut self, update: impl Fn(C) -> C) {
+        self.config = Some(update(self.config.take().unwrap_or_default()));
+        self.embed_config = self.embed_config.take().map(&update);
+    }
+
+    fn http_client(&self) -> Result<Option<reqwest::Client>, String> {
+        if self.timeout.is_none() && self.proxy.is_none() {
+            return Ok(None);
+        }
+
+        let mut http_client = reqwest::Client::builder();
+        if let Some(timeout) = self.timeout {
+            http_client = http_client.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            http_client =
+                http_client.proxy(reqwest::Proxy::all(proxy).map_err(|err| err.to_string())?);
+        }
+
+        http_client.build().map(Some).map_err(|err| err.to_string())
+    }
+
+    fn client_with_config(&self, config: C) -> Result<Arc<async_openai::Client<C>>, String> {
+        let client = async_openai::Client::with_config(config);
+
+        Ok(Arc::new(match self.http_client()? {
+            Some(http_client) => client.with_http_client(http_client),
+            None => client,
+        }))
+    }
+
+    fn build_client(&self) -> Result<Arc<async_openai::Client<C>>, String> {
+        if let Some(client) = &self.client {
+            return Ok(client.clone());
+        }
+
+        self.client_with_config(self.config.clone().unwrap_or_default())
+    }
+
+    fn build_embed_client(&self) -> Result<Option<Arc<async_openai::Client<C>>>, String> {
+        if self.client.is_some() {
+            return Ok(None);
+        }
+
+        self.embed_config
+            .clone()
+            .map(|config| self.client_with_config(config))
+            .transpose()
+    }
+}
+
+/// Classifies errors from `OpenAI` compatible apis
+///
+/// Network errors, rate limits and server errors are transient, and might succeed on a retry.
+pub(crate) fn openai_error_to_language_model_error(err: OpenAIError) -> LanguageModelError {
+    match &err {
+        OpenAIError::Reqwest(_) | OpenAIError::StreamError(_) => LanguageModelError::transient(err),
+        OpenAIError::ApiError(api_error) => {
+            if api_error.code.as_deref() == Some("context_length_exceeded") {
+                LanguageModelError::context_length_exceeded(err)
+            } else if api_error.code.as_deref() == Some("rate_limit_exceeded")
+                || api_error.r#type.as_deref() == Some("server_error")
+            {
+                LanguageModelError::transient(err)
+            } else {
+                LanguageModelError::permanent(err)
+            }
+        }
+        _ => LanguageModelError::permanent(err),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -159,4 +511,131 @@ mod test {
             Some("gpt-3".to_string())
         );
     }
+
+    #[test]
+    fn test_building_via_default_models() {
+        let mut openai = OpenAI::default();
+
+        assert!(openai.default_options.prompt_model.is_none());
+
+        openai.with_default_prompt_model("gpt-4");
+        openai.with_default_embed_model("gpt-3");
+        assert_eq!(
+            openai.default_options.prompt_model,
+            Some("gpt-4".to_string())
+        );
+        assert_eq!(
+            openai.default_options.embed_model,
+            Some("gpt-3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_default_options_are_merged() {
+        let openai = OpenAI::builder()
+            .default_prompt_model("gpt-4")
+            .default_options(Options::builder().top_p(0.5).build().unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            openai.default_options.prompt_model,
+            Some("gpt-4".to_string())
+        );
+        assert_eq!(openai.default_options.top_p, Some(0.5));
+    }
+
+    #[test]
+    fn test_networking_settings() {
+        use async_openai::config::Config as _;
+
+        let openai = OpenAI::builder()
+            .api_base("https://gateway.internal/v1")
+            .embed_api_base("https://embeddings.internal/v1")
+            .organization("org-1")
+            .project("proj-1")
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap();
+
+        let config = openai.client().config();
+        assert_eq!(config.api_base(), "https://gateway.internal/v1");
+        assert_eq!(config.headers()["OpenAI-Organization"], "org-1");
+        assert_eq!(config.headers()["OpenAI-Project"], "proj-1");
+
+        let embed_config = openai.embed_client().config();
+        assert_eq!(embed_config.api_base(), "https://embeddings.internal/v1");
+        assert_eq!(embed_config.headers()["OpenAI-Organization"], "org-1");
+    }
+
+    #[test]
+    fn test_client_set_directly_takes_precedence() {
+        use async_openai::config::Config as _;
+
+        let openai = OpenAI::builder()
+            .embed_api_base("https://embeddings.internal/v1")
+            .client(async_openai::Client::with_config(
+                OpenAIConfig::default().with_api_base("https://custom.internal/v1"),
+            ))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            openai.embed_client().config().api_base(),
+            "https://custom.internal/v1"
+        );
+    }
+
+    #[test]
+    fn test_invalid_proxy_errors() {
+        assert!(OpenAI::builder().proxy("not a url").build().is_err());
+    }
+
+    #[test]
+    fn test_applies_options_to_request() {
+        let options = Options::builder()
+            .top_p(0.5)
+            .frequency_penalty(1.0)
+            .logit_bias(HashMap::from([("50256".to_string(), (-100).into())]))
+            .stop(vec!["\n".to_string()])
+            .n(1)
+            .build()
+            .unwrap();
+
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request.model("gpt-4").messages(vec![]);
+        options.apply_to_request(&mut request);
+        let request = serde_json::to_value(request.build().unwrap()).unwrap();
+
+        assert_eq!(request["top_p"], 0.5);
+        assert_eq!(request["frequency_penalty"], 1.0);
+        assert_eq!(request["logit_bias"], serde_json::json!({ "50256": -100 }));
+        assert_eq!(request["stop"], serde_json::json!(["\n"]));
+        assert_eq!(request["n"], 1);
+    }
+
+    #[test]
+    fn test_classifies_openai_errors() {
+        let api_error = |code: &str| {
+            OpenAIError::ApiError(async_openai::error::ApiError {
+                message: "error".to_string(),
+                r#type: None,
+                param: None,
+                code: Some(code.to_string()),
+            })
+        };
+
+        assert!(matches!(
+            openai_error_to_language_model_error(api_error("context_length_exceeded")),
+            LanguageModelError::ContextLengthExceeded(_)
+        ));
+        assert!(matches!(
+            openai_error_to_language_model_error(api_error("rate_limit_exceeded")),
+            LanguageModelError::TransientError(_)
+        ));
+        assert!(matches!(
+            openai_error_to_language_model_error(api_error("invalid_api_key")),
+            LanguageModelError::PermanentError(_)
+        ));
+    }
 }
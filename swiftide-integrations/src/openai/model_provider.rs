@@ -0,0 +1,115 @@
+//! Lists the models of `OpenAI` and `OpenAI` compatible apis
+use async_openai::{config::Config, error::OpenAIError, types::Model};
+use async_trait::async_trait;
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError,
+    model_provider::{ModelInfo, ModelProvider},
+};
+
+use super::{openai_error_to_language_model_error, GenericOpenAI};
+
+#[async_trait]
+impl<C: Config + Default + std::fmt::Debug + Send + Sync + 'static> ModelProvider
+    for GenericOpenAI<C>
+{
+    #[tracing::instrument(skip_all, err)]
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LanguageModelError> {
+        list_models(&self.client, openai_error_to_language_model_error).await
+    }
+}
+
+/// Lists the models with the `/models` endpoint, shared by `OpenAI` compatible providers
+pub(crate) async fn list_models<C: Config>(
+    client: &async_openai::Client<C>,
+    map_err: fn(OpenAIError) -> LanguageModelError,
+) -> Result<Vec<ModelInfo>, LanguageModelError> {
+    let response = client.models().list().await.map_err(map_err)?;
+
+    Ok(response
+        .data
+        .into_iter()
+        .map(model_info_from_openai)
+        .collect())
+}
+
+fn model_info_from_openai(model: Model) -> ModelInfo {
+    ModelInfo {
+        id: model.id,
+        owned_by: Some(model.owned_by).filter(|owned_by| !owned_by.is_empty()),
+        created: Some(u64::from(model.created)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::openai::OpenAI;
+    use async_openai::config::OpenAIConfig;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn openai(mock_server: &MockServer) -> OpenAI {
+        let config = OpenAIConfig::new()
+            .with_api_base(mock_server.uri())
+            .with_api_key("test");
+
+        OpenAI::builder()
+            .client(async_openai::Client::with_config(config))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_list_models() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [{
+                    "id": "gpt-4o-mini",
+                    "object": "model",
+                    "created": 1_721_172_741,
+                    "owned_by": "system"
+                }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let models = openai(&mock_server).list_models().await.unwrap();
+
+        assert_eq!(
+            models,
+            vec![ModelInfo {
+                id: "gpt-4o-mini".into(),
+                owned_by: Some("system".into()),
+                created: Some(1_721_172_741),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_check_fails_on_invalid_api_key() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": {
+                    "message": "Incorrect API key provided",
+                    "type": "invalid_request_error",
+                    "code": "invalid_api_key"
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let err = openai(&mock_server).health_check().await.unwrap_err();
+
+        assert!(matches!(err, LanguageModelError::PermanentError(_)));
+    }
+}
@@ -3,21 +3,26 @@
 //! and generating responses as part of the Swiftide system.
 use async_openai::types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs};
 use async_trait::async_trait;
-use swiftide_core::{prompt::Prompt, util::debug_long_utf8, SimplePrompt};
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError, prompt::Prompt, util::debug_long_utf8,
+    SimplePrompt,
+};
 
-use super::OpenAI;
+use super::{openai_error_to_language_model_error, GenericOpenAI};
 use anyhow::{Context as _, Result};
 
 /// The `SimplePrompt` trait defines a method for sending a prompt to an AI model and receiving a response.
 #[async_trait]
-impl SimplePrompt for OpenAI {
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    SimplePrompt for GenericOpenAI<C>
+{
     /// Sends a prompt to the OpenAI API and returns the response content.
     ///
     /// # Parameters
     /// - `prompt`: A string slice that holds the prompt to be sent to the OpenAI API.
     ///
     /// # Returns
-    /// - `Result<String>`: On success, returns the content of the response as a `String`.
+    /// - `Result<String, LanguageModelError>`: On success, returns the content of the response as a `String`.
     ///   On failure, returns an error wrapped in a `Result`.
     ///
     /// # Errors
@@ -25,7 +30,7 @@ impl SimplePrompt for OpenAI {
     /// - Returns an error if the request to the OpenAI API fails.
     /// - Returns an error if the response does not contain the expected content.
     #[tracing::instrument(skip_all, err)]
-    async fn prompt(&self, prompt: Prompt) -> Result<String> {
+    async fn prompt(&self, prompt: Prompt) -> Result<String, LanguageModelError> {
         // Retrieve the model from the default options, returning an error if not set.
         let model = self
             .default_options
@@ -34,19 +39,23 @@ impl SimplePrompt for OpenAI {
             .context("Model not set")?;
 
         // Build the request to be sent to the OpenAI API.
-        let request = CreateChatCompletionRequestArgs::default()
+        let mut request = CreateChatCompletionRequestArgs::default()
             .model(model)
             .messages(vec![ChatCompletionRequestUserMessageArgs::default()
                 .content(prompt.render().await?)
-                .build()?
+                .build()
+                .map_err(LanguageModelError::permanent)?
                 .into()])
-            .build()?;
+            .to_owned();
+        self.default_options.apply_to_request(&mut request);
+        let request = request.build().map_err(LanguageModelError::permanent)?;
 
         // Log the request for debugging purposes.
         tracing::debug!(
             model = &model,
             messages = debug_long_utf8(
-                serde_json::to_string_pretty(&request.messages.first())?,
+                serde_json::to_string_pretty(&request.messages.first())
+                    .map_err(LanguageModelError::permanent)?,
                 100
             ),
             "[SimplePrompt] Request to openai"
@@ -57,7 +66,8 @@ impl SimplePrompt for OpenAI {
             .client
             .chat()
             .create(request)
-            .await?
+            .await
+            .map_err(openai_error_to_language_model_error)?
             .choices
             .remove(0)
             .message
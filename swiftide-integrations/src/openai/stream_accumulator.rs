@@ -0,0 +1,106 @@
+//! Collects the chunks of a streamed chat completion of an `OpenAI` compatible api
+use async_openai::types::ChatCompletionMessageToolCallChunk;
+use itertools::Itertools;
+use swiftide_core::chat_completion::{
+    errors::LanguageModelError, ChatCompletionResponse, ToolCall, Usage,
+};
+
+/// Collects the streamed message and tool calls into a full response
+#[derive(Default)]
+pub(crate) struct StreamAccumulator {
+    pub(crate) message: String,
+    /// Reasoning streamed before the answer, for providers that return it separately
+    pub(crate) reasoning_content: String,
+    /// Id, name and arguments of every tool call, in the order of their index
+    tool_calls: Vec<(String, String, String)>,
+    pub(crate) usage: Option<Usage>,
+}
+
+impl StreamAccumulator {
+    /// Tool calls are streamed in chunks, where only the first chunk of a call has the id and
+    /// name, and the arguments are split over all chunks
+    pub(crate) fn add_tool_calls(&mut self, chunks: Vec<ChatCompletionMessageToolCallChunk>) {
+        for chunk in chunks {
+            let index = chunk.index as usize;
+            if self.tool_calls.len() <= index {
+                self.tool_calls.resize_with(index + 1, Default::default);
+            }
+
+            let (id, name, args) = &mut self.tool_calls[index];
+            if let Some(chunk_id) = chunk.id {
+                *id = chunk_id;
+            }
+            if let Some(function) = chunk.function {
+                if let Some(chunk_name) = function.name {
+                    *name = chunk_name;
+                }
+                if let Some(chunk_args) = function.arguments {
+                    args.push_str(&chunk_args);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn response(
+        &self,
+        delta: Option<String>,
+    ) -> Result<ChatCompletionResponse, LanguageModelError> {
+        let tool_calls = self
+            .tool_calls
+            .iter()
+            .map(|(id, name, args)| {
+                ToolCall::builder()
+                    .id(id.as_str())
+                    .name(name.as_str())
+                    .args(args.as_str())
+                    .build()
+                    .expect("infallible")
+            })
+            .collect_vec();
+
+        ChatCompletionResponse::builder()
+            .maybe_message((!self.message.is_empty()).then(|| self.message.clone()))
+            .maybe_reasoning_content(
+                (!self.reasoning_content.is_empty()).then(|| self.reasoning_content.clone()),
+            )
+            .maybe_tool_calls((!tool_calls.is_empty()).then_some(tool_calls))
+            .maybe_usage(self.usage)
+            .maybe_delta(delta)
+            .build()
+            .map_err(LanguageModelError::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_accumulates_streamed_tool_calls() {
+        let chunk = |index, id: Option<&str>, name: Option<&str>, args: &str| {
+            serde_json::from_value::<ChatCompletionMessageToolCallChunk>(json!({
+                "index": index,
+                "id": id,
+                "type": "function",
+                "function": { "name": name, "arguments": args }
+            }))
+            .unwrap()
+        };
+
+        let mut accumulated = StreamAccumulator::default();
+        accumulated.add_tool_calls(vec![chunk(0, Some("call_1"), Some("get_weather"), "{\"ci")]);
+        accumulated.add_tool_calls(vec![chunk(0, None, None, "ty\": \"Paris\"}")]);
+        accumulated.message.push_str("Checking");
+
+        let response = accumulated.response(Some("Checking".into())).unwrap();
+
+        assert_eq!(response.message(), Some("Checking"));
+        assert_eq!(response.delta(), Some("Checking"));
+        assert_eq!(response.reasoning_content(), None);
+        let tool_call = &response.tool_calls().unwrap()[0];
+        assert_eq!(tool_call.id(), "call_1");
+        assert_eq!(tool_call.args(), Some("{\"city\": \"Paris\"}"));
+    }
+}
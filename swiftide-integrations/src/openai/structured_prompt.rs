@@ -0,0 +1,166 @@
+//! This module provides an implementation of the `StructuredPrompt` trait for the `OpenAI` struct.
+//!
+//! Uses `OpenAI` structured outputs in strict mode, the response is guaranteed to match the schema.
+use async_openai::types::{
+    ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs, ResponseFormat,
+    ResponseFormatJsonSchema,
+};
+use async_trait::async_trait;
+use schemars::schema::RootSchema;
+use serde_json::Value;
+use swiftide_core::{
+    chat_completion::errors::LanguageModelError, prompt::Prompt, util::debug_long_utf8,
+    StructuredPrompt,
+};
+
+use super::{openai_error_to_language_model_error, GenericOpenAI};
+use anyhow::{Context as _, Result};
+
+#[async_trait]
+impl<C: async_openai::config::Config + Default + std::fmt::Debug + Send + Sync + 'static>
+    StructuredPrompt for GenericOpenAI<C>
+{
+    #[tracing::instrument(skip_all, err)]
+    async fn structured_prompt_dyn(
+        &self,
+        prompt: Prompt,
+        schema: RootSchema,
+    ) -> Result<Value, LanguageModelError> {
+        let model = self
+            .default_options
+            .prompt_model
+            .as_ref()
+            .context("Model not set")?;
+
+        let mut request = CreateChatCompletionRequestArgs::default()
+            .model(model)
+            .messages(vec![ChatCompletionRequestUserMessageArgs::default()
+                .content(prompt.render().await?)
+                .build()
+                .map_err(LanguageModelError::permanent)?
+                .into()])
+            .response_format(response_format_from_schema(&schema)?)
+            .to_owned();
+        self.default_options.apply_to_request(&mut request);
+        let request = request.build().map_err(LanguageModelError::permanent)?;
+
+        tracing::debug!(
+            model = &model,
+            messages = debug_long_utf8(
+                serde_json::to_string_pretty(&request.messages.first())
+                    .map_err(LanguageModelError::permanent)?,
+                100
+            ),
+            "[StructuredPrompt] Request to openai"
+        );
+
+        let response = self
+            .client
+            .chat()
+            .create(request)
+            .await
+            .map_err(openai_error_to_language_model_error)?
+            .choices
+            .remove(0)
+            .message
+            .content
+            .take()
+            .context("Expected content in response")?;
+
+        tracing::debug!(
+            response = debug_long_utf8(&response, 100),
+            "[StructuredPrompt] Response from openai"
+        );
+
+        serde_json::from_str(&response)
+            .context("Expected json in response")
+            .map_err(LanguageModelError::from)
+    }
+}
+
+/// Builds a strict `json_schema` response format from a json schema
+///
+/// Strict mode requires all properties to be required and no additional properties on every
+/// object in the schema. Optional fields are still nullable through their schema.
+pub(crate) fn response_format_from_schema(schema: &RootSchema) -> Result<ResponseFormat> {
+    let name = schema
+        .schema
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.title.as_deref())
+        .unwrap_or("response")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect::<String>();
+
+    let mut schema = serde_json::to_value(schema)?;
+    make_strict(&mut schema);
+
+    Ok(ResponseFormat::JsonSchema {
+        json_schema: ResponseFormatJsonSchema {
+            description: None,
+            name,
+            schema: Some(schema),
+            strict: Some(true),
+        },
+    })
+}
+
+fn make_strict(schema: &mut Value) {
+    match schema {
+        Value::Object(map) => {
+            if let Some(Value::Object(properties)) = map.get("properties") {
+                let required = properties.keys().cloned().map(Value::String).collect();
+                map.insert("required".to_string(), Value::Array(required));
+                map.insert("additionalProperties".to_string(), Value::Bool(false));
+            }
+            map.values_mut().for_each(make_strict);
+        }
+        Value::Array(values) => values.iter_mut().for_each(make_strict),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use schemars::JsonSchema;
+    use serde_json::json;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Answer {
+        answer: String,
+        confidence: Option<f32>,
+        sources: Vec<Source>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Source {
+        url: String,
+    }
+
+    #[test]
+    fn test_response_format_is_strict() {
+        let ResponseFormat::JsonSchema { json_schema } =
+            response_format_from_schema(&schemars::schema_for!(Answer)).unwrap()
+        else {
+            panic!("Expected a json schema response format");
+        };
+
+        assert_eq!(json_schema.name, "Answer");
+        assert_eq!(json_schema.strict, Some(true));
+
+        let schema = json_schema.schema.unwrap();
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert_eq!(
+            schema["required"],
+            json!(["answer", "confidence", "sources"])
+        );
+        assert_eq!(
+            schema["definitions"]["Source"]["additionalProperties"],
+            json!(false)
+        );
+    }
+}
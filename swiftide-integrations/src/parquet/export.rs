@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow_array::{ArrayRef, RecordBatch, StringArray};
+use futures_util::TryStreamExt as _;
+use parquet::{
+    arrow::AsyncArrowWriter,
+    basic::{Compression, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use swiftide_core::{indexing::NodeRecord, Scroll};
+use tokio::io::AsyncWrite;
+
+/// The number of nodes written per record batch
+const BATCH_SIZE: usize = 1024;
+
+/// Writes every node of the storage to parquet, returning the number of exported nodes
+///
+/// The file has the string columns `id`, `path` and `chunk`, and `metadata`, `vectors` and
+/// `sparse_vectors` as json, so that any storage can be exported and restored with the same
+/// schema. Rows can be deserialized into a [`NodeRecord`] by parsing the json columns.
+///
+/// The `chunk` column can be loaded directly with the [`super::Parquet`] loader.
+///
+/// Columns are compressed with zstd, which parquet readers decompress transparently. Chunks and
+/// vectors are usually the bulk of a store and compress well.
+///
+/// # Errors
+///
+/// Errors if scrolling the storage or writing fails.
+pub async fn export_parquet(
+    storage: &dyn Scroll,
+    writer: impl AsyncWrite + Unpin + Send,
+) -> Result<usize> {
+    let schema = Arc::new(schema());
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = AsyncArrowWriter::try_new(writer, schema.clone(), Some(properties))?;
+    let mut nodes = storage.scroll().map_ok(NodeRecord::from);
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut count = 0;
+
+    while let Some(record) = nodes
+        .try_next()
+        .await
+        .with_context(|| format!("Failed to scroll {}", storage.name()))?
+    {
+        batch.push(record);
+
+        if batch.len() == BATCH_SIZE {
+            count += batch.len();
+            writer
+                .write(&record_batch(&schema, &std::mem::take(&mut batch))?)
+                .await?;
+        }
+    }
+
+    if !batch.is_empty() {
+        count += batch.len();
+        writer.write(&record_batch(&schema, &batch)?).await?;
+    }
+    writer.close().await?;
+
+    tracing::info!(count, storage = storage.name(), "Exported nodes to parquet");
+    Ok(count)
+}
+
+fn schema() -> Schema {
+    Schema::new(
+        [
+            "id",
+            "path",
+            "chunk",
+            "metadata",
+            "vectors",
+            "sparse_vectors",
+        ]
+        .map(|name| Field::new(name, DataType::Utf8, false))
+        .to_vec(),
+    )
+}
+
+fn record_batch(schema: &Arc<Schema>, records: &[NodeRecord]) -> Result<RecordBatch> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            records.iter().map(|record| record.id.to_string()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            records
+                .iter()
+                .map(|record| record.path.to_string_lossy().to_string()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            records.iter().map(|record| record.chunk.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            records
+                .iter()
+                .map(|record| serde_json::to_string(&record.metadata))
+                .collect::<Result<Vec<_>, _>>()?,
+        )),
+        Arc::new(StringArray::from_iter_values(
+            records
+                .iter()
+                .map(|record| serde_json::to_string(&record.vectors))
+                .collect::<Result<Vec<_>, _>>()?,
+        )),
+        Arc::new(StringArray::from_iter_values(
+            records
+                .iter()
+                .map(|record| serde_json::to_string(&record.sparse_vectors))
+                .collect::<Result<Vec<_>, _>>()?,
+        )),
+    ];
+
+    RecordBatch::try_new(schema.clone(), columns).context("Failed to build record batch")
+}
+
+#[cfg(test)]
+mod test {
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use swiftide_core::indexing::{EmbeddedField, IndexingStream, Node};
+
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct Nodes(Vec<Node>);
+
+    impl Scroll for Nodes {
+        fn scroll(&self) -> IndexingStream {
+            self.0.clone().into()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_parquet() {
+        let mut node = Node::new("chunk")
+            .with_metadata(("lang", "rust"))
+            .to_owned();
+        node.with_vectors([(EmbeddedField::Combined, vec![1.0, 2.0])]);
+
+        let dir = temp_dir::TempDir::new().unwrap();
+        let path = dir.child("export.parquet");
+        let file = tokio::fs::File::create(&path).await.unwrap();
+
+        let count = export_parquet(&Nodes(vec![node.clone(); 3]), file)
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+
+        let batch = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(batch.num_rows(), 3);
+
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .value(0)
+                .to_string()
+        };
+        assert_eq!(column("id"), node.id().to_string());
+        assert_eq!(column("chunk"), "chunk");
+        assert_eq!(column("metadata"), r#"{"lang":"rust"}"#);
+        assert_eq!(column("vectors"), r#"{"Combined":[1.0,2.0]}"#);
+    }
+}
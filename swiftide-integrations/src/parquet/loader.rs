@@ -3,7 +3,7 @@ use arrow_array::StringArray;
 use futures_util::StreamExt as _;
 use parquet::arrow::{ParquetRecordBatchStreamBuilder, ProjectionMask};
 use swiftide_core::{
-    indexing::{IndexingStream, Node},
+    indexing::{IndexingStream, LoaderError, Node},
     Loader,
 };
 use tokio::{fs::File, runtime::Handle};
@@ -47,7 +47,8 @@ impl Loader for Parquet {
 
         let swiftide_stream = stream.flat_map_unordered(None, move |result_batch| {
             let Ok(batch) = result_batch else {
-                let new_result: Result<Node> = Err(anyhow::anyhow!(result_batch.unwrap_err()));
+                let new_result: Result<Node> =
+                    Err(LoaderError::permanent(result_batch.unwrap_err()).into());
 
                 return vec![new_result].into();
             };
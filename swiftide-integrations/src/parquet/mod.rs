@@ -1,10 +1,13 @@
-//! Stream data from parquet files
+//! Stream data from parquet files, and export stored nodes to parquet
 use std::path::PathBuf;
 
 use derive_builder::Builder;
 
+pub mod export;
 pub mod loader;
 
+pub use export::export_parquet;
+
 /// Stream data from parquet files on a single column
 ///
 /// Provide a path, column and optional batch size. The column must be of type `StringArray`. Then
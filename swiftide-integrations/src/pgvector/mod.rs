@@ -3,10 +3,13 @@
 //! This module provides a client interface for vector similarity search operations using pgvector,
 //! supporting:
 //! - Vector collection management with configurable schemas
-//! - Efficient vector storage and indexing
+//! - Efficient vector storage and indexing, with configurable HNSW or IVFFlat indices
 //! - Connection pooling with automatic retries
 //! - Batch operations for optimized performance
-//! - Metadata included in retrieval
+//! - Metadata included in retrieval, optionally in typed columns to filter on
+//! - Hybrid search with full-text search and reciprocal rank fusion
+//! - Scrolling through all stored nodes, i.e. for exports
+//! - Optional zstd compression of the stored chunks
 //!
 //! The functionality is primarily used through the [`PgVector`] client, which implements
 //! the [`Persist`] trait for seamless integration with indexing and query pipelines.
@@ -29,15 +32,20 @@ mod fixtures;
 mod persist;
 mod pgv_table_types;
 mod retrieve;
+use crate::compression::ChunkCompression;
 use anyhow::Result;
 use derive_builder::Builder;
 use sqlx::PgPool;
 use std::fmt;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::sync::OnceLock;
+use swiftide_core::indexing::MetadataSchema;
 use tokio::time::Duration;
 
-pub use pgv_table_types::{FieldConfig, MetadataConfig, VectorConfig};
+pub use pgv_table_types::{
+    DistanceOp, FieldConfig, MetadataConfig, MetadataType, VectorConfig, VectorIndex,
+};
 
 /// Default maximum connections for the database connection pool.
 const DB_POOL_CONN_MAX: u32 = 10;
@@ -56,7 +64,10 @@ const BATCH_SIZE: usize = 50;
 /// This struct is used to interact with the Pgvector vector database, providing methods to manage vector collections,
 /// store data, and ensure efficient searches. The client can be cloned with low cost as it shares connections.
 #[derive(Builder, Clone)]
-#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+#[builder(
+    setter(into, strip_option),
+    build_fn(error = "anyhow::Error", validate = "Self::validate")
+)]
 pub struct PgVector {
     /// Name of the table to store vectors.
     #[builder(default = "String::from(\"swiftide_pgv_store\")")]
@@ -69,12 +80,49 @@ pub struct PgVector {
     #[builder(default = "BATCH_SIZE")]
     batch_size: usize,
 
+    /// Limits batches to an estimated size in bytes, in addition to the batch size.
+    ///
+    /// Keeps batches of large nodes from growing into huge inserts.
+    #[builder(default)]
+    max_batch_bytes: Option<usize>,
+
     /// Field configurations for the `PgVector` table schema.
     ///
     /// Supports multiple field types (see [`FieldConfig`]).
     #[builder(default)]
     fields: Vec<FieldConfig>,
 
+    /// Index created on the vector column, defaults to HNSW.
+    ///
+    /// See [`VectorIndex`].
+    #[builder(default)]
+    vector_index: VectorIndex,
+
+    /// Distance function used for the index and retrieval, defaults to cosine distance.
+    #[builder(default)]
+    distance: DistanceOp,
+
+    /// Number of stored rows after which the index is created.
+    ///
+    /// Building an index after the initial bulk load is considerably faster than maintaining it
+    /// on every insert. Defaults to creating HNSW indices on setup, and IVFFlat indices once the
+    /// table holds `lists * 1000` rows.
+    #[builder(default)]
+    create_index_after_rows: Option<u64>,
+
+    /// Text search configuration of the chunks, i.e. `english`, enables hybrid search.
+    ///
+    /// Maintains a generated `tsvector` column with a GIN index, used for the full-text part of
+    /// `HybridSearch`.
+    #[builder(default)]
+    text_search_config: Option<String>,
+
+    /// Compresses chunks before they are stored, see [`ChunkCompression`].
+    ///
+    /// Chunks are decompressed on retrieval. Cannot be combined with full-text search.
+    #[builder(default)]
+    chunk_compression: Option<ChunkCompression>,
+
     /// Database connection URL.
     db_url: String,
 
@@ -97,6 +145,10 @@ pub struct PgVector {
     /// SQL statement used for executing bulk insert.
     #[builder(default = "Arc::new(OnceLock::new())")]
     sql_stmt_bulk_insert: Arc<OnceLock<String>>,
+
+    /// Whether the index on the vector column exists.
+    #[builder(default, setter(skip))]
+    index_created: Arc<AtomicBool>,
 }
 
 impl fmt::Debug for PgVector {
@@ -105,6 +157,11 @@ impl fmt::Debug for PgVector {
             .field("table_name", &self.table_name)
             .field("vector_size", &self.vector_size)
             .field("batch_size", &self.batch_size)
+            .field("max_batch_bytes", &self.max_batch_bytes)
+            .field("vector_index", &self.vector_index)
+            .field("distance", &self.distance)
+            .field("text_search_config", &self.text_search_config)
+            .field("chunk_compression", &self.chunk_compression)
             .finish()
     }
 }
@@ -182,9 +239,42 @@ impl PgVectorBuilder {
         self
     }
 
+    /// Adds a typed metadata column for every field in the schema
+    ///
+    /// Metadata is validated against the column types before it is stored.
+    pub fn with_metadata_schema(&mut self, schema: &MetadataSchema) -> &mut Self {
+        for (name, field_type) in schema.fields() {
+            self.with_metadata(MetadataConfig::new(name).with_type(field_type.into()));
+        }
+
+        self
+    }
+
     pub fn default_fields() -> Vec<FieldConfig> {
         vec![FieldConfig::ID, FieldConfig::Chunk]
     }
+
+    fn validate(&self) -> Result<()> {
+        let compressed = self.chunk_compression.flatten().is_some();
+        let text_search = self
+            .text_search_config
+            .as_ref()
+            .is_some_and(Option::is_some);
+
+        if compressed && text_search {
+            anyhow::bail!("Compressed chunks cannot be used with full-text search");
+        }
+
+        Ok(())
+    }
+}
+
+/// Connection errors and timeouts might succeed on a retry
+fn is_transient(err: &sqlx::Error) -> bool {
+    matches!(
+        err,
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed
+    )
 }
 
 #[cfg(test)]
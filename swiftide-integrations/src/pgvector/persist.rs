@@ -9,18 +9,21 @@
 //!
 //! The implementation ensures thread-safe concurrent access and handles
 //! connection management automatically.
-use crate::pgvector::PgVector;
+use crate::metrics;
+use crate::pgvector::{is_transient, FieldConfig, MetadataType, PgVector};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use sqlx::types::Uuid;
+use std::sync::atomic::Ordering;
 use swiftide_core::{
-    indexing::{IndexingStream, Node},
+    indexing::{IndexingStream, Metadata, Node, PersistError},
     Persist,
 };
 
 #[async_trait]
 impl Persist for PgVector {
     #[tracing::instrument(skip_all)]
-    async fn setup(&self) -> Result<()> {
+    async fn setup(&self) -> Result<(), PersistError> {
         // Get or initialize the connection pool
         let pool = self.pool_get_or_initialize().await?;
 
@@ -32,29 +35,70 @@ impl Persist for PgVector {
                 .map_err(|_| anyhow!("SQL bulk store statement is already set"))?;
         }
 
-        let mut tx = pool.begin().await?;
+        let mut tx = pool.begin().await.map_err(persist_error)?;
 
         // Create extension
         let sql = "CREATE EXTENSION IF NOT EXISTS vector";
-        sqlx::query(sql).execute(&mut *tx).await?;
+        sqlx::query(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(persist_error)?;
 
         // Create table
         let create_table_sql = self.generate_create_table_sql()?;
-        sqlx::query(&create_table_sql).execute(&mut *tx).await?;
+        sqlx::query(&create_table_sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(persist_error)?;
 
-        // Create HNSW index
-        let index_sql = self.create_index_sql()?;
-        sqlx::query(&index_sql).execute(&mut *tx).await?;
+        // Add the full-text search column, also to tables created before it was configured
+        for sql in self.create_text_search_sql()? {
+            sqlx::query(&sql)
+                .execute(&mut *tx)
+                .await
+                .map_err(persist_error)?;
+        }
 
-        tx.commit().await?;
+        // Create the index right away, unless it is deferred until enough rows are stored
+        if let Some(index_sql) = self.create_index_sql()? {
+            if self.create_index_after_rows() == 0 {
+                sqlx::query(&index_sql)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(persist_error)?;
+            }
+        }
+
+        // The index might also exist from an earlier run with deferred creation
+        let (index_exists,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM pg_indexes WHERE tablename = $1 AND indexname = $2)",
+        )
+        .bind(&self.table_name)
+        .bind(self.index_name())
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(persist_error)?;
+
+        tx.commit().await.map_err(persist_error)?;
+
+        if index_exists {
+            self.index_created.store(true, Ordering::Release);
+        }
 
         Ok(())
     }
 
     #[tracing::instrument(skip_all)]
-    async fn store(&self, node: Node) -> Result<Node> {
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
         let mut nodes = vec![node; 1];
-        self.store_nodes(&nodes).await?;
+        metrics::measure(
+            "pgvector",
+            &self.table_name,
+            "store",
+            1,
+            self.store_nodes(&nodes),
+        )
+        .await?;
 
         let node = nodes.swap_remove(0);
 
@@ -63,19 +107,200 @@ impl Persist for PgVector {
 
     #[tracing::instrument(skip_all)]
     async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
-        self.store_nodes(&nodes).await.map(|()| nodes).into()
+        metrics::measure(
+            "pgvector",
+            &self.table_name,
+            "batch_store",
+            nodes.len(),
+            self.store_nodes(&nodes),
+        )
+        .await
+        .map(|()| nodes)
+        .into()
     }
 
     fn batch_size(&self) -> Option<usize> {
         Some(self.batch_size)
     }
+
+    fn max_batch_bytes(&self) -> Option<usize> {
+        self.max_batch_bytes
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn delete(&self, ids: Vec<Uuid>) -> Result<(), PersistError> {
+        let pool = self.pool_get_or_initialize().await?;
+
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE id = ANY($1)",
+            self.table_name
+        ))
+        .bind(ids)
+        .execute(pool)
+        .await
+        .map_err(|e| anyhow!("Failed to delete nodes: {:?}", e))?;
+
+        Ok(())
+    }
+
+    /// Deletes all rows matching every entry of the filter
+    ///
+    /// Every key must be a configured metadata field.
+    #[tracing::instrument(skip_all)]
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        let pool = self.pool_get_or_initialize().await?;
+
+        let (sql, values) = self.delete_by_metadata_sql(filter)?;
+        tracing::debug!("Deleting by metadata with SQL: {}", sql);
+
+        let mut query = sqlx::query(&sql);
+        for value in values {
+            query = query.bind(value);
+        }
+        query
+            .execute(pool)
+            .await
+            .map_err(|e| anyhow!("Failed to delete nodes: {:?}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Classifies errors of the database, keeping the `sqlx::Error`
+fn persist_error(err: sqlx::Error) -> PersistError {
+    if is_transient(&err) {
+        PersistError::transient(err)
+    } else {
+        PersistError::permanent(err)
+    }
+}
+
+impl PgVector {
+    /// Generates the statement deleting rows matching the metadata, with the values to bind in
+    /// order.
+    ///
+    /// Json columns are matched with containment on the stored object, typed columns are compared
+    /// with the value cast to the column type.
+    fn delete_by_metadata_sql(&self, filter: Metadata) -> Result<(String, Vec<serde_json::Value>)> {
+        if filter.is_empty() {
+            return Err(anyhow!("Refusing to delete by an empty metadata filter"));
+        }
+
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        for (idx, (key, value)) in filter.into_iter().enumerate() {
+            let config = self
+                .fields
+                .iter()
+                .find_map(|field| match field {
+                    FieldConfig::Metadata(config) if config.original_field() == key => Some(config),
+                    _ => None,
+                })
+                .ok_or_else(|| anyhow!("Filter on unknown metadata field {key}"))?;
+
+            let column = format!("meta_{}", PgVector::normalize_field_name(&key));
+            let param = idx + 1;
+
+            if config.column_type() == MetadataType::Json {
+                conditions.push(format!("{column} @> ${param}"));
+                values.push(serde_json::json!({ key: value }));
+            } else {
+                conditions.push(format!(
+                    "{column} = CAST(${param} #>> '{{}}' AS {})",
+                    config.column_type().sql_type()
+                ));
+                values.push(value);
+            }
+        }
+
+        Ok((
+            format!(
+                "DELETE FROM {} WHERE {}",
+                self.table_name,
+                conditions.join(" AND ")
+            ),
+            values,
+        ))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::pgvector::fixtures::TestContext;
+    use crate::pgvector::{fixtures::TestContext, MetadataConfig, MetadataType};
+    use futures_util::TryStreamExt;
     use std::collections::HashSet;
-    use swiftide_core::{indexing::EmbeddedField, Persist};
+    use swiftide_core::{
+        indexing::{self, EmbeddedField},
+        Persist,
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_delete_and_delete_by_metadata() {
+        let test_context = TestContext::setup_with_builder(
+            vec!["source"].into(),
+            HashSet::from([EmbeddedField::Combined]),
+            |builder| {
+                builder.with_metadata(MetadataConfig::new("page").with_type(MetadataType::Integer))
+            },
+        )
+        .await
+        .expect("Test setup failed");
+
+        let nodes = [("a.md", 1), ("a.md", 2), ("b.md", 1), ("b.md", 2)]
+            .into_iter()
+            .map(|(source, page)| {
+                indexing::Node::new(format!("{source} {page}"))
+                    .with_metadata([
+                        ("source", serde_json::Value::from(source)),
+                        ("page", serde_json::Value::from(page)),
+                    ])
+                    .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+        let first_id = nodes[0].id();
+
+        let storage = &test_context.pgv_storage;
+        storage
+            .batch_store(nodes)
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let count = || async {
+            let (count,): (i64,) = sqlx::query_as(&format!(
+                "SELECT count(*) FROM {}",
+                storage.get_table_name()
+            ))
+            .fetch_one(storage.get_pool().await.unwrap())
+            .await
+            .unwrap();
+            count
+        };
+        assert_eq!(count().await, 4);
+
+        storage.delete(vec![first_id]).await.unwrap();
+        assert_eq!(count().await, 3);
+
+        storage
+            .delete_by_metadata([("source", "b.md")].into())
+            .await
+            .unwrap();
+        assert_eq!(count().await, 1);
+
+        storage
+            .delete_by_metadata([("page", 2)].into())
+            .await
+            .unwrap();
+        assert_eq!(count().await, 0);
+
+        assert!(storage
+            .delete_by_metadata([("unknown", 2)].into())
+            .await
+            .is_err());
+    }
 
     #[test_log::test(tokio::test)]
     async fn test_persist_setup_no_error_when_table_exists() {
@@ -3,7 +3,7 @@
 //! Provides schema configuration and data type conversion functionality:
 //! - Table schema generation with vector and metadata columns
 //! - Field configuration for different vector embedding types
-//! - HNSW index creation for similarity search optimization
+//! - HNSW and IVFFlat index creation for similarity search optimization
 //! - Bulk data preparation and SQL query generation
 //!
 use crate::pgvector::PgVector;
@@ -13,8 +13,10 @@ use regex::Regex;
 use sqlx::postgres::PgArguments;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
+use std::borrow::Cow;
 use std::collections::BTreeMap;
-use swiftide_core::indexing::{EmbeddedField, Node};
+use std::sync::atomic::Ordering;
+use swiftide_core::indexing::{EmbeddedField, MetadataFieldType, Node};
 use tokio::time::sleep;
 
 /// Configuration for vector embedding columns in the `PostgreSQL` table.
@@ -37,6 +39,10 @@ impl VectorConfig {
             ),
         }
     }
+
+    pub(crate) fn embedded_field(&self) -> &EmbeddedField {
+        &self.embedded_field
+    }
 }
 
 impl From<EmbeddedField> for VectorConfig {
@@ -53,6 +59,7 @@ impl From<EmbeddedField> for VectorConfig {
 pub struct MetadataConfig {
     field: String,
     original_field: String,
+    column_type: MetadataType,
 }
 
 impl MetadataConfig {
@@ -61,6 +68,94 @@ impl MetadataConfig {
         Self {
             field: format!("meta_{}", PgVector::normalize_field_name(&original)),
             original_field: original,
+            column_type: MetadataType::default(),
+        }
+    }
+
+    /// Sets the column type of the metadata field, defaults to [`MetadataType::Json`].
+    ///
+    /// Typed columns can be filtered on with comparisons, and indexed like any other column.
+    #[must_use]
+    pub fn with_type(mut self, column_type: MetadataType) -> Self {
+        self.column_type = column_type;
+        self
+    }
+
+    pub(crate) fn original_field(&self) -> &str {
+        &self.original_field
+    }
+
+    pub(crate) fn column_type(&self) -> MetadataType {
+        self.column_type
+    }
+
+    /// The expression selecting the field as a json object of its original name and value, as
+    /// expected when building documents.
+    pub(crate) fn select_sql(&self) -> String {
+        match self.column_type {
+            MetadataType::Json => self.field.clone(),
+            _ => format!(
+                "jsonb_build_object('{}', {}) AS {}",
+                self.original_field.replace('\'', "''"),
+                self.field,
+                self.field
+            ),
+        }
+    }
+}
+
+/// Column types of metadata fields in the `PostgreSQL` table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetadataType {
+    /// `Json` - Stored as a json object of the original field name and value
+    #[default]
+    Json,
+    /// `Text` - Stored as `TEXT`
+    Text,
+    /// `Integer` - Stored as `BIGINT`
+    Integer,
+    /// `Float` - Stored as `DOUBLE PRECISION`
+    Float,
+    /// `Timestamp` - Stored as `TIMESTAMPTZ`, from an RFC 3339 string
+    Timestamp,
+    /// `Boolean` - Stored as `BOOLEAN`
+    Boolean,
+}
+
+impl MetadataType {
+    pub(crate) fn sql_type(self) -> &'static str {
+        match self {
+            MetadataType::Json => "JSONB",
+            MetadataType::Text => "TEXT",
+            MetadataType::Integer => "BIGINT",
+            MetadataType::Float => "DOUBLE PRECISION",
+            MetadataType::Timestamp => "TIMESTAMPTZ",
+            MetadataType::Boolean => "BOOLEAN",
+        }
+    }
+
+    /// The type metadata values must have to be stored in the column
+    pub(crate) fn field_type(self) -> MetadataFieldType {
+        match self {
+            MetadataType::Json => MetadataFieldType::Json,
+            MetadataType::Text => MetadataFieldType::String,
+            MetadataType::Integer => MetadataFieldType::Integer,
+            MetadataType::Float => MetadataFieldType::Float,
+            MetadataType::Timestamp => MetadataFieldType::Timestamp,
+            MetadataType::Boolean => MetadataFieldType::Boolean,
+        }
+    }
+}
+
+impl From<MetadataFieldType> for MetadataType {
+    fn from(field_type: MetadataFieldType) -> Self {
+        match field_type {
+            MetadataFieldType::String => MetadataType::Text,
+            MetadataFieldType::Integer => MetadataType::Integer,
+            MetadataFieldType::Float => MetadataType::Float,
+            MetadataFieldType::Boolean => MetadataType::Boolean,
+            MetadataFieldType::Timestamp => MetadataType::Timestamp,
+            _ => MetadataType::Json,
         }
     }
 }
@@ -98,13 +193,93 @@ impl FieldConfig {
     }
 }
 
+/// The type of index created on the vector column.
+///
+/// Without an index every query scans the full table, which is only usable for small tables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VectorIndex {
+    /// `Hnsw` - Graph based index with better recall, but slower to build and larger
+    Hnsw {
+        /// Maximum number of connections per layer, pgvector defaults to 16
+        m: u32,
+        /// Size of the candidate list when building the graph, pgvector defaults to 64
+        ef_construction: u32,
+    },
+    /// `IvfFlat` - Clustered index that builds faster, but needs data to pick its clusters
+    ///
+    /// pgvector recommends `rows / 1000` lists for up to 1M rows.
+    IvfFlat {
+        /// Number of clusters the vectors are divided in
+        lists: u32,
+    },
+    /// `None` - No index is created
+    None,
+}
+
+impl Default for VectorIndex {
+    fn default() -> Self {
+        VectorIndex::Hnsw {
+            m: 16,
+            ef_construction: 64,
+        }
+    }
+}
+
+impl VectorIndex {
+    /// The number of rows after which the index is created by default.
+    ///
+    /// HNSW indices are created on setup. IVFFlat indices are created once the table holds
+    /// enough rows for the configured lists, as the clusters are picked from the existing data.
+    pub(crate) fn default_create_after_rows(&self) -> u64 {
+        match self {
+            VectorIndex::IvfFlat { lists } => u64::from(*lists) * 1000,
+            VectorIndex::Hnsw { .. } | VectorIndex::None => 0,
+        }
+    }
+}
+
+/// The distance function used for indexing and retrieval.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistanceOp {
+    /// `Cosine` - Cosine distance, `<=>`
+    #[default]
+    Cosine,
+    /// `L2` - Euclidean distance, `<->`
+    L2,
+    /// `InnerProduct` - Negative inner product, `<#>`
+    InnerProduct,
+}
+
+impl DistanceOp {
+    /// The operator class used when creating the index
+    pub fn operator_class(self) -> &'static str {
+        match self {
+            DistanceOp::Cosine => "vector_cosine_ops",
+            DistanceOp::L2 => "vector_l2_ops",
+            DistanceOp::InnerProduct => "vector_ip_ops",
+        }
+    }
+
+    /// The operator used when ordering by distance, which must match the index to use it
+    pub fn operator(self) -> &'static str {
+        match self {
+            DistanceOp::Cosine => "<=>",
+            DistanceOp::L2 => "<->",
+            DistanceOp::InnerProduct => "<#>",
+        }
+    }
+}
+
+/// Name of the generated column holding the `tsvector` of the chunks.
+pub(crate) const TEXT_SEARCH_COLUMN: &str = "chunk_tsv";
+
 /// Internal structure for managing bulk upsert operations.
 ///
 /// Collects and organizes data for efficient bulk insertions and updates,
 /// grouping related fields for UNNEST-based operations.
 struct BulkUpsertData<'a> {
     ids: Vec<sqlx::types::Uuid>,
-    chunks: Vec<&'a str>,
+    chunks: Vec<Cow<'a, str>>,
     metadata_fields: Vec<Vec<serde_json::Value>>,
     vector_fields: Vec<Vec<ExtPgVector::Vector>>,
     field_mapping: FieldMapping<'a>,
@@ -185,7 +360,9 @@ impl PgVector {
             .map(|field| match field {
                 FieldConfig::ID => "id UUID NOT NULL".to_string(),
                 FieldConfig::Chunk => format!("{} TEXT NOT NULL", field.field_name()),
-                FieldConfig::Metadata(_) => format!("{} JSONB", field.field_name()),
+                FieldConfig::Metadata(config) => {
+                    format!("{} {}", field.field_name(), config.column_type.sql_type())
+                }
                 FieldConfig::Vector(_) => {
                     format!("{} VECTOR({})", field.field_name(), self.vector_size)
                 }
@@ -202,15 +379,17 @@ impl PgVector {
         Ok(sql)
     }
 
-    /// Generates the SQL statement to create an HNSW index on the vector column.
+    /// Generates the SQL statement to create the configured index on the vector column.
+    ///
+    /// Returns `None` if no index is configured.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - No vector field is found in the table configuration.
     /// - The table name or field name is invalid.
-    pub fn create_index_sql(&self) -> Result<String> {
-        let index_name = format!("{}_embedding_idx", self.table_name);
+    pub fn create_index_sql(&self) -> Result<Option<String>> {
+        let index_name = self.index_name();
         let vector_field = self
             .fields
             .iter()
@@ -226,10 +405,107 @@ impl PgVector {
             return Err(anyhow::anyhow!("Invalid table or field name"));
         }
 
-        Ok(format!(
-            "CREATE INDEX IF NOT EXISTS {} ON {} USING hnsw ({} vector_cosine_ops)",
-            index_name, &self.table_name, vector_field
-        ))
+        let (method, options) = match &self.vector_index {
+            VectorIndex::Hnsw { m, ef_construction } => (
+                "hnsw",
+                format!("m = {m}, ef_construction = {ef_construction}"),
+            ),
+            VectorIndex::IvfFlat { lists } => ("ivfflat", format!("lists = {lists}")),
+            VectorIndex::None => return Ok(None),
+        };
+
+        Ok(Some(format!(
+            "CREATE INDEX IF NOT EXISTS {} ON {} USING {} ({} {}) WITH ({})",
+            index_name,
+            &self.table_name,
+            method,
+            vector_field,
+            self.distance.operator_class(),
+            options
+        )))
+    }
+
+    /// Generates the SQL statements to add a generated `tsvector` column for the chunks and a GIN
+    /// index on it.
+    ///
+    /// Returns no statements if no text search configuration is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table name or text search configuration is invalid.
+    pub fn create_text_search_sql(&self) -> Result<Vec<String>> {
+        let Some(config) = &self.text_search_config else {
+            return Ok(Vec::new());
+        };
+
+        if !Self::is_valid_identifier(&self.table_name) || !Self::is_valid_identifier(config) {
+            return Err(anyhow::anyhow!("Invalid table name or text search config"));
+        }
+
+        Ok(vec![
+            format!(
+                "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS {TEXT_SEARCH_COLUMN} TSVECTOR GENERATED ALWAYS AS (to_tsvector('{config}', {chunk})) STORED",
+                table = self.table_name,
+                chunk = FieldConfig::Chunk.field_name(),
+            ),
+            format!(
+                "CREATE INDEX IF NOT EXISTS {table}_{TEXT_SEARCH_COLUMN}_idx ON {table} USING gin ({TEXT_SEARCH_COLUMN})",
+                table = self.table_name,
+            ),
+        ])
+    }
+
+    pub(crate) fn index_name(&self) -> String {
+        format!("{}_embedding_idx", self.table_name)
+    }
+
+    /// The number of rows after which the index is created, see [`VectorIndex`]
+    pub(crate) fn create_index_after_rows(&self) -> u64 {
+        self.create_index_after_rows
+            .unwrap_or_else(|| self.vector_index.default_create_after_rows())
+    }
+
+    /// Creates the index once the table holds enough rows, if it has not been created yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if counting the rows or creating the index fails.
+    pub(crate) async fn create_index_if_ready(&self) -> Result<()> {
+        if self.index_created.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let Some(sql) = self.create_index_sql()? else {
+            self.index_created.store(true, Ordering::Release);
+            return Ok(());
+        };
+
+        let pool = self.pool_get_or_initialize().await?;
+
+        let (rows,): (i64,) = sqlx::query_as(&format!("SELECT count(*) FROM {}", self.table_name))
+            .fetch_one(pool)
+            .await?;
+
+        if u64::try_from(rows).unwrap_or_default() < self.create_index_after_rows() {
+            return Ok(());
+        }
+
+        // Only one batch creates the index, others continue storing in the meantime
+        if self
+            .index_created
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Ok(());
+        }
+
+        tracing::info!(rows, index = self.index_name(), "Creating vector index");
+        if let Err(err) = sqlx::query(&sql).execute(pool).await {
+            self.index_created.store(false, Ordering::Release);
+            return Err(anyhow!(err).context("Failed to create vector index"));
+        }
+
+        Ok(())
     }
 
     /// Stores a list of nodes in the database using an upsert operation.
@@ -268,7 +544,9 @@ impl PgVector {
 
         tx.commit()
             .await
-            .map_err(|e| anyhow!("Failed to commit transaction: {:?}", e))
+            .map_err(|e| anyhow!("Failed to commit transaction: {:?}", e))?;
+
+        self.create_index_if_ready().await
     }
 
     /// Prepares data from nodes into vectors for bulk processing.
@@ -278,7 +556,10 @@ impl PgVector {
 
         for node in nodes {
             bulk_data.ids.push(node.id());
-            bulk_data.chunks.push(node.chunk.as_str());
+            bulk_data.chunks.push(match &self.chunk_compression {
+                Some(compression) => compression.compress(&node.chunk)?,
+                None => Cow::Borrowed(node.chunk.as_str()),
+            });
 
             for field in &self.fields {
                 match field {
@@ -292,10 +573,24 @@ impl PgVector {
                             .get(&config.original_field)
                             .ok_or_else(|| anyhow!("Missing metadata field"))?;
 
-                        let mut metadata_map = BTreeMap::new();
-                        metadata_map.insert(config.original_field.clone(), value.clone());
-
-                        bulk_data.metadata_fields[idx].push(serde_json::to_value(metadata_map)?);
+                        if !config.column_type.field_type().matches(value) {
+                            return Err(anyhow!(
+                                "Metadata field `{}` should be {}, got {value}",
+                                config.original_field,
+                                config.column_type.field_type()
+                            ));
+                        }
+
+                        // Typed columns are bound as json as well, and cast when inserted
+                        let value = if config.column_type == MetadataType::Json {
+                            let mut metadata_map = BTreeMap::new();
+                            metadata_map.insert(config.original_field.clone(), value.clone());
+                            serde_json::to_value(metadata_map)?
+                        } else {
+                            value.clone()
+                        };
+
+                        bulk_data.metadata_fields[idx].push(value);
                     }
                     FieldConfig::Vector(config) => {
                         let idx = bulk_data
@@ -334,6 +629,7 @@ impl PgVector {
         }
 
         let mut columns = Vec::new();
+        let mut select_columns = Vec::new();
         let mut unnest_params = Vec::new();
         let mut param_counter = 1;
 
@@ -341,6 +637,14 @@ impl PgVector {
             let name = field.field_name();
             columns.push(name.to_string());
 
+            // Typed metadata is extracted from the json value and cast to its column type
+            select_columns.push(match field {
+                FieldConfig::Metadata(config) if config.column_type != MetadataType::Json => {
+                    format!("({name} #>> '{{}}')::{}", config.column_type.sql_type())
+                }
+                _ => name.to_string(),
+            });
+
             unnest_params.push(format!(
                 "${param_counter}::{}",
                 match field {
@@ -373,7 +677,7 @@ impl PgVector {
             ON CONFLICT (id) DO UPDATE SET {}",
             self.table_name,
             columns.join(", "),
-            columns.join(", "),
+            select_columns.join(", "),
             unnest_params.join(", "),
             columns.join(", "),
             update_columns
@@ -576,4 +880,53 @@ mod tests {
         assert!(!PgVector::is_valid_identifier("invalid-name")); // Contains hyphen
         assert!(!PgVector::is_valid_identifier("select")); // Reserved keyword
     }
+
+    fn pgvector(index: VectorIndex, distance: DistanceOp) -> PgVector {
+        PgVector::builder()
+            .db_url("postgresql://localhost:5432/vectors")
+            .vector_size(384)
+            .with_vector(EmbeddedField::Combined)
+            .vector_index(index)
+            .distance(distance)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_create_index_sql() {
+        let pgv = pgvector(VectorIndex::default(), DistanceOp::Cosine);
+        assert_eq!(
+            pgv.create_index_sql().unwrap().unwrap(),
+            "CREATE INDEX IF NOT EXISTS swiftide_pgv_store_embedding_idx ON swiftide_pgv_store USING hnsw (vector_combined vector_cosine_ops) WITH (m = 16, ef_construction = 64)"
+        );
+        assert_eq!(pgv.create_index_after_rows(), 0);
+
+        let pgv = pgvector(VectorIndex::IvfFlat { lists: 100 }, DistanceOp::L2);
+        assert_eq!(
+            pgv.create_index_sql().unwrap().unwrap(),
+            "CREATE INDEX IF NOT EXISTS swiftide_pgv_store_embedding_idx ON swiftide_pgv_store USING ivfflat (vector_combined vector_l2_ops) WITH (lists = 100)"
+        );
+        assert_eq!(pgv.create_index_after_rows(), 100_000);
+
+        let pgv = pgvector(VectorIndex::None, DistanceOp::Cosine);
+        assert!(pgv.create_index_sql().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_create_text_search_sql() {
+        let mut pgv = pgvector(VectorIndex::default(), DistanceOp::Cosine);
+        assert!(pgv.create_text_search_sql().unwrap().is_empty());
+
+        pgv.text_search_config = Some("english".to_string());
+        assert_eq!(
+            pgv.create_text_search_sql().unwrap(),
+            vec![
+                "ALTER TABLE swiftide_pgv_store ADD COLUMN IF NOT EXISTS chunk_tsv TSVECTOR GENERATED ALWAYS AS (to_tsvector('english', chunk)) STORED",
+                "CREATE INDEX IF NOT EXISTS swiftide_pgv_store_chunk_tsv_idx ON swiftide_pgv_store USING gin (chunk_tsv)",
+            ]
+        );
+
+        pgv.text_search_config = Some("english'); DROP TABLE x; --".to_string());
+        assert!(pgv.create_text_search_sql().is_err());
+    }
 }
@@ -1,19 +1,34 @@
-use crate::pgvector::{FieldConfig, PgVector, PgVectorBuilder};
+use crate::compression::ChunkCompression;
+use crate::metrics;
+use crate::pgvector::{
+    is_transient, pgv_table_types::TEXT_SEARCH_COLUMN, FieldConfig, MetadataType, PgVector,
+    PgVectorBuilder, VectorConfig,
+};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use futures_util::{stream, StreamExt as _, TryStreamExt as _};
 use pgvector::Vector;
 use sqlx::{prelude::FromRow, types::Uuid, Column, Row};
+use std::collections::HashMap;
 use swiftide_core::{
     document::Document,
-    indexing::Metadata,
+    indexing::{IndexingStream, Metadata, Node},
     querying::{
-        search_strategies::{CustomStrategy, SimilaritySingleEmbedding},
-        states, Query,
+        search_strategies::{CustomStrategy, HybridSearch, SimilaritySingleEmbedding},
+        states, Query, RetrieveError,
     },
-    Retrieve,
+    Retrieve, Scroll,
 };
 
-#[allow(dead_code)]
+/// Comparison operators supported in filters
+const FILTER_OPERATORS: [&str; 6] = ["!=", "<=", ">=", "=", "<", ">"];
+
+/// Constant of reciprocal rank fusion, dampening the weight of the top ranks
+const RRF_K: i32 = 60;
+
+/// The number of rows fetched per page when scrolling
+const SCROLL_LIMIT: i64 = 100;
+
 #[derive(Debug, Clone)]
 struct VectorSearchResult {
     id: Uuid,
@@ -50,7 +65,8 @@ impl FromRow<'_, sqlx::postgres::PgRow> for VectorSearchResult {
 
         Ok(VectorSearchResult {
             id: row.try_get("id")?,
-            chunk: row.try_get("chunk")?,
+            chunk: ChunkCompression::decompress_owned(row.try_get("chunk")?)
+                .map_err(|err| sqlx::Error::Decode(err.into()))?,
             metadata,
         })
     }
@@ -64,27 +80,18 @@ impl Retrieve<SimilaritySingleEmbedding<String>> for PgVector {
         &self,
         search_strategy: &SimilaritySingleEmbedding<String>,
         query_state: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         let embedding = if let Some(embedding) = query_state.embedding.as_ref() {
             Vector::from(embedding.clone())
         } else {
-            return Err(anyhow::Error::msg("Missing embedding in query state"));
+            return Err(RetrieveError::MissingEmbedding);
         };
 
         let vector_column_name = self.get_vector_column_name()?;
 
         let pool = self.pool_get_or_initialize().await?;
 
-        let default_columns: Vec<_> = PgVectorBuilder::default_fields()
-            .iter()
-            .map(|f| f.field_name().to_string())
-            .chain(
-                self.fields
-                    .iter()
-                    .filter(|f| matches!(f, FieldConfig::Metadata(_)))
-                    .map(|f| f.field_name().to_string()),
-            )
-            .collect();
+        let default_columns = self.retrieved_columns();
 
         // Start building the SQL query
         let mut sql = format!(
@@ -93,33 +100,20 @@ impl Retrieve<SimilaritySingleEmbedding<String>> for PgVector {
             self.table_name
         );
 
-        if let Some(filter) = search_strategy.filter() {
-            let filter_parts: Vec<&str> = filter.split('=').collect();
-            if filter_parts.len() == 2 {
-                let key = filter_parts[0].trim();
-                let value = filter_parts[1].trim().trim_matches('"');
-                tracing::debug!(
-                    "Filter being applied: key = {:#?}, value = {:#?}",
-                    key,
-                    value
-                );
-
-                let sql_filter = format!(
-                    " WHERE meta_{}->>'{}' = '{}'",
-                    PgVector::normalize_field_name(key),
-                    key,
-                    value
-                );
-                sql.push_str(&sql_filter);
-            } else {
-                return Err(anyhow!("Invalid filter format"));
-            }
+        let filter = search_strategy
+            .filter()
+            .as_deref()
+            .map(|filter| self.filter_sql(filter))
+            .transpose()?;
+        if let Some((sql_filter, _)) = &filter {
+            sql.push_str(sql_filter);
         }
 
         // Add the ORDER BY clause for vector similarity search
         sql.push_str(&format!(
-            " ORDER BY {} <=> $1 LIMIT $2",
-            &vector_column_name
+            " ORDER BY {} {} $1 LIMIT $2",
+            &vector_column_name,
+            self.distance.operator()
         ));
 
         tracing::debug!("Running retrieve with SQL: {}", sql);
@@ -127,11 +121,19 @@ impl Retrieve<SimilaritySingleEmbedding<String>> for PgVector {
         let top_k = i32::try_from(search_strategy.top_k())
             .map_err(|_| anyhow!("Failed to convert top_k to i32"))?;
 
-        let data: Vec<VectorSearchResult> = sqlx::query_as(&sql)
-            .bind(embedding)
-            .bind(top_k)
-            .fetch_all(pool)
-            .await?;
+        let mut query = sqlx::query_as(&sql).bind(embedding).bind(top_k);
+        if let Some((_, value)) = filter {
+            query = query.bind(value);
+        }
+        let data: Vec<VectorSearchResult> = metrics::measure(
+            "pgvector",
+            &self.table_name,
+            "retrieve",
+            0,
+            query.fetch_all(pool),
+        )
+        .await
+        .map_err(retrieve_error)?;
 
         let docs = data.into_iter().map(Into::into).collect();
 
@@ -145,7 +147,7 @@ impl Retrieve<SimilaritySingleEmbedding> for PgVector {
         &self,
         search_strategy: &SimilaritySingleEmbedding,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         Retrieve::<SimilaritySingleEmbedding<String>>::retrieve(
             self,
             &search_strategy.into_concrete_filter::<String>(),
@@ -155,13 +157,115 @@ impl Retrieve<SimilaritySingleEmbedding> for PgVector {
     }
 }
 
+/// Implement the `Retrieve` trait for `HybridSearch` search strategy.
+///
+/// Runs a vector search and a full-text search on the chunks, each limited to `top_n` results,
+/// and fuses both rankings with reciprocal rank fusion into `top_k` documents.
+///
+/// Requires a text search configuration, see [`PgVectorBuilder::text_search_config`].
+#[async_trait]
+impl Retrieve<HybridSearch> for PgVector {
+    #[tracing::instrument]
+    async fn retrieve(
+        &self,
+        search_strategy: &HybridSearch,
+        query_state: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(text_search_config) = &self.text_search_config else {
+            return Err(RetrieveError::permanent(
+                "Hybrid search requires a text search config",
+            ));
+        };
+
+        let embedding = if let Some(embedding) = query_state.embedding.as_ref() {
+            Vector::from(embedding.clone())
+        } else {
+            return Err(RetrieveError::MissingEmbedding);
+        };
+
+        let vector_column_name =
+            VectorConfig::from(search_strategy.dense_vector_field().clone()).field;
+        if !self
+            .fields
+            .iter()
+            .any(|field| field.field_name() == vector_column_name)
+        {
+            return Err(RetrieveError::permanent(format!(
+                "Vector field {} is not configured",
+                search_strategy.dense_vector_field()
+            )));
+        }
+
+        let pool = self.pool_get_or_initialize().await?;
+
+        let sql = format!(
+            r"
+            WITH vector_search AS (
+                SELECT id, ROW_NUMBER() OVER (ORDER BY {vector} {operator} $1) AS rank
+                FROM {table}
+                ORDER BY {vector} {operator} $1
+                LIMIT $3
+            ),
+            text_search AS (
+                SELECT id, ROW_NUMBER() OVER (ORDER BY ts_rank_cd({tsv}, query) DESC) AS rank
+                FROM {table}, websearch_to_tsquery('{text_search_config}', $2) AS query
+                WHERE {tsv} @@ query
+                ORDER BY ts_rank_cd({tsv}, query) DESC
+                LIMIT $3
+            ),
+            fused AS (
+                SELECT COALESCE(v.id, t.id) AS id,
+                    COALESCE(1.0 / ($5 + v.rank), 0.0) + COALESCE(1.0 / ($5 + t.rank), 0.0) AS score
+                FROM vector_search v
+                FULL OUTER JOIN text_search t ON v.id = t.id
+            )
+            SELECT {columns} FROM {table}
+            JOIN fused USING (id)
+            ORDER BY fused.score DESC
+            LIMIT $4",
+            vector = vector_column_name,
+            operator = self.distance.operator(),
+            table = self.table_name,
+            tsv = TEXT_SEARCH_COLUMN,
+            columns = self.retrieved_columns().join(", "),
+        );
+
+        tracing::debug!("Running hybrid retrieve with SQL: {}", sql);
+
+        let top_n = i32::try_from(search_strategy.top_n())
+            .map_err(|_| anyhow!("Failed to convert top_n to i32"))?;
+        let top_k = i32::try_from(search_strategy.top_k())
+            .map_err(|_| anyhow!("Failed to convert top_k to i32"))?;
+
+        let data: Vec<VectorSearchResult> = metrics::measure(
+            "pgvector",
+            &self.table_name,
+            "retrieve_hybrid",
+            0,
+            sqlx::query_as(&sql)
+                .bind(embedding)
+                .bind(query_state.current())
+                .bind(top_n)
+                .bind(top_k)
+                .bind(RRF_K)
+                .fetch_all(pool),
+        )
+        .await
+        .map_err(retrieve_error)?;
+
+        let docs = data.into_iter().map(Into::into).collect();
+
+        Ok(query_state.retrieved_documents(docs))
+    }
+}
+
 #[async_trait]
 impl Retrieve<CustomStrategy<sqlx::QueryBuilder<'static, sqlx::Postgres>>> for PgVector {
     async fn retrieve(
         &self,
         search_strategy: &CustomStrategy<sqlx::QueryBuilder<'static, sqlx::Postgres>>,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         // Get the database pool
         let pool = self.get_pool().await?;
 
@@ -169,11 +273,17 @@ impl Retrieve<CustomStrategy<sqlx::QueryBuilder<'static, sqlx::Postgres>>> for P
         let mut query_builder = search_strategy.build_query(&query).await?;
 
         // Execute the query using the builder's built-in methods
-        let results = query_builder
-            .build_query_as::<VectorSearchResult>() // Convert to a typed query
-            .fetch_all(pool) // Execute and get all results
-            .await
-            .map_err(|e| anyhow!("Failed to execute search query: {}", e))?;
+        let results = metrics::measure(
+            "pgvector",
+            &self.table_name,
+            "retrieve_custom",
+            0,
+            query_builder
+                .build_query_as::<VectorSearchResult>() // Convert to a typed query
+                .fetch_all(pool), // Execute and get all results
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to execute search query: {}", e))?;
 
         // Transform results into documents
         let documents = results.into_iter().map(Into::into).collect();
@@ -183,14 +293,177 @@ impl Retrieve<CustomStrategy<sqlx::QueryBuilder<'static, sqlx::Postgres>>> for P
     }
 }
 
+impl Scroll for PgVector {
+    /// Streams all rows of the table as nodes, paginated on the id
+    ///
+    /// The table does not store the path of a node, so nodes are restored without a path, with
+    /// only the configured metadata and vectors.
+    fn scroll(&self) -> IndexingStream {
+        let pgvector = self.clone();
+
+        // `None` when done
+        stream::try_unfold(Some(Uuid::nil()), move |cursor| {
+            let pgvector = pgvector.clone();
+            async move {
+                let Some(cursor) = cursor else {
+                    return Ok(None);
+                };
+                let rows = pgvector.scroll_page(cursor).await?;
+
+                let next_cursor = rows
+                    .last()
+                    .filter(|_| rows.len() == usize::try_from(SCROLL_LIMIT).unwrap_or_default())
+                    .map(|(id, _)| *id);
+                let nodes = rows.into_iter().map(|(_, node)| node).collect::<Vec<_>>();
+
+                Ok::<_, anyhow::Error>(Some((nodes, next_cursor)))
+            }
+        })
+        .map_ok(|nodes| stream::iter(nodes.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+        .into()
+    }
+}
+
+/// Classifies errors of the database, keeping the `sqlx::Error`
+fn retrieve_error(err: sqlx::Error) -> RetrieveError {
+    if is_transient(&err) {
+        RetrieveError::transient(err)
+    } else {
+        RetrieveError::permanent(err)
+    }
+}
+
+impl PgVector {
+    /// The columns to select to build a [`Document`]
+    fn retrieved_columns(&self) -> Vec<String> {
+        PgVectorBuilder::default_fields()
+            .iter()
+            .map(|f| f.field_name().to_string())
+            .chain(self.fields.iter().filter_map(|f| match f {
+                FieldConfig::Metadata(config) => Some(config.select_sql()),
+                _ => None,
+            }))
+            .collect()
+    }
+
+    /// Fetches a page of rows with an id after the cursor, as nodes with their id
+    async fn scroll_page(&self, cursor: Uuid) -> Result<Vec<(Uuid, Node)>> {
+        let pool = self.pool_get_or_initialize().await?;
+
+        let vector_configs = self
+            .fields
+            .iter()
+            .filter_map(|field| match field {
+                FieldConfig::Vector(config) => Some(config),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let columns = self
+            .retrieved_columns()
+            .into_iter()
+            .chain(vector_configs.iter().map(|config| config.field.clone()))
+            .collect::<Vec<_>>();
+
+        let sql = format!(
+            "SELECT {} FROM {} WHERE id > $1 ORDER BY id LIMIT $2",
+            columns.join(", "),
+            self.table_name
+        );
+        let rows = sqlx::query(&sql)
+            .bind(cursor)
+            .bind(SCROLL_LIMIT)
+            .fetch_all(pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                let VectorSearchResult {
+                    id,
+                    chunk,
+                    metadata,
+                } = VectorSearchResult::from_row(row)?;
+
+                let mut vectors = HashMap::new();
+                for config in &vector_configs {
+                    if let Some(vector) = row.try_get::<Option<Vector>, _>(config.field.as_str())? {
+                        vectors.insert(config.embedded_field().clone(), vector.to_vec());
+                    }
+                }
+
+                let node = Node::builder()
+                    .chunk(chunk)
+                    .metadata(metadata)
+                    .maybe_vectors((!vectors.is_empty()).then_some(vectors))
+                    .build()?;
+
+                Ok((id, node))
+            })
+            .collect()
+    }
+
+    /// Translates a filter on a metadata field to a `WHERE` clause, with the value to bind as
+    /// `$3`.
+    ///
+    /// Filters are a comparison of a metadata field with a value, i.e. `priority >= 2` or
+    /// `category = "A"`. Supported operators are `=`, `!=`, `<`, `<=`, `>` and `>=`. The value is
+    /// compared as text for json columns, and cast to the column type for typed columns.
+    fn filter_sql(&self, filter: &str) -> Result<(String, String)> {
+        let (position, operator) = FILTER_OPERATORS
+            .iter()
+            .filter_map(|operator| filter.find(operator).map(|position| (position, *operator)))
+            // On the same position, prefer `<=` over `<`
+            .min_by_key(|(position, operator)| (*position, std::cmp::Reverse(operator.len())))
+            .ok_or_else(|| anyhow!("Invalid filter format"))?;
+
+        let key = filter[..position].trim();
+        let value = filter[position + operator.len()..].trim().trim_matches('"');
+        tracing::debug!(
+            "Filter being applied: key = {:#?}, operator = {}, value = {:#?}",
+            key,
+            operator,
+            value
+        );
+
+        let config = self
+            .fields
+            .iter()
+            .find_map(|field| match field {
+                FieldConfig::Metadata(config) if config.original_field() == key => Some(config),
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("Filter on unknown metadata field {key}"))?;
+
+        let column = PgVector::normalize_field_name(key);
+        let sql_filter = match config.column_type() {
+            MetadataType::Json => format!(
+                " WHERE meta_{column}->>'{}' {operator} $3",
+                key.replace('\'', "''")
+            ),
+            MetadataType::Text => format!(" WHERE meta_{column} {operator} $3"),
+            column_type => format!(
+                " WHERE meta_{column} {operator} CAST($3 AS {})",
+                column_type.sql_type()
+            ),
+        };
+
+        Ok((sql_filter, value.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::pgvector::fixtures::TestContext;
+    use crate::compression::ChunkCompression;
+    use crate::pgvector::{fixtures::TestContext, MetadataConfig, MetadataType, PgVector};
     use futures_util::TryStreamExt;
     use std::collections::HashSet;
-    use swiftide_core::{indexing, indexing::EmbeddedField, Persist};
+    use swiftide_core::{document::Document, indexing, indexing::EmbeddedField, Persist, Scroll};
     use swiftide_core::{
-        querying::{search_strategies::SimilaritySingleEmbedding, states, Query},
+        querying::{
+            search_strategies::{HybridSearch, SimilaritySingleEmbedding},
+            states, Query,
+        },
         Retrieve,
     };
 
@@ -257,6 +530,159 @@ mod tests {
         assert_eq!(result.documents().len(), 0);
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_retrieve_with_typed_metadata() {
+        let test_context = TestContext::setup_with_builder(
+            None,
+            HashSet::from([EmbeddedField::Combined]),
+            |builder| {
+                builder
+                    .with_metadata(MetadataConfig::new("priority").with_type(MetadataType::Integer))
+                    .with_metadata(
+                        MetadataConfig::new("published").with_type(MetadataType::Timestamp),
+                    )
+            },
+        )
+        .await
+        .expect("Test setup failed");
+
+        let nodes = [
+            (1, "2024-01-01T00:00:00Z"),
+            (2, "2024-06-01T00:00:00Z"),
+            (10, "2025-01-01T00:00:00Z"),
+        ]
+        .into_iter()
+        .map(|(priority, published)| {
+            indexing::Node::new(format!("priority {priority}"))
+                .with_metadata([
+                    ("priority", serde_json::Value::from(priority)),
+                    ("published", serde_json::Value::from(published)),
+                ])
+                .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+                .to_owned()
+        })
+        .collect();
+
+        test_context
+            .pgv_storage
+            .batch_store(nodes)
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut query = Query::<states::Pending>::new("test_query");
+        query.embedding = Some(vec![1.0; 384]);
+
+        // Compared as integers, where "10" < "2" as text
+        let search_strategy = SimilaritySingleEmbedding::from_filter("priority >= 2".to_string());
+        let result = test_context
+            .pgv_storage
+            .retrieve(&search_strategy, query.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.documents().len(), 2);
+
+        let search_strategy = SimilaritySingleEmbedding::from_filter(
+            "published < \"2024-12-31T00:00:00Z\"".to_string(),
+        );
+        let result = test_context
+            .pgv_storage
+            .retrieve(&search_strategy, query)
+            .await
+            .unwrap();
+        assert_eq!(result.documents().len(), 2);
+        assert!(result.documents().iter().all(|doc| doc
+            .metadata()
+            .get("priority")
+            .unwrap()
+            .as_i64()
+            .unwrap()
+            < 10));
+    }
+
+    #[test]
+    fn test_filter_sql() {
+        let pgv = PgVector::builder()
+            .db_url("postgresql://localhost:5432/vectors")
+            .vector_size(384)
+            .with_vector(EmbeddedField::Combined)
+            .with_metadata("category")
+            .with_metadata(MetadataConfig::new("priority").with_type(MetadataType::Integer))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            pgv.filter_sql("category = \"A\"").unwrap(),
+            (
+                " WHERE meta_category->>'category' = $3".to_string(),
+                "A".to_string()
+            )
+        );
+        assert_eq!(
+            pgv.filter_sql("priority <= 2").unwrap(),
+            (
+                " WHERE meta_priority <= CAST($3 AS BIGINT)".to_string(),
+                "2".to_string()
+            )
+        );
+        assert!(pgv.filter_sql("unknown = 1").is_err());
+        assert!(pgv.filter_sql("priority").is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_retrieve_hybrid_search() {
+        let test_context = TestContext::setup_with_builder(
+            None,
+            HashSet::from([EmbeddedField::Combined]),
+            |builder| builder.text_search_config("english"),
+        )
+        .await
+        .expect("Test setup failed");
+
+        let nodes = vec![
+            indexing::Node::new("swiftide is a rust library")
+                .with_vectors([(EmbeddedField::Combined, vec![-1.0; 384])])
+                .to_owned(),
+            indexing::Node::new("something else entirely")
+                .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+                .to_owned(),
+            indexing::Node::new("unrelated")
+                .with_vectors([(EmbeddedField::Combined, vec![-1.0; 384])])
+                .to_owned(),
+        ];
+
+        test_context
+            .pgv_storage
+            .batch_store(nodes)
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut query = Query::<states::Pending>::new("rust library");
+        query.embedding = Some(vec![1.0; 384]);
+
+        let mut search_strategy = HybridSearch::default();
+        search_strategy.with_top_n(1).with_top_k(5);
+
+        let result = test_context
+            .pgv_storage
+            .retrieve(&search_strategy, query)
+            .await
+            .unwrap();
+
+        // The best vector match and the best full-text match are both retrieved
+        let contents = result
+            .documents()
+            .iter()
+            .map(Document::content)
+            .collect::<Vec<_>>();
+        assert_eq!(contents.len(), 2);
+        assert!(contents.contains(&"swiftide is a rust library"));
+        assert!(contents.contains(&"something else entirely"));
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_retrieve_docs_with_metadata() {
         let test_context = TestContext::setup_with_cfg(
@@ -304,4 +730,77 @@ mod tests {
             Some(&serde_json::Value::from("some text"))
         );
     }
+
+    #[test_log::test(tokio::test)]
+    async fn test_scroll_all_nodes() {
+        let test_context = TestContext::setup_with_cfg(
+            vec!["index"].into(),
+            HashSet::from([EmbeddedField::Combined]),
+        )
+        .await
+        .expect("Test setup failed");
+
+        // More than a single page
+        let nodes = (0..150)
+            .map(|i| {
+                indexing::Node::new(format!("chunk {i}"))
+                    .with_metadata(("index", i))
+                    .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+
+        test_context
+            .pgv_storage
+            .batch_store(nodes.clone())
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut scrolled = test_context
+            .pgv_storage
+            .scroll()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        scrolled.sort_by_key(|node| node.metadata.get("index").unwrap().as_i64());
+
+        assert_eq!(scrolled, nodes);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_compressed_chunks_are_decompressed() {
+        let test_context = TestContext::setup_with_builder(
+            None,
+            HashSet::from([EmbeddedField::Combined]),
+            |builder| builder.chunk_compression(ChunkCompression::zstd()),
+        )
+        .await
+        .expect("Test setup failed");
+
+        let chunk = "Swiftide stores compressed chunks. ".repeat(50);
+        let node = indexing::Node::new(&chunk)
+            .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+            .to_owned();
+        test_context.pgv_storage.store(node).await.unwrap();
+
+        let pool = test_context.pgv_storage.get_pool().await.unwrap();
+        let stored: String = sqlx::query_scalar("SELECT chunk FROM swiftide_pgvector_test")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert!(stored.starts_with("zstd:"));
+        assert!(stored.len() < chunk.len());
+
+        let mut query = Query::<states::Pending>::new("test_query");
+        query.embedding = Some(vec![1.0; 384]);
+        let result = test_context
+            .pgv_storage
+            .retrieve(&SimilaritySingleEmbedding::default(), query)
+            .await
+            .unwrap();
+
+        assert_eq!(result.documents()[0].content(), chunk);
+    }
 }
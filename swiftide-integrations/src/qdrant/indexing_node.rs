@@ -13,7 +13,7 @@ use qdrant_client::{
     client::Payload,
     qdrant::{self, Value},
 };
-use swiftide_core::{indexing::EmbeddedField, Embedding, SparseEmbedding};
+use swiftide_core::{indexing::EmbeddedField, Embedding, MultiEmbedding, SparseEmbedding};
 
 use super::NodeWithVectors;
 
@@ -60,8 +60,18 @@ impl TryInto<qdrant::PointStruct> for NodeWithVectors<'_> {
         let Some(vectors) = node.vectors.clone() else {
             bail!("Node without vectors")
         };
-        let vectors =
-            try_create_vectors(&self.vector_fields, vectors, node.sparse_vectors.clone())?;
+        let multi_vectors = node
+            .multi_vectors
+            .iter()
+            .flatten()
+            .filter(|(field, _)| self.multi_vector_fields.contains(field))
+            .collect::<Vec<_>>();
+        let vectors = try_create_vectors(
+            &self.vector_fields,
+            vectors,
+            node.sparse_vectors.clone(),
+            multi_vectors,
+        )?;
 
         // Construct the `qdrant::PointStruct` and return it.
         Ok(qdrant::PointStruct::new(id.to_string(), vectors, payload))
@@ -72,12 +82,13 @@ fn try_create_vectors(
     vector_fields: &HashSet<&EmbeddedField>,
     vectors: HashMap<EmbeddedField, Embedding>,
     sparse_vectors: Option<HashMap<EmbeddedField, SparseEmbedding>>,
+    multi_vectors: Vec<(&EmbeddedField, &MultiEmbedding)>,
 ) -> Result<qdrant::Vectors> {
     dbg!(&vector_fields);
     dbg!(&vectors);
     if vectors.is_empty() {
         bail!("Node with empty vectors")
-    } else if vectors.len() == 1 && sparse_vectors.is_none() {
+    } else if vectors.len() == 1 && sparse_vectors.is_none() && multi_vectors.is_empty() {
         let Some(vector) = vectors.into_values().next() else {
             bail!("Node has no vector entry")
         };
@@ -108,6 +119,13 @@ fn try_create_vectors(
         }
     }
 
+    for (field, multi_vector) in multi_vectors {
+        qdrant_vectors = qdrant_vectors.add_vector(
+            field.multi_field_name(),
+            qdrant::Vector::new_multi(multi_vector.clone()),
+        );
+    }
+
     Ok(qdrant_vectors.into())
 }
 
@@ -115,7 +133,7 @@ fn try_create_vectors(
 mod tests {
     use std::collections::{HashMap, HashSet};
 
-    use qdrant_client::qdrant::PointStruct;
+    use qdrant_client::qdrant::{NamedVectors, PointStruct, Vector, Vectors};
     use swiftide_core::indexing::{EmbeddedField, Node};
     use test_case::test_case;
 
@@ -213,4 +231,30 @@ mod tests {
         assert_eq!(point.payload, expected_point.payload);
         assert_eq!(point.vectors, expected_point.vectors);
     }
+
+    #[test]
+    fn test_multi_vectors_are_named() {
+        let node = Node::builder()
+            .path("/path")
+            .chunk("data")
+            .vectors([(EmbeddedField::Combined, vec![1.0])])
+            .multi_vectors([(EmbeddedField::Combined, vec![vec![1.0], vec![2.0]])])
+            .build()
+            .unwrap();
+        let fields = HashSet::from([&EmbeddedField::Combined]);
+
+        let point: PointStruct = NodeWithVectors::new(&node, fields.clone())
+            .with_multi_vector_fields(fields)
+            .try_into()
+            .unwrap();
+
+        let expected: Vectors = NamedVectors::default()
+            .add_vector("Combined", vec![1.0])
+            .add_vector(
+                "Combined_multi",
+                Vector::new_multi(vec![vec![1.0], vec![2.0]]),
+            )
+            .into();
+        assert_eq!(point.vectors, Some(expected));
+    }
 }
@@ -7,19 +7,27 @@
 mod indexing_node;
 mod persist;
 mod retrieve;
+mod scroll;
 use std::collections::{HashMap, HashSet};
 
 use std::sync::Arc;
 
 use anyhow::{bail, Context as _, Result};
 use derive_builder::Builder;
-use qdrant_client::qdrant::{self, SparseVectorParamsBuilder, SparseVectorsConfigBuilder};
+use qdrant_client::{
+    qdrant::{self, SparseVectorParamsBuilder, SparseVectorsConfigBuilder},
+    QdrantError,
+};
 
-use swiftide_core::indexing::{EmbeddedField, Node};
+use swiftide_core::{
+    indexing::{EmbeddedField, Node, PersistError},
+    querying::RetrieveError,
+};
 
 const DEFAULT_COLLECTION_NAME: &str = "swiftide";
 const DEFAULT_QDRANT_URL: &str = "http://localhost:6334";
 const DEFAULT_BATCH_SIZE: usize = 50;
+const DEFAULT_TENANT_FIELD: &str = "tenant_id";
 
 /// A struct representing a Qdrant client with configuration options.
 ///
@@ -53,10 +61,56 @@ pub struct Qdrant {
     /// The batch size for operations. Optional.
     #[builder(default = "Some(DEFAULT_BATCH_SIZE)")]
     batch_size: Option<usize>,
+    /// Limits batches to an estimated size in bytes, in addition to the batch size. Optional.
+    ///
+    /// Keeps large nodes under the maximum request size of Qdrant, 32MB by default.
+    #[builder(default)]
+    max_batch_bytes: Option<usize>,
     #[builder(private, default = "Self::default_vectors()")]
     pub(crate) vectors: HashMap<EmbeddedField, VectorConfig>,
     #[builder(private, default)]
     pub(crate) sparse_vectors: HashMap<EmbeddedField, SparseVectorConfig>,
+    /// Multi-vectors, compared with `MaxSim`, see [`QdrantBuilder::with_multi_vector`]
+    #[builder(private, default)]
+    pub(crate) multi_vectors: HashMap<EmbeddedField, VectorConfig>,
+    /// HNSW index configuration of the collection, i.e. `m` and `ef_construct`.
+    ///
+    /// Uses the server defaults if not set.
+    #[builder(setter(into), default)]
+    hnsw_config: Option<qdrant::HnswConfigDiff>,
+    /// Scalar, product or binary quantization of the vectors in the collection.
+    ///
+    /// Quantized vectors are kept in memory, reducing memory usage and speeding up search at a
+    /// small cost in precision.
+    #[builder(setter(into), default)]
+    quantization_config: Option<qdrant::quantization_config::Quantization>,
+    /// Stores the payload on disk instead of in memory
+    #[builder(default)]
+    on_disk_payload: Option<bool>,
+    /// Stores the original vectors on disk instead of in memory
+    #[builder(default)]
+    on_disk_vectors: Option<bool>,
+    /// The number of replicas of every shard
+    #[builder(default)]
+    replication_factor: Option<u32>,
+    /// The number of shards the collection is split in
+    #[builder(default)]
+    shard_number: Option<u32>,
+    /// Payload indexes on metadata fields, created with the collection.
+    ///
+    /// See also [`QdrantBuilder::with_payload_index`]
+    #[builder(private, default)]
+    payload_indexes: HashMap<String, qdrant::FieldType>,
+    /// The payload field holding the tenant of every point. Defaults to "tenant_id".
+    #[builder(setter(into), default = "DEFAULT_TENANT_FIELD.to_string()")]
+    tenant_field: String,
+    /// The tenant to store and retrieve points for, allowing a single collection to serve many
+    /// tenants.
+    ///
+    /// When set, every stored point gets the tenant in its payload, and every retrieval and
+    /// deletion is limited to the points of the tenant. See also [`Qdrant::with_tenant`].
+    #[builder(setter(into), default)]
+    tenant: Option<String>,
 }
 
 impl Qdrant {
@@ -118,6 +172,23 @@ impl Qdrant {
             tracing::debug!(?sparse_vectors_config, "Adding sparse vectors config");
             collection = collection.sparse_vectors_config(sparse_vectors_config);
         }
+        if let Some(hnsw_config) = &self.hnsw_config {
+            tracing::debug!(?hnsw_config, "Adding hnsw config");
+            collection = collection.hnsw_config(hnsw_config.clone());
+        }
+        if let Some(quantization_config) = &self.quantization_config {
+            tracing::debug!(?quantization_config, "Adding quantization config");
+            collection = collection.quantization_config(quantization_config.clone());
+        }
+        if let Some(on_disk_payload) = self.on_disk_payload {
+            collection = collection.on_disk_payload(on_disk_payload);
+        }
+        if let Some(replication_factor) = self.replication_factor {
+            collection = collection.replication_factor(replication_factor);
+        }
+        if let Some(shard_number) = self.shard_number {
+            collection = collection.shard_number(shard_number);
+        }
         tracing::warn!("Creating collection {}", &self.collection_name);
 
         self.client.create_collection(collection).await?;
@@ -127,19 +198,28 @@ impl Qdrant {
     fn create_vectors_config(&self) -> Result<qdrant_client::qdrant::vectors_config::Config> {
         if self.vectors.is_empty() {
             bail!("No configured vectors");
-        } else if self.vectors.len() == 1 && self.sparse_vectors.is_empty() {
+        } else if self.vectors.len() == 1
+            && self.sparse_vectors.is_empty()
+            && self.multi_vectors.is_empty()
+        {
             let config = self
                 .vectors
                 .values()
                 .next()
                 .context("Has one vector config")?;
-            let vector_params = self.create_vector_params(config);
+            let vector_params = self.create_vector_params(config, false);
             return Ok(qdrant::vectors_config::Config::Params(vector_params));
         }
         let mut map = HashMap::<String, qdrant::VectorParams>::default();
         for (embedded_field, config) in &self.vectors {
             let vector_name = embedded_field.to_string();
-            let vector_params = self.create_vector_params(config);
+            let vector_params = self.create_vector_params(config, false);
+
+            map.insert(vector_name, vector_params);
+        }
+        for (embedded_field, config) in &self.multi_vectors {
+            let vector_name = embedded_field.multi_field_name();
+            let vector_params = self.create_vector_params(config, true);
 
             map.insert(vector_name, vector_params);
         }
@@ -163,7 +243,7 @@ impl Qdrant {
         Some(sparse_vectors_config.into())
     }
 
-    fn create_vector_params(&self, config: &VectorConfig) -> qdrant::VectorParams {
+    fn create_vector_params(&self, config: &VectorConfig, multi: bool) -> qdrant::VectorParams {
         let size = config.vector_size.unwrap_or(self.vector_size);
         let distance = config.distance.unwrap_or(self.vector_distance);
 
@@ -172,7 +252,95 @@ impl Qdrant {
             size,
             distance
         );
-        qdrant::VectorParamsBuilder::new(size, distance).build()
+        let mut vector_params = qdrant::VectorParamsBuilder::new(size, distance);
+        if let Some(on_disk) = self.on_disk_vectors {
+            vector_params = vector_params.on_disk(on_disk);
+        }
+        if multi {
+            vector_params = vector_params.multivector_config(
+                qdrant::MultiVectorConfigBuilder::new(qdrant::MultiVectorComparator::MaxSim),
+            );
+        }
+        vector_params.build()
+    }
+
+    /// Returns a copy scoped to the given tenant, sharing the client
+    ///
+    /// Useful to configure the collection once, and serve every request for its own tenant.
+    #[must_use]
+    pub fn with_tenant(&self, tenant: impl Into<String>) -> Self {
+        Self {
+            tenant: Some(tenant.into()),
+            ..self.clone()
+        }
+    }
+
+    /// Creates the configured payload indexes, and a tenant index on the tenant payload field if a
+    /// tenant is configured.
+    ///
+    /// Existing indexes are left as is.
+    ///
+    /// # Errors
+    ///
+    /// Errors if an index cannot be created
+    pub async fn create_payload_indexes(&self) -> Result<()> {
+        for (field, field_type) in &self.payload_indexes {
+            tracing::debug!("Creating payload index on {field}");
+            self.client
+                .create_field_index(
+                    qdrant::CreateFieldIndexCollectionBuilder::new(
+                        &self.collection_name,
+                        field,
+                        *field_type,
+                    )
+                    .wait(true),
+                )
+                .await?;
+        }
+
+        if self.tenant.is_some() {
+            tracing::debug!("Creating tenant index on {}", &self.tenant_field);
+            self.client
+                .create_field_index(
+                    qdrant::CreateFieldIndexCollectionBuilder::new(
+                        &self.collection_name,
+                        &self.tenant_field,
+                        qdrant::FieldType::Keyword,
+                    )
+                    .field_index_params(
+                        qdrant::KeywordIndexParamsBuilder::default().is_tenant(true),
+                    )
+                    .wait(true),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Limits a filter to the points of the tenant, if a tenant is configured
+    pub(crate) fn tenant_filter(&self, filter: Option<qdrant::Filter>) -> Option<qdrant::Filter> {
+        let Some(tenant) = &self.tenant else {
+            return filter;
+        };
+
+        let mut filter = filter.unwrap_or_default();
+        filter.must.push(qdrant::Condition::matches(
+            self.tenant_field.clone(),
+            tenant.clone(),
+        ));
+        Some(filter)
+    }
+
+    /// Returns the point id of a node
+    ///
+    /// With a tenant the id is derived from the tenant as well, so that tenants storing identical
+    /// nodes do not overwrite each others points.
+    pub(crate) fn point_id(&self, id: uuid::Uuid) -> uuid::Uuid {
+        match &self.tenant {
+            Some(tenant) => uuid::Uuid::new_v3(&id, tenant.as_bytes()),
+            None => id,
+        }
     }
 
     /// Returns the inner client for custom operations
@@ -234,6 +402,41 @@ impl QdrantBuilder {
         self
     }
 
+    /// Configures a multi-vector on the collection, holding an embedding per token
+    ///
+    /// Multi-vectors are compared with `MaxSim`, for late interaction retrieval with models like
+    /// `ColBERT`. See also `swiftide_core::querying::search_strategies::MultiVectorSearch`.
+    #[must_use]
+    pub fn with_multi_vector(mut self, vector: impl Into<VectorConfig>) -> QdrantBuilder {
+        let vector = vector.into();
+        if let Some(overridden_vector) = self
+            .multi_vectors
+            .get_or_insert_with(HashMap::default)
+            .insert(vector.embedded_field.clone(), vector)
+        {
+            tracing::warn!(
+                "Overriding multi-vector config: {}",
+                overridden_vector.embedded_field
+            );
+        }
+        self
+    }
+
+    /// Configures a payload index on a metadata field, i.e. to filter on it efficiently
+    ///
+    /// Filtered searches without a payload index degrade badly on large collections.
+    #[must_use]
+    pub fn with_payload_index(
+        mut self,
+        field: impl Into<String>,
+        field_type: qdrant::FieldType,
+    ) -> QdrantBuilder {
+        self.payload_indexes
+            .get_or_insert_with(HashMap::default)
+            .insert(field.into(), field_type);
+        self
+    }
+
     fn default_vectors() -> HashMap<EmbeddedField, VectorConfig> {
         HashMap::from([(EmbeddedField::default(), VectorConfig::default())])
     }
@@ -246,6 +449,8 @@ impl std::fmt::Debug for Qdrant {
             .field("collection_name", &self.collection_name)
             .field("vector_size", &self.vector_size)
             .field("batch_size", &self.batch_size)
+            .field("max_batch_bytes", &self.max_batch_bytes)
+            .field("tenant", &self.tenant)
             .finish()
     }
 }
@@ -305,6 +510,7 @@ pub type Distance = qdrant::Distance;
 struct NodeWithVectors<'a> {
     node: &'a Node,
     vector_fields: HashSet<&'a EmbeddedField>,
+    multi_vector_fields: HashSet<&'a EmbeddedField>,
 }
 
 impl<'a> NodeWithVectors<'a> {
@@ -312,6 +518,71 @@ impl<'a> NodeWithVectors<'a> {
         Self {
             node,
             vector_fields,
+            multi_vector_fields: HashSet::new(),
         }
     }
+
+    pub fn with_multi_vector_fields(
+        mut self,
+        multi_vector_fields: HashSet<&'a EmbeddedField>,
+    ) -> Self {
+        self.multi_vector_fields = multi_vector_fields;
+        self
+    }
+}
+
+/// Connection errors, timeouts and rate limits might succeed on a retry
+fn is_transient(err: &QdrantError) -> bool {
+    match err {
+        QdrantError::ResponseError { status } => matches!(
+            status.code(),
+            tonic::Code::Unavailable
+                | tonic::Code::DeadlineExceeded
+                | tonic::Code::Aborted
+                | tonic::Code::Cancelled
+        ),
+        QdrantError::ResourceExhaustedError { .. } | QdrantError::Io(_) => true,
+        _ => false,
+    }
+}
+
+/// Classifies errors of the client, keeping the `QdrantError`
+fn persist_error(err: QdrantError) -> PersistError {
+    if is_transient(&err) {
+        PersistError::transient(err)
+    } else {
+        PersistError::permanent(err)
+    }
+}
+
+/// Classifies errors of the client, keeping the `QdrantError`
+fn retrieve_error(err: QdrantError) -> RetrieveError {
+    if is_transient(&err) {
+        RetrieveError::transient(err)
+    } else {
+        RetrieveError::permanent(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_errors_are_transient() {
+        let unavailable = QdrantError::ResponseError {
+            status: tonic::Status::unavailable("connection refused"),
+        };
+        assert!(persist_error(unavailable).is_transient());
+
+        let timeout = QdrantError::ResponseError {
+            status: tonic::Status::deadline_exceeded("timed out"),
+        };
+        assert!(retrieve_error(timeout).is_transient());
+
+        let not_found = QdrantError::ResponseError {
+            status: tonic::Status::not_found("collection not found"),
+        };
+        assert!(!persist_error(not_found).is_transient());
+    }
 }
@@ -4,13 +4,14 @@
 
 use std::collections::HashSet;
 use swiftide_core::{
-    indexing::{EmbeddedField, IndexingStream, Node, Persist},
+    indexing::{EmbeddedField, IndexingStream, Metadata, Node, Persist, PersistError},
     prelude::*,
 };
 
-use qdrant_client::qdrant::UpsertPointsBuilder;
+use qdrant_client::qdrant::{self, DeletePointsBuilder, UpsertPointsBuilder};
 
-use super::{NodeWithVectors, Qdrant};
+use super::{persist_error, NodeWithVectors, Qdrant};
+use crate::metrics;
 
 #[async_trait]
 impl Persist for Qdrant {
@@ -23,7 +24,12 @@ impl Persist for Qdrant {
         self.batch_size
     }
 
-    /// Sets up the Qdrant storage by creating the necessary index if it does not exist.
+    fn max_batch_bytes(&self) -> Option<usize> {
+        self.max_batch_bytes
+    }
+
+    /// Sets up the Qdrant storage by creating the necessary index if it does not exist, and the
+    /// configured payload indexes.
     ///
     /// # Returns
     ///
@@ -33,9 +39,12 @@ impl Persist for Qdrant {
     ///
     /// This function will return an error if the index creation fails.
     #[tracing::instrument(skip_all, err)]
-    async fn setup(&self) -> Result<()> {
+    async fn setup(&self) -> Result<(), PersistError> {
         tracing::debug!("Setting up Qdrant storage");
-        self.create_index_if_not_exists().await
+        self.create_index_if_not_exists().await?;
+        self.create_payload_indexes().await?;
+
+        Ok(())
     }
 
     /// Stores a single indexing node in the Qdrant storage.
@@ -54,18 +63,23 @@ impl Persist for Qdrant {
     ///
     /// This function will return an error if the node conversion or storage operation fails.
     #[tracing::instrument(skip_all, err, name = "storage.qdrant.store")]
-    async fn store(&self, node: Node) -> Result<Node> {
-        let node_with_vectors = NodeWithVectors::new(&node, self.vector_fields());
-        let point = node_with_vectors.try_into()?;
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        let point = self.node_to_point(&node)?;
 
         tracing::debug!("Storing node");
 
-        self.client
-            .upsert_points(
+        metrics::measure(
+            "qdrant",
+            &self.collection_name,
+            "store",
+            1,
+            self.client.upsert_points(
                 UpsertPointsBuilder::new(self.collection_name.to_string(), vec![point])
                     .wait(cfg!(debug_assertions)),
-            )
-            .await?;
+            ),
+        )
+        .await
+        .map_err(persist_error)?;
         Ok(node)
     }
 
@@ -86,8 +100,7 @@ impl Persist for Qdrant {
     async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
         let points = nodes
             .iter()
-            .map(|node| NodeWithVectors::new(node, self.vector_fields()))
-            .map(NodeWithVectors::try_into)
+            .map(|node| self.node_to_point(node))
             .collect::<Result<Vec<_>>>();
 
         let Ok(points) = points else {
@@ -96,24 +109,275 @@ impl Persist for Qdrant {
 
         tracing::debug!("Storing batch of {} nodes", points.len());
 
-        let result = self
-            .client
-            .upsert_points(
+        let result = metrics::measure(
+            "qdrant",
+            &self.collection_name,
+            "batch_store",
+            points.len(),
+            self.client.upsert_points(
                 UpsertPointsBuilder::new(self.collection_name.to_string(), points)
                     .wait(cfg!(debug_assertions)),
-            )
-            .await;
+            ),
+        )
+        .await;
 
-        if result.is_ok() {
-            IndexingStream::iter(nodes.into_iter().map(Ok))
-        } else {
-            vec![Err(result.unwrap_err().into())].into()
+        match result {
+            Ok(_) => IndexingStream::iter(nodes.into_iter().map(Ok)),
+            Err(err) => vec![Err(persist_error(err).into())].into(),
         }
     }
+
+    /// Deletes the points of the nodes with the given ids
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the delete operation fails.
+    #[tracing::instrument(skip_all, err, name = "storage.qdrant.delete")]
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        self.delete_by_ids(ids).await?;
+
+        Ok(())
+    }
+
+    /// Deletes the points with a payload matching every entry of the metadata
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a value cannot be matched on, i.e. floats or
+    /// objects, or if the delete operation fails.
+    #[tracing::instrument(skip_all, err, name = "storage.qdrant.delete_by_metadata")]
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        let conditions = filter
+            .into_iter()
+            .map(|(key, value)| {
+                let value: qdrant::r#match::MatchValue = match value {
+                    serde_json::Value::String(value) => value.into(),
+                    serde_json::Value::Bool(value) => value.into(),
+                    serde_json::Value::Number(ref number) if number.is_i64() => {
+                        number.as_i64().unwrap_or_default().into()
+                    }
+                    value => anyhow::bail!("Cannot delete by metadata value {value} of {key}"),
+                };
+                Ok(qdrant::Condition::matches(key, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.delete_by_filter(qdrant::Filter::must(conditions))
+            .await?;
+
+        Ok(())
+    }
 }
 
 impl Qdrant {
+    /// Deletes the points with the given node ids from the collection.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the delete operation fails.
+    pub async fn delete_by_ids(&self, ids: impl IntoIterator<Item = uuid::Uuid>) -> Result<()> {
+        let ids = ids
+            .into_iter()
+            .map(|id| self.point_id(id).to_string().into())
+            .collect::<Vec<qdrant::PointId>>();
+        tracing::debug!("Deleting {} points", ids.len());
+
+        // Points of other tenants are never deleted, even if their id is known
+        if self.tenant.is_some() {
+            return self
+                .delete_by_filter(qdrant::Filter::must([qdrant::Condition::has_id(ids)]))
+                .await;
+        }
+
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(self.collection_name.to_string())
+                    .points(qdrant::PointsIdsList { ids })
+                    .wait(true),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes all points matching the filter from the collection, i.e. all nodes of a path.
+    ///
+    /// With a tenant, only points of the tenant are deleted.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the delete operation fails.
+    pub async fn delete_by_filter(&self, filter: qdrant::Filter) -> Result<()> {
+        let filter = self.tenant_filter(Some(filter)).unwrap_or_default();
+        tracing::debug!(?filter, "Deleting points by filter");
+
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(self.collection_name.to_string())
+                    .points(filter)
+                    .wait(true),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the collection with all its points, and creates it again with the configured
+    /// vectors.
+    ///
+    /// With a tenant, only the points of the tenant are deleted and the collection is kept.
+    ///
+    /// # Errors
+    ///
+    /// Errors if deleting or creating the collection fails.
+    pub async fn purge_collection(&self) -> Result<()> {
+        if self.tenant.is_some() {
+            return self.delete_by_filter(qdrant::Filter::default()).await;
+        }
+
+        tracing::warn!("Purging collection {}", &self.collection_name);
+
+        if self.client.collection_exists(&self.collection_name).await? {
+            self.client.delete_collection(&self.collection_name).await?;
+        }
+        self.create_index_if_not_exists().await
+    }
+
+    fn node_to_point(&self, node: &Node) -> Result<qdrant::PointStruct> {
+        let mut point: qdrant::PointStruct = NodeWithVectors::new(node, self.vector_fields())
+            .with_multi_vector_fields(self.multi_vectors.keys().collect())
+            .try_into()?;
+
+        if let Some(tenant) = &self.tenant {
+            point.id = Some(self.point_id(node.id()).to_string().into());
+            point
+                .payload
+                .insert(self.tenant_field.clone(), tenant.clone().into());
+        }
+
+        Ok(point)
+    }
+
     fn vector_fields(&self) -> HashSet<&EmbeddedField> {
         self.vectors.keys().collect::<HashSet<_>>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt as _;
+    use qdrant_client::qdrant::CountPointsBuilder;
+
+    use super::*;
+
+    async fn count(qdrant: &Qdrant) -> u64 {
+        qdrant
+            .client()
+            .count(CountPointsBuilder::new(qdrant.collection_name.clone()).exact(true))
+            .await
+            .unwrap()
+            .result
+            .unwrap()
+            .count
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_setup_with_collection_config() {
+        let (_guard, qdrant_url) = swiftide_test_utils::start_qdrant().await;
+
+        let qdrant = Qdrant::try_from_url(qdrant_url)
+            .unwrap()
+            .vector_size(3)
+            .hnsw_config(
+                qdrant::HnswConfigDiffBuilder::default()
+                    .m(32)
+                    .ef_construct(200),
+            )
+            .quantization_config(qdrant::ScalarQuantizationBuilder::default().always_ram(true))
+            .on_disk_vectors(true)
+            .on_disk_payload(true)
+            .with_payload_index("category", qdrant::FieldType::Keyword)
+            .with_payload_index("published_at", qdrant::FieldType::Datetime)
+            .build()
+            .unwrap();
+        qdrant.setup().await.unwrap();
+
+        let info = qdrant
+            .client()
+            .collection_info(qdrant.collection_name.clone())
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        assert_eq!(
+            info.payload_schema["category"].data_type,
+            i32::from(qdrant::PayloadSchemaType::Keyword)
+        );
+        assert_eq!(
+            info.payload_schema["published_at"].data_type,
+            i32::from(qdrant::PayloadSchemaType::Datetime)
+        );
+
+        let config = info.config.unwrap();
+
+        let hnsw_config = config.hnsw_config.unwrap();
+        assert_eq!(hnsw_config.m, Some(32));
+        assert_eq!(hnsw_config.ef_construct, Some(200));
+        assert!(config.quantization_config.is_some());
+        assert!(config.params.unwrap().on_disk_payload);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_delete_and_purge() {
+        let (_guard, qdrant_url) = swiftide_test_utils::start_qdrant().await;
+
+        let qdrant = Qdrant::try_from_url(qdrant_url)
+            .unwrap()
+            .vector_size(3)
+            .build()
+            .unwrap();
+        qdrant.setup().await.unwrap();
+
+        let nodes = ["first", "second", "third", "fourth"]
+            .into_iter()
+            .map(|chunk| {
+                Node::new(chunk)
+                    .with_metadata([
+                        ("filter", serde_json::Value::from(chunk.len() > 5)),
+                        ("chunk", serde_json::Value::from(chunk)),
+                    ])
+                    .with_vectors([(EmbeddedField::Combined, vec![1.0; 3])])
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+        let ids = nodes.iter().map(Node::id).collect::<Vec<_>>();
+
+        qdrant
+            .batch_store(nodes)
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(count(&qdrant).await, 4);
+
+        qdrant.delete(vec![ids[0]]).await.unwrap();
+        assert_eq!(count(&qdrant).await, 3);
+
+        // Deletes "fourth"
+        qdrant
+            .delete_by_metadata(("chunk", "fourth").into())
+            .await
+            .unwrap();
+        assert_eq!(count(&qdrant).await, 2);
+
+        // Deletes "second"
+        qdrant
+            .delete_by_filter(qdrant::Filter::must([qdrant::Condition::matches(
+                "filter", true,
+            )]))
+            .await
+            .unwrap();
+        assert_eq!(count(&qdrant).await, 1);
+
+        qdrant.purge_collection().await.unwrap();
+        assert_eq!(count(&qdrant).await, 0);
+    }
+}
@@ -4,13 +4,14 @@ use swiftide_core::{
     indexing::{EmbeddedField, Metadata},
     prelude::{Result, *},
     querying::{
-        search_strategies::{HybridSearch, SimilaritySingleEmbedding},
-        states, Query,
+        search_strategies::{HybridSearch, MultiVectorSearch, SimilaritySingleEmbedding},
+        states, Query, RetrieveError,
     },
     Retrieve,
 };
 
-use super::Qdrant;
+use super::{retrieve_error, Qdrant};
+use crate::metrics;
 
 /// Implement the `Retrieve` trait for `SimilaritySingleEmbedding` search strategy.
 ///
@@ -24,9 +25,9 @@ impl Retrieve<SimilaritySingleEmbedding<qdrant::Filter>> for Qdrant {
         &self,
         search_strategy: &SimilaritySingleEmbedding<qdrant::Filter>,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         let Some(embedding) = &query.embedding else {
-            anyhow::bail!("No embedding for query")
+            return Err(RetrieveError::MissingEmbedding);
         };
         let mut query_builder = SearchPointsBuilder::new(
             &self.collection_name,
@@ -35,8 +36,8 @@ impl Retrieve<SimilaritySingleEmbedding<qdrant::Filter>> for Qdrant {
         )
         .with_payload(true);
 
-        if let Some(filter) = &search_strategy.filter() {
-            query_builder = query_builder.filter(filter.to_owned());
+        if let Some(filter) = self.tenant_filter(search_strategy.filter().clone()) {
+            query_builder = query_builder.filter(filter);
         }
 
         if self.vectors.len() > 1 || !self.sparse_vectors.is_empty() {
@@ -45,12 +46,16 @@ impl Retrieve<SimilaritySingleEmbedding<qdrant::Filter>> for Qdrant {
             query_builder = query_builder.vector_name(EmbeddedField::Combined.field_name());
         }
 
-        let result = self
-            .client
-            .search_points(query_builder.build())
-            .await
-            .context("Failed to retrieve from qdrant")?
-            .result;
+        let result = metrics::measure(
+            "qdrant",
+            &self.collection_name,
+            "retrieve",
+            0,
+            self.client.search_points(query_builder.build()),
+        )
+        .await
+        .map_err(retrieve_error)?
+        .result;
 
         let documents = result
             .into_iter()
@@ -68,7 +73,7 @@ impl Retrieve<SimilaritySingleEmbedding> for Qdrant {
         &self,
         search_strategy: &SimilaritySingleEmbedding,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         Retrieve::<SimilaritySingleEmbedding<qdrant::Filter>>::retrieve(
             self,
             &search_strategy.into_concrete_filter::<qdrant::Filter>(),
@@ -90,41 +95,105 @@ impl Retrieve<HybridSearch> for Qdrant {
         &self,
         search_strategy: &HybridSearch,
         query: Query<states::Pending>,
-    ) -> Result<Query<states::Retrieved>> {
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
         let Some(dense) = &query.embedding else {
-            anyhow::bail!("No embedding for query")
+            return Err(RetrieveError::MissingEmbedding);
         };
 
         let Some(sparse) = &query.sparse_embedding else {
-            anyhow::bail!("No sparse embedding for query")
+            return Err(RetrieveError::permanent("No sparse embedding for query"));
         };
 
         // NOTE: Potential improvement to consume the vectors instead of cloning
-        let result = self
-            .client
-            .query(
-                qdrant::QueryPointsBuilder::new(&self.collection_name)
-                    .with_payload(true)
-                    .add_prefetch(
-                        PrefetchQueryBuilder::default()
-                            .query(qdrant::Query::new_nearest(qdrant::VectorInput::new_sparse(
-                                sparse.indices.clone(),
-                                sparse.values.clone(),
-                            )))
-                            .using(search_strategy.sparse_vector_field().sparse_field_name())
-                            .limit(search_strategy.top_n()),
-                    )
-                    .add_prefetch(
-                        PrefetchQueryBuilder::default()
-                            .query(qdrant::Query::new_nearest(dense.clone()))
-                            .using(search_strategy.dense_vector_field().field_name())
-                            .limit(search_strategy.top_n()),
-                    )
-                    .query(qdrant::Query::new_fusion(qdrant::Fusion::Rrf))
-                    .limit(search_strategy.top_k()),
-            )
-            .await?
-            .result;
+        let mut sparse_prefetch = PrefetchQueryBuilder::default()
+            .query(qdrant::Query::new_nearest(qdrant::VectorInput::new_sparse(
+                sparse.indices.clone(),
+                sparse.values.clone(),
+            )))
+            .using(search_strategy.sparse_vector_field().sparse_field_name())
+            .limit(search_strategy.top_n());
+        let mut dense_prefetch = PrefetchQueryBuilder::default()
+            .query(qdrant::Query::new_nearest(dense.clone()))
+            .using(search_strategy.dense_vector_field().field_name())
+            .limit(search_strategy.top_n());
+        let mut query_builder = qdrant::QueryPointsBuilder::new(&self.collection_name)
+            .with_payload(true)
+            .query(qdrant::Query::new_fusion(qdrant::Fusion::Rrf))
+            .limit(search_strategy.top_k());
+
+        // Both the candidates and the fused results are limited to the tenant
+        if let Some(filter) = self.tenant_filter(None) {
+            sparse_prefetch = sparse_prefetch.filter(filter.clone());
+            dense_prefetch = dense_prefetch.filter(filter.clone());
+            query_builder = query_builder.filter(filter);
+        }
+
+        let result = metrics::measure(
+            "qdrant",
+            &self.collection_name,
+            "retrieve_hybrid",
+            0,
+            self.client.query(
+                query_builder
+                    .add_prefetch(sparse_prefetch)
+                    .add_prefetch(dense_prefetch),
+            ),
+        )
+        .await
+        .map_err(retrieve_error)?
+        .result;
+
+        let documents = result
+            .into_iter()
+            .map(scored_point_into_document)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+/// Implement the `Retrieve` trait for the `MultiVectorSearch` search strategy.
+///
+/// Qdrant scores the points by `MaxSim` of the query and their multi-vector, see
+/// [`super::QdrantBuilder::with_multi_vector`].
+///
+/// Expects a multi-vector embedding to be set on the query.
+#[async_trait]
+impl Retrieve<MultiVectorSearch> for Qdrant {
+    #[tracing::instrument]
+    async fn retrieve(
+        &self,
+        search_strategy: &MultiVectorSearch,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.multi_embedding else {
+            return Err(RetrieveError::permanent(
+                "No multi-vector embedding for query",
+            ));
+        };
+
+        let mut query_builder = qdrant::QueryPointsBuilder::new(&self.collection_name)
+            .with_payload(true)
+            .query(qdrant::Query::new_nearest(qdrant::VectorInput::new_multi(
+                embedding.clone(),
+            )))
+            .using(search_strategy.vector_field().multi_field_name())
+            .limit(search_strategy.top_k());
+
+        if let Some(filter) = self.tenant_filter(None) {
+            query_builder = query_builder.filter(filter);
+        }
+
+        let result = metrics::measure(
+            "qdrant",
+            &self.collection_name,
+            "retrieve_multi_vector",
+            0,
+            self.client.query(query_builder),
+        )
+        .await
+        .map_err(retrieve_error)?
+        .result;
 
         let documents = result
             .into_iter()
@@ -283,4 +352,49 @@ mod tests {
             .unwrap();
         assert_eq!(result.documents().len(), 3);
     }
+
+    #[test_log::test(tokio::test)]
+    async fn test_retrieve_with_tenants() {
+        let (_guard, qdrant_url) = swiftide_test_utils::start_qdrant().await;
+
+        let qdrant_client = Qdrant::try_from_url(qdrant_url)
+            .unwrap()
+            .vector_size(384)
+            .tenant("tenant_a")
+            .build()
+            .unwrap();
+        qdrant_client.setup().await.unwrap();
+
+        let node = indexing::Node::new("shared")
+            .with_vectors([(EmbeddedField::Combined, vec![1.0; 384])])
+            .to_owned();
+
+        // The same node is stored for both tenants, and only once for tenant a
+        for tenant_client in [&qdrant_client, &qdrant_client.with_tenant("tenant_b")] {
+            tenant_client.store(node.clone()).await.unwrap();
+        }
+        qdrant_client.store(node.clone()).await.unwrap();
+
+        let mut query = Query::<states::Pending>::new("test_query");
+        query.embedding = Some(vec![1.0; 384]);
+
+        let search_strategy = SimilaritySingleEmbedding::<()>::default();
+        let result = qdrant_client
+            .retrieve(&search_strategy, query.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.documents().len(), 1);
+        assert_eq!(
+            result.documents()[0].metadata().get("tenant_id"),
+            Some(&serde_json::Value::from("tenant_a"))
+        );
+
+        qdrant_client.purge_collection().await.unwrap();
+        let result = qdrant_client
+            .with_tenant("tenant_b")
+            .retrieve(&search_strategy, query)
+            .await
+            .unwrap();
+        assert_eq!(result.documents().len(), 1);
+    }
 }
@@ -0,0 +1,169 @@
+//! Streams all points of a collection back as nodes, i.e. for exports and migrations.
+
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use futures_util::{stream, StreamExt as _, TryStreamExt as _};
+use qdrant_client::qdrant::{self, vectors_output::VectorsOptions, ScrollPointsBuilder};
+use swiftide_core::{
+    indexing::{IndexingStream, Metadata, Node},
+    Scroll, SparseEmbedding,
+};
+
+use super::Qdrant;
+
+/// The number of points fetched per request
+const SCROLL_LIMIT: u32 = 100;
+
+impl Scroll for Qdrant {
+    /// Streams all points of the collection, or of the tenant if one is configured, with their
+    /// payload and vectors.
+    ///
+    /// The content and path are restored from the payload, and any other payload except the
+    /// update timestamp and tenant is restored as metadata.
+    fn scroll(&self) -> IndexingStream {
+        let qdrant = self.clone();
+
+        // `None` when done, `Some(None)` for the first page
+        stream::try_unfold(Some(None), move |offset| {
+            let qdrant = qdrant.clone();
+            async move {
+                let Some(offset) = offset else {
+                    return Ok(None);
+                };
+                let (nodes, next_offset) = qdrant.scroll_page(offset).await?;
+
+                Ok::<_, anyhow::Error>(Some((nodes, next_offset.map(Some))))
+            }
+        })
+        .map_ok(|nodes| stream::iter(nodes.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+        .into()
+    }
+}
+
+impl Qdrant {
+    async fn scroll_page(
+        &self,
+        offset: Option<qdrant::PointId>,
+    ) -> Result<(Vec<Node>, Option<qdrant::PointId>)> {
+        let mut request = ScrollPointsBuilder::new(&self.collection_name)
+            .limit(SCROLL_LIMIT)
+            .with_payload(true)
+            .with_vectors(true);
+        if let Some(filter) = self.tenant_filter(None) {
+            request = request.filter(filter);
+        }
+        if let Some(offset) = offset {
+            request = request.offset(offset);
+        }
+
+        let response = self.client.scroll(request).await?;
+        let nodes = response
+            .result
+            .into_iter()
+            .map(|point| self.retrieved_point_into_node(point))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((nodes, response.next_page_offset))
+    }
+
+    fn retrieved_point_into_node(&self, point: qdrant::RetrievedPoint) -> Result<Node> {
+        let mut payload = point.payload;
+        let chunk = payload
+            .remove("content")
+            .and_then(|content| content.as_str().cloned())
+            .context("Expected content in qdrant payload")?;
+        let path = payload
+            .remove("path")
+            .and_then(|path| path.as_str().cloned())
+            .unwrap_or_default();
+        payload.remove("last_updated_at");
+        if self.tenant.is_some() {
+            payload.remove(&self.tenant_field);
+        }
+        let metadata: Metadata = payload.into_iter().collect::<Vec<(_, _)>>().into();
+
+        let mut vectors = HashMap::new();
+        let mut sparse_vectors = HashMap::new();
+        match point.vectors.and_then(|vectors| vectors.vectors_options) {
+            // A single vector is stored unnamed
+            Some(VectorsOptions::Vector(vector)) => {
+                let field = self.vectors.keys().next().cloned().unwrap_or_default();
+                vectors.insert(field, vector.data);
+            }
+            Some(VectorsOptions::Vectors(named)) => {
+                for (name, vector) in named.vectors {
+                    if let Some(field) = name.strip_suffix("_sparse") {
+                        sparse_vectors.insert(
+                            field.parse()?,
+                            SparseEmbedding {
+                                indices: vector
+                                    .indices
+                                    .map(|indices| indices.data)
+                                    .unwrap_or_default(),
+                                values: vector.data,
+                            },
+                        );
+                    } else {
+                        vectors.insert(name.parse()?, vector.data);
+                    }
+                }
+            }
+            None => (),
+        }
+
+        Node::builder()
+            .path(path)
+            .chunk(chunk)
+            .metadata(metadata)
+            .maybe_vectors((!vectors.is_empty()).then_some(vectors))
+            .maybe_sparse_vectors((!sparse_vectors.is_empty()).then_some(sparse_vectors))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt as _;
+    use swiftide_core::{indexing::EmbeddedField, Persist as _};
+
+    use super::*;
+
+    #[test_log::test(tokio::test)]
+    async fn test_scroll_all_nodes() {
+        let (_guard, qdrant_url) = swiftide_test_utils::start_qdrant().await;
+
+        let qdrant = Qdrant::try_from_url(qdrant_url)
+            .unwrap()
+            .vector_size(3)
+            .build()
+            .unwrap();
+        qdrant.setup().await.unwrap();
+
+        // More than a single page
+        let nodes = (0..150)
+            .map(|i| {
+                Node::builder()
+                    .path("src/lib.rs")
+                    .chunk(format!("chunk {i}"))
+                    .metadata(Metadata::from(("index", i)))
+                    .vectors([(EmbeddedField::Combined, vec![1.0, 2.0, 3.0])])
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        qdrant
+            .batch_store(nodes.clone())
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut scrolled = qdrant.scroll().try_collect::<Vec<_>>().await.unwrap();
+        scrolled.sort_by_key(|node| node.metadata.get("index").unwrap().as_i64());
+
+        assert_eq!(scrolled, nodes);
+    }
+}
@@ -1,6 +1,8 @@
 //! Redb is a simple, portable, high-performance, ACID, embedded key-value store.
 //!
-//! Redb can be used as a fast, embedded node cache, without the need for external services.
+//! Redb can be used as a fast, embedded node cache, without the need for external services. It can
+//! also cache language model responses, see `swiftide_core::response_cache`, and persist nodes,
+//! which is useful for small applications and tests.
 
 use anyhow::Result;
 use std::{path::PathBuf, sync::Arc};
@@ -8,12 +10,16 @@ use std::{path::PathBuf, sync::Arc};
 use derive_builder::Builder;
 
 mod node_cache;
+mod persist;
+mod response_cache;
 
 /// `Redb` provides a caching filter for indexing nodes using Redb.
 ///
 /// Redb is a simple, portable, high-performance, ACID, embedded key-value store.
 /// It enables using a local file based cache without the need for external services.
 ///
+/// Also implements `Persist` and `Scroll`, storing nodes as json in a table suffixed with `_nodes`.
+///
 /// # Example
 ///
 /// ```no_run
@@ -43,6 +49,12 @@ pub struct Redb {
     /// manually invalidate the cache.
     #[builder(default = "String::new()")]
     cache_key_prefix: String,
+    /// Whether to store the vectors of nodes when persisting, defaults to true
+    #[builder(default = "true")]
+    persist_vectors: bool,
+    /// The number of nodes stored per transaction when persisting
+    #[builder(default, setter(strip_option))]
+    batch_size: Option<usize>,
 }
 
 impl std::fmt::Debug for Redb {
@@ -52,6 +64,8 @@ impl std::fmt::Debug for Redb {
             .field("database_path", &self.database_path)
             .field("table_name", &self.table_name)
             .field("cache_key_prefix", &self.cache_key_prefix)
+            .field("persist_vectors", &self.persist_vectors)
+            .field("batch_size", &self.batch_size)
             .finish()
     }
 }
@@ -74,13 +88,42 @@ impl Redb {
         RedbBuilder::default()
     }
     pub fn node_key(&self, node: &swiftide_core::indexing::Node) -> String {
-        format!("{}.{}", self.cache_key_prefix, node.id())
+        self.node_id_key(node.id())
+    }
+
+    fn node_id_key(&self, id: uuid::Uuid) -> String {
+        format!("{}.{id}", self.cache_key_prefix)
     }
 
     pub fn table_definition(&self) -> redb::TableDefinition<String, bool> {
         redb::TableDefinition::<String, bool>::new(&self.table_name)
     }
 
+    /// Responses are stored in a separate table, suffixed with `_responses`, with the value and
+    /// its expiry in milliseconds since the unix epoch
+    pub fn response_table_name(&self) -> String {
+        format!("{}_responses", self.table_name)
+    }
+
+    fn response_table_definition(
+        table_name: &str,
+    ) -> redb::TableDefinition<'_, String, (String, Option<u64>)> {
+        redb::TableDefinition::new(table_name)
+    }
+
+    /// Persisted nodes are stored in a separate table, suffixed with `_nodes`, as json
+    pub fn node_table_name(&self) -> String {
+        format!("{}_nodes", self.table_name)
+    }
+
+    fn node_table_definition(table_name: &str) -> redb::TableDefinition<'_, String, String> {
+        redb::TableDefinition::new(table_name)
+    }
+
+    fn response_key(&self, key: &str) -> String {
+        format!("{}.{key}", self.cache_key_prefix)
+    }
+
     pub fn database(&self) -> &redb::Database {
         &self.database
     }
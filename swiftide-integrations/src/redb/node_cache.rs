@@ -1,6 +1,9 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use swiftide_core::{indexing::Node, NodeCache};
+use swiftide_core::{
+    indexing::{Node, NodeCacheError},
+    NodeCache,
+};
 
 use super::Redb;
 
@@ -71,7 +74,7 @@ impl NodeCache for Redb {
     }
 
     /// Deletes the full cache table from the database.
-    async fn clear(&self) -> Result<()> {
+    async fn clear(&self) -> Result<(), NodeCacheError> {
         let write_txn = self.database.begin_write().unwrap();
         let _ = write_txn.delete_table(self.table_definition());
 
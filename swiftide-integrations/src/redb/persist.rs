@@ -0,0 +1,225 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt as _};
+use redb::ReadableTable as _;
+use swiftide_core::{
+    indexing::{IndexingStream, Metadata, Node, NodeRecord, PersistError},
+    Persist, Scroll,
+};
+
+use super::Redb;
+
+#[async_trait]
+impl Persist for Redb {
+    /// Creates the node table if it does not exist
+    async fn setup(&self) -> Result<(), PersistError> {
+        self.create_node_table()?;
+
+        Ok(())
+    }
+
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        self.store_nodes(std::slice::from_ref(&node))?;
+        Ok(node)
+    }
+
+    /// Stores all nodes in a single transaction
+    async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
+        self.store_nodes(&nodes).map(|()| nodes).into()
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+    }
+
+    async fn delete(&self, ids: Vec<uuid::Uuid>) -> Result<(), PersistError> {
+        self.delete_nodes(ids)?;
+
+        Ok(())
+    }
+
+    /// Scans the table and deletes every node with matching metadata
+    async fn delete_by_metadata(&self, filter: Metadata) -> Result<(), PersistError> {
+        self.delete_nodes_by_metadata(&filter)?;
+
+        Ok(())
+    }
+}
+
+impl Scroll for Redb {
+    /// Streams all stored nodes, read in a single transaction
+    fn scroll(&self) -> IndexingStream {
+        let redb = self.clone();
+
+        stream::once(async move { redb.read_nodes() })
+            .flat_map(|nodes| match nodes {
+                Ok(nodes) => stream::iter(nodes.into_iter().map(Ok).collect::<Vec<_>>()),
+                Err(err) => stream::iter(vec![Err(err)]),
+            })
+            .boxed()
+            .into()
+    }
+}
+
+impl Redb {
+    fn create_node_table(&self) -> Result<()> {
+        let table_name = self.node_table_name();
+        let write_txn = self.database.begin_write()?;
+        write_txn.open_table(Self::node_table_definition(&table_name))?;
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    fn store_nodes(&self, nodes: &[Node]) -> Result<()> {
+        let table_name = self.node_table_name();
+        let write_txn = self.database.begin_write()?;
+        {
+            let mut table = write_txn.open_table(Self::node_table_definition(&table_name))?;
+
+            for node in nodes {
+                let mut record = NodeRecord::from(node.clone());
+                if !self.persist_vectors {
+                    record.vectors.clear();
+                    record.sparse_vectors.clear();
+                }
+
+                table.insert(self.node_key(node), serde_json::to_string(&record)?)?;
+            }
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    fn delete_nodes(&self, ids: Vec<uuid::Uuid>) -> Result<()> {
+        let table_name = self.node_table_name();
+        let write_txn = self.database.begin_write()?;
+        {
+            let mut table = write_txn.open_table(Self::node_table_definition(&table_name))?;
+            for id in ids {
+                table.remove(self.node_id_key(id))?;
+            }
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    fn delete_nodes_by_metadata(&self, filter: &Metadata) -> Result<()> {
+        let table_name = self.node_table_name();
+        let write_txn = self.database.begin_write()?;
+        {
+            let mut table = write_txn.open_table(Self::node_table_definition(&table_name))?;
+
+            let mut keys = Vec::new();
+            for entry in table.iter()? {
+                let (key, value) = entry?;
+                let record: NodeRecord = serde_json::from_str(&value.value())?;
+
+                if filter
+                    .iter()
+                    .all(|(field, expected)| record.metadata.get(field) == Some(expected))
+                {
+                    keys.push(key.value());
+                }
+            }
+
+            for key in keys {
+                table.remove(key)?;
+            }
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    fn read_nodes(&self) -> Result<Vec<Node>> {
+        let table_name = self.node_table_name();
+        let read_txn = self.database.begin_read()?;
+
+        let table = match read_txn.open_table(Self::node_table_definition(&table_name)) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist { .. }) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        table
+            .iter()?
+            .map(|entry| {
+                let (_, value) = entry?;
+                let record: NodeRecord = serde_json::from_str(&value.value())
+                    .context("Failed to deserialize node from redb")?;
+
+                Node::try_from(record)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt as _;
+    use swiftide_core::indexing::EmbeddedField;
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    fn nodes() -> Vec<Node> {
+        ["first", "second", "third"]
+            .into_iter()
+            .map(|chunk| {
+                Node::new(chunk)
+                    .with_metadata(("chunk", chunk))
+                    .with_vectors([(EmbeddedField::Combined, vec![1.0; 3])])
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_persist_scroll_and_delete() {
+        let tempdir = TempDir::new().unwrap();
+        let redb = Redb::builder()
+            .database_path(tempdir.child("test_persist"))
+            .build()
+            .unwrap();
+        redb.setup().await.unwrap();
+
+        let nodes = nodes();
+        redb.batch_store(nodes.clone())
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut scrolled = redb.scroll().try_collect::<Vec<_>>().await.unwrap();
+        scrolled.sort_by(|a, b| a.chunk.cmp(&b.chunk));
+        assert_eq!(scrolled, nodes);
+
+        redb.delete(vec![nodes[0].id()]).await.unwrap();
+        redb.delete_by_metadata(Metadata::from(("chunk", "second")))
+            .await
+            .unwrap();
+
+        let scrolled = redb.scroll().try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(scrolled, vec![nodes[2].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_persist_without_vectors() {
+        let tempdir = TempDir::new().unwrap();
+        let redb = Redb::builder()
+            .database_path(tempdir.child("test_persist_without_vectors"))
+            .persist_vectors(false)
+            .build()
+            .unwrap();
+
+        let node = nodes().remove(0);
+        redb.store(node.clone()).await.unwrap();
+
+        let scrolled = redb.scroll().try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(scrolled[0].chunk, node.chunk);
+        assert!(scrolled[0].vectors.is_none());
+    }
+}
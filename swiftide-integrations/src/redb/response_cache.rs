@@ -0,0 +1,88 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use redb::ReadableTable as _;
+use swiftide_core::response_cache::ResponseCache;
+
+use super::Redb;
+
+/// Milliseconds since the unix epoch, used to expire responses across restarts
+#[allow(clippy::cast_possible_truncation)]
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[async_trait]
+impl ResponseCache for Redb {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        let table_name = self.response_table_name();
+        let read_txn = self.database.begin_read()?;
+
+        let table = match read_txn.open_table(Self::response_table_definition(&table_name)) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist { .. }) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let Some(access_guard) = table.get(self.response_key(key))? else {
+            return Ok(None);
+        };
+        let (value, expires_at) = access_guard.value();
+
+        if expires_at.is_some_and(|expires_at| expires_at <= now_millis()) {
+            return Ok(None);
+        }
+
+        Ok(Some(value))
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<()> {
+        let table_name = self.response_table_name();
+        let expires_at = ttl.map(|ttl| now_millis() + ttl.as_millis() as u64);
+
+        let write_txn = self.database.begin_write()?;
+        {
+            let mut table = write_txn.open_table(Self::response_table_definition(&table_name))?;
+            table.insert(self.response_key(key), (value.to_string(), expires_at))?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temp_dir::TempDir;
+
+    #[tokio::test]
+    async fn test_response_cache() {
+        let tempdir = TempDir::new().unwrap();
+        let redb = Redb::builder()
+            .database_path(tempdir.child("test_response_cache"))
+            .build()
+            .unwrap();
+
+        assert_eq!(ResponseCache::get(&redb, "key").await.unwrap(), None);
+
+        ResponseCache::set(&redb, "key", "value", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            ResponseCache::get(&redb, "key").await.unwrap().as_deref(),
+            Some("value")
+        );
+
+        ResponseCache::set(&redb, "expiring", "value", Some(Duration::from_millis(10)))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(ResponseCache::get(&redb, "expiring").await.unwrap(), None);
+    }
+}
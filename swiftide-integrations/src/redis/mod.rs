@@ -11,6 +11,9 @@
 //! - Checking if a node is cached
 //! - Setting a node in the cache
 //! - Resetting the cache (primarily for testing purposes)
+//! - Caching language model responses, see `swiftide_core::response_cache`
+//!
+//! For vector search with `RediSearch`, see [`RedisVectorStore`].
 //!
 //! This integration is essential for ensuring efficient node management and caching in the Swiftide system.
 
@@ -22,6 +25,10 @@ use swiftide_core::indexing::Node;
 
 mod node_cache;
 mod persist;
+mod response_cache;
+mod vector_store;
+
+pub use vector_store::{DistanceMetric, RedisVectorStore, RedisVectorStoreBuilder};
 
 /// `Redis` provides a caching mechanism for nodes using Redis.
 /// It helps in optimizing the indexing process by skipping nodes that have already been processed.
@@ -126,6 +133,11 @@ impl Redis {
         format!("{}:{}", self.cache_key_prefix, node.id())
     }
 
+    /// Generates a Redis key for a cached language model response.
+    fn cache_key_for_response(&self, key: &str) -> String {
+        format!("{}:response:{key}", self.cache_key_prefix)
+    }
+
     /// Generates a key for a given node to be persisted in Redis.
     fn persist_key_for_node(&self, node: &Node) -> Result<String> {
         if let Some(key_fn) = self.persist_key_fn {
@@ -1,7 +1,7 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use async_trait::async_trait;
 
-use swiftide_core::indexing::{Node, NodeCache};
+use swiftide_core::indexing::{Node, NodeCache, NodeCacheError};
 
 use super::Redis;
 
@@ -74,10 +74,10 @@ impl NodeCache for Redis {
         }
     }
 
-    async fn clear(&self) -> Result<()> {
+    async fn clear(&self) -> Result<(), NodeCacheError> {
         if self.cache_key_prefix.is_empty() {
-            return Err(anyhow::anyhow!(
-                "No cache key prefix set; not flushing cache"
+            return Err(NodeCacheError::permanent(
+                "No cache key prefix set; not flushing cache",
             ));
         }
 
@@ -85,11 +85,12 @@ impl NodeCache for Redis {
             redis::cmd("DEL")
                 .arg(format!("{}*", self.cache_key_prefix))
                 .query_async(&mut cm)
-                .await?;
+                .await
+                .context("Error clearing redis cache")?;
 
             Ok(())
         } else {
-            anyhow::bail!("Failed to connect to Redis");
+            Err(NodeCacheError::transient("Failed to connect to Redis"))
         }
     }
 }
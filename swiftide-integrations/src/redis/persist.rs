@@ -2,7 +2,7 @@ use anyhow::{Context as _, Result};
 use async_trait::async_trait;
 
 use swiftide_core::{
-    indexing::{IndexingStream, Node},
+    indexing::{IndexingStream, Node, PersistError},
     Persist,
 };
 
@@ -11,7 +11,7 @@ use super::Redis;
 #[async_trait]
 #[allow(dependency_on_unit_never_type_fallback)]
 impl Persist for Redis {
-    async fn setup(&self) -> Result<()> {
+    async fn setup(&self) -> Result<(), PersistError> {
         Ok(())
     }
 
@@ -24,7 +24,7 @@ impl Persist for Redis {
     /// By default nodes are stored with the path and hash as key and the node serialized as JSON as value.
     ///
     /// You can customize the key and value used for storing nodes by setting the `persist_key_fn` and `persist_value_fn` fields.
-    async fn store(&self, node: Node) -> Result<Node> {
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
         if let Some(mut cm) = self.lazy_connect().await {
             redis::cmd("SET")
                 .arg(self.persist_key_for_node(&node)?)
@@ -35,7 +35,7 @@ impl Persist for Redis {
 
             Ok(node)
         } else {
-            anyhow::bail!("Failed to connect to Redis")
+            Err(PersistError::transient("Failed to connect to Redis"))
         }
     }
 
@@ -75,7 +75,9 @@ impl Persist for Redis {
                 IndexingStream::iter([Err(result.unwrap_err())])
             }
         } else {
-            IndexingStream::iter([Err(anyhow::anyhow!("Failed to connect to Redis"))])
+            IndexingStream::iter([Err(
+                PersistError::transient("Failed to connect to Redis").into()
+            )])
         }
     }
 }
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use swiftide_core::response_cache::ResponseCache;
+
+use super::Redis;
+
+#[async_trait]
+impl ResponseCache for Redis {
+    #[tracing::instrument(skip_all, name = "response_cache.redis.get", fields(hit))]
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        let mut cm = self
+            .lazy_connect()
+            .await
+            .context("Failed to connect to Redis")?;
+
+        let value: Option<String> = redis::cmd("GET")
+            .arg(self.cache_key_for_response(key))
+            .query_async(&mut cm)
+            .await?;
+
+        tracing::Span::current().record("hit", value.is_some());
+
+        Ok(value)
+    }
+
+    #[tracing::instrument(skip_all, name = "response_cache.redis.set")]
+    async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<()> {
+        let mut cm = self
+            .lazy_connect()
+            .await
+            .context("Failed to connect to Redis")?;
+
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(self.cache_key_for_response(key)).arg(value);
+
+        if let Some(ttl) = ttl {
+            cmd.arg("PX").arg(ttl.as_millis().max(1).to_string());
+        }
+
+        cmd.query_async::<()>(&mut cm).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use testcontainers::runners::AsyncRunner;
+
+    #[test_log::test(tokio::test)]
+    async fn test_redis_response_cache() {
+        let redis = testcontainers::GenericImage::new("redis", "7.2.4")
+            .with_exposed_port(6379.into())
+            .with_wait_for(testcontainers::core::WaitFor::message_on_stdout(
+                "Ready to accept connections",
+            ))
+            .start()
+            .await
+            .expect("Redis started");
+
+        let host = redis.get_host().await.unwrap();
+        let port = redis.get_host_port_ipv4(6379).await.unwrap();
+        let cache = Redis::try_from_url(format!("redis://{host}:{port}"), "test")
+            .expect("Could not build redis client");
+
+        assert_eq!(ResponseCache::get(&cache, "key").await.unwrap(), None);
+
+        ResponseCache::set(&cache, "key", "value", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            ResponseCache::get(&cache, "key").await.unwrap().as_deref(),
+            Some("value")
+        );
+
+        ResponseCache::set(&cache, "expiring", "value", Some(Duration::from_millis(50)))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(ResponseCache::get(&cache, "expiring").await.unwrap(), None);
+    }
+}
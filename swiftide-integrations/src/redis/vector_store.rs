@@ -0,0 +1,534 @@
+//! Vector search with `RediSearch`
+//!
+//! [`RedisVectorStore`] stores nodes as hashes with their vectors and searches them with a HNSW
+//! index. Requires a Redis server with the search module, like Redis Stack or Redis 8.
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use derive_builder::Builder;
+use tokio::sync::RwLock;
+
+use crate::compression::ChunkCompression;
+
+use swiftide_core::{
+    document::Document,
+    indexing::{EmbeddedField, IndexingStream, Metadata, Node, PersistError},
+    querying::{search_strategies::SimilaritySingleEmbedding, states, Query, RetrieveError},
+    Persist, Retrieve,
+};
+
+/// Stores nodes in Redis and retrieves them with KNN vector search
+///
+/// Every node is stored as a hash under `{key_prefix}:{node id}`, with the chunk as `content`, the
+/// metadata as json in `metadata` and every configured vector as `vector_{field}`. Metadata fields
+/// configured with [`RedisVectorStoreBuilder::with_tag_field`] and
+/// [`RedisVectorStoreBuilder::with_numeric_field`] are also stored as separate fields and
+/// indexed, so that they can be used in filters.
+///
+/// `setup` creates the index if it does not exist yet.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_core::indexing::EmbeddedField;
+/// # use swiftide_integrations::redis::RedisVectorStore;
+/// RedisVectorStore::try_build_from_url("redis://localhost:6379")
+///     .unwrap()
+///     .index_name("swiftide")
+///     .vector_size(1536)
+///     .with_vector(EmbeddedField::Combined)
+///     .with_tag_field("category")
+///     .with_numeric_field("year")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into), build_fn(error = "anyhow::Error"))]
+pub struct RedisVectorStore {
+    client: redis::Client,
+    #[builder(default, setter(skip))]
+    connection_manager: RwLock<Option<redis::aio::ConnectionManager>>,
+    /// The name of the index, defaults to `swiftide`
+    #[builder(default = "\"swiftide\".into()")]
+    index_name: String,
+    /// The prefix of the keys of stored nodes, defaults to `swiftide:node`
+    #[builder(default = "\"swiftide:node\".into()")]
+    key_prefix: String,
+    /// The dimensions of the vectors
+    vector_size: usize,
+    /// The vectors to store, defaults to `EmbeddedField::Combined`
+    #[builder(default = "HashSet::from([EmbeddedField::Combined])", setter(custom))]
+    vectors: HashSet<EmbeddedField>,
+    /// Metadata fields indexed as tags, for exact matches
+    #[builder(default, setter(custom))]
+    tag_fields: Vec<String>,
+    /// Metadata fields indexed as numbers, for ranges
+    #[builder(default, setter(custom))]
+    numeric_fields: Vec<String>,
+    /// The distance metric of the index, defaults to `COSINE`
+    #[builder(default)]
+    distance_metric: DistanceMetric,
+    /// The batch size used for persisting nodes, defaults to 50
+    #[builder(default = "50")]
+    batch_size: usize,
+    /// Compresses the content before it is stored, see [`ChunkCompression`]
+    ///
+    /// The content is decompressed on retrieval, and is not indexed for full-text search.
+    #[builder(default, setter(strip_option))]
+    chunk_compression: Option<ChunkCompression>,
+}
+
+/// Distance metrics supported by `RediSearch`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DistanceMetric {
+    #[default]
+    Cosine,
+    L2,
+    InnerProduct,
+}
+
+impl DistanceMetric {
+    fn as_str(self) -> &'static str {
+        match self {
+            DistanceMetric::Cosine => "COSINE",
+            DistanceMetric::L2 => "L2",
+            DistanceMetric::InnerProduct => "IP",
+        }
+    }
+}
+
+impl RedisVectorStore {
+    /// # Errors
+    ///
+    /// Returns an error if the Redis client cannot be opened
+    pub fn try_build_from_url(url: impl AsRef<str>) -> Result<RedisVectorStoreBuilder> {
+        Ok(RedisVectorStoreBuilder::default()
+            .client(redis::Client::open(url.as_ref()).context("Failed to open redis client")?))
+    }
+
+    pub fn builder() -> RedisVectorStoreBuilder {
+        RedisVectorStoreBuilder::default()
+    }
+
+    /// Lazily connects to the Redis server and returns the connection manager.
+    async fn lazy_connect(&self) -> Result<redis::aio::ConnectionManager> {
+        if let Some(cm) = self.connection_manager.read().await.clone() {
+            return Ok(cm);
+        }
+
+        let cm = self
+            .client
+            .get_connection_manager()
+            .await
+            .context("Failed to connect to Redis")?;
+        *self.connection_manager.write().await = Some(cm.clone());
+
+        Ok(cm)
+    }
+
+    fn key_for_node(&self, node: &Node) -> String {
+        format!("{}:{}", self.key_prefix, node.id())
+    }
+
+    /// Builds the `FT.CREATE` command with a HNSW vector field for every configured vector
+    fn create_index_cmd(&self) -> redis::Cmd {
+        let mut cmd = redis::cmd("FT.CREATE");
+        cmd.arg(&self.index_name)
+            .arg("ON")
+            .arg("HASH")
+            .arg("PREFIX")
+            .arg(1)
+            .arg(format!("{}:", self.key_prefix))
+            .arg("SCHEMA");
+
+        // Compressed content cannot be searched on its text
+        if self.chunk_compression.is_none() {
+            cmd.arg("content").arg("TEXT");
+        }
+        cmd.arg("path").arg("TAG");
+
+        for field in &self.tag_fields {
+            cmd.arg(field).arg("TAG");
+        }
+        for field in &self.numeric_fields {
+            cmd.arg(field).arg("NUMERIC");
+        }
+        for field in &self.vectors {
+            cmd.arg(vector_field_name(field))
+                .arg("VECTOR")
+                .arg("HNSW")
+                .arg(6)
+                .arg("TYPE")
+                .arg("FLOAT32")
+                .arg("DIM")
+                .arg(self.vector_size)
+                .arg("DISTANCE_METRIC")
+                .arg(self.distance_metric.as_str());
+        }
+
+        cmd
+    }
+
+    /// Builds the `HSET` command for a node
+    fn store_node_cmd(&self, node: &Node) -> Result<redis::Cmd> {
+        let content = match &self.chunk_compression {
+            Some(compression) => compression.compress(&node.chunk)?,
+            None => node.chunk.as_str().into(),
+        };
+
+        let mut cmd = redis::cmd("HSET");
+        cmd.arg(self.key_for_node(node))
+            .arg("content")
+            .arg(&*content)
+            .arg("path")
+            .arg(node.path.to_string_lossy().as_ref())
+            .arg("metadata")
+            .arg(serde_json::to_string(&node.metadata)?);
+
+        for field in self.tag_fields.iter().chain(&self.numeric_fields) {
+            match node.metadata.get(field) {
+                Some(serde_json::Value::String(value)) => cmd.arg(field).arg(value),
+                Some(value) => cmd.arg(field).arg(value.to_string()),
+                None => continue,
+            };
+        }
+
+        for field in &self.vectors {
+            let vector = node
+                .vectors
+                .as_ref()
+                .and_then(|vectors| vectors.get(field))
+                .with_context(|| format!("Node without vector for {field}"))?;
+
+            cmd.arg(vector_field_name(field))
+                .arg(vector_to_bytes(vector));
+        }
+
+        Ok(cmd)
+    }
+
+    async fn store_nodes(&self, nodes: &[Node]) -> Result<()> {
+        let mut pipeline = redis::pipe();
+        for node in nodes {
+            pipeline.add_command(self.store_node_cmd(node)?).ignore();
+        }
+
+        let mut cm = self.lazy_connect().await?;
+        pipeline
+            .query_async::<()>(&mut cm)
+            .await
+            .context("Error persisting to redis")
+    }
+}
+
+impl RedisVectorStoreBuilder {
+    /// Adds a vector to store, replacing the default `EmbeddedField::Combined`
+    #[must_use]
+    pub fn with_vector(mut self, vector: impl Into<EmbeddedField>) -> Self {
+        self.vectors
+            .get_or_insert_with(HashSet::new)
+            .insert(vector.into());
+        self
+    }
+
+    /// Indexes a metadata field as a tag, to filter on with `@field:{value}`
+    #[must_use]
+    pub fn with_tag_field(mut self, field: impl Into<String>) -> Self {
+        self.tag_fields
+            .get_or_insert_with(Vec::new)
+            .push(field.into());
+        self
+    }
+
+    /// Indexes a metadata field as a number, to filter on with `@field:[min max]`
+    #[must_use]
+    pub fn with_numeric_field(mut self, field: impl Into<String>) -> Self {
+        self.numeric_fields
+            .get_or_insert_with(Vec::new)
+            .push(field.into());
+        self
+    }
+}
+
+// Redis CM does not implement debug
+#[allow(clippy::missing_fields_in_debug)]
+impl std::fmt::Debug for RedisVectorStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisVectorStore")
+            .field("client", &self.client)
+            .field("index_name", &self.index_name)
+            .field("key_prefix", &self.key_prefix)
+            .field("vectors", &self.vectors)
+            .field("chunk_compression", &self.chunk_compression)
+            .finish()
+    }
+}
+
+impl Clone for RedisVectorStore {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            connection_manager: RwLock::new(None),
+            index_name: self.index_name.clone(),
+            key_prefix: self.key_prefix.clone(),
+            vector_size: self.vector_size,
+            vectors: self.vectors.clone(),
+            tag_fields: self.tag_fields.clone(),
+            numeric_fields: self.numeric_fields.clone(),
+            distance_metric: self.distance_metric,
+            batch_size: self.batch_size,
+            chunk_compression: self.chunk_compression,
+        }
+    }
+}
+
+#[async_trait]
+impl Persist for RedisVectorStore {
+    /// Creates the index if it does not exist
+    async fn setup(&self) -> Result<(), PersistError> {
+        let mut cm = self.lazy_connect().await.map_err(PersistError::transient)?;
+
+        let exists = redis::cmd("FT._LIST")
+            .query_async::<Vec<String>>(&mut cm)
+            .await
+            .context("Failed to list indices, is the search module loaded?")?
+            .contains(&self.index_name);
+
+        if exists {
+            tracing::debug!(index = self.index_name, "Index already exists");
+            return Ok(());
+        }
+
+        tracing::info!(index = self.index_name, "Creating index");
+        self.create_index_cmd()
+            .query_async::<()>(&mut cm)
+            .await
+            .context("Failed to create index")?;
+
+        Ok(())
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        Some(self.batch_size)
+    }
+
+    /// Stores a node as a hash with the HSET command
+    async fn store(&self, node: Node) -> Result<Node, PersistError> {
+        self.store_nodes(std::slice::from_ref(&node)).await?;
+        Ok(node)
+    }
+
+    /// Stores a batch of nodes as hashes in a single pipeline
+    async fn batch_store(&self, nodes: Vec<Node>) -> IndexingStream {
+        self.store_nodes(&nodes).await.map(|()| nodes).into()
+    }
+}
+
+/// Implement the `Retrieve` trait for `SimilaritySingleEmbedding` search strategy.
+///
+/// Can be used in the query pipeline to retrieve documents from Redis with KNN search.
+///
+/// Supports filters as strings in the `RediSearch` query syntax, on the tag and numeric fields,
+/// i.e. `@category:{news} @year:[2020 2024]`.
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding<String>> for RedisVectorStore {
+    #[tracing::instrument]
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding<String>,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        let Some(embedding) = &query.embedding else {
+            return Err(RetrieveError::MissingEmbedding);
+        };
+
+        // With multiple vectors, the combined vector is searched
+        let field = match self.vectors.iter().next() {
+            Some(field) if self.vectors.len() == 1 => field,
+            _ => &EmbeddedField::Combined,
+        };
+
+        let filter = search_strategy.filter().as_deref().unwrap_or("*");
+        let search = format!(
+            "({filter})=>[KNN {top_k} @{field} $vector AS distance]",
+            top_k = search_strategy.top_k(),
+            field = vector_field_name(field),
+        );
+
+        let mut cm = self
+            .lazy_connect()
+            .await
+            .map_err(RetrieveError::transient)?;
+        let response = redis::cmd("FT.SEARCH")
+            .arg(&self.index_name)
+            .arg(search)
+            .arg("PARAMS")
+            .arg(2)
+            .arg("vector")
+            .arg(vector_to_bytes(embedding))
+            .arg("SORTBY")
+            .arg("distance")
+            .arg("RETURN")
+            .arg(2)
+            .arg("content")
+            .arg("metadata")
+            .arg("LIMIT")
+            .arg(0)
+            .arg(search_strategy.top_k())
+            .arg("DIALECT")
+            .arg(2)
+            .query_async::<Vec<redis::Value>>(&mut cm)
+            .await
+            .context("Failed to retrieve from redis")?;
+
+        // The response is the total, followed by the key and fields of every document
+        let documents = response
+            .iter()
+            .skip(2)
+            .step_by(2)
+            .map(|fields| {
+                let fields: HashMap<String, String> = redis::from_redis_value(fields)?;
+                document_from_fields(fields)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(query.retrieved_documents(documents))
+    }
+}
+
+/// Ensures that the `SimilaritySingleEmbedding` search strategy can be used when no filter is set.
+#[async_trait]
+impl Retrieve<SimilaritySingleEmbedding> for RedisVectorStore {
+    async fn retrieve(
+        &self,
+        search_strategy: &SimilaritySingleEmbedding,
+        query: Query<states::Pending>,
+    ) -> Result<Query<states::Retrieved>, RetrieveError> {
+        Retrieve::<SimilaritySingleEmbedding<String>>::retrieve(
+            self,
+            &search_strategy.into_concrete_filter::<String>(),
+            query,
+        )
+        .await
+    }
+}
+
+fn document_from_fields(mut fields: HashMap<String, String>) -> Result<Document> {
+    let content = ChunkCompression::decompress_owned(
+        fields
+            .remove("content")
+            .context("Expected content in redis hash")?,
+    )?;
+
+    let metadata = fields
+        .get("metadata")
+        .map(|metadata| serde_json::from_str::<Metadata>(metadata))
+        .transpose()?;
+
+    Ok(Document::new(content, metadata))
+}
+
+/// Returns the name of the vector field for an embedded field
+fn vector_field_name(field: &EmbeddedField) -> String {
+    format!(
+        "vector_{}",
+        field
+            .field_name()
+            .to_lowercase()
+            .replace(|c: char| !c.is_alphanumeric(), "_")
+    )
+}
+
+/// Vectors are stored and queried as little endian `FLOAT32` blobs
+fn vector_to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+    use testcontainers::{runners::AsyncRunner, ContainerAsync, GenericImage};
+
+    async fn start_redis_stack() -> ContainerAsync<GenericImage> {
+        testcontainers::GenericImage::new("redis/redis-stack-server", "7.4.0-v1")
+            .with_exposed_port(6379.into())
+            .with_wait_for(testcontainers::core::WaitFor::message_on_stdout(
+                "Ready to accept connections",
+            ))
+            .start()
+            .await
+            .expect("Redis started")
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_store_and_retrieve_with_filter() {
+        let redis_container = start_redis_stack().await;
+        let host = redis_container.get_host().await.unwrap();
+        let port = redis_container.get_host_port_ipv4(6379).await.unwrap();
+
+        let store = RedisVectorStore::try_build_from_url(format!("redis://{host}:{port}"))
+            .unwrap()
+            .vector_size(3usize)
+            .with_tag_field("filter")
+            .build()
+            .unwrap();
+        store.setup().await.unwrap();
+        store
+            .setup()
+            .await
+            .expect("Should not fail if index exists");
+
+        let nodes = ["true", "true", "false"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, filter)| {
+                let mut node = Node::new(format!("chunk {i}"));
+                node.metadata.insert("filter", filter);
+                node.with_vectors([(EmbeddedField::Combined, vec![1.0, 0.5, 0.0])]);
+                node
+            })
+            .collect();
+        store
+            .batch_store(nodes)
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut query = Query::<states::Pending>::new("chunk");
+        query.embedding = Some(vec![1.0, 0.5, 0.0]);
+
+        let result = store
+            .retrieve(&SimilaritySingleEmbedding::default(), query.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.documents().len(), 3);
+
+        let search_strategy = SimilaritySingleEmbedding::from_filter("@filter:{true}".to_string());
+        let result = store.retrieve(&search_strategy, query).await.unwrap();
+        assert_eq!(result.documents().len(), 2);
+        assert_eq!(
+            result.documents()[0].metadata().get("filter"),
+            Some(&serde_json::json!("true"))
+        );
+    }
+
+    #[test]
+    fn test_document_from_compressed_fields() {
+        let chunk = "Swiftide stores compressed chunks. ".repeat(50);
+        let content = ChunkCompression::zstd().compress(&chunk).unwrap();
+
+        let document = document_from_fields(HashMap::from([
+            ("content".to_string(), content.into_owned()),
+            ("metadata".to_string(), "{}".to_string()),
+        ]))
+        .unwrap();
+
+        assert_eq!(document.content(), chunk);
+    }
+}
@@ -0,0 +1,18 @@
+//! Classifies errors of integrations that call their api directly with `reqwest`
+use reqwest::StatusCode;
+use swiftide_core::chat_completion::errors::LanguageModelError;
+
+/// Network errors, timeouts, rate limits and server errors are transient
+pub(crate) fn reqwest_error_to_language_model_error(err: reqwest::Error) -> LanguageModelError {
+    let is_transient = err.is_timeout()
+        || err.is_connect()
+        || err.status().is_some_and(|status| {
+            status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+        });
+
+    if is_transient {
+        LanguageModelError::transient(err)
+    } else {
+        LanguageModelError::permanent(err)
+    }
+}
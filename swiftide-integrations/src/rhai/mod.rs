@@ -0,0 +1,211 @@
+//! Transforms nodes with [Rhai](https://rhai.rs) scripts
+//!
+//! Scripts are loaded at runtime, so custom cleanup and enrichment can be configured without
+//! recompiling. Every node is run through the script with these variables in scope:
+//! - `chunk`: the chunk of the node, as a string
+//! - `metadata`: the metadata of the node, as a map
+//! - `path`: the path of the node, as a string, read only
+//!
+//! Changes to `chunk` and `metadata` are written back to the node.
+//!
+//! Swiftide does not have a YAML configuration or CLI to build pipelines from, so scripts are not
+//! configurable from one yet. Until then, a script can be loaded from a path with
+//! [`Script::from_file`], i.e. from an environment variable or the configuration of an
+//! application.
+//!
+//! # Example
+//!
+//! ```
+//! # use swiftide_core::{indexing::Node, Transformer};
+//! # use swiftide_integrations::rhai::Script;
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let script = Script::from_source(
+//!     r#"
+//!     chunk.trim();
+//!     metadata.words = chunk.split(" ").len();
+//!     "#,
+//! )?;
+//!
+//! let node = script.transform_node(Node::new("  hello world ")).await?;
+//!
+//! assert_eq!(node.chunk, "hello world");
+//! assert_eq!(node.metadata.get("words"), Some(&2.into()));
+//! # Ok(())
+//! # }
+//! ```
+use std::{collections::BTreeMap, path::Path, sync::Arc};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use swiftide_core::{indexing::Node, Transformer};
+
+/// Limits the operations of a script, so that a runaway script does not stall the pipeline
+const DEFAULT_MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Runs a Rhai script on every node
+#[derive(Clone)]
+pub struct Script {
+    engine: Arc<::rhai::Engine>,
+    ast: Arc<::rhai::AST>,
+    concurrency: Option<usize>,
+}
+
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script")
+            .field("source", &self.ast.source())
+            .field("concurrency", &self.concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Script {
+    /// Compiles a script from its source
+    ///
+    /// # Errors
+    ///
+    /// Errors if the script does not compile
+    pub fn from_source(source: impl AsRef<str>) -> Result<Self> {
+        Self::from_engine(Self::default_engine(), source)
+    }
+
+    /// Compiles the script in the file at `path`
+    ///
+    /// # Errors
+    ///
+    /// Errors if the file cannot be read or the script does not compile
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read script {}", path.display()))?;
+
+        let mut script = Self::from_source(source)?;
+        if let Some(ast) = Arc::get_mut(&mut script.ast) {
+            ast.set_source(path.to_string_lossy().as_ref());
+        }
+
+        Ok(script)
+    }
+
+    /// Compiles a script with a custom engine, i.e. with additional functions registered
+    ///
+    /// # Errors
+    ///
+    /// Errors if the script does not compile
+    pub fn from_engine(engine: ::rhai::Engine, source: impl AsRef<str>) -> Result<Self> {
+        let ast = engine
+            .compile(source.as_ref())
+            .context("Failed to compile script")?;
+
+        Ok(Self {
+            engine: Arc::new(engine),
+            ast: Arc::new(ast),
+            concurrency: None,
+        })
+    }
+
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    fn default_engine() -> ::rhai::Engine {
+        let mut engine = ::rhai::Engine::new();
+        engine.set_max_operations(DEFAULT_MAX_OPERATIONS);
+        engine
+    }
+
+    /// Runs the script on the chunk and metadata of a node
+    fn run(&self, node: &mut Node) -> Result<()> {
+        let metadata = node
+            .metadata
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut scope = ::rhai::Scope::new();
+        scope
+            .push("chunk", node.chunk.clone())
+            .push("metadata", ::rhai::serde::to_dynamic(metadata)?)
+            .push_constant("path", node.path.to_string_lossy().to_string());
+
+        self.engine
+            .run_ast_with_scope(&mut scope, &self.ast)
+            .context("Failed to run script")?;
+
+        node.chunk = scope
+            .get_value::<::rhai::ImmutableString>("chunk")
+            .context("Expected `chunk` to be a string")?
+            .to_string();
+
+        let metadata = scope
+            .get_value::<::rhai::Dynamic>("metadata")
+            .context("Expected `metadata` to be in scope")?;
+        let metadata: BTreeMap<String, serde_json::Value> =
+            ::rhai::serde::from_dynamic(&metadata).context("Expected `metadata` to be a map")?;
+        node.metadata = metadata.into_iter().collect::<Vec<_>>().into();
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Transformer for Script {
+    #[tracing::instrument(skip_all, name = "transformers.rhai_script")]
+    async fn transform_node(&self, mut node: Node) -> Result<Node> {
+        self.run(&mut node)
+            .with_context(|| format!("Script failed on {}", node.path.display()))?;
+
+        Ok(node)
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.concurrency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::indexing::Metadata;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_script_changes_chunk_and_metadata() {
+        let script = Script::from_source(
+            r#"
+            chunk = chunk.to_upper();
+            metadata.remove("secret");
+            metadata.path = path;
+            "#,
+        )
+        .unwrap();
+
+        let node = Node::builder()
+            .chunk("hello")
+            .path("README.md")
+            .metadata(Metadata::from(("secret", "password")))
+            .build()
+            .unwrap();
+
+        let node = script.transform_node(node).await.unwrap();
+
+        assert_eq!(node.chunk, "HELLO");
+        assert_eq!(node.metadata.get("secret"), None);
+        assert_eq!(node.metadata.get("path"), Some(&"README.md".into()));
+    }
+
+    #[tokio::test]
+    async fn test_runaway_script_errors() {
+        let script = Script::from_source("loop {}").unwrap();
+
+        assert!(script.transform_node(Node::new("hello")).await.is_err());
+    }
+
+    #[test]
+    fn test_invalid_script_errors() {
+        assert!(Script::from_source("chunk = ").is_err());
+    }
+}
@@ -1,6 +1,8 @@
-//! Scraping loader using and html to markdown transformer
+//! Scraping loader using and html to markdown transformer, and tools for agents to browse the web
 mod html_to_markdown_transformer;
 mod loader;
+mod web_tools;
 
 pub use html_to_markdown_transformer::HtmlToMarkdownTransformer;
 pub use loader::ScrapingLoader;
+pub use web_tools::{BrowseUrl, SearchWeb, WebTools};
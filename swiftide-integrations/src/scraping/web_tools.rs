@@ -0,0 +1,293 @@
+//! Tools for agents to browse the web
+//!
+//! `browse_url` fetches a page and `search_web` searches the web, both return the page as
+//! markdown, truncated to a token budget.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_integrations::scraping::WebTools;
+//! # fn main() -> anyhow::Result<()> {
+//! let tools = WebTools::builder().max_tokens(2000usize).build()?.tools();
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use derive_builder::Builder;
+use htmd::HtmlToMarkdown;
+use serde::Deserialize;
+use spider::website::Website;
+use swiftide_core::{
+    chat_completion::{errors::ToolError, ParamSpec, Tool, ToolOutput, ToolSpec},
+    tokenizer::{ApproximateTokens, EstimateTokens},
+    util::safe_truncate_utf8,
+    AgentContext,
+};
+
+const DEFAULT_MAX_TOKENS: usize = 4000;
+
+/// Searches with the html version of `DuckDuckGo`, which does not require an api key
+const DEFAULT_SEARCH_URL: &str = "https://html.duckduckgo.com/html/?q={query}";
+
+/// Fetches pages with spider and converts them to markdown within a token budget
+#[derive(Clone, Builder)]
+#[builder(setter(into, strip_option), build_fn(error = "anyhow::Error"))]
+pub struct WebTools {
+    /// Pages are truncated to this many tokens
+    #[builder(default = "DEFAULT_MAX_TOKENS")]
+    max_tokens: usize,
+
+    /// Estimates the tokens of a page, defaults to [`ApproximateTokens`]
+    #[builder(default = "Arc::new(ApproximateTokens::default())", setter(custom))]
+    estimator: Arc<dyn EstimateTokens>,
+
+    /// Url to search with, `{query}` is replaced with the encoded query
+    #[builder(default = "DEFAULT_SEARCH_URL.to_string()")]
+    search_url: String,
+
+    #[builder(
+        default = "Arc::new(HtmlToMarkdown::builder().skip_tags(vec![\"script\", \"style\", \"nav\", \"footer\"]).build())",
+        setter(custom)
+    )]
+    htmd: Arc<HtmlToMarkdown>,
+}
+
+impl std::fmt::Debug for WebTools {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebTools")
+            .field("max_tokens", &self.max_tokens)
+            .field("estimator", &self.estimator)
+            .field("search_url", &self.search_url)
+            .finish()
+    }
+}
+
+impl WebToolsBuilder {
+    pub fn estimator(&mut self, estimator: impl EstimateTokens + 'static) -> &mut Self {
+        self.estimator = Some(Arc::new(estimator));
+        self
+    }
+
+    /// Customizes how html is converted to markdown
+    pub fn htmd(&mut self, htmd: HtmlToMarkdown) -> &mut Self {
+        self.htmd = Some(Arc::new(htmd));
+        self
+    }
+}
+
+impl WebTools {
+    pub fn builder() -> WebToolsBuilder {
+        WebToolsBuilder::default()
+    }
+
+    /// Returns the `browse_url` and `search_web` tools
+    pub fn tools(&self) -> Vec<Box<dyn Tool>> {
+        vec![self.browse_url().boxed(), self.search_web().boxed()]
+    }
+
+    /// Fetches the page at an url as markdown
+    pub fn browse_url(&self) -> BrowseUrl {
+        BrowseUrl { web: self.clone() }
+    }
+
+    /// Searches the web, returning the results page as markdown
+    pub fn search_web(&self) -> SearchWeb {
+        SearchWeb { web: self.clone() }
+    }
+
+    async fn fetch(&self, url: &str) -> Result<ToolOutput> {
+        let mut website = Website::new(url);
+        website.with_limit(1);
+        website.scrape().await;
+
+        let Some(html) = website
+            .get_pages()
+            .and_then(|pages| pages.first())
+            .map(spider::page::Page::get_html)
+            .filter(|html| !html.trim().is_empty())
+        else {
+            return Ok(ToolOutput::Fail(format!("Could not fetch {url}")));
+        };
+
+        let markdown = self.htmd.convert(&html)?;
+        Ok(ToolOutput::Text(self.truncate(markdown).await?))
+    }
+
+    /// Truncates the text to the token budget, on character boundaries
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    async fn truncate(&self, mut text: String) -> Result<String> {
+        loop {
+            let tokens = self.estimator.estimate(&text).await?;
+            if tokens <= self.max_tokens {
+                return Ok(text);
+            }
+
+            // Shrink proportionally, with some margin so the loop converges quickly
+            let chars = text.chars().count();
+            let keep = (chars as f64 * self.max_tokens as f64 / tokens as f64 * 0.95) as usize;
+            text = safe_truncate_utf8(&text, keep.min(chars.saturating_sub(1)));
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BrowseUrlArgs {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct SearchWebArgs {
+    query: String,
+}
+
+/// Fetches a web page and returns it as markdown
+#[derive(Clone, Debug)]
+pub struct BrowseUrl {
+    web: WebTools,
+}
+
+#[async_trait]
+impl Tool for BrowseUrl {
+    async fn invoke(
+        &self,
+        _agent_context: &dyn AgentContext,
+        raw_args: Option<&str>,
+    ) -> Result<ToolOutput, ToolError> {
+        let args = self
+            .tool_spec()
+            .prepare_args(raw_args)?
+            .context("Missing arguments")?;
+        let args: BrowseUrlArgs = serde_json::from_str(&args)?;
+
+        Ok(self.web.fetch(&args.url).await?)
+    }
+
+    fn name(&self) -> &'static str {
+        "browse_url"
+    }
+
+    fn tool_spec(&self) -> ToolSpec {
+        ToolSpec::builder()
+            .name("browse_url")
+            .description("Fetches a web page and returns its content as markdown")
+            .parameters(vec![ParamSpec::builder()
+                .name("url")
+                .description("The url of the page, including the scheme")
+                .required(true)
+                .build()
+                .expect("infallible")])
+            .build()
+            .expect("infallible")
+    }
+}
+
+/// Searches the web and returns the results as markdown
+#[derive(Clone, Debug)]
+pub struct SearchWeb {
+    web: WebTools,
+}
+
+#[async_trait]
+impl Tool for SearchWeb {
+    async fn invoke(
+        &self,
+        _agent_context: &dyn AgentContext,
+        raw_args: Option<&str>,
+    ) -> Result<ToolOutput, ToolError> {
+        let args = self
+            .tool_spec()
+            .prepare_args(raw_args)?
+            .context("Missing arguments")?;
+        let args: SearchWebArgs = serde_json::from_str(&args)?;
+
+        let url = self
+            .web
+            .search_url
+            .replace("{query}", &encode_query(&args.query));
+
+        Ok(self.web.fetch(&url).await?)
+    }
+
+    fn name(&self) -> &'static str {
+        "search_web"
+    }
+
+    fn tool_spec(&self) -> ToolSpec {
+        ToolSpec::builder()
+            .name("search_web")
+            .description(
+                "Searches the web and returns the results with their urls as markdown. Use \
+                 `browse_url` to read a result",
+            )
+            .parameters(vec![ParamSpec::builder()
+                .name("query")
+                .description("What to search for")
+                .required(true)
+                .build()
+                .expect("infallible")])
+            .build()
+            .expect("infallible")
+    }
+}
+
+impl From<BrowseUrl> for Box<dyn Tool> {
+    fn from(val: BrowseUrl) -> Self {
+        Box::new(val)
+    }
+}
+
+impl From<SearchWeb> for Box<dyn Tool> {
+    fn from(val: SearchWeb) -> Self {
+        Box::new(val)
+    }
+}
+
+/// Percent encodes a query string value
+fn encode_query(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b' ' => "+".to_string(),
+            byte if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_truncates_to_token_budget() {
+        let web = WebTools::builder().max_tokens(10usize).build().unwrap();
+
+        let text = web.truncate("a".repeat(100)).await.unwrap();
+        assert!(text.len() <= 40);
+
+        let text = web.truncate("short".to_string()).await.unwrap();
+        assert_eq!(text, "short");
+    }
+
+    #[test]
+    fn test_encode_query() {
+        assert_eq!(encode_query("rust & swiftide"), "rust+%26+swiftide");
+    }
+
+    #[test]
+    fn test_url_is_required() {
+        let web = WebTools::builder().build().unwrap();
+
+        let result = web.browse_url().tool_spec().prepare_args(Some("{}"));
+        assert!(matches!(result, Err(ToolError::InvalidArguments(_))));
+    }
+}
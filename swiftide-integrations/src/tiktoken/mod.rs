@@ -0,0 +1,80 @@
+//! Token estimation with tiktoken, the tokenizer used by `OpenAI` models
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_core::tokenizer::EstimateTokens;
+//! # use swiftide_integrations::tiktoken::TikToken;
+//! # async fn run() -> anyhow::Result<()> {
+//! let tiktoken = TikToken::try_from_model("gpt-4o")?;
+//! let tokens = tiktoken.estimate(&"Hello, world!").await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use swiftide_core::tokenizer::{Estimatable, EstimateTokens};
+use tiktoken_rs::CoreBPE;
+
+/// Estimates tokens with a tiktoken encoding
+///
+/// Defaults to `o200k_base`, the encoding of `gpt-4o` and later models.
+#[derive(Clone)]
+pub struct TikToken {
+    bpe: Arc<CoreBPE>,
+}
+
+impl std::fmt::Debug for TikToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TikToken").finish_non_exhaustive()
+    }
+}
+
+impl Default for TikToken {
+    fn default() -> Self {
+        Self {
+            bpe: Arc::new(tiktoken_rs::o200k_base().expect("o200k_base is bundled")),
+        }
+    }
+}
+
+impl TikToken {
+    /// Uses the encoding of an `OpenAI` model, i.e. `gpt-4o`
+    ///
+    /// # Errors
+    ///
+    /// Errors if the model is not known by tiktoken
+    pub fn try_from_model(model: impl AsRef<str>) -> Result<Self> {
+        Ok(Self {
+            bpe: Arc::new(tiktoken_rs::get_bpe_from_model(model.as_ref())?),
+        })
+    }
+}
+
+#[async_trait]
+impl EstimateTokens for TikToken {
+    async fn estimate(&self, value: &dyn Estimatable) -> Result<usize> {
+        let tokens = value
+            .for_estimate()
+            .await?
+            .iter()
+            .map(|text| self.bpe.encode_ordinary(text).len())
+            .sum::<usize>();
+
+        Ok(tokens + value.additional_tokens())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_estimate() {
+        let tiktoken = TikToken::try_from_model("gpt-4o").unwrap();
+
+        assert_eq!(tiktoken.estimate(&"hello world").await.unwrap(), 2);
+    }
+}
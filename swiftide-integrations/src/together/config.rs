@@ -0,0 +1,76 @@
+use derive_builder::Builder;
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use secrecy::{ExposeSecret as _, SecretString};
+use serde::Deserialize;
+
+const TOGETHER_API_BASE: &str = "https://api.together.xyz/v1";
+
+#[derive(Clone, Debug, Deserialize, Builder)]
+#[serde(default)]
+#[builder(setter(into, strip_option))]
+pub struct TogetherConfig {
+    #[builder(default = TOGETHER_API_BASE.to_string())]
+    api_base: String,
+    api_key: SecretString,
+}
+
+impl TogetherConfig {
+    pub fn builder() -> TogetherConfigBuilder {
+        TogetherConfigBuilder::default()
+    }
+
+    pub fn with_api_base(&mut self, api_base: &str) -> &mut Self {
+        self.api_base = api_base.to_string();
+
+        self
+    }
+
+    pub fn with_api_key(&mut self, api_key: impl Into<SecretString>) -> &mut Self {
+        self.api_key = api_key.into();
+
+        self
+    }
+}
+
+impl Default for TogetherConfig {
+    fn default() -> Self {
+        Self {
+            api_base: TOGETHER_API_BASE.to_string(),
+            api_key: std::env::var("TOGETHER_API_KEY")
+                .unwrap_or_else(|_| String::new())
+                .into(),
+        }
+    }
+}
+
+impl async_openai::config::Config for TogetherConfig {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        let api_key = self.api_key.expose_secret();
+        assert!(!api_key.is_empty(), "API key for Together is required");
+
+        headers.insert(
+            AUTHORIZATION,
+            format!("Bearer {api_key}").as_str().parse().unwrap(),
+        );
+
+        headers
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_base, path)
+    }
+
+    fn api_base(&self) -> &str {
+        &self.api_base
+    }
+
+    fn api_key(&self) -> &SecretString {
+        &self.api_key
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        vec![]
+    }
+}
@@ -0,0 +1,77 @@
+//! This module provides integration with `Together AI`'s API, enabling the use of open models hosted
+//! by Together for prompting, chat completion, embeddings and reranking.
+//!
+//! The `Together` client is a [`GenericOpenAI`] configured with a [`TogetherConfig`]. Together
+//! exposes an `OpenAI` compatible api for chat completions and embeddings. Reranking is available
+//! via [`Rerank`], which implements [`swiftide_core::Rerank`].
+//!
+//! The module is conditionally compiled based on the "together" feature flag.
+
+use crate::openai::GenericOpenAI;
+
+pub mod config;
+mod rerank;
+
+pub use config::TogetherConfig;
+pub use rerank::{Rerank, RerankBuilder};
+
+/// The `Together` client encapsulates an `async_openai` client configured for Together and
+/// default options for embedding and prompt models.
+///
+/// By default it will look for a `TOGETHER_API_KEY` environment variable. Note that either a
+/// prompt model or embedding model always need to be set, either with
+/// [`Together::with_default_prompt_model`] or [`Together::with_default_embed_model`] or via the
+/// builder. You can find available models in the Together documentation.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::together::Together;
+/// let together = Together::builder()
+///     .default_prompt_model("meta-llama/Llama-3.3-70B-Instruct-Turbo")
+///     .default_embed_model("togethercomputer/m2-bert-80M-8k-retrieval")
+///     .build()
+///     .unwrap();
+/// ```
+pub type Together = GenericOpenAI<TogetherConfig>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_models() {
+        let together = Together::builder()
+            .default_prompt_model("meta-llama/Llama-3.3-70B-Instruct-Turbo")
+            .default_embed_model("togethercomputer/m2-bert-80M-8k-retrieval")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            together.options().prompt_model.as_deref(),
+            Some("meta-llama/Llama-3.3-70B-Instruct-Turbo")
+        );
+        assert_eq!(
+            together.options().embed_model.as_deref(),
+            Some("togethercomputer/m2-bert-80M-8k-retrieval")
+        );
+    }
+
+    #[test]
+    fn test_custom_api_base() {
+        let config = TogetherConfig::builder()
+            .api_base("http://localhost:1234/v1")
+            .api_key("test")
+            .build()
+            .unwrap();
+        let together = Together::builder()
+            .client(async_openai::Client::with_config(config))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            async_openai::config::Config::api_base(together.client().config()),
+            "http://localhost:1234/v1"
+        );
+    }
+}
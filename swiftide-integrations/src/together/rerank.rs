@@ -0,0 +1,194 @@
+//! Reranking via the Together rerank endpoint
+use anyhow::{Context as _, Result};
+use async_openai::config::Config as _;
+use async_trait::async_trait;
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+use swiftide_core::{document::Document, reranking::select_by_score};
+
+use super::TogetherConfig;
+
+const DEFAULT_RERANK_MODEL: &str = "Salesforce/Llama-Rank-V1";
+
+/// Reranks documents with the Together rerank api, implementing [`swiftide_core::Rerank`].
+///
+/// By default it will look for a `TOGETHER_API_KEY` environment variable and use the
+/// `Salesforce/Llama-Rank-V1` model.
+///
+/// # Example
+///
+/// ```no_run
+/// # use swiftide_integrations::together;
+/// let rerank = together::Rerank::builder()
+///     .model("Salesforce/Llama-Rank-V1")
+///     .top_n(5)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into, strip_option))]
+pub struct Rerank {
+    #[builder(default)]
+    config: TogetherConfig,
+    #[builder(default)]
+    http_client: reqwest::Client,
+    /// The rerank model to use
+    #[builder(default = DEFAULT_RERANK_MODEL.to_string())]
+    model: String,
+    /// Only return the `top_n` most relevant documents
+    #[builder(default)]
+    top_n: Option<usize>,
+}
+
+impl Default for Rerank {
+    fn default() -> Self {
+        Self {
+            config: TogetherConfig::default(),
+            http_client: reqwest::Client::default(),
+            model: DEFAULT_RERANK_MODEL.to_string(),
+            top_n: None,
+        }
+    }
+}
+
+impl Rerank {
+    pub fn builder() -> RerankBuilder {
+        RerankBuilder::default()
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct RerankRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_n: Option<usize>,
+    return_documents: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct RerankResponse {
+    results: Vec<RerankResult>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RerankResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+#[async_trait]
+impl swiftide_core::Rerank for Rerank {
+    #[tracing::instrument(skip_all, err)]
+    async fn rerank(&self, query: &str, documents: Vec<Document>) -> Result<Vec<Document>> {
+        if documents.is_empty() {
+            return Ok(documents);
+        }
+
+        let request = RerankRequest {
+            model: &self.model,
+            query,
+            documents: documents.iter().map(Document::content).collect(),
+            top_n: self.top_n,
+            return_documents: false,
+        };
+
+        tracing::debug!(
+            model = &self.model,
+            num_documents = documents.len(),
+            "[Rerank] Request to together"
+        );
+
+        let response = self
+            .http_client
+            .post(self.config.url("/rerank"))
+            .headers(self.config.headers())
+            .json(&request)
+            .send()
+            .await
+            .context("Request to Together failed")?
+            .error_for_status()
+            .context("Together returned an error")?
+            .json::<RerankResponse>()
+            .await
+            .context("Failed to parse rerank response")?;
+
+        let reranked = select_by_score(
+            documents,
+            response
+                .results
+                .into_iter()
+                .map(|result| (result.index, result.relevance_score)),
+            self.top_n,
+            None,
+        );
+
+        tracing::debug!(
+            num_documents = reranked.len(),
+            "[Rerank] Response from together"
+        );
+
+        Ok(reranked)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use swiftide_core::Rerank as _;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_rerank_orders_by_relevance() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/rerank"))
+            .and(header("authorization", "Bearer test"))
+            .and(body_partial_json(json!({
+                "model": "Salesforce/Llama-Rank-V1",
+                "query": "What is swiftide?",
+                "documents": ["Rust is fast", "Swiftide is a rag library"],
+                "top_n": 2
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "rerank-1",
+                "model": "Salesforce/Llama-Rank-V1",
+                "results": [
+                    { "index": 0, "relevance_score": 0.1 },
+                    { "index": 1, "relevance_score": 0.9 }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = TogetherConfig::builder()
+            .api_base(mock_server.uri())
+            .api_key("test")
+            .build()
+            .unwrap();
+
+        let rerank = Rerank::builder().config(config).top_n(2).build().unwrap();
+
+        let documents = rerank
+            .rerank(
+                "What is swiftide?",
+                vec!["Rust is fast".into(), "Swiftide is a rag library".into()],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            documents,
+            vec![
+                Document::from("Swiftide is a rag library"),
+                Document::from("Rust is fast")
+            ]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Token estimation with `HuggingFace` tokenizers
+//!
+//! Loads the `tokenizer.json` that ships with open-weight models, i.e. the models served with
+//! Ollama, TGI or llama.cpp, so that token budgets match the model instead of approximating them
+//! with tiktoken.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_core::tokenizer::EstimateTokens;
+//! # use swiftide_integrations::tokenizers::HuggingFaceTokens;
+//! # async fn run() -> anyhow::Result<()> {
+//! let tokenizer = HuggingFaceTokens::from_file("models/llama-3.1-8b/tokenizer.json")?;
+//! let tokens = tokenizer.estimate(&"Hello, world!").await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{path::Path, sync::Arc};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use swiftide_core::tokenizer::{Estimatable, EstimateTokens};
+
+/// Estimates tokens with a `HuggingFace` tokenizer
+///
+/// Special tokens added by the tokenizer, i.e. a begin of sequence token, are not counted.
+#[derive(Clone)]
+pub struct HuggingFaceTokens {
+    tokenizer: Arc<::tokenizers::Tokenizer>,
+}
+
+impl std::fmt::Debug for HuggingFaceTokens {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HuggingFaceTokens").finish_non_exhaustive()
+    }
+}
+
+impl HuggingFaceTokens {
+    /// Loads a tokenizer from a `tokenizer.json` file
+    ///
+    /// # Errors
+    ///
+    /// Errors if the file cannot be read or is not a valid tokenizer
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let tokenizer = ::tokenizers::Tokenizer::from_file(path)
+            .map_err(|err| anyhow::anyhow!(err))
+            .with_context(|| format!("Failed to load tokenizer from {}", path.display()))?;
+
+        Ok(tokenizer.into())
+    }
+
+    /// Loads a tokenizer from the contents of a `tokenizer.json` file
+    ///
+    /// # Errors
+    ///
+    /// Errors if the bytes are not a valid tokenizer
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self> {
+        let tokenizer = ::tokenizers::Tokenizer::from_bytes(bytes)
+            .map_err(|err| anyhow::anyhow!(err))
+            .context("Failed to load tokenizer")?;
+
+        Ok(tokenizer.into())
+    }
+}
+
+impl From<::tokenizers::Tokenizer> for HuggingFaceTokens {
+    fn from(tokenizer: ::tokenizers::Tokenizer) -> Self {
+        Self {
+            tokenizer: Arc::new(tokenizer),
+        }
+    }
+}
+
+#[async_trait]
+impl EstimateTokens for HuggingFaceTokens {
+    async fn estimate(&self, value: &dyn Estimatable) -> Result<usize> {
+        let mut tokens = 0;
+
+        for text in value.for_estimate().await? {
+            tokens += self
+                .tokenizer
+                .encode(text.as_ref(), false)
+                .map_err(|err| anyhow::anyhow!(err))
+                .context("Failed to encode text")?
+                .len();
+        }
+
+        Ok(tokens + value.additional_tokens())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOKENIZER: &str = r#"{
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": { "type": "Whitespace" },
+        "post_processor": null,
+        "decoder": null,
+        "model": {
+            "type": "WordLevel",
+            "vocab": { "hello": 0, "world": 1, "[UNK]": 2 },
+            "unk_token": "[UNK]"
+        }
+    }"#;
+
+    #[tokio::test]
+    async fn test_estimate() {
+        let tokenizer = HuggingFaceTokens::from_bytes(TOKENIZER).unwrap();
+
+        assert_eq!(tokenizer.estimate(&"hello world").await.unwrap(), 2);
+        assert_eq!(
+            tokenizer.estimate(&"hello swiftide world").await.unwrap(),
+            3
+        );
+    }
+}
@@ -6,9 +6,17 @@ use derive_builder::Builder;
 use crate::treesitter::{ChunkSize, CodeSplitter, SupportedLanguages};
 use swiftide_core::{
     indexing::{IndexingStream, Node},
+    tokenizer::EstimateTokens,
     ChunkerTransformer,
 };
 
+/// The name of the symbol of a chunk, when chunking by symbol
+pub const NAME_SYMBOL: &str = "Symbol (code)";
+/// The declaration of the symbol of a chunk without its body, when chunking by symbol
+pub const NAME_SIGNATURE: &str = "Signature (code)";
+/// The type the symbol of a chunk is defined in, when chunking by symbol
+pub const NAME_ENCLOSING_TYPE: &str = "Enclosing type (code)";
+
 /// The `ChunkCode` struct is responsible for chunking code into smaller pieces
 /// based on the specified language and chunk size.
 ///
@@ -20,12 +28,26 @@ use swiftide_core::{
 /// ```no_run
 /// # use swiftide_integrations::treesitter::transformers::ChunkCode;
 /// # use swiftide_integrations::treesitter::SupportedLanguages;
+/// # use swiftide_core::tokenizer::ApproximateTokens;
 /// // Chunk rust code with a maximum chunk size of 1000 bytes.
 /// ChunkCode::try_for_language_and_chunk_size(SupportedLanguages::Rust, 1000);
 ///
 /// // Chunk python code with a minimum chunk size of 500 bytes and maximum chunk size of 2048.
 /// // Smaller chunks than 500 bytes will be discarded.
 /// ChunkCode::try_for_language_and_chunk_size(SupportedLanguages::Python, 500..2048);
+///
+/// // Chunk go code into chunks of at most 512 tokens, as estimated from the characters.
+/// ChunkCode::try_for_language_and_token_budget(
+///     SupportedLanguages::Go,
+///     512,
+///     ApproximateTokens::default(),
+/// );
+///
+/// // Chunk java code into a chunk per function and type, with the signature and enclosing type
+/// // of every chunk in its metadata.
+/// ChunkCode::try_for_language(SupportedLanguages::Java)
+///     .unwrap()
+///     .by_symbol();
 /// ````
 #[derive(Debug, Clone, Builder)]
 #[builder(pattern = "owned", setter(into, strip_option))]
@@ -33,6 +55,9 @@ pub struct ChunkCode {
     chunker: CodeSplitter,
     #[builder(default)]
     concurrency: Option<usize>,
+    /// Chunks by top-level symbol instead of by size, see [`CodeSplitter::split_symbols`]
+    #[builder(default)]
+    by_symbol: bool,
 }
 
 impl ChunkCode {
@@ -54,6 +79,7 @@ impl ChunkCode {
         Ok(Self {
             chunker: CodeSplitter::builder().try_language(lang)?.build()?,
             concurrency: None,
+            by_symbol: false,
         })
     }
 
@@ -78,6 +104,28 @@ impl ChunkCode {
                 .chunk_size(chunk_size)
                 .build()?,
             concurrency: None,
+            by_symbol: false,
+        })
+    }
+
+    /// Tries to create a `ChunkCode` instance for a given programming language, with chunks that
+    /// fit a budget in estimated tokens.
+    ///
+    /// # Errors
+    /// - Returns an error if the language is not supported or if the `CodeSplitter` fails to build.
+    pub fn try_for_language_and_token_budget(
+        lang: impl TryInto<SupportedLanguages>,
+        chunk_size: impl Into<ChunkSize>,
+        estimator: impl EstimateTokens + 'static,
+    ) -> Result<Self> {
+        Ok(Self {
+            chunker: CodeSplitter::builder()
+                .try_language(lang)?
+                .chunk_size(chunk_size)
+                .estimator(estimator)
+                .build()?,
+            concurrency: None,
+            by_symbol: false,
         })
     }
 
@@ -86,6 +134,39 @@ impl ChunkCode {
         self.concurrency = Some(concurrency);
         self
     }
+
+    /// Chunks by top-level symbol, one function or type per chunk, instead of by size
+    ///
+    /// The name, signature and enclosing type of the symbol are added to the metadata.
+    #[must_use]
+    pub fn by_symbol(mut self) -> Self {
+        self.by_symbol = true;
+        self
+    }
+
+    fn transform_node_by_symbol(&self, node: &Node) -> Result<Vec<Result<Node>>> {
+        let symbols = self.chunker.split_symbols(&node.chunk)?;
+
+        Ok(symbols
+            .into_iter()
+            .map(|symbol| {
+                let mut metadata = node.metadata.clone();
+                if let Some(name) = symbol.name {
+                    metadata.insert(NAME_SYMBOL, name);
+                }
+                metadata.insert(NAME_SIGNATURE, symbol.signature);
+                if let Some(enclosing_type) = symbol.enclosing_type {
+                    metadata.insert(NAME_ENCLOSING_TYPE, enclosing_type);
+                }
+
+                Node::build_from_other(node)
+                    .chunk(symbol.code)
+                    .offset(symbol.offset)
+                    .metadata(metadata)
+                    .build()
+            })
+            .collect())
+    }
 }
 
 #[async_trait]
@@ -102,7 +183,16 @@ impl ChunkerTransformer for ChunkCode {
     /// - If the code splitting fails, an error is sent downstream.
     #[tracing::instrument(skip_all, name = "transformers.chunk_code")]
     async fn transform_node(&self, node: Node) -> IndexingStream {
-        let split_result = self.chunker.split(&node.chunk);
+        if self.by_symbol {
+            return match self.transform_node_by_symbol(&node) {
+                Ok(nodes) => IndexingStream::iter(nodes),
+                Err(err) => IndexingStream::iter(vec![Err(
+                    err.context(format!("Failed to chunk {}", node.path.display()))
+                )]),
+            };
+        }
+
+        let split_result = self.chunker.split_estimated(&node.chunk).await;
 
         if let Ok(split) = split_result {
             let mut offset = 0;
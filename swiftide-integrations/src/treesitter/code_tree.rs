@@ -8,11 +8,35 @@ use tree_sitter::{Parser, Query, QueryCursor, Tree};
 use anyhow::{Context as _, Result};
 use std::collections::HashSet;
 
-use crate::treesitter::queries::{go, java, javascript, python, ruby, rust, typescript};
+use crate::treesitter::queries::{
+    csharp, go, java, javascript, kotlin, php, python, ruby, rust, swift, typescript,
+};
 
 use super::SupportedLanguages;
 
-#[derive(Debug)]
+/// Import statements of all supported languages
+const IMPORT_KINDS: &[&str] = &[
+    "use_declaration",
+    "import_statement",
+    "import_from_statement",
+    "import_declaration",
+    "import_header",
+    "import",
+    "using_directive",
+    "namespace_use_declaration",
+];
+
+/// Nodes that can hold imports
+const NAMESPACE_KINDS: &[&str] = &[
+    "import_list",
+    "namespace_declaration",
+    "file_scoped_namespace_declaration",
+    "namespace_definition",
+    "declaration_list",
+    "compound_statement",
+];
+
+#[derive(Debug, Clone)]
 pub struct CodeParser {
     language: SupportedLanguages,
 }
@@ -84,6 +108,23 @@ impl CodeTree<'_> {
         })
     }
 
+    /// Returns the top-level imports in the code as written, i.e. `use std::fmt;`
+    pub fn imports(&self) -> Vec<String> {
+        let mut imports = Vec::new();
+        self.collect_imports(self.ts_tree.root_node(), &mut imports);
+        imports
+    }
+
+    fn collect_imports(&self, node: tree_sitter::Node, imports: &mut Vec<String>) {
+        for child in node.named_children(&mut node.walk()) {
+            if IMPORT_KINDS.contains(&child.kind()) {
+                imports.push(self.code[child.byte_range()].trim().to_string());
+            } else if NAMESPACE_KINDS.contains(&child.kind()) {
+                self.collect_imports(child, imports);
+            }
+        }
+    }
+
     /// Given a `tree-sitter` query, searches the code and returns a list of matching symbols
     fn ts_query_for_matches(&self, query: &Query) -> Result<HashSet<String>> {
         let mut cursor = QueryCursor::new();
@@ -107,7 +148,9 @@ impl CodeTree<'_> {
 }
 
 fn ts_queries_for_language(language: SupportedLanguages) -> (&'static str, &'static str) {
-    use SupportedLanguages::{Go, Java, Javascript, Python, Ruby, Rust, Typescript};
+    use SupportedLanguages::{
+        CSharp, Go, Java, Javascript, Kotlin, Php, Python, Ruby, Rust, Swift, Typescript,
+    };
 
     match language {
         Rust => (rust::DEFS, rust::REFS),
@@ -118,6 +161,10 @@ fn ts_queries_for_language(language: SupportedLanguages) -> (&'static str, &'sta
         Ruby => (ruby::DEFS, ruby::REFS),
         Java => (java::DEFS, java::REFS),
         Go => (go::DEFS, go::REFS),
+        Kotlin => (kotlin::DEFS, kotlin::REFS),
+        Swift => (swift::DEFS, swift::REFS),
+        CSharp => (csharp::DEFS, csharp::REFS),
+        Php => (php::DEFS, php::REFS),
     }
 }
 
@@ -282,6 +329,22 @@ mod tests {
         assert!(result.references.is_empty());
     }
 
+    #[test]
+    fn test_imports() {
+        let parser = CodeParser::from_language(SupportedLanguages::Rust);
+        let code = r"
+        use std::fmt;
+        use crate::{a, b};
+
+        fn main() {
+            use std::io;
+        }
+        ";
+
+        let tree = parser.parse(code).unwrap();
+        assert_eq!(tree.imports(), vec!["use std::fmt;", "use crate::{a, b};"]);
+    }
+
     #[test]
     fn test_parsing_go() {
         let parser = CodeParser::from_language(SupportedLanguages::Go);
@@ -305,4 +368,92 @@ mod tests {
         assert_eq!(result.references, vec!["Println", "int", "string"]);
         assert_eq!(result.definitions, vec!["Person", "main"]);
     }
+
+    #[test]
+    fn test_parsing_kotlin() {
+        let parser = CodeParser::from_language(SupportedLanguages::Kotlin);
+        let code = r#"
+        class Person(val name: String) {
+            fun greet() {
+                println(name)
+            }
+        }
+
+        fun main() {
+            val p = Person("John")
+            p.greet()
+        }
+        "#;
+
+        let tree = parser.parse(code).unwrap();
+        let result = tree.references_and_definitions().unwrap();
+        assert_eq!(result.references, vec!["String", "println"]);
+        assert_eq!(result.definitions, vec!["Person", "greet", "main"]);
+    }
+
+    #[test]
+    fn test_parsing_swift() {
+        let parser = CodeParser::from_language(SupportedLanguages::Swift);
+        let code = r#"
+        class Person {
+            func greet() {
+                print("Hello")
+            }
+        }
+
+        func main() {
+            let p = Person()
+            p.greet()
+        }
+        "#;
+
+        let tree = parser.parse(code).unwrap();
+        let result = tree.references_and_definitions().unwrap();
+        assert_eq!(result.references, vec!["print"]);
+        assert_eq!(result.definitions, vec!["Person", "greet", "main"]);
+    }
+
+    #[test]
+    fn test_parsing_csharp() {
+        let parser = CodeParser::from_language(SupportedLanguages::CSharp);
+        let code = r#"
+        class Person {
+            void Greet() {
+                Console.WriteLine("Hello");
+            }
+
+            static void Main() {
+                var p = new Person();
+                p.Greet();
+            }
+        }
+        "#;
+
+        let tree = parser.parse(code).unwrap();
+        let result = tree.references_and_definitions().unwrap();
+        assert_eq!(result.references, vec!["WriteLine"]);
+        assert_eq!(result.definitions, vec!["Greet", "Main", "Person"]);
+    }
+
+    #[test]
+    fn test_parsing_php() {
+        let parser = CodeParser::from_language(SupportedLanguages::Php);
+        let code = r#"<?php
+        class Person {
+            public function greet() {
+                echo strtoupper("hello");
+            }
+        }
+
+        function main() {
+            $p = new Person();
+            $p->greet();
+        }
+        "#;
+
+        let tree = parser.parse(code).unwrap();
+        let result = tree.references_and_definitions().unwrap();
+        assert_eq!(result.references, vec!["strtoupper"]);
+        assert_eq!(result.definitions, vec!["Person", "greet", "main"]);
+    }
 }
@@ -0,0 +1,204 @@
+//! Adds the edges of the call graph of the code in a chunk to its metadata
+//!
+//! Every edge is a triple of a subject, a predicate and an object:
+//! - `<path> defines <symbol>` for every symbol defined in the chunk
+//! - `<symbol> references <symbol>` for every non-local symbol a function or type references
+//!
+//! Edges from a symbol are only extracted if the chunk can be parsed on its own, i.e. when
+//! chunking by symbol. Otherwise only the definitions are recorded.
+//!
+//! The triples have the same shape as the ones stored by the `Neo4j` integration, so that the
+//! call graph can be persisted in a graph store by setting its triples key to [`NAME`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_core::indexing::Node;
+//! # use swiftide_integrations::treesitter::transformers::metadata_call_graph_code::*;
+//! # use swiftide_core::Transformer;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let transformer = MetadataCallGraphCode::try_from_language("rust").unwrap();
+//! let code = r#"
+//!   fn main() {
+//!     greet();
+//!   }
+//! "#;
+//! let mut node = Node::new(code.to_string());
+//! node.path = "src/main.rs".into();
+//!
+//! node = transformer.transform_node(node).await.unwrap();
+//!
+//! assert_eq!(
+//!     node.metadata.get(NAME).unwrap(),
+//!     &serde_json::json!([
+//!         { "subject": "src/main.rs", "predicate": "defines", "object": "main" },
+//!         { "subject": "main", "predicate": "references", "object": "greet" },
+//!     ])
+//! );
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use swiftide_core::{indexing::Node, Transformer};
+
+use crate::treesitter::{CodeParser, CodeSplitter, SupportedLanguages};
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+
+pub const PREDICATE_DEFINES: &str = "defines";
+pub const PREDICATE_REFERENCES: &str = "references";
+
+/// An edge in the call graph, i.e. `("main", "references", "greet")`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edge {
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+}
+
+impl Edge {
+    fn new(subject: &str, predicate: &str, object: &str) -> Self {
+        Self {
+            subject: subject.to_string(),
+            predicate: predicate.to_string(),
+            object: object.to_string(),
+        }
+    }
+}
+
+/// `MetadataCallGraphCode` extracts the symbols a chunk defines, and the symbols every function
+/// or type in it references.
+#[swiftide_macros::indexing_transformer(
+    metadata_field_name = "Call graph (code)",
+    derive(skip_default)
+)]
+pub struct MetadataCallGraphCode {
+    code_parser: Arc<CodeParser>,
+    splitter: Arc<CodeSplitter>,
+}
+
+impl MetadataCallGraphCode {
+    /// Tries to build a new `MetadataCallGraphCode` transformer
+    ///
+    /// # Errors
+    ///
+    /// Language is not supported by tree-sitter
+    pub fn try_from_language(language: impl TryInto<SupportedLanguages>) -> Result<Self> {
+        let language: SupportedLanguages = language
+            .try_into()
+            .ok()
+            .context("Treesitter language not supported")?;
+
+        MetadataCallGraphCode::builder()
+            .code_parser(CodeParser::from_language(language))
+            .splitter(CodeSplitter::new(language))
+            .build()
+    }
+
+    /// Returns the edges of the call graph of the given code
+    ///
+    /// # Errors
+    ///
+    /// Errors if the code cannot be parsed or queried
+    pub fn edges(&self, path: &str, code: &str) -> Result<Vec<Edge>> {
+        let refs_defs = self.code_parser.parse(code)?.references_and_definitions()?;
+
+        let mut edges = refs_defs
+            .definitions
+            .iter()
+            .map(|definition| Edge::new(path, PREDICATE_DEFINES, definition))
+            .collect::<Vec<_>>();
+
+        // Partial code cannot be split into symbols
+        let Ok(symbols) = self.splitter.split_symbols(code) else {
+            return Ok(edges);
+        };
+
+        for symbol in symbols {
+            let Some(name) = symbol.name else {
+                continue;
+            };
+
+            let references = self
+                .code_parser
+                .parse(&symbol.code)?
+                .references_and_definitions()?
+                .references;
+
+            edges.extend(
+                references
+                    .iter()
+                    .filter(|reference| **reference != name)
+                    .map(|reference| Edge::new(&name, PREDICATE_REFERENCES, reference)),
+            );
+        }
+
+        Ok(edges)
+    }
+}
+
+#[async_trait]
+impl Transformer for MetadataCallGraphCode {
+    /// Extracts the call graph from code and adds its edges as metadata to the node if present
+    #[tracing::instrument(skip_all, name = "transformers.metadata_call_graph_code")]
+    async fn transform_node(&self, mut node: Node) -> Result<Node> {
+        let edges = self.edges(&node.path.to_string_lossy(), &node.chunk)?;
+
+        if !edges.is_empty() {
+            node.metadata.insert(NAME, serde_json::to_value(edges)?);
+        }
+        Ok(node)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_call_graph_from_code() {
+        let transformer = MetadataCallGraphCode::try_from_language("rust").unwrap();
+        let code = indoc! {r#"
+            fn main() {
+                greet("World");
+            }
+
+            fn greet(name: &str) {
+                println!("Hello, {name}!");
+            }
+        "#};
+        let mut node = Node::new(code);
+        node.path = "src/main.rs".into();
+
+        let node = transformer.transform_node(node).await.unwrap();
+
+        let edges: Vec<Edge> =
+            serde_json::from_value(node.metadata.get(NAME).unwrap().clone()).unwrap();
+
+        assert_eq!(
+            edges,
+            vec![
+                Edge::new("src/main.rs", "defines", "greet"),
+                Edge::new("src/main.rs", "defines", "main"),
+                Edge::new("main", "references", "greet"),
+                Edge::new("greet", "references", "println"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partial_code_only_records_definitions() {
+        let transformer = MetadataCallGraphCode::try_from_language("rust").unwrap();
+
+        let edges = transformer
+            .edges("src/main.rs", "fn main() {\n    greet(")
+            .unwrap();
+
+        assert!(edges.iter().all(|edge| edge.predicate == PREDICATE_DEFINES));
+    }
+}
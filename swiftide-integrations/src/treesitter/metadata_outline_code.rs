@@ -0,0 +1,236 @@
+//! Adds a structured outline of the code in a node to its metadata, using tree-sitter.
+//!
+//! The outline lists the imports, and the signature and doc comments of every type and function,
+//! grouped by the type they are defined in. It helps retrieval for questions like "where is X
+//! defined" without an LLM call.
+//!
+//! Used with `then_chunk`, the outline is emitted as a summary node of its own next to the
+//! original node instead.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_core::indexing::Node;
+//! # use swiftide_integrations::treesitter::transformers::metadata_outline_code::*;
+//! # use swiftide_core::Transformer;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let transformer = MetadataOutlineCode::try_from_language("rust").unwrap();
+//! let code = r#"
+//! use std::fmt;
+//!
+//! /// Says hello
+//! fn main() {
+//!     println!("Hello, World!");
+//! }
+//! "#;
+//!
+//! let node = Transformer::transform_node(&transformer, Node::new(code)).await?;
+//!
+//! assert_eq!(
+//!     node.metadata.get(NAME).unwrap().as_str().unwrap(),
+//!     "use std::fmt;\n\n/// Says hello\nfn main()"
+//! );
+//! # Ok(())
+//! # }
+//! ```
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use swiftide_core::{
+    indexing::{IndexingStream, Node},
+    ChunkerTransformer, Transformer,
+};
+
+use crate::treesitter::{CodeParser, CodeSplitter, CodeSymbol, SupportedLanguages};
+
+pub const NAME: &str = "Outline (code)";
+/// Set on the summary node emitted when used as a chunker
+pub const NAME_SUMMARY: &str = "Outline summary (code)";
+
+/// `MetadataOutlineCode` adds an outline of imports, types and functions to the metadata of a
+/// node, or emits it as a separate summary node when used as a chunker.
+#[derive(Debug, Clone)]
+pub struct MetadataOutlineCode {
+    parser: CodeParser,
+    splitter: CodeSplitter,
+}
+
+impl MetadataOutlineCode {
+    /// Tries to build a new `MetadataOutlineCode` transformer
+    ///
+    /// # Errors
+    ///
+    /// Language is not supported by tree-sitter
+    pub fn try_from_language(language: impl TryInto<SupportedLanguages>) -> Result<Self> {
+        let language: SupportedLanguages = language
+            .try_into()
+            .ok()
+            .context("Treesitter language not supported")?;
+
+        Ok(Self {
+            parser: CodeParser::from_language(language),
+            splitter: CodeSplitter::new(language),
+        })
+    }
+
+    /// Renders the outline of the given code
+    ///
+    /// # Errors
+    ///
+    /// Errors if the code cannot be parsed
+    pub fn outline(&self, code: &str) -> Result<String> {
+        let imports = self.parser.parse(code)?.imports();
+        let symbols = self.splitter.split_symbols(code)?;
+
+        Ok(render_outline(&imports, &symbols))
+    }
+}
+
+fn render_outline(imports: &[String], symbols: &[CodeSymbol]) -> String {
+    let mut sections = Vec::new();
+
+    if !imports.is_empty() {
+        sections.push(imports.join("\n"));
+    }
+
+    let mut enclosing_type = None;
+    for symbol in symbols {
+        let mut section = String::new();
+        let indent = if symbol.enclosing_type.is_some() {
+            "    "
+        } else {
+            ""
+        };
+
+        if symbol.enclosing_type.is_some() && symbol.enclosing_type != enclosing_type {
+            section.push_str(&format!(
+                "{}:\n",
+                symbol.enclosing_type.as_deref().unwrap_or_default()
+            ));
+        }
+        enclosing_type.clone_from(&symbol.enclosing_type);
+
+        for line in symbol.doc.iter().flat_map(|doc| doc.lines()) {
+            section.push_str(&format!("{indent}{}\n", line.trim()));
+        }
+        section.push_str(&format!("{indent}{}", symbol.signature));
+
+        sections.push(section);
+    }
+
+    sections.join("\n\n")
+}
+
+#[async_trait]
+impl Transformer for MetadataOutlineCode {
+    /// Adds the outline of the code to the metadata of the node
+    #[tracing::instrument(skip_all, name = "transformers.metadata_outline_code")]
+    async fn transform_node(&self, mut node: Node) -> Result<Node> {
+        let outline = self.outline(&node.chunk)?;
+
+        if !outline.is_empty() {
+            node.metadata.insert(NAME, outline);
+        }
+        Ok(node)
+    }
+}
+
+#[async_trait]
+impl ChunkerTransformer for MetadataOutlineCode {
+    /// Emits the node unchanged, followed by a summary node with the outline as its chunk
+    #[tracing::instrument(skip_all, name = "transformers.metadata_outline_code")]
+    async fn transform_node(&self, node: Node) -> IndexingStream {
+        let outline = match self.outline(&node.chunk) {
+            Ok(outline) => outline,
+            Err(err) => {
+                return vec![Err(
+                    err.context(format!("Failed to outline {}", node.path.display()))
+                )]
+                .into()
+            }
+        };
+
+        if outline.is_empty() {
+            return vec![Ok(node)].into();
+        }
+
+        let mut summary = Node::build_from_other(&node).chunk(outline).build();
+        if let Ok(summary) = summary.as_mut() {
+            summary.metadata.insert(NAME_SUMMARY, true);
+        }
+
+        vec![Ok(node), summary].into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::TryStreamExt as _;
+    use indoc::indoc;
+
+    use super::*;
+
+    const CODE: &str = indoc! {r#"
+        use std::fmt;
+
+        /// A person
+        struct Person {
+            name: String,
+        }
+
+        impl Person {
+            /// Greets the person
+            pub fn greet(&self) -> String {
+                format!("Hello, {}", self.name)
+            }
+
+            fn age(&self) -> usize {
+                42
+            }
+        }
+
+        fn main() {}
+    "#};
+
+    #[tokio::test]
+    async fn test_outline_as_metadata() {
+        let transformer = MetadataOutlineCode::try_from_language("rust").unwrap();
+
+        let node = Transformer::transform_node(&transformer, Node::new(CODE))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            node.metadata.get(NAME).unwrap().as_str().unwrap(),
+            indoc! {"
+                use std::fmt;
+
+                /// A person
+                struct Person
+
+                Person:
+                    /// Greets the person
+                    pub fn greet(&self) -> String
+
+                    fn age(&self) -> usize
+
+                fn main()"}
+        );
+    }
+
+    #[tokio::test]
+    async fn test_outline_as_summary_node() {
+        let transformer = MetadataOutlineCode::try_from_language("rust").unwrap();
+
+        let nodes: Vec<Node> = ChunkerTransformer::transform_node(&transformer, Node::new(CODE))
+            .await
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].chunk, CODE);
+        assert!(nodes[1].chunk.starts_with("use std::fmt;"));
+        assert_eq!(nodes[1].metadata.get(NAME_SUMMARY), Some(&true.into()));
+    }
+}
@@ -98,7 +98,9 @@ impl CodeOutliner {
 
     fn is_unneeded_node(&self, node: Node) -> bool {
         match self.language {
-            SupportedLanguages::Rust | SupportedLanguages::Java => matches!(node.kind(), "block"),
+            SupportedLanguages::Rust | SupportedLanguages::Java | SupportedLanguages::CSharp => {
+                matches!(node.kind(), "block")
+            }
             SupportedLanguages::Typescript | SupportedLanguages::Javascript => {
                 matches!(node.kind(), "statement_block")
             }
@@ -118,6 +120,10 @@ impl CodeOutliner {
                 }
                 _ => false,
             },
+            SupportedLanguages::Kotlin | SupportedLanguages::Swift => {
+                matches!(node.kind(), "function_body")
+            }
+            SupportedLanguages::Php => matches!(node.kind(), "compound_statement"),
             SupportedLanguages::Go => unimplemented!(),
         }
     }
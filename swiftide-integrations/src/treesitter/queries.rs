@@ -359,3 +359,127 @@ pub mod go {
     (type_identifier) @name 
             "#;
 }
+
+pub mod kotlin {
+    pub const DEFS: &str = r"
+    (class_declaration
+        name: (identifier) @name)
+
+    (object_declaration
+        name: (identifier) @name)
+
+    (function_declaration
+        name: (identifier) @name)
+            ";
+
+    pub const REFS: &str = r"
+    (call_expression
+        (identifier) @name)
+
+    (call_expression
+        (navigation_expression (identifier) @name .))
+
+    (user_type (identifier) @name)
+            ";
+}
+
+// https://github.com/alex-pinkus/tree-sitter-swift/blob/main/queries/tags.scm
+pub mod swift {
+    pub const DEFS: &str = r"
+    (class_declaration
+        name: (type_identifier) @name)
+
+    (protocol_declaration
+        name: (type_identifier) @name)
+
+    (function_declaration
+        name: (simple_identifier) @name)
+            ";
+
+    pub const REFS: &str = r"
+    (call_expression
+        (simple_identifier) @name)
+
+    (call_expression
+        (navigation_expression
+            suffix: (navigation_suffix
+                suffix: (simple_identifier) @name)))
+            ";
+}
+
+// https://github.com/tree-sitter/tree-sitter-c-sharp/blob/master/queries/tags.scm
+pub mod csharp {
+    pub const DEFS: &str = r"
+    (class_declaration
+        name: (identifier) @name)
+
+    (struct_declaration
+        name: (identifier) @name)
+
+    (record_declaration
+        name: (identifier) @name)
+
+    (enum_declaration
+        name: (identifier) @name)
+
+    (interface_declaration
+        name: (identifier) @name)
+
+    (method_declaration
+        name: (identifier) @name)
+            ";
+
+    pub const REFS: &str = r"
+    (invocation_expression
+        function: (identifier) @name)
+
+    (invocation_expression
+        function: (member_access_expression
+            name: (identifier) @name))
+
+    (object_creation_expression
+        type: (identifier) @name)
+
+    (base_list (identifier) @name)
+            ";
+}
+
+// https://github.com/tree-sitter/tree-sitter-php/blob/master/queries/tags.scm
+pub mod php {
+    pub const DEFS: &str = r"
+    (class_declaration
+        name: (name) @name)
+
+    (interface_declaration
+        name: (name) @name)
+
+    (trait_declaration
+        name: (name) @name)
+
+    (function_definition
+        name: (name) @name)
+
+    (method_declaration
+        name: (name) @name)
+            ";
+
+    pub const REFS: &str = r"
+    (function_call_expression
+        function: [
+            (name) @name
+            (qualified_name (name) @name)
+        ])
+
+    (object_creation_expression
+        [
+            (name) @name
+            (qualified_name (name) @name)
+        ])
+
+    (scoped_call_expression
+        name: (name) @name)
+
+    (member_call_expression
+        name: (name) @name)
+            ";
+}
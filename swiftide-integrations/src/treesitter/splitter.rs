@@ -1,5 +1,6 @@
 use anyhow::{Context as _, Result};
-use std::ops::Range;
+use std::{ops::Range, sync::Arc};
+use swiftide_core::tokenizer::EstimateTokens;
 use tree_sitter::{Node, Parser};
 
 use derive_builder::Builder;
@@ -9,10 +10,83 @@ use super::supported_languages::SupportedLanguages;
 // TODO: Instead of counting bytes, count tokens with titktoken
 const DEFAULT_MAX_BYTES: usize = 1500;
 
+/// The number of times code is split again with a smaller size when chunks exceed the token budget
+const MAX_TOKEN_ATTEMPTS: usize = 10;
+
+/// Functions and methods, always a symbol of their own
+const FUNCTION_KINDS: &[&str] = &[
+    "function_item",
+    "function_signature_item",
+    "function_definition",
+    "function_declaration",
+    "generator_function_declaration",
+    "method_definition",
+    "method_declaration",
+    "constructor_declaration",
+    "protocol_function_declaration",
+    "method",
+    "singleton_method",
+];
+
+/// Types that can contain methods. If they do, every method is a symbol with the type as
+/// enclosing type, otherwise the type is a symbol of its own.
+const CONTAINER_KINDS: &[&str] = &[
+    "impl_item",
+    "trait_item",
+    "class_definition",
+    "class_declaration",
+    "abstract_class_declaration",
+    "interface_declaration",
+    "enum_declaration",
+    "record_declaration",
+    "struct_declaration",
+    "object_declaration",
+    "protocol_declaration",
+    "trait_declaration",
+    "class",
+    "module",
+];
+
+/// Other top-level declarations that are a symbol of their own
+const DECLARATION_KINDS: &[&str] = &[
+    "struct_item",
+    "enum_item",
+    "union_item",
+    "type_item",
+    "const_item",
+    "static_item",
+    "macro_definition",
+    "mod_item",
+    "type_declaration",
+    "type_alias_declaration",
+    "lexical_declaration",
+];
+
+/// Nodes that wrap declarations, or hold the members of a type
+const TRANSPARENT_KINDS: &[&str] = &[
+    "export_statement",
+    "decorated_definition",
+    "namespace_declaration",
+    "file_scoped_namespace_declaration",
+    "namespace_definition",
+    "compound_statement",
+    "declaration_list",
+    "class_body",
+    "interface_body",
+    "enum_body",
+    "enum_body_declarations",
+    "enum_class_body",
+    "protocol_body",
+    "body_statement",
+    "block",
+];
+
 #[derive(Debug, Builder, Clone)]
 /// Splits code files into meaningful chunks
 ///
 /// Supports splitting code files into chunks based on a maximum size or a range of bytes.
+///
+/// With an estimator, the chunk size is in tokens instead, see [`CodeSplitter::split_estimated`].
 #[builder(setter(into), build_fn(error = "anyhow::Error"))]
 pub struct CodeSplitter {
     /// Maximum size of a chunk in bytes or a range of bytes
@@ -20,6 +94,9 @@ pub struct CodeSplitter {
     chunk_size: ChunkSize,
     #[builder(setter(custom))]
     language: SupportedLanguages,
+    /// Estimates tokens, so that the chunk size is a token budget instead of bytes
+    #[builder(default, setter(custom))]
+    estimator: Option<Arc<dyn EstimateTokens>>,
 }
 
 impl CodeSplitterBuilder {
@@ -45,6 +122,12 @@ impl CodeSplitterBuilder {
         );
         Ok(self)
     }
+
+    /// Interprets the chunk size as tokens, estimated with the given estimator
+    pub fn estimator(&mut self, estimator: impl EstimateTokens + 'static) -> &mut Self {
+        self.estimator = Some(Some(Arc::new(estimator)));
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -75,6 +158,25 @@ impl Default for ChunkSize {
     }
 }
 
+/// A single symbol in code, i.e. a function, a method or a type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeSymbol {
+    /// The code of the symbol, including any preceding comments
+    pub code: String,
+    /// The byte offset of the symbol in the source
+    pub offset: usize,
+    /// The name of the symbol, if it has one
+    pub name: Option<String>,
+    /// The tree-sitter kind of the symbol, i.e. `function_item`
+    pub kind: String,
+    /// The declaration of the symbol without its body
+    pub signature: String,
+    /// The comments and attributes directly preceding the symbol, i.e. doc comments
+    pub doc: Option<String>,
+    /// The name of the type the symbol is defined in, i.e. the class of a method
+    pub enclosing_type: Option<String>,
+}
+
 impl CodeSplitter {
     /// Creates a new `CodeSplitter` with the specified language and default chunk size.
     ///
@@ -89,6 +191,7 @@ impl CodeSplitter {
         Self {
             chunk_size: ChunkSize::default(),
             language,
+            estimator: None,
         }
     }
 
@@ -189,6 +292,80 @@ impl CodeSplitter {
         Ok(self.chunk_node(root_node, code, 0, None))
     }
 
+    /// Splits the given code into chunks that fit the chunk size in estimated tokens
+    ///
+    /// Code is split by bytes, starting from the density of the code as a whole, and split again
+    /// with fewer bytes until every chunk fits the token budget. This keeps chunks within the
+    /// limits of an embedding model, regardless of how dense the language is. Chunks with fewer
+    /// tokens than the minimum are discarded.
+    ///
+    /// Without an estimator, this is the same as [`CodeSplitter::split`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the code fails to parse or the tokens cannot be estimated
+    pub async fn split_estimated(&self, code: &str) -> Result<Vec<String>> {
+        let Some(estimator) = &self.estimator else {
+            return self.split(code);
+        };
+
+        let max_tokens = self.max_bytes();
+        let tokens = estimator.estimate(&code).await?;
+        let mut max_bytes = (code.len() * max_tokens / tokens.max(1)).max(1);
+
+        let mut attempt = 0;
+        loop {
+            let splitter = CodeSplitter {
+                chunk_size: ChunkSize::Bytes(max_bytes),
+                language: self.language,
+                estimator: None,
+            };
+            let chunks = splitter.split(code)?;
+
+            let mut chunk_tokens = Vec::with_capacity(chunks.len());
+            for chunk in &chunks {
+                chunk_tokens.push(estimator.estimate(chunk).await?);
+            }
+            let largest = chunk_tokens.iter().copied().max().unwrap_or_default();
+
+            attempt += 1;
+            if largest <= max_tokens || max_bytes == 1 || attempt == MAX_TOKEN_ATTEMPTS {
+                return Ok(chunks
+                    .into_iter()
+                    .zip(chunk_tokens)
+                    .filter(|(_, tokens)| *tokens >= self.min_bytes())
+                    .map(|(chunk, _)| chunk)
+                    .collect());
+            }
+
+            max_bytes = (max_bytes * max_tokens / largest).clamp(1, max_bytes - 1);
+        }
+    }
+
+    /// Splits the given code into its top-level symbols
+    ///
+    /// Every function and type is a symbol of its own. Methods are split from the type they are
+    /// defined in, with the type recorded as their enclosing type. Code outside of any symbol,
+    /// like imports and top-level statements, is not included. The chunk size is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node cannot be found or fails to parse
+    pub fn split_symbols(&self, code: &str) -> Result<Vec<CodeSymbol>> {
+        let mut parser = Parser::new();
+        parser.set_language(&self.language.into())?;
+        let tree = parser.parse(code, None).context("No nodes found")?;
+        let root_node = tree.root_node();
+
+        if root_node.has_error() {
+            anyhow::bail!("Syntax error parsing code");
+        }
+
+        let mut symbols = Vec::new();
+        collect_symbols(root_node, code, None, &mut symbols);
+        Ok(symbols)
+    }
+
     /// Returns the maximum number of bytes allowed in a chunk.
     ///
     /// # Returns
@@ -215,10 +392,94 @@ impl CodeSplitter {
     }
 }
 
+/// Recursively collects the symbols in the children of a node
+fn collect_symbols(
+    node: Node,
+    source: &str,
+    enclosing_type: Option<&str>,
+    symbols: &mut Vec<CodeSymbol>,
+) {
+    for child in node.named_children(&mut node.walk()) {
+        let kind = child.kind();
+
+        if FUNCTION_KINDS.contains(&kind) || DECLARATION_KINDS.contains(&kind) {
+            symbols.push(code_symbol(child, source, enclosing_type));
+        } else if CONTAINER_KINDS.contains(&kind) {
+            let name = symbol_name(child, source);
+            let mut members = Vec::new();
+            collect_symbols(
+                child,
+                source,
+                name.as_deref().or(enclosing_type),
+                &mut members,
+            );
+
+            if members.is_empty() {
+                symbols.push(code_symbol(child, source, enclosing_type));
+            } else {
+                symbols.extend(members);
+            }
+        } else if TRANSPARENT_KINDS.contains(&kind) {
+            collect_symbols(child, source, enclosing_type, symbols);
+        }
+    }
+}
+
+fn code_symbol(node: Node, source: &str, enclosing_type: Option<&str>) -> CodeSymbol {
+    // Exports and decorators are part of the symbol
+    let mut outer = node;
+    while let Some(parent) = outer
+        .parent()
+        .filter(|parent| matches!(parent.kind(), "export_statement" | "decorated_definition"))
+    {
+        outer = parent;
+    }
+
+    // So are the comments and attributes directly preceding it
+    let mut start = outer.start_byte();
+    let mut sibling = outer.prev_named_sibling();
+    while let Some(previous) = sibling.filter(|previous| {
+        previous.kind().contains("comment") || previous.kind() == "attribute_item"
+    }) {
+        start = previous.start_byte();
+        sibling = previous.prev_named_sibling();
+    }
+
+    let signature_end = node.child_by_field_name("body").map_or_else(
+        || {
+            source[node.start_byte()..node.end_byte()]
+                .find('\n')
+                .map_or(node.end_byte(), |end| node.start_byte() + end)
+        },
+        |body| body.start_byte(),
+    );
+
+    CodeSymbol {
+        code: source[start..outer.end_byte()].to_string(),
+        offset: start,
+        name: symbol_name(node, source),
+        kind: node.kind().to_string(),
+        signature: source[node.start_byte()..signature_end].trim().to_string(),
+        doc: Some(source[start..outer.start_byte()].trim())
+            .filter(|doc| !doc.is_empty())
+            .map(str::to_string),
+        enclosing_type: enclosing_type.map(str::to_string),
+    }
+}
+
+fn symbol_name(node: Node, source: &str) -> Option<String> {
+    // Rust implementations are named after the type they implement
+    node.child_by_field_name("name")
+        .or_else(|| node.child_by_field_name("type"))
+        .and_then(|name| name.utf8_text(source.as_bytes()).ok())
+        .map(str::to_string)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use indoc::indoc;
+    use swiftide_core::tokenizer::ApproximateTokens;
 
     #[test]
     fn test_split_single_chunk() {
@@ -434,4 +695,110 @@ mod test {
 
         // assert there are no nodes smaller than 10
     }
+
+    #[tokio::test]
+    async fn test_split_estimated() {
+        let estimator = ApproximateTokens::default();
+        let splitter = CodeSplitter::builder()
+            .try_language(SupportedLanguages::Rust)
+            .unwrap()
+            .chunk_size(10)
+            .estimator(estimator)
+            .build()
+            .unwrap();
+
+        let text = indoc! {r#"
+            fn main() {
+                println!("Hello, World!");
+                println!("Goodbye, World!");
+            }
+        "#};
+        let chunks = splitter.split_estimated(text).await.unwrap();
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(estimator.estimate(chunk).await.unwrap() <= 10);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_estimated_without_estimator() {
+        let splitter = CodeSplitter::new(SupportedLanguages::Rust);
+
+        let chunks = splitter
+            .split_estimated("fn hello_world() {}")
+            .await
+            .unwrap();
+
+        assert_eq!(chunks, vec!["fn hello_world() {}"]);
+    }
+
+    #[test]
+    fn test_split_symbols() {
+        let splitter = CodeSplitter::new(SupportedLanguages::Rust);
+
+        let text = indoc! {r#"
+            use std::fmt;
+
+            /// A person
+            struct Person {
+                name: String,
+            }
+
+            impl Person {
+                pub fn greet(&self) -> String {
+                    format!("Hello, {}", self.name)
+                }
+            }
+
+            fn main() {}
+        "#};
+
+        let symbols = splitter.split_symbols(text).unwrap();
+
+        assert_eq!(symbols.len(), 3);
+
+        assert_eq!(symbols[0].name.as_deref(), Some("Person"));
+        assert_eq!(
+            symbols[0].code,
+            "/// A person\nstruct Person {\n    name: String,\n}"
+        );
+        assert_eq!(symbols[0].doc.as_deref(), Some("/// A person"));
+        assert_eq!(symbols[0].enclosing_type, None);
+
+        assert_eq!(symbols[1].name.as_deref(), Some("greet"));
+        assert_eq!(symbols[1].signature, "pub fn greet(&self) -> String");
+        assert_eq!(symbols[1].enclosing_type.as_deref(), Some("Person"));
+        assert_eq!(symbols[1].offset, text.find("pub fn").unwrap());
+
+        assert_eq!(symbols[2].name.as_deref(), Some("main"));
+        assert_eq!(symbols[2].code, "fn main() {}");
+    }
+
+    #[test]
+    fn test_split_symbols_python() {
+        let splitter = CodeSplitter::new(SupportedLanguages::Python);
+
+        let text = indoc! {r#"
+            import os
+
+            class Person:
+                def __init__(self, name):
+                    self.name = name
+
+                @property
+                def greeting(self):
+                    return "Hello " + self.name
+        "#};
+
+        let symbols = splitter.split_symbols(text).unwrap();
+
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].signature, "def __init__(self, name):");
+        assert_eq!(symbols[1].name.as_deref(), Some("greeting"));
+        assert!(symbols[1].code.starts_with("@property"));
+        assert!(symbols
+            .iter()
+            .all(|symbol| symbol.enclosing_type.as_deref() == Some("Person")));
+    }
 }
@@ -10,6 +10,12 @@
 //! - Python
 //! - Ruby
 //! - Javascript
+//! - Java
+//! - Go
+//! - Kotlin
+//! - Swift
+//! - C#
+//! - PHP
 
 #[allow(unused_imports)]
 pub use std::str::FromStr as _;
@@ -49,6 +55,15 @@ pub enum SupportedLanguages {
     Java,
     #[serde(alias = "go")]
     Go,
+    #[serde(alias = "kotlin")]
+    Kotlin,
+    #[serde(alias = "swift")]
+    Swift,
+    #[serde(alias = "csharp", alias = "c#")]
+    #[strum(to_string = "CSharp", serialize = "c#")]
+    CSharp,
+    #[serde(alias = "php")]
+    Php,
 }
 
 /// Static array of file extensions for Rust files.
@@ -72,6 +87,18 @@ static JAVA_EXTENSIONS: &[&str] = &["java"];
 /// Static array of file extensions for Go files.
 static GO_EXTENSIONS: &[&str] = &["go"];
 
+/// Static array of file extensions for Kotlin files.
+static KOTLIN_EXTENSIONS: &[&str] = &["kt", "kts"];
+
+/// Static array of file extensions for Swift files.
+static SWIFT_EXTENSIONS: &[&str] = &["swift"];
+
+/// Static array of file extensions for C# files.
+static CSHARP_EXTENSIONS: &[&str] = &["cs"];
+
+/// Static array of file extensions for PHP files.
+static PHP_EXTENSIONS: &[&str] = &["php"];
+
 impl SupportedLanguages {
     /// Returns the file extensions associated with the supported language.
     ///
@@ -86,6 +113,10 @@ impl SupportedLanguages {
             SupportedLanguages::Javascript => JAVASCRIPT_EXTENSIONS,
             SupportedLanguages::Java => JAVA_EXTENSIONS,
             SupportedLanguages::Go => GO_EXTENSIONS,
+            SupportedLanguages::Kotlin => KOTLIN_EXTENSIONS,
+            SupportedLanguages::Swift => SWIFT_EXTENSIONS,
+            SupportedLanguages::CSharp => CSHARP_EXTENSIONS,
+            SupportedLanguages::Php => PHP_EXTENSIONS,
         }
     }
 }
@@ -110,6 +141,10 @@ impl From<SupportedLanguages> for tree_sitter::Language {
             SupportedLanguages::Ruby => tree_sitter_ruby::LANGUAGE,
             SupportedLanguages::Java => tree_sitter_java::LANGUAGE,
             SupportedLanguages::Go => tree_sitter_go::LANGUAGE,
+            SupportedLanguages::Kotlin => tree_sitter_kotlin_ng::LANGUAGE,
+            SupportedLanguages::Swift => tree_sitter_swift::LANGUAGE,
+            SupportedLanguages::CSharp => tree_sitter_c_sharp::LANGUAGE,
+            SupportedLanguages::Php => tree_sitter_php::LANGUAGE_PHP,
         }
         .into()
     }
@@ -135,6 +170,10 @@ mod test {
             SupportedLanguages::from_str("java"),
             Ok(SupportedLanguages::Java)
         );
+        assert_eq!(
+            SupportedLanguages::from_str("c#"),
+            Ok(SupportedLanguages::CSharp)
+        );
     }
 
     /// Tests the case-insensitive string conversion for `SupportedLanguages` with different casing.
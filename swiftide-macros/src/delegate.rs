@@ -0,0 +1,239 @@
+//! Derives `Persist`, `Retrieve` and `NodeCache` for wrappers around existing stores, by
+//! delegating every method to a field of the wrapper.
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{parse_quote, Data, DeriveInput, Error, Generics, Index, Result, Type};
+
+/// The field that is delegated to
+struct Delegate {
+    accessor: TokenStream,
+    ty: Type,
+}
+
+/// Returns the field marked with `#[delegate]`, or the only field of the struct
+fn delegate_field(input: &DeriveInput) -> Result<Delegate> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "Delegating is only supported on structs",
+        ));
+    };
+
+    let fields = data.fields.iter().enumerate().collect::<Vec<_>>();
+    let marked = fields
+        .iter()
+        .filter(|(_, field)| {
+            field
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("delegate"))
+        })
+        .collect::<Vec<_>>();
+
+    let (index, field) = match marked.as_slice() {
+        [marked] => **marked,
+        [] if fields.len() == 1 => fields[0],
+        [] => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Mark the field to delegate to with `#[delegate]`",
+            ))
+        }
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Only one field can be marked with `#[delegate]`",
+            ))
+        }
+    };
+
+    let accessor = field.ident.as_ref().map_or_else(
+        || Index::from(index).to_token_stream(),
+        ToTokens::to_token_stream,
+    );
+
+    Ok(Delegate {
+        accessor,
+        ty: field.ty.clone(),
+    })
+}
+
+/// Adds the bound on the delegated field to the generics of the wrapper
+///
+/// The traits require the wrapper to be `Clone`, which a derived `Clone` only is if the delegated
+/// field is, hence the wrapper itself is bound as well.
+fn with_bounds(generics: &Generics, bounds: [syn::WherePredicate; 2]) -> Generics {
+    let mut generics = generics.clone();
+    generics.make_where_clause().predicates.extend(bounds);
+    generics
+}
+
+pub(crate) fn persist_derive_impl(input: &DeriveInput) -> Result<TokenStream> {
+    let Delegate { accessor, ty } = delegate_field(input)?;
+    let ident = &input.ident;
+    let generics = with_bounds(
+        &input.generics,
+        [
+            parse_quote! { #ty: ::swiftide::traits::Persist },
+            parse_quote! { Self: Clone + ::std::fmt::Debug },
+        ],
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        #[::swiftide::reexports::async_trait::async_trait]
+        impl #impl_generics ::swiftide::traits::Persist for #ident #ty_generics #where_clause {
+            async fn setup(&self) -> ::std::result::Result<(), ::swiftide::errors::PersistError> {
+                self.#accessor.setup().await
+            }
+
+            async fn store(&self, node: ::swiftide::indexing::Node) -> ::std::result::Result<::swiftide::indexing::Node, ::swiftide::errors::PersistError> {
+                self.#accessor.store(node).await
+            }
+
+            async fn batch_store(&self, nodes: Vec<::swiftide::indexing::Node>) -> ::swiftide::indexing::IndexingStream {
+                self.#accessor.batch_store(nodes).await
+            }
+
+            fn batch_size(&self) -> Option<usize> {
+                self.#accessor.batch_size()
+            }
+
+            fn max_batch_bytes(&self) -> Option<usize> {
+                self.#accessor.max_batch_bytes()
+            }
+
+            async fn delete(&self, ids: Vec<::swiftide::reexports::uuid::Uuid>) -> ::std::result::Result<(), ::swiftide::errors::PersistError> {
+                self.#accessor.delete(ids).await
+            }
+
+            async fn delete_by_metadata(&self, filter: ::swiftide::indexing::Metadata) -> ::std::result::Result<(), ::swiftide::errors::PersistError> {
+                self.#accessor.delete_by_metadata(filter).await
+            }
+        }
+    })
+}
+
+pub(crate) fn retrieve_derive_impl(input: &DeriveInput) -> Result<TokenStream> {
+    let Delegate { accessor, ty } = delegate_field(input)?;
+    let ident = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    // The wrapper retrieves with every search strategy the delegated field supports
+    let mut generics = with_bounds(
+        &input.generics,
+        [
+            parse_quote! { #ty: ::swiftide::traits::Retrieve<Strategy> },
+            parse_quote! { Self: Clone },
+        ],
+    );
+    generics
+        .params
+        .push(parse_quote! { Strategy: ::swiftide::traits::SearchStrategy });
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        #[::swiftide::reexports::async_trait::async_trait]
+        impl #impl_generics ::swiftide::traits::Retrieve<Strategy> for #ident #ty_generics #where_clause {
+            async fn retrieve(
+                &self,
+                search_strategy: &Strategy,
+                query: ::swiftide::query::Query<::swiftide::query::states::Pending>,
+            ) -> ::std::result::Result<::swiftide::query::Query<::swiftide::query::states::Retrieved>, ::swiftide::errors::RetrieveError> {
+                self.#accessor.retrieve(search_strategy, query).await
+            }
+        }
+    })
+}
+
+pub(crate) fn node_cache_derive_impl(input: &DeriveInput) -> Result<TokenStream> {
+    let Delegate { accessor, ty } = delegate_field(input)?;
+    let ident = &input.ident;
+    let generics = with_bounds(
+        &input.generics,
+        [
+            parse_quote! { #ty: ::swiftide::traits::NodeCache },
+            parse_quote! { Self: Clone + ::std::fmt::Debug },
+        ],
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        #[::swiftide::reexports::async_trait::async_trait]
+        impl #impl_generics ::swiftide::traits::NodeCache for #ident #ty_generics #where_clause {
+            async fn get(&self, node: &::swiftide::indexing::Node) -> bool {
+                self.#accessor.get(node).await
+            }
+
+            async fn set(&self, node: &::swiftide::indexing::Node) {
+                self.#accessor.set(node).await;
+            }
+
+            async fn clear(&self) -> ::std::result::Result<(), ::swiftide::errors::NodeCacheError> {
+                self.#accessor.clear().await
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delegates_to_only_field() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(Debug, Clone)]
+            pub struct Metered(Qdrant);
+        };
+
+        let output = persist_derive_impl(&input)
+            .unwrap()
+            .to_string()
+            .replace(' ', "");
+
+        assert!(output.contains(
+            "impl::swiftide::traits::PersistforMeteredwhereQdrant:::swiftide::traits::Persist"
+        ));
+        assert!(output.contains("self.0.store(node).await"));
+        assert!(output.contains("self.0.batch_size()"));
+        assert!(output.contains("self.0.max_batch_bytes()"));
+    }
+
+    #[test]
+    fn test_delegates_to_marked_field() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(Debug, Clone)]
+            pub struct Tenant<S> {
+                tenant: String,
+                #[delegate]
+                store: S,
+            }
+        };
+
+        let output = retrieve_derive_impl(&input)
+            .unwrap()
+            .to_string()
+            .replace(' ', "");
+
+        assert!(output.contains("impl<S,Strategy:::swiftide::traits::SearchStrategy>::swiftide::traits::Retrieve<Strategy>forTenant<S>whereS:::swiftide::traits::Retrieve<Strategy>"));
+        assert!(output.contains("self.store.retrieve(search_strategy,query).await"));
+    }
+
+    #[test]
+    fn test_requires_marked_field() {
+        let input: DeriveInput = parse_quote! {
+            pub struct Tenant {
+                tenant: String,
+                store: Redis,
+            }
+        };
+
+        let err = node_cache_derive_impl(&input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Mark the field to delegate to with `#[delegate]`"
+        );
+    }
+}
@@ -8,6 +8,8 @@ use syn::{Fields, Ident, ItemStruct};
 struct TransformerArgs {
     metadata_field_name: Option<String>,
     default_prompt_file: Option<String>,
+    /// Also implements `BatchableTransformer` by transforming the nodes in a batch concurrently
+    batchable: bool,
 
     derive: DeriveOptions,
 }
@@ -50,12 +52,19 @@ pub(crate) fn indexing_transformer_impl(args: TokenStream, input: ItemStruct) ->
         None => quote! {},
     };
 
+    // The default prompt can be overridden by registering a template with the same file name
     let default_prompt_fn = match &args.default_prompt_file {
-        Some(file) => quote! {
-            fn default_prompt() -> hidden::Template {
-                include_str!(#file).into()
+        Some(file) => {
+            let name = std::path::Path::new(file)
+                .file_name()
+                .map_or_else(|| file.clone(), |name| name.to_string_lossy().to_string());
+
+            quote! {
+                fn default_prompt() -> hidden::Template {
+                    hidden::Template::named(#name, include_str!(#file))
+                }
             }
-        },
+        }
         None => quote! {},
     };
 
@@ -83,6 +92,23 @@ pub(crate) fn indexing_transformer_impl(args: TokenStream, input: ItemStruct) ->
         }
     };
 
+    let batchable = args.batchable.then(|| batchable_impl(struct_name));
+    let batchable_imports = args.batchable.then(|| {
+        quote! {
+            pub use async_trait::async_trait;
+            pub use futures_util::future::{join_all, try_join_all};
+            pub use swiftide_core::{
+                indexing::{IndexingStream, Node}, BatchableTransformer, WithBatchIndexingDefaults
+            };
+        }
+    });
+    let batch_size_field = args.batchable.then(|| {
+        quote! {
+            #[builder(default)]
+            batch_size: Option<usize>,
+        }
+    });
+
     quote! {
         mod hidden {
             pub use std::sync::Arc;
@@ -94,6 +120,7 @@ pub(crate) fn indexing_transformer_impl(args: TokenStream, input: ItemStruct) ->
                 template::Template,
                 SimplePrompt, Transformer, WithIndexingDefaults
             };
+            #batchable_imports
         }
 
         #metadata_field_name
@@ -112,6 +139,7 @@ pub(crate) fn indexing_transformer_impl(args: TokenStream, input: ItemStruct) ->
             concurrency: Option<usize>,
             #[builder(private, default)]
             indexing_defaults: Option<hidden::IndexingDefaults>,
+            #batch_size_field
         }
 
         #default_impl
@@ -149,7 +177,7 @@ pub(crate) fn indexing_transformer_impl(args: TokenStream, input: ItemStruct) ->
             async fn prompt(&self, prompt: hidden::Prompt) -> hidden::Result<String> {
 
                 if let Some(client) = &self.client {
-                    return client.prompt(prompt).await
+                    return Ok(client.prompt(prompt).await?)
                 };
 
                 let Some(defaults) = &self.indexing_defaults.as_ref() else {
@@ -159,7 +187,7 @@ pub(crate) fn indexing_transformer_impl(args: TokenStream, input: ItemStruct) ->
                 let Some(client) = defaults.simple_prompt() else {
                     anyhow::bail!("No client provided")
                 };
-                client.prompt(prompt).await
+                Ok(client.prompt(prompt).await?)
             }
         }
 
@@ -176,10 +204,66 @@ pub(crate) fn indexing_transformer_impl(args: TokenStream, input: ItemStruct) ->
             }
         }
 
+        #batchable
+
         #default_prompt_fn
     }
 }
 
+/// Implements `BatchableTransformer` on top of the `Transformer` implementation of the struct
+fn batchable_impl(struct_name: &Ident) -> TokenStream {
+    quote! {
+        impl #struct_name {
+            /// Sets the batch size for the transformer, defaults to the batch size of the
+            /// pipeline
+            #[must_use]
+            pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+                self.batch_size = Some(batch_size);
+                self
+            }
+
+            /// Prompts all prompts concurrently, returning the responses in the same order
+            ///
+            /// # Errors
+            ///
+            /// Gives an error if no (default) client is provided, or if any of the prompts fail
+            #[allow(dead_code)]
+            async fn prompt_batch(&self, prompts: Vec<hidden::Prompt>) -> hidden::Result<Vec<String>> {
+                hidden::try_join_all(prompts.into_iter().map(|prompt| self.prompt(prompt))).await
+            }
+        }
+
+        #[hidden::async_trait]
+        impl hidden::BatchableTransformer for #struct_name {
+            /// Transforms the nodes in the batch concurrently
+            async fn batch_transform(&self, nodes: Vec<hidden::Node>) -> hidden::IndexingStream {
+                let nodes = hidden::join_all(
+                    nodes
+                        .into_iter()
+                        .map(|node| <Self as hidden::Transformer>::transform_node(self, node)),
+                )
+                .await;
+
+                hidden::IndexingStream::iter(nodes)
+            }
+
+            fn concurrency(&self) -> Option<usize> {
+                self.concurrency
+            }
+
+            fn batch_size(&self) -> Option<usize> {
+                self.batch_size
+            }
+        }
+
+        impl hidden::WithBatchIndexingDefaults for #struct_name {
+            fn with_indexing_defaults(&mut self, defaults: hidden::IndexingDefaults) {
+                self.indexing_defaults = Some(defaults);
+            }
+        }
+    }
+}
+
 fn parse_args(args: TokenStream) -> Result<TransformerArgs, Error> {
     let attr_args = NestedMeta::parse_meta_list(args)?;
 
@@ -283,7 +367,7 @@ mod tests {
                 /// Gives an error if no (default) client is provided
                 async fn prompt(&self, prompt: hidden::Prompt) -> hidden::Result<String> {
                     if let Some(client) = &self.client {
-                        return client.prompt(prompt).await
+                        return Ok(client.prompt(prompt).await?)
                     };
 
                     let Some(defaults) = &self.indexing_defaults.as_ref() else {
@@ -293,7 +377,7 @@ mod tests {
                     let Some(client) = defaults.simple_prompt() else {
                         anyhow::bail!("No client provided")
                     };
-                    client.prompt(prompt).await
+                    Ok(client.prompt(prompt).await?)
                 }
             }
 
@@ -313,4 +397,21 @@ mod tests {
 
         assert_eq!(output.to_string(), expected_output.to_string());
     }
+
+    #[test]
+    fn test_batchable() {
+        let input: ItemStruct = parse_quote! {
+            pub struct TestStruct {}
+        };
+
+        let args: TokenStream = quote!(batchable);
+        let output = indexing_transformer_impl(args, input)
+            .to_string()
+            .replace(' ', "");
+
+        assert!(output.contains("batch_size:Option<usize>"));
+        assert!(output.contains("implhidden::BatchableTransformerforTestStruct"));
+        assert!(output.contains("implhidden::WithBatchIndexingDefaultsforTestStruct"));
+        assert!(output.contains("pubfnwith_batch_size(mutself,batch_size:usize)->Self"));
+    }
 }
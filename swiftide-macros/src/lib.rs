@@ -2,15 +2,38 @@
 //! for indexing transformers
 use proc_macro::TokenStream;
 
+mod delegate;
 mod indexing_transformer;
 #[cfg(test)]
 mod test_utils;
 mod tool;
+use delegate::{node_cache_derive_impl, persist_derive_impl, retrieve_derive_impl};
 use indexing_transformer::indexing_transformer_impl;
 use syn::{parse_macro_input, DeriveInput, ItemFn, ItemStruct};
 use tool::{tool_derive_impl, tool_impl};
 
 /// Generates boilerplate for an indexing transformer.
+///
+/// With `batchable`, the transformer also implements `BatchableTransformer` by transforming the
+/// nodes in a batch concurrently, and can be used with `then_in_batch`. It gets a
+/// `with_batch_size` setter and a `prompt_batch` helper to prompt multiple prompts at once.
+///
+/// # Example
+/// ```ignore
+/// #[indexing_transformer(metadata_field_name = "Summary", batchable)]
+/// pub struct MetadataSummary {}
+///
+/// #[async_trait]
+/// impl Transformer for MetadataSummary {
+///     async fn transform_node(&self, mut node: Node) -> Result<Node> {
+///         let summary = self.prompt(format!("Summarize: {}", node.chunk).into()).await?;
+///         node.metadata.insert(NAME, summary);
+///         Ok(node)
+///     }
+/// }
+///
+/// pipeline.then_in_batch(MetadataSummary::default().with_batch_size(10))
+/// ```
 #[proc_macro_attribute]
 pub fn indexing_transformer(args: TokenStream, input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as ItemStruct);
@@ -37,6 +60,39 @@ pub fn indexing_transformer(args: TokenStream, input: TokenStream) -> TokenStrea
 ///
 /// ```
 ///
+/// Parameters can be of any type that implements `Deserialize` and `JsonSchema`, i.e. numbers,
+/// booleans, vectors, structs and enums. The json schema of the parameter is generated from its
+/// type. Strings are the default, and `Option` parameters are not required.
+///
+/// ```ignore
+/// #[tool(
+///     description = "Reads lines from a file",
+///     param(name = "path", description = "The path of the file"),
+///     param(name = "lines", description = "The lines to read")
+/// )]
+/// pub async fn read_lines(context: &dyn AgentContext, path: &str, lines: Option<Vec<usize>>) -> Result<ToolOutput,
+/// ToolError> {
+///    Ok("hello".into())
+/// }
+/// ```
+///
+/// Parameters can have a `default`, an explicit `required` flag, and are validated with `min`,
+/// `max`, `pattern` (a regex) and `one_of` (repeated for every allowed value). Invalid arguments
+/// are returned to the llm as a failed tool call, without invoking the function.
+///
+/// ```ignore
+/// #[tool(
+///     description = "Searches code",
+///     param(name = "query", description = "The query", pattern = "^\\w+$"),
+///     param(name = "language", description = "The language", one_of = "rust", one_of = "python"),
+///     param(name = "limit", description = "The maximum results", default = 10, min = 1, max = 50)
+/// )]
+/// pub async fn search_code(context: &dyn AgentContext, query: &str, language: &str, limit: usize) -> Result<ToolOutput,
+/// ToolError> {
+///    Ok("hello".into())
+/// }
+/// ```
+///
 pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as ItemFn);
     tool_impl(&args.into(), &input).into()
@@ -45,6 +101,10 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
 /// Derive tool on a struct. The macro expects a snake case method on the struct that takes the
 /// equally named params as `&str` arguments.
 ///
+/// Parameters of another type can be declared with `rust_type`, i.e.
+/// `param(name = "ids", description = "The ids", rust_type = "Vec<u64>")`, and are passed by
+/// reference.
+///
 /// Useful if your structs have internal state and you want to use it in your tool.
 ///
 /// # Example
@@ -72,3 +132,58 @@ pub fn derive_tool(input: TokenStream) -> TokenStream {
         Err(err) => err.into_compile_error().into(),
     }
 }
+
+/// Derives `Persist` for a wrapper around a storage, by delegating every method to the wrapped
+/// storage.
+///
+/// Delegates to the only field of the struct, or to the field marked with `#[delegate]`. Together
+/// with the `Retrieve` and `NodeCache` derives, it removes the boilerplate of wrappers that add
+/// metrics, prefixes or multi-tenancy to an existing store. Methods that need to behave
+/// differently can be implemented by hand instead of derived.
+///
+/// # Example
+/// ```ignore
+/// #[derive(Debug, Clone, Persist, Retrieve, NodeCache)]
+/// pub struct Tenant<S> {
+///   tenant: String,
+///   #[delegate]
+///   store: S,
+/// }
+/// ```
+#[proc_macro_derive(Persist, attributes(delegate))]
+pub fn derive_persist(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match persist_derive_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+/// Derives `Retrieve` for a wrapper around a storage, for every search strategy the wrapped
+/// storage supports.
+///
+/// See the [`Persist`](derive@Persist) derive for how the wrapped storage is selected.
+#[proc_macro_derive(Retrieve, attributes(delegate))]
+pub fn derive_retrieve(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match retrieve_derive_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+/// Derives `NodeCache` for a wrapper around a cache, by delegating every method to the wrapped
+/// cache.
+///
+/// See the [`Persist`](derive@Persist) derive for how the wrapped cache is selected.
+#[proc_macro_derive(NodeCache, attributes(delegate))]
+pub fn derive_node_cache(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match node_cache_derive_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
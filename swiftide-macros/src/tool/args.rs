@@ -1,6 +1,6 @@
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens as _};
-use syn::{parse::Result, Error, FnArg, Ident, ItemFn, PatType};
+use syn::{parse::Result, parse_quote, Error, FnArg, Ident, ItemFn, PatType, Type};
 
 pub(crate) fn args_struct_name(input: &ItemFn) -> Ident {
     let struct_name_str = input
@@ -29,9 +29,10 @@ pub(crate) fn build_tool_args(input: &ItemFn) -> Result<TokenStream> {
     let mut struct_fields = Vec::new();
 
     for arg in args.iter().skip(1) {
-        if let syn::FnArg::Typed(PatType { pat, .. }) = arg {
+        if let syn::FnArg::Typed(PatType { pat, ty, .. }) = arg {
             if let syn::Pat::Ident(ident) = &**pat {
-                struct_fields.push(quote! { pub #ident: String });
+                let ty = owned_arg_type(ty);
+                struct_fields.push(quote! { pub #ident: #ty });
             }
         }
     }
@@ -50,6 +51,35 @@ pub(crate) fn build_tool_args(input: &ItemFn) -> Result<TokenStream> {
     })
 }
 
+/// The owned type an argument is deserialized into, i.e. `String` for `&str`
+pub(crate) fn owned_arg_type(ty: &Type) -> Type {
+    let Type::Reference(reference) = ty else {
+        return ty.clone();
+    };
+
+    match &*reference.elem {
+        Type::Path(path) if path.path.is_ident("str") => parse_quote! { String },
+        Type::Slice(slice) => {
+            let elem = &slice.elem;
+            parse_quote! { Vec<#elem> }
+        }
+        elem => elem.clone(),
+    }
+}
+
+/// Strings are the default parameter type and do not need a schema
+pub(crate) fn is_string_type(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("String"))
+}
+
+/// Optional parameters are not required
+pub(crate) fn is_option_type(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::Path(path) if path.path.segments.last().is_some_and(|segment| segment.ident == "Option")
+    )
+}
+
 fn validate_first_argument_is_agent_context(input_fn: &ItemFn) -> Result<()> {
     let expected_first_arg = quote! { &dyn AgentContext };
     let error_msg = "The first argument must be `&dyn AgentContext`";
@@ -162,6 +192,46 @@ mod tests {
         assert_ts_eq!(&output, &expected);
     }
 
+    #[test]
+    fn test_typed_arguments() {
+        let input: ItemFn = parse_quote! {
+            pub async fn search_code(context: &dyn AgentContext, limit: usize, paths: &[String], filter: &Filter) -> Result<ToolOutput> {
+                return Ok("hello".into())
+            }
+        };
+
+        let output = build_tool_args(&input).unwrap();
+
+        let expected = quote! {
+            #[derive(::swiftide::reexports::serde::Serialize, ::swiftide::reexports::serde::Deserialize)]
+            struct SearchCodeArgs {
+                pub limit: usize,
+                pub paths: Vec<String>,
+                pub filter: Filter
+            }
+        };
+
+        assert_ts_eq!(&output, &expected);
+    }
+
+    #[test]
+    fn test_owned_arg_type() {
+        let cases: Vec<(Type, Type)> = vec![
+            (parse_quote! { &str }, parse_quote! { String }),
+            (parse_quote! { &String }, parse_quote! { String }),
+            (parse_quote! { &[u32] }, parse_quote! { Vec<u32> }),
+            (parse_quote! { Option<bool> }, parse_quote! { Option<bool> }),
+        ];
+
+        for (ty, expected) in cases {
+            assert_eq!(
+                owned_arg_type(&ty).to_token_stream().to_string(),
+                expected.to_token_stream().to_string()
+            );
+        }
+        assert!(is_option_type(&parse_quote! { Option<bool> }));
+        assert!(!is_string_type(&parse_quote! { Vec<String> }));
+    }
+
     // TODO: Handle no arguments
-    // TODO: Should it only allow &str as arg types?
 }
@@ -22,7 +22,38 @@ struct ToolArgs {
 struct ParamOptions {
     name: String,
     description: String,
-    // TODO: I.e. openai also supports enums instead of strings as arg type
+    /// The type of the parameter, i.e. `rust_type = "Vec<u32>"`, defaults to `String`
+    ///
+    /// Any type implementing `Deserialize` and `JsonSchema` can be used. For tool functions, the
+    /// type is taken from the signature.
+    rust_type: Option<syn::Type>,
+    /// Overrides if the argument is required, by default `Option` parameters and parameters with
+    /// a default are not
+    required: Option<bool>,
+    /// The value used if the argument is missing, i.e. `default = 10`
+    default: Option<syn::Lit>,
+    /// The minimum of a number argument
+    min: Option<syn::Lit>,
+    /// The maximum of a number argument
+    max: Option<syn::Lit>,
+    /// A regex a string argument must match
+    pattern: Option<String>,
+    /// An allowed value of a string argument, can be repeated
+    #[darling(multiple)]
+    one_of: Vec<String>,
+}
+
+impl ParamOptions {
+    /// Arguments with a default, an explicit required flag or constraints are prepared and
+    /// validated before the tool is invoked
+    fn validates(&self) -> bool {
+        self.required.is_some()
+            || self.default.is_some()
+            || self.min.is_some()
+            || self.max.is_some()
+            || self.pattern.is_some()
+            || !self.one_of.is_empty()
+    }
 }
 
 #[derive(Debug)]
@@ -76,12 +107,29 @@ impl serde::Serialize for ParamOptions {
 
 #[allow(clippy::too_many_lines)]
 pub(crate) fn tool_impl(input_args: &TokenStream, input: &ItemFn) -> TokenStream {
-    let args = match parse_args(input_args.clone()) {
+    let mut args = match parse_args(input_args.clone()) {
         Ok(args) => args,
         Err(e) => return e.write_errors(),
     };
     let fn_name = &input.sig.ident;
     let fn_args = &input.sig.inputs;
+
+    // The types of the parameters are taken from the function signature
+    for arg in fn_args.iter().skip(1) {
+        if let FnArg::Typed(PatType { pat, ty, .. }) = arg {
+            if let Pat::Ident(ident) = &**pat {
+                if let Some(param) = args
+                    .param
+                    .iter_mut()
+                    .find(|param| ident.ident == param.name)
+                {
+                    param
+                        .rust_type
+                        .get_or_insert_with(|| args::owned_arg_type(ty));
+                }
+            }
+        }
+    }
     let tool_name = fn_name.to_string();
 
     let tool_args = args::build_tool_args(input).unwrap_or_else(syn::Error::into_compile_error);
@@ -90,7 +138,11 @@ pub(crate) fn tool_impl(input_args: &TokenStream, input: &ItemFn) -> TokenStream
 
     let wrapped_fn = wrapped::wrap_tool_fn(input);
 
-    let tool_spec = tool_spec::tool_spec(&tool_name, &args);
+    let tool_spec = match tool_spec::tool_spec(&tool_name, &args) {
+        Ok(tool_spec) => tool_spec,
+        Err(err) => return err.into_compile_error(),
+    };
+    let prepare_args = prepare_args(&args);
 
     let mut found_spec_arg_names = args
         .param
@@ -165,6 +217,8 @@ pub(crate) fn tool_impl(input_args: &TokenStream, input: &ItemFn) -> TokenStream
         }
     } else {
         quote! {
+            #prepare_args
+
             let Some(args) = raw_args
             else { return Err(::swiftide::chat_completion::errors::ToolError::MissingArguments(format!("No arguments provided for {}", #tool_name))) };
 
@@ -221,7 +275,11 @@ pub(crate) fn tool_derive_impl(input: &DeriveInput) -> syn::Result<TokenStream>
         .iter()
         .map(|p| {
             let field_name = syn::Ident::new(&p.name, struct_ident.span());
-            quote! { pub #field_name: String }
+            let ty = p
+                .rust_type
+                .clone()
+                .unwrap_or_else(|| syn::parse_quote! { String });
+            quote! { pub #field_name: #ty }
         })
         .collect::<Vec<_>>();
 
@@ -246,6 +304,8 @@ pub(crate) fn tool_derive_impl(input: &DeriveInput) -> syn::Result<TokenStream>
         })
         .collect::<Vec<_>>();
 
+    let prepare_args = prepare_args(&parsed.tool);
+
     // Build the trait impl
     let expected_fn_name = struct_ident.to_string().to_case(Case::Snake);
     let expected_fn_ident = syn::Ident::new(&expected_fn_name, struct_ident.span());
@@ -253,6 +313,8 @@ pub(crate) fn tool_derive_impl(input: &DeriveInput) -> syn::Result<TokenStream>
         quote! { return self.#expected_fn_ident(agent_context).await }
     } else {
         quote! {
+            #prepare_args
+
             let Some(args) = raw_args
             else { return Err(::swiftide::chat_completion::errors::ToolError::MissingArguments(format!("No arguments provided for {}", #expected_fn_name))) };
 
@@ -261,7 +323,7 @@ pub(crate) fn tool_derive_impl(input: &DeriveInput) -> syn::Result<TokenStream>
         }
     };
 
-    let tool_spec = tool_spec::tool_spec(&expected_fn_name, &parsed.tool);
+    let tool_spec = tool_spec::tool_spec(&expected_fn_name, &parsed.tool)?;
 
     let struct_lifetimes = input
         .generics
@@ -300,6 +362,25 @@ pub(crate) fn tool_derive_impl(input: &DeriveInput) -> syn::Result<TokenStream>
     })
 }
 
+/// Applies defaults and validates the arguments with the tool spec, invalid arguments are returned
+/// to the llm as a failed tool call
+fn prepare_args(args: &ToolArgs) -> TokenStream {
+    if !args.param.iter().any(ParamOptions::validates) {
+        return quote! {};
+    }
+
+    quote! {
+        let raw_args = match self.tool_spec().prepare_args(raw_args) {
+            Ok(args) => args,
+            Err(::swiftide::chat_completion::errors::ToolError::InvalidArguments(message)) => {
+                return Ok(::swiftide::chat_completion::ToolOutput::Fail(message))
+            }
+            Err(err) => return Err(err),
+        };
+        let raw_args = raw_args.as_deref();
+    }
+}
+
 fn parse_args(args: TokenStream) -> Result<ToolArgs, Error> {
     let attr_args = NestedMeta::parse_meta_list(args)?;
 
@@ -399,6 +480,85 @@ mod tests {
         insta::assert_snapshot!(crate::test_utils::pretty_macro_output(&output));
     }
 
+    #[test]
+    fn test_typed_args() {
+        let args = quote! {
+            description = "Hello world tool",
+            param(
+                name = "limit",
+                description = "my param description"
+            ),
+            param(
+                name = "filter",
+                description = "my param description"
+            )
+        };
+        let input: ItemFn = parse_quote! {
+            pub async fn search_code(context: &dyn AgentContext, limit: Option<usize>, filter: &Filter) -> Result<ToolOutput> {
+                return Ok("hello".into())
+            }
+        };
+
+        let output = tool_impl(&args, &input).to_string().replace(' ', "");
+
+        assert!(output.contains("publimit:Option<usize>,pubfilter:Filter"));
+        assert!(output.contains("self.search_code(agent_context,args.limit,&args.filter)"));
+        assert!(output.contains(".schema_for::<Option<usize>>().required(false)"));
+        assert!(output.contains(".schema_for::<Filter>().required(true)"));
+    }
+
+    #[test]
+    fn test_derive_with_typed_args() {
+        let input: DeriveInput = parse_quote! {
+            #[tool(description="Hello derive", param(name="ids", description="test param", rust_type = "Vec<u64>"))]
+            pub struct HelloDerive {
+                my_thing: String
+            }
+        };
+
+        let output = tool_derive_impl(&input)
+            .unwrap()
+            .to_string()
+            .replace(' ', "");
+
+        assert!(output.contains("pubids:Vec<u64>"));
+        assert!(output.contains(".schema_for::<Vec<u64>>().required(true)"));
+    }
+
+    #[test]
+    fn test_validated_args() {
+        let args = quote! {
+            description = "Hello world tool",
+            param(
+                name = "query",
+                description = "my param description",
+                pattern = "^[a-z]+$",
+                one_of = "hello",
+                one_of = "world"
+            ),
+            param(
+                name = "limit",
+                description = "my param description",
+                default = 10,
+                min = 1,
+                max = 50.5
+            )
+        };
+        let input: ItemFn = parse_quote! {
+            pub async fn search_code(context: &dyn AgentContext, query: &str, limit: usize) -> Result<ToolOutput> {
+                return Ok("hello".into())
+            }
+        };
+
+        let output = tool_impl(&args, &input).to_string().replace(' ', "");
+
+        assert!(output.contains(r#".pattern("^[a-z]+$").one_of(["hello","world"])"#));
+        assert!(output.contains(
+            ".schema_for::<usize>().required(false).default_value(::swiftide::reexports::serde_json::json!(10)).minimum(1.0).maximum(50.5)"
+        ));
+        assert!(output.contains("letraw_args=matchself.tool_spec().prepare_args(raw_args)"));
+    }
+
     #[test]
     fn test_snapshot_derive_with_lifetime() {
         let input: DeriveInput = parse_quote! {
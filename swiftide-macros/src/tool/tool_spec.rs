@@ -1,41 +1,101 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Literal, TokenStream};
 use quote::quote;
+use syn::{Error, Lit, Result};
 
-use super::{Description, ToolArgs};
+use super::{
+    args::{is_option_type, is_string_type},
+    Description, ParamOptions, ToolArgs,
+};
 
-pub fn tool_spec(tool_name: &str, args: &ToolArgs) -> TokenStream {
+pub fn tool_spec(tool_name: &str, args: &ToolArgs) -> Result<TokenStream> {
     let description = match &args.description {
         Description::Literal(description) => quote! { #description },
         Description::Path(path) => quote! { #path },
     };
 
     if args.param.is_empty() {
-        quote! { swiftide::chat_completion::ToolSpec::builder().name(#tool_name).description(#description).build().unwrap() }
+        Ok(
+            quote! { swiftide::chat_completion::ToolSpec::builder().name(#tool_name).description(#description).build().unwrap() },
+        )
     } else {
         let params = args
             .param
             .iter()
-            .map(|param| {
-                let name = &param.name;
-                let description = &param.description;
+            .map(param_spec)
+            .collect::<Result<Vec<_>>>()?;
 
-                quote! {
-                    swiftide::chat_completion::ParamSpec::builder()
-                        .name(#name)
-                        .description(#description)
-                        .build().expect("infallible")
-
-                }
-            })
-            .collect::<Vec<_>>();
-
-        quote! {
+        Ok(quote! {
             swiftide::chat_completion::ToolSpec::builder()
             .name(#tool_name)
             .description(#description)
             .parameters(vec![#(#params),*])
             .build()
             .unwrap()
-        }
+        })
     }
 }
+
+fn param_spec(param: &ParamOptions) -> Result<TokenStream> {
+    let name = &param.name;
+    let description = &param.description;
+
+    // Strings are the default, any other type gets its json schema
+    let typed = param.rust_type.as_ref().filter(|ty| !is_string_type(ty));
+    let schema = typed.map(|ty| quote! { .schema_for::<#ty>() });
+
+    let required = param
+        .required
+        .or(param.default.as_ref().map(|_| false))
+        .or(typed.map(|ty| !is_option_type(ty)))
+        .map(|required| quote! { .required(#required) });
+
+    let default = param.default.as_ref().map(
+        |default| quote! { .default_value(::swiftide::reexports::serde_json::json!(#default)) },
+    );
+    let minimum = param
+        .min
+        .as_ref()
+        .map(number_literal)
+        .transpose()?
+        .map(|minimum| quote! { .minimum(#minimum) });
+    let maximum = param
+        .max
+        .as_ref()
+        .map(number_literal)
+        .transpose()?
+        .map(|maximum| quote! { .maximum(#maximum) });
+    let pattern = param
+        .pattern
+        .as_ref()
+        .map(|pattern| quote! { .pattern(#pattern) });
+    let one_of = (!param.one_of.is_empty()).then(|| {
+        let values = &param.one_of;
+        quote! { .one_of([#(#values),*]) }
+    });
+
+    Ok(quote! {
+        swiftide::chat_completion::ParamSpec::builder()
+            .name(#name)
+            .description(#description)
+            #schema
+            #required
+            #default
+            #minimum
+            #maximum
+            #pattern
+            #one_of
+            .build().expect("infallible")
+
+    })
+}
+
+/// Minimums and maximums are floats in the spec
+fn number_literal(lit: &Lit) -> Result<Literal> {
+    let number = match lit {
+        Lit::Int(int) => int.base10_parse::<f64>()?,
+        Lit::Float(float) => float.base10_parse::<f64>()?,
+        _ => return Err(Error::new_spanned(lit, "expected a number")),
+    };
+
+    Ok(Literal::f64_unsuffixed(number))
+}
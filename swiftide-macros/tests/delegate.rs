@@ -0,0 +1,6 @@
+#[rustversion::attr(nightly, ignore)]
+#[test]
+fn test_delegate() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/delegate/delegate_pass.rs");
+}
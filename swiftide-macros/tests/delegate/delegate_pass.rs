@@ -0,0 +1,34 @@
+use swiftide::indexing::persist::MemoryStore;
+use swiftide::query::search_strategies::SimilaritySingleEmbedding;
+use swiftide::traits::{NodeCache, Persist, Retrieve};
+use swiftide_macros::{NodeCache, Persist, Retrieve};
+
+#[derive(Debug, Clone, Persist, Retrieve, NodeCache)]
+struct Wrapped(MemoryStore);
+
+#[derive(Debug, Clone, Persist, Retrieve, NodeCache)]
+struct Tenant<S> {
+    _tenant: String,
+    #[delegate]
+    store: S,
+}
+
+fn assert_persist(_: impl Persist) {}
+fn assert_retrieve(_: impl Retrieve<SimilaritySingleEmbedding>) {}
+fn assert_node_cache(_: impl NodeCache) {}
+
+fn main() {
+    let store = MemoryStore::default();
+
+    assert_persist(Wrapped(store.clone()));
+    assert_retrieve(Wrapped(store.clone()));
+    assert_node_cache(Wrapped(store.clone()));
+
+    let tenant = Tenant {
+        _tenant: "tenant".to_string(),
+        store,
+    };
+    assert_persist(tenant.clone());
+    assert_retrieve(tenant.clone());
+    assert_node_cache(tenant);
+}
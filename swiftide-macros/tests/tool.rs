@@ -5,6 +5,8 @@ fn test_tool() {
     t.pass("tests/tool/tool_single_argument_pass.rs");
     t.pass("tests/tool/tool_no_argument_pass.rs");
     t.pass("tests/tool/tool_multiple_arguments_pass.rs");
+    t.pass("tests/tool/tool_typed_arguments_pass.rs");
+    t.pass("tests/tool/tool_validated_arguments_pass.rs");
     t.compile_fail("tests/tool/tool_missing_arg_fail.rs");
     t.compile_fail("tests/tool/tool_missing_parameter_fail.rs");
 }
@@ -0,0 +1,19 @@
+use swiftide::chat_completion::{errors::ToolError, ToolOutput};
+use swiftide::traits::AgentContext;
+
+#[swiftide_macros::tool(
+    description = "My first tool",
+    param(name = "ids", description = "My param"),
+    param(name = "limit", description = "My other param"),
+    param(name = "verbose", description = "My optional param")
+)]
+async fn basic_tool(
+    _agent_context: &dyn AgentContext,
+    ids: &[u64],
+    limit: usize,
+    verbose: Option<bool>,
+) -> Result<ToolOutput, ToolError> {
+    Ok(format!("Hello {ids:?} {limit} {verbose:?}").into())
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+use swiftide::chat_completion::{errors::ToolError, ToolOutput};
+use swiftide::traits::AgentContext;
+
+#[swiftide_macros::tool(
+    description = "My first tool",
+    param(name = "query", description = "My param", pattern = "^[a-z]+$"),
+    param(
+        name = "language",
+        description = "My other param",
+        one_of = "rust",
+        one_of = "python"
+    ),
+    param(
+        name = "limit",
+        description = "My number param",
+        default = 10,
+        min = 1,
+        max = 50
+    )
+)]
+async fn basic_tool(
+    _agent_context: &dyn AgentContext,
+    query: &str,
+    language: &str,
+    limit: usize,
+) -> Result<ToolOutput, ToolError> {
+    Ok(format!("Hello {query} {language} {limit}").into())
+}
+
+fn main() {}
@@ -53,8 +53,11 @@ impl SimpleBuilder {
     }
 }
 
+/// Can be overridden by registering a `simple_answer.prompt.md` template
 fn default_prompt() -> Template {
-    indoc::indoc! {"
+    Template::named(
+        "simple_answer.prompt.md",
+        indoc::indoc! {"
     Answer the following question based on the context provided:
     {{ question }}
 
@@ -68,8 +71,8 @@ fn default_prompt() -> Template {
     ---
     {{ documents }}
     ---
-    "}
-    .into()
+    "},
+    )
 }
 
 #[async_trait]
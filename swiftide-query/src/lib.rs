@@ -2,6 +2,8 @@ pub mod answers;
 mod query;
 pub mod query_transformers;
 pub mod response_transformers;
+mod retrieval_tool;
 
 pub use query::*;
+pub use retrieval_tool::{RetrievalTool, RetrievalToolBuilder};
 pub mod evaluators;
@@ -12,18 +12,24 @@
 //! must implement.
 //!
 //! A query pipeline is lazy and only runs when query is called.
+//!
+//! Queries, retrievals and answers are traced with an `openinference.span.kind`, so that spans
+//! exported with `tracing-opentelemetry` are visualized as a RAG pipeline in i.e. Arize Phoenix.
 
-use futures_util::TryFutureExt as _;
-use std::sync::Arc;
+use std::{future::Future, sync::Arc};
 use swiftide_core::{
+    events::{EventSender, PipelineEvent},
     prelude::*,
     querying::{
         search_strategies::SimilaritySingleEmbedding, states, Answer, Query, QueryState,
         QueryStream, Retrieve, SearchStrategy, TransformQuery, TransformResponse,
     },
-    EvaluateQuery,
+    EvaluateQuery, Rerank,
 };
-use tokio::sync::mpsc::Sender;
+use tokio::sync::{broadcast, mpsc::Sender};
+use web_time::Instant;
+
+use crate::response_transformers::RerankDocuments;
 
 /// The starting point of a query pipeline
 pub struct Pipeline<
@@ -36,6 +42,7 @@ pub struct Pipeline<
     query_sender: Sender<Result<Query<states::Pending>>>,
     evaluator: Option<Arc<Box<dyn EvaluateQuery>>>,
     default_concurrency: usize,
+    events: EventSender,
 }
 
 /// By default the [`SearchStrategy`] is [`SimilaritySingleEmbedding`], which embed the current
@@ -52,6 +59,7 @@ impl Default for Pipeline<'_, SimilaritySingleEmbedding> {
             stream,
             evaluator: None,
             default_concurrency: num_cpus::get(),
+            events: EventSender::default(),
         }
     }
 }
@@ -75,10 +83,18 @@ impl<'a, STRATEGY: SearchStrategy> Pipeline<'a, STRATEGY> {
             stream,
             evaluator: None,
             default_concurrency: num_cpus::get(),
+            events: EventSender::default(),
         }
     }
 }
 
+impl<STRATEGY: SearchStrategy, STATE: QueryState> Pipeline<'_, STRATEGY, STATE> {
+    /// Subscribes to the events of the pipeline, see [`swiftide_core::events`]
+    pub fn subscribe(&self) -> broadcast::Receiver<PipelineEvent> {
+        self.events.subscribe()
+    }
+}
+
 impl<'stream: 'static, STRATEGY> Pipeline<'stream, STRATEGY, states::Pending>
 where
     STRATEGY: SearchStrategy,
@@ -105,16 +121,21 @@ where
             search_strategy,
             evaluator,
             default_concurrency,
+            events,
         } = self;
 
+        let events_for_stream = events.clone();
         let new_stream = stream
             .map_ok(move |query| {
                 let transformer = Arc::clone(&transformer);
+                let events = events_for_stream.clone();
                 let span = tracing::info_span!("then_transform_query", query = ?query);
 
-                tokio::spawn(
+                spawn(
                     async move {
-                        let transformed_query = transformer.transform_query(query).await?;
+                        let transformed_query = events
+                            .observe(transformer.name(), None, transformer.transform_query(query))
+                            .await?;
                         tracing::debug!(
                             transformed_query = transformed_query.current(),
                             query_transformer = transformer.name(),
@@ -125,7 +146,6 @@ where
                     }
                     .instrument(span.or_current()),
                 )
-                .err_into::<anyhow::Error>()
             })
             .try_buffer_unordered(default_concurrency)
             .map(|x| x.and_then(|x| x));
@@ -136,6 +156,7 @@ where
             query_sender,
             evaluator,
             default_concurrency,
+            events,
         }
     }
 }
@@ -156,21 +177,36 @@ impl<'stream: 'static, STRATEGY: SearchStrategy + 'stream>
             search_strategy,
             evaluator,
             default_concurrency,
+            events,
         } = self;
 
         let strategy_for_stream = search_strategy.clone();
         let evaluator_for_stream = evaluator.clone();
+        let events_for_stream = events.clone();
 
         let new_stream = stream
             .map_ok(move |query| {
                 let search_strategy = strategy_for_stream.clone();
                 let retriever = Arc::clone(&retriever);
-                let span = tracing::info_span!("then_retrieve", query = ?query);
+                let span = tracing::info_span!(
+                    "then_retrieve",
+                    query = ?query,
+                    openinference.span.kind = "RETRIEVER"
+                );
                 let evaluator_for_stream = evaluator_for_stream.clone();
+                let events = events_for_stream.clone();
 
-                tokio::spawn(
+                spawn(
                     async move {
-                        let result = retriever.retrieve(&search_strategy, query).await?;
+                        let result = events
+                            .observe(
+                                retriever.name(),
+                                None,
+                                futures_util::TryFutureExt::err_into(
+                                    retriever.retrieve(&search_strategy, query),
+                                ),
+                            )
+                            .await?;
 
                         tracing::debug!(documents = ?result.documents(), "Retrieved documents");
 
@@ -183,7 +219,6 @@ impl<'stream: 'static, STRATEGY: SearchStrategy + 'stream>
                     }
                     .instrument(span.or_current()),
                 )
-                .err_into::<anyhow::Error>()
             })
             .try_buffer_unordered(default_concurrency)
             .map(|x| x.and_then(|x| x));
@@ -194,6 +229,7 @@ impl<'stream: 'static, STRATEGY: SearchStrategy + 'stream>
             query_sender,
             evaluator,
             default_concurrency,
+            events,
         }
     }
 }
@@ -212,15 +248,24 @@ impl<'stream: 'static, STRATEGY: SearchStrategy> Pipeline<'stream, STRATEGY, sta
             search_strategy,
             evaluator,
             default_concurrency,
+            events,
         } = self;
 
+        let events_for_stream = events.clone();
         let new_stream = stream
             .map_ok(move |query| {
                 let transformer = Arc::clone(&transformer);
+                let events = events_for_stream.clone();
                 let span = tracing::info_span!("then_transform_response", query = ?query);
-                tokio::spawn(
+                spawn(
                     async move {
-                        let transformed_query = transformer.transform_response(query).await?;
+                        let transformed_query = events
+                            .observe(
+                                transformer.name(),
+                                None,
+                                transformer.transform_response(query),
+                            )
+                            .await?;
                         tracing::debug!(
                             transformed_query = transformed_query.current(),
                             response_transformer = transformer.name(),
@@ -231,7 +276,6 @@ impl<'stream: 'static, STRATEGY: SearchStrategy> Pipeline<'stream, STRATEGY, sta
                     }
                     .instrument(span.or_current()),
                 )
-                .err_into::<anyhow::Error>()
             })
             .try_buffer_unordered(default_concurrency)
             .map(|x| x.and_then(|x| x));
@@ -242,10 +286,24 @@ impl<'stream: 'static, STRATEGY: SearchStrategy> Pipeline<'stream, STRATEGY, sta
             query_sender,
             evaluator,
             default_concurrency,
+            events,
         }
     }
 }
 
+impl<'stream: 'static, STRATEGY: SearchStrategy> Pipeline<'stream, STRATEGY, states::Retrieved> {
+    /// Reranks the retrieved documents by relevance to the original query
+    ///
+    /// See [`crate::response_transformers::RerankDocuments`]
+    #[must_use]
+    pub fn then_rerank<T: Rerank + 'stream>(
+        self,
+        reranker: T,
+    ) -> Pipeline<'stream, STRATEGY, states::Retrieved> {
+        self.then_transform_response(RerankDocuments::from_reranker(reranker))
+    }
+}
+
 impl<'stream: 'static, STRATEGY: SearchStrategy> Pipeline<'stream, STRATEGY, states::Retrieved> {
     /// Generates an answer based on previous transformations
     #[must_use]
@@ -260,19 +318,28 @@ impl<'stream: 'static, STRATEGY: SearchStrategy> Pipeline<'stream, STRATEGY, sta
             search_strategy,
             evaluator,
             default_concurrency,
+            events,
         } = self;
         let evaluator_for_stream = evaluator.clone();
+        let events_for_stream = events.clone();
 
         let new_stream = stream
             .map_ok(move |query: Query<states::Retrieved>| {
                 let answerer = Arc::clone(&answerer);
-                let span = tracing::info_span!("then_answer", query = ?query);
+                let span = tracing::info_span!(
+                    "then_answer",
+                    query = ?query,
+                    openinference.span.kind = "CHAIN"
+                );
                 let evaluator_for_stream = evaluator_for_stream.clone();
+                let events = events_for_stream.clone();
 
-                tokio::spawn(
+                spawn(
                     async move {
                         tracing::debug!(answerer = answerer.name(), "Answering query");
-                        let result = answerer.answer(query).await?;
+                        let result = events
+                            .observe(answerer.name(), None, answerer.answer(query))
+                            .await?;
 
                         if let Some(evaluator) = evaluator_for_stream.as_ref() {
                             evaluator.evaluate(result.clone().into()).await?;
@@ -283,7 +350,6 @@ impl<'stream: 'static, STRATEGY: SearchStrategy> Pipeline<'stream, STRATEGY, sta
                     }
                     .instrument(span.or_current()),
                 )
-                .err_into::<anyhow::Error>()
             })
             .try_buffer_unordered(default_concurrency)
             .map(|x| x.and_then(|x| x));
@@ -294,6 +360,7 @@ impl<'stream: 'static, STRATEGY: SearchStrategy> Pipeline<'stream, STRATEGY, sta
             query_sender,
             evaluator,
             default_concurrency,
+            events,
         }
     }
 }
@@ -304,19 +371,31 @@ impl<STRATEGY: SearchStrategy> Pipeline<'_, STRATEGY, states::Answered> {
     /// # Errors
     ///
     /// Errors if any of the transformations failed or no response was found
-    #[tracing::instrument(skip_all, name = "query_pipeline.query")]
+    #[tracing::instrument(
+        skip_all,
+        name = "query_pipeline.query",
+        fields(openinference.span.kind = "CHAIN")
+    )]
     pub async fn query(
         mut self,
         query: impl Into<Query<states::Pending>>,
     ) -> Result<Query<states::Answered>> {
         tracing::debug!("Sending query");
-        let now = std::time::Instant::now();
+        let now = Instant::now();
 
-        self.query_sender.send(Ok(query.into())).await?;
+        let events = self.events.clone();
+        let answer = events
+            .observe_run(
+                async {
+                    self.query_sender.send(Ok(query.into())).await?;
 
-        let answer = self.stream.try_next().await?.ok_or_else(|| {
-            anyhow::anyhow!("Pipeline did not receive a response from the query stream")
-        });
+                    self.stream.try_next().await?.ok_or_else(|| {
+                        anyhow::anyhow!("Pipeline did not receive a response from the query stream")
+                    })
+                },
+                |_| 1,
+            )
+            .await;
 
         let elapsed_in_seconds = now.elapsed().as_secs();
         tracing::warn!(
@@ -336,25 +415,38 @@ impl<STRATEGY: SearchStrategy> Pipeline<'_, STRATEGY, states::Answered> {
     /// # Errors
     ///
     /// Errors if any of the transformations failed or no response was found
-    #[tracing::instrument(skip_all, name = "query_pipeline.query_mut")]
+    #[tracing::instrument(
+        skip_all,
+        name = "query_pipeline.query_mut",
+        fields(openinference.span.kind = "CHAIN")
+    )]
     pub async fn query_mut(
         &mut self,
         query: impl Into<Query<states::Pending>>,
     ) -> Result<Query<states::Answered>> {
         tracing::warn!("Sending query");
-        let now = std::time::Instant::now();
-
-        self.query_sender.send(Ok(query.into())).await?;
-
-        let answer = self
-            .stream
-            .by_ref()
-            .take(1)
-            .try_next()
-            .await?
-            .ok_or_else(|| {
-                anyhow::anyhow!("Pipeline did not receive a response from the query stream")
-            });
+        let now = Instant::now();
+
+        let events = self.events.clone();
+        let answer = events
+            .observe_run(
+                async {
+                    self.query_sender.send(Ok(query.into())).await?;
+
+                    self.stream
+                        .by_ref()
+                        .take(1)
+                        .try_next()
+                        .await?
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Pipeline did not receive a response from the query stream"
+                            )
+                        })
+                },
+                |_| 1,
+            )
+            .await;
 
         tracing::debug!(?answer, "Received an answer");
 
@@ -374,33 +466,47 @@ impl<STRATEGY: SearchStrategy> Pipeline<'_, STRATEGY, states::Answered> {
     ///
     /// Errors if any of the transformations failed, no response was found, or the stream was
     /// closed.
-    #[tracing::instrument(skip_all, name = "query_pipeline.query_all")]
+    #[tracing::instrument(
+        skip_all,
+        name = "query_pipeline.query_all",
+        fields(openinference.span.kind = "CHAIN")
+    )]
     pub async fn query_all(
         self,
         queries: Vec<impl Into<Query<states::Pending>> + Clone>,
     ) -> Result<Vec<Query<states::Answered>>> {
         tracing::warn!("Sending queries");
-        let now = std::time::Instant::now();
+        let now = Instant::now();
 
         let Pipeline {
             query_sender,
             mut stream,
+            events,
             ..
         } = self;
 
-        for query in &queries {
-            query_sender.send(Ok(query.clone().into())).await?;
-        }
-        tracing::info!("All queries sent");
-
-        let mut results = vec![];
-        while let Some(result) = stream.try_next().await? {
-            tracing::debug!(?result, "Received an answer");
-            results.push(result);
-            if results.len() == queries.len() {
-                break;
-            }
-        }
+        let results = events
+            .observe_run(
+                async {
+                    for query in &queries {
+                        query_sender.send(Ok(query.clone().into())).await?;
+                    }
+                    tracing::info!("All queries sent");
+
+                    let mut results = vec![];
+                    while let Some(result) = stream.try_next().await? {
+                        tracing::debug!(?result, "Received an answer");
+                        results.push(result);
+                        if results.len() == queries.len() {
+                            break;
+                        }
+                    }
+
+                    Ok(results)
+                },
+                Vec::len,
+            )
+            .await?;
 
         let elapsed_in_seconds = now.elapsed().as_secs();
         tracing::warn!(
@@ -413,6 +519,25 @@ impl<STRATEGY: SearchStrategy> Pipeline<'_, STRATEGY, states::Answered> {
     }
 }
 
+/// Runs the future on a new task
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn<F>(future: F) -> impl Future<Output = Result<F::Output>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    futures_util::TryFutureExt::err_into(tokio::spawn(future))
+}
+
+/// Tasks cannot be spawned on a runtime in wasm, so the future runs in place
+#[cfg(target_arch = "wasm32")]
+fn spawn<F>(future: F) -> impl Future<Output = Result<F::Output>>
+where
+    F: Future + 'static,
+{
+    futures_util::FutureExt::map(future, Ok)
+}
+
 #[cfg(test)]
 mod test {
     use swiftide_core::{
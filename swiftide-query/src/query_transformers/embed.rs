@@ -4,19 +4,32 @@ use swiftide_core::{
     indexing::EmbeddingModel,
     prelude::*,
     querying::{states, Query, TransformQuery},
+    EmbeddingPrefixes,
 };
 
+/// Embeds the current query
+///
+/// If the model has [`EmbeddingPrefixes`], the query prefix is prepended to the query.
 #[derive(Debug, Clone)]
 pub struct Embed {
     embed_model: Arc<dyn EmbeddingModel>,
+    prefixes: Option<EmbeddingPrefixes>,
 }
 
 impl Embed {
     pub fn from_client(client: impl EmbeddingModel + 'static) -> Embed {
         Embed {
             embed_model: Arc::new(client),
+            prefixes: None,
         }
     }
+
+    /// Uses these prefixes instead of the prefixes configured on the model
+    #[must_use]
+    pub fn with_prefixes(mut self, prefixes: EmbeddingPrefixes) -> Self {
+        self.prefixes = Some(prefixes);
+        self
+    }
 }
 
 #[async_trait]
@@ -26,12 +39,16 @@ impl TransformQuery for Embed {
         &self,
         mut query: Query<states::Pending>,
     ) -> Result<Query<states::Pending>> {
-        let Some(embedding) = self
-            .embed_model
-            .embed(vec![query.current().to_string()])
-            .await?
-            .pop()
-        else {
+        let input = match self
+            .prefixes
+            .as_ref()
+            .or_else(|| self.embed_model.prefixes())
+        {
+            Some(prefixes) => prefixes.apply_query(query.current()),
+            None => query.current().to_string(),
+        };
+
+        let Some(embedding) = self.embed_model.embed(vec![input]).await?.pop() else {
             anyhow::bail!("Failed to embed query")
         };
 
@@ -40,3 +57,28 @@ impl TransformQuery for Embed {
         Ok(query)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::MockEmbeddingModel;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_applies_query_prefix() {
+        let mut model = MockEmbeddingModel::new();
+        model
+            .expect_embed()
+            .withf(|input| *input == ["query: what is rust?"])
+            .times(1)
+            .returning(|_| Ok(vec![vec![1.0]]));
+
+        let query = Embed::from_client(model)
+            .with_prefixes(EmbeddingPrefixes::e5())
+            .transform_query(Query::from("what is rust?"))
+            .await
+            .unwrap();
+
+        assert_eq!(query.embedding, Some(vec![1.0]));
+    }
+}
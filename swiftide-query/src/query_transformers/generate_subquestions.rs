@@ -45,8 +45,11 @@ impl GenerateSubquestionsBuilder {
     }
 }
 
+/// Can be overridden by registering a `generate_subquestions.prompt.md` template
 fn default_prompt() -> Template {
-    indoc::indoc!("
+    Template::named(
+        "generate_subquestions.prompt.md",
+        indoc::indoc!("
     Your job is to help a query tool find the right context.
 
     Given the following question:
@@ -66,7 +69,8 @@ fn default_prompt() -> Template {
     - Additional question 3
     - Additional question 4
     - Additional question 5
-    ").into()
+    "),
+    )
 }
 
 #[async_trait]
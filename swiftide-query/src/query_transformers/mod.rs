@@ -3,6 +3,8 @@ mod generate_subquestions;
 pub use generate_subquestions::GenerateSubquestions;
 
 mod embed;
+mod multi_embed;
 mod sparse_embed;
 pub use embed::Embed;
+pub use multi_embed::MultiEmbed;
 pub use sparse_embed::SparseEmbed;
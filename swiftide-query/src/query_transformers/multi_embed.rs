@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use swiftide_core::{
+    prelude::*,
+    querying::{states, Query, TransformQuery},
+    MultiEmbeddingModel,
+};
+
+/// Embed a query with an embedding per token, for late interaction retrieval.
+#[derive(Debug, Clone)]
+pub struct MultiEmbed {
+    embed_model: Arc<dyn MultiEmbeddingModel>,
+}
+
+impl MultiEmbed {
+    pub fn from_client(client: impl MultiEmbeddingModel + 'static) -> MultiEmbed {
+        MultiEmbed {
+            embed_model: Arc::new(client),
+        }
+    }
+}
+
+#[async_trait]
+impl TransformQuery for MultiEmbed {
+    #[tracing::instrument(skip_all)]
+    async fn transform_query(
+        &self,
+        mut query: Query<states::Pending>,
+    ) -> Result<Query<states::Pending>> {
+        let Some(embedding) = self
+            .embed_model
+            .multi_embed(vec![query.current().to_string()])
+            .await?
+            .pop()
+        else {
+            anyhow::bail!("Failed to embed query")
+        };
+
+        query.multi_embedding = Some(embedding);
+
+        Ok(query)
+    }
+}
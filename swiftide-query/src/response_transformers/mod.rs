@@ -1,4 +1,6 @@
 //! Transform retrieved queries
+mod rerank;
 mod summary;
 
+pub use rerank::*;
 pub use summary::*;
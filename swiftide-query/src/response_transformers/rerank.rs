@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use swiftide_core::{
+    prelude::*,
+    querying::{states, Query},
+    Rerank, TransformResponse,
+};
+
+/// Reranks the retrieved documents with any [`Rerank`] implementation
+///
+/// The documents on the query are replaced with the reranked documents, ordered by relevance to
+/// the original query. See also [`crate::Pipeline::then_rerank`].
+#[derive(Clone)]
+pub struct RerankDocuments {
+    reranker: Arc<dyn Rerank>,
+}
+
+impl std::fmt::Debug for RerankDocuments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RerankDocuments")
+            .field("reranker", &self.reranker.name())
+            .finish()
+    }
+}
+
+impl RerankDocuments {
+    pub fn from_reranker(reranker: impl Rerank + 'static) -> RerankDocuments {
+        RerankDocuments {
+            reranker: Arc::new(reranker),
+        }
+    }
+}
+
+#[async_trait]
+impl TransformResponse for RerankDocuments {
+    #[tracing::instrument(skip_all)]
+    async fn transform_response(
+        &self,
+        mut query: Query<states::Retrieved>,
+    ) -> Result<Query<states::Retrieved>> {
+        let documents = std::mem::take(query.documents_mut());
+        let num_documents = documents.len();
+
+        let reranked = self.reranker.rerank(query.original(), documents).await?;
+
+        tracing::debug!(
+            reranker = self.reranker.name(),
+            before = num_documents,
+            after = reranked.len(),
+            "Reranked documents"
+        );
+        *query.documents_mut() = reranked;
+
+        Ok(query)
+    }
+
+    fn name(&self) -> &'static str {
+        self.reranker.name()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use swiftide_core::{document::Document, MockRerank};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replaces_documents_with_reranked() {
+        let mut reranker = MockRerank::new();
+        reranker
+            .expect_rerank()
+            .withf(|query, documents| query == "What" && documents.len() == 2)
+            .returning(|_, mut documents| {
+                documents.reverse();
+                documents.truncate(1);
+                Ok(documents)
+            });
+
+        let query = Query::<states::Pending>::from("What")
+            .retrieved_documents(vec![Document::from("first"), Document::from("second")]);
+
+        let transformer = RerankDocuments::from_reranker(reranker);
+        let query = transformer.transform_response(query).await.unwrap();
+
+        assert_eq!(query.documents(), &[Document::from("second")]);
+    }
+}
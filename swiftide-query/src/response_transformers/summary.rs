@@ -44,9 +44,12 @@ impl SummaryBuilder {
     }
 }
 
+/// Can be overridden by registering a `summary.prompt.md` template
 fn default_prompt() -> Template {
-    indoc::indoc!(
-        "
+    Template::named(
+        "summary.prompt.md",
+        indoc::indoc!(
+            "
     Your job is to help a query tool find the right context.
 
     Summarize the following documents.
@@ -64,8 +67,8 @@ fn default_prompt() -> Template {
     ---
     {% endfor -%}
     "
+        ),
     )
-    .into()
 }
 
 #[async_trait]
@@ -0,0 +1,354 @@
+//! Use a query pipeline as a tool for agents
+//!
+//! The agent provides the query, optionally values for metadata filters, and the number of
+//! documents to retrieve. For every call a new pipeline is built with a
+//! [`SimilaritySingleEmbedding`] strategy with that filter and `top_k`. The answer of the pipeline
+//! is returned to the agent.
+//!
+//! Metadata filters are converted to the filter of the retriever with a closure. Filters not
+//! provided by the agent are left out of the metadata.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let tool = RetrievalTool::builder()
+//!     .name("search_code")
+//!     .description("Searches the code of the project")
+//!     .filter_field("language", "Only search files in this programming language")
+//!     .to_filter(|metadata: Metadata| {
+//!         metadata
+//!             .iter()
+//!             .map(|(key, value)| format!("{key} = {value}"))
+//!             .collect::<Vec<_>>()
+//!             .join(" AND ")
+//!     })
+//!     .pipeline(move |strategy| {
+//!         query::Pipeline::from_search_strategy(strategy)
+//!             .then_transform_query(query_transformers::Embed::from_client(openai.clone()))
+//!             .then_retrieve(lancedb.clone())
+//!             .then_answer(answers::Simple::from_client(openai.clone()))
+//!     })
+//!     .build()?;
+//! ```
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use derive_builder::Builder;
+use swiftide_core::{
+    chat_completion::{errors::ToolError, ParamConstraint, ParamSpec, Tool, ToolOutput, ToolSpec},
+    indexing::Metadata,
+    querying::{
+        search_strategies::{SearchFilter, SimilaritySingleEmbedding},
+        states,
+    },
+    AgentContext,
+};
+
+use crate::Pipeline;
+
+/// Builds a pipeline with the strategy for a tool call
+pub type PipelineFn<FILTER> = Arc<
+    dyn Fn(
+            SimilaritySingleEmbedding<FILTER>,
+        ) -> Pipeline<'static, SimilaritySingleEmbedding<FILTER>, states::Answered>
+        + Send
+        + Sync,
+>;
+
+/// Converts the metadata filters provided by the agent to the filter of the retriever
+pub type ToFilterFn<FILTER> = Arc<dyn Fn(Metadata) -> FILTER + Send + Sync>;
+
+/// A tool that answers queries of an agent with a query pipeline
+#[derive(Clone, Builder)]
+#[builder(
+    setter(into),
+    build_fn(error = "anyhow::Error", validate = "Self::validate")
+)]
+pub struct RetrievalTool<FILTER: SearchFilter + 'static = ()> {
+    /// Name of the tool, i.e. `search_documentation`
+    name: &'static str,
+
+    /// Describes to the agent what it can find with the tool
+    description: &'static str,
+
+    #[builder(setter(custom))]
+    pipeline: PipelineFn<FILTER>,
+
+    /// Metadata fields the agent can filter on, with a description
+    #[builder(default, setter(custom))]
+    filter_fields: Vec<(&'static str, &'static str)>,
+
+    #[builder(default, setter(custom))]
+    to_filter: Option<ToFilterFn<FILTER>>,
+
+    /// The number of documents retrieved if the agent does not provide it
+    #[builder(default = "5")]
+    default_top_k: u64,
+
+    /// The maximum number of documents the agent can retrieve
+    #[builder(default = "20")]
+    max_top_k: u64,
+}
+
+impl<FILTER: SearchFilter> std::fmt::Debug for RetrievalTool<FILTER> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetrievalTool")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("filter_fields", &self.filter_fields)
+            .field("default_top_k", &self.default_top_k)
+            .field("max_top_k", &self.max_top_k)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<FILTER: SearchFilter> RetrievalToolBuilder<FILTER> {
+    /// Builds the pipeline for each call with the strategy of the call
+    pub fn pipeline(
+        &mut self,
+        pipeline: impl Fn(
+                SimilaritySingleEmbedding<FILTER>,
+            )
+                -> Pipeline<'static, SimilaritySingleEmbedding<FILTER>, states::Answered>
+            + Send
+            + Sync
+            + 'static,
+    ) -> &mut Self {
+        self.pipeline = Some(Arc::new(pipeline));
+        self
+    }
+
+    /// Adds a metadata field the agent can filter on
+    pub fn filter_field(&mut self, name: &'static str, description: &'static str) -> &mut Self {
+        self.filter_fields
+            .get_or_insert_with(Vec::new)
+            .push((name, description));
+        self
+    }
+
+    /// Converts the metadata filters to the filter of the retriever, required with filter fields
+    pub fn to_filter(
+        &mut self,
+        to_filter: impl Fn(Metadata) -> FILTER + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.to_filter = Some(Some(Arc::new(to_filter)));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        let has_filter_fields = self
+            .filter_fields
+            .as_ref()
+            .is_some_and(|fields| !fields.is_empty());
+        let has_to_filter = self.to_filter.as_ref().is_some_and(Option::is_some);
+
+        if has_filter_fields && !has_to_filter {
+            anyhow::bail!("Filter fields require `to_filter` to convert them to a filter");
+        }
+
+        if let (Some(default_top_k), Some(max_top_k)) = (self.default_top_k, self.max_top_k) {
+            if default_top_k > max_top_k {
+                anyhow::bail!("`default_top_k` cannot be larger than `max_top_k`");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<FILTER: SearchFilter> RetrievalTool<FILTER> {
+    pub fn builder() -> RetrievalToolBuilder<FILTER> {
+        RetrievalToolBuilder::default()
+    }
+
+    /// Builds and runs a pipeline for the arguments of a tool call
+    async fn query(&self, args: &str) -> Result<ToolOutput, ToolError> {
+        let args: serde_json::Map<String, serde_json::Value> = serde_json::from_str(args)?;
+
+        let query = args
+            .get("query")
+            .and_then(serde_json::Value::as_str)
+            .context("Missing query")?;
+
+        let top_k = args
+            .get("top_k")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(self.default_top_k);
+
+        let mut metadata = Metadata::default();
+        metadata.extend(self.filter_fields.iter().filter_map(|(name, _)| {
+            args.get(*name)
+                .filter(|value| !value.is_null())
+                .map(|value| (*name, value.clone()))
+        }));
+
+        let mut strategy = match &self.to_filter {
+            Some(to_filter) if !metadata.is_empty() => {
+                SimilaritySingleEmbedding::from_filter(to_filter(metadata))
+            }
+            _ => SimilaritySingleEmbedding::default(),
+        };
+        strategy.with_top_k(top_k);
+
+        tracing::debug!(query, top_k, "Querying pipeline from tool");
+
+        let answer = (self.pipeline)(strategy).query(query).await?;
+
+        Ok(answer.answer().into())
+    }
+}
+
+#[async_trait]
+impl<FILTER: SearchFilter + 'static> Tool for RetrievalTool<FILTER> {
+    async fn invoke(
+        &self,
+        _agent_context: &dyn AgentContext,
+        raw_args: Option<&str>,
+    ) -> Result<ToolOutput, ToolError> {
+        let args = self
+            .tool_spec()
+            .prepare_args(raw_args)?
+            .ok_or_else(|| ToolError::MissingArguments(self.name.to_string()))?;
+
+        self.query(&args).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn tool_spec(&self) -> ToolSpec {
+        let mut parameters = vec![ParamSpec::builder()
+            .name("query")
+            .description("What to search for")
+            .build()
+            .expect("infallible")];
+
+        parameters.extend(self.filter_fields.iter().map(|(name, description)| {
+            ParamSpec::builder()
+                .name(name)
+                .description(description)
+                .required(false)
+                .build()
+                .expect("infallible")
+        }));
+
+        parameters.push(
+            ParamSpec::builder()
+                .name("top_k")
+                .description("The number of documents to retrieve")
+                .schema(serde_json::json!({ "type": "integer" }))
+                .default_value(serde_json::Value::from(self.default_top_k))
+                .constraint(ParamConstraint::Minimum(1.into()))
+                .constraint(ParamConstraint::Maximum(self.max_top_k.into()))
+                .build()
+                .expect("infallible"),
+        );
+
+        ToolSpec::builder()
+            .name(self.name)
+            .description(self.description)
+            .parameters(parameters)
+            .build()
+            .expect("infallible")
+    }
+}
+
+impl<FILTER: SearchFilter + 'static> From<RetrievalTool<FILTER>> for Box<dyn Tool> {
+    fn from(val: RetrievalTool<FILTER>) -> Self {
+        Box::new(val)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use swiftide_core::querying::{Document, Query};
+
+    use super::*;
+
+    fn tool() -> RetrievalTool<Metadata> {
+        RetrievalTool::builder()
+            .name("search")
+            .description("Searches documents")
+            .filter_field("language", "The language of the document")
+            .to_filter(|metadata| metadata)
+            .pipeline(|strategy| {
+                Pipeline::from_search_strategy(strategy)
+                    .then_retrieve(
+                        |strategy: &SimilaritySingleEmbedding<Metadata>,
+                         query: Query<states::Pending>| {
+                            let documents = vec![Document::new(
+                                format!(
+                                    "top_k: {}, filter: {:?}",
+                                    strategy.top_k(),
+                                    strategy.filter()
+                                ),
+                                None,
+                            )];
+                            Ok(query.retrieved_documents(documents))
+                        },
+                    )
+                    .then_answer(|query: Query<states::Retrieved>| {
+                        let answer = query.documents()[0].content().to_string();
+                        Ok(query.answered(answer))
+                    })
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_applies_filters_and_top_k() {
+        let tool = tool();
+        let spec = tool.tool_spec();
+
+        let args = spec
+            .prepare_args(Some(
+                r#"{"query": "What?", "language": "rust", "top_k": 3}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        let output = tool.query(&args).await.unwrap();
+        assert_eq!(
+            output.content().unwrap(),
+            r#"top_k: 3, filter: Some({"language": "rust"})"#
+        );
+
+        let args = spec
+            .prepare_args(Some(r#"{"query": "What?"}"#))
+            .unwrap()
+            .unwrap();
+        let output = tool.query(&args).await.unwrap();
+        assert_eq!(output.content().unwrap(), "top_k: 5, filter: None");
+    }
+
+    #[test]
+    fn test_top_k_is_limited() {
+        let spec = tool().tool_spec();
+
+        assert!(spec
+            .prepare_args(Some(r#"{"query": "What?", "top_k": 100}"#))
+            .is_err());
+    }
+
+    #[test]
+    fn test_filter_fields_require_to_filter() {
+        let result = RetrievalTool::<Metadata>::builder()
+            .name("search")
+            .description("Searches documents")
+            .filter_field("language", "The language of the document")
+            .pipeline(|strategy| {
+                Pipeline::from_search_strategy(strategy)
+                    .then_retrieve(
+                        |_: &SimilaritySingleEmbedding<Metadata>, query: Query<states::Pending>| {
+                            Ok(query.retrieved_documents(vec![]))
+                        },
+                    )
+                    .then_answer(|query: Query<states::Retrieved>| Ok(query.answered("")))
+            })
+            .build();
+
+        assert!(result.is_err());
+    }
+}
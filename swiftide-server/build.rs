@@ -0,0 +1,11 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Only the grpc feature needs the generated code. Protoc is vendored, so that it does not
+    // have to be installed.
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+        tonic_build::compile_protos("proto/query.proto")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+//! Endpoints that run an agent with a query
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    response::{
+        sse::{Event, KeepAlive},
+        AppendHeaders, IntoResponse, Sse,
+    },
+    Json,
+};
+use futures_util::{stream, Stream, StreamExt as _};
+use serde::Serialize;
+use swiftide_agents::AgentBuilder;
+use swiftide_core::{
+    chat_completion::{ChatMessage, ToolCall, Usage},
+    CancellationToken,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use web_time::Instant;
+
+use crate::{elapsed_ms, error_event, json_event, ApiError, QueryRequest, Server, UsageReport};
+
+/// Creates a new agent builder for every request
+pub(crate) type AgentFactory = Arc<dyn Fn() -> AgentBuilder + Send + Sync>;
+
+/// A message added by the agent
+#[derive(Debug, Clone, Serialize)]
+pub struct Message {
+    /// One of `system`, `user`, `assistant`, `tool` or `summary`
+    pub role: &'static str,
+    pub content: Option<String>,
+    /// The tool calls of an assistant message, or the tool call a tool message is the output of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+}
+
+impl From<&ChatMessage> for Message {
+    fn from(message: &ChatMessage) -> Self {
+        let (role, content, tool_calls) = match message {
+            ChatMessage::System(content) => ("system", Some(content.clone()), None),
+            ChatMessage::User(content) => ("user", Some(content.clone()), None),
+            ChatMessage::UserWithParts(parts) => (
+                "user",
+                Some(
+                    parts
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ),
+                None,
+            ),
+            ChatMessage::Assistant(content, tool_calls) => {
+                ("assistant", content.clone(), tool_calls.clone())
+            }
+            ChatMessage::ToolOutput(tool_call, output) => (
+                "tool",
+                Some(output.to_string()),
+                Some(vec![tool_call.clone()]),
+            ),
+            ChatMessage::Summary(content) => ("summary", Some(content.clone()), None),
+        };
+
+        Self {
+            role,
+            content,
+            tool_calls,
+        }
+    }
+}
+
+/// The messages an agent added in response to a query
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentResponse {
+    pub messages: Vec<Message>,
+}
+
+/// Builds an agent from the factory and runs it with the query
+///
+/// Every message the agent adds is sent on `messages`. If sending fails, i.e. because the client
+/// disconnected, the agent is cancelled.
+async fn run_agent(
+    server: &Server,
+    query: String,
+    messages: mpsc::UnboundedSender<Message>,
+) -> Result<UsageReport> {
+    let start = Instant::now();
+    let usage = Arc::new(Mutex::new(Usage::default()));
+    let cancel = CancellationToken::new();
+
+    let mut builder = (server
+        .agent
+        .as_ref()
+        .expect("Agent routes are only served with an agent"))();
+
+    builder
+        .on_new_message({
+            let cancel = cancel.clone();
+            move |_, message| {
+                if messages.send(Message::from(&*message)).is_err() {
+                    cancel.cancel();
+                }
+                Box::pin(async { Ok(()) })
+            }
+        })
+        .after_completion({
+            let usage = Arc::clone(&usage);
+            move |_, response| {
+                if let Some(completion) = response.usage() {
+                    let mut usage = usage.lock().expect("poisoned lock");
+                    usage.prompt_tokens += completion.prompt_tokens;
+                    usage.completion_tokens += completion.completion_tokens;
+                    usage.total_tokens += completion.total_tokens;
+                }
+                Box::pin(async { Ok(()) })
+            }
+        });
+
+    let mut agent = builder.build()?;
+    agent.query_with_cancel(query, cancel).await?;
+
+    let usage = *usage.lock().expect("poisoned lock");
+    Ok(UsageReport {
+        elapsed_ms: elapsed_ms(start),
+        usage: Some(usage),
+    })
+}
+
+/// Runs the agent and responds with the messages it added
+pub(crate) async fn query(
+    State(server): State<Arc<Server>>,
+    payload: Result<Json<QueryRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let request = server.validate(payload)?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let usage = run_agent(&server, request.query, tx).await?;
+
+    let mut messages = vec![];
+    while let Ok(message) = rx.try_recv() {
+        messages.push(message);
+    }
+
+    Ok((
+        AppendHeaders(usage.headers()),
+        Json(AgentResponse { messages }),
+    ))
+}
+
+/// Streams the messages of the agent as `message` events, followed by a `usage` or an `error`
+/// event
+///
+/// The agent is cancelled if the client disconnects.
+pub(crate) async fn query_stream(
+    State(server): State<Arc<Server>>,
+    payload: Result<Json<QueryRequest>, JsonRejection>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let request = server.validate(payload)?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let handle = tokio::spawn(async move { run_agent(&server, request.query, tx).await });
+
+    // The channel closes when the agent is dropped, after it finished
+    let last = stream::once(async move {
+        match handle.await.map_err(anyhow::Error::from).and_then(|r| r) {
+            Ok(usage) => json_event("usage", &usage),
+            Err(err) => error_event(&err),
+        }
+    });
+
+    let events = UnboundedReceiverStream::new(rx)
+        .map(|message| json_event("message", &message))
+        .chain(last)
+        .map(Ok);
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,224 @@
+//! Serves a query pipeline over gRPC with [tonic]
+//!
+//! The service is defined in `proto/query.proto` and has a `Query` and a `StreamQuery` rpc, the
+//! gRPC counterparts of `/query` and `/query/stream`. Invalid queries are rejected with
+//! `INVALID_ARGUMENT`, failed queries with `INTERNAL` and a generic message.
+//!
+//! Every document the answer is based on is cited, with its `path` metadata as source if it has
+//! one.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let service = Server::default()
+//!     .with_query_pipeline(move || query_pipeline(&openai, &qdrant))
+//!     .into_grpc_service()?;
+//!
+//! tonic::transport::Server::builder()
+//!     .add_service(service)
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! ```
+use std::{pin::Pin, sync::Arc};
+
+use futures_util::{Stream, StreamExt as _};
+use swiftide_core::querying::{states, Query};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use web_time::Instant;
+
+use crate::{
+    elapsed_ms,
+    query::{pipeline, stream_query, QueryUpdate, Step},
+    Server, UsageReport,
+};
+
+/// Generated from `proto/query.proto`
+#[allow(clippy::pedantic)]
+pub mod proto {
+    tonic::include_proto!("swiftide.query.v1");
+}
+
+use proto::{query_event::Event, query_service_server::QueryServiceServer};
+
+impl Server {
+    /// Builds a gRPC service that answers queries with the query pipeline
+    ///
+    /// # Errors
+    ///
+    /// Errors if no query pipeline is configured
+    pub fn into_grpc_service(self) -> anyhow::Result<QueryServiceServer<QueryService>> {
+        if self.query_pipeline.is_none() {
+            anyhow::bail!("A query pipeline is required to serve queries over gRPC");
+        }
+
+        Ok(QueryServiceServer::new(QueryService {
+            server: Arc::new(self),
+        }))
+    }
+}
+
+/// Answers queries over gRPC, see [`Server::into_grpc_service`]
+#[derive(Debug, Clone)]
+pub struct QueryService {
+    server: Arc<Server>,
+}
+
+#[tonic::async_trait]
+impl proto::query_service_server::QueryService for QueryService {
+    type StreamQueryStream = Pin<Box<dyn Stream<Item = Result<proto::QueryEvent, Status>> + Send>>;
+
+    async fn query(
+        &self,
+        request: Request<proto::QueryRequest>,
+    ) -> Result<Response<proto::QueryResponse>, Status> {
+        let query = request.into_inner().query;
+        self.server
+            .validate_query(&query)
+            .map_err(Status::invalid_argument)?;
+
+        let start = Instant::now();
+        let answer = pipeline(&self.server)
+            .answer(query)
+            .await
+            .map_err(|err| internal(&err))?;
+        let usage = UsageReport {
+            elapsed_ms: elapsed_ms(start),
+            usage: None,
+        };
+
+        Ok(Response::new(query_response(&answer, &usage)))
+    }
+
+    async fn stream_query(
+        &self,
+        request: Request<proto::QueryRequest>,
+    ) -> Result<Response<Self::StreamQueryStream>, Status> {
+        let query = request.into_inner().query;
+        self.server
+            .validate_query(&query)
+            .map_err(Status::invalid_argument)?;
+
+        let events =
+            ReceiverStream::new(stream_query(pipeline(&self.server), query)).map(|update| {
+                let event = match update {
+                    QueryUpdate::Step(step) => Event::Step(step.into()),
+                    QueryUpdate::Answered(Ok(answer), usage) => {
+                        Event::Answer(query_response(&answer, &usage))
+                    }
+                    QueryUpdate::Answered(Err(err), _) => return Err(internal(&err)),
+                };
+
+                Ok(proto::QueryEvent { event: Some(event) })
+            });
+
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+fn internal(error: &anyhow::Error) -> Status {
+    tracing::error!(error = ?error, "Failed to answer query");
+    Status::internal(crate::INTERNAL_ERROR)
+}
+
+fn query_response(answer: &Query<states::Answered>, usage: &UsageReport) -> proto::QueryResponse {
+    let documents = answer
+        .documents()
+        .iter()
+        .map(|document| proto::Document {
+            content: document.content().to_string(),
+            metadata: document
+                .metadata()
+                .iter()
+                .map(|(key, value)| {
+                    let value = value
+                        .as_str()
+                        .map_or_else(|| value.to_string(), ToString::to_string);
+                    (key.clone(), value)
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let citations = answer
+        .documents()
+        .iter()
+        .enumerate()
+        .map(|(index, document)| proto::Citation {
+            document_index: u32::try_from(index).unwrap_or(u32::MAX),
+            source: document
+                .metadata()
+                .get("path")
+                .and_then(|path| path.as_str())
+                .map(ToString::to_string),
+        })
+        .collect();
+
+    proto::QueryResponse {
+        answer: answer.answer().to_string(),
+        documents,
+        citations,
+        usage: Some(usage.into()),
+    }
+}
+
+impl From<&UsageReport> for proto::Usage {
+    fn from(report: &UsageReport) -> Self {
+        Self {
+            elapsed_ms: report.elapsed_ms,
+            prompt_tokens: report.usage.map(|usage| usage.prompt_tokens),
+            completion_tokens: report.usage.map(|usage| usage.completion_tokens),
+            total_tokens: report.usage.map(|usage| usage.total_tokens),
+        }
+    }
+}
+
+impl From<Step> for proto::Step {
+    fn from(step: Step) -> Self {
+        Self {
+            step: step.step.to_string(),
+            status: step.status.to_string(),
+            nodes: step.nodes.map(|nodes| nodes as u64),
+            elapsed_ms: step.elapsed_ms,
+            error: step.error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swiftide_core::querying::Document;
+
+    use super::*;
+
+    #[test]
+    fn test_cites_documents_by_path() {
+        let answer = Query::builder()
+            .original("What is swiftide?")
+            .current("Swiftide is a library")
+            .documents(vec![
+                Document::new("Swiftide", Some([("path", "README.md")].into())),
+                Document::new("Rust", None),
+            ])
+            .build()
+            .unwrap();
+
+        let response = query_response(&answer, &UsageReport::default());
+
+        assert_eq!(response.answer, "Swiftide is a library");
+        assert_eq!(response.documents[0].metadata["path"], "README.md");
+        assert_eq!(
+            response.citations,
+            vec![
+                proto::Citation {
+                    document_index: 0,
+                    source: Some("README.md".to_string())
+                },
+                proto::Citation {
+                    document_index: 1,
+                    source: None
+                }
+            ]
+        );
+    }
+}
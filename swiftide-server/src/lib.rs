@@ -0,0 +1,424 @@
+//! Serves query pipelines and agents over HTTP with [axum]
+//!
+//! [`Server`] turns a query pipeline and an agent into a [`Router`] with the endpoints:
+//! - `POST /query`: answers a query with the answer and the documents it is based on
+//! - `POST /query/stream`: streams the steps of the pipeline as server-sent events, followed by
+//!   the answer
+//! - `POST /agent`: runs the agent with a query and responds with the messages it added
+//! - `POST /agent/stream`: streams the messages of the agent as server-sent events
+//!
+//! Only the endpoints for what is configured are served. Requests are JSON with a `query`, i.e.
+//! `{ "query": "What is swiftide?" }`. Invalid requests are rejected with a `4xx` and a JSON body
+//! with an `error`. Failed runs are logged, clients only get a generic `error`, so that details
+//! of the pipeline or agent are not leaked.
+//!
+//! Responses carry the time the run took, and for agents the tokens their completions used, in
+//! the `x-swiftide-*` headers. Streams end with a `usage` event with the same values instead.
+//!
+//! Pipelines and agents are consumed by running them, hence the server creates a new one for
+//! every request.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use swiftide_agents::Agent;
+//! # use swiftide_core::{
+//! #     querying::search_strategies::SimilaritySingleEmbedding, ChatCompletion, EmbeddingModel,
+//! #     Retrieve, SimplePrompt,
+//! # };
+//! # use swiftide_query::{answers, query_transformers, Pipeline};
+//! # use swiftide_server::Server;
+//! # async fn serve(
+//! #     openai: impl ChatCompletion + SimplePrompt + EmbeddingModel + Clone + 'static,
+//! #     qdrant: impl Retrieve<SimilaritySingleEmbedding> + Clone + 'static,
+//! # ) -> anyhow::Result<()> {
+//! let router = Server::default()
+//!     .with_query_pipeline({
+//!         let openai = openai.clone();
+//!         move || {
+//!             Pipeline::default()
+//!                 .then_transform_query(query_transformers::Embed::from_client(openai.clone()))
+//!                 .then_retrieve(qdrant.clone())
+//!                 .then_answer(answers::Simple::from_client(openai.clone()))
+//!         }
+//!     })
+//!     .with_agent(move || {
+//!         let mut builder = Agent::builder();
+//!         builder.llm(&openai);
+//!         builder
+//!     })
+//!     .into_router();
+//!
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
+//! axum::serve(listener, router).await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::Arc;
+
+use axum::{
+    extract::rejection::JsonRejection,
+    http::StatusCode,
+    response::{sse::Event, IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use swiftide_core::chat_completion::Usage;
+
+mod agent;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod query;
+
+pub use agent::{AgentResponse, Message};
+pub use query::{QueryResponse, Step};
+
+/// Time the run took in milliseconds
+pub const ELAPSED_HEADER: &str = "x-swiftide-elapsed-ms";
+/// Prompt tokens used by the completions of an agent
+pub const PROMPT_TOKENS_HEADER: &str = "x-swiftide-prompt-tokens";
+/// Completion tokens used by the completions of an agent
+pub const COMPLETION_TOKENS_HEADER: &str = "x-swiftide-completion-tokens";
+/// Total tokens used by the completions of an agent
+pub const TOTAL_TOKENS_HEADER: &str = "x-swiftide-total-tokens";
+
+/// The error clients get when a run fails, the error itself is logged
+pub(crate) const INTERNAL_ERROR: &str = "Internal server error";
+
+/// Queries longer than this are rejected by default
+pub const DEFAULT_MAX_QUERY_LENGTH: usize = 16_384;
+
+/// Builds a [`Router`] that serves a query pipeline and an agent
+#[derive(Clone)]
+pub struct Server {
+    query_pipeline: Option<query::PipelineFactory>,
+    agent: Option<agent::AgentFactory>,
+    max_query_length: usize,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self {
+            query_pipeline: None,
+            agent: None,
+            max_query_length: DEFAULT_MAX_QUERY_LENGTH,
+        }
+    }
+}
+
+impl std::fmt::Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("query_pipeline", &self.query_pipeline.is_some())
+            .field("agent", &self.agent.is_some())
+            .field("max_query_length", &self.max_query_length)
+            .finish()
+    }
+}
+
+impl Server {
+    /// Serves `/query` and `/query/stream` with a pipeline created by the factory per request
+    #[must_use]
+    pub fn with_query_pipeline<STRATEGY>(
+        mut self,
+        factory: impl Fn() -> swiftide_query::Pipeline<
+                'static,
+                STRATEGY,
+                swiftide_core::querying::states::Answered,
+            > + Send
+            + Sync
+            + 'static,
+    ) -> Self
+    where
+        STRATEGY: swiftide_core::querying::SearchStrategy + 'static,
+    {
+        self.query_pipeline = Some(query::PipelineFactory::new(factory));
+        self
+    }
+
+    /// Serves `/agent` and `/agent/stream` with an agent built from the builder created by the
+    /// factory per request
+    ///
+    /// The server adds hooks to the builder to collect the messages and the usage of the agent.
+    #[must_use]
+    pub fn with_agent(
+        mut self,
+        factory: impl Fn() -> swiftide_agents::AgentBuilder + Send + Sync + 'static,
+    ) -> Self {
+        self.agent = Some(Arc::new(factory));
+        self
+    }
+
+    /// Rejects queries with more characters than this, defaults to
+    /// [`DEFAULT_MAX_QUERY_LENGTH`]
+    #[must_use]
+    pub fn with_max_query_length(mut self, max_query_length: usize) -> Self {
+        self.max_query_length = max_query_length;
+        self
+    }
+
+    /// Builds the router with the endpoints for what is configured
+    pub fn into_router(self) -> Router {
+        let mut router = Router::new();
+
+        if self.query_pipeline.is_some() {
+            router = router
+                .route("/query", post(query::query))
+                .route("/query/stream", post(query::query_stream));
+        }
+
+        if self.agent.is_some() {
+            router = router
+                .route("/agent", post(agent::query))
+                .route("/agent/stream", post(agent::query_stream));
+        }
+
+        router.with_state(Arc::new(self))
+    }
+
+    /// Validates the body of a request
+    fn validate(
+        &self,
+        payload: Result<Json<QueryRequest>, JsonRejection>,
+    ) -> Result<QueryRequest, ApiError> {
+        let Json(request) = payload?;
+        self.validate_query(&request.query)
+            .map_err(ApiError::unprocessable)?;
+
+        Ok(request)
+    }
+
+    /// Returns why a query is invalid, if it is
+    fn validate_query(&self, query: &str) -> Result<(), String> {
+        if query.trim().is_empty() {
+            return Err("Query must not be empty".to_string());
+        }
+
+        if query.chars().count() > self.max_query_length {
+            return Err(format!(
+                "Query must not be longer than {} characters",
+                self.max_query_length
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The body of a request
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryRequest {
+    pub query: String,
+}
+
+/// How long a run took and, for agents, the tokens it used
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageReport {
+    pub elapsed_ms: u64,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl UsageReport {
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![(ELAPSED_HEADER, self.elapsed_ms.to_string())];
+
+        if let Some(usage) = &self.usage {
+            headers.extend([
+                (PROMPT_TOKENS_HEADER, usage.prompt_tokens.to_string()),
+                (
+                    COMPLETION_TOKENS_HEADER,
+                    usage.completion_tokens.to_string(),
+                ),
+                (TOTAL_TOKENS_HEADER, usage.total_tokens.to_string()),
+            ]);
+        }
+
+        headers
+    }
+}
+
+/// Milliseconds since `start`, saturating
+fn elapsed_ms(start: web_time::Instant) -> u64 {
+    u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// A server-sent event with a JSON body
+fn json_event(name: &'static str, data: &impl Serialize) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|err| error_event(&anyhow::Error::from(err)))
+}
+
+/// Logs the error and sends a generic error event
+fn error_event(error: &anyhow::Error) -> Event {
+    tracing::error!(error = ?error, "Failed to stream response");
+
+    Event::default()
+        .event("error")
+        .json_data(serde_json::json!({ "error": INTERNAL_ERROR }))
+        .expect("Infallible")
+}
+
+/// An error response with a JSON body
+#[derive(Debug)]
+pub(crate) struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn unprocessable(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self {
+            status: rejection.status(),
+            message: rejection.body_text(),
+        }
+    }
+}
+
+/// Logs the error and responds with a generic message
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        tracing::error!(error = ?error, "Failed to handle request");
+
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: INTERNAL_ERROR.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({ "error": self.message })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use axum::{body::Body, http::Request};
+    use http_body_util::BodyExt as _;
+    use swiftide_agents::{tools::control::Stop, Agent};
+    use swiftide_core::{
+        chat_completion::{ChatCompletionRequest, ChatCompletionResponse, ChatMessage},
+        test_utils::MockChatCompletion,
+        Tool as _,
+    };
+    use tower::ServiceExt as _;
+
+    use super::*;
+
+    fn server(llm: &MockChatCompletion) -> Router {
+        let llm = llm.clone();
+        Server::default()
+            .with_agent(move || {
+                let mut builder = Agent::builder();
+                builder.llm(&llm).no_system_prompt();
+                builder
+            })
+            .with_max_query_length(10)
+            .into_router()
+    }
+
+    async fn post(router: Router, uri: &str, body: &str) -> Response {
+        router
+            .oneshot(
+                Request::post(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn json_body(response: Response) -> serde_json::Value {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_validates_queries() {
+        let llm = MockChatCompletion::new();
+
+        let response = post(server(&llm), "/agent", r#"{"query": " "}"#).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            json_body(response).await,
+            serde_json::json!({ "error": "Query must not be empty" })
+        );
+
+        let response = post(server(&llm), "/agent", r#"{"query": "Way too long"}"#).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = post(server(&llm), "/agent", r#"{"question": "Hello"}"#).await;
+        assert!(response.status().is_client_error());
+
+        // Only configured endpoints are served
+        let response = post(server(&llm), "/query", r#"{"query": "Hello"}"#).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_hides_internal_errors() {
+        let response = ApiError::from(anyhow::anyhow!("Connection to postgres://admin:secret"))
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            json_body(response).await,
+            serde_json::json!({ "error": INTERNAL_ERROR })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_agent_responds_with_messages_and_usage() {
+        let llm = MockChatCompletion::new();
+        llm.expect_complete(
+            ChatCompletionRequest::builder()
+                .messages(vec![ChatMessage::new_user("Hello")])
+                .tools_spec(HashSet::from([Stop::default().tool_spec()]))
+                .build()
+                .unwrap(),
+            Ok(ChatCompletionResponse::builder()
+                .message("Hi!")
+                .usage(Usage {
+                    prompt_tokens: 10,
+                    completion_tokens: 2,
+                    total_tokens: 12,
+                    cached_prompt_tokens: None,
+                })
+                .build()
+                .unwrap()),
+        );
+
+        let response = post(server(&llm), "/agent", r#"{"query": "Hello"}"#).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[TOTAL_TOKENS_HEADER], "12");
+        assert!(response.headers().contains_key(ELAPSED_HEADER));
+        assert_eq!(
+            json_body(response).await,
+            serde_json::json!({
+                "messages": [{ "role": "assistant", "content": "Hi!" }]
+            })
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Endpoints that answer queries with a query pipeline
+use std::{convert::Infallible, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    response::{
+        sse::{Event, KeepAlive},
+        AppendHeaders, IntoResponse, Sse,
+    },
+    Json,
+};
+use futures_util::{future::BoxFuture, FutureExt as _, Stream, StreamExt as _};
+use serde::Serialize;
+use swiftide_core::{
+    events::PipelineEvent,
+    querying::{states, Document, Query, SearchStrategy},
+};
+use swiftide_query::Pipeline;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use web_time::Instant;
+
+use crate::{
+    elapsed_ms, error_event, json_event, ApiError, QueryRequest, Server, UsageReport,
+    INTERNAL_ERROR,
+};
+
+/// The answer to a query and the documents it is based on
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResponse {
+    pub answer: String,
+    pub documents: Vec<Document>,
+}
+
+impl From<Query<states::Answered>> for QueryResponse {
+    fn from(query: Query<states::Answered>) -> Self {
+        Self {
+            answer: query.answer().to_string(),
+            documents: query.documents().to_vec(),
+        }
+    }
+}
+
+/// Erases the search strategy of a pipeline, so that any pipeline can be served
+pub(crate) trait AnswerQuery: Send {
+    fn subscribe(&self) -> broadcast::Receiver<PipelineEvent>;
+
+    fn answer(
+        self: Box<Self>,
+        query: String,
+    ) -> BoxFuture<'static, Result<Query<states::Answered>>>;
+}
+
+impl<STRATEGY: SearchStrategy + 'static> AnswerQuery
+    for Pipeline<'static, STRATEGY, states::Answered>
+{
+    fn subscribe(&self) -> broadcast::Receiver<PipelineEvent> {
+        Pipeline::subscribe(self)
+    }
+
+    fn answer(
+        self: Box<Self>,
+        query: String,
+    ) -> BoxFuture<'static, Result<Query<states::Answered>>> {
+        (*self).query(query).boxed()
+    }
+}
+
+/// Creates a new pipeline for every request
+#[derive(Clone)]
+pub(crate) struct PipelineFactory(Arc<dyn Fn() -> Box<dyn AnswerQuery> + Send + Sync>);
+
+impl PipelineFactory {
+    pub(crate) fn new<STRATEGY: SearchStrategy + 'static>(
+        factory: impl Fn() -> Pipeline<'static, STRATEGY, states::Answered> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(move || {
+            Box::new(factory()) as Box<dyn AnswerQuery>
+        }))
+    }
+
+    fn create(&self) -> Box<dyn AnswerQuery> {
+        (self.0)()
+    }
+}
+
+pub(crate) fn pipeline(server: &Server) -> Box<dyn AnswerQuery> {
+    server
+        .query_pipeline
+        .as_ref()
+        .expect("Query routes are only served with a pipeline")
+        .create()
+}
+
+/// Answers a query
+pub(crate) async fn query(
+    State(server): State<Arc<Server>>,
+    payload: Result<Json<QueryRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let request = server.validate(payload)?;
+
+    let start = Instant::now();
+    let answer = pipeline(&server).answer(request.query).await?;
+    let usage = UsageReport {
+        elapsed_ms: elapsed_ms(start),
+        usage: None,
+    };
+
+    Ok((
+        AppendHeaders(usage.headers()),
+        Json(QueryResponse::from(answer)),
+    ))
+}
+
+/// Streams the steps of the pipeline as `step` events, followed by an `answer` or `error` event
+/// and a `usage` event
+///
+/// The query is aborted if the client disconnects.
+pub(crate) async fn query_stream(
+    State(server): State<Arc<Server>>,
+    payload: Result<Json<QueryRequest>, JsonRejection>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let request = server.validate(payload)?;
+    let updates = stream_query(pipeline(&server), request.query);
+
+    let events = ReceiverStream::new(updates)
+        .flat_map(|update| {
+            let events = match update {
+                QueryUpdate::Step(step) => vec![json_event("step", &step)],
+                QueryUpdate::Answered(result, usage) => vec![
+                    match result {
+                        Ok(answer) => json_event("answer", &QueryResponse::from(answer)),
+                        Err(err) => error_event(&err),
+                    },
+                    json_event("usage", &usage),
+                ],
+            };
+            futures_util::stream::iter(events)
+        })
+        .map(Ok);
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Progress of a query while it is answered
+pub(crate) enum QueryUpdate {
+    Step(Step),
+    /// Always the last update
+    Answered(Result<Query<states::Answered>>, UsageReport),
+}
+
+/// Progress of a step of the pipeline
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+    pub step: &'static str,
+    /// One of `started`, `finished` or `failed`
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    /// A generic error for failed steps, the error itself is logged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Step {
+    /// Only the progress of steps is streamed
+    fn from_event(event: &PipelineEvent) -> Option<Self> {
+        let step = match event {
+            PipelineEvent::StepStarted { step, nodes } => Self {
+                step,
+                status: "started",
+                nodes: Some(*nodes),
+                elapsed_ms: None,
+                error: None,
+            },
+            PipelineEvent::StepFinished {
+                step,
+                nodes,
+                elapsed,
+            } => Self {
+                step,
+                status: "finished",
+                nodes: Some(*nodes),
+                elapsed_ms: Some(u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)),
+                error: None,
+            },
+            PipelineEvent::NodeFailed { step, error, .. } => {
+                tracing::error!(step, %error, "Step failed");
+
+                Self {
+                    step,
+                    status: "failed",
+                    nodes: None,
+                    elapsed_ms: None,
+                    error: Some(INTERNAL_ERROR.to_string()),
+                }
+            }
+            _ => return None,
+        };
+
+        Some(step)
+    }
+}
+
+/// Answers the query in the background, sending the progress of the pipeline
+///
+/// The query is aborted when the receiver is dropped.
+pub(crate) fn stream_query(
+    pipeline: Box<dyn AnswerQuery>,
+    query: String,
+) -> mpsc::Receiver<QueryUpdate> {
+    let (tx, rx) = mpsc::channel(64);
+
+    tokio::spawn(async move {
+        let start = Instant::now();
+        let mut events = pipeline.subscribe();
+        let mut answer = pipeline.answer(query);
+
+        let result = loop {
+            tokio::select! {
+                result = &mut answer => break result,
+                Ok(event) = events.recv() => {
+                    let Some(step) = Step::from_event(&event) else {
+                        continue;
+                    };
+                    if tx.send(QueryUpdate::Step(step)).await.is_err() {
+                        tracing::debug!("Receiver dropped, aborting query");
+                        return;
+                    }
+                }
+            }
+        };
+
+        let usage = UsageReport {
+            elapsed_ms: elapsed_ms(start),
+            usage: None,
+        };
+        let _ = tx.send(QueryUpdate::Answered(result, usage)).await;
+    });
+
+    rx
+}
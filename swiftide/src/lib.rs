@@ -99,17 +99,41 @@
 //! integrations. You need to cherry-pick the tools and integrations you want to use.
 #![doc = document_features::document_features!()]
 
+#[doc(inline)]
+pub use swiftide_core::cost;
+#[doc(inline)]
+pub use swiftide_core::errors;
+#[doc(inline)]
+pub use swiftide_core::middleware;
+#[doc(inline)]
+pub use swiftide_core::model_provider;
+#[doc(inline)]
+pub use swiftide_core::normalize;
 #[doc(inline)]
 pub use swiftide_core::prompt;
 #[doc(inline)]
+pub use swiftide_core::rate_limit;
+#[doc(inline)]
+pub use swiftide_core::response_cache;
+#[doc(inline)]
+pub use swiftide_core::retry;
+#[doc(inline)]
 pub use swiftide_core::template;
 #[doc(inline)]
+pub use swiftide_core::tokenizer;
+#[doc(inline)]
 pub use swiftide_core::type_aliases::*;
+#[doc(inline)]
+pub use swiftide_core::CancellationToken;
 
 #[cfg(feature = "swiftide-agents")]
 #[doc(inline)]
 pub use swiftide_agents as agents;
 
+#[cfg(feature = "server")]
+#[doc(inline)]
+pub use swiftide_server as server;
+
 /// Common traits for common behaviour, re-exported from indexing and query
 pub mod traits {
     #[doc(inline)]
@@ -259,4 +283,5 @@ pub mod reexports {
     pub use ::async_trait;
     pub use ::serde;
     pub use ::serde_json;
+    pub use ::uuid;
 }